@@ -1,9 +1,14 @@
 use std::env::current_dir;
 use std::fs::create_dir_all;
 
+use basset::migration::MigrateMsg;
+use basset::operator::OperatorResponse;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use basset::airdrop::{ExecuteMsg, InstantiateMsg, MIRAirdropHandleMsg, QueryMsg};
+use basset::airdrop::{
+    AirdropInfoResponse, ClaimHistoryResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
+    KeepersResponse, MIRAirdropHandleMsg, QueryMsg, UnclaimedAirdropsResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,5 +19,12 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(MIRAirdropHandleMsg), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(AirdropInfoResponse), &out_dir);
+    export_schema(&schema_for!(KeepersResponse), &out_dir);
+    export_schema(&schema_for!(ClaimHistoryResponse), &out_dir);
+    export_schema(&schema_for!(UnclaimedAirdropsResponse), &out_dir);
+    export_schema(&schema_for!(OperatorResponse), &out_dir);
 }
@@ -1,19 +1,38 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, SubMsg, Uint128, WasmMsg,
+    attr, to_binary, to_vec, Api, Binary, ContractResult, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, QueryRequest, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    WasmQuery,
 };
 
 use crate::state::{
-    read_airdrop_info, read_all_airdrop_infos, read_config, remove_airdrop_info,
-    store_airdrop_info, store_config, update_airdrop_info, Config, CONFIG,
+    is_keeper, read_airdrop_info, read_all_airdrop_infos, read_allocation, read_claims,
+    read_config, read_keepers, remove_airdrop_info, remove_keeper, store_airdrop_info,
+    store_allocation, store_claim, store_config, store_keeper, update_airdrop_info, ClaimRecord,
+    Config, CONFIG, OPERATOR, PENDING_SWAP,
 };
 use basset::airdrop::{
-    ANCAirdropHandleMsg, AirdropInfo, AirdropInfoElem, AirdropInfoResponse, ConfigResponse,
-    ExecuteMsg, InstantiateMsg, MIRAirdropHandleMsg, PairHandleMsg, QueryMsg,
+    ANCAirdropHandleMsg, AirdropInfo, AirdropInfoElem, AirdropInfoResponse, AirdropQueryMsg,
+    ClaimFormat, ClaimHistoryResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
+    IsClaimedResponse, KeepersResponse, LatestStageResponse, MIRAirdropHandleMsg, PairHandleMsg,
+    QueryMsg, UnclaimedAirdropElem, UnclaimedAirdropsResponse, UnclaimedStage,
 };
+use basset::contract_error::ContractError;
 use basset::hub::ExecuteMsg as HubHandleMsg;
+use basset::migration::{
+    assert_state_version, init_version, migrate_state, MigrateMsg, MigrationStep, VERSIONING_STEP,
+};
+use basset::querier::query_token_balance;
+use basset::validation::{assert_no_funds, validate_cw20_address};
+use cw20::Cw20ExecuteMsg;
+
+const CONTRACT_NAME: &str = "crates.io:anchor-airdrop-registry";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Bump together with a new `MigrationStep` whenever the storage layout changes.
+const STATE_VERSION: u64 = 1;
+const MIGRATION_STEPS: &[MigrationStep] = &[VERSIONING_STEP];
+const SWAP_REPLY_ID: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -21,23 +40,32 @@ pub fn instantiate(
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
-    let sndr_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
+) -> Result<Response, ContractError> {
+    let sndr_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
 
     let config = Config {
         owner: sndr_raw,
-        hub_contract: msg.hub_contract,
-        reward_contract: msg.reward_contract,
+        hub_contract: deps.api.addr_validate(&msg.hub_contract)?.to_string(),
+        reward_contract: deps.api.addr_validate(&msg.reward_contract)?.to_string(),
         airdrop_tokens: vec![],
     };
 
     store_config(deps.storage, &config)?;
+    init_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION, STATE_VERSION)?;
 
     Ok(Response::default())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    assert_state_version(deps.storage, STATE_VERSION)?;
+    assert_no_funds(&info)?;
+
     match msg {
         ExecuteMsg::FabricateMIRClaim {
             stage,
@@ -49,6 +77,17 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             amount,
             proof,
         } => execute_fabricate_anchor_claim(deps, env, info, stage, amount, proof),
+        ExecuteMsg::FabricateClaim {
+            airdrop_token,
+            stage,
+            amount,
+            proof,
+        } => execute_fabricate_claim(deps, env, info, airdrop_token, stage, amount, proof),
+        ExecuteMsg::RecordAllocation {
+            airdrop_token,
+            stage,
+            amount,
+        } => execute_record_allocation(deps, env, info, airdrop_token, stage, amount),
         ExecuteMsg::UpdateConfig {
             owner,
             hub_contract,
@@ -65,84 +104,327 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             airdrop_token,
             airdrop_info,
         } => execute_update_airdrop(deps, env, info, airdrop_token, airdrop_info),
+        ExecuteMsg::UpdateOperator { operator } => {
+            execute_update_operator(deps, env, info, operator)
+        }
+        ExecuteMsg::AddKeeper { keeper } => execute_add_keeper(deps, env, info, keeper),
+        ExecuteMsg::RemoveKeeper { keeper } => execute_remove_keeper(deps, env, info, keeper),
+        ExecuteMsg::SwapToRewardDenom { airdrop_token } => {
+            execute_swap_to_reward_denom(deps, env, info, airdrop_token)
+        }
     }
 }
 
 fn execute_fabricate_mir_claim(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     stage: u8,
     amount: Uint128,
     proof: Vec<String>,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    let claim_msg = to_binary(&MIRAirdropHandleMsg::Claim {
+        stage,
+        amount,
+        proof,
+    })?;
+    let messages =
+        fabricate_claim_messages(deps, env, "MIR".to_string(), stage, amount, claim_msg)?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(vec![
+            attr("action", "fabricate_mir_claim"),
+            attr("sender", info.sender),
+            attr("airdrop_token", "MIR"),
+            attr("stage", stage.to_string()),
+            attr("amount", amount),
+        ]))
+}
+
+fn execute_fabricate_anchor_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stage: u8,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> Result<Response, ContractError> {
+    let claim_msg = to_binary(&ANCAirdropHandleMsg::Claim {
+        stage,
+        amount,
+        proof,
+    })?;
+    let messages =
+        fabricate_claim_messages(deps, env, "ANC".to_string(), stage, amount, claim_msg)?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(vec![
+            attr("action", "fabricate_anc_claim"),
+            attr("sender", info.sender),
+            attr("airdrop_token", "ANC"),
+            attr("stage", stage.to_string()),
+            attr("amount", amount),
+        ]))
+}
+
+pub fn execute_fabricate_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    airdrop_token: String,
+    stage: u8,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> Result<Response, ContractError> {
+    // only owner, the operator and keepers can send this message.
     let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if !is_keeper(deps.storage, &sender_raw) {
+        OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
+    }
 
-    let mut messages: Vec<SubMsg> = vec![];
-
-    let airdrop_info = read_airdrop_info(deps.storage, "MIR".to_string()).unwrap();
-    messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.hub_contract,
-        msg: to_binary(&HubHandleMsg::ClaimAirdrop {
-            airdrop_token_contract: airdrop_info.airdrop_token_contract,
-            airdrop_contract: airdrop_info.airdrop_contract,
-            airdrop_swap_contract: airdrop_info.airdrop_swap_contract,
-            claim_msg: to_binary(&MIRAirdropHandleMsg::Claim {
-                stage,
-                amount,
-                proof,
-            })?,
-            swap_msg: to_binary(&PairHandleMsg::Swap {
-                belief_price: airdrop_info.swap_belief_price,
-                max_spread: airdrop_info.swap_max_spread,
-                to: Some(config.reward_contract),
-            })?,
-        })?,
-        funds: vec![],
-    })));
+    let airdrop_info = read_airdrop_info(deps.storage, airdrop_token.clone())?;
+    let claim_msg = build_claim_msg(
+        airdrop_info.claim_format.unwrap_or(ClaimFormat::Merkle {}),
+        stage,
+        amount,
+        proof,
+    )?;
+    let messages =
+        fabricate_claim_messages(deps, env, airdrop_token.clone(), stage, amount, claim_msg)?;
 
     Ok(Response::new()
         .add_submessages(messages)
-        .add_attributes(vec![attr("action", "fabricate_mir_claim")]))
+        .add_attributes(vec![
+            attr("action", "fabricate_claim"),
+            attr("sender", info.sender),
+            attr("airdrop_token", airdrop_token),
+            attr("stage", stage.to_string()),
+            attr("amount", amount),
+        ]))
 }
 
-fn execute_fabricate_anchor_claim(
+pub fn execute_record_allocation(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
+    airdrop_token: String,
     stage: u8,
     amount: Uint128,
-    proof: Vec<String>,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    // only owner, the operator and keepers can send this message.
     let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if !is_keeper(deps.storage, &sender_raw) {
+        OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
+    }
+
+    // the airdrop token must be registered
+    read_airdrop_info(deps.storage, airdrop_token.clone())?;
+    store_allocation(deps.storage, airdrop_token.clone(), stage, amount)?;
 
-    let mut messages: Vec<SubMsg> = vec![];
-
-    let airdrop_info = read_airdrop_info(deps.storage, "ANC".to_string())?;
-    messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.hub_contract,
-        msg: to_binary(&HubHandleMsg::ClaimAirdrop {
-            airdrop_token_contract: airdrop_info.airdrop_token_contract,
-            airdrop_contract: airdrop_info.airdrop_contract,
-            airdrop_swap_contract: airdrop_info.airdrop_swap_contract,
-            claim_msg: to_binary(&ANCAirdropHandleMsg::Claim {
-                stage,
-                amount,
-                proof,
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "record_allocation"),
+        attr("airdrop_token", airdrop_token),
+        attr("stage", stage.to_string()),
+        attr("amount", amount),
+    ]))
+}
+
+fn build_claim_msg(
+    claim_format: ClaimFormat,
+    stage: u8,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> StdResult<Binary> {
+    match claim_format {
+        ClaimFormat::Merkle {} => to_binary(&MIRAirdropHandleMsg::Claim {
+            stage,
+            amount,
+            proof,
+        }),
+        ClaimFormat::Raw { template } => {
+            let template = String::from_utf8(template.to_vec())
+                .map_err(|_| StdError::generic_err("Claim template is not valid utf-8"))?;
+            let claim_msg = template
+                .replace("\"$stage\"", &stage.to_string())
+                .replace("\"$amount\"", &String::from_utf8(to_vec(&amount)?)?)
+                .replace("\"$proof\"", &String::from_utf8(to_vec(&proof)?)?);
+            Ok(Binary::from(claim_msg.into_bytes()))
+        }
+    }
+}
+
+/// Builds the hub claim message of the given stage followed by the swap of the
+/// forwarded tokens, and records the claim.
+fn fabricate_claim_messages(
+    deps: DepsMut,
+    env: Env,
+    airdrop_token: String,
+    stage: u8,
+    amount: Uint128,
+    claim_msg: Binary,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let config = read_config(deps.storage)?;
+    let airdrop_info = read_airdrop_info(deps.storage, airdrop_token.clone())?;
+
+    PENDING_SWAP.save(deps.storage, &airdrop_token)?;
+    store_claim(
+        deps.storage,
+        airdrop_token.clone(),
+        stage,
+        &ClaimRecord {
+            amount,
+            height: env.block.height,
+        },
+    )?;
+
+    Ok(vec![
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.hub_contract,
+            msg: to_binary(&HubHandleMsg::ClaimAirdrop {
+                airdrop_token_contract: airdrop_info.airdrop_token_contract,
+                airdrop_contract: airdrop_info.airdrop_contract,
+                claim_msg,
             })?,
-            swap_msg: to_binary(&PairHandleMsg::Swap {
+            funds: vec![],
+        })),
+        // the hub forwards the claimed tokens to the registry, then they are sold for the reward denom.
+        // A failing swap keeps the claim, the tokens wait in the registry for another SwapToRewardDenom
+        SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::SwapToRewardDenom { airdrop_token })?,
+                funds: vec![],
+            }),
+            SWAP_REPLY_ID,
+        ),
+    ])
+}
+
+/// Set the operator allowed to manage the airdrops and fabricate the claims.
+/// Only the owner and the operator are allowed to execute
+pub fn execute_update_operator(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.update(
+        deps.storage,
+        deps.api,
+        &config.owner,
+        &sender_raw,
+        operator.clone(),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_operator"),
+        attr("operator", operator.unwrap_or_default()),
+    ]))
+}
+
+pub fn execute_add_keeper(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    keeper: String,
+) -> Result<Response, ContractError> {
+    // only owner can send this message.
+    let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let keeper_raw = deps.api.addr_canonicalize(&keeper)?;
+    store_keeper(deps.storage, &keeper_raw)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "add_keeper"), attr("keeper", keeper)]))
+}
+
+pub fn execute_remove_keeper(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    keeper: String,
+) -> Result<Response, ContractError> {
+    // only owner can send this message.
+    let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let keeper_raw = deps.api.addr_canonicalize(&keeper)?;
+    if !is_keeper(deps.storage, &keeper_raw) {
+        return Err(StdError::generic_err(format!("{} is not a keeper", keeper)).into());
+    }
+    remove_keeper(deps.storage, &keeper_raw);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_keeper"),
+        attr("keeper", keeper),
+    ]))
+}
+
+pub fn execute_swap_to_reward_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    airdrop_token: String,
+) -> Result<Response, ContractError> {
+    // only the registry itself, the owner or the operator can send this message.
+    let config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if info.sender != env.contract.address {
+        OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
+    }
+
+    let airdrop_info = read_airdrop_info(deps.storage, airdrop_token.clone())?;
+
+    let airdrop_token_balance = query_token_balance(
+        &deps.querier,
+        &airdrop_info.airdrop_token_contract,
+        env.contract.address.as_str(),
+    )?;
+
+    if airdrop_token_balance.is_zero() {
+        return Err(ContractError::NoAirdropBalance {
+            address: env.contract.address.to_string(),
+            token_contract: airdrop_info.airdrop_token_contract,
+        });
+    }
+
+    let messages: Vec<SubMsg> = vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: airdrop_info.airdrop_token_contract.clone(),
+        msg: to_binary(&Cw20ExecuteMsg::Send {
+            contract: airdrop_info.airdrop_swap_contract,
+            amount: airdrop_token_balance,
+            msg: to_binary(&PairHandleMsg::Swap {
                 belief_price: airdrop_info.swap_belief_price,
                 max_spread: airdrop_info.swap_max_spread,
-                to: Some(config.reward_contract),
+                to: Some(config.reward_contract.clone()),
             })?,
         })?,
         funds: vec![],
-    })));
+    }))];
 
     Ok(Response::new()
         .add_submessages(messages)
-        .add_attributes(vec![attr("action", "fabricate_anc_claim")]))
+        .add_attributes(vec![
+            attr("action", "swap_to_reward_denom"),
+            attr("sender", info.sender),
+            attr("recipient", config.reward_contract),
+            attr("airdrop_token", airdrop_token),
+            attr("amount", airdrop_token_balance),
+        ]))
 }
+
 pub fn execute_update_config(
     deps: DepsMut,
     _env: Env,
@@ -150,12 +432,12 @@ pub fn execute_update_config(
     owner: Option<String>,
     hub_contract: Option<String>,
     reward_contract: Option<String>,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     // only owner can send this message.
     let mut config = read_config(deps.storage)?;
-    let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     if sender_raw != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
     if let Some(o) = owner {
@@ -163,10 +445,10 @@ pub fn execute_update_config(
         config.owner = owner_raw
     }
     if let Some(hub) = hub_contract {
-        config.hub_contract = hub;
+        config.hub_contract = deps.api.addr_validate(&hub)?.to_string();
     }
     if let Some(reward_addr) = reward_contract {
-        config.reward_contract = reward_addr;
+        config.reward_contract = deps.api.addr_validate(&reward_addr)?.to_string();
     }
 
     store_config(deps.storage, &config)?;
@@ -180,22 +462,23 @@ pub fn execute_add_airdrop(
     info: MessageInfo,
     airdrop_token: String,
     airdrop_info: AirdropInfo,
-) -> StdResult<Response> {
-    // only owner can send this message.
+) -> Result<Response, ContractError> {
+    // only owner and the operator can send this message.
     let config = read_config(deps.storage)?;
-    let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
-    if sender_raw != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
 
     let exists = read_airdrop_info(deps.storage, airdrop_token.clone());
     if exists.is_ok() {
         return Err(StdError::generic_err(format!(
             "There is a token info with this {}",
             airdrop_token
-        )));
+        ))
+        .into());
     }
 
+    validate_airdrop_info(deps.api, &airdrop_info)?;
+
     CONFIG.update(deps.storage, |mut conf| -> StdResult<Config> {
         conf.airdrop_tokens.push(airdrop_token.clone());
         Ok(conf)
@@ -215,22 +498,23 @@ pub fn execute_update_airdrop(
     info: MessageInfo,
     airdrop_token: String,
     airdrop_info: AirdropInfo,
-) -> StdResult<Response> {
-    // only owner can send this message.
+) -> Result<Response, ContractError> {
+    // only owner and the operator can send this message.
     let config = read_config(deps.storage)?;
-    let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
-    if sender_raw != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
 
     let exists = read_airdrop_info(deps.storage, airdrop_token.clone());
     if exists.is_err() {
         return Err(StdError::generic_err(format!(
             "There is no token info with this {}",
             airdrop_token
-        )));
+        ))
+        .into());
     }
 
+    validate_airdrop_info(deps.api, &airdrop_info)?;
+
     update_airdrop_info(deps.storage, airdrop_token.clone(), airdrop_info)?;
     Ok(Response::new().add_attributes(vec![
         attr("action", "update_airdrop_info"),
@@ -238,25 +522,31 @@ pub fn execute_update_airdrop(
     ]))
 }
 
+fn validate_airdrop_info(api: &dyn Api, airdrop_info: &AirdropInfo) -> Result<(), ContractError> {
+    validate_cw20_address(api, &airdrop_info.airdrop_token_contract)?;
+    api.addr_validate(&airdrop_info.airdrop_contract)?;
+    api.addr_validate(&airdrop_info.airdrop_swap_contract)?;
+    Ok(())
+}
+
 pub fn execute_remove_airdrop(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     airdrop_token: String,
-) -> StdResult<Response> {
-    // only owner can send this message.
+) -> Result<Response, ContractError> {
+    // only owner and the operator can send this message.
     let config = read_config(deps.storage)?;
-    let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
-    if sender_raw != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &config.owner, &sender_raw)?;
 
     let exists = read_airdrop_info(deps.storage, airdrop_token.clone());
     if exists.is_err() {
         return Err(StdError::generic_err(format!(
             "There is no token info with this {}",
             airdrop_token
-        )));
+        ))
+        .into());
     }
 
     CONFIG.update(deps.storage, |mut conf| -> StdResult<Config> {
@@ -271,6 +561,40 @@ pub fn execute_remove_airdrop(
     ]))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != SWAP_REPLY_ID {
+        return Err(StdError::generic_err(format!("Unknown reply id {}", msg.id)).into());
+    }
+
+    match msg.result {
+        ContractResult::Err(error) => Ok(Response::new().add_attributes(vec![
+            attr("action", "swap_failed"),
+            attr("airdrop_token", PENDING_SWAP.load(deps.storage)?),
+            attr("error", error),
+        ])),
+        ContractResult::Ok(_) => Ok(Response::new()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = migrate_state(
+        deps.storage,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        &[],
+        STATE_VERSION,
+        MIGRATION_STEPS,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("from_state_version", previous.to_string()),
+        attr("to_state_version", STATE_VERSION.to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -285,6 +609,23 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
         )?),
+        QueryMsg::Keepers { start_after, limit } => {
+            to_binary(&query_keepers(deps, start_after, limit)?)
+        }
+        QueryMsg::ClaimHistory {
+            airdrop_token,
+            start_after,
+            limit,
+        } => to_binary(&query_claim_history(
+            deps,
+            airdrop_token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::UnclaimedAirdrops { start_after, limit } => {
+            to_binary(&query_unclaimed_airdrops(deps, start_after, limit)?)
+        }
+        QueryMsg::Operator {} => to_binary(&OPERATOR.query(deps.storage, deps.api)?),
     }
 }
 
@@ -307,7 +648,7 @@ fn query_airdrop_infos(
     limit: Option<u32>,
 ) -> StdResult<AirdropInfoResponse> {
     if let Some(air_token) = airdrop_token {
-        let info = read_airdrop_info(deps.storage, air_token.clone()).unwrap();
+        let info = read_airdrop_info(deps.storage, air_token.clone())?;
 
         Ok(AirdropInfoResponse {
             airdrop_info: vec![AirdropInfoElem {
@@ -322,3 +663,86 @@ fn query_airdrop_infos(
         })
     }
 }
+
+fn query_keepers(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<KeepersResponse> {
+    let start_after = if let Some(start) = start_after {
+        Some(deps.api.addr_canonicalize(&start)?)
+    } else {
+        None
+    };
+
+    let keepers = read_keepers(deps.storage, start_after, limit)?
+        .iter()
+        .map(|keeper| Ok(deps.api.addr_humanize(keeper)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(KeepersResponse { keepers })
+}
+
+fn query_claim_history(
+    deps: Deps,
+    airdrop_token: String,
+    start_after: Option<u8>,
+    limit: Option<u32>,
+) -> StdResult<ClaimHistoryResponse> {
+    let claims = read_claims(deps.storage, airdrop_token.clone(), start_after, limit)?;
+    Ok(ClaimHistoryResponse {
+        airdrop_token,
+        claims,
+    })
+}
+
+fn query_unclaimed_airdrops(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<UnclaimedAirdropsResponse> {
+    let config = read_config(deps.storage)?;
+
+    let mut airdrops: Vec<UnclaimedAirdropElem> = vec![];
+    for elem in read_all_airdrop_infos(deps.storage, start_after, limit)? {
+        let airdrop_token = elem.airdrop_token;
+        let airdrop_info = elem.info;
+
+        // the query schema of raw format airdrop contracts is unknown
+        if let Some(ClaimFormat::Raw { .. }) = airdrop_info.claim_format {
+            continue;
+        }
+
+        let latest_stage: LatestStageResponse =
+            deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: airdrop_info.airdrop_contract.clone(),
+                msg: to_binary(&AirdropQueryMsg::LatestStage {})?,
+            }))?;
+
+        let mut unclaimed_stages: Vec<UnclaimedStage> = vec![];
+        for stage in 1..=latest_stage.latest_stage {
+            let claimed: IsClaimedResponse =
+                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: airdrop_info.airdrop_contract.clone(),
+                    msg: to_binary(&AirdropQueryMsg::IsClaimed {
+                        stage,
+                        address: config.hub_contract.clone(),
+                    })?,
+                }))?;
+            if !claimed.is_claimed {
+                unclaimed_stages.push(UnclaimedStage {
+                    stage,
+                    amount: read_allocation(deps.storage, airdrop_token.clone(), stage)?,
+                });
+            }
+        }
+
+        airdrops.push(UnclaimedAirdropElem {
+            airdrop_token,
+            latest_stage: latest_stage.latest_stage,
+            unclaimed_stages,
+        });
+    }
+
+    Ok(UnclaimedAirdropsResponse { airdrops })
+}
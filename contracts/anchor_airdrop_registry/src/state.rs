@@ -1,9 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use basset::airdrop::{AirdropInfo, AirdropInfoElem};
-use cosmwasm_std::{from_slice, to_vec, CanonicalAddr, Order, StdResult, Storage};
-use cw_storage_plus::{Bound, Item, Map};
+use basset::airdrop::{AirdropInfo, AirdropInfoElem, ClaimHistoryElem};
+use basset::contract_error::ContractError;
+use basset::operator::Operator;
+use basset::pagination::{calc_limit, calc_range_start};
+use cosmwasm_std::{from_slice, to_vec, CanonicalAddr, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map, U8Key};
 
 pub static KEY_CONFIG: &[u8] = b"config";
 pub static PREFIX_AIRODROP_INFO: &[u8] = b"airdrop_info";
@@ -18,6 +21,20 @@ pub struct Config {
 
 pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
 pub const AIRDROP_INFO: Map<&[u8], AirdropInfo> = Map::new("airdrop_info");
+pub const KEEPERS: Map<&[u8], bool> = Map::new("keepers");
+/// Manages the airdrops and fabricates the claims next to the owner
+pub const OPERATOR: Operator = Operator::new("operator");
+pub const CLAIMS: Map<(&[u8], U8Key), ClaimRecord> = Map::new("claims");
+/// Amount of a stage the hub can claim, recorded from the published airdrop data
+pub const ALLOCATIONS: Map<(&[u8], U8Key), Uint128> = Map::new("allocations");
+/// Airdrop token of the swap sent by the last fabricated claim
+pub const PENDING_SWAP: Item<String> = Item::new("pending_swap");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimRecord {
+    pub amount: Uint128,
+    pub height: u64,
+}
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
     CONFIG.save(storage, config)
@@ -53,40 +70,126 @@ pub fn remove_airdrop_info(storage: &mut dyn Storage, airdrop_token: String) ->
 }
 
 pub fn read_airdrop_info(storage: &dyn Storage, airdrop_token: String) -> StdResult<AirdropInfo> {
-    let key = to_vec(&airdrop_token).unwrap();
+    let key = to_vec(&airdrop_token)?;
     AIRDROP_INFO.load(storage, &key)
 }
 
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+pub fn store_keeper(storage: &mut dyn Storage, keeper: &CanonicalAddr) -> StdResult<()> {
+    KEEPERS.save(storage, keeper.as_slice(), &true)
+}
+
+pub fn remove_keeper(storage: &mut dyn Storage, keeper: &CanonicalAddr) {
+    KEEPERS.remove(storage, keeper.as_slice())
+}
+
+pub fn is_keeper(storage: &dyn Storage, keeper: &CanonicalAddr) -> bool {
+    KEEPERS.has(storage, keeper.as_slice())
+}
+
+/// Records the claim of the stage of the airdrop token.
+/// Returns an error if the stage has already been claimed.
+pub fn store_claim(
+    storage: &mut dyn Storage,
+    airdrop_token: String,
+    stage: u8,
+    claim: &ClaimRecord,
+) -> Result<(), ContractError> {
+    let key = to_vec(&airdrop_token)?;
+    if CLAIMS.has(storage, (&key, stage.into())) {
+        return Err(ContractError::AlreadyClaimed {
+            airdrop_token,
+            stage,
+        });
+    }
+    Ok(CLAIMS.save(storage, (&key, stage.into()), claim)?)
+}
+
+pub fn store_allocation(
+    storage: &mut dyn Storage,
+    airdrop_token: String,
+    stage: u8,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = to_vec(&airdrop_token)?;
+    ALLOCATIONS.save(storage, (&key, stage.into()), &amount)
+}
+
+pub fn read_allocation(
+    storage: &dyn Storage,
+    airdrop_token: String,
+    stage: u8,
+) -> StdResult<Option<Uint128>> {
+    let key = to_vec(&airdrop_token)?;
+    ALLOCATIONS.may_load(storage, (&key, stage.into()))
+}
+
 pub fn read_all_airdrop_infos(
     storage: &dyn Storage,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> StdResult<Vec<AirdropInfoElem>> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after).map(Bound::exclusive);
+    let limit = calc_limit(limit);
+    let start = calc_airdrop_start(start_after)?.map(Bound::exclusive);
 
-    let infos: Vec<AirdropInfoElem> = AIRDROP_INFO
+    AIRDROP_INFO
         .range(storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
-            let (k, v) = item.unwrap();
-            let key: String = from_slice(&k).unwrap();
-            AirdropInfoElem {
-                airdrop_token: key,
+            let (k, v) = item?;
+            Ok(AirdropInfoElem {
+                airdrop_token: from_slice(&k)?,
                 info: v,
-            }
+            })
         })
-        .collect();
+        .collect()
+}
 
-    Ok(infos)
+pub fn read_keepers(
+    storage: &dyn Storage,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let limit = calc_limit(limit);
+    let start = calc_range_start(start_after);
+
+    KEEPERS
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| Ok(CanonicalAddr::from(k)))
+        .collect()
 }
 
-fn calc_range_start(start_after: Option<String>) -> Option<Vec<u8>> {
-    start_after.map(|air| {
-        let mut v = to_vec(&air).unwrap();
-        v.push(1);
-        v
-    })
+pub fn read_claims(
+    storage: &dyn Storage,
+    airdrop_token: String,
+    start_after: Option<u8>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ClaimHistoryElem>> {
+    let limit = calc_limit(limit);
+    let start = start_after.map(|stage| Bound::exclusive(U8Key::from(stage)));
+
+    let key = to_vec(&airdrop_token)?;
+    CLAIMS
+        .prefix(&key)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, v) = item?;
+            Ok(ClaimHistoryElem {
+                stage: k[0],
+                amount: v.amount,
+                height: v.height,
+            })
+        })
+        .collect()
+}
+
+fn calc_airdrop_start(start_after: Option<String>) -> StdResult<Option<Vec<u8>>> {
+    start_after
+        .map(|air| {
+            let mut v = to_vec(&air)?;
+            v.push(1);
+            Ok(v)
+        })
+        .transpose()
 }
@@ -0,0 +1,114 @@
+use basset::airdrop::{AirdropQueryMsg, IsClaimedResponse, LatestStageResponse};
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use std::collections::HashMap;
+
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let contract_addr = String::from(MOCK_CONTRACT_ADDR);
+    let custom_querier: WasmMockQuerier =
+        WasmMockQuerier::new(MockQuerier::new(&[(&contract_addr, contract_balance)]));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    token_balances: HashMap<String, HashMap<String, Uint128>>,
+    airdrop_stages: HashMap<String, (u8, Vec<u8>)>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Some((latest_stage, claimed_stages)) = self.airdrop_stages.get(contract_addr)
+                {
+                    return match from_binary(msg).unwrap() {
+                        AirdropQueryMsg::LatestStage {} => SystemResult::Ok(ContractResult::from(
+                            to_binary(&LatestStageResponse {
+                                latest_stage: *latest_stage,
+                            }),
+                        )),
+                        AirdropQueryMsg::IsClaimed { stage, .. } => {
+                            SystemResult::Ok(ContractResult::from(to_binary(&IsClaimedResponse {
+                                is_claimed: claimed_stages.contains(&stage),
+                            })))
+                        }
+                    };
+                }
+                match from_binary(msg).unwrap() {
+                    Cw20QueryMsg::Balance { address } => {
+                        let balance = self
+                            .token_balances
+                            .get(contract_addr)
+                            .and_then(|balances| balances.get(&address))
+                            .copied()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                            balance,
+                        })))
+                    }
+                    _ => unimplemented!(),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            token_balances: HashMap::new(),
+            airdrop_stages: HashMap::new(),
+        }
+    }
+
+    // configure the token owner mock querier
+    pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
+        for (contract_addr, balances) in balances.iter() {
+            let mut contract_balances: HashMap<String, Uint128> = HashMap::new();
+            for (addr, balance) in balances.iter() {
+                contract_balances.insert(addr.to_string(), **balance);
+            }
+            self.token_balances
+                .insert(contract_addr.to_string(), contract_balances);
+        }
+    }
+
+    // configure the latest stage and the claimed stages of the airdrop contracts
+    pub fn with_airdrop_stages(&mut self, stages: &[(&String, u8, &[u8])]) {
+        for (contract_addr, latest_stage, claimed_stages) in stages.iter() {
+            self.airdrop_stages.insert(
+                contract_addr.to_string(),
+                (*latest_stage, claimed_stages.to_vec()),
+            );
+        }
+    }
+}
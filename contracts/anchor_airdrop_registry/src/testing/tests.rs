@@ -1,17 +1,23 @@
-use crate::contract::{execute, instantiate, query};
+use super::mock_querier::mock_dependencies as dependencies;
+use crate::contract::{execute, instantiate, query, reply};
 use basset::airdrop::{
-    ANCAirdropHandleMsg, AirdropInfoElem, AirdropInfoResponse, ConfigResponse, ExecuteMsg,
-    InstantiateMsg, MIRAirdropHandleMsg, PairHandleMsg, QueryMsg,
+    ANCAirdropHandleMsg, AirdropInfoElem, AirdropInfoResponse, ClaimFormat, ClaimHistoryElem,
+    ClaimHistoryResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, KeepersResponse,
+    MIRAirdropHandleMsg, PairHandleMsg, QueryMsg, UnclaimedAirdropElem, UnclaimedAirdropsResponse,
+    UnclaimedStage,
 };
 
 use basset::airdrop::AirdropInfo;
 use basset::airdrop::ExecuteMsg::UpdateConfig;
+use basset::contract_error::ContractError;
 use basset::hub::ExecuteMsg::ClaimAirdrop;
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use basset::operator::OperatorResponse;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_binary, to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError, SubMsg,
-    Uint128, WasmMsg,
+    attr, from_binary, to_binary, Binary, ContractResult, CosmosMsg, DepsMut, Env, MessageInfo,
+    Reply, Response, StdError, SubMsg, Uint128, WasmMsg,
 };
+use cw20::Cw20ExecuteMsg;
 
 fn do_init(deps: DepsMut, env: Env, info: MessageInfo) {
     let init_msg = InstantiateMsg {
@@ -33,6 +39,7 @@ fn do_add_airdrop_info(deps: DepsMut, env: Env, info: MessageInfo, airdrop_token
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
     let res = execute(deps, env, info, msg).unwrap();
@@ -85,32 +92,72 @@ fn proper_mir_claim() {
         proof: vec![],
     };
 
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(res.messages.len(), 1);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages.len(), 2);
 
     let expected = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: "hub_contract".to_string(),
         msg: to_binary(&ClaimAirdrop {
             airdrop_token_contract: "airdrop_token_contract".to_string(),
             airdrop_contract: "airdrop_contract".to_string(),
-            airdrop_swap_contract: "swap_contract".to_string(),
             claim_msg: to_binary(&MIRAirdropHandleMsg::Claim {
                 stage: 0,
                 amount: Uint128::new(1000),
                 proof: vec![],
             })
             .unwrap(),
-            swap_msg: to_binary(&PairHandleMsg::Swap {
-                belief_price: None,
-                max_spread: None,
-                to: Some("reward_contract".to_string()),
-            })
-            .unwrap(),
         })
         .unwrap(),
         funds: vec![],
     }));
     assert_eq!(res.messages[0], expected);
+
+    let expected_swap = SubMsg::reply_on_error(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: mock_env().contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::SwapToRewardDenom {
+                airdrop_token: "MIR".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        }),
+        1,
+    );
+    assert_eq!(res.messages[1], expected_swap);
+
+    // a failing swap keeps the claim and reports the airdrop token
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: ContractResult::Err("insufficient liquidity".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "swap_failed"),
+            attr("airdrop_token", "MIR"),
+            attr("error", "insufficient liquidity"),
+        ]
+    );
+
+    // the same stage cannot be claimed twice
+    let msg = ExecuteMsg::FabricateMIRClaim {
+        stage: 0,
+        amount: Uint128::new(1000),
+        proof: vec![],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), msg).unwrap_err();
+    assert_eq!(
+        res,
+        ContractError::AlreadyClaimed {
+            airdrop_token: "MIR".to_string(),
+            stage: 0
+        }
+    );
 }
 
 #[test]
@@ -130,21 +177,522 @@ fn proper_anc_claim() {
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages.len(), 2);
 
     let expected = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: "hub_contract".to_string(),
         msg: to_binary(&ClaimAirdrop {
             airdrop_token_contract: "airdrop_token_contract".to_string(),
             airdrop_contract: "airdrop_contract".to_string(),
-            airdrop_swap_contract: "swap_contract".to_string(),
             claim_msg: to_binary(&ANCAirdropHandleMsg::Claim {
                 stage: 0,
                 amount: Uint128::new(1000),
                 proof: vec![],
             })
             .unwrap(),
-            swap_msg: to_binary(&PairHandleMsg::Swap {
+        })
+        .unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.messages[0], expected);
+
+    let expected_swap = SubMsg::reply_on_error(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: mock_env().contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::SwapToRewardDenom {
+                airdrop_token: "ANC".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        }),
+        1,
+    );
+    assert_eq!(res.messages[1], expected_swap);
+}
+
+#[test]
+fn proper_fabricate_claim() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    do_add_airdrop_info(deps.as_mut(), mock_env(), info.clone(), "MIR");
+
+    let msg = ExecuteMsg::FabricateClaim {
+        airdrop_token: "MIR".to_string(),
+        stage: 1,
+        amount: Uint128::new(1000),
+        proof: vec!["proof".to_string()],
+    };
+
+    // only owner and keepers can send this
+    let keeper_info = mock_info("keeper", &[]);
+    let res = execute(deps.as_mut(), mock_env(), keeper_info.clone(), msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let add_keeper = ExecuteMsg::AddKeeper {
+        keeper: "keeper".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), info, add_keeper).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), keeper_info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    let expected = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "hub_contract".to_string(),
+        msg: to_binary(&ClaimAirdrop {
+            airdrop_token_contract: "airdrop_token_contract".to_string(),
+            airdrop_contract: "airdrop_contract".to_string(),
+            claim_msg: to_binary(&MIRAirdropHandleMsg::Claim {
+                stage: 1,
+                amount: Uint128::new(1000),
+                proof: vec!["proof".to_string()],
+            })
+            .unwrap(),
+        })
+        .unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.messages[0], expected);
+
+    let expected_logs = vec![
+        attr("action", "fabricate_claim"),
+        attr("sender", "keeper"),
+        attr("airdrop_token", "MIR"),
+        attr("stage", "1"),
+        attr("amount", "1000"),
+    ];
+    assert_eq!(res.attributes, expected_logs);
+
+    // the same stage cannot be claimed twice
+    let res = execute(deps.as_mut(), mock_env(), keeper_info.clone(), msg).unwrap_err();
+    assert_eq!(
+        res,
+        ContractError::AlreadyClaimed {
+            airdrop_token: "MIR".to_string(),
+            stage: 1
+        }
+    );
+
+    // unregistered airdrop tokens cannot be claimed
+    let msg = ExecuteMsg::FabricateClaim {
+        airdrop_token: "BUZZ".to_string(),
+        stage: 1,
+        amount: Uint128::new(1000),
+        proof: vec![],
+    };
+    let res = execute(deps.as_mut(), mock_env(), keeper_info, msg);
+    assert!(res.is_err());
+}
+
+#[test]
+fn proper_fabricate_claim_with_raw_format() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    let template =
+        br#"{"claim_airdrop":{"round":"$stage","claim_amount":"$amount","proofs":"$proof"}}"#;
+    let msg = ExecuteMsg::AddAirdropInfo {
+        airdrop_token: "BUZZ".to_string(),
+        airdrop_info: AirdropInfo {
+            airdrop_token_contract: "buzz_airdrop_token_contract".to_string(),
+            airdrop_contract: "buzz_airdrop_contract".to_string(),
+            airdrop_swap_contract: "buzz_swap_contract".to_string(),
+            swap_belief_price: None,
+            swap_max_spread: None,
+            claim_format: Some(ClaimFormat::Raw {
+                template: Binary::from(template.to_vec()),
+            }),
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::FabricateClaim {
+        airdrop_token: "BUZZ".to_string(),
+        stage: 2,
+        amount: Uint128::new(1000),
+        proof: vec!["proof1".to_string(), "proof2".to_string()],
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    let expected_claim =
+        br#"{"claim_airdrop":{"round":2,"claim_amount":"1000","proofs":["proof1","proof2"]}}"#;
+    let expected = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "hub_contract".to_string(),
+        msg: to_binary(&ClaimAirdrop {
+            airdrop_token_contract: "buzz_airdrop_token_contract".to_string(),
+            airdrop_contract: "buzz_airdrop_contract".to_string(),
+            claim_msg: Binary::from(expected_claim.to_vec()),
+        })
+        .unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.messages[0], expected);
+}
+
+#[test]
+fn proper_claim_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    do_add_airdrop_info(deps.as_mut(), mock_env(), info.clone(), "MIR");
+    do_add_airdrop_info(deps.as_mut(), mock_env(), info.clone(), "ANC");
+
+    for stage in 0..3u8 {
+        let msg = ExecuteMsg::FabricateMIRClaim {
+            stage,
+            amount: Uint128::new(1000 * (stage as u128 + 1)),
+            proof: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    let history_query = QueryMsg::ClaimHistory {
+        airdrop_token: "MIR".to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: ClaimHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), history_query).unwrap()).unwrap();
+    let height = mock_env().block.height;
+    let expected = ClaimHistoryResponse {
+        airdrop_token: "MIR".to_string(),
+        claims: vec![
+            ClaimHistoryElem {
+                stage: 0,
+                amount: Uint128::new(1000),
+                height,
+            },
+            ClaimHistoryElem {
+                stage: 1,
+                amount: Uint128::new(2000),
+                height,
+            },
+            ClaimHistoryElem {
+                stage: 2,
+                amount: Uint128::new(3000),
+                height,
+            },
+        ],
+    };
+    assert_eq!(res, expected);
+
+    // test start after and limit
+    let history_query = QueryMsg::ClaimHistory {
+        airdrop_token: "MIR".to_string(),
+        start_after: Some(0),
+        limit: Some(1),
+    };
+    let res: ClaimHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), history_query).unwrap()).unwrap();
+    assert_eq!(res.claims, vec![expected.claims[1].clone()]);
+
+    // claims of other tokens are tracked separately
+    let history_query = QueryMsg::ClaimHistory {
+        airdrop_token: "ANC".to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let res: ClaimHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), history_query).unwrap()).unwrap();
+    assert_eq!(res.claims, vec![]);
+}
+
+#[test]
+fn proper_unclaimed_airdrops() {
+    let mut deps = dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    do_add_airdrop_info(deps.as_mut(), mock_env(), info.clone(), "MIR");
+
+    let msg = ExecuteMsg::AddAirdropInfo {
+        airdrop_token: "ANC".to_string(),
+        airdrop_info: AirdropInfo {
+            airdrop_token_contract: "anc_airdrop_token_contract".to_string(),
+            airdrop_contract: "anc_airdrop_contract".to_string(),
+            airdrop_swap_contract: "anc_swap_contract".to_string(),
+            swap_belief_price: None,
+            swap_max_spread: None,
+            claim_format: None,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // raw format airdrops are not reported
+    let msg = ExecuteMsg::AddAirdropInfo {
+        airdrop_token: "BUZZ".to_string(),
+        airdrop_info: AirdropInfo {
+            airdrop_token_contract: "buzz_airdrop_token_contract".to_string(),
+            airdrop_contract: "buzz_airdrop_contract".to_string(),
+            airdrop_swap_contract: "buzz_swap_contract".to_string(),
+            swap_belief_price: None,
+            swap_max_spread: None,
+            claim_format: Some(ClaimFormat::Raw {
+                template: Binary::from(b"{}".to_vec()),
+            }),
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    deps.querier.with_airdrop_stages(&[
+        (&"airdrop_contract".to_string(), 3, &[1, 3]),
+        (&"anc_airdrop_contract".to_string(), 0, &[]),
+    ]);
+
+    // only the owner, the operator and the keepers record the allocations
+    let record = ExecuteMsg::RecordAllocation {
+        airdrop_token: "MIR".to_string(),
+        stage: 2,
+        amount: Uint128::new(1000),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("invalid", &[]),
+        record.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), info, record).unwrap();
+
+    let unclaimed = |start_after: Option<&str>, limit| QueryMsg::UnclaimedAirdrops {
+        start_after: start_after.map(|token| token.to_string()),
+        limit,
+    };
+    let res: UnclaimedAirdropsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), unclaimed(None, None)).unwrap()).unwrap();
+    let expected = UnclaimedAirdropsResponse {
+        airdrops: vec![
+            UnclaimedAirdropElem {
+                airdrop_token: "ANC".to_string(),
+                latest_stage: 0,
+                unclaimed_stages: vec![],
+            },
+            UnclaimedAirdropElem {
+                airdrop_token: "MIR".to_string(),
+                latest_stage: 3,
+                unclaimed_stages: vec![UnclaimedStage {
+                    stage: 2,
+                    amount: Some(Uint128::new(1000)),
+                }],
+            },
+        ],
+    };
+    assert_eq!(res, expected);
+
+    // the airdrops are paged by token
+    let res: UnclaimedAirdropsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), unclaimed(None, Some(1))).unwrap()).unwrap();
+    assert_eq!(res.airdrops, expected.airdrops[..1].to_vec());
+    let res: UnclaimedAirdropsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), unclaimed(Some("ANC"), None)).unwrap())
+            .unwrap();
+    assert_eq!(res.airdrops, expected.airdrops[1..].to_vec());
+
+    // a stage without an allocation has no amount
+    deps.querier
+        .with_airdrop_stages(&[(&"airdrop_contract".to_string(), 4, &[1, 3])]);
+    let res: UnclaimedAirdropsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), unclaimed(Some("BUZZ"), None)).unwrap())
+            .unwrap();
+    assert_eq!(
+        res.airdrops[0].unclaimed_stages,
+        vec![
+            UnclaimedStage {
+                stage: 2,
+                amount: Some(Uint128::new(1000)),
+            },
+            UnclaimedStage {
+                stage: 4,
+                amount: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn proper_keepers() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    let msg = ExecuteMsg::AddKeeper {
+        keeper: "keeper1".to_string(),
+    };
+
+    // only owner can send this
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info.clone(), msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "add_keeper"), attr("keeper", "keeper1")]
+    );
+
+    let msg = ExecuteMsg::AddKeeper {
+        keeper: "keeper2".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let keepers_query = QueryMsg::Keepers {
+        start_after: None,
+        limit: None,
+    };
+    let res: KeepersResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), keepers_query).unwrap()).unwrap();
+    assert_eq!(
+        res.keepers,
+        vec!["keeper1".to_string(), "keeper2".to_string()]
+    );
+
+    let keepers_query = QueryMsg::Keepers {
+        start_after: Some("keeper1".to_string()),
+        limit: None,
+    };
+    let res: KeepersResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), keepers_query).unwrap()).unwrap();
+    assert_eq!(res.keepers, vec!["keeper2".to_string()]);
+
+    let msg = ExecuteMsg::RemoveKeeper {
+        keeper: "keeper1".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "remove_keeper"), attr("keeper", "keeper1")]
+    );
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        res,
+        ContractError::Std(StdError::generic_err("keeper1 is not a keeper"))
+    );
+
+    let keepers_query = QueryMsg::Keepers {
+        start_after: None,
+        limit: None,
+    };
+    let res: KeepersResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), keepers_query).unwrap()).unwrap();
+    assert_eq!(res.keepers, vec!["keeper2".to_string()]);
+}
+
+#[test]
+fn operator_manages_the_airdrops() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let operator = mock_info("operator", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    let msg = ExecuteMsg::UpdateOperator {
+        operator: Some("operator".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), operator.clone(), msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res: OperatorResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Operator {}).unwrap()).unwrap();
+    assert_eq!(res.operator, Some("operator".to_string()));
+
+    do_add_airdrop_info(deps.as_mut(), mock_env(), operator.clone(), "MIR");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        operator.clone(),
+        ExecuteMsg::RemoveAirdropInfo {
+            airdrop_token: "MIR".to_string(),
+        },
+    )
+    .unwrap();
+
+    // the keepers and the config are left to the owner
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        operator.clone(),
+        ExecuteMsg::AddKeeper {
+            keeper: "keeper1".to_string(),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        operator,
+        UpdateConfig {
+            owner: Some("operator".to_string()),
+            hub_contract: None,
+            reward_contract: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+}
+
+#[test]
+fn proper_swap_to_reward_denom() {
+    let mut deps = dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    do_init(deps.as_mut(), mock_env(), info.clone());
+
+    do_add_airdrop_info(deps.as_mut(), mock_env(), info.clone(), "MIR");
+
+    let msg = ExecuteMsg::SwapToRewardDenom {
+        airdrop_token: "MIR".to_string(),
+    };
+
+    // the registry has not received any airdrop tokens yet
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(
+        res,
+        ContractError::NoAirdropBalance {
+            address: "cosmos2contract".to_string(),
+            token_contract: "airdrop_token_contract".to_string()
+        }
+    );
+
+    deps.querier.with_token_balances(&[(
+        &"airdrop_token_contract".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::new(1000))],
+    )]);
+
+    // only the registry itself or the owner can send this
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let contract_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), mock_env(), contract_info, msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let expected = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "airdrop_token_contract".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Send {
+            contract: "swap_contract".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&PairHandleMsg::Swap {
                 belief_price: None,
                 max_spread: None,
                 to: Some("reward_contract".to_string()),
@@ -155,6 +703,19 @@ fn proper_anc_claim() {
         funds: vec![],
     }));
     assert_eq!(res.messages[0], expected);
+
+    let expected_logs = vec![
+        attr("action", "swap_to_reward_denom"),
+        attr("sender", MOCK_CONTRACT_ADDR),
+        attr("recipient", "reward_contract"),
+        attr("airdrop_token", "MIR"),
+        attr("amount", "1000"),
+    ];
+    assert_eq!(res.attributes, expected_logs);
+
+    // the owner can also trigger the swap
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.messages[0], expected);
 }
 
 #[test]
@@ -173,6 +734,7 @@ fn proper_add_airdrop_info() {
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
 
@@ -180,7 +742,7 @@ fn proper_add_airdrop_info() {
     let owner = "invalid";
     let invalid_info = mock_info(owner, &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(res.messages.len(), 0);
@@ -208,6 +770,7 @@ fn proper_add_airdrop_info() {
                 airdrop_swap_contract: "swap_contract".to_string(),
                 swap_belief_price: None,
                 swap_max_spread: None,
+                claim_format: None,
             },
         }],
     };
@@ -234,12 +797,13 @@ fn proper_add_airdrop_info() {
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("There is a token info with this MIR")
+        ContractError::Std(StdError::generic_err("There is a token info with this MIR"))
     );
 }
 
@@ -260,7 +824,7 @@ fn proper_remove_airdrop_info() {
     // only owner can send this
     let invalid_info = mock_info("invalid", &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(res.messages.len(), 0);
@@ -305,12 +869,15 @@ fn proper_remove_airdrop_info() {
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("There is no token info with this BUZZ")
+        ContractError::Std(StdError::generic_err(
+            "There is no token info with this BUZZ"
+        ))
     );
 }
 
@@ -332,13 +899,14 @@ fn proper_update_airdrop_info() {
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
 
     // only owner can send this
     let invalid_info = mock_info("invalid", &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(res.messages.len(), 0);
@@ -366,6 +934,7 @@ fn proper_update_airdrop_info() {
                 airdrop_swap_contract: "swap_contract".to_string(),
                 swap_belief_price: None,
                 swap_max_spread: None,
+                claim_format: None,
             },
         }],
     };
@@ -385,6 +954,7 @@ fn proper_update_airdrop_info() {
         airdrop_swap_contract: "swap_contract".to_string(),
         swap_belief_price: None,
         swap_max_spread: None,
+        claim_format: None,
     };
     let infos = AirdropInfoResponse {
         airdrop_info: vec![AirdropInfoElem {
@@ -418,12 +988,15 @@ fn proper_update_airdrop_info() {
             airdrop_swap_contract: "swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("There is no token info with this BUZZ")
+        ContractError::Std(StdError::generic_err(
+            "There is no token info with this BUZZ"
+        ))
     );
 }
 
@@ -487,6 +1060,7 @@ fn proper_query() {
             airdrop_swap_contract: "buzz_swap_contract".to_string(),
             swap_belief_price: None,
             swap_max_spread: None,
+            claim_format: None,
         },
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -519,6 +1093,7 @@ fn proper_query() {
         airdrop_swap_contract: "swap_contract".to_string(),
         swap_belief_price: None,
         swap_max_spread: None,
+        claim_format: None,
     };
     let infos = AirdropInfoResponse {
         airdrop_info: vec![
@@ -534,6 +1109,7 @@ fn proper_query() {
                     airdrop_swap_contract: "buzz_swap_contract".to_string(),
                     swap_belief_price: None,
                     swap_max_spread: None,
+                    claim_format: None,
                 },
             },
             AirdropInfoElem {
@@ -579,4 +1155,12 @@ fn proper_query() {
             }]
         }
     );
+    // an unknown airdrop token is an error, it used to abort the query
+    let info_query = QueryMsg::AirdropInfo {
+        airdrop_token: Some("UNKNOWN".to_string()),
+        start_after: None,
+        limit: None,
+    };
+    let res = query(deps.as_ref(), mock_env(), info_query);
+    assert!(matches!(res, Err(StdError::NotFound { .. })));
 }
@@ -0,0 +1,137 @@
+//! Native benchmarks of the storage heavy paths of the hub on mock storage, e.g. to compare
+//! storage refactors before deploying them:
+//!
+//! cargo bench -p anchor_basset_hub --features bench
+
+use anchor_basset_hub::contract::{execute, instantiate};
+use anchor_basset_hub::state::{
+    get_finished_requests, remove_unbond_wait_list, store_unbond_history, store_unbond_wait_list,
+    BATCH_STATUS, MAX_WITHDRAW_REQUESTS,
+};
+use basset::hub::{
+    BatchStatus, ExecuteMsg, HubMode, InstantiateMsg, UnbondBatchStatus, UnbondHistory,
+};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{coins, Addr, Decimal, OwnedDeps, Uint128};
+use cw_storage_plus::U64Key;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20;
+
+/// Time routine over ROUNDS fresh set ups, only the routine is measured
+fn bench<T, S: FnMut() -> T, R: FnMut(T)>(name: &str, mut setup: S, mut routine: R) {
+    let mut total = Duration::default();
+    let mut fastest = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let input = setup();
+        let start = Instant::now();
+        routine(input);
+        let elapsed = start.elapsed();
+        total += elapsed;
+        fastest = fastest.min(elapsed);
+    }
+    println!(
+        "{:<40} mean {:>12?}   min {:>12?}",
+        name,
+        total / ROUNDS,
+        fastest
+    );
+}
+
+fn history(batch_id: u64, time: u64, released: bool) -> UnbondHistory {
+    UnbondHistory {
+        batch_id,
+        time,
+        amount: Uint128::new(1000000),
+        applied_exchange_rate: Decimal::one(),
+        withdraw_rate: Decimal::one(),
+        released,
+    }
+}
+
+/// A user with a request in each of batches released batches
+fn wait_list(batches: u64) -> MockStorage {
+    let mut storage = MockStorage::default();
+    for batch_id in 1..=batches {
+        store_unbond_wait_list(
+            &mut storage,
+            batch_id,
+            "user".to_string(),
+            Uint128::new(1000),
+        )
+        .unwrap();
+        store_unbond_history(&mut storage, batch_id, history(batch_id, 0, true)).unwrap();
+    }
+    storage
+}
+
+/// A hub with batches unbonded batches to release and a request of the user in each
+fn unbonded_hub(batches: u64) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(&coins(1000000 * batches as u128, "uluna"));
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 210,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        reward_denom: "uusd".to_string(),
+        validator: "validator".to_string(),
+        mode: HubMode::default(),
+    };
+    let info = mock_info("creator", &coins(1000000, "uluna"));
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    for batch_id in 1..=batches {
+        store_unbond_wait_list(
+            &mut deps.storage,
+            batch_id,
+            "user".to_string(),
+            Uint128::new(1000000),
+        )
+        .unwrap();
+        store_unbond_history(&mut deps.storage, batch_id, history(batch_id, 0, false)).unwrap();
+        let status = UnbondBatchStatus {
+            batch_id,
+            status: BatchStatus::Undelegating,
+            collecting_time: Some(0),
+            undelegating_time: Some(0),
+            released_time: None,
+        };
+        BATCH_STATUS
+            .save(&mut deps.storage, U64Key::new(batch_id), &status)
+            .unwrap();
+    }
+    deps
+}
+
+fn main() {
+    for batches in [10, MAX_WITHDRAW_REQUESTS as u64] {
+        bench(
+            &format!("finished requests of {} batches", batches),
+            || wait_list(batches),
+            |mut storage| {
+                let finished = get_finished_requests(&storage, "user".to_string()).unwrap();
+                remove_unbond_wait_list(&mut storage, finished.batches, Addr::unchecked("user"))
+                    .unwrap();
+            },
+        );
+    }
+    for batches in [1, 10, MAX_WITHDRAW_REQUESTS as u64] {
+        bench(
+            &format!("withdraw unbonded of {} batches", batches),
+            || unbonded_hub(batches),
+            |mut deps| {
+                let mut env = mock_env();
+                env.block.time = env.block.time.plus_seconds(1000);
+                execute(
+                    deps.as_mut(),
+                    env,
+                    mock_info("user", &[]),
+                    ExecuteMsg::WithdrawUnbonded {},
+                )
+                .unwrap();
+            },
+        );
+    }
+}
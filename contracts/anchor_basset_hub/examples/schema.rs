@@ -1,14 +1,25 @@
 use std::env::current_dir;
 use std::fs::create_dir_all;
 
+use basset::migration::MigrateMsg;
+use basset::operator::OperatorResponse;
+use basset::snapshot::ExportStateResponse;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use anchor_basset_hub::state::Parameters;
 use basset::hub::{
-    AllHistoryResponse, CurrentBatchResponse, InstantiateMsg, QueryMsg, StateResponse,
-    UnbondRequestsResponse, WhitelistedValidatorsResponse, WithdrawableUnbondedResponse,
+    AccessControlResponse, AllHistoryResponse, AutoClaimResponse, BondData, BufferTargetResponse,
+    CircuitBreakerResponse, CollateralInfoResponse, ConfigHistoryResponse, ConfigResponse,
+    CurrentBatchResponse, Cw20HookMsg, EpochAutoAdvanceResponse, ExchangeRateTwapResponse,
+    FeesCollectedResponse, IbcBridgeResponse, InstantiateMsg, InsuranceResponse,
+    KeeperIncentiveResponse, LiquidityResponse, MinBondAmountResponse, ModeResponse,
+    OperatorsResponse, PendingChangesResponse, PriceGuardResponse, ProposalResponse, QueryMsg,
+    RampAllowancesResponse, SimulateBondResponse, SimulateUnbondResponse, StateResponse,
+    SurplusResponse, TaxModeResponse, UnbondBatchProofResponse, UnbondBatchStatus,
+    UnbondBatchesResponse, UnbondData, UnbondPriorityResponse, UnbondRequestsResponse,
+    UserHistoryResponse, ValidatorDelegationsResponse, WhitelistedValidatorsResponse,
+    WithdrawableUnbondedResponse, WormholeResponse,
 };
-use basset::hub::{Config, ExecuteMsg, State};
+use basset::hub::{Config, ExecuteMsg, Parameters, State};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -19,13 +30,51 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(Config), &out_dir);
     export_schema(&schema_for!(Parameters), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
     export_schema(&schema_for!(WhitelistedValidatorsResponse), &out_dir);
     export_schema(&schema_for!(WithdrawableUnbondedResponse), &out_dir);
     export_schema(&schema_for!(UnbondRequestsResponse), &out_dir);
     export_schema(&schema_for!(CurrentBatchResponse), &out_dir);
     export_schema(&schema_for!(AllHistoryResponse), &out_dir);
+    export_schema(&schema_for!(PendingChangesResponse), &out_dir);
+    export_schema(&schema_for!(CircuitBreakerResponse), &out_dir);
+    export_schema(&schema_for!(PriceGuardResponse), &out_dir);
+    export_schema(&schema_for!(IbcBridgeResponse), &out_dir);
+    export_schema(&schema_for!(WormholeResponse), &out_dir);
+    export_schema(&schema_for!(InsuranceResponse), &out_dir);
+    export_schema(&schema_for!(CollateralInfoResponse), &out_dir);
+    export_schema(&schema_for!(BufferTargetResponse), &out_dir);
+    export_schema(&schema_for!(UnbondBatchProofResponse), &out_dir);
+    export_schema(&schema_for!(SimulateBondResponse), &out_dir);
+    export_schema(&schema_for!(SimulateUnbondResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
+    export_schema(&schema_for!(KeeperIncentiveResponse), &out_dir);
+    export_schema(&schema_for!(EpochAutoAdvanceResponse), &out_dir);
+    export_schema(&schema_for!(MinBondAmountResponse), &out_dir);
+    export_schema(&schema_for!(AutoClaimResponse), &out_dir);
+    export_schema(&schema_for!(TaxModeResponse), &out_dir);
+    export_schema(&schema_for!(RampAllowancesResponse), &out_dir);
+    export_schema(&schema_for!(ValidatorDelegationsResponse), &out_dir);
+    export_schema(&schema_for!(SurplusResponse), &out_dir);
+    export_schema(&schema_for!(UnbondPriorityResponse), &out_dir);
+    export_schema(&schema_for!(FeesCollectedResponse), &out_dir);
+    export_schema(&schema_for!(ExchangeRateTwapResponse), &out_dir);
+    export_schema(&schema_for!(UnbondBatchesResponse), &out_dir);
+    export_schema(&schema_for!(LiquidityResponse), &out_dir);
+    export_schema(&schema_for!(UnbondBatchStatus), &out_dir);
+    export_schema(&schema_for!(BondData), &out_dir);
+    export_schema(&schema_for!(UnbondData), &out_dir);
+    export_schema(&schema_for!(UserHistoryResponse), &out_dir);
+    export_schema(&schema_for!(ModeResponse), &out_dir);
+    export_schema(&schema_for!(ProposalResponse), &out_dir);
+    export_schema(&schema_for!(AccessControlResponse), &out_dir);
+    export_schema(&schema_for!(OperatorsResponse), &out_dir);
+    export_schema(&schema_for!(OperatorResponse), &out_dir);
+    export_schema(&schema_for!(ExportStateResponse), &out_dir);
 }
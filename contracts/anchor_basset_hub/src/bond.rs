@@ -1,41 +1,136 @@
+use crate::config::active_validators;
 use crate::contract::{query_total_issued, slashing};
+use crate::history::record_user_event;
 use crate::math::decimal_division;
-use crate::state::{is_valid_validator, CONFIG, CURRENT_BATCH, PARAMETERS, STATE};
-use basset::hub::State;
+use crate::priority::skim_bond;
+use crate::state::{
+    add_collected_fee, is_access_listed, is_valid_validator, read_access_mode, read_hub_mode,
+    BOND_COUNT, CONFIG, CURRENT_BATCH, EPOCH_AUTO_ADVANCE, MIN_BOND_AMOUNT, PARAMETERS,
+    PEG_RECOVERY_FEES, PRICE_GUARD, STATE,
+};
+use crate::unbond::advance_epoch;
+use basset::contract_error::ContractError;
+use basset::hub::{AccessMode, BondData, HubMode, SimulateBondResponse, State, UserAction};
+use basset::oracle::{assert_price_deviation, query_oracle_price};
+use basset::snapshot::close_import;
 use cosmwasm_std::{
-    attr, to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StakingMsg, StdError,
-    StdResult, Uint128, WasmMsg,
+    attr, coin, to_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Response, StakingMsg, StdError, StdResult, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 
+/// The allocations of a BondBatch, each one is a Mint message
+const MAX_BOND_ALLOCATIONS: usize = 500;
+
+/// Where the minted bAsset goes
+enum BondPayout {
+    Sender,
+    Hook(Addr, Binary),
+    Allocations(Vec<(Addr, Uint128)>),
+}
+
 pub fn execute_bond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, ContractError> {
+    bond(deps, env, info, validator, BondPayout::Sender)
+}
+
+/// Bond, then send the minted bAsset to target_contract with msg as the cw20 hook, e.g. to
+/// provide liquidity or deposit collateral in one transaction. The hub is the sender of the
+/// hook, so msg must name the beneficiary when the target credits the sender
+pub fn execute_bond_and_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+    target_contract: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let target_contract = deps.api.addr_validate(&target_contract)?;
+    bond(
+        deps,
+        env,
+        info,
+        validator,
+        BondPayout::Hook(target_contract, msg),
+    )
+}
+
+/// Bond the deposits of many users at once, e.g. for an exchange. The minted bAsset is
+/// split in proportion to the allocations, the last recipient gets the rounding dust
+pub fn execute_bond_batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+    allocations: Vec<(String, Uint128)>,
+) -> Result<Response, ContractError> {
+    if allocations.is_empty() || allocations.len() > MAX_BOND_ALLOCATIONS {
+        return Err(StdError::generic_err(format!(
+            "A bond batch must have between 1 and {} allocations",
+            MAX_BOND_ALLOCATIONS
+        ))
+        .into());
+    }
+    let allocations = allocations
+        .into_iter()
+        .map(|(recipient, amount)| {
+            if amount.is_zero() {
+                return Err(StdError::generic_err(format!(
+                    "The allocation of {} is zero",
+                    recipient
+                )));
+            }
+            Ok((deps.api.addr_validate(&recipient)?, amount))
+        })
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+    bond(
+        deps,
+        env,
+        info,
+        validator,
+        BondPayout::Allocations(allocations),
+    )
+}
+
+fn bond(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
-    // validator must be whitelisted
-    let is_valid = is_valid_validator(deps.storage, validator.clone())?;
-    if !is_valid {
-        return Err(StdError::generic_err(
-            "The chosen validator is currently not supported",
-        ));
+    payout: BondPayout,
+) -> Result<Response, ContractError> {
+    assert_bond_allowed(deps.as_ref(), &info.sender)?;
+    // the holders would lose their bAsset to an imported state
+    close_import(deps.storage);
+
+    // validator must be whitelisted, the external mode delegates nothing
+    let mode = read_hub_mode(deps.storage)?;
+    if mode == HubMode::Staking && !is_valid_validator(deps.storage, validator.clone())? {
+        return Err(
+            StdError::generic_err("The chosen validator is currently not supported").into(),
+        );
+    }
+    if mode == HubMode::Staking && !active_validators(deps.as_ref())?.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "The validator {} is not in the active set",
+            validator
+        ))
+        .into());
     }
 
     let params = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
-    let threshold = params.er_threshold;
-    let recovery_fee = params.peg_recovery_fee;
-
-    // current batch requested fee is need for accurate exchange rate computation.
-    let current_batch = CURRENT_BATCH.load(deps.storage)?;
-    let requested_with_fee = current_batch.requested_with_fee;
 
     // coin must have be sent along with transaction and it should be in underlying coin denom
     if info.funds.len() > 1usize {
         return Err(StdError::generic_err(
             "More than one coin is sent; only one asset is supported",
-        ));
+        )
+        .into());
     }
 
     let payment = info
@@ -46,70 +141,338 @@ pub fn execute_bond(
             StdError::generic_err(format!("No {} assets are provided to bond", coin_denom))
         })?;
 
-    // check slashing
-    slashing(&mut deps, env)?;
+    if let BondPayout::Allocations(allocations) = &payout {
+        let allocated = allocations
+            .iter()
+            .try_fold(Uint128::zero(), |total, (_, amount)| {
+                total.checked_add(*amount)
+            })?;
+        if allocated != payment.amount {
+            return Err(StdError::generic_err(format!(
+                "The allocations add up to {}{}, {}{} is attached",
+                allocated, coin_denom, payment.amount, coin_denom
+            ))
+            .into());
+        }
+    }
+
+    if let Some(min_bond_amount) = MIN_BOND_AMOUNT.may_load(deps.storage)? {
+        if payment.amount < min_bond_amount {
+            return Err(StdError::generic_err(format!(
+                "Bonds of less than {}{} are not accepted",
+                min_bond_amount, coin_denom
+            ))
+            .into());
+        }
+    }
 
-    let state = STATE.load(deps.storage)?;
+    // check slashing, the payment is refunded if the circuit breaker tripped
+    let contract_address = env.contract.address.clone();
+    if let Some(alert) = slashing(&mut deps, env.clone())? {
+        return Ok(Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![payment.clone()],
+            })
+            .add_event(alert)
+            .add_attributes(vec![
+                attr("action", "bond_refunded"),
+                attr("sender", info.sender.as_str()),
+                attr("recipient", info.sender.as_str()),
+                attr("amount", payment.amount),
+            ]));
+    }
+
+    // a lapsed epoch is closed by bonds too, so the batch does not wait for the next unbond
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut state = STATE.load(deps.storage)?;
+    let mut current_batch = CURRENT_BATCH.load(deps.storage)?;
+    if let Some(max_undelegations) = EPOCH_AUTO_ADVANCE.may_load(deps.storage)? {
+        if let Some(mut undelegations) = advance_epoch(
+            &mut deps,
+            &env,
+            &mut state,
+            &mut current_batch,
+            Some(max_undelegations),
+        )? {
+            CURRENT_BATCH.save(deps.storage, &current_batch)?;
+            STATE.save(deps.storage, &state)?;
+            messages.append(&mut undelegations);
+        }
+    }
+
+    // current batch requested fee is need for accurate exchange rate computation.
+    let requested_with_fee = current_batch.requested_with_fee;
     let sender = info.sender;
 
     // get the total supply
     let mut total_supply = query_total_issued(deps.as_ref()).unwrap_or_default();
 
     // peg recovery fee should be considered
-    let mint_amount = decimal_division(payment.amount, state.exchange_rate);
-    let mut mint_amount_with_fee = mint_amount;
-    if state.exchange_rate < threshold {
-        let max_peg_fee = mint_amount * recovery_fee;
-        let required_peg_fee = ((total_supply + mint_amount + current_batch.requested_with_fee)
-            .checked_sub(state.total_bond_amount + payment.amount))?;
-        let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
-        mint_amount_with_fee = (mint_amount.checked_sub(peg_fee))?;
+    let (mint_amount_with_fee, peg_fee) = mint_with_fee(
+        deps.as_ref(),
+        &state,
+        total_supply,
+        requested_with_fee,
+        payment.amount,
+    )?;
+
+    // the payment would be kept without minting anything
+    if mint_amount_with_fee.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "Bonding {}{} mints no bAsset at the exchange rate {}",
+            payment.amount, coin_denom, state.exchange_rate
+        ))
+        .into());
     }
 
+    add_collected_fee(deps.storage, &PEG_RECOVERY_FEES, peg_fee)?;
+
     // total supply should be updated for exchange rate calculation.
     total_supply += mint_amount_with_fee;
 
     // exchange rate should be updated for future
     STATE.update(deps.storage, |mut prev_state| -> StdResult<State> {
         prev_state.total_bond_amount += payment.amount;
+        if mode == HubMode::External {
+            prev_state.prev_hub_balance += payment.amount;
+        }
         prev_state.update_exchange_rate(total_supply, requested_with_fee);
         Ok(prev_state)
     })?;
+    // the bonded and minted amounts of every owner of the bond
+    let shares = match &payout {
+        BondPayout::Allocations(allocations) => {
+            split_mint(allocations, payment.amount, mint_amount_with_fee)?
+        }
+        _ => vec![(sender.clone(), payment.amount, mint_amount_with_fee)],
+    };
+    for (owner, bonded, minted) in shares.iter() {
+        record_user_event(
+            deps.storage,
+            &env,
+            &deps.api.addr_canonicalize(owner.as_str())?,
+            state.exchange_rate,
+            UserAction::Bond {
+                amount: *bonded,
+                minted: *minted,
+            },
+        )?;
+    }
 
-    let mut messages: Vec<CosmosMsg> = vec![
-        // send the delegate message
-        CosmosMsg::Staking(StakingMsg::Delegate {
-            validator,
-            amount: payment.clone(),
-        }),
-    ];
+    let bond_id = BOND_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    BOND_COUNT.save(deps.storage, &(bond_id + 1))?;
 
-    // issue the basset token for sender
-    let mint_msg = Cw20ExecuteMsg::Mint {
-        recipient: sender.to_string(),
-        amount: mint_amount_with_fee,
-    };
+    let mut skimmed = Uint128::zero();
+    if mode == HubMode::Staking {
+        // the unbond buffer below its target keeps a share of the bond liquid
+        skimmed = skim_bond(
+            deps.storage,
+            payment.amount,
+            state.total_bond_amount + payment.amount,
+        )?;
+        if !skimmed.is_zero() {
+            STATE.update(deps.storage, |mut state| -> StdResult<State> {
+                state.prev_hub_balance += skimmed;
+                Ok(state)
+            })?;
+        }
+
+        // send the delegate message
+        let delegated = payment.amount - skimmed;
+        if !delegated.is_zero() {
+            messages.push(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator,
+                amount: coin(delegated.u128(), payment.denom.as_str()),
+            }));
+        }
+    }
 
     let config = CONFIG.load(deps.storage)?;
     let token_address = deps
         .api
-        .addr_humanize(
-            &config
-                .token_contract
-                .expect("the token contract must have been registered"),
-        )?
+        .addr_humanize(&config.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?
         .to_string();
 
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: token_address,
-        msg: to_binary(&mint_msg)?,
-        funds: vec![],
-    }));
+    // who the bond is for, with the underlying bonded for them
+    let recipients: Vec<(Addr, Uint128)> = match &payout {
+        BondPayout::Hook(target_contract, _) => vec![(target_contract.clone(), payment.amount)],
+        _ => shares
+            .iter()
+            .map(|(owner, bonded, _)| (owner.clone(), *bonded))
+            .collect(),
+    };
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
+    // issue the basset token for its owners, or for the hub to send it on with the hook
+    let mints = match &payout {
+        BondPayout::Hook(..) => vec![(contract_address, mint_amount_with_fee)],
+        _ => shares
+            .into_iter()
+            .map(|(owner, _, minted)| (owner, minted))
+            .collect(),
+    };
+    for (recipient, amount) in mints {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_address.clone(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    let mut attributes = vec![
         attr("action", "mint"),
-        attr("from", sender),
-        attr("bonded", payment.amount),
-        attr("minted", mint_amount_with_fee),
-    ]))
+        attr("from", sender.as_str()),
+        attr("sender", sender.as_str()),
+    ];
+    for (recipient, amount) in recipients {
+        attributes.push(attr("recipient", recipient));
+        attributes.push(attr("amount", amount));
+    }
+    attributes.push(attr("bonded", payment.amount));
+    attributes.push(attr("minted", mint_amount_with_fee));
+    match payout {
+        BondPayout::Hook(target_contract, msg) => {
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token_address,
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: target_contract.to_string(),
+                    amount: mint_amount_with_fee,
+                    msg,
+                })?,
+                funds: vec![],
+            }));
+            attributes.push(attr("target_contract", target_contract));
+        }
+        BondPayout::Allocations(allocations) => {
+            attributes.push(attr("allocations", allocations.len().to_string()));
+        }
+        BondPayout::Sender => {}
+    }
+    if !skimmed.is_zero() {
+        attributes.push(attr("buffered", skimmed));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(attributes)
+        .set_data(to_binary(&BondData {
+            bond_id,
+            minted_amount: mint_amount_with_fee,
+            exchange_rate: state.exchange_rate,
+        })?))
+}
+
+/// The bAsset minted for bonding amount and the peg recovery fee kept from it
+fn mint_with_fee(
+    deps: Deps,
+    state: &State,
+    total_supply: Uint128,
+    requested_with_fee: Uint128,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let mint_amount = decimal_division(amount, state.exchange_rate);
+    if state.exchange_rate >= params.er_threshold {
+        return Ok((mint_amount, Uint128::zero()));
+    }
+    assert_peg_recovery_price(deps, state.exchange_rate)?;
+    let max_peg_fee = mint_amount * params.peg_recovery_fee;
+    let required_peg_fee = (total_supply + mint_amount + requested_with_fee)
+        .checked_sub(state.total_bond_amount + amount)?;
+    let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
+    Ok((mint_amount.checked_sub(peg_fee)?, peg_fee))
+}
+
+/// The outcome of bonding amount at the stored state, as a slashing is only accounted by the
+/// bond itself
+pub fn query_simulate_bond(deps: Deps, amount: Uint128) -> StdResult<SimulateBondResponse> {
+    let mut state = STATE.load(deps.storage)?;
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_supply = query_total_issued(deps).unwrap_or_default();
+    let (minted, peg_recovery_fee) =
+        mint_with_fee(deps, &state, total_supply, requested_with_fee, amount)
+            .map_err(StdError::from)?;
+
+    state.total_bond_amount += amount;
+    state.update_exchange_rate(total_supply + minted, requested_with_fee);
+    Ok(SimulateBondResponse {
+        minted,
+        peg_recovery_fee,
+        exchange_rate: state.exchange_rate,
+    })
+}
+
+/// Split minted in proportion to the bonded allocations, the last one gets the rounding dust
+fn split_mint(
+    allocations: &[(Addr, Uint128)],
+    bonded: Uint128,
+    minted: Uint128,
+) -> StdResult<Vec<(Addr, Uint128, Uint128)>> {
+    let mut left = minted;
+    let mut shares = vec![];
+    for (i, (recipient, amount)) in allocations.iter().enumerate() {
+        let share = if i + 1 == allocations.len() {
+            left
+        } else {
+            minted.multiply_ratio(*amount, bonded)
+        };
+        left = left.checked_sub(share)?;
+        shares.push((recipient.clone(), *amount, share));
+    }
+    Ok(shares)
+}
+
+/// Permissioned deployments restrict bonding to an allowlist, or refuse a denylist
+fn assert_bond_allowed(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let allowed = match read_access_mode(deps.storage)? {
+        AccessMode::Open => true,
+        AccessMode::Allowlist => {
+            is_access_listed(deps.storage, &deps.api.addr_canonicalize(sender.as_str())?)?
+        }
+        AccessMode::Denylist => {
+            !is_access_listed(deps.storage, &deps.api.addr_canonicalize(sender.as_str())?)?
+        }
+    };
+    if !allowed {
+        return Err(ContractError::BondNotAllowed {
+            address: sender.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The peg recovery fee is charged from the exchange rate, which must not be far from
+/// the market price of the bAsset reported by the oracle, if the owner configured one
+pub(crate) fn assert_peg_recovery_price(
+    deps: Deps,
+    exchange_rate: Decimal,
+) -> Result<(), ContractError> {
+    let guard = match PRICE_GUARD.may_load(deps.storage)? {
+        Some(guard) => guard,
+        None => return Ok(()),
+    };
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps
+        .api
+        .addr_humanize(&config.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?;
+    let underlying_coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+
+    let price = query_oracle_price(
+        &deps.querier,
+        deps.api.addr_humanize(&guard.oracle_contract)?.as_str(),
+        token_address.as_str(),
+        &underlying_coin_denom,
+    )?;
+    assert_price_deviation(exchange_rate, price, guard.max_deviation)
 }
@@ -1,35 +1,121 @@
+use crate::history::{live_config, record_config_change};
 use crate::state::{
-    read_validators, remove_white_validators, store_white_validators, Parameters, CONFIG,
-    PARAMETERS,
+    is_valid_validator, read_circuit_breaker, read_hub_mode, read_valid_validators,
+    read_validators, remove_white_validators, store_white_validators, ACCESS_LIST, ACCESS_MODE,
+    AUTO_CLAIM_REWARDS, CIRCUIT_BREAKER, CONFIG, EPOCH_AUTO_ADVANCE, IBC_BRIDGE, KEEPER_INCENTIVE,
+    MIN_BOND_AMOUNT, OPERATOR, PARAMETERS, PRICE_GUARD, RAMP_UP, TAX_FREE, TIMELOCK, WORMHOLE,
 };
-use basset::hub::{Config, ExecuteMsg};
+use basset::contract_error::ContractError;
+use basset::hub::{
+    AccessMode, Config, ExecuteMsg, HubMode, KeeperIncentive, Parameters, TimelockedChange,
+    MAX_KEEPER_BOUNTY, MAX_KEEPER_RESERVE,
+};
+use basset::oracle::PriceGuard;
+use basset::reward::ExecuteMsg as RewardExecuteMsg;
+use basset::snapshot::{finish_import, import_state, is_importing, StateEntry};
+use basset::staking::{query_unbonding_time, UNBONDING_SAFETY_MARGIN};
+use basset::validation::validate_cw20_address;
 use cosmwasm_std::{
-    attr, to_binary, Addr, CosmosMsg, Decimal, DepsMut, DistributionMsg, Env, MessageInfo,
-    Response, StakingMsg, StdError, StdResult, WasmMsg,
+    attr, to_binary, Addr, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Env, MessageInfo,
+    Response, StakingMsg, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 
 use rand::{Rng, SeedableRng, XorShiftRng};
 
+/// The largest share of an unbond the pool may keep, in basis points
+const MAX_UNBOND_FEE_BPS: u64 = 500;
+
+// settings for the validators registered by one ImportValidators
+const MAX_IMPORT_LIMIT: u32 = 30;
+const DEFAULT_IMPORT_LIMIT: u32 = 10;
+
 /// Update general parameters
 /// Only creator/owner is allowed to execute
 #[allow(clippy::too_many_arguments)]
 pub fn execute_update_params(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     epoch_period: Option<u64>,
     unbonding_period: Option<u64>,
     peg_recovery_fee: Option<Decimal>,
     er_threshold: Option<Decimal>,
-) -> StdResult<Response> {
+    unbond_fee_bps: Option<u64>,
+) -> Result<Response, ContractError> {
     // only owner can send this message.
     let config = CONFIG.load(deps.storage)?;
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     if sender_raw != config.creator {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if TIMELOCK.is_active(deps.storage)?
+        && (unbonding_period.is_some()
+            || peg_recovery_fee.is_some()
+            || er_threshold.is_some()
+            || unbond_fee_bps.is_some())
+    {
+        return Err(ContractError::Timelocked {});
+    }
+
+    if let Some(unbonding_period) = unbonding_period {
+        assert_unbonding_period(deps.as_ref(), unbonding_period)?;
+    }
+
+    let previous = live_config(deps.as_ref())?;
+    update_params(
+        deps.storage,
+        epoch_period,
+        unbonding_period,
+        peg_recovery_fee,
+        er_threshold,
+        unbond_fee_bps,
+    )?;
+    let snapshot = record_config_change(deps, &env, previous)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![attr("action", "update_params")])
+        .add_attributes(snapshot))
+}
+
+/// Withdrawals must not be released before the undelegations of their batch mature, the
+/// unbonding period covers the unbonding time of the chain and the safety margin. The
+/// external mode undelegates nothing, and a chain that does not serve the staking params
+/// to contracts leaves the period to the owner
+pub(crate) fn assert_unbonding_period(
+    deps: Deps,
+    unbonding_period: u64,
+) -> Result<(), ContractError> {
+    if read_hub_mode(deps.storage)? == HubMode::External {
+        return Ok(());
+    }
+    if let Some(unbonding_time) = query_unbonding_time(&deps.querier)? {
+        let min_period = unbonding_time + UNBONDING_SAFETY_MARGIN;
+        if unbonding_period < min_period {
+            return Err(StdError::generic_err(format!(
+                "The unbonding period must be at least {} seconds, the unbonding time of the chain is {}",
+                min_period, unbonding_time
+            )).into());
+        }
     }
+    Ok(())
+}
 
-    let params: Parameters = PARAMETERS.load(deps.storage)?;
+fn update_params(
+    storage: &mut dyn Storage,
+    epoch_period: Option<u64>,
+    unbonding_period: Option<u64>,
+    peg_recovery_fee: Option<Decimal>,
+    er_threshold: Option<Decimal>,
+    unbond_fee_bps: Option<u64>,
+) -> StdResult<()> {
+    let params: Parameters = PARAMETERS.load(storage)?;
+    if unbond_fee_bps.unwrap_or_default() > MAX_UNBOND_FEE_BPS {
+        return Err(StdError::generic_err(format!(
+            "The unbond fee must not be more than {} basis points",
+            MAX_UNBOND_FEE_BPS
+        )));
+    }
 
     let new_params = Parameters {
         epoch_period: epoch_period.unwrap_or(params.epoch_period),
@@ -38,32 +124,54 @@ pub fn execute_update_params(
         peg_recovery_fee: peg_recovery_fee.unwrap_or(params.peg_recovery_fee),
         er_threshold: er_threshold.unwrap_or(params.er_threshold),
         reward_denom: params.reward_denom,
+        unbond_fee_bps: unbond_fee_bps.unwrap_or(params.unbond_fee_bps),
     };
 
-    PARAMETERS.save(deps.storage, &new_params)?;
-
-    Ok(Response::new().add_attributes(vec![attr("action", "update_params")]))
+    PARAMETERS.save(storage, &new_params)
 }
 
 /// Update the config. Update the owner, reward and token contracts.
 /// Only creator/owner is allowed to execute
 pub fn execute_update_config(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     owner: Option<String>,
     reward_contract: Option<String>,
     token_contract: Option<String>,
     airdrop_registry_contract: Option<String>,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     // only owner must be able to send this message.
     let conf = CONFIG.load(deps.storage)?;
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     if sender_raw != conf.creator {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
+    }
+    if TIMELOCK.is_active(deps.storage)? {
+        return Err(ContractError::Timelocked {});
     }
 
+    update_config(
+        deps,
+        env,
+        owner,
+        reward_contract,
+        token_contract,
+        airdrop_registry_contract,
+    )
+}
+
+fn update_config(
+    deps: DepsMut,
+    env: Env,
+    owner: Option<String>,
+    reward_contract: Option<String>,
+    token_contract: Option<String>,
+    airdrop_registry_contract: Option<String>,
+) -> Result<Response, ContractError> {
+    let previous = live_config(deps.as_ref())?;
     let mut messages: Vec<CosmosMsg> = vec![];
+    let register_token = reward_contract.is_some() || token_contract.is_some();
 
     if let Some(o) = owner {
         let owner_raw = deps.api.addr_canonicalize(o.as_str())?;
@@ -74,6 +182,7 @@ pub fn execute_update_config(
         })?;
     }
     if let Some(reward) = reward_contract {
+        let reward = deps.api.addr_validate(&reward)?.to_string();
         let reward_raw = deps.api.addr_canonicalize(reward.as_str())?;
 
         CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
@@ -88,6 +197,7 @@ pub fn execute_update_config(
     }
 
     if let Some(token) = token_contract {
+        let token = validate_cw20_address(deps.api, &token)?;
         let token_raw = deps.api.addr_canonicalize(token.as_str())?;
 
         CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
@@ -97,6 +207,7 @@ pub fn execute_update_config(
     }
 
     if let Some(airdrop) = airdrop_registry_contract {
+        let airdrop = deps.api.addr_validate(&airdrop)?;
         let airdrop_raw = deps.api.addr_canonicalize(airdrop.as_str())?;
         CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
             last_config.airdrop_registry_contract = Some(airdrop_raw);
@@ -104,27 +215,54 @@ pub fn execute_update_config(
         })?;
     }
 
+    // the reward contract only accepts balance updates from the token registered by the hub
+    let config = CONFIG.load(deps.storage)?;
+    if let (true, Some(reward), Some(token)) = (
+        register_token,
+        config.reward_contract,
+        config.token_contract,
+    ) {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&reward)?.to_string(),
+            msg: to_binary(&RewardExecuteMsg::RegisterTokenContract {
+                token_contract: deps.api.addr_humanize(&token)?.to_string(),
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    let snapshot = record_config_change(deps, &env, previous)?;
+
     Ok(Response::new()
         .add_messages(messages)
-        .add_attributes(vec![attr("action", "update_config")]))
+        .add_attributes(vec![attr("action", "update_config")])
+        .add_attributes(snapshot))
 }
 
 /// Register a white listed validator.
-/// Only creator/owner is allowed to execute
+/// Only creator/owner, the operator and the hub itself are allowed to execute, the owner
+/// proposes a TimelockedChange while the timelock is active
 pub fn execute_register_validator(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let hub_conf = CONFIG.load(deps.storage)?;
 
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     let contract_raw = deps.api.addr_canonicalize(env.contract.address.as_str())?;
-    if hub_conf.creator != sender_raw && contract_raw != sender_raw {
-        return Err(StdError::generic_err("unauthorized"));
+    if contract_raw != sender_raw {
+        OPERATOR.assert_allowed(deps.storage, &hub_conf.creator, &sender_raw)?;
+        if TIMELOCK.is_active(deps.storage)? {
+            return Err(ContractError::Timelocked {});
+        }
     }
 
+    register_validator(deps, validator)
+}
+
+fn register_validator(deps: DepsMut, validator: String) -> Result<Response, ContractError> {
     // given validator must be first a validator in the system.
     let exists = deps
         .querier
@@ -132,9 +270,7 @@ pub fn execute_register_validator(
         .iter()
         .any(|val| val.address == validator);
     if !exists {
-        return Err(StdError::generic_err(
-            "The specified address is not a validator",
-        ));
+        return Err(StdError::generic_err("The specified address is not a validator").into());
     }
 
     store_white_validators(deps.storage, validator.clone())?;
@@ -145,6 +281,47 @@ pub fn execute_register_validator(
     ]))
 }
 
+/// Register the validators of the chain with a commission of at most max_commission, in the
+/// order of the staking query. Only creator/owner is allowed to execute
+pub fn execute_import_validators(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_commission: Decimal,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    if TIMELOCK.is_active(deps.storage)? {
+        return Err(ContractError::Timelocked {});
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_IMPORT_LIMIT).min(MAX_IMPORT_LIMIT) as usize;
+    let registered = read_validators(deps.storage)?;
+    let imported: Vec<String> = deps
+        .querier
+        .query_all_validators()?
+        .into_iter()
+        .filter(|validator| {
+            validator.commission <= max_commission && !registered.contains(&validator.address)
+        })
+        .take(limit)
+        .map(|validator| validator.address)
+        .collect();
+    for validator in imported.iter() {
+        store_white_validators(deps.storage, validator.clone())?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "import_validators"),
+        attr("imported", imported.len().to_string()),
+        attr("validators", imported.join(",")),
+    ]))
+}
+
 /// Deregister a previously-whitelisted validator.
 /// Only creator/owner is allowed to execute
 pub fn execute_deregister_validator(
@@ -152,19 +329,29 @@ pub fn execute_deregister_validator(
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let token = CONFIG.load(deps.storage)?;
 
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     if token.creator != sender_raw {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
+    if TIMELOCK.is_active(deps.storage)? {
+        return Err(ContractError::Timelocked {});
+    }
+
+    deregister_validator(deps, env, validator)
+}
+
+fn deregister_validator(
+    deps: DepsMut,
+    env: Env,
+    validator: String,
+) -> Result<Response, ContractError> {
     let validators_before_remove = read_validators(deps.storage)?;
 
     if validators_before_remove.len() == 1 {
-        return Err(StdError::generic_err(
-            "Cannot remove the last whitelisted validator",
-        ));
+        return Err(StdError::generic_err("Cannot remove the last whitelisted validator").into());
     }
 
     remove_white_validators(deps.storage, validator.to_string())?;
@@ -178,7 +365,18 @@ pub fn execute_deregister_validator(
 
     if let Ok(q) = query {
         let delegated_amount = q;
-        let validators = read_validators(deps.storage)?;
+        // the stake is only moved to the validators still in the active set
+        let active = active_validators(deps.as_ref())?;
+        let validators: Vec<String> = read_validators(deps.storage)?
+            .into_iter()
+            .filter(|validator| active.contains(validator))
+            .collect();
+        if validators.is_empty() {
+            return Err(StdError::generic_err(
+                "No other whitelisted validator is in the active set",
+            )
+            .into());
+        }
 
         // redelegate the amount to a random validator.
         let block_height = env.block.height;
@@ -210,3 +408,638 @@ pub fn execute_deregister_validator(
         attr("new-validator", replaced_val),
     ]))
 }
+
+/// The validators of the active set, the staking messages to the others fail
+pub(crate) fn active_validators(deps: Deps) -> StdResult<Vec<String>> {
+    Ok(deps
+        .querier
+        .query_all_validators()?
+        .into_iter()
+        .map(|validator| validator.address)
+        .collect())
+}
+
+/// The validator in the active set with the least stake of the hub, e.g. for a cover or a
+/// surplus delegated on its own
+pub(crate) fn least_delegated_validator(
+    deps: Deps,
+    env: &Env,
+    denom: &str,
+) -> StdResult<Option<String>> {
+    let delegations = deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?;
+    let active = active_validators(deps)?;
+    Ok(read_valid_validators(deps.storage)?
+        .into_iter()
+        .filter(|validator| active.contains(validator))
+        .min_by_key(|validator| {
+            delegations
+                .iter()
+                .filter(|d| &d.validator == validator && d.amount.denom == denom)
+                .map(|d| d.amount.amount)
+                .sum::<Uint128>()
+        }))
+}
+
+/// Deregister the whitelisted validators that left the active set, their stake is moved to
+/// the validators still in it
+pub(crate) fn remove_exited_validators(
+    mut deps: DepsMut,
+    env: &Env,
+) -> Result<Response, ContractError> {
+    let active = active_validators(deps.as_ref())?;
+    let exited: Vec<String> = read_validators(deps.storage)?
+        .into_iter()
+        .filter(|validator| !active.contains(validator))
+        .collect();
+
+    let mut messages: Vec<SubMsg> = vec![];
+    for validator in exited.iter() {
+        let res = deregister_validator(deps.branch(), env.clone(), validator.clone())?;
+        for msg in res.messages {
+            // the index is updated once for all of them
+            if !messages.contains(&msg) {
+                messages.push(msg);
+            }
+        }
+    }
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(vec![
+            attr("action", "remove_exited_validators"),
+            attr("validators", exited.join(",")),
+        ]))
+}
+
+/// Queue a sensitive change until the timelock period has passed.
+/// Only creator/owner is allowed to execute
+pub fn execute_propose_pending_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    change: TimelockedChange,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending = TIMELOCK.propose(deps.storage, env.block.time.seconds(), change)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "propose_pending_change"),
+        attr("id", pending.id.to_string()),
+        attr("effective_at", pending.effective_at.to_string()),
+    ]))
+}
+
+/// Apply a queued change whose timelock period has passed.
+/// Only creator/owner is allowed to execute
+pub fn execute_apply_pending_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let change = TIMELOCK.take_ready(deps.storage, env.block.time.seconds(), id)?;
+    let res = match change {
+        TimelockedChange::UpdateParams {
+            unbonding_period,
+            peg_recovery_fee,
+            er_threshold,
+            unbond_fee_bps,
+        } => {
+            if let Some(unbonding_period) = unbonding_period {
+                assert_unbonding_period(deps.as_ref(), unbonding_period)?;
+            }
+            let previous = live_config(deps.as_ref())?;
+            update_params(
+                deps.storage,
+                None,
+                unbonding_period,
+                peg_recovery_fee,
+                er_threshold,
+                unbond_fee_bps,
+            )?;
+            let snapshot = record_config_change(deps, &env, previous)?;
+            Response::new()
+                .add_attributes(vec![attr("action", "update_params")])
+                .add_attributes(snapshot)
+        }
+        TimelockedChange::RegisterValidator { validator } => register_validator(deps, validator)?,
+        TimelockedChange::DeregisterValidator { validator } => {
+            deregister_validator(deps, env, validator)?
+        }
+        TimelockedChange::UpdateTimelockPeriod { timelock_period } => {
+            TIMELOCK.set_delay(deps.storage, timelock_period)?;
+            Response::new().add_attributes(vec![
+                attr("action", "update_timelock_period"),
+                attr("timelock_period", timelock_period.to_string()),
+            ])
+        }
+        TimelockedChange::UpdateConfig {
+            owner,
+            reward_contract,
+            token_contract,
+            airdrop_registry_contract,
+        } => update_config(
+            deps,
+            env,
+            owner,
+            reward_contract,
+            token_contract,
+            airdrop_registry_contract,
+        )?,
+        TimelockedChange::UpdateKeeperIncentive { incentive } => {
+            set_keeper_incentive(deps.storage, incentive)?
+        }
+    };
+
+    Ok(res.add_attribute("pending_change_id", id.to_string()))
+}
+
+/// Drop a queued change.
+/// Only creator/owner is allowed to execute
+pub fn execute_cancel_pending_change(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    TIMELOCK.cancel(deps.storage, id)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "cancel_pending_change"),
+        attr("id", id.to_string()),
+    ]))
+}
+
+/// Configure the circuit breaker pausing the contract on exchange rate anomalies.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_circuit_breaker(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_exchange_rate_change: Option<Decimal>,
+    guardian: Option<String>,
+    freeze_transfers: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let guardian = guardian
+        .map(|guardian| deps.api.addr_canonicalize(guardian.as_str()))
+        .transpose()?;
+
+    let mut breaker = read_circuit_breaker(deps.storage)?;
+    breaker.max_exchange_rate_change = max_exchange_rate_change;
+    breaker.guardian = guardian;
+    breaker.freeze_transfers = freeze_transfers;
+    CIRCUIT_BREAKER.save(deps.storage, &breaker)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_circuit_breaker")]))
+}
+
+/// Configure the oracle that peg recovery and the reward swaps are checked against.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_price_guard(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    oracle_contract: Option<String>,
+    max_deviation: Decimal,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match oracle_contract {
+        Some(oracle_contract) => {
+            if max_deviation.is_zero() || max_deviation >= Decimal::one() {
+                return Err(StdError::generic_err("max_deviation must be between 0 and 1").into());
+            }
+            let oracle_contract = deps.api.addr_validate(&oracle_contract)?;
+            PRICE_GUARD.save(
+                deps.storage,
+                &PriceGuard {
+                    oracle_contract: deps.api.addr_canonicalize(oracle_contract.as_str())?,
+                    max_deviation,
+                },
+            )?;
+        }
+        None => PRICE_GUARD.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_price_guard")]))
+}
+
+/// Set the cw20-ics20 bridge the bAsset is sent to other chains through.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_ibc_bridge(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    bridge_contract: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match bridge_contract {
+        Some(bridge) => {
+            let bridge = deps.api.addr_validate(&bridge)?;
+            IBC_BRIDGE.save(deps.storage, &deps.api.addr_canonicalize(bridge.as_str())?)?;
+        }
+        None => IBC_BRIDGE.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_ibc_bridge")]))
+}
+
+/// Set the wormhole core contract the unbond receipts are published to.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_wormhole(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    core_contract: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match core_contract {
+        Some(core_contract) => {
+            let core_contract = deps.api.addr_validate(&core_contract)?;
+            WORMHOLE.save(
+                deps.storage,
+                &deps.api.addr_canonicalize(core_contract.as_str())?,
+            )?;
+        }
+        None => WORMHOLE.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_wormhole")]))
+}
+
+/// Set the bounty paid to the callers of the permissionless maintenance handlers.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_keeper_incentive(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    incentive: Option<KeeperIncentive>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    // the reserve is held back from the rewards of the holders
+    if TIMELOCK.is_active(deps.storage)? {
+        return Err(ContractError::Timelocked {});
+    }
+
+    set_keeper_incentive(deps.storage, incentive)
+}
+
+fn set_keeper_incentive(
+    storage: &mut dyn Storage,
+    incentive: Option<KeeperIncentive>,
+) -> Result<Response, ContractError> {
+    match incentive {
+        Some(incentive) => {
+            if incentive.bounty > incentive.reserve {
+                return Err(StdError::generic_err(
+                    "The keeper reserve must cover at least one bounty",
+                )
+                .into());
+            }
+            if incentive.bounty > Uint128::new(MAX_KEEPER_BOUNTY)
+                || incentive.reserve > Uint128::new(MAX_KEEPER_RESERVE)
+            {
+                return Err(StdError::generic_err(format!(
+                    "The keeper bounty is at most {} and its reserve at most {}",
+                    MAX_KEEPER_BOUNTY, MAX_KEEPER_RESERVE
+                ))
+                .into());
+            }
+            KEEPER_INCENTIVE.save(storage, &incentive)?;
+        }
+        None => KEEPER_INCENTIVE.remove(storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_keeper_incentive")]))
+}
+
+/// Let bonds close a lapsed epoch when it takes at most max_undelegations undelegate messages.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_epoch_auto_advance(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_undelegations: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match max_undelegations {
+        Some(max_undelegations) => EPOCH_AUTO_ADVANCE.save(deps.storage, &max_undelegations)?,
+        None => EPOCH_AUTO_ADVANCE.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_epoch_auto_advance")]))
+}
+
+/// Cap the stake a rebalance redelegates to each validator per epoch.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_ramp_up(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_growth: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match max_growth {
+        Some(max_growth) if max_growth.is_zero() => {
+            return Err(StdError::generic_err("The ramp up must let the validators grow").into())
+        }
+        Some(max_growth) => RAMP_UP.save(deps.storage, &max_growth)?,
+        None => RAMP_UP.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_ramp_up")]))
+}
+
+/// Update the smallest bond accepted, so that dust bonds do not lose their payment to rounding.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_min_bond_amount(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    min_bond_amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match min_bond_amount {
+        Some(min_bond_amount) => MIN_BOND_AMOUNT.save(deps.storage, &min_bond_amount)?,
+        None => MIN_BOND_AMOUNT.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_min_bond_amount")]))
+}
+
+/// Let the unbonds claim the rewards of their user. Only creator/owner is allowed to execute
+pub fn execute_update_auto_claim(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if enabled {
+        AUTO_CLAIM_REWARDS.save(deps.storage, &true)?;
+    } else {
+        AUTO_CLAIM_REWARDS.remove(deps.storage);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_auto_claim"),
+        attr("enabled", enabled.to_string()),
+    ]))
+}
+
+/// Stop or resume deducting the tax from the amounts of the hub and the reward contract.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_tax_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    tax_free: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if tax_free {
+        TAX_FREE.save(deps.storage, &true)?;
+    } else {
+        TAX_FREE.remove(deps.storage);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_tax_mode"),
+        attr("tax_free", tax_free.to_string()),
+    ]))
+}
+
+/// Lift the pause set by the circuit breaker.
+/// Only the guardian, or creator/owner when there is no guardian, is allowed to execute
+pub fn execute_resume(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    set_paused(deps, info, false)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "resume")]))
+}
+
+/// Pause the hub, and with it the token and the reward contract, without waiting for the breaker to trip
+pub fn execute_pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    set_paused(deps, info, true)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "pause")]))
+}
+
+/// The guardian, or the owner when there is no guardian, reviews the pause
+fn set_paused(deps: DepsMut, info: MessageInfo, paused: bool) -> Result<(), ContractError> {
+    let mut breaker = read_circuit_breaker(deps.storage)?;
+    let reviewer = match breaker.guardian.clone() {
+        Some(guardian) => guardian,
+        None => CONFIG.load(deps.storage)?.creator,
+    };
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != reviewer {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    breaker.paused = paused;
+    Ok(CIRCUIT_BREAKER.save(deps.storage, &breaker)?)
+}
+
+/// Move the delegations of the hub during an incident, the contract must be paused.
+/// Only creator/owner is allowed to execute
+pub fn execute_emergency(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msgs: Vec<StakingMsg>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !read_circuit_breaker(deps.storage)?.paused {
+        return Err(ContractError::NotPaused {});
+    }
+
+    for msg in msgs.iter() {
+        let destination = match msg {
+            StakingMsg::Delegate { validator, .. } => Some(validator),
+            StakingMsg::Redelegate { dst_validator, .. } => Some(dst_validator),
+            StakingMsg::Undelegate { .. } => None,
+            _ => return Err(StdError::generic_err("Unsupported staking message").into()),
+        };
+        if let Some(validator) = destination {
+            if !is_valid_validator(deps.storage, validator.clone())?
+                || !active_validators(deps.as_ref())?.contains(validator)
+            {
+                return Err(StdError::generic_err(
+                    "The chosen validator is currently not supported",
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attributes(vec![attr("action", "execute_emergency")]))
+}
+
+/// Restrict who is allowed to bond.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_access_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    mode: AccessMode,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    ACCESS_MODE.save(deps.storage, &mode)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_access_mode"),
+        attr("mode", format!("{:?}", mode).to_lowercase()),
+    ]))
+}
+
+/// Update the addresses the access mode applies to.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_access_list(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for address in add.unwrap_or_default() {
+        let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+        ACCESS_LIST.save(deps.storage, address_raw.as_slice(), &true)?;
+    }
+    for address in remove.unwrap_or_default() {
+        let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+        ACCESS_LIST.remove(deps.storage, address_raw.as_slice());
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_access_list")]))
+}
+
+/// Restore a page of exported state, e.g. on a fresh code id after a failed migration.
+/// Only creator/owner is allowed to execute, before the first bond
+pub fn execute_import_state(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    entries: Vec<StateEntry>,
+) -> Result<Response, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    // the config is cleared by the first page, the owner importing it writes the next ones
+    if !is_importing(deps.storage)? {
+        let config = CONFIG.load(deps.storage)?;
+        if sender_raw != config.creator {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+
+    let count = entries.len();
+    import_state(deps.storage, &sender_raw, &[], entries)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "import_state"),
+        attr("entries", count.to_string()),
+    ]))
+}
+
+/// Close the import for good.
+/// Only the sender of the first imported page is allowed to execute
+pub fn execute_finish_import(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    finish_import(deps.storage, &sender_raw)?;
+
+    Ok(Response::new().add_attribute("action", "finish_import"))
+}
@@ -1,31 +1,92 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg,
-    Env, MessageInfo, QueryRequest, Response, StakingMsg, StdError, StdResult, SubMsg, Uint128,
-    WasmMsg, WasmQuery,
+    attr, from_binary, to_binary, Addr, BankMsg, Binary, Coin, ContractResult, CosmosMsg, Decimal,
+    Deps, DepsMut, DistributionMsg, Env, Event, MessageInfo, Reply, Response, StakingMsg, StdError,
+    StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 
 use crate::config::{
-    execute_deregister_validator, execute_register_validator, execute_update_config,
-    execute_update_params,
+    assert_unbonding_period, execute_apply_pending_change, execute_cancel_pending_change,
+    execute_deregister_validator, execute_emergency, execute_finish_import, execute_import_state,
+    execute_import_validators, execute_pause, execute_propose_pending_change,
+    execute_register_validator, execute_resume, execute_update_access_list,
+    execute_update_access_mode, execute_update_auto_claim, execute_update_circuit_breaker,
+    execute_update_config, execute_update_epoch_auto_advance, execute_update_ibc_bridge,
+    execute_update_keeper_incentive, execute_update_min_bond_amount, execute_update_params,
+    execute_update_price_guard, execute_update_ramp_up, execute_update_tax_mode,
+    execute_update_wormhole,
+};
+use crate::gov::{
+    execute_cast_vote, execute_register_proposal, execute_signal_vote, query_proposal,
+};
+use crate::history::{query_config_history, query_user_history};
+use crate::ibc::execute_ibc_transfer;
+use crate::insurance::{
+    cover_request_msg, execute_update_insurance, handle_cover_reply, query_insurance,
+    slashing_cover, INSURANCE_REPLY_ID,
+};
+use crate::keeper::{
+    execute_run_operations, execute_update_operator, execute_update_operators, has_pending_rewards,
+    pay_keeper_bounty, query_keeper_incentive, query_operators, query_ramp_allowances,
+    query_validator_delegations,
 };
 
+use crate::priority::{
+    execute_fund_unbond_buffer, execute_update_buffer_target, execute_update_unbond_priority,
+    query_buffer_target, query_unbond_priority, skimmed_bonded,
+};
 use crate::state::{
-    all_unbond_history, get_unbond_requests, query_get_finished_amount, read_valid_validators,
-    CurrentBatch, Parameters, CONFIG, CURRENT_BATCH, PARAMETERS, STATE,
+    all_unbond_history, get_unbond_requests, query_get_finished_amount, read_access_list,
+    read_access_mode, read_circuit_breaker, read_hub_mode, read_unbond_history,
+    read_valid_validators, read_wait_list, CurrentBatch, AUTO_CLAIM_REWARDS, CIRCUIT_BREAKER,
+    CONFIG, CURRENT_BATCH, EPOCH_AUTO_ADVANCE, EXECUTION_LOCK, HUB_MODE, IBC_BRIDGE,
+    KEEPER_INCENTIVE, MAX_WITHDRAW_REQUESTS, MIN_BOND_AMOUNT, OPERATOR, PARAMETERS,
+    PEG_RECOVERY_FEES, PENDING_COVER, PRICE_GUARD, REWARDS_TOPPED_UP, STATE, TAX_FREE, TIMELOCK,
+    UNBOND_FEES,
+};
+use crate::surplus::{execute_reconcile_surplus, execute_update_surplus_policy, query_surplus};
+use crate::twap::{checkpoint_exchange_rate, query_exchange_rate_twap};
+use crate::unbond::{
+    execute_unbond, execute_withdraw_unbonded, query_liquidity, query_simulate_unbond,
+    query_unbond_batch_proof, query_unbond_batch_status, query_unbond_batches, transition_batch,
+    AUTO_CLAIM_REPLY_ID, UNBOND_REPLY_ID,
 };
-use crate::unbond::{execute_unbond, execute_withdraw_unbonded};
+use crate::wormhole::query_wormhole;
 
-use crate::bond::execute_bond;
-use basset::hub::ExecuteMsg::SwapHook;
+use crate::bond::{
+    execute_bond, execute_bond_and_execute, execute_bond_batch, query_simulate_bond,
+};
+use basset::contract_error::ContractError;
+use basset::hub::ExecuteMsg::ForwardAirdropHook;
 use basset::hub::{
-    AllHistoryResponse, Config, ConfigResponse, CurrentBatchResponse, Cw20HookMsg, ExecuteMsg,
-    InstantiateMsg, QueryMsg, State, StateResponse, UnbondRequestsResponse,
-    WhitelistedValidatorsResponse, WithdrawableUnbondedResponse,
+    AccessControlResponse, AllHistoryResponse, AutoClaimResponse, BatchStatus,
+    CircuitBreakerResponse, CollateralInfoResponse, Config, ConfigResponse, CurrentBatchResponse,
+    Cw20HookMsg, EpochAutoAdvanceResponse, ExecuteMsg, FeesCollectedResponse, HubMode,
+    IbcBridgeResponse, InstantiateMsg, MinBondAmountResponse, ModeResponse, OperationKind,
+    Parameters, PendingChangesResponse, PriceGuardResponse, QueryMsg, State, StateResponse,
+    TaxModeResponse, UnbondRequestsResponse, WhitelistedValidatorsResponse,
+    WithdrawableUnbondedResponse,
 };
-use basset::reward::ExecuteMsg::{SwapToRewardDenom, UpdateGlobalIndex};
-use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use basset::ibc::Ics20TransferMsg;
+use basset::math::decimal_multiplication_in_256;
+use basset::migration::{
+    assert_state_version, init_version, migrate_state, MigrateMsg, MigrationStep, VERSIONING_STEP,
+};
+use basset::querier::{query_token_balance, query_token_supply};
+use basset::reward::ExecuteMsg::{FundKeeperReserve, SwapToRewardDenom, UpdateGlobalIndex};
+use basset::snapshot::{assert_not_importing, export_state, open_import};
+use basset::validation::{assert_no_funds, validate_native_denom};
+use basset::{treasury, TaxCache};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+
+const CONTRACT_NAME: &str = "crates.io:anchor-basset-hub";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Bump together with a new `MigrationStep` whenever the storage layout changes.
+const STATE_VERSION: u64 = 1;
+const MIGRATION_STEPS: &[MigrationStep] = &[VERSIONING_STEP];
+/// Reply ids of the airdrop hooks start here, offset by the index of the hook
+const AIRDROP_HOOK_REPLY_ID_OFFSET: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -33,16 +94,22 @@ pub fn instantiate(
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let sender = info.sender.clone();
     let _sndr_raw = deps.api.addr_canonicalize(sender.as_str())?;
 
+    validate_native_denom(&msg.underlying_coin_denom)?;
+    validate_native_denom(&msg.reward_denom)?;
+
     let payment = info
         .funds
         .iter()
         .find(|x| x.denom == msg.underlying_coin_denom && x.amount > Uint128::zero())
         .ok_or_else(|| {
-            StdError::generic_err(format!("No {} assets are provided to bond", "uluna"))
+            StdError::generic_err(format!(
+                "No {} assets are provided to bond",
+                msg.underlying_coin_denom
+            ))
         })?;
 
     // store config
@@ -65,6 +132,7 @@ pub fn instantiate(
     };
 
     STATE.save(deps.storage, &state)?;
+    checkpoint_exchange_rate(deps.storage, env.block.time.seconds())?;
 
     // instantiate parameters
     let params = Parameters {
@@ -74,6 +142,7 @@ pub fn instantiate(
         peg_recovery_fee: msg.peg_recovery_fee,
         er_threshold: msg.er_threshold,
         reward_denom: msg.reward_denom,
+        unbond_fee_bps: 0,
     };
 
     PARAMETERS.save(deps.storage, &params)?;
@@ -83,6 +152,27 @@ pub fn instantiate(
         requested_with_fee: Default::default(),
     };
     CURRENT_BATCH.save(deps.storage, &batch)?;
+    transition_batch(
+        deps.storage,
+        batch.id,
+        BatchStatus::Collecting,
+        env.block.time.seconds(),
+    )?;
+
+    init_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION, STATE_VERSION)?;
+    open_import(deps.storage)?;
+
+    HUB_MODE.save(deps.storage, &msg.mode)?;
+    assert_unbonding_period(deps.as_ref(), msg.unbonding_period)?;
+    if msg.mode == HubMode::External {
+        // the hub keeps the payment, which is released to unbonding batches from its balance
+        STATE.update(deps.storage, |mut state| -> StdResult<State> {
+            state.prev_hub_balance = payment.amount;
+            Ok(state)
+        })?;
+        return Ok(Response::new()
+            .add_attributes(vec![attr("mode", "external"), attr("bond", payment.amount)]));
+    }
 
     let mut messages = vec![];
 
@@ -92,7 +182,7 @@ pub fn instantiate(
     };
     messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: env.contract.address.to_string(),
-        msg: to_binary(&register_validator).unwrap(),
+        msg: to_binary(&register_validator)?,
         funds: vec![],
     })));
 
@@ -111,26 +201,98 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    // a snapshot may hold an older state version, which is migrated once imported
+    match msg {
+        ExecuteMsg::ImportState { entries } => {
+            return execute_import_state(deps, env, info, entries)
+        }
+        ExecuteMsg::FinishImport {} => return execute_finish_import(deps, env, info),
+        _ => assert_not_importing(deps.storage)?,
+    }
+    assert_state_version(deps.storage, STATE_VERSION)?;
+    checkpoint_exchange_rate(deps.storage, env.block.time.seconds())?;
+
+    let accepts_funds = matches!(
+        msg,
+        ExecuteMsg::Bond { .. }
+            | ExecuteMsg::BondAndExecute { .. }
+            | ExecuteMsg::BondBatch { .. }
+            | ExecuteMsg::RewardTopUp {}
+            | ExecuteMsg::FundUnbondBuffer {}
+    );
+    if !accepts_funds {
+        assert_no_funds(&info)?;
+    }
+
+    // user operations are halted until the guardian reviewed a tripped circuit breaker
+    let user_operation = matches!(
+        msg,
+        ExecuteMsg::Receive(_)
+            | ExecuteMsg::Bond { .. }
+            | ExecuteMsg::BondAndExecute { .. }
+            | ExecuteMsg::BondBatch { .. }
+            | ExecuteMsg::UpdateGlobalIndex { .. }
+            | ExecuteMsg::WithdrawUnbonded {}
+            | ExecuteMsg::CheckSlashing {}
+            | ExecuteMsg::RunOperations { .. }
+            | ExecuteMsg::ReconcileSurplus {}
+    );
+    if user_operation && read_circuit_breaker(deps.storage)?.paused {
+        return Err(ContractError::Paused {});
+    }
+
     match msg {
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::Bond { validator } => execute_bond(deps, env, info, validator),
+        ExecuteMsg::BondAndExecute {
+            validator,
+            target_contract,
+            msg,
+        } => execute_bond_and_execute(deps, env, info, validator, target_contract, msg),
+        ExecuteMsg::BondBatch {
+            validator,
+            allocations,
+        } => execute_bond_batch(deps, env, info, validator, allocations),
         ExecuteMsg::UpdateGlobalIndex { airdrop_hooks } => {
-            execute_update_global(deps, env, airdrop_hooks)
+            let rewarded = has_pending_rewards(deps.as_ref(), &env)?;
+            let res = execute_update_global(deps.branch(), env.clone(), airdrop_hooks)?;
+            let kind = OperationKind::UpdateGlobalIndex;
+            pay_keeper_bounty(deps, &env, &info, kind, rewarded, res)
         }
         ExecuteMsg::WithdrawUnbonded {} => execute_withdraw_unbonded(deps, env, info),
+        ExecuteMsg::RewardTopUp {} => execute_reward_top_up(deps, info),
         ExecuteMsg::RegisterValidator { validator } => {
             execute_register_validator(deps, env, info, validator)
         }
+        ExecuteMsg::ImportValidators {
+            max_commission,
+            limit,
+        } => execute_import_validators(deps, env, info, max_commission, limit),
         ExecuteMsg::DeregisterValidator { validator } => {
             execute_deregister_validator(deps, env, info, validator)
         }
-        ExecuteMsg::CheckSlashing {} => execute_slashing(deps, env),
+        ExecuteMsg::CheckSlashing {} => {
+            let bonded = STATE.load(deps.storage)?.total_bond_amount;
+            let res = execute_slashing(deps.branch(), env.clone())?;
+            // a slash lowers the bonded amount, or trips the circuit breaker
+            let found =
+                !res.events.is_empty() || STATE.load(deps.storage)?.total_bond_amount != bonded;
+            let kind = OperationKind::CheckSlashing;
+            pay_keeper_bounty(deps, &env, &info, kind, found, res)
+        }
+        ExecuteMsg::RunOperations { ops } => execute_run_operations(deps, env, info, ops),
         ExecuteMsg::UpdateParams {
             epoch_period,
             unbonding_period,
             peg_recovery_fee,
             er_threshold,
+            unbond_fee_bps,
         } => execute_update_params(
             deps,
             env,
@@ -139,7 +301,94 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             unbonding_period,
             peg_recovery_fee,
             er_threshold,
+            unbond_fee_bps,
         ),
+        ExecuteMsg::ProposePendingChange { change } => {
+            execute_propose_pending_change(deps, env, info, change)
+        }
+        ExecuteMsg::ApplyPendingChange { id } => execute_apply_pending_change(deps, env, info, id),
+        ExecuteMsg::CancelPendingChange { id } => {
+            execute_cancel_pending_change(deps, env, info, id)
+        }
+        ExecuteMsg::UpdateCircuitBreaker {
+            max_exchange_rate_change,
+            guardian,
+            freeze_transfers,
+        } => execute_update_circuit_breaker(
+            deps,
+            env,
+            info,
+            max_exchange_rate_change,
+            guardian,
+            freeze_transfers,
+        ),
+        ExecuteMsg::UpdatePriceGuard {
+            oracle_contract,
+            max_deviation,
+        } => execute_update_price_guard(deps, env, info, oracle_contract, max_deviation),
+        ExecuteMsg::UpdateIbcBridge { bridge_contract } => {
+            execute_update_ibc_bridge(deps, env, info, bridge_contract)
+        }
+        ExecuteMsg::UpdateWormhole { core_contract } => {
+            execute_update_wormhole(deps, env, info, core_contract)
+        }
+        ExecuteMsg::UpdateKeeperIncentive { incentive } => {
+            execute_update_keeper_incentive(deps, env, info, incentive)
+        }
+        ExecuteMsg::UpdateEpochAutoAdvance { max_undelegations } => {
+            execute_update_epoch_auto_advance(deps, env, info, max_undelegations)
+        }
+        ExecuteMsg::UpdateMinBondAmount { min_bond_amount } => {
+            execute_update_min_bond_amount(deps, env, info, min_bond_amount)
+        }
+        ExecuteMsg::UpdateAutoClaim { enabled } => {
+            execute_update_auto_claim(deps, env, info, enabled)
+        }
+        ExecuteMsg::UpdateTaxMode { tax_free } => {
+            execute_update_tax_mode(deps, env, info, tax_free)
+        }
+        ExecuteMsg::UpdateRampUp { max_growth } => {
+            execute_update_ramp_up(deps, env, info, max_growth)
+        }
+        ExecuteMsg::UpdateUnbondPriority { max_request } => {
+            execute_update_unbond_priority(deps, env, info, max_request)
+        }
+        ExecuteMsg::FundUnbondBuffer {} => execute_fund_unbond_buffer(deps, env, info),
+        ExecuteMsg::UpdateBufferTarget { target } => {
+            execute_update_buffer_target(deps, env, info, target)
+        }
+        ExecuteMsg::UpdateSurplusPolicy { policy } => {
+            execute_update_surplus_policy(deps, env, info, policy)
+        }
+        ExecuteMsg::ReconcileSurplus {} => execute_reconcile_surplus(deps, env, info),
+        ExecuteMsg::UpdateInsurance { insurance } => {
+            execute_update_insurance(deps, env, info, insurance)
+        }
+        ExecuteMsg::RegisterProposal {
+            proposal_id,
+            snapshot_height,
+            end_time,
+        } => execute_register_proposal(deps, env, info, proposal_id, snapshot_height, end_time),
+        ExecuteMsg::SignalVote { proposal_id, vote } => {
+            execute_signal_vote(deps, env, info, proposal_id, vote)
+        }
+        ExecuteMsg::CastVote { proposal_id } => execute_cast_vote(deps, env, proposal_id),
+        ExecuteMsg::Pause {} => execute_pause(deps, env, info),
+        ExecuteMsg::Resume {} => execute_resume(deps, env, info),
+        ExecuteMsg::ExecuteEmergency { msgs } => execute_emergency(deps, env, info, msgs),
+        ExecuteMsg::UpdateAccessMode { mode } => execute_update_access_mode(deps, env, info, mode),
+        ExecuteMsg::UpdateAccessList { add, remove } => {
+            execute_update_access_list(deps, env, info, add, remove)
+        }
+        ExecuteMsg::UpdateOperator { operator } => {
+            execute_update_operator(deps, env, info, operator)
+        }
+        ExecuteMsg::UpdateOperators { kind, add, remove } => {
+            execute_update_operators(deps, env, info, kind, add, remove)
+        }
+        ExecuteMsg::ImportState { .. } | ExecuteMsg::FinishImport {} => {
+            unreachable!("imports are handled before the state version check")
+        }
         ExecuteMsg::UpdateConfig {
             owner,
             reward_contract,
@@ -154,33 +403,20 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             token_contract,
             airdrop_registry_contract,
         ),
-        ExecuteMsg::SwapHook {
-            airdrop_token_contract,
-            airdrop_swap_contract,
-            swap_msg,
-        } => swap_hook(
-            deps,
-            env,
-            info,
+        ExecuteMsg::ForwardAirdropHook {
             airdrop_token_contract,
-            airdrop_swap_contract,
-            swap_msg,
-        ),
+        } => forward_airdrop_hook(deps, env, info, airdrop_token_contract),
         ExecuteMsg::ClaimAirdrop {
             airdrop_token_contract,
             airdrop_contract,
-            airdrop_swap_contract,
             claim_msg,
-            swap_msg,
         } => claim_airdrop(
             deps,
             env,
             info,
             airdrop_token_contract,
             airdrop_contract,
-            airdrop_swap_contract,
             claim_msg,
-            swap_msg,
         ),
     }
 }
@@ -191,56 +427,94 @@ pub fn receive_cw20(
     env: Env,
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
-) -> StdResult<Response> {
-    let contract_addr = info.sender.clone();
+) -> Result<Response, ContractError> {
+    // only token contract can execute this message
+    let conf = CONFIG.load(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())?
+        != conf
+            .token_contract
+            .ok_or_else(|| ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            })?
+    {
+        return Err(ContractError::Unauthorized {});
+    }
 
     match from_binary(&cw20_msg.msg) {
         Ok(Cw20HookMsg::Unbond {}) => {
-            // only token contract can execute this message
-            let conf = CONFIG.load(deps.storage)?;
-            if deps.api.addr_canonicalize(contract_addr.as_str())?
-                != conf
-                    .token_contract
-                    .expect("the token contract must have been registered")
-            {
-                return Err(StdError::generic_err("unauthorized"));
-            }
             execute_unbond(deps, env, info, cw20_msg.amount, cw20_msg.sender)
         }
-        Err(err) => Err(err),
+        Ok(Cw20HookMsg::IbcTransfer {
+            channel,
+            remote_address,
+            timeout,
+        }) => execute_ibc_transfer(
+            deps,
+            env,
+            cw20_msg.sender,
+            cw20_msg.amount,
+            Ics20TransferMsg {
+                channel,
+                remote_address,
+                timeout,
+            },
+        ),
+        Err(err) => Err(err.into()),
     }
 }
 
+/// Send the hooks to the airdrop registry, a failing airdrop claim must not block the
+/// operations it is sent with, its reply reports it
+pub(crate) fn airdrop_hook_msgs(
+    deps: Deps,
+    config: &Config,
+    hooks: Vec<Binary>,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let registry_addr =
+        deps.api
+            .addr_humanize(config.airdrop_registry_contract.as_ref().ok_or_else(|| {
+                ContractError::ContractNotRegistered {
+                    contract: "airdrop registry".to_string(),
+                }
+            })?)?;
+
+    Ok(hooks
+        .into_iter()
+        .enumerate()
+        .map(|(index, msg)| {
+            SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: registry_addr.to_string(),
+                    msg,
+                    funds: vec![],
+                }),
+                AIRDROP_HOOK_REPLY_ID_OFFSET + index as u64,
+            )
+        })
+        .collect())
+}
+
 /// Update general parameters
 /// Permissionless
 pub fn execute_update_global(
     deps: DepsMut,
     env: Env,
     airdrop_hooks: Option<Vec<Binary>>,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let mut messages: Vec<SubMsg> = vec![];
 
     let config = CONFIG.load(deps.storage)?;
     let reward_addr = deps
         .api
-        .addr_humanize(
-            &config
-                .reward_contract
-                .expect("the reward contract must have been registered"),
-        )?
+        .addr_humanize(config.reward_contract.as_ref().ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "reward".to_string(),
+            }
+        })?)?
         .to_string();
 
-    if airdrop_hooks.is_some() {
-        let registry_addr = deps
-            .api
-            .addr_humanize(&config.airdrop_registry_contract.unwrap())?;
-        for msg in airdrop_hooks.unwrap() {
-            messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: registry_addr.to_string(),
-                msg,
-                funds: vec![],
-            })))
-        }
+    if let Some(hooks) = airdrop_hooks {
+        messages.append(&mut airdrop_hook_msgs(deps.as_ref(), &config, hooks)?);
     }
 
     // Send withdraw message
@@ -251,13 +525,24 @@ pub fn execute_update_global(
     let swap_msg = SwapToRewardDenom {};
     messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: reward_addr.clone(),
-        msg: to_binary(&swap_msg).unwrap(),
+        msg: to_binary(&swap_msg)?,
         funds: vec![],
     })));
 
+    // the keeper reserve is topped up before the rest is distributed
+    if let Some(incentive) = KEEPER_INCENTIVE.may_load(deps.storage)? {
+        messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: reward_addr.clone(),
+            msg: to_binary(&FundKeeperReserve {
+                target: incentive.reserve,
+            })?,
+            funds: vec![],
+        })));
+    }
+
     messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: reward_addr,
-        msg: to_binary(&UpdateGlobalIndex {}).unwrap(),
+        msg: to_binary(&UpdateGlobalIndex {})?,
         funds: vec![],
     })));
 
@@ -266,12 +551,70 @@ pub fn execute_update_global(
         last_state.last_index_modification = env.block.time.seconds();
         Ok(last_state)
     })?;
+    REWARDS_TOPPED_UP.remove(deps.storage);
 
     Ok(Response::new()
         .add_submessages(messages)
         .add_attributes(vec![attr("action", "update_global_index")]))
 }
 
+/// Forward rewards of the external mode to the reward contract, the next
+/// global index update distributes them
+/// Permissionless
+pub fn execute_reward_top_up(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    if read_hub_mode(deps.storage)? != HubMode::External {
+        return Err(
+            StdError::generic_err("Rewards are only topped up in the external mode").into(),
+        );
+    }
+
+    let underlying_coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let rewards: Vec<Coin> = info
+        .funds
+        .into_iter()
+        .filter(|coin| coin.denom != underlying_coin_denom && !coin.amount.is_zero())
+        .collect();
+    if rewards.is_empty() {
+        return Err(StdError::generic_err("No rewards are provided").into());
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let reward_addr = deps
+        .api
+        .addr_humanize(&config.reward_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "reward".to_string(),
+            }
+        })?)?;
+
+    // the treasury is queried once for the rate, not once per coin
+    let tax_free = TAX_FREE.may_load(deps.storage)?.unwrap_or_default();
+    let mut tax = TaxCache::new(treasury(&deps.querier, tax_free));
+    let mut amount = vec![];
+    for coin in rewards.iter() {
+        amount.push(tax.deduct_tax(coin.clone())?);
+    }
+    REWARDS_TOPPED_UP.save(deps.storage, &true)?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: reward_addr.to_string(),
+            amount,
+        })
+        .add_attributes(vec![
+            attr("action", "reward_top_up"),
+            attr("from", info.sender),
+            attr(
+                "amount",
+                rewards
+                    .iter()
+                    .map(|coin| coin.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        ]))
+}
+
 /// Create withdraw requests for all validators
 fn withdraw_all_rewards(deps: &DepsMut, delegator: Addr) -> StdResult<Vec<SubMsg>> {
     let mut messages: Vec<SubMsg> = vec![];
@@ -292,7 +635,9 @@ fn withdraw_all_rewards(deps: &DepsMut, delegator: Addr) -> StdResult<Vec<SubMsg
 
 /// Check whether slashing has happened
 /// This is used for checking slashing while bonding or unbonding
-pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
+/// Returns the alert event when the new exchange rate tripped the circuit breaker,
+/// in which case the contract is paused and the state is left untouched
+pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<Option<Event>> {
     //read params
     let params = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
@@ -303,7 +648,7 @@ pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
     // Check the actual bonded amount
     let delegations = deps.querier.query_all_delegations(env.contract.address)?;
     if delegations.is_empty() {
-        Ok(())
+        Ok(None)
     } else {
         let mut actual_total_bonded = Uint128::zero();
         for delegation in delegations {
@@ -311,6 +656,11 @@ pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
                 actual_total_bonded += delegation.amount.amount
             }
         }
+        // the pending cover is delegated once the insurance fund pays it
+        let pending_cover = PENDING_COVER.may_load(deps.storage)?.unwrap_or_default();
+        actual_total_bonded += pending_cover;
+        // so is the bonded underlying skimmed into the unbond buffer
+        actual_total_bonded += skimmed_bonded(deps.storage)?;
 
         // Need total issued for updating the exchange rate
         let total_issued = query_total_issued(deps.as_ref())?;
@@ -318,40 +668,82 @@ pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
 
         // Slashing happens if the expected amount is less than stored amount
         if state_total_bonded.u128() > actual_total_bonded.u128() {
-            STATE.update(deps.storage, |mut state| -> StdResult<State> {
-                state.total_bond_amount = actual_total_bonded;
-                state.update_exchange_rate(total_issued, current_requested_fee);
-                Ok(state)
-            })?;
+            let mut state = STATE.load(deps.storage)?;
+            let previous_rate = state.exchange_rate;
+            // the cover of the insurance fund is kept out of the exchange rate
+            let cover = slashing_cover(deps.storage, state_total_bonded - actual_total_bonded)?;
+            state.total_bond_amount = actual_total_bonded + cover;
+            state.update_exchange_rate(total_issued, current_requested_fee);
+
+            let alert = trip_circuit_breaker(deps.storage, previous_rate, state.exchange_rate)?;
+            if alert.is_some() {
+                return Ok(alert);
+            }
+            STATE.save(deps.storage, &state)?;
+            if !cover.is_zero() {
+                PENDING_COVER.save(deps.storage, &(pending_cover + cover))?;
+            }
         }
 
-        Ok(())
+        Ok(None)
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Pause the contract if the exchange rate moved more than the circuit breaker allows
+pub(crate) fn trip_circuit_breaker(
+    storage: &mut dyn Storage,
+    previous_rate: Decimal,
+    new_rate: Decimal,
+) -> StdResult<Option<Event>> {
+    let mut breaker = read_circuit_breaker(storage)?;
+    let max_change = match breaker.max_exchange_rate_change {
+        Some(max_change) if !previous_rate.is_zero() => max_change,
+        _ => return Ok(None),
+    };
+
+    let change = if new_rate > previous_rate {
+        new_rate - previous_rate
+    } else {
+        previous_rate - new_rate
+    };
+    if change <= decimal_multiplication_in_256(previous_rate, max_change) {
+        return Ok(None);
+    }
+
+    breaker.paused = true;
+    CIRCUIT_BREAKER.save(storage, &breaker)?;
+
+    Ok(Some(Event::new("circuit_breaker_tripped").add_attributes(
+        vec![
+            attr("previous_exchange_rate", previous_rate.to_string()),
+            attr("exchange_rate", new_rate.to_string()),
+        ],
+    )))
+}
+
+/// Claim an airdrop for the hub and forward the claimed tokens
+/// Only the airdrop registry is allowed to execute
 pub fn claim_airdrop(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     airdrop_token_contract: String,
     airdrop_contract: String,
-    airdrop_swap_contract: String,
     claim_msg: Binary,
-    swap_msg: Binary,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let conf = CONFIG.load(deps.storage)?;
 
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
 
-    let airdrop_reg_raw = conf.airdrop_registry_contract.unwrap();
+    let airdrop_reg_raw =
+        conf.airdrop_registry_contract
+            .ok_or_else(|| ContractError::ContractNotRegistered {
+                contract: "airdrop registry".to_string(),
+            })?;
     let airdrop_reg = deps.api.addr_humanize(&airdrop_reg_raw)?;
 
     if airdrop_reg_raw != sender_raw {
-        return Err(StdError::generic_err(format!(
-            "Sender must be {}",
-            airdrop_reg
-        )));
+        return Err(StdError::generic_err(format!("Sender must be {}", airdrop_reg)).into());
     }
 
     let mut messages: Vec<SubMsg> = vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -362,49 +754,59 @@ pub fn claim_airdrop(
 
     messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: env.contract.address.to_string(),
-        msg: to_binary(&SwapHook {
-            airdrop_token_contract,
-            airdrop_swap_contract,
-            swap_msg,
+        msg: to_binary(&ForwardAirdropHook {
+            airdrop_token_contract: airdrop_token_contract.clone(),
         })?,
         funds: vec![],
     })));
 
-    Ok(Response::new().add_submessages(messages))
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(vec![
+            attr("action", "claim_airdrop"),
+            attr("sender", info.sender),
+            attr("token_contract", airdrop_token_contract),
+        ]))
 }
 
-pub fn swap_hook(
+/// Send the whole airdrop token balance of the hub to the airdrop registry
+/// Only the hub itself is allowed to execute
+pub fn forward_airdrop_hook(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     airdrop_token_contract: String,
-    airdrop_swap_contract: String,
-    swap_msg: Binary,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     if info.sender != env.contract.address {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
-    let airdrop_token_balance: BalanceResponse =
-        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: airdrop_token_contract.to_string(),
-            msg: to_binary(&Cw20QueryMsg::Balance {
-                address: env.contract.address.to_string(),
-            })?,
-        }))?;
+    let conf = CONFIG.load(deps.storage)?;
+    let airdrop_reg = deps
+        .api
+        .addr_humanize(&conf.airdrop_registry_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "airdrop registry".to_string(),
+            }
+        })?)?;
+
+    let airdrop_token_balance = query_token_balance(
+        &deps.querier,
+        &airdrop_token_contract,
+        env.contract.address.as_str(),
+    )?;
 
-    if airdrop_token_balance.balance == Uint128::new(0) {
-        return Err(StdError::generic_err(format!(
-            "There is no balance for {} in airdrop token contract {}",
-            &env.contract.address, &airdrop_token_contract
-        )));
+    if airdrop_token_balance.is_zero() {
+        return Err(ContractError::NoAirdropBalance {
+            address: env.contract.address.to_string(),
+            token_contract: airdrop_token_contract,
+        });
     }
     let messages: Vec<SubMsg> = vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: airdrop_token_contract.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Send {
-            contract: airdrop_swap_contract,
-            amount: airdrop_token_balance.balance,
-            msg: swap_msg,
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: airdrop_reg.to_string(),
+            amount: airdrop_token_balance,
         })?,
         funds: vec![],
     }))];
@@ -412,24 +814,84 @@ pub fn swap_hook(
     Ok(Response::new()
         .add_submessages(messages)
         .add_attributes(vec![
-            attr("action", "swap_airdrop_token"),
+            attr("action", "forward_airdrop_token"),
             attr("token_contract", airdrop_token_contract),
-            attr("swap_amount", airdrop_token_balance.balance),
+            attr("recipient", airdrop_reg),
+            attr("amount", airdrop_token_balance),
         ]))
 }
 
 /// Handler for tracking slashing
-pub fn execute_slashing(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn execute_slashing(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
     // call slashing
-    slashing(&mut deps, env)?;
+    if let Some(alert) = slashing(&mut deps, env.clone())? {
+        return Ok(Response::new()
+            .add_event(alert)
+            .add_attributes(vec![attr("action", "check_slashing")]));
+    }
+    let mut res = Response::new();
+    if let Some(cover_request) = cover_request_msg(deps.branch(), &env)? {
+        res = res.add_submessage(cover_request);
+    }
     // read state for log
     let state = STATE.load(deps.storage)?;
-    Ok(Response::new().add_attributes(vec![
+    Ok(res.add_attributes(vec![
         attr("action", "check_slashing"),
         attr("new_exchange_rate", state.exchange_rate.to_string()),
     ]))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id == UNBOND_REPLY_ID {
+        EXECUTION_LOCK.unlock(deps.storage);
+        return Ok(Response::new());
+    }
+    if msg.id == INSURANCE_REPLY_ID {
+        return handle_cover_reply(deps, env);
+    }
+    if msg.id == AUTO_CLAIM_REPLY_ID {
+        return match msg.result {
+            ContractResult::Err(error) => Ok(Response::new().add_attributes(vec![
+                attr("action", "auto_claim_failed"),
+                attr("error", error),
+            ])),
+            ContractResult::Ok(_) => Ok(Response::new()),
+        };
+    }
+    if msg.id < AIRDROP_HOOK_REPLY_ID_OFFSET {
+        return Err(StdError::generic_err(format!("Unknown reply id {}", msg.id)).into());
+    }
+
+    let hook_index = msg.id - AIRDROP_HOOK_REPLY_ID_OFFSET;
+    match msg.result {
+        ContractResult::Err(error) => Ok(Response::new().add_attributes(vec![
+            attr("action", "airdrop_hook_failed"),
+            attr("hook_index", hook_index.to_string()),
+            attr("error", error),
+        ])),
+        ContractResult::Ok(_) => Ok(Response::new()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = migrate_state(
+        deps.storage,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        &[],
+        STATE_VERSION,
+        MIGRATION_STEPS,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("from_state_version", previous.to_string()),
+        attr("to_state_version", STATE_VERSION.to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -441,39 +903,174 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_binary(&query_withdrawable_unbonded(deps, address, env)?)
         }
         QueryMsg::Parameters {} => to_binary(&query_params(deps)?),
-        QueryMsg::UnbondRequests { address } => to_binary(&query_unbond_requests(deps, address)?),
+        QueryMsg::UnbondRequests {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_unbond_requests(deps, address, start_after, limit)?),
+        QueryMsg::CollateralInfo { address } => to_binary(&query_collateral_info(deps, address)?),
         QueryMsg::AllHistory { start_from, limit } => {
             to_binary(&query_unbond_requests_limitation(deps, start_from, limit)?)
         }
+        QueryMsg::PendingChanges { start_after, limit } => {
+            to_binary(&query_pending_changes(deps, start_after, limit)?)
+        }
+        QueryMsg::CircuitBreaker {} => to_binary(&query_circuit_breaker(deps)?),
+        QueryMsg::PriceGuard {} => to_binary(&query_price_guard(deps)?),
+        QueryMsg::IbcBridge {} => to_binary(&query_ibc_bridge(deps)?),
+        QueryMsg::Wormhole {} => to_binary(&query_wormhole(deps)?),
+        QueryMsg::KeeperIncentive {} => to_binary(&query_keeper_incentive(deps)?),
+        QueryMsg::EpochAutoAdvance {} => to_binary(&EpochAutoAdvanceResponse {
+            max_undelegations: EPOCH_AUTO_ADVANCE.may_load(deps.storage)?,
+        }),
+        QueryMsg::MinBondAmount {} => to_binary(&MinBondAmountResponse {
+            min_bond_amount: MIN_BOND_AMOUNT.may_load(deps.storage)?,
+        }),
+        QueryMsg::AutoClaim {} => to_binary(&AutoClaimResponse {
+            enabled: AUTO_CLAIM_REWARDS
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        }),
+        QueryMsg::TaxMode {} => to_binary(&TaxModeResponse {
+            tax_free: TAX_FREE.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::RampAllowances {} => to_binary(&query_ramp_allowances(deps, env)?),
+        QueryMsg::ValidatorDelegations {} => to_binary(&query_validator_delegations(deps, env)?),
+        QueryMsg::UnbondPriority {} => to_binary(&query_unbond_priority(deps)?),
+        QueryMsg::BufferTarget {} => to_binary(&query_buffer_target(deps)?),
+        QueryMsg::Insurance {} => to_binary(&query_insurance(deps)?),
+        QueryMsg::Surplus {} => to_binary(&query_surplus(deps, env)?),
+        QueryMsg::ExchangeRateTwap { window_seconds } => {
+            to_binary(&query_exchange_rate_twap(deps, env, window_seconds)?)
+        }
+        QueryMsg::FeesCollected {} => to_binary(&FeesCollectedResponse {
+            peg_recovery_fees: PEG_RECOVERY_FEES
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+            unbond_fees: UNBOND_FEES.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::UnbondBatches { start_after, limit } => {
+            to_binary(&query_unbond_batches(deps, start_after, limit)?)
+        }
+        QueryMsg::Liquidity {} => to_binary(&query_liquidity(deps)?),
+        QueryMsg::UnbondBatchStatus { batch_id } => {
+            to_binary(&query_unbond_batch_status(deps, batch_id)?)
+        }
+        QueryMsg::UnbondBatchProof { batch_id } => {
+            to_binary(&query_unbond_batch_proof(deps, batch_id)?)
+        }
+        QueryMsg::SimulateBond { amount } => to_binary(&query_simulate_bond(deps, amount)?),
+        QueryMsg::SimulateUnbond { amount } => to_binary(&query_simulate_unbond(deps, amount)?),
+        QueryMsg::UserHistory {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_user_history(deps, address, start_after, limit)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_binary(&query_config_history(deps, start_after, limit)?)
+        }
+        QueryMsg::Proposal { proposal_id } => to_binary(&query_proposal(deps, proposal_id)?),
+        QueryMsg::Mode {} => to_binary(&ModeResponse {
+            mode: read_hub_mode(deps.storage)?,
+        }),
+        QueryMsg::AccessControl { start_after, limit } => {
+            to_binary(&query_access_control(deps, start_after, limit)?)
+        }
+        QueryMsg::Operator {} => to_binary(&OPERATOR.query(deps.storage, deps.api)?),
+        QueryMsg::Operators {
+            kind,
+            start_after,
+            limit,
+        } => to_binary(&query_operators(deps, kind, start_after, limit)?),
+        QueryMsg::ExportState { start_after, limit } => {
+            to_binary(&export_state(deps.storage, start_after, limit)?)
+        }
     }
 }
 
-fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+fn query_circuit_breaker(deps: Deps) -> StdResult<CircuitBreakerResponse> {
+    let breaker = read_circuit_breaker(deps.storage)?;
+    let guardian = breaker
+        .guardian
+        .map(|guardian| deps.api.addr_humanize(&guardian))
+        .transpose()?
+        .map(|guardian| guardian.to_string());
+
+    Ok(CircuitBreakerResponse {
+        max_exchange_rate_change: breaker.max_exchange_rate_change,
+        guardian,
+        paused: breaker.paused,
+        freeze_transfers: breaker.freeze_transfers,
+    })
+}
+
+fn query_price_guard(deps: Deps) -> StdResult<PriceGuardResponse> {
+    Ok(match PRICE_GUARD.may_load(deps.storage)? {
+        Some(guard) => PriceGuardResponse {
+            oracle_contract: Some(deps.api.addr_humanize(&guard.oracle_contract)?.to_string()),
+            max_deviation: Some(guard.max_deviation),
+        },
+        None => PriceGuardResponse {
+            oracle_contract: None,
+            max_deviation: None,
+        },
+    })
+}
+
+fn query_ibc_bridge(deps: Deps) -> StdResult<IbcBridgeResponse> {
+    let bridge_contract = IBC_BRIDGE
+        .may_load(deps.storage)?
+        .map(|bridge| deps.api.addr_humanize(&bridge))
+        .transpose()?
+        .map(|bridge| bridge.to_string());
+    Ok(IbcBridgeResponse { bridge_contract })
+}
+
+fn query_access_control(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AccessControlResponse> {
+    let start_after = start_after
+        .map(|address| deps.api.addr_canonicalize(address.as_str()))
+        .transpose()?;
+    let addresses = read_access_list(deps.storage, start_after, limit)?
+        .iter()
+        .map(|address| Ok(deps.api.addr_humanize(address)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(AccessControlResponse {
+        mode: read_access_mode(deps.storage)?,
+        addresses,
+    })
+}
+
+fn query_pending_changes(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingChangesResponse> {
+    Ok(PendingChangesResponse {
+        timelock_period: TIMELOCK.delay(deps.storage)?,
+        changes: TIMELOCK.read_pending(deps.storage, start_after, limit)?,
+    })
+}
+
+pub(crate) fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
     let mut reward: Option<String> = None;
     let mut token: Option<String> = None;
     let mut airdrop: Option<String> = None;
-    if config.reward_contract.is_some() {
-        reward = Some(
-            deps.api
-                .addr_humanize(&config.reward_contract.unwrap())
-                .unwrap()
-                .to_string(),
-        );
+    if let Some(reward_contract) = config.reward_contract {
+        reward = Some(deps.api.addr_humanize(&reward_contract)?.to_string());
     }
-    if config.token_contract.is_some() {
-        token = Some(
-            deps.api
-                .addr_humanize(&config.token_contract.unwrap())
-                .unwrap()
-                .to_string(),
-        );
+    if let Some(token_contract) = config.token_contract {
+        token = Some(deps.api.addr_humanize(&token_contract)?.to_string());
     }
-    if config.airdrop_registry_contract.is_some() {
+    if let Some(airdrop_registry_contract) = config.airdrop_registry_contract {
         airdrop = Some(
             deps.api
-                .addr_humanize(&config.airdrop_registry_contract.unwrap())
-                .unwrap()
+                .addr_humanize(&airdrop_registry_contract)?
                 .to_string(),
         );
     }
@@ -534,26 +1131,62 @@ fn query_params(deps: Deps) -> StdResult<Parameters> {
 }
 
 pub(crate) fn query_total_issued(deps: Deps) -> StdResult<Uint128> {
-    let token_address = deps
+    query_token_supply(&deps.querier, &token_contract(deps)?)
+}
+
+fn token_contract(deps: Deps) -> StdResult<String> {
+    Ok(deps
         .api
-        .addr_humanize(
-            &CONFIG
-                .load(deps.storage)?
-                .token_contract
-                .expect("token contract must have been registered"),
-        )?
-        .to_string();
-    let token_info: TokenInfoResponse =
-        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: token_address,
-            msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
-        }))?;
+        .addr_humanize(&CONFIG.load(deps.storage)?.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?
+        .to_string())
+}
 
-    Ok(token_info.total_supply)
+fn query_collateral_info(deps: Deps, address: String) -> StdResult<CollateralInfoResponse> {
+    let exchange_rate = STATE.load(deps.storage)?.exchange_rate;
+    let balance = query_token_balance(&deps.querier, &token_contract(deps)?, &address)?;
+
+    let mut requests = read_wait_list(
+        deps.storage,
+        address.clone(),
+        None,
+        MAX_WITHDRAW_REQUESTS + 1,
+    )?;
+    let more_requests = requests.len() > MAX_WITHDRAW_REQUESTS;
+    requests.truncate(MAX_WITHDRAW_REQUESTS);
+
+    let mut unbonding = Uint128::zero();
+    let mut unbonding_value = Uint128::zero();
+    for (batch_id, amount) in requests {
+        let rate = match read_unbond_history(deps.storage, batch_id) {
+            Ok(history) if history.released => history.withdraw_rate,
+            _ => exchange_rate,
+        };
+        unbonding += amount;
+        unbonding_value += amount * rate;
+    }
+
+    Ok(CollateralInfoResponse {
+        address,
+        exchange_rate,
+        balance,
+        balance_value: balance * exchange_rate,
+        unbonding,
+        unbonding_value,
+        more_requests,
+    })
 }
 
-fn query_unbond_requests(deps: Deps, address: String) -> StdResult<UnbondRequestsResponse> {
-    let requests = get_unbond_requests(deps.storage, address.clone())?;
+fn query_unbond_requests(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UnbondRequestsResponse> {
+    let requests = get_unbond_requests(deps.storage, address.clone(), start_after, limit)?;
     let res = UnbondRequestsResponse { address, requests };
     Ok(res)
 }
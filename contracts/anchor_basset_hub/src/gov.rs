@@ -0,0 +1,170 @@
+use crate::state::{read_hub_mode, Proposal, SignalledVote, CONFIG, PROPOSALS, SIGNALLED_VOTES};
+use basset::contract_error::ContractError;
+use basset::gov::{encode_msg_vote_weighted, VoteOption, MSG_VOTE_WEIGHTED_TYPE_URL};
+use basset::hub::{HubMode, ProposalResponse};
+use basset::querier::query_token_balance_at;
+use cosmwasm_std::{
+    attr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+};
+use cw_storage_plus::U64Key;
+
+/// Open a gov proposal to the signals of the holders
+/// Only the owner is allowed to execute
+pub fn execute_register_proposal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+    snapshot_height: u64,
+    end_time: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // balances after the snapshot may still change, so it must be in the past
+    if snapshot_height >= env.block.height {
+        return Err(
+            StdError::generic_err("The snapshot height must be before the current height").into(),
+        );
+    }
+    if end_time <= env.block.time.seconds() {
+        return Err(StdError::generic_err("The end time must be in the future").into());
+    }
+    if PROPOSALS.has(deps.storage, U64Key::new(proposal_id)) {
+        return Err(StdError::generic_err(format!(
+            "Proposal {} is already registered",
+            proposal_id
+        ))
+        .into());
+    }
+
+    PROPOSALS.save(
+        deps.storage,
+        U64Key::new(proposal_id),
+        &Proposal {
+            snapshot_height,
+            end_time,
+            tally: Default::default(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_proposal"),
+        attr("proposal_id", proposal_id.to_string()),
+        attr("snapshot_height", snapshot_height.to_string()),
+        attr("end_time", end_time.to_string()),
+    ]))
+}
+
+/// Signal a vote weighted by the bAsset the sender held at the snapshot height
+pub fn execute_signal_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+    vote: VoteOption,
+) -> Result<Response, ContractError> {
+    let mut proposal = load_open_proposal(deps.as_ref(), &env, proposal_id)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps
+        .api
+        .addr_humanize(&config.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?;
+    let weight = query_token_balance_at(
+        &deps.querier,
+        token_address.as_str(),
+        info.sender.as_str(),
+        proposal.snapshot_height,
+    )?;
+    if weight.is_zero() {
+        return Err(StdError::generic_err("No bAsset was held at the snapshot height").into());
+    }
+
+    let holder = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = (U64Key::new(proposal_id), holder.as_slice());
+    if let Some(previous) = SIGNALLED_VOTES.may_load(deps.storage, key.clone())? {
+        proposal.tally.remove(previous.option, previous.weight)?;
+    }
+    proposal.tally.add(vote, weight);
+    SIGNALLED_VOTES.save(
+        deps.storage,
+        key,
+        &SignalledVote {
+            option: vote,
+            weight,
+        },
+    )?;
+    PROPOSALS.save(deps.storage, U64Key::new(proposal_id), &proposal)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "signal_vote"),
+        attr("proposal_id", proposal_id.to_string()),
+        attr("voter", info.sender),
+        attr("weight", weight),
+    ]))
+}
+
+/// Vote with the delegations of the hub, split as the holders signalled. A later cast
+/// replaces the vote of the hub with the current tally
+/// Permissionless
+pub fn execute_cast_vote(
+    deps: DepsMut,
+    env: Env,
+    proposal_id: u64,
+) -> Result<Response, ContractError> {
+    if read_hub_mode(deps.storage)? == HubMode::External {
+        return Err(StdError::generic_err("The hub has no delegations to vote with").into());
+    }
+    let proposal = load_open_proposal(deps.as_ref(), &env, proposal_id)?;
+    let options = proposal.tally.weighted_options()?;
+
+    let vote = CosmosMsg::Stargate {
+        type_url: MSG_VOTE_WEIGHTED_TYPE_URL.to_string(),
+        value: encode_msg_vote_weighted(proposal_id, env.contract.address.as_str(), &options),
+    };
+
+    Ok(Response::new().add_message(vote).add_attributes(vec![
+        attr("action", "cast_vote"),
+        attr("proposal_id", proposal_id.to_string()),
+        attr("yes", proposal.tally.yes),
+        attr("no", proposal.tally.no),
+        attr("abstain", proposal.tally.abstain),
+        attr("no_with_veto", proposal.tally.no_with_veto),
+    ]))
+}
+
+fn load_open_proposal(deps: Deps, env: &Env, proposal_id: u64) -> StdResult<Proposal> {
+    let proposal = PROPOSALS
+        .may_load(deps.storage, U64Key::new(proposal_id))?
+        .ok_or_else(|| {
+            StdError::generic_err(format!("Proposal {} is not registered", proposal_id))
+        })?;
+    if env.block.time.seconds() >= proposal.end_time {
+        return Err(StdError::generic_err(format!(
+            "Voting on proposal {} has ended",
+            proposal_id
+        )));
+    }
+    Ok(proposal)
+}
+
+pub fn query_proposal(deps: Deps, proposal_id: u64) -> StdResult<ProposalResponse> {
+    let proposal = PROPOSALS
+        .may_load(deps.storage, U64Key::new(proposal_id))?
+        .ok_or_else(|| {
+            StdError::generic_err(format!("Proposal {} is not registered", proposal_id))
+        })?;
+    Ok(ProposalResponse {
+        proposal_id,
+        snapshot_height: proposal.snapshot_height,
+        end_time: proposal.end_time,
+        tally: proposal.tally,
+    })
+}
@@ -0,0 +1,139 @@
+use crate::contract::query_config;
+use crate::state::{CONFIG_HISTORY, CONFIG_VERSION, PARAMETERS, USER_HISTORY, USER_HISTORY_COUNT};
+use basset::hub::{
+    ConfigHistoryResponse, ConfigResponse, ConfigVersion, Parameters, UserAction, UserEvent,
+    UserHistoryResponse,
+};
+use basset::pagination::{calc_limit, calc_range_start_u64};
+use cosmwasm_std::{
+    attr, Attribute, CanonicalAddr, Decimal, Deps, DepsMut, Env, Order, StdResult, Storage,
+};
+use cw_storage_plus::U64Key;
+
+/// The events kept for each address, older events are removed
+const MAX_USER_EVENTS: u64 = 100;
+/// The replaced config versions kept, older versions are removed
+const MAX_CONFIG_VERSIONS: u64 = 100;
+
+/// Append an event to the history of address, e.g. for the cost basis of integrators
+pub(crate) fn record_user_event(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address: &CanonicalAddr,
+    exchange_rate: Decimal,
+    action: UserAction,
+) -> StdResult<()> {
+    let id = USER_HISTORY_COUNT
+        .may_load(storage, address.as_slice())?
+        .unwrap_or_default();
+    USER_HISTORY.save(
+        storage,
+        (address.as_slice(), U64Key::new(id)),
+        &UserEvent {
+            id,
+            time: env.block.time.seconds(),
+            height: env.block.height,
+            exchange_rate,
+            action,
+        },
+    )?;
+    USER_HISTORY_COUNT.save(storage, address.as_slice(), &(id + 1))?;
+
+    if id >= MAX_USER_EVENTS {
+        USER_HISTORY.remove(
+            storage,
+            (address.as_slice(), U64Key::new(id - MAX_USER_EVENTS)),
+        );
+    }
+    Ok(())
+}
+
+pub fn query_user_history(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UserHistoryResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let limit = calc_limit(limit);
+    let start = calc_range_start_u64(start_after);
+
+    let events = USER_HISTORY
+        .prefix(address_raw.as_slice())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<Vec<UserEvent>>>()?;
+
+    Ok(UserHistoryResponse { address, events })
+}
+
+/// The config and parameters live before a change
+pub(crate) fn live_config(deps: Deps) -> StdResult<(ConfigResponse, Parameters)> {
+    Ok((query_config(deps)?, PARAMETERS.load(deps.storage)?))
+}
+
+/// Keep previous, the set live before a change, and return the attributes of the whole
+/// set live after it
+pub(crate) fn record_config_change(
+    deps: DepsMut,
+    env: &Env,
+    previous: (ConfigResponse, Parameters),
+) -> StdResult<Vec<Attribute>> {
+    let version = CONFIG_VERSION.may_load(deps.storage)?.unwrap_or_default();
+    let (config, params) = previous;
+    CONFIG_HISTORY.save(
+        deps.storage,
+        U64Key::new(version),
+        &ConfigVersion {
+            version,
+            replaced_height: env.block.height,
+            replaced_time: env.block.time.seconds(),
+            config,
+            params,
+        },
+    )?;
+    CONFIG_VERSION.save(deps.storage, &(version + 1))?;
+    if version >= MAX_CONFIG_VERSIONS {
+        CONFIG_HISTORY.remove(deps.storage, U64Key::new(version - MAX_CONFIG_VERSIONS));
+    }
+
+    let (config, params) = live_config(deps.as_ref())?;
+    Ok(vec![
+        attr("config_version", (version + 1).to_string()),
+        attr("owner", config.owner),
+        attr(
+            "reward_contract",
+            config.reward_contract.unwrap_or_default(),
+        ),
+        attr("token_contract", config.token_contract.unwrap_or_default()),
+        attr(
+            "airdrop_registry_contract",
+            config.airdrop_registry_contract.unwrap_or_default(),
+        ),
+        attr("epoch_period", params.epoch_period.to_string()),
+        attr("underlying_coin_denom", params.underlying_coin_denom),
+        attr("unbonding_period", params.unbonding_period.to_string()),
+        attr("peg_recovery_fee", params.peg_recovery_fee.to_string()),
+        attr("er_threshold", params.er_threshold.to_string()),
+        attr("reward_denom", params.reward_denom),
+        attr("unbond_fee_bps", params.unbond_fee_bps.to_string()),
+    ])
+}
+
+pub fn query_config_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ConfigHistoryResponse> {
+    let limit = calc_limit(limit);
+    let start = calc_range_start_u64(start_after);
+
+    let versions = CONFIG_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<Vec<ConfigVersion>>>()?;
+
+    Ok(ConfigHistoryResponse { versions })
+}
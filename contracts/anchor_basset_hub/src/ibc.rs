@@ -0,0 +1,50 @@
+use crate::state::{CONFIG, IBC_BRIDGE};
+use basset::contract_error::ContractError;
+use basset::ibc::Ics20TransferMsg;
+use cosmwasm_std::{attr, to_binary, DepsMut, Env, Response, StdError, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
+
+/// Forward the bAsset sent by the token contract to the bridge, which locks it and
+/// transfers the voucher to remote_address. The rewards of the locked bAsset accrue to the bridge
+pub(crate) fn execute_ibc_transfer(
+    deps: DepsMut,
+    _env: Env,
+    sender: String,
+    amount: Uint128,
+    transfer: Ics20TransferMsg,
+) -> Result<Response, ContractError> {
+    let bridge = IBC_BRIDGE
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No IBC bridge is configured"))?;
+    let bridge = deps.api.addr_humanize(&bridge)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps
+        .api
+        .addr_humanize(&config.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?;
+
+    let attributes = vec![
+        attr("action", "ibc_transfer"),
+        attr("sender", sender),
+        attr("channel", transfer.channel.as_str()),
+        attr("remote_address", transfer.remote_address.as_str()),
+        attr("amount", amount),
+    ];
+    let send = Cw20ExecuteMsg::Send {
+        contract: bridge.to_string(),
+        amount,
+        msg: to_binary(&transfer)?,
+    };
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: token_address.to_string(),
+            msg: to_binary(&send)?,
+            funds: vec![],
+        })
+        .add_attributes(attributes))
+}
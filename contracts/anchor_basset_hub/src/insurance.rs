@@ -0,0 +1,169 @@
+use crate::config::least_delegated_validator;
+use crate::contract::{query_total_issued, trip_circuit_breaker};
+use crate::state::{
+    CONFIG, COVER_REQUEST, CURRENT_BATCH, INSURANCE, INSURANCE_COVERED, PARAMETERS, PENDING_COVER,
+    STATE,
+};
+use basset::contract_error::ContractError;
+use basset::hub::{Insurance, InsuranceResponse};
+use basset::insurance::InsuranceExecuteMsg;
+use cosmwasm_std::{
+    attr, coin, to_binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StakingMsg,
+    StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+
+/// Checked before the airdrop hooks, whose ids count up from one
+pub(crate) const INSURANCE_REPLY_ID: u64 = u64::MAX;
+
+/// Set the insurance fund asked to cover the slashing. Removing it reprices the bAsset by
+/// the cover still pending. Only creator/owner is allowed to execute
+pub fn execute_update_insurance(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    insurance: Option<Insurance>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let res = Response::new().add_attributes(vec![attr("action", "update_insurance")]);
+    match insurance {
+        Some(insurance) => {
+            let contract = deps.api.addr_validate(&insurance.contract)?;
+            INSURANCE.save(
+                deps.storage,
+                &(
+                    deps.api.addr_canonicalize(contract.as_str())?,
+                    insurance.max_cover,
+                ),
+            )?;
+            Ok(res)
+        }
+        None => {
+            INSURANCE.remove(deps.storage);
+            let settled = settle_cover(deps, Uint128::zero())?;
+            Ok(res
+                .add_attributes(settled.attributes)
+                .add_events(settled.events))
+        }
+    }
+}
+
+/// The part of a slashing loss the insurance fund is asked to cover, zero without a fund
+pub(crate) fn slashing_cover(storage: &dyn Storage, loss: Uint128) -> StdResult<Uint128> {
+    Ok(match INSURANCE.may_load(storage)? {
+        Some((_, max_cover)) => loss.min(max_cover),
+        None => Uint128::zero(),
+    })
+}
+
+/// Request the pending cover from the insurance fund, none when nothing is pending or a
+/// request is already sent. The balance of the underlying is kept to measure what the fund
+/// pays in the reply
+pub(crate) fn cover_request_msg(deps: DepsMut, env: &Env) -> StdResult<Option<SubMsg>> {
+    let pending = PENDING_COVER.may_load(deps.storage)?.unwrap_or_default();
+    if COVER_REQUEST.may_load(deps.storage)?.is_some() {
+        return Ok(None);
+    }
+    let contract = match INSURANCE.may_load(deps.storage)? {
+        Some((contract, _)) if !pending.is_zero() => deps.api.addr_humanize(&contract)?,
+        _ => return Ok(None),
+    };
+
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, coin_denom)?
+        .amount;
+    COVER_REQUEST.save(deps.storage, &balance)?;
+
+    Ok(Some(SubMsg::reply_always(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: to_binary(&InsuranceExecuteMsg::RequestCover { amount: pending })?,
+            funds: vec![],
+        }),
+        INSURANCE_REPLY_ID,
+    )))
+}
+
+/// Delegate the cover the insurance fund paid and reprice the bAsset by the rest.
+/// A failed request pays nothing, the hub still settles it
+pub(crate) fn handle_cover_reply(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let balance_before = COVER_REQUEST.load(deps.storage)?;
+    COVER_REQUEST.remove(deps.storage);
+
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, coin_denom.as_str())?
+        .amount;
+    let pending = PENDING_COVER.may_load(deps.storage)?.unwrap_or_default();
+    let paid = balance.saturating_sub(balance_before).min(pending);
+
+    let mut res = settle_cover(deps.branch(), paid)?;
+    if !paid.is_zero() {
+        let covered = INSURANCE_COVERED
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        INSURANCE_COVERED.save(deps.storage, &(covered + paid))?;
+
+        // the cover is delegated to the validator with the least stake of the hub
+        if let Some(validator) = least_delegated_validator(deps.as_ref(), &env, &coin_denom)? {
+            res = res.add_message(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator,
+                amount: coin(paid.u128(), coin_denom),
+            }));
+        }
+    }
+    Ok(res)
+}
+
+/// Clear the pending cover, the part the fund did not pay leaves the bonded amount and
+/// lowers the exchange rate, which may trip the circuit breaker
+fn settle_cover(deps: DepsMut, paid: Uint128) -> Result<Response, ContractError> {
+    let pending = PENDING_COVER.may_load(deps.storage)?.unwrap_or_default();
+    PENDING_COVER.remove(deps.storage);
+    let unpaid = pending.checked_sub(paid)?;
+
+    let mut res = Response::new().add_attributes(vec![
+        attr("action", "settle_cover"),
+        attr("paid", paid),
+        attr("unpaid", unpaid),
+    ]);
+    if !unpaid.is_zero() {
+        let total_issued = query_total_issued(deps.as_ref())?;
+        let current_requested_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+        let mut state = STATE.load(deps.storage)?;
+        let previous_rate = state.exchange_rate;
+        state.total_bond_amount = state.total_bond_amount.checked_sub(unpaid)?;
+        state.update_exchange_rate(total_issued, current_requested_fee);
+        STATE.save(deps.storage, &state)?;
+
+        if let Some(alert) = trip_circuit_breaker(deps.storage, previous_rate, state.exchange_rate)?
+        {
+            res = res.add_event(alert);
+        }
+    }
+    Ok(res)
+}
+
+pub fn query_insurance(deps: Deps) -> StdResult<InsuranceResponse> {
+    let insurance = match INSURANCE.may_load(deps.storage)? {
+        Some((contract, max_cover)) => Some(Insurance {
+            contract: deps.api.addr_humanize(&contract)?.to_string(),
+            max_cover,
+        }),
+        None => None,
+    };
+    Ok(InsuranceResponse {
+        insurance,
+        pending_cover: PENDING_COVER.may_load(deps.storage)?.unwrap_or_default(),
+        covered: INSURANCE_COVERED
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+    })
+}
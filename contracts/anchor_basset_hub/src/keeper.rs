@@ -0,0 +1,393 @@
+use crate::config::{active_validators, remove_exited_validators};
+use crate::contract::{airdrop_hook_msgs, execute_slashing, execute_update_global};
+use crate::state::{
+    is_operator, read_hub_mode, read_operators, read_validators, CONFIG, KEEPER_BOUNTIES,
+    KEEPER_INCENTIVE, KEEPER_PAID_HEIGHTS, LAST_REBALANCE, OPERATOR, OPERATORS, PARAMETERS,
+    RAMP_RECEIVED, RAMP_UP, REBALANCE_CHECKPOINTS, REWARDS_TOPPED_UP,
+};
+use basset::contract_error::ContractError;
+use basset::hub::{
+    HubMode, KeeperIncentiveResponse, Op, OperationKind, OperatorsResponse, RampAllowance,
+    RampAllowancesResponse, ValidatorDelegation, ValidatorDelegationsResponse,
+};
+use basset::reward::ExecuteMsg as RewardExecuteMsg;
+use cosmwasm_std::{
+    attr, coin, to_binary, Deps, DepsMut, Env, MessageInfo, Response, StakingMsg, StdError,
+    StdResult, Uint128, WasmMsg,
+};
+
+/// Run the keeper operations in order, the responses are merged into one.
+/// Only the owner, the operator and the operators of each kind are allowed to execute
+pub fn execute_run_operations(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ops: Vec<Op>,
+) -> Result<Response, ContractError> {
+    if ops.is_empty() {
+        return Err(StdError::generic_err("No operations are provided").into());
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    let mut response = Response::new().add_attributes(vec![
+        attr("action", "run_operations"),
+        attr("operator", info.sender),
+    ]);
+    for op in ops {
+        let kind = op.kind();
+        if !is_operator(deps.storage, kind, &sender_raw)? {
+            OPERATOR.assert_allowed(deps.storage, &config.creator, &sender_raw)?;
+        }
+
+        let res = match op {
+            Op::UpdateGlobalIndex {} => execute_update_global(deps.branch(), env.clone(), None)?,
+            Op::CheckSlashing {} => execute_slashing(deps.branch(), env.clone())?,
+            Op::Rebalance {} => rebalance(deps.branch(), &env)?,
+            Op::RemoveExitedValidators {} => remove_exited_validators(deps.branch(), &env)?,
+            Op::ClaimAirdrops { airdrop_hooks } => Response::new()
+                .add_submessages(airdrop_hook_msgs(deps.as_ref(), &config, airdrop_hooks)?)
+                .add_attributes(vec![attr("action", "claim_airdrops")]),
+        };
+        response.messages.extend(res.messages);
+        response.attributes.extend(res.attributes);
+        response.events.extend(res.events);
+    }
+
+    Ok(response)
+}
+
+/// The epoch of the block, the ramp up allowances are renewed every epoch
+fn ramp_epoch(deps: Deps, env: &Env) -> StdResult<u64> {
+    let epoch_period = PARAMETERS.load(deps.storage)?.epoch_period.max(1);
+    Ok(env.block.time.seconds() / epoch_period)
+}
+
+/// The stake validator may still receive in the current epoch, none without a ramp up cap
+fn ramp_allowance(deps: Deps, epoch: u64, validator: &str) -> StdResult<Option<Uint128>> {
+    let max_growth = match RAMP_UP.may_load(deps.storage)? {
+        Some(max_growth) => max_growth,
+        None => return Ok(None),
+    };
+    Ok(Some(
+        max_growth.saturating_sub(received_in(deps, epoch, validator)?),
+    ))
+}
+
+fn received_in(deps: Deps, epoch: u64, validator: &str) -> StdResult<Uint128> {
+    Ok(match RAMP_RECEIVED.may_load(deps.storage, validator)? {
+        Some((received_epoch, received)) if received_epoch == epoch => received,
+        _ => Uint128::zero(),
+    })
+}
+
+/// Redelegate from the validators holding more than their share to the ones holding less,
+/// until every whitelisted validator holds the same amount. With a ramp up, each validator
+/// receives at most the ramp up per epoch and the rest waits for the next epochs
+fn rebalance(deps: DepsMut, env: &Env) -> Result<Response, ContractError> {
+    if read_hub_mode(deps.storage)? == HubMode::External {
+        return Err(StdError::generic_err("The hub has no delegations to rebalance").into());
+    }
+
+    let denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let delegations = deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?;
+    let validators = read_validators(deps.storage)?;
+    let mut amounts: Vec<(String, Uint128)> = validators
+        .into_iter()
+        .map(|validator| {
+            let amount = delegations
+                .iter()
+                .filter(|delegation| delegation.validator == validator)
+                .map(|delegation| delegation.amount.amount)
+                .sum();
+            (validator, amount)
+        })
+        .collect();
+    if amounts.is_empty() {
+        return Err(StdError::generic_err("No validators are whitelisted").into());
+    }
+
+    let total: Uint128 = amounts.iter().map(|(_, amount)| *amount).sum();
+    let count = amounts.len() as u128;
+    let share = total.u128() / count;
+    // the first validators keep the remainder of the division
+    let remainder = (total.u128() % count) as usize;
+    let targets: Vec<Uint128> = (0..amounts.len())
+        .map(|index| Uint128::new(share + u128::from(index < remainder)))
+        .collect();
+
+    let epoch = ramp_epoch(deps.as_ref(), env)?;
+    // the validators out of the active set receive nothing
+    let active = active_validators(deps.as_ref())?;
+    let mut allowances = amounts
+        .iter()
+        .map(|(validator, _)| match active.contains(validator) {
+            true => ramp_allowance(deps.as_ref(), epoch, validator),
+            false => Ok(Some(Uint128::zero())),
+        })
+        .collect::<StdResult<Vec<Option<Uint128>>>>()?;
+    let room = |index: usize, amounts: &[(String, Uint128)], allowances: &[Option<Uint128>]| {
+        let deficit = targets[index].saturating_sub(amounts[index].1);
+        allowances[index].map_or(deficit, |allowance| deficit.min(allowance))
+    };
+
+    let mut messages = vec![];
+    let mut receiver = 0;
+    for donor in 0..amounts.len() {
+        while amounts[donor].1 > targets[donor] {
+            while receiver < amounts.len() && room(receiver, &amounts, &allowances).is_zero() {
+                receiver += 1;
+            }
+            if receiver == amounts.len() {
+                break;
+            }
+            let amount =
+                (amounts[donor].1 - targets[donor]).min(room(receiver, &amounts, &allowances));
+            amounts[donor].1 -= amount;
+            amounts[receiver].1 += amount;
+            if let Some(allowance) = allowances[receiver].as_mut() {
+                *allowance -= amount;
+                let received = received_in(deps.as_ref(), epoch, &amounts[receiver].0)?;
+                RAMP_RECEIVED.save(
+                    deps.storage,
+                    &amounts[receiver].0,
+                    &(epoch, received + amount),
+                )?;
+            }
+            messages.push(StakingMsg::Redelegate {
+                src_validator: amounts[donor].0.clone(),
+                dst_validator: amounts[receiver].0.clone(),
+                amount: coin(amount.u128(), &denom),
+            });
+        }
+    }
+
+    for ((validator, amount), target) in amounts.iter().zip(targets.iter()) {
+        REBALANCE_CHECKPOINTS.save(deps.storage, validator, &(*target, *amount))?;
+    }
+    LAST_REBALANCE.save(deps.storage, &env.block.time.seconds())?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "rebalance"),
+        attr("total_delegated", total),
+    ]))
+}
+
+/// Set the operator allowed to run every keeper operation.
+/// Only creator/owner and the operator are allowed to execute
+pub fn execute_update_operator(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.update(
+        deps.storage,
+        deps.api,
+        &config.creator,
+        &sender_raw,
+        operator.clone(),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_operator"),
+        attr("operator", operator.unwrap_or_default()),
+    ]))
+}
+
+/// Add and remove the operators of one kind of keeper operation.
+/// Only creator/owner and the operator are allowed to execute
+pub fn execute_update_operators(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    kind: OperationKind,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &config.creator, &sender_raw)?;
+
+    for address in add.unwrap_or_default() {
+        let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+        OPERATORS.save(
+            deps.storage,
+            (kind.as_str().as_bytes(), address_raw.as_slice()),
+            &true,
+        )?;
+    }
+    for address in remove.unwrap_or_default() {
+        let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+        OPERATORS.remove(
+            deps.storage,
+            (kind.as_str().as_bytes(), address_raw.as_slice()),
+        );
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_operators"),
+        attr("kind", kind.as_str()),
+    ]))
+}
+
+pub fn query_operators(
+    deps: Deps,
+    kind: OperationKind,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OperatorsResponse> {
+    let start_after = start_after
+        .map(|address| deps.api.addr_canonicalize(address.as_str()))
+        .transpose()?;
+    let operators = read_operators(deps.storage, kind, start_after, limit)?
+        .iter()
+        .map(|address| Ok(deps.api.addr_humanize(address)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(OperatorsResponse { kind, operators })
+}
+
+/// Whether the next index update has rewards to distribute: the delegations accumulated
+/// some, or rewards were topped up in the external mode
+pub fn has_pending_rewards(deps: Deps, env: &Env) -> StdResult<bool> {
+    if REWARDS_TOPPED_UP
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        return Ok(true);
+    }
+    for delegation in deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?
+    {
+        let full = deps
+            .querier
+            .query_delegation(env.contract.address.clone(), delegation.validator)?;
+        if full.is_some_and(|full| {
+            full.accumulated_rewards
+                .iter()
+                .any(|coin| !coin.amount.is_zero())
+        }) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Add the bounty of the caller of a permissionless maintenance handler to its response.
+/// Nothing is paid without an incentive, when the call did no work, within min_blocks of the
+/// last paid call of the same kind or once max_per_block bounties were paid in the block
+pub fn pay_keeper_bounty(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    kind: OperationKind,
+    worked: bool,
+    res: Response,
+) -> Result<Response, ContractError> {
+    let incentive = match KEEPER_INCENTIVE.may_load(deps.storage)? {
+        Some(incentive) => incentive,
+        None => return Ok(res),
+    };
+    if !worked {
+        return Ok(res);
+    }
+    if let Some(height) = KEEPER_PAID_HEIGHTS.may_load(deps.storage, kind.as_str())? {
+        if env.block.height < height + incentive.min_blocks {
+            return Ok(res);
+        }
+    }
+    let paid = match KEEPER_BOUNTIES.may_load(deps.storage)? {
+        Some((height, paid)) if height == env.block.height => paid,
+        _ => 0,
+    };
+    if paid >= incentive.max_per_block {
+        return Ok(res);
+    }
+    KEEPER_BOUNTIES.save(deps.storage, &(env.block.height, paid + 1))?;
+    KEEPER_PAID_HEIGHTS.save(deps.storage, kind.as_str(), &env.block.height)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let reward_addr = deps
+        .api
+        .addr_humanize(&config.reward_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "reward".to_string(),
+            }
+        })?)?;
+    Ok(res
+        .add_message(WasmMsg::Execute {
+            contract_addr: reward_addr.to_string(),
+            msg: to_binary(&RewardExecuteMsg::PayKeeperBounty {
+                recipient: info.sender.to_string(),
+                amount: incentive.bounty,
+            })?,
+            funds: vec![],
+        })
+        .add_attribute("keeper_bounty", incentive.bounty))
+}
+
+pub fn query_keeper_incentive(deps: Deps) -> StdResult<KeeperIncentiveResponse> {
+    Ok(KeeperIncentiveResponse {
+        incentive: KEEPER_INCENTIVE.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_ramp_allowances(deps: Deps, env: Env) -> StdResult<RampAllowancesResponse> {
+    let epoch = ramp_epoch(deps, &env)?;
+    let allowances = read_validators(deps.storage)?
+        .into_iter()
+        .map(|validator| {
+            Ok(RampAllowance {
+                received: received_in(deps, epoch, &validator)?,
+                allowance: ramp_allowance(deps, epoch, &validator)?,
+                validator,
+            })
+        })
+        .collect::<StdResult<Vec<RampAllowance>>>()?;
+
+    Ok(RampAllowancesResponse {
+        max_growth: RAMP_UP.may_load(deps.storage)?,
+        allowances,
+    })
+}
+
+/// Compare the delegations of the whitelisted validators with the checkpoints of the last
+/// rebalance
+pub fn query_validator_delegations(
+    deps: Deps,
+    env: Env,
+) -> StdResult<ValidatorDelegationsResponse> {
+    let delegations = deps.querier.query_all_delegations(env.contract.address)?;
+    let validators = read_validators(deps.storage)?
+        .into_iter()
+        .map(|validator| {
+            let delegated: Uint128 = delegations
+                .iter()
+                .filter(|delegation| delegation.validator == validator)
+                .map(|delegation| delegation.amount.amount)
+                .sum();
+            let checkpoint = REBALANCE_CHECKPOINTS.may_load(deps.storage, &validator)?;
+            let rebalanced = checkpoint.map_or_else(Uint128::zero, |(_, amount)| amount);
+            Ok(ValidatorDelegation {
+                target: checkpoint.map(|(target, _)| target),
+                delegated,
+                rebalanced: checkpoint.map(|(_, amount)| amount),
+                gained: delegated.saturating_sub(rebalanced),
+                lost: rebalanced.saturating_sub(delegated),
+                validator,
+            })
+        })
+        .collect::<StdResult<Vec<ValidatorDelegation>>>()?;
+
+    Ok(ValidatorDelegationsResponse {
+        rebalanced_at: LAST_REBALANCE.may_load(deps.storage)?,
+        validators,
+    })
+}
@@ -3,8 +3,17 @@ pub mod state;
 
 mod bond;
 mod config;
+mod gov;
+mod history;
+mod ibc;
+mod insurance;
+mod keeper;
 mod math;
+mod priority;
+mod surplus;
+mod twap;
 mod unbond;
+mod wormhole;
 
 #[cfg(test)]
 mod testing;
@@ -1,16 +1,19 @@
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Decimal, Fraction, Uint128};
 
-const DECIMAL_FRACTIONAL: Uint128 = Uint128::new(1_000_000_000u128);
-
-/// return a / b
+/// return a / b, truncated to an integer
 pub fn decimal_division(a: Uint128, b: Decimal) -> Uint128 {
-    let decimal = Decimal::from_ratio(a, b * DECIMAL_FRACTIONAL);
-    decimal * DECIMAL_FRACTIONAL
+    a.multiply_ratio(b.denominator(), b.numerator())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use basset::hub::State;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    const CASES: u64 = 256;
+    /// Upper bound of the whole Luna supply in uluna
+    const TOTAL_LUNA_SUPPLY: u64 = 1_000_000_000_000_000;
 
     #[test]
     fn test_decimal_division() {
@@ -19,4 +22,87 @@ mod tests {
         let res = decimal_division(a, b);
         assert_eq!(res, Uint128::new(500));
     }
+
+    #[test]
+    fn decimal_division_never_over_issues() {
+        for case in 0..CASES {
+            let mut rng = XorShiftRng::seed_from_u64(case);
+            let amount = Uint128::from(rng.gen_range(0, TOTAL_LUNA_SUPPLY));
+            let rate = Decimal::from_ratio(
+                rng.gen_range(1, TOTAL_LUNA_SUPPLY),
+                rng.gen_range(1, TOTAL_LUNA_SUPPLY),
+            );
+            if rate.is_zero() {
+                continue;
+            }
+
+            let minted = decimal_division(amount, rate);
+            // the minted tokens are never worth more than the bonded amount,
+            // and rounding keeps less than one token back
+            let worth = minted * rate;
+            assert!(worth <= amount, "case {}: {} / {}", case, amount, rate);
+            assert!(
+                amount - worth <= Uint128::new(1) * rate + Uint128::new(1),
+                "case {}: {} / {}",
+                case,
+                amount,
+                rate
+            );
+        }
+    }
+
+    #[test]
+    fn exchange_rate_never_decreases_without_slashing() {
+        for case in 0..CASES {
+            let mut rng = XorShiftRng::seed_from_u64(case);
+            let mut state = State {
+                exchange_rate: Decimal::one(),
+                total_bond_amount: Uint128::from(rng.gen_range(1u64, 1_000_000)),
+                ..Default::default()
+            };
+            let mut supply = Uint128::zero();
+            let mut requested = Uint128::zero();
+
+            for step in 0..64 {
+                let previous_rate = state.exchange_rate;
+                match rng.gen_range(0, 3) {
+                    // bond, as in execute_bond without peg fee
+                    0 => {
+                        let amount = Uint128::from(rng.gen_range(1, TOTAL_LUNA_SUPPLY / 100));
+                        supply += decimal_division(amount, state.exchange_rate);
+                        state.total_bond_amount += amount;
+                    }
+                    // queue an unbond, as in execute_unbond
+                    1 => {
+                        let amount = supply.multiply_ratio(rng.gen_range(0, 101u64), 100u64);
+                        supply = supply.checked_sub(amount).unwrap();
+                        requested += amount;
+                    }
+                    // undelegate the batch once the epoch passed
+                    _ => {
+                        let undelegated = requested * state.exchange_rate;
+                        state.total_bond_amount =
+                            state.total_bond_amount.checked_sub(undelegated).unwrap();
+                        requested = Uint128::zero();
+                        if supply.is_zero() {
+                            continue;
+                        }
+                    }
+                }
+                state.update_exchange_rate(supply, requested);
+                if supply.is_zero() && requested.is_zero() {
+                    // the rate restarts at one once everything is unbonded
+                    continue;
+                }
+                assert!(
+                    state.exchange_rate >= previous_rate,
+                    "case {} step {}: {} -> {}",
+                    case,
+                    step,
+                    previous_rate,
+                    state.exchange_rate
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,297 @@
+use crate::state::{
+    read_unbond_history, read_wait_list, CONFIG, MAX_WITHDRAW_REQUESTS, PARAMETERS, STATE,
+    UNBOND_BUFFER, UNBOND_BUFFER_ADVANCES, UNBOND_BUFFER_KEPT, UNBOND_BUFFER_SKIMMED,
+    UNBOND_BUFFER_SKIM_ADVANCES, UNBOND_BUFFER_TARGET, UNBOND_PRIORITY,
+};
+use basset::contract_error::ContractError;
+use basset::hub::{BufferTarget, BufferTargetResponse, UnbondPriorityResponse};
+use cosmwasm_std::{
+    attr, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Uint128,
+};
+use cw_storage_plus::U64Key;
+
+/// The requests of a withdrawal paid out of the unbond buffer ahead of their release
+pub(crate) struct PriorityWithdrawals {
+    pub batches: Vec<u64>,
+    pub amount: Uint128,
+}
+
+/// Let the requests of less than max_request be withdrawn from the unbond buffer before
+/// their batch is released. Only creator/owner is allowed to execute
+pub fn execute_update_unbond_priority(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_request: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match max_request {
+        Some(max_request) => UNBOND_PRIORITY.save(deps.storage, &max_request)?,
+        None => UNBOND_PRIORITY.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_unbond_priority")]))
+}
+
+/// Add the underlying sent to the unbond buffer. It is left out of the balance the released
+/// batches are reconciled with. Only creator/owner is allowed to execute
+pub fn execute_fund_unbond_buffer(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == coin_denom && !coin.amount.is_zero() => coin.amount,
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "Only {} is added to the unbond buffer",
+                coin_denom
+            ))
+            .into())
+        }
+    };
+    let buffer = UNBOND_BUFFER.may_load(deps.storage)?.unwrap_or_default();
+    UNBOND_BUFFER.save(deps.storage, &(buffer + amount))?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.prev_hub_balance += amount;
+        Ok(state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "fund_unbond_buffer"),
+        attr("amount", amount),
+    ]))
+}
+
+/// Set the share of the total bonded the bonds keep in the unbond buffer.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_buffer_target(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    target: Option<BufferTarget>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match target {
+        Some(target) => {
+            if target.target_rate > Decimal::one() || target.skim_rate > Decimal::one() {
+                return Err(StdError::generic_err(
+                    "The buffer target and skim rates must not exceed 100%",
+                )
+                .into());
+            }
+            UNBOND_BUFFER_TARGET.save(deps.storage, &target)?
+        }
+        None => UNBOND_BUFFER_TARGET.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_buffer_target")]))
+}
+
+/// The level the bonds refill the unbond buffer to, zero without a target
+fn buffer_target_amount(storage: &dyn Storage, total_bonded: Uint128) -> StdResult<Uint128> {
+    Ok(match UNBOND_BUFFER_TARGET.may_load(storage)? {
+        Some(target) => total_bonded * target.target_rate,
+        None => Uint128::zero(),
+    })
+}
+
+/// Skim the share of a bond the unbond buffer takes while it is below its target, the share
+/// is kept undelegated and still counted as bonded. Returns the amount skimmed
+pub(crate) fn skim_bond(
+    storage: &mut dyn Storage,
+    amount: Uint128,
+    total_bonded: Uint128,
+) -> StdResult<Uint128> {
+    let skim_rate = match UNBOND_BUFFER_TARGET.may_load(storage)? {
+        Some(target) => target.skim_rate,
+        None => return Ok(Uint128::zero()),
+    };
+    let buffer = UNBOND_BUFFER.may_load(storage)?.unwrap_or_default();
+    let shortfall = buffer_target_amount(storage, total_bonded)?.saturating_sub(buffer);
+    let skimmed = (amount * skim_rate).min(shortfall);
+    if skimmed.is_zero() {
+        return Ok(skimmed);
+    }
+
+    UNBOND_BUFFER.save(storage, &(buffer + skimmed))?;
+    let total_skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+    UNBOND_BUFFER_SKIMMED.save(storage, &(total_skimmed + skimmed))?;
+    Ok(skimmed)
+}
+
+/// The share of an undelegation the skimmed underlying pays, it is not delegated
+pub(crate) fn skimmed_share(
+    storage: &dyn Storage,
+    undelegation_amount: Uint128,
+    total_bonded: Uint128,
+) -> StdResult<Uint128> {
+    if total_bonded.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    let skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+    Ok(undelegation_amount
+        .multiply_ratio(skimmed, total_bonded)
+        .min(skimmed))
+}
+
+/// Take the skimmed share of a batch sent to undelegation out of the buffer, the batch is
+/// paid with it at its release
+pub(crate) fn keep_skimmed_share(
+    storage: &mut dyn Storage,
+    batch_id: u64,
+    kept: Uint128,
+) -> StdResult<()> {
+    if kept.is_zero() {
+        return Ok(());
+    }
+    let skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+    UNBOND_BUFFER_SKIMMED.save(storage, &skimmed.checked_sub(kept)?)?;
+    let buffer = UNBOND_BUFFER.may_load(storage)?.unwrap_or_default();
+    UNBOND_BUFFER.save(storage, &buffer.checked_sub(kept)?)?;
+    UNBOND_BUFFER_KEPT.save(storage, U64Key::new(batch_id), &kept)
+}
+
+/// The skimmed underlying kept for a batch at its undelegation, released with the batch
+pub(crate) fn take_kept_share(storage: &mut dyn Storage, batch_id: u64) -> StdResult<Uint128> {
+    let kept = UNBOND_BUFFER_KEPT
+        .may_load(storage, U64Key::new(batch_id))?
+        .unwrap_or_default();
+    UNBOND_BUFFER_KEPT.remove(storage, U64Key::new(batch_id));
+    Ok(kept)
+}
+
+/// The skimmed underlying kept for the batches not released yet, the hub already holds it
+pub(crate) fn kept_shares(storage: &dyn Storage) -> StdResult<Uint128> {
+    UNBOND_BUFFER_KEPT
+        .range(storage, None, None, Order::Ascending)
+        .try_fold(Uint128::zero(), |total, item| {
+            Ok(total.checked_add(item?.1)?)
+        })
+}
+
+/// The bonded underlying of the buffer, what it holds and what it paid ahead to the
+/// unbonding batches. It is counted as bonded by the slashing check
+pub(crate) fn skimmed_bonded(storage: &dyn Storage) -> StdResult<Uint128> {
+    let skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+    UNBOND_BUFFER_SKIM_ADVANCES
+        .range(storage, None, None, Order::Ascending)
+        .try_fold(skimmed, |total, item| Ok(total.checked_add(item?.1)?))
+}
+
+/// Pay the small requests of sender in the batches still unbonding out of the buffer, in
+/// the order of the batches and while the buffer covers them. A request is paid at the
+/// withdraw rate applied to its batch, the buffer bears the slashing of its batch.
+/// The skimmed underlying is paid out last, it is lent to the batch until its release
+pub(crate) fn take_priority_withdrawals(
+    storage: &mut dyn Storage,
+    sender: &str,
+) -> StdResult<PriorityWithdrawals> {
+    let mut withdrawals = PriorityWithdrawals {
+        batches: vec![],
+        amount: Uint128::zero(),
+    };
+    let max_request = match UNBOND_PRIORITY.may_load(storage)? {
+        Some(max_request) => max_request,
+        None => return Ok(withdrawals),
+    };
+    let mut buffer = UNBOND_BUFFER.may_load(storage)?.unwrap_or_default();
+    let mut skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+
+    for (batch_id, requested) in
+        read_wait_list(storage, sender.to_string(), None, MAX_WITHDRAW_REQUESTS)?
+    {
+        let history = match read_unbond_history(storage, batch_id) {
+            Ok(history) if !history.released => history,
+            _ => continue,
+        };
+        let amount = requested * history.withdraw_rate;
+        if amount >= max_request || amount > buffer || amount.is_zero() {
+            continue;
+        }
+        let lent = amount.saturating_sub(buffer.saturating_sub(skimmed));
+        buffer -= amount;
+        if !lent.is_zero() {
+            skimmed -= lent;
+            UNBOND_BUFFER_SKIM_ADVANCES.update(
+                storage,
+                U64Key::new(batch_id),
+                |previous| -> StdResult<Uint128> { Ok(previous.unwrap_or_default() + lent) },
+            )?;
+        }
+        UNBOND_BUFFER_ADVANCES.update(
+            storage,
+            U64Key::new(batch_id),
+            |advanced| -> StdResult<Uint128> { Ok(advanced.unwrap_or_default() + requested) },
+        )?;
+        withdrawals.batches.push(batch_id);
+        withdrawals.amount += amount;
+    }
+    UNBOND_BUFFER.save(storage, &buffer)?;
+    UNBOND_BUFFER_SKIMMED.save(storage, &skimmed)?;
+
+    Ok(withdrawals)
+}
+
+/// Refill the buffer with the share of a released batch it paid ahead, the share is not
+/// owed to the requests anymore. The skimmed underlying lent to the batch is skimmed again
+/// first, a slashed batch pays the rest less. Returns the amount refilled
+pub(crate) fn refill_unbond_buffer(
+    storage: &mut dyn Storage,
+    batch_id: u64,
+    released: Uint128,
+    requested: Uint128,
+) -> StdResult<Uint128> {
+    let advanced = match UNBOND_BUFFER_ADVANCES.may_load(storage, U64Key::new(batch_id))? {
+        Some(advanced) => advanced,
+        None => return Ok(Uint128::zero()),
+    };
+    UNBOND_BUFFER_ADVANCES.remove(storage, U64Key::new(batch_id));
+    let refilled = released.multiply_ratio(advanced, requested);
+    let buffer = UNBOND_BUFFER.may_load(storage)?.unwrap_or_default();
+    UNBOND_BUFFER.save(storage, &(buffer + refilled))?;
+
+    if let Some(lent) = UNBOND_BUFFER_SKIM_ADVANCES.may_load(storage, U64Key::new(batch_id))? {
+        UNBOND_BUFFER_SKIM_ADVANCES.remove(storage, U64Key::new(batch_id));
+        let skimmed = UNBOND_BUFFER_SKIMMED.may_load(storage)?.unwrap_or_default();
+        UNBOND_BUFFER_SKIMMED.save(storage, &(skimmed + lent.min(refilled)))?;
+    }
+    Ok(refilled)
+}
+
+pub fn query_unbond_priority(deps: Deps) -> StdResult<UnbondPriorityResponse> {
+    Ok(UnbondPriorityResponse {
+        max_request: UNBOND_PRIORITY.may_load(deps.storage)?,
+        buffer: UNBOND_BUFFER.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+pub fn query_buffer_target(deps: Deps) -> StdResult<BufferTargetResponse> {
+    let total_bonded = STATE.load(deps.storage)?.total_bond_amount;
+    Ok(BufferTargetResponse {
+        target: UNBOND_BUFFER_TARGET.may_load(deps.storage)?,
+        buffer: UNBOND_BUFFER.may_load(deps.storage)?.unwrap_or_default(),
+        target_amount: buffer_target_amount(deps.storage, total_bonded)?,
+        skimmed: UNBOND_BUFFER_SKIMMED
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+    })
+}
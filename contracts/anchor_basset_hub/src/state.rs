@@ -2,12 +2,22 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{
-    from_slice, to_vec, Addr, Decimal, Order, StdError, StdResult, Storage, Uint128,
+    from_slice, to_vec, Addr, CanonicalAddr, Decimal, Order, StdError, StdResult, Storage, Uint128,
 };
 use cosmwasm_storage::{Bucket, PrefixedStorage, ReadonlyBucket, ReadonlyPrefixedStorage};
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map, U64Key};
 
-use basset::hub::{Config, State, UnbondHistory, UnbondRequest};
+use basset::gov::{Tally, VoteOption};
+use basset::hub::{
+    AccessMode, BufferTarget, CircuitBreaker, Config, ConfigVersion, HubMode, KeeperIncentive,
+    OperationKind, Parameters, State, SurplusPolicy, TimelockedChange, UnbondBatchStatus,
+    UnbondHistory, UnbondRequest, UserEvent,
+};
+use basset::lock::ExecutionLock;
+use basset::operator::Operator;
+use basset::oracle::PriceGuard;
+use basset::pagination::{calc_limit_up_to, calc_range_start};
+use basset::timelock::Timelock;
 
 pub type LastBatch = u64;
 
@@ -16,16 +26,6 @@ pub static PREFIX_AIRDROP_INFO: &[u8] = b"airedrop_info";
 pub static UNBOND_HISTORY_MAP: &[u8] = b"history_map";
 pub static VALIDATORS: &[u8] = b"validators";
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct Parameters {
-    pub epoch_period: u64,
-    pub underlying_coin_denom: String,
-    pub unbonding_period: u64,
-    pub peg_recovery_fee: Decimal,
-    pub er_threshold: Decimal,
-    pub reward_denom: String,
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CurrentBatch {
     pub id: u64,
@@ -37,6 +37,205 @@ pub const PARAMETERS: Item<Parameters> = Item::new("\u{0}\u{b}parameteres");
 pub const CURRENT_BATCH: Item<CurrentBatch> = Item::new("\u{0}\u{d}current_batch");
 pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
 
+pub const CIRCUIT_BREAKER: Item<CircuitBreaker> = Item::new("circuit_breaker");
+/// Read raw by the reward contract to check its swaps
+pub const PRICE_GUARD: Item<PriceGuard> = Item::new("price_guard");
+/// The cw20-ics20 bridge the bAsset is sent to other chains through
+pub const IBC_BRIDGE: Item<CanonicalAddr> = Item::new("ibc_bridge");
+/// The wormhole core contract the unbond receipts are published to
+pub const WORMHOLE: Item<CanonicalAddr> = Item::new("wormhole");
+/// The number of published receipts, it is not reset when the core contract changes
+pub const WORMHOLE_RECEIPTS: Item<u64> = Item::new("wormhole_receipts");
+/// The keeper bounty, unset when no bounty is paid
+pub const KEEPER_INCENTIVE: Item<KeeperIncentive> = Item::new("keeper_incentive");
+/// The height of the last paid keeper bounty and the bounties paid at that height
+pub const KEEPER_BOUNTIES: Item<(u64, u32)> = Item::new("keeper_bounties");
+/// The height of the last paid keeper bounty, per operation kind
+pub const KEEPER_PAID_HEIGHTS: Map<&str, u64> = Map::new("keeper_paid_heights");
+/// Whether rewards were topped up since the last index update, in the external mode
+pub const REWARDS_TOPPED_UP: Item<bool> = Item::new("rewards_topped_up");
+/// The undelegate messages a bond may send to close a lapsed epoch, unset when bonds
+/// leave the epoch to the next unbond
+pub const EPOCH_AUTO_ADVANCE: Item<u32> = Item::new("epoch_auto_advance");
+/// The smallest bond of the underlying accepted, unset when any bond minting bAsset is
+pub const MIN_BOND_AMOUNT: Item<Uint128> = Item::new("min_bond_amount");
+/// Whether the unbonds claim the rewards of their user, unset when they do not
+pub const AUTO_CLAIM_REWARDS: Item<bool> = Item::new("auto_claim_rewards");
+/// Whether the chain removed the tax, unset while it charges it. Read raw by the reward contract
+pub const TAX_FREE: Item<bool> = Item::new("tax_free");
+/// The stake a rebalance may redelegate to each validator per epoch, unset without a cap
+pub const RAMP_UP: Item<Uint128> = Item::new("ramp_up");
+/// The epoch of the last rebalance to each validator and the stake it received in that epoch
+pub const RAMP_RECEIVED: Map<&str, (u64, Uint128)> = Map::new("ramp_received");
+/// The target and the delegation of each validator at the last rebalance
+pub const REBALANCE_CHECKPOINTS: Map<&str, (Uint128, Uint128)> = Map::new("rebalance_checkpoints");
+/// The time of the last rebalance
+pub const LAST_REBALANCE: Item<u64> = Item::new("last_rebalance");
+/// Requests of less of the underlying are withdrawn from the unbond buffer ahead of their
+/// release, unset without priority
+pub const UNBOND_PRIORITY: Item<Uint128> = Item::new("unbond_priority");
+/// The underlying the small requests are paid ahead from
+pub const UNBOND_BUFFER: Item<Uint128> = Item::new("unbond_buffer");
+/// The share of the total bonded the bonds keep in the unbond buffer, unset without a target
+pub const UNBOND_BUFFER_TARGET: Item<BufferTarget> = Item::new("unbond_buffer_target");
+/// The bonded underlying left undelegated in the unbond buffer
+pub const UNBOND_BUFFER_SKIMMED: Item<Uint128> = Item::new("unbond_buffer_skimmed");
+/// The requests of each unbonding batch paid ahead, in bAsset
+pub const UNBOND_BUFFER_ADVANCES: Map<U64Key, Uint128> = Map::new("unbond_buffer_advances");
+/// The skimmed underlying paid ahead to the requests of each unbonding batch, it is skimmed
+/// again out of the release of the batch
+pub const UNBOND_BUFFER_SKIM_ADVANCES: Map<U64Key, Uint128> =
+    Map::new("unbond_buffer_skim_advances");
+/// The skimmed underlying each unbonding batch is paid with instead of undelegating it,
+/// it counts as released with the batch
+pub const UNBOND_BUFFER_KEPT: Map<U64Key, Uint128> = Map::new("unbond_buffer_kept");
+/// The insurance fund asked to cover the slashing and the most it covers of one slashing
+pub const INSURANCE: Item<(CanonicalAddr, Uint128)> = Item::new("insurance");
+/// The slashing kept in total_bond_amount until the insurance fund replies
+pub const PENDING_COVER: Item<Uint128> = Item::new("pending_cover");
+/// The balance of the underlying before the cover request in flight
+pub const COVER_REQUEST: Item<Uint128> = Item::new("cover_request");
+/// The lifetime cover paid by the insurance fund
+pub const INSURANCE_COVERED: Item<Uint128> = Item::new("insurance_covered");
+/// What ReconcileSurplus does with the surplus, unset for the default policy
+pub const SURPLUS_POLICY: Item<SurplusPolicy> = Item::new("surplus_policy");
+/// The lifetime peg recovery fees of bonds and unbonds, in bAsset
+pub const PEG_RECOVERY_FEES: Item<Uint128> = Item::new("peg_recovery_fees");
+/// The exchange rate accumulated over time at every checkpoint, for the twap
+pub const RATE_OBSERVATIONS: Map<U64Key, Decimal> = Map::new("rate_observations");
+/// The lifetime unbond fees, in bAsset
+pub const UNBOND_FEES: Item<Uint128> = Item::new("unbond_fees");
+
+pub const ACCESS_MODE: Item<AccessMode> = Item::new("access_mode");
+/// Allowed or denied addresses depending on the access mode
+pub const ACCESS_LIST: Map<&[u8], bool> = Map::new("access_list");
+
+/// Runs the routine operations next to the owner, the operator of every kind
+pub const OPERATOR: Operator = Operator::new("operator");
+/// Held by an unbond until the token contract burnt the bAsset
+pub const EXECUTION_LOCK: ExecutionLock = ExecutionLock::new("execution_lock");
+/// The operators of the keeper operations, keyed by operation kind and canonical address
+pub const OPERATORS: Map<(&[u8], &[u8]), bool> = Map::new("operators");
+
+/// The underlying withdrawn from each released batch, keyed by batch id.
+/// Withdrawals before the claims were recorded are missing
+pub const BATCH_CLAIMED: Map<U64Key, Uint128> = Map::new("batch_claimed");
+/// The phase of each unbond batch, keyed by batch id. Batches of hubs instantiated before
+/// the phases were recorded have none until their next transition
+pub const BATCH_STATUS: Map<U64Key, UnbondBatchStatus> = Map::new("batch_status");
+/// The released underlying not withdrawn yet, recorded like the batch claims
+pub const UNCLAIMED_UNBONDED: Item<Uint128> = Item::new("unclaimed_unbonded");
+
+/// The number of bonds so far, the id of the next one
+pub const BOND_COUNT: Item<u64> = Item::new("bond_count");
+/// The bond, unbond and withdraw events of each address, keyed by canonical address and id
+pub const USER_HISTORY: Map<(&[u8], U64Key), UserEvent> = Map::new("user_history");
+/// The number of events recorded for each address, the id of the next one
+pub const USER_HISTORY_COUNT: Map<&[u8], u64> = Map::new("user_history_count");
+/// The replaced config and parameter sets, keyed by version
+pub const CONFIG_HISTORY: Map<U64Key, ConfigVersion> = Map::new("config_history");
+/// The version of the live config and parameters, the number of replaced ones
+pub const CONFIG_VERSION: Item<u64> = Item::new("config_version");
+
+/// Sensitive changes waiting for the timelock period to pass
+pub const TIMELOCK: Timelock<TimelockedChange> =
+    Timelock::new("timelock_period", "pending_change_id", "pending_changes");
+
+/// Hubs instantiated before the modes existed stake
+pub const HUB_MODE: Item<HubMode> = Item::new("hub_mode");
+
+/// Gov proposals open to the signals of the holders, keyed by proposal id
+pub const PROPOSALS: Map<U64Key, Proposal> = Map::new("proposals");
+/// The signalled votes, keyed by proposal id and canonical holder address
+pub const SIGNALLED_VOTES: Map<(U64Key, &[u8]), SignalledVote> = Map::new("signalled_votes");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub snapshot_height: u64,
+    pub end_time: u64,
+    pub tally: Tally,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignalledVote {
+    pub option: VoteOption,
+    /// The bAsset balance of the holder at the snapshot height
+    pub weight: Uint128,
+}
+
+pub fn read_hub_mode(storage: &dyn Storage) -> StdResult<HubMode> {
+    Ok(HUB_MODE.may_load(storage)?.unwrap_or_default())
+}
+
+/// The breaker is disabled until the owner configures it
+pub fn read_circuit_breaker(storage: &dyn Storage) -> StdResult<CircuitBreaker> {
+    Ok(CIRCUIT_BREAKER.may_load(storage)?.unwrap_or_default())
+}
+
+/// Anyone can bond until the owner restricts the access
+pub fn read_access_mode(storage: &dyn Storage) -> StdResult<AccessMode> {
+    Ok(ACCESS_MODE.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn is_access_listed(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<bool> {
+    Ok(ACCESS_LIST.may_load(storage, address.as_slice())?.is_some())
+}
+
+pub fn read_access_list(
+    storage: &dyn Storage,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let limit = calc_limit_up_to(limit, MAX_LIMIT);
+    let start = calc_range_start(start_after);
+
+    ACCESS_LIST
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| Ok(CanonicalAddr::from(key)))
+        .collect()
+}
+
+pub fn is_operator(
+    storage: &dyn Storage,
+    kind: OperationKind,
+    address: &CanonicalAddr,
+) -> StdResult<bool> {
+    Ok(OPERATORS
+        .may_load(storage, (kind.as_str().as_bytes(), address.as_slice()))?
+        .is_some())
+}
+
+pub fn read_operators(
+    storage: &dyn Storage,
+    kind: OperationKind,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let limit = calc_limit_up_to(limit, MAX_LIMIT);
+    let start = calc_range_start(start_after);
+
+    OPERATORS
+        .prefix(kind.as_str().as_bytes())
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| Ok(CanonicalAddr::from(key)))
+        .collect()
+}
+
+/// Add fee to the lifetime counter of fees
+pub fn add_collected_fee(
+    storage: &mut dyn Storage,
+    fees: &Item<Uint128>,
+    fee: Uint128,
+) -> StdResult<()> {
+    if fee.is_zero() {
+        return Ok(());
+    }
+    let collected = fees.may_load(storage)?.unwrap_or_default();
+    fees.save(storage, &collected.checked_add(fee)?)
+}
+
 /// Store undelegation wait list per each batch
 /// HashMap<user's address, <batch_id, requested_amount>
 pub fn store_unbond_wait_list(
@@ -84,92 +283,104 @@ pub fn read_unbond_wait_list(
     res.load(&batch)
 }
 
-pub fn get_unbond_requests(storage: &dyn Storage, sender_addr: String) -> StdResult<UnbondRequest> {
+/// Return a page of the user's unbond requests, ordered by the encoded batch id
+pub fn get_unbond_requests(
+    storage: &dyn Storage,
+    sender_addr: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UnbondRequest> {
+    let lim = calc_limit_up_to(limit, MAX_LIMIT);
+    read_wait_list(storage, sender_addr, start_after, lim)
+}
+
+pub(crate) fn read_wait_list(
+    storage: &dyn Storage,
+    sender_addr: String,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<UnbondRequest> {
     let vec = to_vec(&sender_addr)?;
-    let mut requests: UnbondRequest = vec![];
+    let start = start_after
+        .map(|batch_id| -> StdResult<Vec<u8>> {
+            let mut key = to_vec(&batch_id)?;
+            key.push(0);
+            Ok(key)
+        })
+        .transpose()?;
 
     let res: ReadonlyBucket<Uint128> =
         ReadonlyBucket::multilevel(storage, &[PREFIX_WAIT_MAP, &vec]);
-    let _un: Vec<_> = res
-        .range(None, None, Order::Ascending)
+    res.range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
         .map(|item| {
-            let (k, value) = item.unwrap();
-            let user_batch: u64 = from_slice(&k).unwrap();
-            requests.push((user_batch, value))
+            let (k, value) = item?;
+            let user_batch: u64 = from_slice(&k)?;
+            Ok((user_batch, value))
         })
-        .collect();
-    Ok(requests)
+        .collect()
 }
 
-pub fn get_unbond_batches(storage: &dyn Storage, sender_addr: String) -> StdResult<Vec<u64>> {
-    let vec = to_vec(&sender_addr)?;
-    let mut deprecated_batches: Vec<u64> = vec![];
-    let res: ReadonlyBucket<Uint128> =
-        ReadonlyBucket::multilevel(storage, &[PREFIX_WAIT_MAP, &vec]);
-    let _un: Vec<_> = res
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (k, _) = item.unwrap();
-            let user_batch: u64 = from_slice(&k).unwrap();
-            let history = read_unbond_history(storage, user_batch);
-            if let Ok(h) = history {
-                if h.released {
-                    deprecated_batches.push(user_batch);
-                }
-            }
-        })
-        .collect();
-    Ok(deprecated_batches)
+/// Requests of a user settled by a single withdrawal, the remaining ones are settled by the next.
+/// It must stay above the number of batches an unbonding period spans, which are not released yet.
+pub const MAX_WITHDRAW_REQUESTS: usize = 30;
+
+/// Released requests among the first `MAX_WITHDRAW_REQUESTS` requests of a user
+pub struct FinishedRequests {
+    pub batches: Vec<u64>,
+    /// The requested amounts proportional to the withdraw rates
+    pub amount: Uint128,
+    /// The requested bAsset and the withdrawable amount of every batch
+    pub claims: Vec<(u64, Uint128, Uint128)>,
+    /// Whether the user has requests beyond the ones read
+    pub more: bool,
 }
 
-/// Return all requested unbond amount.
+/// Return the released requests and their withdrawable amount.
 /// This needs to be called after process withdraw rate function.
-/// If the batch is released, this will return user's requested
-/// amount proportional to withdraw rate.
-pub fn get_finished_amount(storage: &dyn Storage, sender_addr: String) -> StdResult<Uint128> {
-    let vec = to_vec(&sender_addr)?;
-    let mut withdrawable_amount: Uint128 = Uint128::zero();
-    let res: ReadonlyBucket<Uint128> =
-        ReadonlyBucket::multilevel(storage, &[PREFIX_WAIT_MAP, &vec]);
-    let _un: Vec<_> = res
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (k, v) = item.unwrap();
-            let user_batch: u64 = from_slice(&k).unwrap();
-            let history = read_unbond_history(storage, user_batch);
-            if let Ok(h) = history {
-                if h.released {
-                    withdrawable_amount += v * h.withdraw_rate;
-                }
+pub fn get_finished_requests(
+    storage: &dyn Storage,
+    sender_addr: String,
+) -> StdResult<FinishedRequests> {
+    let mut requests = read_wait_list(storage, sender_addr, None, MAX_WITHDRAW_REQUESTS + 1)?;
+    let more = requests.len() > MAX_WITHDRAW_REQUESTS;
+    requests.truncate(MAX_WITHDRAW_REQUESTS);
+
+    let mut finished = FinishedRequests {
+        batches: vec![],
+        amount: Uint128::zero(),
+        claims: vec![],
+        more,
+    };
+    for (batch_id, amount) in requests {
+        if let Ok(h) = read_unbond_history(storage, batch_id) {
+            if h.released {
+                finished.batches.push(batch_id);
+                finished.amount += amount * h.withdraw_rate;
+                finished
+                    .claims
+                    .push((batch_id, amount, amount * h.withdraw_rate));
             }
-        })
-        .collect();
-    Ok(withdrawable_amount)
+        }
+    }
+    Ok(finished)
 }
 
-/// Return the finished amount for all batches that has been before the given block time.
+/// Return the finished amount for the batches that has been before the given block time,
+/// among the requests the next withdrawal settles.
 pub fn query_get_finished_amount(
     storage: &dyn Storage,
     sender_addr: String,
     block_time: u64,
 ) -> StdResult<Uint128> {
-    let vec = to_vec(&sender_addr)?;
     let mut withdrawable_amount: Uint128 = Uint128::zero();
-    let res: ReadonlyBucket<Uint128> =
-        ReadonlyBucket::multilevel(storage, &[PREFIX_WAIT_MAP, &vec]);
-    let _un: Vec<_> = res
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (k, v) = item.unwrap();
-            let user_batch: u64 = from_slice(&k).unwrap();
-            let history = read_unbond_history(storage, user_batch);
-            if let Ok(h) = history {
-                if h.time < block_time {
-                    withdrawable_amount += v * h.withdraw_rate;
-                }
+    for (batch_id, amount) in read_wait_list(storage, sender_addr, None, MAX_WITHDRAW_REQUESTS)? {
+        if let Ok(h) = read_unbond_history(storage, batch_id) {
+            if h.time < block_time {
+                withdrawable_amount += amount * h.withdraw_rate;
             }
-        })
-        .collect();
+        }
+    }
     Ok(withdrawable_amount)
 }
 
@@ -197,15 +408,9 @@ pub fn remove_white_validators(
 // Returns all validators
 pub fn read_validators(storage: &dyn Storage) -> StdResult<Vec<String>> {
     let res = ReadonlyPrefixedStorage::new(storage, VALIDATORS);
-    let validators: Vec<String> = res
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (key, _) = item;
-            let sender: String = from_slice(&key).unwrap();
-            sender
-        })
-        .collect();
-    Ok(validators)
+    res.range(None, None, Order::Ascending)
+        .map(|(key, _)| from_slice(&key))
+        .collect()
 }
 
 /// Check whether the validator is whitelisted.
@@ -221,15 +426,9 @@ pub fn is_valid_validator(storage: &dyn Storage, validator_address: String) -> S
 /// Read whitelisted validators
 pub fn read_valid_validators(storage: &dyn Storage) -> StdResult<Vec<String>> {
     let res = ReadonlyPrefixedStorage::new(storage, VALIDATORS);
-    let validators: Vec<String> = res
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (key, _) = item;
-            let sender: String = from_slice(&key).unwrap();
-            sender
-        })
-        .collect();
-    Ok(validators)
+    res.range(None, None, Order::Ascending)
+        .map(|(key, _)| from_slice(&key))
+        .collect()
 }
 
 /// Store unbond history map
@@ -256,9 +455,8 @@ pub fn read_unbond_history(storage: &dyn Storage, epoc_id: u64) -> StdResult<Unb
     }
 }
 
-// settings for pagination
+// the unbond history is paged further than the other queries
 const MAX_LIMIT: u32 = 100;
-const DEFAULT_LIMIT: u32 = 10;
 
 /// Return all unbond_history from UnbondHistory map
 #[allow(clippy::needless_lifetimes)]
@@ -269,14 +467,11 @@ pub fn all_unbond_history(
 ) -> StdResult<Vec<UnbondHistory>> {
     let vec = convert(start);
 
-    let lim = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let lim = calc_limit_up_to(limit, MAX_LIMIT);
     let res = ReadonlyPrefixedStorage::new(storage, UNBOND_HISTORY_MAP)
         .range(vec.as_deref(), None, Order::Ascending)
         .take(lim)
-        .map(|item| {
-            let history: UnbondHistory = from_slice(&item.1).unwrap();
-            Ok(history)
-        })
+        .map(|item| from_slice(&item.1))
         .collect();
     res
 }
@@ -294,7 +489,7 @@ mod test {
     use super::*;
 
     use cosmwasm_std::testing::mock_dependencies;
-    use cosmwasm_std::{Api, StdResult, Storage};
+    use cosmwasm_std::{Api, Decimal, StdResult, Storage};
     use cosmwasm_storage::{singleton, singleton_read};
 
     pub static STATE_KEY: &[u8] = b"state";
@@ -403,6 +598,7 @@ mod test {
                 peg_recovery_fee: Default::default(),
                 er_threshold: Default::default(),
                 reward_denom: "uusd".to_string(),
+                unbond_fee_bps: 0,
             },
         )
         .unwrap();
@@ -412,4 +608,53 @@ mod test {
             read_params(&deps.storage).unwrap()
         );
     }
+
+    #[test]
+    fn withdrawal_settles_bounded_requests() {
+        let mut deps = mock_dependencies(&[]);
+        let user = "addr0000".to_string();
+        let batches = MAX_WITHDRAW_REQUESTS as u64 + 5;
+        for batch_id in 1..=batches {
+            store_unbond_wait_list(&mut deps.storage, batch_id, user.clone(), Uint128::new(10))
+                .unwrap();
+            let history = UnbondHistory {
+                batch_id,
+                time: batch_id,
+                amount: Uint128::new(10),
+                applied_exchange_rate: Decimal::one(),
+                withdraw_rate: Decimal::one(),
+                // the latest batch is still unbonding
+                released: batch_id < batches,
+            };
+            store_unbond_history(&mut deps.storage, batch_id, history).unwrap();
+        }
+
+        // the requests are paged for queries
+        let page = get_unbond_requests(&deps.storage, user.clone(), None, Some(20)).unwrap();
+        let rest = get_unbond_requests(
+            &deps.storage,
+            user.clone(),
+            page.last().map(|r| r.0),
+            Some(20),
+        )
+        .unwrap();
+        assert_eq!(page.len() + rest.len(), batches as usize);
+
+        let finished = get_finished_requests(&deps.storage, user.clone()).unwrap();
+        assert!(finished.more);
+        assert_eq!(
+            finished.amount,
+            Uint128::new(10 * finished.batches.len() as u128)
+        );
+        remove_unbond_wait_list(&mut deps.storage, finished.batches, Addr::unchecked(&user))
+            .unwrap();
+
+        // the next withdrawal settles the rest, the unbonding batch stays
+        let finished = get_finished_requests(&deps.storage, user.clone()).unwrap();
+        assert!(!finished.more);
+        remove_unbond_wait_list(&mut deps.storage, finished.batches, Addr::unchecked(&user))
+            .unwrap();
+        let left = get_unbond_requests(&deps.storage, user, None, None).unwrap();
+        assert_eq!(left, vec![(batches, Uint128::new(10))]);
+    }
 }
@@ -0,0 +1,164 @@
+use crate::config::least_delegated_validator;
+use crate::contract::{query_total_issued, trip_circuit_breaker};
+use crate::priority::kept_shares;
+use crate::state::{
+    read_hub_mode, read_unbond_history, CONFIG, CURRENT_BATCH, OPERATOR, PARAMETERS, STATE,
+    SURPLUS_POLICY, UNBOND_BUFFER,
+};
+use basset::contract_error::ContractError;
+use basset::hub::{HubMode, SurplusPolicy, SurplusResponse};
+use cosmwasm_std::{
+    attr, coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StakingMsg, StdError,
+    StdResult, Uint128,
+};
+
+/// Set what ReconcileSurplus does with the surplus. Only creator/owner is allowed to execute
+pub fn execute_update_surplus_policy(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    policy: SurplusPolicy,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    SURPLUS_POLICY.save(deps.storage, &policy)?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_surplus_policy"),
+        attr("policy", policy_name(policy)),
+    ]))
+}
+
+/// Account for the surplus following the surplus policy, bonding it raises the exchange rate
+/// and may trip the circuit breaker. Only creator/owner and the operator are allowed to execute
+pub fn execute_reconcile_surplus(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &config.creator, &sender_raw)?;
+
+    // the release of a matured batch is reconciled with the whole balance received
+    if let Some(batch_id) = matured_batch(deps.as_ref(), &env)? {
+        return Err(StdError::generic_err(format!(
+            "Batch {} is released, the surplus is reconciled after its next withdrawal",
+            batch_id
+        ))
+        .into());
+    }
+    let surplus = query_surplus(deps.as_ref(), env.clone())?;
+    if surplus.surplus.is_zero() {
+        return Err(StdError::generic_err("The hub holds no surplus").into());
+    }
+    let amount = surplus.surplus;
+
+    let mut res = Response::new().add_attributes(vec![
+        attr("action", "reconcile_surplus"),
+        attr("policy", policy_name(surplus.policy)),
+        attr("surplus", amount),
+    ]);
+    let mut state = STATE.load(deps.storage)?;
+    match surplus.policy {
+        SurplusPolicy::Buffer => {
+            // the buffer is left out of the balance the released batches are reconciled with
+            let buffer = UNBOND_BUFFER.may_load(deps.storage)?.unwrap_or_default();
+            UNBOND_BUFFER.save(deps.storage, &(buffer + amount))?;
+            state.prev_hub_balance += amount;
+            STATE.save(deps.storage, &state)?;
+        }
+        SurplusPolicy::Bond => {
+            if read_hub_mode(deps.storage)? == HubMode::External {
+                return Err(StdError::generic_err(
+                    "The hub has no delegations to bond the surplus to",
+                )
+                .into());
+            }
+            let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+            let validator = least_delegated_validator(deps.as_ref(), &env, &coin_denom)?
+                .ok_or_else(|| StdError::generic_err("No validators are whitelisted"))?;
+
+            let total_issued = query_total_issued(deps.as_ref())?;
+            let current_requested_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+            let previous_rate = state.exchange_rate;
+            state.total_bond_amount += amount;
+            state.update_exchange_rate(total_issued, current_requested_fee);
+            STATE.save(deps.storage, &state)?;
+
+            res = res.add_message(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator,
+                amount: coin(amount.u128(), coin_denom),
+            }));
+            if let Some(alert) =
+                trip_circuit_breaker(deps.storage, previous_rate, state.exchange_rate)?
+            {
+                res = res.add_event(alert);
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// The balance of the underlying above the one the hub tracks and the unbonds of the batches
+/// not released yet, counted at their withdraw rate. An undelegation may complete before
+/// the batch is released, so every batch in flight is counted as arriving. There is none
+/// while a matured batch waits for its release, the release takes the balance received
+pub fn query_surplus(deps: Deps, env: Env) -> StdResult<SurplusResponse> {
+    let policy = SURPLUS_POLICY.may_load(deps.storage)?.unwrap_or_default();
+    if matured_batch(deps, &env)?.is_some() {
+        return Ok(SurplusResponse {
+            policy,
+            surplus: Uint128::zero(),
+        });
+    }
+
+    let state = STATE.load(deps.storage)?;
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, coin_denom)?
+        .amount;
+
+    let mut unreleased = Uint128::zero();
+    let mut batch_id = state.last_processed_batch + 1;
+    while let Ok(history) = read_unbond_history(deps.storage, batch_id) {
+        if !history.released {
+            unreleased += history.amount * history.withdraw_rate;
+        }
+        batch_id += 1;
+    }
+    // the unbonds received since the last withdrawal are already counted as unbonded, and
+    // the skimmed share of the batches is already held
+    let arriving = unreleased
+        .saturating_sub(state.actual_unbonded_amount)
+        .saturating_sub(kept_shares(deps.storage)?);
+
+    Ok(SurplusResponse {
+        policy,
+        surplus: balance
+            .saturating_sub(state.prev_hub_balance)
+            .saturating_sub(arriving),
+    })
+}
+
+/// The first batch whose undelegation matured but that the withdrawals did not release yet
+fn matured_batch(deps: Deps, env: &Env) -> StdResult<Option<u64>> {
+    let last_processed_batch = STATE.load(deps.storage)?.last_processed_batch;
+    let unbonding_period = PARAMETERS.load(deps.storage)?.unbonding_period;
+    let historical_time = env.block.time.seconds().saturating_sub(unbonding_period);
+    Ok(read_unbond_history(deps.storage, last_processed_batch + 1)
+        .ok()
+        .filter(|history| !history.released && history.time <= historical_time)
+        .map(|history| history.batch_id))
+}
+
+fn policy_name(policy: SurplusPolicy) -> &'static str {
+    match policy {
+        SurplusPolicy::Buffer => "buffer",
+        SurplusPolicy::Bond => "bond",
+    }
+}
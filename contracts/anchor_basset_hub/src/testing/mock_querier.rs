@@ -1,4 +1,6 @@
 use basset::hub::Config;
+use basset::oracle::{OracleQueryMsg, PriceResponse};
+use basset::staking::{encode_unbonding_time, STAKING_PARAMS_QUERY_PATH};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
 use cosmwasm_std::{
     from_binary, from_slice, to_binary, AllBalanceResponse, Api, BalanceResponse, BankQuery,
@@ -9,7 +11,9 @@ use cosmwasm_storage::to_length_prefixed;
 use cw20_legacy::state::{MinterData, TokenInfo};
 use std::collections::HashMap;
 
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg};
+use basset::token::{BalanceAtResponse, TokenQueryMsg};
+use basset::wormhole::{WormholeQueryMsg, WormholeStateResponse};
+use cw20::BalanceResponse as Cw20BalanceResponse;
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 
 pub const MOCK_CONTRACT_ADDR: &str = "cosmos2contract";
@@ -56,6 +60,10 @@ pub struct WasmMockQuerier {
     token_querier: TokenQuerier,
     balance_querier: BalanceQuerier,
     tax_querier: TaxQuerier,
+    oracle_prices: HashMap<(String, String), Decimal>,
+    snapshot_balances: HashMap<(String, u64), Uint128>,
+    wormhole_fee: Coin,
+    unbonding_time: Option<u64>,
 }
 
 impl Querier for WasmMockQuerier {
@@ -209,9 +217,33 @@ impl WasmMockQuerier {
                     unimplemented!()
                 }
             }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == "wormhole" =>
+            {
+                let WormholeQueryMsg::GetState {} = from_binary(msg).unwrap();
+                let res = WormholeStateResponse {
+                    fee: self.wormhole_fee.clone(),
+                };
+                SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == "oracle" =>
+            {
+                let OracleQueryMsg::Price { base, quote } = from_binary(msg).unwrap();
+                match self.oracle_prices.get(&(base, quote)) {
+                    Some(rate) => {
+                        SystemResult::Ok(ContractResult::from(to_binary(&PriceResponse {
+                            rate: *rate,
+                            last_updated_base: 0,
+                            last_updated_quote: 0,
+                        })))
+                    }
+                    None => SystemResult::Ok(ContractResult::Err("No price".to_string())),
+                }
+            }
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
                 match from_binary(msg).unwrap() {
-                    Cw20QueryMsg::TokenInfo {} => {
+                    TokenQueryMsg::TokenInfo {} => {
                         let balances: &HashMap<String, Uint128> =
                             match self.token_querier.balances.get(contract_addr) {
                                 Some(balances) => balances,
@@ -243,7 +275,7 @@ impl WasmMockQuerier {
                         };
                         SystemResult::Ok(ContractResult::Ok(to_binary(&token_inf).unwrap()))
                     }
-                    Cw20QueryMsg::Balance { address } => {
+                    TokenQueryMsg::Balance { address } => {
                         let balances: &HashMap<String, Uint128> =
                             match self.token_querier.balances.get(contract_addr) {
                                 Some(balances) => balances,
@@ -274,9 +306,26 @@ impl WasmMockQuerier {
                             to_binary(&Cw20BalanceResponse { balance }).unwrap(),
                         ))
                     }
+                    TokenQueryMsg::BalanceAt { address, height } => {
+                        let balance = self
+                            .snapshot_balances
+                            .get(&(address, height))
+                            .copied()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_binary(&BalanceAtResponse { balance, height }).unwrap(),
+                        ))
+                    }
                     _ => panic!("DO NOT ENTER HERE"),
                 }
             }
+            QueryRequest::Stargate { path, .. }
+                if path == STAKING_PARAMS_QUERY_PATH && self.unbonding_time.is_some() =>
+            {
+                SystemResult::Ok(ContractResult::Ok(encode_unbonding_time(
+                    self.unbonding_time.unwrap(),
+                )))
+            }
             _ => self.base.handle_query(request),
         }
     }
@@ -350,6 +399,10 @@ impl WasmMockQuerier {
             token_querier: TokenQuerier::default(),
             tax_querier: TaxQuerier::default(),
             balance_querier: BalanceQuerier::default(),
+            oracle_prices: HashMap::new(),
+            snapshot_balances: HashMap::new(),
+            wormhole_fee: Coin::new(0, "uluna"),
+            unbonding_time: None,
         }
     }
 
@@ -363,7 +416,29 @@ impl WasmMockQuerier {
     }
 
     // configure the tax mock querier
-    pub fn _with_tax(&mut self, rate: Decimal, caps: &[(&String, &Uint128)]) {
+    pub fn with_tax(&mut self, rate: Decimal, caps: &[(&String, &Uint128)]) {
         self.tax_querier = TaxQuerier::_new(rate, caps);
     }
+
+    // configure the bAsset balance of address at the beginning of height
+    pub fn with_snapshot_balance(&mut self, address: &str, height: u64, balance: Uint128) {
+        self.snapshot_balances
+            .insert((address.to_string(), height), balance);
+    }
+
+    // configure the fee of the "wormhole" core contract
+    pub fn with_wormhole_fee(&mut self, fee: Coin) {
+        self.wormhole_fee = fee;
+    }
+
+    // configure the unbonding time of the staking params, unsupported by default
+    pub fn with_unbonding_time(&mut self, unbonding_time: u64) {
+        self.unbonding_time = Some(unbonding_time);
+    }
+
+    // configure the price of base in quote reported by the "oracle" contract
+    pub fn with_oracle_price(&mut self, base: &str, quote: &str, rate: Decimal) {
+        self.oracle_prices
+            .insert((base.to_string(), quote.to_string()), rate);
+    }
 }
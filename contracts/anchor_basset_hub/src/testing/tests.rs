@@ -16,38 +16,72 @@
 //!          //...
 //!      });
 //! 4. Anywhere you see query(deps.as_ref(), ...) you must replace it with query(&mut deps, ...)
+#![allow(clippy::doc_overindented_list_items, clippy::get_first)]
 use cosmwasm_std::{
-    coin, from_binary, to_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, DepsMut,
-    DistributionMsg, Env, FullDelegation, MessageInfo, OwnedDeps, Querier, Response, StakingMsg,
-    StdError, Storage, SubMsg, Uint128, Validator, WasmMsg,
+    attr, coin, from_binary, to_binary, Addr, Api, BankMsg, Binary, Coin, ContractResult,
+    CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Env, Event, FullDelegation, MessageInfo,
+    Order, OwnedDeps, Querier, Reply, Response, StakingMsg, StdError, StdResult, Storage, SubMsg,
+    SubMsgExecutionResponse, Uint128, Validator, WasmMsg,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::testing::{mock_env, mock_info};
 
-use crate::contract::{execute, instantiate, query};
-use crate::unbond::execute_unbond;
+use crate::contract::{execute, instantiate, migrate, query, reply};
+use crate::insurance::INSURANCE_REPLY_ID;
+use crate::unbond::{execute_unbond, AUTO_CLAIM_REPLY_ID, UNBOND_REPLY_ID};
 use basset::hub::QueryMsg;
 use basset::hub::{
-    AllHistoryResponse, ConfigResponse, CurrentBatchResponse, ExecuteMsg, InstantiateMsg,
-    StateResponse, UnbondRequestsResponse, WhitelistedValidatorsResponse,
-    WithdrawableUnbondedResponse,
+    AllHistoryResponse, CollateralInfoResponse, ConfigHistoryResponse, ConfigResponse,
+    CurrentBatchResponse, ExchangeRateTwapResponse, ExecuteMsg, InstantiateMsg, StateResponse,
+    UnbondRequestsResponse, WhitelistedValidatorsResponse, WithdrawableUnbondedResponse,
 };
 
+use basset::contract_error::ContractError;
+use basset::hub::Cw20HookMsg;
 use basset::hub::Cw20HookMsg::Unbond;
-use basset::hub::ExecuteMsg::{CheckSlashing, Receive, UpdateConfig, UpdateParams};
+use basset::hub::ExecuteMsg::{
+    ApplyPendingChange, CancelPendingChange, CheckSlashing, Pause, ProposePendingChange, Receive,
+    Resume, UpdateConfig, UpdateParams,
+};
+use basset::hub::{
+    AccessControlResponse, AccessMode, AutoClaimResponse, BatchStatus, BondData, BufferTarget,
+    BufferTargetResponse, CircuitBreaker, CircuitBreakerResponse, EpochAutoAdvanceResponse,
+    FeesCollectedResponse, HubMode, IbcBridgeResponse, Insurance, InsuranceResponse,
+    KeeperIncentive, KeeperIncentiveResponse, LiquidityResponse, MinBondAmountResponse,
+    ModeResponse, Op, OperationKind, OperatorsResponse, PendingChangesResponse, PriceGuardResponse,
+    ProposalResponse, RampAllowance, RampAllowancesResponse, SimulateBondResponse,
+    SimulateUnbondResponse, SurplusPolicy, SurplusResponse, TaxModeResponse, TimelockedChange,
+    UnbondBatch, UnbondBatchProofResponse, UnbondBatchStatus, UnbondBatchesResponse, UnbondData,
+    UnbondPriorityResponse, UserAction, UserEvent, UserHistoryResponse, ValidatorDelegation,
+    ValidatorDelegationsResponse, WormholeResponse, MAX_KEEPER_RESERVE,
+};
+use basset::operator::OperatorResponse;
+use cw2::{get_contract_version, set_contract_version};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use cw20_legacy::msg::ExecuteMsg::{Burn, Mint};
 
 use super::mock_querier::{mock_dependencies as dependencies, WasmMockQuerier};
 use crate::math::decimal_division;
-use crate::state::{read_unbond_wait_list, Parameters, CONFIG};
+use crate::state::{
+    read_unbond_wait_list, store_unbond_wait_list, CIRCUIT_BREAKER, CONFIG, MAX_WITHDRAW_REQUESTS,
+    STATE,
+};
+use crate::twap::checkpoint_exchange_rate;
 use basset::airdrop::ExecuteMsg::{FabricateANCClaim, FabricateMIRClaim};
-use basset::airdrop::PairHandleMsg;
+use basset::gov::{encode_msg_vote_weighted, Tally, VoteOption};
+use basset::hub::Parameters;
 use basset::hub::QueryMsg::{AllHistory, UnbondRequests, WithdrawableUnbonded};
+use basset::ibc::Ics20TransferMsg;
+use basset::insurance::InsuranceExecuteMsg;
+use basset::migration::MigrateMsg;
+use basset::reward::ExecuteMsg as RewardExecuteMsg;
 use basset::reward::ExecuteMsg::{SwapToRewardDenom, UpdateGlobalIndex};
+use basset::snapshot::ExportStateResponse;
+use basset::wormhole::{UnbondReceipt, WormholeExecuteMsg};
 use std::borrow::BorrowMut;
+use std::str::FromStr;
 
 const DEFAULT_VALIDATOR: &str = "default-validator";
 const DEFAULT_VALIDATOR2: &str = "default-validator2000";
@@ -93,6 +127,7 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         er_threshold: Decimal::one(),
         reward_denom: "uusd".to_string(),
         validator,
+        mode: HubMode::Staking,
     };
 
     let owner_info = mock_info(owner.as_str(), &[coin(1000000, "uluna")]);
@@ -105,8 +140,9 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         airdrop_registry_contract: Some("airdrop_registry".to_string()),
     };
 
+    let owner_info = mock_info(owner.as_str(), &[]);
     let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
-    assert_eq!(1, res.messages.len());
+    assert_eq!(2, res.messages.len());
 }
 
 pub fn do_register_validator(deps: DepsMut, validator: Validator) {
@@ -132,7 +168,7 @@ pub fn do_bond(deps: DepsMut, addr: String, amount: Uint128, validator: Validato
 }
 
 pub fn do_unbond(
-    deps: DepsMut,
+    mut deps: DepsMut,
     addr: String,
     env: Env,
     info: MessageInfo,
@@ -145,7 +181,38 @@ pub fn do_unbond(
         msg: to_binary(&successful_bond).unwrap(),
     });
 
-    execute(deps, env, info, receive).unwrap()
+    let res = execute(deps.branch(), env, info, receive).unwrap();
+    burn_unbonded(deps);
+    res
+}
+
+/// Deliver the reply to the burn of an unbond, as the chain does once the token burnt it
+pub fn burn_unbonded(deps: DepsMut) {
+    reply(
+        deps,
+        mock_env(),
+        Reply {
+            id: UNBOND_REPLY_ID,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+}
+
+/// Unbond and deliver the reply to its burn
+fn unbond_and_burn(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    sender: String,
+) -> StdResult<Response> {
+    let res = execute_unbond(deps.branch(), env, info, amount, sender)?;
+    burn_unbonded(deps);
+    Ok(res)
 }
 
 /// Covers if all the fields of InitMsg are stored in
@@ -167,11 +234,24 @@ fn proper_initialization() {
         er_threshold: Decimal::one(),
         reward_denom: "uusd".to_string(),
         validator: validator.address.clone(),
+        mode: HubMode::Staking,
     };
 
     let _owner = "owner1";
     let owner_info = mock_info("owner1", &[coin(1000000, "uluna")]);
 
+    // malformed denoms are rejected
+    let mut invalid_msg = msg.clone();
+    invalid_msg.reward_denom = "u usd".to_string();
+    let res = instantiate(deps.as_mut(), mock_env(), owner_info.clone(), invalid_msg).unwrap_err();
+    assert_eq!(
+        res,
+        ContractError::InvalidDenom {
+            denom: "u usd".to_string(),
+            reason: "contains invalid characters".to_string()
+        }
+    );
+
     // we can just call .unwrap() to assert this was a success
     let res: Response = instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
     assert_eq!(2, res.messages.len());
@@ -277,7 +357,7 @@ fn proper_register_validator() {
 
     // invalid requests
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     //invalid validator
 
@@ -289,7 +369,9 @@ fn proper_register_validator() {
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg);
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("The specified address is not a validator")
+        ContractError::Std(StdError::generic_err(
+            "The specified address is not a validator"
+        ))
     );
 
     // successful call
@@ -323,6 +405,73 @@ fn proper_register_validator() {
     assert_eq!(query_res.validators.get(0).unwrap(), &validator.address);
 }
 
+#[test]
+fn validators_are_imported_from_the_staking_query() {
+    let mut deps = dependencies(&[]);
+    let mut expensive = sample_validator("expensive".to_string());
+    expensive.commission = Decimal::percent(20);
+    deps.querier.update_staking(
+        "uluna",
+        &[
+            sample_validator(DEFAULT_VALIDATOR.to_string()),
+            expensive,
+            sample_validator(DEFAULT_VALIDATOR2.to_string()),
+            sample_validator(DEFAULT_VALIDATOR3.to_string()),
+        ],
+        &[],
+    );
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        DEFAULT_VALIDATOR.to_string(),
+    );
+    do_register_validator(
+        deps.as_mut(),
+        sample_validator(DEFAULT_VALIDATOR.to_string()),
+    );
+
+    let import = |limit| ExecuteMsg::ImportValidators {
+        max_commission: Decimal::percent(5),
+        limit,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        import(None),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let owner = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner.clone(), import(Some(1))).unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("validators", DEFAULT_VALIDATOR2)));
+    // the registered and the expensive validators are skipped
+    execute(deps.as_mut(), mock_env(), owner.clone(), import(None)).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), owner, import(None)).unwrap();
+    assert!(res.attributes.contains(&attr("imported", "0")));
+
+    let validators: WhitelistedValidatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WhitelistedValidators {},
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let mut expected = vec![
+        DEFAULT_VALIDATOR.to_string(),
+        DEFAULT_VALIDATOR2.to_string(),
+        DEFAULT_VALIDATOR3.to_string(),
+    ];
+    expected.sort();
+    assert_eq!(validators.validators, expected);
+}
+
 /// Covers if delegate message is sent to the specified validator,
 /// mint message is sent to the token contract, state is changed based on new mint,
 /// and check unsuccessful calls, like unsupported validators, and invalid coin.
@@ -376,6 +525,18 @@ fn proper_bond() {
 
     let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
     assert_eq!(2, res.messages.len());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "mint"),
+            attr("from", addr1.as_str()),
+            attr("sender", addr1.as_str()),
+            attr("recipient", addr1.as_str()),
+            attr("amount", bond_amount),
+            attr("bonded", bond_amount),
+            attr("minted", bond_amount),
+        ]
+    );
 
     // set bob's balance in token contract
     deps.querier
@@ -431,7 +592,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, bond);
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("The chosen validator is currently not supported")
+        ContractError::Std(StdError::generic_err(
+            "The chosen validator is currently not supported"
+        ))
     );
 
     // no-send funds
@@ -445,7 +608,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond);
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("No uluna assets are provided to bond")
+        ContractError::Std(StdError::generic_err(
+            "No uluna assets are provided to bond"
+        ))
     );
 
     //send other tokens than luna funds
@@ -459,7 +624,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond.clone());
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("No uluna assets are provided to bond")
+        ContractError::Std(StdError::generic_err(
+            "No uluna assets are provided to bond"
+        ))
     );
 
     //bond with more than one coin is not possible
@@ -474,7 +641,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("More than one coin is sent; only one asset is supported")
+        ContractError::Std(StdError::generic_err(
+            "More than one coin is sent; only one asset is supported"
+        ))
     );
 }
 
@@ -533,7 +702,7 @@ fn proper_deregister() {
 
     let invalid_info = mock_info("invalid", &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, msg);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     let msg = ExecuteMsg::DeregisterValidator {
         validator: validator.address.clone(),
@@ -591,7 +760,9 @@ fn proper_deregister() {
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("Cannot remove the last whitelisted validator")
+        ContractError::Std(StdError::generic_err(
+            "Cannot remove the last whitelisted validator"
+        ))
     );
 }
 
@@ -721,6 +892,11 @@ pub fn proper_update_global_index_two_validators() {
     // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
 
+    // the initial bond is backed by a supply, so that the bonds after the first mint bAsset
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1_000_000u128))],
+    )]);
     // bond
     do_bond(
         deps.as_mut(),
@@ -914,7 +1090,7 @@ pub fn proper_receive() {
 
     let invalid_info = mock_info(&invalid, &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, receive);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     // successful call
     let successful_unbond = Unbond {};
@@ -953,144 +1129,542 @@ pub fn proper_receive() {
 /// the current epoch is updated to the new values,
 /// the request is stored in unbond wait list, and unbond history map is updated
 #[test]
-pub fn proper_unbond() {
+pub fn bond_and_unbond_return_their_receipts_as_data() {
     let mut deps = dependencies(&[]);
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
-
-    let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-    let reward_contract = "reward".to_string();
     init(
-        deps.borrow_mut(),
-        owner,
-        reward_contract,
-        token_contract.clone(),
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
         validator.address.clone(),
     );
-
-    // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
 
     let bob = "bob".to_string();
     let bond = ExecuteMsg::Bond {
         validator: validator.address.clone(),
     };
+    for bond_id in 0..2 {
+        // the supply backs the bonded amount, the exchange rate stays one
+        let supply = Uint128::new(1_000_000 + 10 * bond_id as u128);
+        deps.querier
+            .with_token_balances(&[(&"token".to_string(), &[(&bob, &supply)])]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(&bob, &[coin(10, "uluna")]),
+            bond.clone(),
+        )
+        .unwrap();
+        let data: BondData = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(
+            data,
+            BondData {
+                bond_id,
+                minted_amount: Uint128::new(10),
+                exchange_rate: Decimal::one(),
+            }
+        );
+    }
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&bob, &Uint128::new(1_000_020u128))],
+    )]);
+    set_delegation(&mut deps.querier, validator, 1_000_020, "uluna");
 
-    let info = mock_info(&bob, &[coin(10, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("token", &[]),
+        Receive(Cw20ReceiveMsg {
+            sender: bob,
+            amount: Uint128::new(5),
+            msg: to_binary(&Unbond {}).unwrap(),
+        }),
+    )
+    .unwrap();
+    let data: UnbondData = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(
+        data,
+        UnbondData {
+            batch_id: 1,
+            burned_amount: Uint128::new(5),
+        }
+    );
+}
 
-    //set bob's balance to 10 in token contract
+#[test]
+pub fn unbond_paths_are_locked_until_the_burn_replies() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
     deps.querier
         .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(10u128))])]);
+    set_delegation(&mut deps.querier, validator, 10, "uluna");
 
-    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
-    assert_eq!(2, res.messages.len());
-
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(10, "uluna"));
-        }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
-
-    set_delegation(&mut deps.querier, validator.clone(), 10, "uluna");
+    let unbond = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(5),
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("token", &[]),
+        unbond.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.last().unwrap().id, UNBOND_REPLY_ID);
 
-    //check the current batch before unbond
-    let current_batch = QueryMsg::CurrentBatch {};
-    let query_batch: CurrentBatchResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
-    assert_eq!(query_batch.id, 1);
-    assert_eq!(query_batch.requested_with_fee, Uint128::zero());
+    // the unbond paths are refused while the burn is pending
+    let locked = ContractError::Locked {};
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("token", &[]),
+        unbond.clone(),
+    );
+    assert_eq!(res.unwrap_err(), locked);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    );
+    assert_eq!(res.unwrap_err(), locked);
 
-    let token_info = mock_info(&token_contract, &[]);
-    let mut token_env = mock_env();
+    // the reply to the burn releases the lock
+    burn_unbonded(deps.as_mut());
+    execute(deps.as_mut(), mock_env(), mock_info("token", &[]), unbond).unwrap();
+}
 
-    // check the state before unbond
-    let state = QueryMsg::State {};
-    let query_state: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), state).unwrap()).unwrap();
-    assert_eq!(
-        query_state.last_unbonded_time,
-        mock_env().block.time.seconds()
+#[test]
+pub fn dust_bonds_are_refused() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
     );
-    assert_eq!(query_state.total_bond_amount, Uint128::new(1000010));
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    // successful call
-    let successful_bond = Unbond {};
-    let receive = Receive(Cw20ReceiveMsg {
-        sender: bob.clone(),
-        amount: Uint128::new(1),
-        msg: to_binary(&successful_bond).unwrap(),
-    });
-    let res = execute(deps.as_mut(), mock_env(), token_info, receive).unwrap();
-    assert_eq!(1, res.messages.len());
+    // the initial bond mints nothing, the exchange rate is 100001 after the first bond
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9u128))])]);
-
-    //read the undelegated waitlist of the current epoch for the user bob
-    let wait_list = read_unbond_wait_list(&deps.storage, 1, bob.clone()).unwrap();
-    assert_eq!(Uint128::new(1), wait_list);
-
-    //successful call
-    let successful_bond = Unbond {};
-    let receive = Receive(Cw20ReceiveMsg {
-        sender: bob.clone(),
-        amount: Uint128::new(5),
-        msg: to_binary(&successful_bond).unwrap(),
-    });
-    let token_info = mock_info(&token_contract, &[]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(10u128))])]);
 
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
     let res = execute(
         deps.as_mut(),
-        token_env.clone(),
-        token_info.clone(),
-        receive,
+        mock_env(),
+        mock_info(&bob, &[coin(100000, "uluna")]),
+        bond.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "Bonding 100000uluna mints no bAsset at the exchange rate 100001"
+        ))
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(100001, "uluna")]),
+        bond.clone(),
     )
     .unwrap();
-    assert_eq!(1, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(4u128))])]);
+    let data: BondData = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data.minted_amount, Uint128::new(1));
 
-    let msg = &res.messages[0].msg;
-    match msg {
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr,
-            msg,
-            funds: _,
-        }) => {
-            assert_eq!(contract_addr, &token_contract);
-            assert_eq!(
-                msg,
-                &to_binary(&Burn {
-                    amount: Uint128::new(5)
-                })
-                .unwrap()
-            );
-        }
-        _ => panic!("Unexpected message: {:?}", msg),
-    }
+    // only the owner sets the minimum
+    let update = ExecuteMsg::UpdateMinBondAmount {
+        min_bond_amount: Some(Uint128::new(1000)),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        update.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), update).unwrap();
+    let res: MinBondAmountResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::MinBondAmount {}).unwrap())
+            .unwrap();
+    assert_eq!(res.min_bond_amount, Some(Uint128::new(1000)));
 
-    let waitlist2 = read_unbond_wait_list(&deps.storage, 1, bob.to_string()).unwrap();
-    assert_eq!(Uint128::new(6), waitlist2);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(999, "uluna")]),
+        bond,
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "Bonds of less than 1000uluna are not accepted"
+        ))
+    );
+}
 
-    let current_batch = QueryMsg::CurrentBatch {};
-    let query_batch: CurrentBatchResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
-    assert_eq!(query_batch.id, 1);
-    assert_eq!(query_batch.requested_with_fee, Uint128::new(6));
+#[test]
+pub fn unexpected_funds_are_refused() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    token_env.block.time = token_env.block.time.plus_seconds(31);
+    let bob = "bob".to_string();
+    let info = mock_info(&bob, &[coin(10, "uluna")]);
+    for msg in [
+        ExecuteMsg::UpdateGlobalIndex {
+            airdrop_hooks: None,
+        },
+        ExecuteMsg::WithdrawUnbonded {},
+        ExecuteMsg::CheckSlashing {},
+    ] {
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedFunds {});
+    }
 
-    //pushing time forward to check the unbond message
-    let successful_bond = Unbond {};
+    // bonds still take their coins
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::Bond {
+            validator: validator.address,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+pub fn unbond_fee_stays_in_the_pool() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // the initial bond is backed 1:1
+    let bob = "bob".to_string();
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1000000))],
+    )]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000000),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator.clone(), 2000000, "uluna");
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&"owner1".to_string(), &Uint128::new(1000000)),
+            (&bob, &Uint128::new(1000000)),
+        ],
+    )]);
+
+    let update_fee = |unbond_fee_bps| UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        unbond_fee_bps: Some(unbond_fee_bps),
+    };
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        update_fee(501),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The unbond fee must not be more than 500 basis points"
+        ))
+    );
+    execute(deps.as_mut(), mock_env(), owner_info, update_fee(100)).unwrap();
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.unbond_fee_bps, 100);
+
+    // 1% of the unbond is kept, the exchange rate rises for the holders left
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(10000),
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("token", &[]), receive).unwrap();
+    assert_eq!(
+        read_unbond_wait_list(&deps.storage, 1, bob).unwrap(),
+        Uint128::new(9900)
+    );
+    let fees: FeesCollectedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeesCollected {}).unwrap())
+            .unwrap();
+    assert_eq!(fees.unbond_fees, Uint128::new(100));
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(
+        state.exchange_rate,
+        Decimal::from_ratio(2000000u128, 1999900u128)
+    );
+}
+
+#[test]
+pub fn exchange_rate_twap_weights_the_rates_by_time() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+    let twap = |deps: Deps, seconds: u64, window_seconds: u64| -> StdResult<Decimal> {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        let res: ExchangeRateTwapResponse = from_binary(&query(
+            deps,
+            env,
+            QueryMsg::ExchangeRateTwap { window_seconds },
+        )?)?;
+        assert_eq!(res.window_seconds, window_seconds);
+        Ok(res.twap)
+    };
+
+    // the rate rises to 1.1 after 100 seconds, a handler checkpoints before changing it
+    let now = mock_env().block.time.seconds();
+    checkpoint_exchange_rate(&mut deps.storage, now + 100).unwrap();
+    STATE
+        .update(&mut deps.storage, |mut state| -> StdResult<_> {
+            state.exchange_rate = Decimal::from_ratio(11u64, 10u64);
+            Ok(state)
+        })
+        .unwrap();
+
+    assert_eq!(
+        twap(deps.as_ref(), 200, 200).unwrap(),
+        Decimal::from_ratio(105u64, 100u64)
+    );
+    assert_eq!(
+        twap(deps.as_ref(), 200, 50).unwrap(),
+        Decimal::from_ratio(11u64, 10u64)
+    );
+    assert_eq!(
+        twap(deps.as_ref(), 200, 150).unwrap(),
+        Decimal::from_ratio(160u64, 150u64)
+    );
+    assert_eq!(twap(deps.as_ref(), 100, 100).unwrap(), Decimal::one());
+
+    // a later checkpoint does not change the averages
+    checkpoint_exchange_rate(&mut deps.storage, now + 200).unwrap();
+    assert_eq!(
+        twap(deps.as_ref(), 200, 150).unwrap(),
+        Decimal::from_ratio(160u64, 150u64)
+    );
+
+    assert_eq!(
+        twap(deps.as_ref(), 200, 201).unwrap_err(),
+        StdError::generic_err("The exchange rate is observed for less than 201 seconds")
+    );
+    assert_eq!(
+        twap(deps.as_ref(), 200, 0).unwrap_err(),
+        StdError::generic_err("The twap window must be between 1 and 604800 seconds")
+    );
+}
+
+#[test]
+pub fn proper_unbond() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let reward_contract = "reward".to_string();
+    init(
+        deps.borrow_mut(),
+        owner,
+        reward_contract,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(10, "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(10u128))])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(10, "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    set_delegation(&mut deps.querier, validator.clone(), 10, "uluna");
+
+    //check the current batch before unbond
+    let current_batch = QueryMsg::CurrentBatch {};
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
+    assert_eq!(query_batch.id, 1);
+    assert_eq!(query_batch.requested_with_fee, Uint128::zero());
+
+    let token_info = mock_info(&token_contract, &[]);
+    let mut token_env = mock_env();
+
+    // check the state before unbond
+    let state = QueryMsg::State {};
+    let query_state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), state).unwrap()).unwrap();
+    assert_eq!(
+        query_state.last_unbonded_time,
+        mock_env().block.time.seconds()
+    );
+    assert_eq!(query_state.total_bond_amount, Uint128::new(1000010));
+
+    // successful call
+    let successful_bond = Unbond {};
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(1),
+        msg: to_binary(&successful_bond).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), token_info, receive).unwrap();
+    burn_unbonded(deps.as_mut());
+    assert_eq!(1, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9u128))])]);
+
+    //read the undelegated waitlist of the current epoch for the user bob
+    let wait_list = read_unbond_wait_list(&deps.storage, 1, bob.clone()).unwrap();
+    assert_eq!(Uint128::new(1), wait_list);
+
+    //successful call
+    let successful_bond = Unbond {};
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(5),
+        msg: to_binary(&successful_bond).unwrap(),
+    });
+    let token_info = mock_info(&token_contract, &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        token_env.clone(),
+        token_info.clone(),
+        receive,
+    )
+    .unwrap();
+    burn_unbonded(deps.as_mut());
+    assert_eq!(1, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(4u128))])]);
+
+    let msg = &res.messages[0].msg;
+    match msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds: _,
+        }) => {
+            assert_eq!(contract_addr, &token_contract);
+            assert_eq!(
+                msg,
+                &to_binary(&Burn {
+                    amount: Uint128::new(5)
+                })
+                .unwrap()
+            );
+        }
+        _ => panic!("Unexpected message: {:?}", msg),
+    }
+
+    let waitlist2 = read_unbond_wait_list(&deps.storage, 1, bob.to_string()).unwrap();
+    assert_eq!(Uint128::new(6), waitlist2);
+
+    let current_batch = QueryMsg::CurrentBatch {};
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
+    assert_eq!(query_batch.id, 1);
+    assert_eq!(query_batch.requested_with_fee, Uint128::new(6));
+
+    token_env.block.time = token_env.block.time.plus_seconds(31);
+
+    //pushing time forward to check the unbond message
+    let successful_bond = Unbond {};
     let receive = Receive(Cw20ReceiveMsg {
         sender: bob,
         amount: Uint128::new(2),
         msg: to_binary(&successful_bond).unwrap(),
     });
     let res = execute(deps.as_mut(), token_env.clone(), token_info, receive).unwrap();
+    burn_unbonded(deps.as_mut());
     assert_eq!(2, res.messages.len());
 
     let msg = &res.messages[1].msg;
@@ -1140,6 +1714,8 @@ pub fn proper_unbond() {
     // the last request (2) gets combined and processed with the previous requests (1, 5)
     let waitlist = QueryMsg::UnbondRequests {
         address: "bob".to_string(),
+        start_after: None,
+        limit: None,
     };
     let query_unbond: UnbondRequestsResponse =
         from_binary(&query(deps.as_ref(), mock_env(), waitlist).unwrap()).unwrap();
@@ -1158,51 +1734,131 @@ pub fn proper_unbond() {
     assert_eq!(res.history[0].batch_id, 1);
 }
 
-/// Covers if the pick_validator function sends different Undelegate messages
-/// to different validators, when a validator does not have enough delegation.
 #[test]
-pub fn proper_pick_validator() {
+fn collateral_info_values_the_balance_and_the_unbonding() {
     let mut deps = dependencies(&[]);
-
-    let addr1 = "addr1000".to_string();
-    let addr2 = "addr2000".to_string();
-    let addr3 = "addr3000".to_string();
-
-    // create 3 validators
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
-    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
-    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
-
     set_validator_mock(&mut deps.querier);
-
-    let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-    let reward_contract = "reward".to_string();
-
     init(
-        deps.borrow_mut(),
-        owner,
-        reward_contract,
-        token_contract.clone(),
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
         validator.address.clone(),
     );
-
     do_register_validator(deps.as_mut(), validator.clone());
-    do_register_validator(deps.as_mut(), validator2.clone());
-    do_register_validator(deps.as_mut(), validator3.clone());
 
-    // bond to a validator
+    let bob = "bob".to_string();
     do_bond(
         deps.as_mut(),
-        addr1.clone(),
-        Uint128::new(10),
+        bob.clone(),
+        Uint128::new(1000),
         validator.clone(),
     );
-    do_bond(
+    set_delegation(&mut deps.querier, validator, 1000, "uluna");
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000u128))])]);
+    do_unbond(
         deps.as_mut(),
-        addr2.clone(),
-        Uint128::new(300),
-        validator2.clone(),
+        bob.clone(),
+        mock_env(),
+        mock_info("token", &[]),
+        Uint128::new(400),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(600u128))])]);
+
+    let info: CollateralInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CollateralInfo {
+                address: bob.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        info,
+        CollateralInfoResponse {
+            address: bob.clone(),
+            exchange_rate: Decimal::one(),
+            balance: Uint128::new(600),
+            balance_value: Uint128::new(600),
+            unbonding: Uint128::new(400),
+            unbonding_value: Uint128::new(400),
+            more_requests: false,
+        }
+    );
+
+    // the query counts a bounded number of requests
+    for batch_id in 10..10 + MAX_WITHDRAW_REQUESTS as u64 {
+        store_unbond_wait_list(&mut deps.storage, batch_id, bob.clone(), Uint128::new(1)).unwrap();
+    }
+    let info: CollateralInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CollateralInfo { address: bob },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(info.unbonding, Uint128::new(429));
+    assert!(info.more_requests);
+}
+
+/// Covers if the pick_validator function sends different Undelegate messages
+/// to different validators, when a validator does not have enough delegation.
+#[test]
+pub fn proper_pick_validator() {
+    let mut deps = dependencies(&[]);
+
+    let addr1 = "addr1000".to_string();
+    let addr2 = "addr2000".to_string();
+    let addr3 = "addr3000".to_string();
+
+    // create 3 validators
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let reward_contract = "reward".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        reward_contract,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3.clone());
+
+    // the initial bond is backed by a supply, so that the bonds after the first mint bAsset
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1_000_000u128))],
+    )]);
+    // bond to a validator
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        addr2.clone(),
+        Uint128::new(300),
+        validator2.clone(),
     );
     do_bond(
         deps.as_mut(),
@@ -1539,7 +2195,7 @@ pub fn proper_slashing() {
 
     let info = mock_info(&addr1, &[]);
     let mut env = mock_env();
-    let _res = execute_unbond(
+    let _res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -1553,7 +2209,7 @@ pub fn proper_slashing() {
 
     env.block.time = env.block.time.plus_seconds(31);
 
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -1605,6 +2261,122 @@ pub fn proper_slashing() {
     }
 }
 
+#[test]
+pub fn slashing_is_covered_by_the_insurance_fund() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    let addr1 = "addr1000".to_string();
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+
+    let update_insurance = ExecuteMsg::UpdateInsurance {
+        insurance: Some(Insurance {
+            contract: "insurance".to_string(),
+            max_cover: Uint128::new(60),
+        }),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        update_insurance.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update_insurance,
+    )
+    .unwrap();
+
+    // 100 is slashed, the fund is asked to cover 60 of it
+    set_delegation(&mut deps.querier, validator.clone(), 900, "uluna");
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(0, "uluna"))]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "insurance".to_string(),
+                msg: to_binary(&InsuranceExecuteMsg::RequestCover {
+                    amount: Uint128::new(60)
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            INSURANCE_REPLY_ID,
+        )]
+    );
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate.to_string(), "0.96");
+
+    // the fund pays 50, it is delegated and the unpaid 10 reprices the bAsset
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(50, "uluna"))]);
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: INSURANCE_REPLY_ID,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: DEFAULT_VALIDATOR.to_string(),
+            amount: coin(50, "uluna"),
+        }))]
+    );
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(950));
+    assert_eq!(state.exchange_rate.to_string(), "0.95");
+    let insurance: InsuranceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Insurance {}).unwrap()).unwrap();
+    assert_eq!(insurance.pending_cover, Uint128::zero());
+    assert_eq!(insurance.covered, Uint128::new(50));
+
+    // once delegated, the cover is not a slashing
+    set_delegation(&mut deps.querier, validator, 950, "uluna");
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+}
+
 /// Covers if the withdraw_rate function is updated before and after withdraw_unbonded,
 /// the finished amount is accurate, user requests are removed from the waitlist, and
 /// the BankMsg::Send is sent.
@@ -1655,7 +2427,7 @@ pub fn proper_withdraw_unbonded() {
 
     set_delegation(&mut deps.querier, validator, 100, "uluna");
 
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         mock_env(),
         info,
@@ -1693,10 +2465,12 @@ pub fn proper_withdraw_unbonded() {
     assert!(wdraw_unbonded_res.is_err());
     assert_eq!(
         wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+        ContractError::Std(StdError::generic_err(
+            "No withdrawable uluna assets are available yet"
+        ))
     );
 
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -1729,6 +2503,8 @@ pub fn proper_withdraw_unbonded() {
     //first query AllUnbondedRequests
     let all_unbonded = UnbondRequests {
         address: bob.clone(),
+        start_after: None,
+        limit: None,
     };
     let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
     let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
@@ -1779,6 +2555,8 @@ pub fn proper_withdraw_unbonded() {
 
     let waitlist = UnbondRequests {
         address: bob.clone(),
+        start_after: None,
+        limit: None,
     };
     let query_unbond: UnbondRequestsResponse =
         from_binary(&query(deps.as_ref(), mock_env(), waitlist).unwrap()).unwrap();
@@ -1798,117 +2576,496 @@ pub fn proper_withdraw_unbonded() {
     assert_eq!(state_query.exchange_rate, Decimal::one());
 }
 
-/// Covers slashing during the unbonded period and its effect on the finished amount.
 #[test]
-pub fn proper_withdraw_unbonded_respect_slashing() {
+pub fn small_requests_are_withdrawn_from_the_unbond_buffer() {
     let mut deps = dependencies(&[]);
-
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
-
-    let bond_amount = Uint128::new(10000);
-    let unbond_amount = Uint128::new(500);
-
-    let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-    let reward_contract = "reward".to_string();
-
     init(
         &mut deps,
-        owner,
-        reward_contract,
-        token_contract,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
         validator.address.clone(),
     );
-
-    // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
-
     let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
-
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
-
-    //set bob's balance to 10 in token contract
+    let alice = "alice".to_string();
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
-
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
-
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
-        }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
-
-    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
-    assert_eq!(1, res.messages.len());
+    // a small and a large request are sent to undelegation in batch 1
+    let info = mock_info("token", &[]);
+    unbond_and_burn(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        Uint128::new(5),
+        bob.clone(),
+    )
+    .unwrap();
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
-
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(0),
-        },
-    )]);
-
-    let info = mock_info(&bob, &[]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(95u128))])]);
     let mut env = mock_env();
-    //set the block time 30 seconds from now.
-
     env.block.time = env.block.time.plus_seconds(31);
+    let res = unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        Uint128::new(15),
+        alice.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
 
-    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res = execute(
+    // only the owner sets the rules and funds the buffer
+    let priority = ExecuteMsg::UpdateUnbondPriority {
+        max_request: Some(Uint128::new(10)),
+    };
+    let res = execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        wdraw_unbonded_msg.clone(),
+        mock_info("bob", &[]),
+        priority.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner1", &[]),
+        priority,
+    )
+    .unwrap();
+    let fund = ExecuteMsg::FundUnbondBuffer {};
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner1", &[coin(12, "uusd")]),
+        fund.clone(),
     );
-    assert!(wdraw_unbonded_res.is_err());
     assert_eq!(
-        wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "Only uluna is added to the unbond buffer"
+        ))
     );
-
-    // trigger undelegation message
-    let res = execute_unbond(
+    execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_info("owner1", &[coin(12, "uluna")]),
+        fund,
     )
     .unwrap();
-    assert_eq!(2, res.messages.len());
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
-
-    //this query should be zero since the undelegated period is not passed
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(12, "uluna"))]);
+    let priority = |deps: Deps| -> UnbondPriorityResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::UnbondPriority {}).unwrap()).unwrap()
     };
-    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(0));
+    assert_eq!(
+        priority(deps.as_ref()),
+        UnbondPriorityResponse {
+            max_request: Some(Uint128::new(10)),
+            buffer: Uint128::new(12),
+        }
+    );
+
+    // the small request bypasses the unbonding batch, the large one waits for its release
+    let withdraw = ExecuteMsg::WithdrawUnbonded {};
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        withdraw.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: bob.clone(),
+            amount: vec![coin(5, "uluna")],
+        })]
+    );
+    assert!(res.attributes.contains(&attr("priority_amount", "5")));
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&alice, &[]),
+        withdraw.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "No withdrawable uluna assets are available yet"
+        ))
+    );
+    assert_eq!(priority(deps.as_ref()).buffer, Uint128::new(7));
 
+    // the release refills the buffer with the share it paid ahead
     env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(27, "uluna"))]);
+    let res = execute(deps.as_mut(), env, mock_info(&alice, &[]), withdraw).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: alice,
+            amount: vec![coin(15, "uluna")],
+        })]
+    );
+    assert_eq!(priority(deps.as_ref()).buffer, Uint128::new(12));
+    let liquidity: LiquidityResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Liquidity {}).unwrap()).unwrap();
+    assert_eq!(liquidity.released_unclaimed, Uint128::zero());
+}
 
-    // fabricate balance of the hub contract
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
+#[test]
+pub fn bonds_refill_the_unbond_buffer_to_its_target() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let target = ExecuteMsg::UpdateBufferTarget {
+        target: Some(BufferTarget {
+            target_rate: Decimal::percent(10),
+            skim_rate: Decimal::percent(10),
+        }),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        target.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), target).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"bob".to_string(), &Uint128::new(1000000))],
+    )]);
+
+    // the buffer takes a tenth of the bonds until it holds a tenth of the total bonded
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[coin(1000, "uluna")]),
+        bond.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address.clone(),
+            amount: coin(900, "uluna"),
+        })
+    );
+    assert!(res.attributes.contains(&attr("buffered", "100")));
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"bob".to_string(), &Uint128::new(1001000))],
+    )]);
+    set_delegation(&mut deps.querier, validator.clone(), 1000900, "uluna");
+
+    // the skimmed underlying is not taken for a slashing
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[coin(1000, "uluna")]),
+        bond,
+    )
+    .unwrap();
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(1002000));
+    assert_eq!(state.exchange_rate, Decimal::one());
+
+    let buffer: BufferTargetResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BufferTarget {}).unwrap()).unwrap();
+    assert_eq!(
+        buffer,
+        BufferTargetResponse {
+            target: Some(BufferTarget {
+                target_rate: Decimal::percent(10),
+                skim_rate: Decimal::percent(10),
+            }),
+            buffer: Uint128::new(200),
+            target_amount: Uint128::new(100200),
+            skimmed: Uint128::new(200),
+        }
+    );
+}
+
+#[test]
+pub fn slashing_counts_the_skimmed_underlying_paid_ahead() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    let owner_info = mock_info("owner1", &[]);
+    for msg in [
+        ExecuteMsg::UpdateBufferTarget {
+            target: Some(BufferTarget {
+                target_rate: Decimal::percent(10),
+                skim_rate: Decimal::percent(10),
+            }),
+        },
+        ExecuteMsg::UpdateUnbondPriority {
+            max_request: Some(Uint128::new(50)),
+        },
+    ] {
+        execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    }
+
+    // a tenth of the bond is skimmed into the buffer
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000u128))])]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(1000, "uluna")]),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator.clone(), 900, "uluna");
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(100, "uluna"))]);
+
+    // the buffer pays the skimmed share of the batch, it is not delegated
+    let alice = "alice".to_string();
+    let info = mock_info("token", &[]);
+    unbond_and_burn(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(990u128))])]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    let res = unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        Uint128::new(60),
+        alice.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: validator.address.clone(),
+            amount: coin(63, "uluna"),
+        })
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(930u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 837, "uluna");
+    let buffer = |deps: Deps| -> BufferTargetResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::BufferTarget {}).unwrap()).unwrap()
+    };
+    assert_eq!(buffer(deps.as_ref()).buffer, Uint128::new(93));
+    assert_eq!(buffer(deps.as_ref()).skimmed, Uint128::new(93));
+
+    // the small request is paid with skimmed underlying, lent to the batch until its release
+    let withdraw = ExecuteMsg::WithdrawUnbonded {};
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        withdraw.clone(),
+    )
+    .unwrap();
+    assert_eq!(buffer(deps.as_ref()).buffer, Uint128::new(83));
+    assert_eq!(buffer(deps.as_ref()).skimmed, Uint128::new(83));
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(90, "uluna"))]);
+
+    // only the slashed delegations lower the total bonded
+    set_delegation(&mut deps.querier, validator.clone(), 737, "uluna");
+    let check = CheckSlashing {};
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        check.clone(),
+    )
+    .unwrap();
+    let state = |deps: Deps| -> StateResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::State {}).unwrap()).unwrap()
+    };
+    assert_eq!(state(deps.as_ref()).total_bond_amount, Uint128::new(830));
+    assert_eq!(
+        state(deps.as_ref()).exchange_rate,
+        Decimal::from_ratio(830u128, 930u128)
+    );
+
+    // the release pays the batch with the undelegated and the kept share, and skims back
+    // what the buffer lent
+    env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(153, "uluna"))]);
+    let res = execute(deps.as_mut(), env.clone(), mock_info(&alice, &[]), withdraw).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: alice,
+            amount: vec![coin(60, "uluna")],
+        })]
+    );
+    assert_eq!(buffer(deps.as_ref()).buffer, Uint128::new(93));
+    assert_eq!(buffer(deps.as_ref()).skimmed, Uint128::new(93));
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(93, "uluna"))]);
+    execute(deps.as_mut(), env, owner_info, check).unwrap();
+    assert_eq!(state(deps.as_ref()).total_bond_amount, Uint128::new(830));
+}
+
+/// Covers slashing during the unbonded period and its effect on the finished amount.
+#[test]
+pub fn proper_withdraw_unbonded_respect_slashing() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let bond_amount = Uint128::new(10000);
+    let unbond_amount = Uint128::new(500);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let reward_contract = "reward".to_string();
+
+    init(
+        &mut deps,
+        owner,
+        reward_contract,
+        token_contract,
+        validator.address.clone(),
+    );
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+
+    let res = unbond_and_burn(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(0),
+        },
+    )]);
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    //set the block time 30 seconds from now.
+
+    env.block.time = env.block.time.plus_seconds(31);
+
+    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
+    let wdraw_unbonded_res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        wdraw_unbonded_msg.clone(),
+    );
+    assert!(wdraw_unbonded_res.is_err());
+    assert_eq!(
+        wdraw_unbonded_res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "No withdrawable uluna assets are available yet"
+        ))
+    );
+
+    // trigger undelegation message
+    let res = unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
+
+    //this query should be zero since the undelegated period is not passed
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(0));
+
+    env.block.time = env.block.time.plus_seconds(91);
+
+    // fabricate balance of the hub contract
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
             amount: Uint128::new(900),
         },
     )]);
@@ -1916,6 +3073,8 @@ pub fn proper_withdraw_unbonded_respect_slashing() {
     //first query AllUnbondedRequests
     let all_unbonded = UnbondRequests {
         address: bob.clone(),
+        start_after: None,
+        limit: None,
     };
     let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
     let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
@@ -1938,6 +3097,51 @@ pub fn proper_withdraw_unbonded_respect_slashing() {
 
     assert_eq!(success_res.messages.len(), 1);
 
+    // the batch is reconciled with the 900 received for the 1000 promised
+    assert_eq!(
+        success_res.events,
+        vec![
+            Event::new("unbond_batch_reconciled").add_attributes(vec![
+                attr("batch_id", "1"),
+                attr("expected", "1000"),
+                attr("released", "899"),
+                attr("adjustment", "slashed"),
+                attr("difference", "101"),
+                attr("previous_withdraw_rate", "1"),
+                attr("withdraw_rate", "0.899"),
+            ]),
+            Event::new("unbond_batch_withdrawn").add_attributes(vec![
+                attr("batch_id", "1"),
+                attr("requested", "1000"),
+                attr("applied_exchange_rate", "1"),
+                attr("withdraw_rate", "0.899"),
+                attr("amount", "899"),
+            ]),
+        ]
+    );
+    let proof: UnbondBatchProofResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondBatchProof { batch_id: 1 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        proof,
+        UnbondBatchProofResponse {
+            batch_id: 1,
+            requested: Uint128::new(1000),
+            applied_exchange_rate: Decimal::one(),
+            expected: Uint128::new(1000),
+            withdraw_rate: Decimal::from_ratio(899u128, 1000u128),
+            released: true,
+            paid: Uint128::new(899),
+            slashing_adjustment: Uint128::new(101),
+        }
+    );
+
     let sent_message = &success_res.messages[0].msg;
     match sent_message {
         CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
@@ -2006,7 +3210,7 @@ pub fn proper_withdraw_unbonded_respect_inactivity_slashing() {
 
     set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    let res = unbond_and_burn(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
     assert_eq!(1, res.messages.len());
 
     deps.querier
@@ -2041,11 +3245,13 @@ pub fn proper_withdraw_unbonded_respect_inactivity_slashing() {
     assert!(wdraw_unbonded_res.is_err());
     assert_eq!(
         wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+        ContractError::Std(StdError::generic_err(
+            "No withdrawable uluna assets are available yet"
+        ))
     );
 
     // trigger undelegation message
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -2095,6 +3301,8 @@ pub fn proper_withdraw_unbonded_respect_inactivity_slashing() {
     //first query AllUnbondedRequests
     let all_unbonded = UnbondRequests {
         address: bob.clone(),
+        start_after: None,
+        limit: None,
     };
     let query_unbonded = query(deps.as_ref(), env.clone(), all_unbonded).unwrap();
     let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
@@ -2194,7 +3402,7 @@ pub fn proper_withdraw_unbond_with_dummies() {
         "uluna",
     );
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    let res = unbond_and_burn(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
     assert_eq!(1, res.messages.len());
 
     deps.querier
@@ -2214,7 +3422,7 @@ pub fn proper_withdraw_unbond_with_dummies() {
 
     env.block.time = env.block.time.plus_seconds(31);
     // trigger undelegation message
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -2234,7 +3442,7 @@ pub fn proper_withdraw_unbond_with_dummies() {
         "uluna",
     );
 
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -2249,7 +3457,7 @@ pub fn proper_withdraw_unbond_with_dummies() {
 
     env.block.time = env.block.time.plus_seconds(31);
 
-    let res = execute_unbond(
+    let res = unbond_and_burn(
         deps.as_mut(),
         env.clone(),
         info.clone(),
@@ -2326,6 +3534,7 @@ pub fn test_update_params() {
         unbonding_period: None,
         peg_recovery_fee: None,
         er_threshold: None,
+        unbond_fee_bps: None,
     };
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
@@ -2346,7 +3555,7 @@ pub fn test_update_params() {
         invalid_info,
         update_prams.clone(),
     );
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
     let creator_info = mock_info("owner1", &[]);
     let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
     assert_eq!(res.messages.len(), 0);
@@ -2366,6 +3575,7 @@ pub fn test_update_params() {
         unbonding_period: Some(3),
         peg_recovery_fee: Some(Decimal::one()),
         er_threshold: Some(Decimal::zero()),
+        unbond_fee_bps: None,
     };
 
     //the result must be 1
@@ -2383,38 +3593,109 @@ pub fn test_update_params() {
     assert_eq!(params.reward_denom, "uusd");
 }
 
-/// Covers if peg recovery is applied (in "bond", "unbond",
-/// and "withdraw_unbonded" messages) in case of a slashing event
 #[test]
-pub fn proper_recovery_fee() {
+pub fn config_changes_are_kept_in_the_history() {
     let mut deps = dependencies(&[]);
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
-
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: None,
-        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
-        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
-    };
-    let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-    let reward_contract = "reward".to_string();
-
-    let bond_amount = Uint128::new(1000000u128);
-    let unbond_amount = Uint128::new(100000u128);
-
     init(
         &mut deps,
-        owner,
-        reward_contract,
-        token_contract.clone(),
-        validator.address.clone(),
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
     );
 
-    let creator_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
+    let mut env = mock_env();
+    env.block.height += 100;
+    let update_params = UpdateParams {
+        epoch_period: Some(20),
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        unbond_fee_bps: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner1", &[]),
+        update_params,
+    )
+    .unwrap();
+    // the whole live set is emitted, the registration of init is version 1
+    assert!(res.attributes.contains(&attr("config_version", "2")));
+    assert!(res.attributes.contains(&attr("epoch_period", "20")));
+    assert!(res.attributes.contains(&attr("unbonding_period", "2")));
+    assert!(res.attributes.contains(&attr("token_contract", "token")));
+
+    let history: ConfigHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ConfigHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(history.versions.len(), 2);
+    assert_eq!(history.versions[0].config.token_contract, None);
+    let replaced = &history.versions[1];
+    assert_eq!(replaced.version, 1);
+    assert_eq!(replaced.replaced_height, env.block.height);
+    assert_eq!(replaced.config.token_contract, Some("token".to_string()));
+    assert_eq!(replaced.params.epoch_period, 30);
+
+    let history: ConfigHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ConfigHistory {
+                start_after: Some(0),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(history.versions, vec![replaced.clone()]);
+}
+
+/// Covers if peg recovery is applied (in "bond", "unbond",
+/// and "withdraw_unbonded" messages) in case of a slashing event
+#[test]
+pub fn proper_recovery_fee() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
+        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
+        unbond_fee_bps: None,
+    };
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let reward_contract = "reward".to_string();
+
+    let bond_amount = Uint128::new(1000000u128);
+    let unbond_amount = Uint128::new(100000u128);
+
+    init(
+        &mut deps,
+        owner,
+        reward_contract,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
 
     let get_params = QueryMsg::Parameters {};
     let parmas: Parameters =
@@ -2445,7 +3726,13 @@ pub fn proper_recovery_fee() {
     set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
 
     let report_slashing = CheckSlashing {};
-    let res = execute(deps.as_mut(), mock_env(), info, report_slashing).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        report_slashing,
+    )
+    .unwrap();
     assert_eq!(0, res.messages.len());
 
     let ex_rate = QueryMsg::State {};
@@ -2492,6 +3779,10 @@ pub fn proper_recovery_fee() {
         ),
         _ => panic!("Unexpected message: {:?}", mint_msg),
     }
+    let fees: FeesCollectedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::FeesCollected {}).unwrap())
+            .unwrap();
+    assert_eq!(fees.peg_recovery_fees, peg_fee);
 
     // check unbond message
     let unbond = Unbond {};
@@ -2512,6 +3803,7 @@ pub fn proper_recovery_fee() {
         receive,
     )
     .unwrap();
+    burn_unbonded(deps.as_mut());
     assert_eq!(1, res.messages.len());
 
     //check current batch
@@ -2541,6 +3833,7 @@ pub fn proper_recovery_fee() {
         receive,
     )
     .unwrap();
+    burn_unbonded(deps.as_mut());
     assert_eq!(2, res.messages.len());
 
     let ex_rate = QueryMsg::State {};
@@ -2612,6 +3905,221 @@ pub fn proper_recovery_fee() {
 }
 
 /// Covers if the storage affected by update_config are updated properly
+#[test]
+pub fn proper_price_guard() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_params = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::from_ratio(1u128, 1000u128)),
+        er_threshold: Some(Decimal::from_ratio(99u128, 100u128)),
+        unbond_fee_bps: None,
+    };
+    let owner_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_params).unwrap();
+
+    // only the owner can configure the oracle
+    let update_guard = ExecuteMsg::UpdatePriceGuard {
+        oracle_contract: Some("oracle".to_string()),
+        max_deviation: Decimal::percent(5),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        update_guard.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdatePriceGuard {
+            oracle_contract: Some("oracle".to_string()),
+            max_deviation: Decimal::one(),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "max_deviation must be between 0 and 1"
+        ))
+    );
+
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_guard).unwrap();
+    let res: PriceGuardResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PriceGuard {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        PriceGuardResponse {
+            oracle_contract: Some("oracle".to_string()),
+            max_deviation: Some(Decimal::percent(5)),
+        }
+    );
+
+    // no peg recovery happens above the threshold, the oracle is not asked
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    execute(deps.as_mut(), mock_env(), info.clone(), bond_msg.clone()).unwrap();
+
+    // a slashing moves the exchange rate to 0.9
+    set_delegation(&mut deps.querier, validator, 900000, "uluna");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+
+    // the exchange rate is more than 5% from the market price
+    deps.querier
+        .with_oracle_price("token", "uluna", Decimal::from_ratio(95u128, 100u128));
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::PriceDeviation {
+            implied: Decimal::from_str("0.9").unwrap(),
+            oracle: Decimal::from_str("0.95").unwrap()
+        }
+    );
+    let unbond = Receive(Cw20ReceiveMsg {
+        sender: bob,
+        amount: Uint128::new(10),
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("token", &[]), unbond);
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::PriceDeviation {
+            implied: Decimal::from_str("0.9").unwrap(),
+            oracle: Decimal::from_str("0.95").unwrap()
+        }
+    );
+
+    deps.querier
+        .with_oracle_price("token", "uluna", Decimal::from_ratio(92u128, 100u128));
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // removing the oracle disables the check
+    deps.querier
+        .with_oracle_price("token", "uluna", Decimal::from_ratio(95u128, 100u128));
+    let remove_guard = ExecuteMsg::UpdatePriceGuard {
+        oracle_contract: None,
+        max_deviation: Decimal::zero(),
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, remove_guard).unwrap();
+    execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+}
+
+#[test]
+pub fn proper_ibc_transfer() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let transfer = Receive(Cw20ReceiveMsg {
+        sender: "bob".to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&Cw20HookMsg::IbcTransfer {
+            channel: "channel-1".to_string(),
+            remote_address: "osmo1bob".to_string(),
+            timeout: None,
+        })
+        .unwrap(),
+    });
+    let token_info = mock_info("token", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info.clone(),
+        transfer.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("No IBC bridge is configured"))
+    );
+
+    // only the owner can configure the bridge
+    let update_bridge = ExecuteMsg::UpdateIbcBridge {
+        bridge_contract: Some("bridge".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        update_bridge.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update_bridge,
+    )
+    .unwrap();
+    let res: IbcBridgeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IbcBridge {}).unwrap()).unwrap();
+    assert_eq!(res.bridge_contract, Some("bridge".to_string()));
+
+    // only the token contract can send the hook
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bridge", &[]),
+        transfer.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the received bAsset is sent on to the bridge
+    let res = execute(deps.as_mut(), mock_env(), token_info, transfer).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "bridge".to_string(),
+                amount: Uint128::new(1000),
+                msg: to_binary(&Ics20TransferMsg {
+                    channel: "channel-1".to_string(),
+                    remote_address: "osmo1bob".to_string(),
+                    timeout: None,
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    assert_eq!(res.attributes[0], attr("action", "ibc_transfer"));
+}
+
 #[test]
 pub fn proper_update_config() {
     let mut deps = dependencies(&[]);
@@ -2656,7 +4164,7 @@ pub fn proper_update_config() {
     };
     let info = mock_info(&invalid_owner, &[]);
     let res = execute(deps.as_mut(), mock_env(), info, update_config);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     // change the owner
     let update_config = UpdateConfig {
@@ -2679,6 +4187,7 @@ pub fn proper_update_config() {
         unbonding_period: None,
         peg_recovery_fee: None,
         er_threshold: None,
+        unbond_fee_bps: None,
     };
 
     let new_owner_info = mock_info(&new_owner, &[]);
@@ -2691,11 +4200,12 @@ pub fn proper_update_config() {
         unbonding_period: None,
         peg_recovery_fee: None,
         er_threshold: None,
+        unbond_fee_bps: None,
     };
 
     let new_owner_info = mock_info(&owner, &[]);
     let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_prams);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
     let update_config = UpdateConfig {
         owner: None,
@@ -2705,7 +4215,7 @@ pub fn proper_update_config() {
     };
     let new_owner_info = mock_info(&new_owner, &[]);
     let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap();
-    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages.len(), 2);
 
     let msg: SubMsg = SubMsg::new(CosmosMsg::Distribution(
         DistributionMsg::SetWithdrawAddress {
@@ -2714,6 +4224,19 @@ pub fn proper_update_config() {
     ));
     assert_eq!(msg, res.messages[0]);
 
+    // the new reward contract is told which token updates the balances
+    let register_token = |token: &str| {
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "new reward".to_string(),
+            msg: to_binary(&RewardExecuteMsg::RegisterTokenContract {
+                token_contract: token.to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+    assert_eq!(register_token("token"), res.messages[1]);
+
     let config = QueryMsg::Config {};
     let config_query: ConfigResponse =
         from_binary(&query(deps.as_ref(), mock_env(), config).unwrap()).unwrap();
@@ -2730,7 +4253,7 @@ pub fn proper_update_config() {
     };
     let new_owner_info = mock_info(&new_owner, &[]);
     let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap();
-    assert_eq!(res.messages.len(), 0);
+    assert_eq!(res.messages, vec![register_token("new token")]);
 
     let config = QueryMsg::Config {};
     let config_query: ConfigResponse =
@@ -2789,9 +4312,7 @@ fn proper_claim_airdrop() {
     let claim_msg = ExecuteMsg::ClaimAirdrop {
         airdrop_token_contract: "airdrop_token".to_string(),
         airdrop_contract: "MIR_contract".to_string(),
-        airdrop_swap_contract: "airdrop_swap".to_string(),
         claim_msg: to_binary(&MIRMsg::MIRClaim {}).unwrap(),
-        swap_msg: Default::default(),
     };
 
     //invalid sender
@@ -2799,7 +4320,10 @@ fn proper_claim_airdrop() {
     let res = execute(deps.as_mut(), mock_env(), info, claim_msg.clone()).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err(format!("Sender must be {}", &airdrop_registry))
+        ContractError::Std(StdError::generic_err(format!(
+            "Sender must be {}",
+            &airdrop_registry
+        )))
     );
 
     let valid_info = mock_info(&airdrop_registry, &[]);
@@ -2818,10 +4342,8 @@ fn proper_claim_airdrop() {
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-            msg: to_binary(&ExecuteMsg::SwapHook {
+            msg: to_binary(&ExecuteMsg::ForwardAirdropHook {
                 airdrop_token_contract: "airdrop_token".to_string(),
-                airdrop_swap_contract: "airdrop_swap".to_string(),
-                swap_msg: Default::default()
             })
             .unwrap(),
             funds: vec![]
@@ -2830,7 +4352,7 @@ fn proper_claim_airdrop() {
 }
 
 #[test]
-fn proper_swap_hook() {
+fn proper_forward_airdrop_hook() {
     let mut deps = dependencies(&[]);
 
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
@@ -2843,27 +4365,20 @@ fn proper_swap_hook() {
     init(
         &mut deps,
         owner.clone(),
-        reward_contract.clone(),
+        reward_contract,
         token_contract,
         validator.address,
     );
 
-    let swap_msg = ExecuteMsg::SwapHook {
+    let forward_msg = ExecuteMsg::ForwardAirdropHook {
         airdrop_token_contract: "airdrop_token".to_string(),
-        airdrop_swap_contract: "swap_contract".to_string(),
-        swap_msg: to_binary(&PairHandleMsg::Swap {
-            belief_price: None,
-            max_spread: None,
-            to: Some(reward_contract.clone()),
-        })
-        .unwrap(),
     };
 
     //invalid sender
     let info = mock_info(&owner, &[]);
     let env = mock_env();
-    let res = execute(deps.as_mut(), mock_env(), info, swap_msg.clone()).unwrap_err();
-    assert_eq!(res, StdError::generic_err("unauthorized"));
+    let res = execute(deps.as_mut(), mock_env(), info, forward_msg.clone()).unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
 
     // no balance for hub
     let contract_info = mock_info(env.contract.address.as_str(), &[]);
@@ -2871,7 +4386,7 @@ fn proper_swap_hook() {
         deps.as_mut(),
         mock_env(),
         contract_info.clone(),
-        swap_msg.clone(),
+        forward_msg.clone(),
     );
 
     assert!(res.is_err());
@@ -2881,21 +4396,15 @@ fn proper_swap_hook() {
         &[(&env.contract.address.to_string(), &Uint128::new(1000))],
     )]);
 
-    let res = execute(deps.as_mut(), mock_env(), contract_info, swap_msg).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), contract_info, forward_msg).unwrap();
     assert_eq!(res.messages.len(), 1);
     assert_eq!(
         res.messages[0],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: "airdrop_token".to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Send {
-                contract: "swap_contract".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "airdrop_registry".to_string(),
                 amount: Uint128::new(1000),
-                msg: to_binary(&PairHandleMsg::Swap {
-                    belief_price: None,
-                    max_spread: None,
-                    to: Some(reward_contract),
-                })
-                .unwrap()
             })
             .unwrap(),
             funds: vec![],
@@ -2966,54 +4475,3191 @@ fn proper_update_global_index_with_airdrop() {
 
     assert_eq!(
         res.messages[0],
-        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "airdrop_registry".to_string(),
-            msg: binary_msg,
-            funds: vec![],
-        }))
+        SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "airdrop_registry".to_string(),
+                msg: binary_msg,
+                funds: vec![],
+            }),
+            1
+        )
     );
 
     assert_eq!(
         res.messages[1],
-        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "airdrop_registry".to_string(),
-            msg: binary_msg2,
-            funds: vec![],
-        }))
+        SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "airdrop_registry".to_string(),
+                msg: binary_msg2,
+                funds: vec![],
+            }),
+            2
+        )
     );
 }
 
-fn set_delegation(querier: &mut WasmMockQuerier, validator: Validator, amount: u128, denom: &str) {
-    querier.update_staking(
-        "uluna",
-        &[validator.clone()],
-        &[sample_delegation(validator.address, coin(amount, denom))],
+#[test]
+fn proper_migrate() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
     );
-}
 
-fn set_delegation_query(
-    querier: &mut WasmMockQuerier,
-    delegate: &[FullDelegation],
-    validators: &[Validator],
-) {
-    querier.update_staking("uluna", validators, delegate);
-}
+    let contract_info = get_contract_version(&deps.storage).unwrap();
+    assert_eq!(contract_info.contract, "crates.io:anchor-basset-hub");
+    assert_eq!(contract_info.version, env!("CARGO_PKG_VERSION"));
 
-fn sample_delegation(addr: String, amount: Coin) -> FullDelegation {
-    let can_redelegate = amount.clone();
-    let accumulated_rewards = coin(0, &amount.denom);
-    FullDelegation {
-        validator: addr,
-        delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
-        amount,
-        can_redelegate,
-        accumulated_rewards: vec![accumulated_rewards],
-    }
+    // a deployment that predates state versioning refuses to run until migrated
+    deps.storage.remove(b"state_version");
+    let update_params = UpdateParams {
+        epoch_period: Some(20),
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        unbond_fee_bps: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        update_params.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::StateVersionMismatch {
+            expected: 1,
+            found: 0
+        }
+    );
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("from_state_version", "0"),
+            attr("to_state_version", "1"),
+        ]
+    );
+
+    execute(deps.as_mut(), mock_env(), owner_info, update_params).unwrap();
+
+    // the code of the hub does not migrate another contract
+    set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.2.0").unwrap();
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::ContractNameMismatch {
+            expected: "crates.io:anchor-basset-hub".to_string(),
+            found: "crates.io:cw20-base".to_string(),
+        }
+    );
 }
 
-// sample MIR claim msg
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-pub enum MIRMsg {
-    MIRClaim {},
+#[test]
+fn proper_timelocked_changes() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    let owner_info = mock_info(&owner, &[]);
+
+    // only the owner can propose
+    let set_period = ProposePendingChange {
+        change: TimelockedChange::UpdateTimelockPeriod {
+            timelock_period: 100,
+        },
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("invalid", &[]),
+        set_period.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // without a timelock period the change can be applied right away
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), set_period).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ApplyPendingChange { id: 0 },
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("action", "update_timelock_period")));
+
+    // sensitive changes can no longer be applied directly
+    let update_fee = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::percent(1)),
+        er_threshold: None,
+        unbond_fee_bps: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), update_fee);
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::DeregisterValidator {
+            validator: validator.address,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        UpdateConfig {
+            owner: Some("owner2".to_string()),
+            reward_contract: None,
+            token_contract: None,
+            airdrop_registry_contract: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateKeeperIncentive { incentive: None },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::RegisterValidator {
+            validator: DEFAULT_VALIDATOR2.to_string(),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::ImportValidators {
+            max_commission: Decimal::one(),
+            limit: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+
+    let update_epoch = UpdateParams {
+        epoch_period: Some(20),
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        unbond_fee_bps: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_epoch).unwrap();
+
+    let propose = ProposePendingChange {
+        change: TimelockedChange::UpdateParams {
+            unbonding_period: Some(5),
+            peg_recovery_fee: None,
+            er_threshold: None,
+            unbond_fee_bps: None,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), propose).unwrap();
+
+    let pending: PendingChangesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PendingChanges {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let effective_at = mock_env().block.time.seconds() + 100;
+    assert_eq!(pending.timelock_period, 100);
+    assert_eq!(pending.changes.len(), 1);
+    assert_eq!(pending.changes[0].id, 1);
+    assert_eq!(pending.changes[0].effective_at, effective_at);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ApplyPendingChange { id: 1 },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::TimelockNotExpired {
+            id: 1,
+            effective_at
+        }
+    );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ApplyPendingChange { id: 1 },
+    )
+    .unwrap();
+
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.epoch_period, 20);
+    assert_eq!(params.unbonding_period, 5);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        CancelPendingChange { id: 1 },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::PendingChangeNotFound { id: 1 }
+    );
+
+    // a validator only joins the whitelist once the period has passed
+    let propose = ProposePendingChange {
+        change: TimelockedChange::RegisterValidator {
+            validator: DEFAULT_VALIDATOR2.to_string(),
+        },
+    };
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), propose).unwrap();
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ApplyPendingChange { id: 2 },
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("action", "register_validator")));
+    let validators: WhitelistedValidatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::WhitelistedValidators {},
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(validators
+        .validators
+        .contains(&DEFAULT_VALIDATOR2.to_string()));
+
+    // the owner and the contracts of the config move with the timelock too
+    let propose = ProposePendingChange {
+        change: TimelockedChange::UpdateConfig {
+            owner: Some("owner2".to_string()),
+            reward_contract: Some("new reward".to_string()),
+            token_contract: None,
+            airdrop_registry_contract: None,
+        },
+    };
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), propose).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ApplyPendingChange { id: 3 },
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        ContractError::TimelockNotExpired { id: 3, .. }
+    ));
+
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        ApplyPendingChange { id: 3 },
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("action", "update_config")));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Distribution(
+            DistributionMsg::SetWithdrawAddress {
+                address: "new reward".to_string(),
+            }
+        ))
+    );
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.owner, "owner2");
+    assert_eq!(config.reward_contract.unwrap(), "new reward");
+}
+
+#[test]
+fn proper_circuit_breaker() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+
+    let update_breaker = ExecuteMsg::UpdateCircuitBreaker {
+        max_exchange_rate_change: Some(Decimal::percent(5)),
+        guardian: Some("guardian".to_string()),
+        freeze_transfers: false,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("invalid", &[]),
+        update_breaker.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_breaker,
+    )
+    .unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    let rate_before = state.exchange_rate;
+
+    // the slashing moves the exchange rate beyond the allowed 5%
+    set_delegation(&mut deps.querier, validator.clone(), 900, "uluna");
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("circuit_breaker_tripped").add_attributes(vec![
+            attr("previous_exchange_rate", rate_before.to_string()),
+            attr("exchange_rate", "0.9"),
+        ])]
+    );
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, rate_before);
+    let breaker: CircuitBreakerResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CircuitBreaker {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        breaker,
+        CircuitBreakerResponse {
+            max_exchange_rate_change: Some(Decimal::percent(5)),
+            guardian: Some("guardian".to_string()),
+            paused: true,
+            freeze_transfers: false,
+        }
+    );
+
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address,
+    };
+    let bond_info = mock_info(&addr1, &[coin(1000, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), bond_info.clone(), bond.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Paused {});
+
+    // only the guardian can resume
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), Resume {});
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        Resume {},
+    )
+    .unwrap();
+
+    // the anomaly is still there, the bond is refunded and the contract paused again
+    let res = execute(deps.as_mut(), mock_env(), bond_info, bond).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr1.clone(),
+            amount: vec![coin(1000, "uluna")],
+        }))]
+    );
+    assert_eq!(res.events.len(), 1);
+
+    // the slashing is genuine, the owner disables the breaker before resuming
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::UpdateCircuitBreaker {
+            max_exchange_rate_change: None,
+            guardian: Some("guardian".to_string()),
+            freeze_transfers: false,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        Resume {},
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert!(res.events.is_empty());
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate.to_string(), "0.9");
+}
+
+#[test]
+fn proper_pause() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // the owner reviews the pause until a guardian is set
+    execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), Pause {}).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), Resume {}).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::UpdateCircuitBreaker {
+            max_exchange_rate_change: None,
+            guardian: Some("guardian".to_string()),
+            freeze_transfers: true,
+        },
+    )
+    .unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), Pause {});
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        Pause {},
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("action", "pause")]);
+
+    let breaker: CircuitBreakerResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CircuitBreaker {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        breaker,
+        CircuitBreakerResponse {
+            max_exchange_rate_change: None,
+            guardian: Some("guardian".to_string()),
+            paused: true,
+            freeze_transfers: true,
+        }
+    );
+
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr1000", &[coin(10, "uluna")]),
+        bond,
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Paused {});
+}
+
+#[test]
+fn proper_execute_emergency() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let redelegate = StakingMsg::Redelegate {
+        src_validator: validator.address.clone(),
+        dst_validator: validator2.address.clone(),
+        amount: coin(10, "uluna"),
+    };
+    let emergency = ExecuteMsg::ExecuteEmergency {
+        msgs: vec![redelegate.clone()],
+    };
+
+    // only the owner, and only while paused
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr1000", &[]),
+        emergency.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        emergency.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::NotPaused {});
+
+    CIRCUIT_BREAKER
+        .save(
+            deps.as_mut().storage,
+            &CircuitBreaker {
+                max_exchange_rate_change: Some(Decimal::percent(5)),
+                guardian: None,
+                paused: true,
+                freeze_transfers: false,
+            },
+        )
+        .unwrap();
+
+    // the delegations can only move to whitelisted validators
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        emergency.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The chosen validator is currently not supported"
+        ))
+    );
+
+    do_register_validator(deps.as_mut(), validator2.clone());
+    let undelegate = StakingMsg::Undelegate {
+        validator: validator.address,
+        amount: coin(5, "uluna"),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::ExecuteEmergency {
+            msgs: vec![redelegate.clone(), undelegate.clone()],
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(redelegate)),
+            SubMsg::new(CosmosMsg::Staking(undelegate)),
+        ]
+    );
+}
+
+#[test]
+fn proper_access_control() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+    let addr2 = "addr2000".to_string();
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // the initial bond is backed by a supply, so that the bonds after the first mint bAsset
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1_000_000u128))],
+    )]);
+    // bonding is open by default
+    let res: AccessControlResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccessControl {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        AccessControlResponse {
+            mode: AccessMode::Open,
+            addresses: vec![],
+        }
+    );
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+
+    // only the owner manages the access
+    let update_mode = ExecuteMsg::UpdateAccessMode {
+        mode: AccessMode::Allowlist,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        update_mode.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let update_list = ExecuteMsg::UpdateAccessList {
+        add: Some(vec![addr1.clone()]),
+        remove: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        update_list.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_mode).unwrap();
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_list).unwrap();
+
+    // the allowlist only admits the listed addresses
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr2, &[coin(10, "uluna")]),
+        bond.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::BondNotAllowed {
+            address: "addr2000".to_string()
+        }
+    );
+
+    // the denylist refuses them
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateAccessMode {
+            mode: AccessMode::Denylist,
+        },
+    )
+    .unwrap();
+    do_bond(
+        deps.as_mut(),
+        addr2.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[coin(10, "uluna")]),
+        bond,
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::BondNotAllowed {
+            address: "addr1000".to_string()
+        }
+    );
+
+    let res: AccessControlResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccessControl {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        AccessControlResponse {
+            mode: AccessMode::Denylist,
+            addresses: vec![addr1.clone()],
+        }
+    );
+
+    // removed addresses can bond again
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::UpdateAccessList {
+            add: None,
+            remove: Some(vec![addr1.clone()]),
+        },
+    )
+    .unwrap();
+    do_bond(deps.as_mut(), addr1, Uint128::new(10), validator);
+}
+
+#[test]
+fn proper_export_import_state() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(deps.as_mut(), addr1, Uint128::new(1000), validator.clone());
+
+    // the fresh instance is owned by the same owner
+    let mut restored = dependencies(&[]);
+    set_validator_mock(&mut restored.querier);
+    init(
+        &mut restored,
+        owner.clone(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let mut start_after = None;
+    loop {
+        let export = QueryMsg::ExportState {
+            start_after,
+            limit: Some(3),
+        };
+        let page: ExportStateResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), export).unwrap()).unwrap();
+        if page.entries.is_empty() {
+            break;
+        }
+        start_after = page.entries.last().map(|entry| entry.key.clone());
+
+        let import = ExecuteMsg::ImportState {
+            entries: page.entries,
+        };
+        let res = execute(
+            restored.as_mut(),
+            mock_env(),
+            mock_info("addr1000", &[]),
+            import.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let res = execute(
+            restored.as_mut(),
+            mock_env(),
+            mock_info(&owner, &[]),
+            import,
+        )
+        .unwrap();
+        assert_eq!(res.attributes[0], attr("action", "import_state"));
+    }
+
+    // the contract cannot be used until the import is finished
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::UpdateGlobalIndex {
+            airdrop_hooks: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::ImportInProgress {});
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info("addr1000", &[]),
+        ExecuteMsg::FinishImport {},
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::FinishImport {},
+    )
+    .unwrap();
+
+    let all: Vec<_> = deps.storage.range(None, None, Order::Ascending).collect();
+    let restored_all: Vec<_> = restored
+        .storage
+        .range(None, None, Order::Ascending)
+        .collect();
+    assert_eq!(all, restored_all);
+
+    let state = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let restored_state = query(restored.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    assert_eq!(state, restored_state);
+
+    // the import is closed for good, and a bonded hub never imports
+    let import = ExecuteMsg::ImportState { entries: vec![] };
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        import.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::ImportClosed {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), import);
+    assert_eq!(res.unwrap_err(), ContractError::ImportClosed {});
+}
+
+/// The delegation of the hub to validator, next to the validators of set_validator_mock
+fn set_delegation(querier: &mut WasmMockQuerier, validator: Validator, amount: u128, denom: &str) {
+    let mut validators: Vec<Validator> =
+        [DEFAULT_VALIDATOR, DEFAULT_VALIDATOR2, DEFAULT_VALIDATOR3]
+            .iter()
+            .filter(|address| **address != validator.address)
+            .map(|address| sample_validator(address.to_string()))
+            .collect();
+    validators.push(validator.clone());
+    querier.update_staking(
+        "uluna",
+        &validators,
+        &[sample_delegation(
+            validator.address.clone(),
+            coin(amount, denom),
+        )],
+    );
+}
+
+fn set_delegation_query(
+    querier: &mut WasmMockQuerier,
+    delegate: &[FullDelegation],
+    validators: &[Validator],
+) {
+    querier.update_staking("uluna", validators, delegate);
+}
+
+fn sample_delegation(addr: String, amount: Coin) -> FullDelegation {
+    let can_redelegate = amount.clone();
+    let accumulated_rewards = coin(0, &amount.denom);
+    FullDelegation {
+        validator: addr,
+        delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        amount,
+        can_redelegate,
+        accumulated_rewards: vec![accumulated_rewards],
+    }
+}
+
+// sample MIR claim msg
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MIRMsg {
+    MIRClaim {},
+}
+
+/// Covers the external mode, where the hub keeps the underlying instead of delegating it
+/// and rewards are topped up by whoever earns them.
+#[test]
+pub fn proper_external_mode() {
+    let mut deps = dependencies(&[]);
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        reward_denom: "uusd".to_string(),
+        validator: String::new(),
+        mode: HubMode::External,
+    };
+    let owner_info = mock_info("owner1", &[coin(1000, "uluna")]);
+    let res = instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    assert!(res.messages.is_empty());
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        UpdateConfig {
+            owner: None,
+            reward_contract: Some("reward".to_string()),
+            token_contract: Some("token".to_string()),
+            airdrop_registry_contract: None,
+        },
+    )
+    .unwrap();
+    let res: ModeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Mode {}).unwrap()).unwrap();
+    assert_eq!(res.mode, HubMode::External);
+
+    // bonding only mints, whatever the validator
+    let bob = "bob".to_string();
+    let bond = ExecuteMsg::Bond {
+        validator: "any".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(1000, "uluna")]),
+        bond,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Mint {
+                recipient: bob.clone(),
+                amount: Uint128::new(1000),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000))])]);
+
+    // the top up is forwarded to the reward contract, without the underlying
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bridge", &[coin(10, "uluna")]),
+        ExecuteMsg::RewardTopUp {},
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("No rewards are provided"))
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bridge", &[coin(500, "uusd")]),
+        ExecuteMsg::RewardTopUp {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "reward".to_string(),
+            amount: vec![coin(500, "uusd")],
+        })]
+    );
+
+    // the tax is deducted from the top ups until the owner flags the chain as tax free
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::new(100))],
+    );
+    let top_up = |deps: &mut OwnedDeps<_, _, _>| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bridge", &[coin(505, "uusd")]),
+            ExecuteMsg::RewardTopUp {},
+        )
+        .unwrap()
+        .messages
+    };
+    assert_eq!(
+        top_up(&mut deps),
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "reward".to_string(),
+            amount: vec![coin(500, "uusd")],
+        })]
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::UpdateTaxMode { tax_free: true },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::UpdateTaxMode { tax_free: true },
+    )
+    .unwrap();
+    let res: TaxModeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TaxMode {}).unwrap()).unwrap();
+    assert!(res.tax_free);
+    assert_eq!(
+        top_up(&mut deps),
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "reward".to_string(),
+            amount: vec![coin(505, "uusd")],
+        })]
+    );
+
+    // there is nothing to withdraw, the index is updated from the top ups
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::UpdateGlobalIndex {
+            airdrop_hooks: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "reward".to_string(),
+                msg: to_binary(&SwapToRewardDenom {}).unwrap(),
+                funds: vec![],
+            }),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "reward".to_string(),
+                msg: to_binary(&UpdateGlobalIndex {}).unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+
+    // the unbonded batch is released from the kept balance without undelegating
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        env.clone(),
+        mock_info("token", &[]),
+        Uint128::new(500),
+    );
+    assert_eq!(res.messages.len(), 1);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(500))])]);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(2000, "uluna"))]);
+
+    env.block.time = env.block.time.plus_seconds(2);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: bob,
+            amount: vec![coin(1000, "uluna")],
+        })]
+    );
+}
+
+/// Covers that rewards are only topped up in the external mode.
+#[test]
+pub fn reward_top_up_requires_the_external_mode() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bridge", &[coin(500, "uusd")]),
+        ExecuteMsg::RewardTopUp {},
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "Rewards are only topped up in the external mode"
+        ))
+    );
+}
+
+/// Covers that signals are weighted by the snapshot balance, a new signal replaces the
+/// previous one, and the hub casts the tally as a weighted vote until the end time.
+#[test]
+pub fn proper_governance_voting() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+    let env = mock_env();
+    let end_time = env.block.time.seconds() + 100;
+    let snapshot_height = env.block.height - 5;
+
+    // only the owner registers past snapshots
+    let register = |snapshot_height| ExecuteMsg::RegisterProposal {
+        proposal_id: 7,
+        snapshot_height,
+        end_time,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        register(snapshot_height),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner1", &[]),
+        register(env.block.height),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The snapshot height must be before the current height"
+        ))
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner1", &[]),
+        register(snapshot_height),
+    )
+    .unwrap();
+
+    deps.querier
+        .with_snapshot_balance("alice", snapshot_height, Uint128::new(300));
+    deps.querier
+        .with_snapshot_balance("bob", snapshot_height, Uint128::new(100));
+    let signal = |vote| ExecuteMsg::SignalVote {
+        proposal_id: 7,
+        vote,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("carol", &[]),
+        signal(VoteOption::Yes),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "No bAsset was held at the snapshot height"
+        ))
+    );
+    let cast = ExecuteMsg::CastVote { proposal_id: 7 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("carol", &[]),
+        cast.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("No votes are signalled"))
+    );
+
+    for (voter, vote) in [
+        ("alice", VoteOption::Yes),
+        ("bob", VoteOption::No),
+        ("bob", VoteOption::Abstain),
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(voter, &[]),
+            signal(vote),
+        )
+        .unwrap();
+    }
+    let res: ProposalResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Proposal { proposal_id: 7 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.tally,
+        Tally {
+            yes: Uint128::new(300),
+            no: Uint128::zero(),
+            abstain: Uint128::new(100),
+            no_with_veto: Uint128::zero(),
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("carol", &[]),
+        cast.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Stargate {
+            type_url: "/cosmos.gov.v1beta1.MsgVoteWeighted".to_string(),
+            value: encode_msg_vote_weighted(
+                7,
+                MOCK_CONTRACT_ADDR,
+                &[
+                    (VoteOption::Yes, Decimal::percent(75)),
+                    (VoteOption::Abstain, Decimal::percent(25)),
+                ],
+            ),
+        })]
+    );
+
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("alice", &[]),
+        signal(VoteOption::No),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("Voting on proposal 7 has ended"))
+    );
+}
+
+#[test]
+pub fn proper_run_operations() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3.clone());
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(10, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(300, "uluna")),
+            sample_delegation(validator3.address.clone(), coin(200, "uluna")),
+        ],
+        &[validator.clone(), validator2.clone(), validator3.clone()],
+    );
+
+    let rebalance = ExecuteMsg::RunOperations {
+        ops: vec![Op::Rebalance {}],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        rebalance.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // only the owner manages the operators
+    let update = ExecuteMsg::UpdateOperators {
+        kind: OperationKind::Rebalance,
+        add: Some(vec!["keeper".to_string()]),
+        remove: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        update.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), update).unwrap();
+
+    let operators: OperatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Operators {
+                kind: OperationKind::Rebalance,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(operators.operators, vec!["keeper".to_string()]);
+
+    // every validator ends up with 170
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        rebalance.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
+                src_validator: validator2.address.clone(),
+                dst_validator: validator.address.clone(),
+                amount: coin(130, "uluna"),
+            })),
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
+                src_validator: validator3.address.clone(),
+                dst_validator: validator.address.clone(),
+                amount: coin(30, "uluna"),
+            })),
+        ]
+    );
+
+    // each kind is granted on its own
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::Rebalance {}, Op::UpdateGlobalIndex {}],
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the owner runs all of them
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![
+                Op::UpdateGlobalIndex {},
+                Op::ClaimAirdrops {
+                    airdrop_hooks: vec![Binary::from(b"hook".to_vec())],
+                },
+            ],
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "airdrop_registry".to_string(),
+                msg: Binary::from(b"hook".to_vec()),
+                funds: vec![],
+            }),
+            1,
+        )
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::UpdateOperators {
+            kind: OperationKind::Rebalance,
+            add: None,
+            remove: Some(vec!["keeper".to_string()]),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        rebalance,
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+}
+
+#[test]
+pub fn exited_validators_are_left_out_and_removed() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3.clone());
+
+    // validator2 leaves the active set with a delegation left
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(300, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(50, "uluna")),
+        ],
+        &[validator.clone(), validator3.clone()],
+    );
+
+    let bond = ExecuteMsg::Bond {
+        validator: validator2.address.clone(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[coin(100, "uluna")]),
+        bond,
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(format!(
+            "The validator {} is not in the active set",
+            validator2.address
+        )))
+    );
+
+    // the rebalance only fills the active validators
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::Rebalance {}],
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Redelegate {
+            src_validator: validator.address.clone(),
+            dst_validator: validator3.address.clone(),
+            amount: coin(116, "uluna"),
+        })]
+    );
+
+    // the cleanup moves the stake of the exited validator to an active one
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::RemoveExitedValidators {}],
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    match &res.messages[0].msg {
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        }) => {
+            assert_eq!(src_validator, &validator2.address);
+            assert!(dst_validator == &validator.address || dst_validator == &validator3.address);
+            assert_eq!(amount, &coin(50, "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", res.messages[0]),
+    }
+    assert!(res
+        .attributes
+        .contains(&attr("validators", validator2.address.as_str())));
+
+    let whitelist: WhitelistedValidatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WhitelistedValidators {},
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        whitelist.validators,
+        vec![validator.address.clone(), validator3.address.clone()]
+    );
+}
+
+#[test]
+pub fn rebalances_ramp_up_new_validators() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(10, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(300, "uluna")),
+        ],
+        &[validator.clone(), validator2.clone()],
+    );
+
+    let ramp_up = ExecuteMsg::UpdateRampUp {
+        max_growth: Some(Uint128::new(100)),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ramp_up.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), ramp_up).unwrap();
+
+    // the new validator receives 100 of the 145 it is short of in this epoch
+    let rebalance = |deps: DepsMut, env: Env| {
+        execute(
+            deps,
+            env,
+            mock_info("owner1", &[]),
+            ExecuteMsg::RunOperations {
+                ops: vec![Op::Rebalance {}],
+            },
+        )
+        .unwrap()
+        .messages
+    };
+    let redelegation = vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
+        src_validator: validator2.address.clone(),
+        dst_validator: validator.address.clone(),
+        amount: coin(100, "uluna"),
+    }))];
+    assert_eq!(rebalance(deps.as_mut(), mock_env()), redelegation);
+    assert_eq!(rebalance(deps.as_mut(), mock_env()), vec![]);
+
+    let allowances: RampAllowancesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::RampAllowances {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        allowances,
+        RampAllowancesResponse {
+            max_growth: Some(Uint128::new(100)),
+            allowances: vec![
+                RampAllowance {
+                    validator: validator.address.clone(),
+                    received: Uint128::new(100),
+                    allowance: Some(Uint128::zero()),
+                },
+                RampAllowance {
+                    validator: validator2.address.clone(),
+                    received: Uint128::zero(),
+                    allowance: Some(Uint128::new(100)),
+                },
+            ],
+        }
+    );
+
+    // the allowance is renewed in the next epoch
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(30);
+    assert_eq!(rebalance(deps.as_mut(), env), redelegation);
+}
+
+#[test]
+pub fn operator_runs_the_routine_operations() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+
+    let set_operator = ExecuteMsg::UpdateOperator {
+        operator: Some("hot".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        set_operator.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        set_operator,
+    )
+    .unwrap();
+    let operator: OperatorResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Operator {}).unwrap()).unwrap();
+    assert_eq!(operator.operator, Some("hot".to_string()));
+
+    // the operator registers validators and runs every kind of keeper operation
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        ExecuteMsg::RegisterValidator {
+            validator: validator2.address.clone(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::UpdateGlobalIndex {}],
+        },
+    )
+    .unwrap();
+
+    // the parameters are left to the owner
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        UpdateParams {
+            epoch_period: Some(20),
+            unbonding_period: None,
+            peg_recovery_fee: None,
+            er_threshold: None,
+            unbond_fee_bps: None,
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the operator rotates itself
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        ExecuteMsg::UpdateOperator {
+            operator: Some("hot2".to_string()),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hot", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::UpdateGlobalIndex {}],
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+}
+
+#[test]
+pub fn withdraw_unbonded_publishes_a_wormhole_receipt() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // only the owner sets the core contract
+    let update = ExecuteMsg::UpdateWormhole {
+        core_contract: Some("wormhole".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        update.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), update).unwrap();
+    deps.querier.with_wormhole_fee(coin(2, "uluna"));
+
+    let bob = "bob".to_string();
+    let info = mock_info(&bob, &[coin(100, "uluna")]);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    // the second unbond sends the batch
+    let mut env = mock_env();
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+    env.block.time = env.block.time.plus_seconds(31);
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+
+    env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(20, "uluna"))]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    )
+    .unwrap();
+
+    // the fee is paid out of the withdrawal
+    let receipt = UnbondReceipt {
+        sequence: 0,
+        time: env.block.time.seconds(),
+        amount: Uint128::new(20),
+        recipient: bob.clone(),
+    };
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: bob,
+                amount: vec![coin(18, "uluna")],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "wormhole".to_string(),
+                msg: to_binary(&WormholeExecuteMsg::PostMessage {
+                    message: receipt.encode(),
+                    nonce: 0,
+                })
+                .unwrap(),
+                funds: vec![coin(2, "uluna")],
+            })),
+        ]
+    );
+
+    let wormhole: WormholeResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Wormhole {}).unwrap()).unwrap();
+    assert_eq!(
+        wormhole,
+        WormholeResponse {
+            core_contract: Some("wormhole".to_string()),
+            receipts: 1,
+        }
+    );
+}
+
+#[test]
+pub fn proper_bond_and_execute() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let hook = to_binary(&"provide_liquidity").unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[coin(100, "uluna")]),
+        ExecuteMsg::BondAndExecute {
+            validator: validator.address.clone(),
+            target_contract: "pair".to_string(),
+            msg: hook.clone(),
+        },
+    )
+    .unwrap();
+
+    // the hub holds the minted bAsset only until it is sent on
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address,
+                amount: coin(100, "uluna"),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Mint {
+                    recipient: MOCK_CONTRACT_ADDR.to_string(),
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "pair".to_string(),
+                    amount: Uint128::new(100),
+                    msg: hook,
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+        ]
+    );
+    assert!(res.attributes.contains(&attr("target_contract", "pair")));
+    assert_eq!(
+        res.attributes[3..5],
+        [attr("recipient", "pair"), attr("amount", "100")]
+    );
+}
+
+#[test]
+pub fn proper_bond_batch() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bond_batch = |allocations: &[(&str, u128)]| ExecuteMsg::BondBatch {
+        validator: validator.address.clone(),
+        allocations: allocations
+            .iter()
+            .map(|(recipient, amount)| (recipient.to_string(), Uint128::new(*amount)))
+            .collect(),
+    };
+    let exchange = mock_info("exchange", &[coin(1000, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        exchange.clone(),
+        bond_batch(&[("alice", 300), ("bob", 600)]),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The allocations add up to 900uluna, 1000uluna is attached"
+        ))
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        exchange.clone(),
+        bond_batch(&[("alice", 1000), ("bob", 0)]),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("The allocation of bob is zero"))
+    );
+
+    // one delegation, a mint for every recipient
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        exchange,
+        bond_batch(&[("alice", 300), ("bob", 700)]),
+    )
+    .unwrap();
+    let mint = |recipient: &str, amount: u128| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Mint {
+                recipient: recipient.to_string(),
+                amount: Uint128::new(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    };
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address,
+                amount: coin(1000, "uluna"),
+            })),
+            mint("alice", 300),
+            mint("bob", 700),
+        ]
+    );
+    let data: BondData = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data.minted_amount, Uint128::new(1000));
+    assert!(res.attributes.contains(&attr("allocations", "2")));
+    assert_eq!(
+        res.attributes[3..7],
+        [
+            attr("recipient", "alice"),
+            attr("amount", "300"),
+            attr("recipient", "bob"),
+            attr("amount", "700"),
+        ]
+    );
+}
+
+#[test]
+pub fn unbond_batches_track_the_unclaimed_underlying() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let alice = "alice".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(100, "uluna")]),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    // the second unbond sends the batch
+    let mut env = mock_env();
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::new(80u128)),
+            (&alice, &Uint128::new(10u128)),
+        ],
+    )]);
+    env.block.time = env.block.time.plus_seconds(31);
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&alice, &[]),
+        Uint128::new(10),
+        alice.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+    let sent_at = env.block.time.seconds();
+
+    let liquidity: LiquidityResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Liquidity {}).unwrap()).unwrap();
+    assert_eq!(
+        liquidity,
+        LiquidityResponse {
+            released_unclaimed: Uint128::zero(),
+            unbonding: Uint128::new(20),
+        }
+    );
+
+    env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(20, "uluna"))]);
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    )
+    .unwrap();
+
+    // alice has not withdrawn her share of the released batch
+    let batches: UnbondBatchesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondBatches {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        batches.batches,
+        vec![UnbondBatch {
+            batch_id: 1,
+            requested: Uint128::new(20),
+            applied_exchange_rate: Decimal::one(),
+            release_time: sent_at + 2,
+            released: true,
+            unclaimed: Uint128::new(10),
+        }]
+    );
+
+    let liquidity: LiquidityResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Liquidity {}).unwrap()).unwrap();
+    assert_eq!(
+        liquidity,
+        LiquidityResponse {
+            released_unclaimed: Uint128::new(10),
+            unbonding: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+pub fn unbond_batches_move_through_their_phases() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(100, "uluna")]),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let status = |deps: Deps, batch_id: u64| {
+        query(deps, mock_env(), QueryMsg::UnbondBatchStatus { batch_id })
+            .and_then(|res| from_binary::<UnbondBatchStatus>(&res))
+    };
+    let opened_at = mock_env().block.time.seconds();
+    assert_eq!(
+        status(deps.as_ref(), 1).unwrap(),
+        UnbondBatchStatus {
+            batch_id: 1,
+            status: BatchStatus::Collecting,
+            collecting_time: Some(opened_at),
+            undelegating_time: None,
+            released_time: None,
+        }
+    );
+
+    // the unbond after the epoch period sends the batch and opens the next one
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    let sent_at = env.block.time.seconds();
+    assert_eq!(
+        status(deps.as_ref(), 1).unwrap().status,
+        BatchStatus::Undelegating
+    );
+    assert_eq!(
+        status(deps.as_ref(), 1).unwrap().undelegating_time,
+        Some(sent_at)
+    );
+    assert_eq!(
+        status(deps.as_ref(), 2).unwrap(),
+        UnbondBatchStatus {
+            batch_id: 2,
+            status: BatchStatus::Collecting,
+            collecting_time: Some(sent_at),
+            undelegating_time: None,
+            released_time: None,
+        }
+    );
+    assert_eq!(
+        status(deps.as_ref(), 3).unwrap_err(),
+        StdError::generic_err("Unbond batch 3 does not exist")
+    );
+
+    env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(10, "uluna"))]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    )
+    .unwrap();
+    assert_eq!(
+        status(deps.as_ref(), 1).unwrap(),
+        UnbondBatchStatus {
+            batch_id: 1,
+            status: BatchStatus::Released,
+            collecting_time: Some(opened_at),
+            undelegating_time: Some(sent_at),
+            released_time: Some(env.block.time.seconds()),
+        }
+    );
+}
+
+#[test]
+pub fn permissionless_handlers_pay_keeper_bounties() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // only the owner sets the incentive, with a reserve covering a bounty
+    let update = |reserve| ExecuteMsg::UpdateKeeperIncentive {
+        incentive: Some(KeeperIncentive {
+            bounty: Uint128::new(10),
+            max_per_block: 1,
+            min_blocks: 10,
+            reserve: Uint128::new(reserve),
+        }),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        update(100),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(5),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The keeper reserve must cover at least one bounty"
+        ))
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(MAX_KEEPER_RESERVE + 1),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The keeper bounty is at most 10000000 and its reserve at most 1000000000"
+        ))
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(100),
+    )
+    .unwrap();
+
+    let bounty = SubMsg::new(WasmMsg::Execute {
+        contract_addr: "reward".to_string(),
+        msg: to_binary(&RewardExecuteMsg::PayKeeperBounty {
+            recipient: "keeper".to_string(),
+            amount: Uint128::new(10),
+        })
+        .unwrap(),
+        funds: vec![],
+    });
+    let keeper = mock_info("keeper", &[]);
+    let at_height = |blocks: u64| {
+        let mut env = mock_env();
+        env.block.height += blocks;
+        env
+    };
+
+    // a check finding no slash is not paid
+    let res = execute(deps.as_mut(), mock_env(), keeper.clone(), CheckSlashing {}).unwrap();
+    assert!(res.messages.is_empty());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1000000))],
+    )]);
+    set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
+    let res = execute(deps.as_mut(), mock_env(), keeper.clone(), CheckSlashing {}).unwrap();
+    assert_eq!(res.messages, vec![bounty.clone()]);
+
+    // the next slash within min_blocks is not paid
+    set_delegation(&mut deps.querier, validator.clone(), 800000, "uluna");
+    let res = execute(
+        deps.as_mut(),
+        at_height(9),
+        keeper.clone(),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    set_delegation(&mut deps.querier, validator.clone(), 700000, "uluna");
+    let res = execute(
+        deps.as_mut(),
+        at_height(10),
+        keeper.clone(),
+        CheckSlashing {},
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![bounty.clone()]);
+
+    // the index update tops the reserve up before the rewards are distributed
+    let update_global = ExecuteMsg::UpdateGlobalIndex {
+        airdrop_hooks: None,
+    };
+    let reward_msg = |msg: &RewardExecuteMsg| {
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "reward".to_string(),
+            msg: to_binary(msg).unwrap(),
+            funds: vec![],
+        })
+    };
+    let mut expected = vec![
+        SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+            validator: validator.address.clone(),
+        }),
+        reward_msg(&RewardExecuteMsg::SwapToRewardDenom {}),
+        reward_msg(&RewardExecuteMsg::FundKeeperReserve {
+            target: Uint128::new(100),
+        }),
+        reward_msg(&RewardExecuteMsg::UpdateGlobalIndex {}),
+    ];
+    // without accumulated rewards it is not paid
+    let res = execute(
+        deps.as_mut(),
+        at_height(11),
+        keeper.clone(),
+        update_global.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages, expected);
+
+    let mut delegation = sample_delegation(validator.address.clone(), coin(700000, "uluna"));
+    delegation.accumulated_rewards = vec![coin(100, "uusd")];
+    set_delegation_query(&mut deps.querier, &[delegation], &[validator]);
+    let res = execute(deps.as_mut(), at_height(12), keeper, update_global).unwrap();
+    expected.push(bounty);
+    assert_eq!(res.messages, expected);
+
+    let res: KeeperIncentiveResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperIncentive {}).unwrap())
+            .unwrap();
+    assert_eq!(res.incentive.unwrap().max_per_block, 1);
+}
+
+#[test]
+pub fn bonds_close_a_lapsed_epoch() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[coin(100, "uluna")]),
+        bond.clone(),
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator.clone(), 100, "uluna");
+    unbond_and_burn(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // only the owner enables it, the epoch is left to the unbonds until then
+    let update = |max_undelegations| ExecuteMsg::UpdateEpochAutoAdvance {
+        max_undelegations: Some(max_undelegations),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&bob, &[]), update(1));
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the batch needs one undelegation, more than the guard allows
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(0),
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[coin(10, "uluna")]),
+        bond.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 110, "uluna");
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(1),
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[coin(10, "uluna")]),
+        bond,
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: validator.address,
+            amount: coin(10, "uluna"),
+        })
+    );
+
+    let batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(batch.id, 2);
+    assert!(batch.requested_with_fee.is_zero());
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.last_unbonded_time, env.block.time.seconds());
+
+    let res: EpochAutoAdvanceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::EpochAutoAdvance {}).unwrap())
+            .unwrap();
+    assert_eq!(res.max_undelegations, Some(1));
+}
+
+#[test]
+pub fn user_history_records_bonds_and_unbonds() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let env = mock_env();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[coin(100, "uluna")]),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+
+    let history = |start_after| QueryMsg::UserHistory {
+        address: bob.clone(),
+        start_after,
+        limit: None,
+    };
+    let res: UserHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), history(None)).unwrap()).unwrap();
+    assert_eq!(
+        res.events,
+        vec![
+            UserEvent {
+                id: 0,
+                time: env.block.time.seconds(),
+                height: env.block.height,
+                exchange_rate: Decimal::one(),
+                action: UserAction::Bond {
+                    amount: Uint128::new(100),
+                    minted: Uint128::new(100),
+                },
+            },
+            UserEvent {
+                id: 1,
+                time: env.block.time.seconds(),
+                height: env.block.height,
+                exchange_rate: Decimal::one(),
+                action: UserAction::Unbond {
+                    amount: Uint128::new(10),
+                    requested: Uint128::new(10),
+                    batch_id: 1,
+                },
+            },
+        ]
+    );
+
+    // paged by event id, other addresses have no history
+    let res: UserHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), history(Some(0))).unwrap()).unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(res.events[0].id, 1);
+    let res: UserHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UserHistory {
+                address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(res.events.is_empty());
+}
+
+#[test]
+pub fn simulations_quote_the_bond_and_the_unbond() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        UpdateParams {
+            epoch_period: None,
+            unbonding_period: None,
+            peg_recovery_fee: Some(Decimal::from_ratio(5u64, 1000u64)),
+            er_threshold: None,
+            unbond_fee_bps: Some(100),
+        },
+    )
+    .unwrap();
+
+    // 10% of the bonded is slashed, the peg recovery fee applies
+    STATE
+        .update(&mut deps.storage, |mut state| -> StdResult<_> {
+            state.total_bond_amount = Uint128::new(900000);
+            state.exchange_rate = Decimal::from_ratio(9u64, 10u64);
+            Ok(state)
+        })
+        .unwrap();
+    set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1000000))],
+    )]);
+
+    let simulated: SimulateBondResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateBond {
+                amount: Uint128::new(9000),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        simulated,
+        SimulateBondResponse {
+            minted: Uint128::new(9950),
+            peg_recovery_fee: Uint128::new(50),
+            exchange_rate: Decimal::from_ratio(909000u128, 1009950u128),
+        }
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[coin(9000, "uluna")]),
+        ExecuteMsg::Bond {
+            validator: validator.address.clone(),
+        },
+    )
+    .unwrap();
+    let data: BondData = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data.minted_amount, simulated.minted);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, simulated.exchange_rate);
+
+    // the unbond pays both fees
+    set_delegation(&mut deps.querier, validator, 909000, "uluna");
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&"owner1".to_string(), &Uint128::new(1000000)),
+            (&"bob".to_string(), &Uint128::new(9950)),
+        ],
+    )]);
+    let simulated: SimulateUnbondResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateUnbond {
+                amount: Uint128::new(9950),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(simulated.burned, Uint128::new(9950));
+    assert_eq!(simulated.peg_recovery_fee, Uint128::new(49));
+    assert_eq!(simulated.unbond_fee, Uint128::new(99));
+    assert_eq!(simulated.requested, Uint128::new(9802));
+    assert_eq!(
+        simulated.estimated_release,
+        Uint128::new(9802) * simulated.exchange_rate
+    );
+    do_unbond(
+        deps.as_mut(),
+        "bob".to_string(),
+        mock_env(),
+        mock_info("token", &[]),
+        Uint128::new(9950),
+    );
+    assert_eq!(
+        read_unbond_wait_list(&deps.storage, 1, "bob".to_string()).unwrap(),
+        simulated.requested
+    );
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, simulated.exchange_rate);
+}
+
+#[test]
+pub fn unbonding_period_covers_the_chain_unbonding_time() {
+    let mut deps = dependencies(&[]);
+    set_validator_mock(&mut deps.querier);
+    deps.querier.with_unbonding_time(1_814_400);
+    let msg = |unbonding_period| InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        reward_denom: "uusd".to_string(),
+        validator: DEFAULT_VALIDATOR.to_string(),
+        mode: HubMode::Staking,
+    };
+    let owner_info = mock_info("owner1", &[coin(1000000, "uluna")]);
+    let too_short = ContractError::Std(StdError::generic_err(
+        "The unbonding period must be at least 1815000 seconds, the unbonding time of the chain is 1814400",
+    ));
+
+    // the period must cover the unbonding time and the safety margin
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        msg(1_814_400),
+    );
+    assert_eq!(res.unwrap_err(), too_short);
+    instantiate(deps.as_mut(), mock_env(), owner_info, msg(1_815_000)).unwrap();
+
+    let update_period = |unbonding_period| UpdateParams {
+        epoch_period: None,
+        unbonding_period: Some(unbonding_period),
+        peg_recovery_fee: None,
+        er_threshold: None,
+        unbond_fee_bps: None,
+    };
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        update_period(1_814_999),
+    );
+    assert_eq!(res.unwrap_err(), too_short);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        update_period(1_900_000),
+    )
+    .unwrap();
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.unbonding_period, 1_900_000);
+}
+
+#[test]
+pub fn unbonds_claim_the_rewards_when_enabled() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    let bob = "bob".to_string();
+    do_bond(deps.as_mut(), bob.clone(), Uint128::new(1000), validator);
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::new(1000)),
+            (&"owner1".to_string(), &Uint128::new(1000000)),
+        ],
+    )]);
+
+    // the rewards are left to the user by default
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        mock_info("token", &[]),
+        Uint128::new(100),
+    );
+    assert_eq!(res.messages.len(), 1);
+
+    // only the owner enables the claims
+    let update = ExecuteMsg::UpdateAutoClaim { enabled: true };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        update.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), update).unwrap();
+    let res: AutoClaimResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AutoClaim {}).unwrap()).unwrap();
+    assert!(res.enabled);
+
+    // a holder keeping a balance claims when it chooses
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::new(800)),
+            (&"owner1".to_string(), &Uint128::new(1000000)),
+        ],
+    )]);
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        mock_info("token", &[]),
+        Uint128::new(100),
+    );
+    assert_eq!(res.messages.len(), 1);
+
+    // the claim follows the burn of the whole balance
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::zero()),
+            (&"owner1".to_string(), &Uint128::new(1000000)),
+        ],
+    )]);
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        mock_info("token", &[]),
+        Uint128::new(800),
+    );
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "reward".to_string(),
+                msg: to_binary(&RewardExecuteMsg::ClaimRewardsFor { address: bob }).unwrap(),
+                funds: vec![],
+            }),
+            AUTO_CLAIM_REPLY_ID,
+        )
+    );
+
+    // a failing claim does not revert the unbond
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: AUTO_CLAIM_REPLY_ID,
+            result: ContractResult::Err("No rewards have accrued yet".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "auto_claim_failed"),
+            attr("error", "No rewards have accrued yet"),
+        ]
+    );
+}
+
+#[test]
+pub fn validator_delegations_drift_from_the_last_rebalance() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(100, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(300, "uluna")),
+        ],
+        &[validator.clone(), validator2.clone()],
+    );
+
+    let delegations = |deps: &OwnedDeps<_, _, _>| -> ValidatorDelegationsResponse {
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ValidatorDelegations {}).unwrap())
+            .unwrap()
+    };
+
+    // before a rebalance the whole delegation is drift
+    let res = delegations(&deps);
+    assert_eq!(res.rebalanced_at, None);
+    assert_eq!(
+        res.validators[0],
+        ValidatorDelegation {
+            validator: validator.address.clone(),
+            target: None,
+            delegated: Uint128::new(100),
+            rebalanced: None,
+            gained: Uint128::new(100),
+            lost: Uint128::zero(),
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::RunOperations {
+            ops: vec![Op::Rebalance {}],
+        },
+    )
+    .unwrap();
+
+    // a slashing of the first validator since the rebalance
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(190, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(200, "uluna")),
+        ],
+        &[validator.clone(), validator2.clone()],
+    );
+    let res = delegations(&deps);
+    assert_eq!(res.rebalanced_at, Some(mock_env().block.time.seconds()));
+    assert_eq!(
+        res.validators,
+        vec![
+            ValidatorDelegation {
+                validator: validator.address,
+                target: Some(Uint128::new(200)),
+                delegated: Uint128::new(190),
+                rebalanced: Some(Uint128::new(200)),
+                gained: Uint128::zero(),
+                lost: Uint128::new(10),
+            },
+            ValidatorDelegation {
+                validator: validator2.address,
+                target: Some(Uint128::new(200)),
+                delegated: Uint128::new(200),
+                rebalanced: Some(Uint128::new(200)),
+                gained: Uint128::zero(),
+                lost: Uint128::zero(),
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn surplus_is_buffered_or_bonded() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&"owner1".to_string(), &Uint128::new(1000000))],
+    )]);
+
+    // a tip of 500 sent to the hub
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(500),
+        },
+    )]);
+    let surplus = |deps: Deps| -> SurplusResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::Surplus {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        surplus(deps.as_ref()),
+        SurplusResponse {
+            policy: SurplusPolicy::Buffer,
+            surplus: Uint128::new(500),
+        }
+    );
+
+    // only the owner and the operator reconcile
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::ReconcileSurplus {},
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let owner = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        ExecuteMsg::ReconcileSurplus {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    let buffer: UnbondPriorityResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::UnbondPriority {}).unwrap())
+            .unwrap();
+    assert_eq!(buffer.buffer, Uint128::new(500));
+    assert_eq!(surplus(deps.as_ref()).surplus, Uint128::zero());
+
+    // another tip of 1000 is bonded
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(1500),
+        },
+    )]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        ExecuteMsg::UpdateSurplusPolicy {
+            policy: SurplusPolicy::Bond,
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner,
+        ExecuteMsg::ReconcileSurplus {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(1000, "uluna"),
+        }))]
+    );
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(1001000));
+    assert_eq!(state.exchange_rate, Decimal::from_ratio(1001u64, 1000u64));
+}
+
+#[test]
+pub fn surplus_leaves_the_batches_in_flight_their_unbonds() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "reward".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000u128))])]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator.clone(), 1000, "uluna");
+
+    // a batch of 30 is sent to undelegation
+    let info = mock_info("token", &[]);
+    unbond_and_burn(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(990u128))])]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    unbond_and_burn(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        Uint128::new(20),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(970u128))])]);
+    set_delegation(&mut deps.querier, validator, 970, "uluna");
+
+    // the undelegation completes ahead of the release of the batch, along with a tip of 20
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(50, "uluna"))]);
+    let surplus = |deps: Deps, env: Env| -> SurplusResponse {
+        from_binary(&query(deps, env, QueryMsg::Surplus {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        surplus(deps.as_ref(), env.clone()).surplus,
+        Uint128::new(20)
+    );
+    let owner = mock_info("owner1", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::ReconcileSurplus {},
+    )
+    .unwrap();
+    assert_eq!(surplus(deps.as_ref(), env.clone()).surplus, Uint128::zero());
+
+    // once the batch matured, its release takes what the hub received
+    env.block.time = env.block.time.plus_seconds(91);
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(55, "uluna"))]);
+    assert_eq!(surplus(deps.as_ref(), env.clone()).surplus, Uint128::zero());
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::ReconcileSurplus {},
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "Batch 1 is released, the surplus is reconciled after its next withdrawal"
+        ))
+    );
+
+    // the withdrawal pays the batch its 30 and the tip of 5 sent since, less the rounding
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        ExecuteMsg::WithdrawUnbonded {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: bob,
+            amount: vec![coin(33, "uluna")],
+        })]
+    );
+    deps.querier
+        .with_native_balances(&[(MOCK_CONTRACT_ADDR.to_string(), coin(22, "uluna"))]);
+    assert_eq!(surplus(deps.as_ref(), env).surplus, Uint128::zero());
 }
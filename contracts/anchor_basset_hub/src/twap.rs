@@ -0,0 +1,159 @@
+use crate::state::{RATE_OBSERVATIONS, STATE};
+use basset::hub::ExchangeRateTwapResponse;
+use basset::math::{
+    decimal_division_in_256, decimal_multiplication_in_256, decimal_subtraction_in_256,
+    decimal_summation_in_256,
+};
+use cosmwasm_std::{Decimal, Deps, Env, Order, StdError, StdResult, Storage};
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
+
+/// The longest window of the twap, the older observations are removed
+pub const MAX_TWAP_WINDOW: u64 = 7 * 24 * 60 * 60;
+
+/// The observations removed by a checkpoint, the older ones are removed by the next
+const MAX_PRUNED_OBSERVATIONS: usize = 10;
+
+/// Accumulate the exchange rate since the last checkpoint. It must run before any change of
+/// the state, so the stored rate is the one in effect since the last checkpoint
+pub(crate) fn checkpoint_exchange_rate(storage: &mut dyn Storage, now: u64) -> StdResult<()> {
+    let cumulative = match last_observation(storage)? {
+        Some((time, _)) if time >= now => return Ok(()),
+        Some((time, cumulative)) => cumulative_at(storage, time, cumulative, now)?,
+        None => Decimal::zero(),
+    };
+    RATE_OBSERVATIONS.save(storage, U64Key::new(now), &cumulative)?;
+
+    // the latest observation before the longest window is kept to interpolate from it
+    let cutoff = now.saturating_sub(MAX_TWAP_WINDOW);
+    let expired = RATE_OBSERVATIONS
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(U64Key::new(cutoff))),
+            Order::Descending,
+        )
+        .skip(1)
+        .take(MAX_PRUNED_OBSERVATIONS)
+        .map(|item| Ok(item?.0))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+    for key in expired {
+        RATE_OBSERVATIONS.remove(storage, U64Key::new(read_observation_time(&key)?));
+    }
+    Ok(())
+}
+
+pub fn query_exchange_rate_twap(
+    deps: Deps,
+    env: Env,
+    window_seconds: u64,
+) -> StdResult<ExchangeRateTwapResponse> {
+    if window_seconds == 0 || window_seconds > MAX_TWAP_WINDOW {
+        return Err(StdError::generic_err(format!(
+            "The twap window must be between 1 and {} seconds",
+            MAX_TWAP_WINDOW
+        )));
+    }
+    let now = env.block.time.seconds();
+    let start = now.saturating_sub(window_seconds);
+
+    let end_cumulative = match last_observation(deps.storage)? {
+        Some((time, cumulative)) => cumulative_at(deps.storage, time, cumulative, now)?,
+        None => {
+            return Err(StdError::generic_err(
+                "The exchange rate was never observed",
+            ))
+        }
+    };
+    let (time, cumulative) = match RATE_OBSERVATIONS
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(U64Key::new(start))),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+    {
+        Some((key, cumulative)) => (read_observation_time(&key)?, cumulative),
+        None => {
+            return Err(StdError::generic_err(format!(
+                "The exchange rate is observed for less than {} seconds",
+                window_seconds
+            )))
+        }
+    };
+    let start_cumulative = interpolate(deps.storage, time, cumulative, start)?;
+
+    Ok(ExchangeRateTwapResponse {
+        window_seconds,
+        twap: decimal_division_in_256(
+            decimal_subtraction_in_256(end_cumulative, start_cumulative),
+            Decimal::from_ratio(window_seconds, 1u64),
+        )?,
+    })
+}
+
+fn last_observation(storage: &dyn Storage) -> StdResult<Option<(u64, Decimal)>> {
+    RATE_OBSERVATIONS
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .map(|item| {
+            let (key, cumulative) = item?;
+            Ok((read_observation_time(&key)?, cumulative))
+        })
+        .transpose()
+}
+
+/// The accumulator at now from the last observation, the stored rate held since
+fn cumulative_at(
+    storage: &dyn Storage,
+    time: u64,
+    cumulative: Decimal,
+    now: u64,
+) -> StdResult<Decimal> {
+    let rate = STATE.load(storage)?.exchange_rate;
+    Ok(decimal_summation_in_256(
+        cumulative,
+        decimal_multiplication_in_256(rate, Decimal::from_ratio(now - time, 1u64)),
+    ))
+}
+
+/// The accumulator at at, from the observation at time before it and the one after it
+fn interpolate(
+    storage: &dyn Storage,
+    time: u64,
+    cumulative: Decimal,
+    at: u64,
+) -> StdResult<Decimal> {
+    let next = RATE_OBSERVATIONS
+        .range(
+            storage,
+            Some(Bound::exclusive(U64Key::new(time))),
+            None,
+            Order::Ascending,
+        )
+        .next()
+        .transpose()?;
+    match next {
+        Some((key, next_cumulative)) => {
+            let next_time = read_observation_time(&key)?;
+            // the rate between two observations is the slope of the accumulator
+            Ok(decimal_summation_in_256(
+                cumulative,
+                decimal_multiplication_in_256(
+                    decimal_subtraction_in_256(next_cumulative, cumulative),
+                    Decimal::from_ratio(at - time, next_time - time),
+                ),
+            ))
+        }
+        None => cumulative_at(storage, time, cumulative, at),
+    }
+}
+
+fn read_observation_time(key: &[u8]) -> StdResult<u64> {
+    let time: [u8; 8] = key
+        .try_into()
+        .map_err(|_| StdError::generic_err("Corrupted rate observation key"))?;
+    Ok(u64::from_be_bytes(time))
+}
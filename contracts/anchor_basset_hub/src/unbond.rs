@@ -1,17 +1,40 @@
+use crate::bond::assert_peg_recovery_price;
 use crate::contract::{query_total_issued, slashing};
+use crate::history::record_user_event;
+use crate::priority::{
+    keep_skimmed_share, refill_unbond_buffer, skimmed_share, take_kept_share,
+    take_priority_withdrawals,
+};
 use crate::state::{
-    get_finished_amount, get_unbond_batches, read_unbond_history, remove_unbond_wait_list,
-    store_unbond_history, store_unbond_wait_list, CONFIG, CURRENT_BATCH, PARAMETERS, STATE,
+    add_collected_fee, all_unbond_history, get_finished_requests, read_hub_mode,
+    read_unbond_history, remove_unbond_wait_list, store_unbond_history, store_unbond_wait_list,
+    CurrentBatch, AUTO_CLAIM_REWARDS, BATCH_CLAIMED, BATCH_STATUS, CONFIG, CURRENT_BATCH,
+    EXECUTION_LOCK, PARAMETERS, PEG_RECOVERY_FEES, STATE, UNBOND_FEES, UNCLAIMED_UNBONDED,
+};
+use crate::wormhole::unbond_receipt_msg;
+use basset::contract_error::ContractError;
+use basset::delegation::pick_undelegations;
+use basset::hub::{
+    BatchStatus, HubMode, LiquidityResponse, SimulateUnbondResponse, State, UnbondBatch,
+    UnbondBatchProofResponse, UnbondBatchStatus, UnbondBatchesResponse, UnbondData, UnbondHistory,
+    UserAction,
 };
-use basset::hub::{State, UnbondHistory};
+use basset::querier::query_token_balance;
+use basset::reward::ExecuteMsg as RewardExecuteMsg;
 use cosmwasm_std::{
-    attr, coin, coins, to_binary, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
-    Response, StakingMsg, StdError, StdResult, Storage, Uint128, WasmMsg,
+    attr, coins, to_binary, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, Env, Event, MessageInfo,
+    Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
-use rand::{Rng, SeedableRng, XorShiftRng};
+use cw_storage_plus::U64Key;
 use signed_integer::SignedInt;
 
+/// The reply to the burn of an unbond, it releases the execution lock
+pub(crate) const UNBOND_REPLY_ID: u64 = 0;
+
+/// The reply to a failed claim of the rewards of an unbond, checked before the airdrop hooks
+pub(crate) const AUTO_CLAIM_REPLY_ID: u64 = u64::MAX - 1;
+
 /// This message must be call by receive_cw20
 /// This message will undelegate coin and burn basset token
 pub(crate) fn execute_unbond(
@@ -20,34 +43,55 @@ pub(crate) fn execute_unbond(
     _info: MessageInfo,
     amount: Uint128,
     sender: String,
-) -> StdResult<Response> {
-    // Read params
-    let params = PARAMETERS.load(deps.storage)?;
-    let epoch_period = params.epoch_period;
-    let threshold = params.er_threshold;
-    let recovery_fee = params.peg_recovery_fee;
+) -> Result<Response, ContractError> {
+    EXECUTION_LOCK.assert_unlocked(deps.storage)?;
 
     let mut current_batch = CURRENT_BATCH.load(deps.storage)?;
 
     // Check slashing, update state, and calculate the new exchange rate.
-    slashing(&mut deps, env.clone())?;
+    // The tokens are sent back if the circuit breaker tripped
+    if let Some(alert) = slashing(&mut deps, env.clone())? {
+        let config = CONFIG.load(deps.storage)?;
+        let token_address = deps
+            .api
+            .addr_humanize(&config.token_contract.ok_or_else(|| {
+                ContractError::ContractNotRegistered {
+                    contract: "token".to_string(),
+                }
+            })?)?;
+        return Ok(Response::new()
+            .add_message(WasmMsg::Execute {
+                contract_addr: token_address.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: sender.clone(),
+                    amount,
+                })?,
+                funds: vec![],
+            })
+            .add_event(alert)
+            .add_attributes(vec![
+                attr("action", "unbond_refunded"),
+                attr("sender", sender.as_str()),
+                attr("recipient", sender),
+                attr("amount", amount),
+            ]));
+    }
 
     let mut state = STATE.load(deps.storage)?;
 
     let mut total_supply = query_total_issued(deps.as_ref()).unwrap_or_default();
 
     // Collect all the requests within a epoch period
-    // Apply peg recovery fee
-    let amount_with_fee: Uint128;
-    if state.exchange_rate < threshold {
-        let max_peg_fee = amount * recovery_fee;
-        let required_peg_fee = ((total_supply + current_batch.requested_with_fee)
-            .checked_sub(state.total_bond_amount))?;
-        let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
-        amount_with_fee = (amount.checked_sub(peg_fee))?;
-    } else {
-        amount_with_fee = amount;
-    }
+    // Apply peg recovery fee, the unbond fee stays in the pool
+    let (amount_with_fee, peg_fee, unbond_fee) = requested_with_fees(
+        deps.as_ref(),
+        &state,
+        total_supply,
+        current_batch.requested_with_fee,
+        amount,
+    )?;
+    add_collected_fee(deps.storage, &PEG_RECOVERY_FEES, peg_fee)?;
+    add_collected_fee(deps.storage, &UNBOND_FEES, unbond_fee)?;
     current_batch.requested_with_fee += amount_with_fee;
 
     store_unbond_wait_list(
@@ -57,98 +101,247 @@ pub(crate) fn execute_unbond(
         amount_with_fee,
     )?;
 
-    total_supply = (total_supply.checked_sub(amount))
-        .expect("the requested can not be more than the total supply");
+    record_user_event(
+        deps.storage,
+        &env,
+        &deps.api.addr_canonicalize(&sender)?,
+        state.exchange_rate,
+        UserAction::Unbond {
+            amount,
+            requested: amount_with_fee,
+            batch_id: current_batch.id,
+        },
+    )?;
+
+    total_supply = total_supply.checked_sub(amount)?;
 
     // Update exchange rate
     state.update_exchange_rate(total_supply, current_batch.requested_with_fee);
 
-    let current_time = env.block.time.seconds();
-    let passed_time = current_time - state.last_unbonded_time;
+    // If the epoch period is passed, the undelegate message would be sent.
+    let batch_id = current_batch.id;
+    let messages =
+        advance_epoch(&mut deps, &env, &mut state, &mut current_batch, None)?.unwrap_or_default();
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    // Store the new requested_with_fee or id in the current batch
+    CURRENT_BATCH.save(deps.storage, &current_batch)?;
 
-    // If the epoch period is passed, the undelegate message would be sent.
-    if passed_time > epoch_period {
-        // Apply the current exchange rate.
-        let undelegation_amount = current_batch.requested_with_fee * state.exchange_rate;
-
-        // the contract must stop if
-        if undelegation_amount == Uint128::new(1) {
-            return Err(StdError::generic_err(
-                "Burn amount must be greater than 1 ubluna",
-            ));
-        }
+    // Store state's new exchange rate
+    STATE.save(deps.storage, &state)?;
 
-        let delegator = env.contract.address;
+    // Send Burn message to token contract
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps
+        .api
+        .addr_humanize(&config.token_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "token".to_string(),
+            }
+        })?)?;
 
-        let block_height = env.block.height;
+    // the unbond is locked until its reply, the burn updates the balances of the reward contract
+    EXECUTION_LOCK.lock(deps.storage)?;
+    let burn_msg = Cw20ExecuteMsg::Burn { amount };
+    let burn_msg = SubMsg::reply_on_success(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_address.to_string(),
+            msg: to_binary(&burn_msg)?,
+            funds: vec![],
+        }),
+        UNBOND_REPLY_ID,
+    );
+
+    // the claim follows the burn, so that the rewards are accrued on the balance before it.
+    // Only a holder unbonding all of its balance leaves, the others claim when they choose
+    let mut claim_msgs = vec![];
+    if AUTO_CLAIM_REWARDS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        if let Some(reward_contract) = config.reward_contract {
+            let balance = query_token_balance(&deps.querier, token_address.as_str(), &sender)?;
+            if balance.is_zero() {
+                // a failing claim leaves the rewards to the holder, it must not revert the unbond
+                claim_msgs.push(SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: deps.api.addr_humanize(&reward_contract)?.to_string(),
+                        msg: to_binary(&RewardExecuteMsg::ClaimRewardsFor {
+                            address: sender.clone(),
+                        })?,
+                        funds: vec![],
+                    },
+                    AUTO_CLAIM_REPLY_ID,
+                ));
+            }
+        }
+    }
 
-        // Send undelegated requests to possibly more than one validators
-        let mut undelegated_msgs = pick_validator(
-            deps.as_ref(),
-            undelegation_amount,
-            delegator.to_string(),
-            block_height,
-        )?;
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessage(burn_msg)
+        .add_submessages(claim_msgs)
+        .add_attributes(vec![
+            attr("action", "burn"),
+            attr("from", sender.as_str()),
+            attr("sender", sender),
+            attr("amount", amount),
+            attr("burnt_amount", amount),
+            attr("unbonded_amount", amount_with_fee),
+        ])
+        .set_data(to_binary(&UnbondData {
+            batch_id,
+            burned_amount: amount,
+        })?))
+}
 
-        messages.append(&mut undelegated_msgs);
+/// The request left of unbonding amount, with the peg recovery and unbond fees kept from it
+fn requested_with_fees(
+    deps: Deps,
+    state: &State,
+    total_supply: Uint128,
+    requested_with_fee: Uint128,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128, Uint128), ContractError> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let mut peg_fee = Uint128::zero();
+    if state.exchange_rate < params.er_threshold {
+        assert_peg_recovery_price(deps, state.exchange_rate)?;
+        let max_peg_fee = amount * params.peg_recovery_fee;
+        let required_peg_fee =
+            (total_supply + requested_with_fee).checked_sub(state.total_bond_amount)?;
+        peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
+    }
+    let amount_with_fee = amount.checked_sub(peg_fee)?;
+    let unbond_fee = amount_with_fee.multiply_ratio(params.unbond_fee_bps, 10_000u64);
+    Ok((
+        amount_with_fee.checked_sub(unbond_fee)?,
+        peg_fee,
+        unbond_fee,
+    ))
+}
 
-        state.total_bond_amount = (state.total_bond_amount.checked_sub(undelegation_amount))
-            .expect("undelegation amount can not be more than stored total bonded amount");
+/// The outcome of unbonding amount at the stored state, as a slashing is only accounted by
+/// the unbond itself
+pub fn query_simulate_unbond(deps: Deps, amount: Uint128) -> StdResult<SimulateUnbondResponse> {
+    let mut state = STATE.load(deps.storage)?;
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_supply = query_total_issued(deps).unwrap_or_default();
+    let (requested, peg_recovery_fee, unbond_fee) =
+        requested_with_fees(deps, &state, total_supply, requested_with_fee, amount)
+            .map_err(StdError::from)?;
+
+    state.update_exchange_rate(
+        total_supply.checked_sub(amount)?,
+        requested_with_fee + requested,
+    );
+    Ok(SimulateUnbondResponse {
+        burned: amount,
+        requested,
+        peg_recovery_fee,
+        unbond_fee,
+        exchange_rate: state.exchange_rate,
+        estimated_release: requested * state.exchange_rate,
+    })
+}
 
-        // Store history for withdraw unbonded
-        let history = UnbondHistory {
-            batch_id: current_batch.id,
-            time: env.block.time.seconds(),
-            amount: current_batch.requested_with_fee,
-            applied_exchange_rate: state.exchange_rate,
-            withdraw_rate: state.exchange_rate,
-            released: false,
-        };
-        store_unbond_history(deps.storage, current_batch.id, history)?;
-        // batch info must be updated to new batch
-        current_batch.id += 1;
-        current_batch.requested_with_fee = Uint128::zero();
-
-        // state.last_unbonded_time must be updated to the current block time
-        state.last_unbonded_time = env.block.time.seconds();
+/// Send the current batch to undelegation once the epoch period passed. With
+/// max_undelegations, a batch needing more undelegate messages is left for a later call
+/// instead, none is returned then and when the epoch did not pass
+pub(crate) fn advance_epoch(
+    deps: &mut DepsMut,
+    env: &Env,
+    state: &mut State,
+    current_batch: &mut CurrentBatch,
+    max_undelegations: Option<u32>,
+) -> StdResult<Option<Vec<CosmosMsg>>> {
+    let epoch_period = PARAMETERS.load(deps.storage)?.epoch_period;
+    let passed_time = env.block.time.seconds() - state.last_unbonded_time;
+    if passed_time <= epoch_period {
+        return Ok(None);
     }
 
-    // Store the new requested_with_fee or id in the current batch
-    CURRENT_BATCH.save(deps.storage, &current_batch)?;
+    // Apply the current exchange rate.
+    let undelegation_amount = current_batch.requested_with_fee * state.exchange_rate;
 
-    // Store state's new exchange rate
-    STATE.save(deps.storage, &state)?;
+    // the contract must stop if
+    if undelegation_amount == Uint128::new(1) {
+        if max_undelegations.is_some() {
+            return Ok(None);
+        }
+        return Err(StdError::generic_err(
+            "Burn amount must be greater than 1 ubluna",
+        ));
+    }
+    if max_undelegations.is_some() && undelegation_amount.is_zero() {
+        return Ok(None);
+    }
 
-    // Send Burn message to token contract
-    let config = CONFIG.load(deps.storage)?;
-    let token_address = deps.api.addr_humanize(
-        &config
-            .token_contract
-            .expect("the token contract must have been registered"),
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if read_hub_mode(deps.storage)? == HubMode::Staking {
+        // the skimmed underlying is not delegated, the buffer pays its share of the batch
+        let kept = skimmed_share(deps.storage, undelegation_amount, state.total_bond_amount)?;
+        // Send undelegated requests to possibly more than one validators
+        messages = pick_validator(
+            deps.as_ref(),
+            undelegation_amount - kept,
+            env.contract.address.to_string(),
+            env.block.height,
+        )?;
+        if let Some(max_undelegations) = max_undelegations {
+            if messages.len() > max_undelegations as usize {
+                return Ok(None);
+            }
+        }
+        keep_skimmed_share(deps.storage, current_batch.id, kept)?;
+    } else {
+        // the kept balance is released right away, the next withdrawal counts it as unbonded
+        state.prev_hub_balance = state
+            .prev_hub_balance
+            .checked_sub(undelegation_amount)
+            .unwrap_or_default();
+    }
+
+    state.total_bond_amount = state.total_bond_amount.checked_sub(undelegation_amount)?;
+
+    // Store history for withdraw unbonded
+    let history = UnbondHistory {
+        batch_id: current_batch.id,
+        time: env.block.time.seconds(),
+        amount: current_batch.requested_with_fee,
+        applied_exchange_rate: state.exchange_rate,
+        withdraw_rate: state.exchange_rate,
+        released: false,
+    };
+    let now = env.block.time.seconds();
+    transition_batch(
+        deps.storage,
+        current_batch.id,
+        BatchStatus::Undelegating,
+        now,
+    )?;
+    transition_batch(
+        deps.storage,
+        current_batch.id + 1,
+        BatchStatus::Collecting,
+        now,
     )?;
+    store_unbond_history(deps.storage, current_batch.id, history)?;
+    // batch info must be updated to new batch
+    current_batch.id += 1;
+    current_batch.requested_with_fee = Uint128::zero();
 
-    let burn_msg = Cw20ExecuteMsg::Burn { amount };
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: token_address.to_string(),
-        msg: to_binary(&burn_msg)?,
-        funds: vec![],
-    }));
-
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "burn"),
-        attr("from", sender),
-        attr("burnt_amount", amount),
-        attr("unbonded_amount", amount_with_fee),
-    ]))
+    // state.last_unbonded_time must be updated to the current block time
+    state.last_unbonded_time = env.block.time.seconds();
+
+    Ok(Some(messages))
 }
 
 pub fn execute_withdraw_unbonded(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    EXECUTION_LOCK.assert_unlocked(deps.storage)?;
     let sender_human = info.sender;
     let contract_address = env.contract.address.clone();
 
@@ -166,58 +359,130 @@ pub fn execute_withdraw_unbonded(
         .amount;
 
     // calculate withdraw rate for user requests
-    process_withdraw_rate(deps.storage, historical_time, hub_balance)?;
+    let reconciliations = process_withdraw_rate(
+        deps.storage,
+        historical_time,
+        env.block.time.seconds(),
+        hub_balance,
+    )?;
 
-    let withdraw_amount = get_finished_amount(deps.storage, sender_human.to_string()).unwrap();
+    let finished = get_finished_requests(deps.storage, sender_human.to_string())?;
+    // the small requests still unbonding are paid out of the unbond buffer
+    let priority = take_priority_withdrawals(deps.storage, sender_human.as_str())?;
+    let withdraw_amount = finished.amount + priority.amount;
 
     if withdraw_amount.is_zero() {
         return Err(StdError::generic_err(format!(
             "No withdrawable {} assets are available yet",
             coin_denom
-        )));
+        ))
+        .into());
     }
 
     // remove the previous batches for the user
-    let deprecated_batches = get_unbond_batches(deps.storage, sender_human.to_string())?;
-    remove_unbond_wait_list(deps.storage, deprecated_batches, sender_human.clone())?;
+    remove_unbond_wait_list(
+        deps.storage,
+        [finished.batches, priority.batches].concat(),
+        sender_human.clone(),
+    )?;
+    // every batch paid shows how its payout derives from its rates
+    let mut withdrawals: Vec<Event> = vec![];
+    for (batch_id, requested, claimed) in finished.claims {
+        BATCH_CLAIMED.update(
+            deps.storage,
+            U64Key::new(batch_id),
+            |previous| -> StdResult<Uint128> { Ok(previous.unwrap_or_default() + claimed) },
+        )?;
+        let history = read_unbond_history(deps.storage, batch_id)?;
+        withdrawals.push(Event::new("unbond_batch_withdrawn").add_attributes(vec![
+            attr("batch_id", batch_id.to_string()),
+            attr("requested", requested),
+            attr(
+                "applied_exchange_rate",
+                history.applied_exchange_rate.to_string(),
+            ),
+            attr("withdraw_rate", history.withdraw_rate.to_string()),
+            attr("amount", claimed),
+        ]));
+    }
+    let unclaimed = UNCLAIMED_UNBONDED
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    UNCLAIMED_UNBONDED.save(deps.storage, &unclaimed.saturating_sub(finished.amount))?;
 
     // Update previous balance used for calculation in next Luna batch release
     let prev_balance = (hub_balance.checked_sub(withdraw_amount))?;
-    STATE.update(deps.storage, |mut last_state| -> StdResult<State> {
+    let state = STATE.update(deps.storage, |mut last_state| -> StdResult<State> {
         last_state.prev_hub_balance = prev_balance;
         Ok(last_state)
     })?;
+    record_user_event(
+        deps.storage,
+        &env,
+        &deps.api.addr_canonicalize(sender_human.as_str())?,
+        state.exchange_rate,
+        UserAction::WithdrawUnbonded {
+            amount: withdraw_amount,
+        },
+    )?;
+
+    // the wormhole fee of the receipt is paid out of the withdrawal
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut payout = withdraw_amount;
+    if let Some((receipt_msg, fee)) = unbond_receipt_msg(
+        deps,
+        &env,
+        sender_human.as_str(),
+        withdraw_amount,
+        &coin_denom,
+    )? {
+        messages.push(receipt_msg);
+        payout = withdraw_amount.checked_sub(fee)?;
+    }
 
     // Send the money to the user
     let bank_msg: CosmosMsg = BankMsg::Send {
         to_address: sender_human.to_string(),
-        amount: coins(withdraw_amount.u128(), &*coin_denom),
+        amount: coins(payout.u128(), &*coin_denom),
     }
     .into();
+    messages.insert(0, bank_msg);
 
     Ok(Response::new()
         .add_attributes(vec![
             attr("action", "finish_burn"),
             attr("from", contract_address),
+            attr("sender", sender_human.as_str()),
+            attr("recipient", sender_human.as_str()),
             attr("amount", withdraw_amount),
+            attr("priority_amount", priority.amount),
+            attr("more_requests", finished.more.to_string()),
         ])
-        .add_message(bank_msg))
+        .add_events(reconciliations)
+        .add_events(withdrawals)
+        .add_messages(messages))
 }
 
 /// This is designed for an accurate unbonded amount calculation.
-/// Execute while processing withdraw_unbonded
+/// Execute while processing withdraw_unbonded, every released batch is reconciled
+/// with the Luna actually received and reported by an event
 fn process_withdraw_rate(
     storage: &mut dyn Storage,
     historical_time: u64,
+    block_time: u64,
     hub_balance: Uint128,
-) -> StdResult<()> {
+) -> StdResult<Vec<Event>> {
     // balance change of the hub contract must be checked.
     let mut total_unbonded_amount = Uint128::zero();
+    let mut events: Vec<Event> = vec![];
 
     let mut state = STATE.load(storage)?;
 
+    // a balance below the previous one received nothing, it must not count as received
     let balance_change = SignedInt::from_subtraction(hub_balance, state.prev_hub_balance);
-    state.actual_unbonded_amount += balance_change.0;
+    if !balance_change.1 {
+        state.actual_unbonded_amount += balance_change.0;
+    }
 
     let last_processed_batch = state.last_processed_batch;
     let mut batch_count: u64 = 0;
@@ -244,6 +509,8 @@ fn process_withdraw_rate(
         let historical_rate = history.withdraw_rate;
         let unbonded_amount = burnt_amount * historical_rate;
         total_unbonded_amount += unbonded_amount;
+        // the share paid by the unbond buffer never left the hub
+        state.actual_unbonded_amount += take_kept_share(storage, i)?;
         batch_count += 1;
         i += 1;
     }
@@ -304,7 +571,29 @@ fn process_withdraw_rate(
             // store the history and mark it as released
             history_for_i.withdraw_rate = new_withdraw_rate;
             history_for_i.released = true;
+            let released = history_for_i.amount * new_withdraw_rate;
+            // the requests paid ahead by the unbond buffer refill it instead
+            let refilled = refill_unbond_buffer(storage, iterator, released, history_for_i.amount)?;
+            if !refilled.is_zero() {
+                BATCH_CLAIMED.update(
+                    storage,
+                    U64Key::new(iterator),
+                    |previous| -> StdResult<Uint128> {
+                        Ok(previous.unwrap_or_default() + refilled)
+                    },
+                )?;
+            }
+            events.push(reconciliation_event(
+                iterator,
+                unbonded_amount_of_batch,
+                released,
+                historical_rate_of_batch,
+                new_withdraw_rate,
+            ));
             store_unbond_history(storage, iterator, history_for_i)?;
+            transition_batch(storage, iterator, BatchStatus::Released, block_time)?;
+            let unclaimed = UNCLAIMED_UNBONDED.may_load(storage)?.unwrap_or_default();
+            UNCLAIMED_UNBONDED.save(storage, &(unclaimed + released - refilled))?;
             state.last_processed_batch = iterator;
             iterator += 1;
         }
@@ -313,7 +602,34 @@ fn process_withdraw_rate(
     state.actual_unbonded_amount = Uint128::zero();
     STATE.save(storage, &state)?;
 
-    Ok(())
+    Ok(events)
+}
+
+/// The batch promised expected at its unbonding and releases what its share of the
+/// received Luna pays, a slashed undelegation pays less
+fn reconciliation_event(
+    batch_id: u64,
+    expected: Uint128,
+    released: Uint128,
+    previous_rate: Decimal,
+    withdraw_rate: Decimal,
+) -> Event {
+    let (adjustment, difference) = if released < expected {
+        ("slashed", expected - released)
+    } else if released > expected {
+        ("surplus", released - expected)
+    } else {
+        ("none", Uint128::zero())
+    };
+    Event::new("unbond_batch_reconciled").add_attributes(vec![
+        attr("batch_id", batch_id.to_string()),
+        attr("expected", expected),
+        attr("released", released),
+        attr("adjustment", adjustment),
+        attr("difference", difference),
+        attr("previous_withdraw_rate", previous_rate.to_string()),
+        attr("withdraw_rate", withdraw_rate.to_string()),
+    ])
 }
 
 fn pick_validator(
@@ -326,41 +642,148 @@ fn pick_validator(
     let params = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    let mut claimed = claim;
-
-    let all_delegations = deps
+    let delegations: Vec<(String, Uint128)> = deps
         .querier
-        .query_all_delegations(delegator)
-        .expect("There must be at least one delegation");
-
-    // pick a random validator
-    // if it does not have requested amount, undelegate all it has
-    // and pick another random validator
-    let mut iteration_index = 0;
-    let mut deletable_delegations = all_delegations;
-
-    while claimed.u128() > 0 {
-        let mut rng = XorShiftRng::seed_from_u64(block_height + iteration_index);
-        let random_index = rng.gen_range(0, deletable_delegations.len());
-        let delegation = deletable_delegations.remove(random_index);
-        let val = delegation.amount.amount;
-        let undelegated_amount: Uint128;
-        if val.u128() > claimed.u128() {
-            undelegated_amount = claimed;
-            claimed = Uint128::zero();
-        } else {
-            undelegated_amount = val;
-            claimed = (claimed.checked_sub(val))?;
-        }
-        if undelegated_amount.u128() > 0 {
-            let msgs: CosmosMsg = CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: delegation.validator,
-                amount: coin(undelegated_amount.u128(), &*coin_denom),
-            });
-            messages.push(msgs);
+        .query_all_delegations(delegator)?
+        .into_iter()
+        .map(|delegation| (delegation.validator, delegation.amount.amount))
+        .collect();
+
+    pick_undelegations(&delegations, claim, &coin_denom, block_height)
+}
+
+pub fn query_unbond_batches(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UnbondBatchesResponse> {
+    let unbonding_period = PARAMETERS.load(deps.storage)?.unbonding_period;
+    let batches = all_unbond_history(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|history| {
+            let claimed = BATCH_CLAIMED
+                .may_load(deps.storage, U64Key::new(history.batch_id))?
+                .unwrap_or_default();
+            Ok(UnbondBatch {
+                batch_id: history.batch_id,
+                requested: history.amount,
+                applied_exchange_rate: history.applied_exchange_rate,
+                release_time: history.time + unbonding_period,
+                released: history.released,
+                unclaimed: (history.amount * history.withdraw_rate).saturating_sub(claimed),
+            })
+        })
+        .collect::<StdResult<Vec<UnbondBatch>>>()?;
+
+    Ok(UnbondBatchesResponse { batches })
+}
+
+/// The rates of batch_id and the payout they derive, for the requests to check what they
+/// were paid
+pub fn query_unbond_batch_proof(deps: Deps, batch_id: u64) -> StdResult<UnbondBatchProofResponse> {
+    let history = read_unbond_history(deps.storage, batch_id)?;
+    let expected = history.amount * history.applied_exchange_rate;
+    let paid = history.amount * history.withdraw_rate;
+    Ok(UnbondBatchProofResponse {
+        batch_id,
+        requested: history.amount,
+        applied_exchange_rate: history.applied_exchange_rate,
+        expected,
+        withdraw_rate: history.withdraw_rate,
+        released: history.released,
+        paid,
+        slashing_adjustment: expected.saturating_sub(paid),
+    })
+}
+
+/// Move batch_id to the next phase, a batch never skips or goes back to a phase
+pub(crate) fn transition_batch(
+    storage: &mut dyn Storage,
+    batch_id: u64,
+    status: BatchStatus,
+    time: u64,
+) -> StdResult<()> {
+    let mut batch = match status {
+        BatchStatus::Collecting => UnbondBatchStatus {
+            batch_id,
+            status,
+            collecting_time: None,
+            undelegating_time: None,
+            released_time: None,
+        },
+        _ => read_batch_status(storage, batch_id)?,
+    };
+    if status != BatchStatus::Collecting && batch.status.next() != Some(status) {
+        return Err(StdError::generic_err(format!(
+            "Unbond batch {} cannot move from {:?} to {:?}",
+            batch_id, batch.status, status
+        )));
+    }
+
+    batch.status = status;
+    match status {
+        BatchStatus::Collecting => batch.collecting_time = Some(time),
+        BatchStatus::Undelegating => batch.undelegating_time = Some(time),
+        BatchStatus::Released => batch.released_time = Some(time),
+    }
+    BATCH_STATUS.save(storage, U64Key::new(batch_id), &batch)
+}
+
+/// The recorded phase of batch_id, inferred from its history for the batches
+/// without one
+fn read_batch_status(storage: &dyn Storage, batch_id: u64) -> StdResult<UnbondBatchStatus> {
+    if let Some(batch) = BATCH_STATUS.may_load(storage, U64Key::new(batch_id))? {
+        return Ok(batch);
+    }
+    if let Ok(history) = read_unbond_history(storage, batch_id) {
+        return Ok(UnbondBatchStatus {
+            batch_id,
+            status: if history.released {
+                BatchStatus::Released
+            } else {
+                BatchStatus::Undelegating
+            },
+            collecting_time: None,
+            undelegating_time: Some(history.time),
+            released_time: None,
+        });
+    }
+    if batch_id == CURRENT_BATCH.load(storage)?.id {
+        return Ok(UnbondBatchStatus {
+            batch_id,
+            status: BatchStatus::Collecting,
+            collecting_time: None,
+            undelegating_time: None,
+            released_time: None,
+        });
+    }
+    Err(StdError::generic_err(format!(
+        "Unbond batch {} does not exist",
+        batch_id
+    )))
+}
+
+pub fn query_unbond_batch_status(deps: Deps, batch_id: u64) -> StdResult<UnbondBatchStatus> {
+    read_batch_status(deps.storage, batch_id)
+}
+
+pub fn query_liquidity(deps: Deps) -> StdResult<LiquidityResponse> {
+    let state = STATE.load(deps.storage)?;
+
+    // the batches after the last processed one are the ones still unbonding
+    let mut unbonding = Uint128::zero();
+    let mut batch_id = state.last_processed_batch + 1;
+    while let Ok(history) = read_unbond_history(deps.storage, batch_id) {
+        if !history.released {
+            unbonding += history.amount * history.withdraw_rate;
         }
-        iteration_index += 1;
+        batch_id += 1;
     }
-    Ok(messages)
+
+    Ok(LiquidityResponse {
+        released_unclaimed: UNCLAIMED_UNBONDED
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        unbonding,
+    })
 }
@@ -0,0 +1,80 @@
+use crate::state::{WORMHOLE, WORMHOLE_RECEIPTS};
+use basset::hub::WormholeResponse;
+use basset::wormhole::{
+    UnbondReceipt, WormholeExecuteMsg, WormholeQueryMsg, WormholeStateResponse,
+};
+use cosmwasm_std::{
+    to_binary, CosmosMsg, Deps, DepsMut, Env, StdError, StdResult, Uint128, WasmMsg,
+};
+
+/// The receipt of a withdrawn unbond and the wormhole fee it pays, none when no core
+/// contract is set
+pub fn unbond_receipt_msg(
+    deps: DepsMut,
+    env: &Env,
+    recipient: &str,
+    amount: Uint128,
+    coin_denom: &str,
+) -> StdResult<Option<(CosmosMsg, Uint128)>> {
+    let core_contract = match WORMHOLE.may_load(deps.storage)? {
+        Some(core_contract) => deps.api.addr_humanize(&core_contract)?,
+        None => return Ok(None),
+    };
+
+    let state: WormholeStateResponse = deps
+        .querier
+        .query_wasm_smart(core_contract.as_str(), &WormholeQueryMsg::GetState {})?;
+    let fee = state.fee;
+    if !fee.amount.is_zero() && fee.denom != coin_denom {
+        return Err(StdError::generic_err(format!(
+            "The wormhole fee must be paid in {}",
+            coin_denom
+        )));
+    }
+    if fee.amount >= amount {
+        return Err(StdError::generic_err(
+            "The withdrawn amount does not cover the wormhole fee",
+        ));
+    }
+
+    let sequence = WORMHOLE_RECEIPTS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    WORMHOLE_RECEIPTS.save(deps.storage, &(sequence + 1))?;
+    let receipt = UnbondReceipt {
+        sequence,
+        time: env.block.time.seconds(),
+        amount,
+        recipient: recipient.to_string(),
+    };
+
+    let funds = if fee.amount.is_zero() {
+        vec![]
+    } else {
+        vec![fee.clone()]
+    };
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: core_contract.to_string(),
+        msg: to_binary(&WormholeExecuteMsg::PostMessage {
+            message: receipt.encode(),
+            // the nonce only groups messages into batches, the core contract sequences them
+            nonce: sequence as u32,
+        })?,
+        funds,
+    });
+    Ok(Some((msg, fee.amount)))
+}
+
+pub fn query_wormhole(deps: Deps) -> StdResult<WormholeResponse> {
+    let core_contract = WORMHOLE
+        .may_load(deps.storage)?
+        .map(|core_contract| deps.api.addr_humanize(&core_contract))
+        .transpose()?
+        .map(|core_contract| core_contract.to_string());
+    Ok(WormholeResponse {
+        core_contract,
+        receipts: WORMHOLE_RECEIPTS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+    })
+}
@@ -0,0 +1,85 @@
+//! Native benchmarks of the storage heavy paths of the reward contract on mock storage,
+//! e.g. to compare storage refactors before deploying them:
+//!
+//! cargo bench -p anchor_basset_reward --features bench
+
+use anchor_basset_reward::state::{
+    index_holders, read_holders, read_top_holders, store_holder, Holder, HOLDERS_INDEX_CURSOR,
+};
+use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{Addr, Api, Decimal, OwnedDeps, Uint128};
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20;
+
+/// Time routine over ROUNDS fresh set ups, only the routine is measured
+fn bench<T, S: FnMut() -> T, R: FnMut(T)>(name: &str, mut setup: S, mut routine: R) {
+    let mut total = Duration::default();
+    let mut fastest = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let input = setup();
+        let start = Instant::now();
+        routine(input);
+        let elapsed = start.elapsed();
+        total += elapsed;
+        fastest = fastest.min(elapsed);
+    }
+    println!(
+        "{:<40} mean {:>12?}   min {:>12?}",
+        name,
+        total / ROUNDS,
+        fastest
+    );
+}
+
+/// holders holders with distinct balances
+fn holders(holders: u64) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    for i in 0..holders {
+        let address = deps.api.addr_canonicalize(&format!("holder{}", i)).unwrap();
+        let holder = Holder {
+            balance: Uint128::from(i + 1),
+            index: Decimal::zero(),
+            pending_rewards: Decimal::zero(),
+        };
+        store_holder(&mut deps.storage, &address, &holder).unwrap();
+    }
+    deps
+}
+
+fn main() {
+    for count in [100, 10000] {
+        bench(
+            &format!("page through {} holders", count),
+            || holders(count),
+            |deps| {
+                let mut start_after = None;
+                loop {
+                    let page = read_holders(deps.as_ref(), start_after, Some(30)).unwrap();
+                    match page.last() {
+                        Some(last) => start_after = Some(Addr::unchecked(&last.address)),
+                        None => break,
+                    }
+                }
+            },
+        );
+        bench(
+            &format!("top holders of {} holders", count),
+            || holders(count),
+            |deps| {
+                read_top_holders(deps.as_ref(), Some(30)).unwrap();
+            },
+        );
+        bench(
+            &format!("index {} holders", count),
+            || {
+                let mut deps = holders(count);
+                HOLDERS_INDEX_CURSOR
+                    .save(&mut deps.storage, &vec![])
+                    .unwrap();
+                deps
+            },
+            |mut deps| while !index_holders(&mut deps.storage, 100).unwrap() {},
+        );
+    }
+}
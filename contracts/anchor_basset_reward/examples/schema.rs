@@ -1,10 +1,17 @@
 use std::env::current_dir;
 use std::fs::create_dir_all;
 
+use basset::migration::MigrateMsg;
+use basset::operator::OperatorResponse;
 use basset::reward::{
-    AccruedRewardsResponse, ConfigResponse, ExecuteMsg, HolderResponse, HoldersResponse,
-    InstantiateMsg, QueryMsg, StateResponse,
+    AccrualRecipientResponse, AccruedRewardsResponse, AprResponse, CommunityFeeResponse,
+    ConfigResponse, DenomMigrationsResponse, EarnConfigResponse, ExecuteMsg, FeesCollectedResponse,
+    HolderDistributionResponse, HolderResponse, HoldersResponse, HubsResponse, IndexDeltaResponse,
+    InstantiateMsg, KeeperReserveResponse, PendingChangesResponse, PrecisionReportResponse,
+    QueryMsg, RegisteredContractsResponse, RewardDepositResponse, RewardRecipientResponse,
+    SolvencyResponse, StateResponse, SwapHistoryResponse, SwapRoutesResponse, TopHoldersResponse,
 };
+use basset::snapshot::ExportStateResponse;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 fn main() {
@@ -16,9 +23,31 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
+    export_schema(&schema_for!(RegisteredContractsResponse), &out_dir);
     export_schema(&schema_for!(AccruedRewardsResponse), &out_dir);
+    export_schema(&schema_for!(IndexDeltaResponse), &out_dir);
     export_schema(&schema_for!(HolderResponse), &out_dir);
     export_schema(&schema_for!(HoldersResponse), &out_dir);
+    export_schema(&schema_for!(TopHoldersResponse), &out_dir);
+    export_schema(&schema_for!(HolderDistributionResponse), &out_dir);
+    export_schema(&schema_for!(SwapRoutesResponse), &out_dir);
+    export_schema(&schema_for!(SwapHistoryResponse), &out_dir);
+    export_schema(&schema_for!(DenomMigrationsResponse), &out_dir);
+    export_schema(&schema_for!(EarnConfigResponse), &out_dir);
+    export_schema(&schema_for!(RewardDepositResponse), &out_dir);
+    export_schema(&schema_for!(RewardRecipientResponse), &out_dir);
+    export_schema(&schema_for!(SolvencyResponse), &out_dir);
+    export_schema(&schema_for!(PrecisionReportResponse), &out_dir);
+    export_schema(&schema_for!(AprResponse), &out_dir);
+    export_schema(&schema_for!(CommunityFeeResponse), &out_dir);
+    export_schema(&schema_for!(FeesCollectedResponse), &out_dir);
+    export_schema(&schema_for!(KeeperReserveResponse), &out_dir);
+    export_schema(&schema_for!(HubsResponse), &out_dir);
+    export_schema(&schema_for!(PendingChangesResponse), &out_dir);
+    export_schema(&schema_for!(AccrualRecipientResponse), &out_dir);
+    export_schema(&schema_for!(OperatorResponse), &out_dir);
+    export_schema(&schema_for!(ExportStateResponse), &out_dir);
 }
@@ -1,29 +1,108 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
-use crate::global::{execute_swap, execute_update_global_index};
-use crate::state::{read_config, read_state, store_config, store_state, Config, State};
+use crate::denom::{
+    execute_complete_reward_denom_migration, execute_migrate_reward_denom, query_denom_migrations,
+};
+use crate::earn::{
+    execute_update_earn_config, execute_update_reward_deposit, handle_deposit_reply,
+    query_earn_config, query_reward_deposit, DEPOSIT_REPLY_ID,
+};
+use crate::fee::{execute_update_community_fee, query_community_fee, query_fees_collected};
+use crate::global::{
+    execute_swap, execute_update_global_index, handle_swap_reply, query_apr, SWAP_REPLY_ID_OFFSET,
+};
+use crate::hubs::{execute_add_hub, execute_remove_hub, query_hubs};
+use crate::keeper::{execute_fund_keeper_reserve, execute_pay_keeper_bounty, query_keeper_reserve};
+use crate::payout::{execute_update_pro_rata_claims, query_solvency};
+use crate::querier::{assert_hub_owner, query_hub_owner};
+use crate::state::{
+    read_config, read_state, store_config, store_state, Config, State, CONFIG_KEY,
+    HOLDERS_INDEX_CURSOR, OPERATOR,
+};
+use crate::swap::{
+    execute_deregister_swap_route, execute_register_swap_route, query_swap_history,
+    query_swap_routes,
+};
+use crate::tiers::{execute_update_holder_tiers, query_holder_distribution};
+use crate::timelock::{
+    execute_apply_pending_change, execute_cancel_pending_change, execute_propose_pending_change,
+    query_pending_changes,
+};
 use crate::user::{
-    execute_claim_rewards, execute_decrease_balance, execute_increase_balance,
-    query_accrued_rewards, query_holder, query_holders,
+    execute_claim_rewards, execute_claim_rewards_as, execute_claim_rewards_for,
+    execute_decrease_balance, execute_increase_balance, execute_index_holders,
+    execute_migrate_holder, execute_register_token_contract, execute_set_reward_recipient,
+    execute_sync_holder, execute_update_reward_recipient, query_accrual_recipient,
+    query_accrued_rewards, query_accrued_rewards_at, query_holder, query_holders,
+    query_index_delta, query_precision_report, query_registered_contracts, query_reward_recipient,
+    query_top_holders,
 };
 use cosmwasm_std::{
-    to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    attr, from_slice, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult, Storage, Uint128,
 };
 
+use basset::astroport::AssetInfo;
+use basset::contract_error::ContractError;
+use basset::migration::{
+    assert_state_version, init_version, migrate_state, MigrateMsg, MigrationStep, VERSIONING_STEP,
+};
 use basset::reward::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse};
+use basset::snapshot::{
+    assert_not_importing, export_state, finish_import, import_state, open_import, StateEntry,
+};
+use basset::validation::{assert_no_funds, validate_cw20_address, validate_native_denom};
 use terra_cosmwasm::TerraMsgWrapper;
 
+const CONTRACT_NAME: &str = "crates.io:anchor-basset-reward";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Bump together with a new `MigrationStep` whenever the storage layout changes.
+const STATE_VERSION: u64 = 2;
+const MIGRATION_STEPS: &[MigrationStep] = &[VERSIONING_STEP, HOLDERS_INDEX_STEP];
+
+/// The holders are indexed by balance from version 2, lazily since there are too many
+/// to index within the migration, see ExecuteMsg::IndexHolders
+const HOLDERS_INDEX_STEP: MigrationStep = MigrationStep {
+    from: 1,
+    upgrade: start_holders_index,
+};
+
+fn start_holders_index(storage: &mut dyn Storage) -> StdResult<()> {
+    HOLDERS_INDEX_CURSOR.save(storage, &vec![])
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    let reward_asset = msg.reward_asset.unwrap_or(AssetInfo::NativeToken {
+        denom: msg.reward_denom.clone(),
+    });
+    let (reward_denom, cw20_rewards) = match reward_asset {
+        AssetInfo::NativeToken { denom } => {
+            validate_native_denom(&denom)?;
+            (denom, false)
+        }
+        AssetInfo::Token { contract_addr } => (
+            validate_cw20_address(deps.api, &contract_addr)?.to_string(),
+            true,
+        ),
+    };
+    if reward_denom != msg.reward_denom {
+        return Err(StdError::generic_err(
+            "reward_denom must be the denom or the token address of reward_asset",
+        )
+        .into());
+    }
+
     let conf = Config {
         hub_contract: deps.api.addr_canonicalize(&msg.hub_contract)?,
-        reward_denom: msg.reward_denom,
+        reward_denom,
+        cw20_rewards,
     };
 
     store_config(deps.storage, &conf)?;
@@ -36,6 +115,9 @@ pub fn instantiate(
         },
     )?;
 
+    init_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION, STATE_VERSION)?;
+    open_import(deps.storage)?;
+
     Ok(Response::default())
 }
 
@@ -45,30 +127,244 @@ pub fn execute(
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
-) -> StdResult<Response<TerraMsgWrapper>> {
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    // a snapshot may hold an older state version, which is migrated once imported
+    match msg {
+        ExecuteMsg::ImportState { entries } => {
+            return execute_import_state(deps, env, info, entries)
+        }
+        ExecuteMsg::FinishImport {} => return execute_finish_import(deps, env, info),
+        _ => assert_not_importing(deps.storage)?,
+    }
+    assert_state_version(deps.storage, STATE_VERSION)?;
+    assert_no_funds(&info)?;
+
     match msg {
         ExecuteMsg::ClaimRewards { recipient } => execute_claim_rewards(deps, env, info, recipient),
+        ExecuteMsg::ClaimRewardsAs { denom, max_spread } => {
+            execute_claim_rewards_as(deps, env, info, denom, max_spread)
+        }
         ExecuteMsg::SwapToRewardDenom {} => execute_swap(deps, env, info),
         ExecuteMsg::UpdateGlobalIndex {} => execute_update_global_index(deps, env, info),
+        ExecuteMsg::RegisterTokenContract { token_contract } => {
+            execute_register_token_contract(deps, env, info, token_contract)
+        }
         ExecuteMsg::IncreaseBalance { address, amount } => {
             execute_increase_balance(deps, env, info, address, amount)
         }
         ExecuteMsg::DecreaseBalance { address, amount } => {
             execute_decrease_balance(deps, env, info, address, amount)
         }
+        ExecuteMsg::FundKeeperReserve { target } => {
+            execute_fund_keeper_reserve(deps, env, info, target)
+        }
+        ExecuteMsg::PayKeeperBounty { recipient, amount } => {
+            execute_pay_keeper_bounty(deps, env, info, recipient, amount)
+        }
+        ExecuteMsg::ClaimRewardsFor { address } => {
+            execute_claim_rewards_for(deps, env, info, address)
+        }
+        ExecuteMsg::RegisterSwapRoute { offer_denom, hops } => {
+            execute_register_swap_route(deps, env, info, offer_denom, hops)
+        }
+        ExecuteMsg::DeregisterSwapRoute { offer_denom } => {
+            execute_deregister_swap_route(deps, env, info, offer_denom)
+        }
+        ExecuteMsg::UpdateOperator { operator } => {
+            execute_update_operator(deps, env, info, operator)
+        }
+        ExecuteMsg::UpdateEarnConfig { earn } => execute_update_earn_config(deps, env, info, earn),
+        ExecuteMsg::UpdateCommunityFee { fee } => {
+            execute_update_community_fee(deps, env, info, fee)
+        }
+        ExecuteMsg::UpdateProRataClaims { enabled } => {
+            execute_update_pro_rata_claims(deps, env, info, enabled)
+        }
+        ExecuteMsg::MigrateRewardDenom { new_denom } => {
+            execute_migrate_reward_denom(deps, env, info, new_denom)
+        }
+        ExecuteMsg::CompleteRewardDenomMigration {} => {
+            execute_complete_reward_denom_migration(deps, env, info)
+        }
+        ExecuteMsg::AddHub { hub } => execute_add_hub(deps, env, info, hub),
+        ExecuteMsg::RemoveHub { hub } => execute_remove_hub(deps, env, info, hub),
+        ExecuteMsg::ProposePendingChange { change } => {
+            execute_propose_pending_change(deps, env, info, change)
+        }
+        ExecuteMsg::ApplyPendingChange { id } => execute_apply_pending_change(deps, env, info, id),
+        ExecuteMsg::CancelPendingChange { id } => {
+            execute_cancel_pending_change(deps, env, info, id)
+        }
+        ExecuteMsg::UpdateHolderTiers { min_balances } => {
+            execute_update_holder_tiers(deps, env, info, min_balances)
+        }
+        ExecuteMsg::UpdateRewardDeposit { deposit } => {
+            execute_update_reward_deposit(deps, env, info, deposit)
+        }
+        ExecuteMsg::SetRewardRecipient { recipient } => {
+            execute_set_reward_recipient(deps, env, info, recipient)
+        }
+        ExecuteMsg::UpdateRewardRecipient { recipient } => {
+            execute_update_reward_recipient(deps, env, info, recipient)
+        }
+        ExecuteMsg::IndexHolders { limit } => execute_index_holders(deps, env, info, limit),
+        ExecuteMsg::SyncHolder { address } => execute_sync_holder(deps, env, info, address),
+        ExecuteMsg::MigrateHolder { new_address } => {
+            execute_migrate_holder(deps, env, info, new_address)
+        }
+        ExecuteMsg::ImportState { .. } | ExecuteMsg::FinishImport {} => {
+            unreachable!("imports are handled before the state version check")
+        }
     }
 }
 
+/// Set the operator allowed to manage the swap routes.
+/// Only the owner of the hub and the operator are allowed to execute
+pub fn execute_update_operator(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: Option<String>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    let owner = query_hub_owner(deps.as_ref(), deps.api.addr_humanize(&config.hub_contract)?)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    OPERATOR.update(
+        deps.storage,
+        deps.api,
+        &owner,
+        &sender_raw,
+        operator.clone(),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_operator"),
+        attr("operator", operator.unwrap_or_default()),
+    ]))
+}
+
+/// Restore a page of exported state, e.g. on a fresh code id after a failed migration.
+/// Only the owner of the hub is allowed to execute, before the first holder
+pub fn execute_import_state(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    entries: Vec<StateEntry>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    for entry in entries.iter() {
+        if entry.key.as_slice() == CONFIG_KEY.as_bytes()
+            && from_slice::<Config>(entry.value.as_slice())?.hub_contract != config.hub_contract
+        {
+            return Err(StdError::generic_err("The imported state belongs to another hub").into());
+        }
+    }
+
+    let count = entries.len();
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    import_state(deps.storage, &sender_raw, &[CONFIG_KEY.as_bytes()], entries)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "import_state"),
+        attr("entries", count.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    if msg.id == DEPOSIT_REPLY_ID {
+        return handle_deposit_reply(deps, env, msg);
+    }
+    if msg.id >= SWAP_REPLY_ID_OFFSET {
+        return handle_swap_reply(deps, env, msg);
+    }
+
+    Err(StdError::generic_err(format!("Unknown reply id {}", msg.id)).into())
+}
+
+/// Close the import for good.
+/// Only the sender of the first imported page is allowed to execute
+pub fn execute_finish_import(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    finish_import(deps.storage, &sender_raw)?;
+
+    Ok(Response::new().add_attribute("action", "finish_import"))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let previous = migrate_state(
+        deps.storage,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        &[],
+        STATE_VERSION,
+        MIGRATION_STEPS,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("from_state_version", previous.to_string()),
+        attr("to_state_version", STATE_VERSION.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::RegisteredContracts {} => to_binary(&query_registered_contracts(deps)?),
+        QueryMsg::Hubs {} => to_binary(&query_hubs(deps)?),
+        QueryMsg::Operator {} => to_binary(&OPERATOR.query(deps.storage, deps.api)?),
         QueryMsg::AccruedRewards { address } => to_binary(&query_accrued_rewards(deps, address)?),
+        QueryMsg::AccruedRewardsAt {
+            address,
+            global_index,
+        } => to_binary(&query_accrued_rewards_at(deps, address, global_index)?),
+        QueryMsg::PrecisionReport {} => to_binary(&query_precision_report(deps)?),
+        QueryMsg::IndexDelta {
+            address,
+            since_index,
+        } => to_binary(&query_index_delta(deps, address, since_index)?),
         QueryMsg::Holder { address } => to_binary(&query_holder(deps, address)?),
         QueryMsg::Holders { start_after, limit } => {
             to_binary(&query_holders(deps, start_after, limit)?)
         }
+        QueryMsg::TopHolders { limit } => to_binary(&query_top_holders(deps, limit)?),
+        QueryMsg::HolderDistribution {} => to_binary(&query_holder_distribution(deps)?),
+        QueryMsg::SwapRoutes { start_after, limit } => {
+            to_binary(&query_swap_routes(deps, start_after, limit)?)
+        }
+        QueryMsg::SwapHistory {} => to_binary(&query_swap_history(deps)?),
+        QueryMsg::DenomMigrations {} => to_binary(&query_denom_migrations(deps)?),
+        QueryMsg::EarnConfig {} => to_binary(&query_earn_config(deps)?),
+        QueryMsg::CommunityFee {} => to_binary(&query_community_fee(deps)?),
+        QueryMsg::FeesCollected {} => to_binary(&query_fees_collected(deps)?),
+        QueryMsg::KeeperReserve {} => to_binary(&query_keeper_reserve(deps)?),
+        QueryMsg::Solvency {} => to_binary(&query_solvency(deps, env)?),
+        QueryMsg::RewardDeposit { address } => to_binary(&query_reward_deposit(deps, address)?),
+        QueryMsg::RewardRecipient { address } => to_binary(&query_reward_recipient(deps, address)?),
+        QueryMsg::AccrualRecipient { address } => {
+            to_binary(&query_accrual_recipient(deps, address)?)
+        }
+        QueryMsg::Apr { period } => to_binary(&query_apr(deps, period)?),
+        QueryMsg::ExportState { start_after, limit } => {
+            to_binary(&export_state(deps.storage, start_after, limit)?)
+        }
+        QueryMsg::PendingChanges { start_after, limit } => {
+            to_binary(&query_pending_changes(deps, start_after, limit)?)
+        }
     }
 }
 
@@ -76,7 +372,8 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config: Config = read_config(deps.storage)?;
     Ok(ConfigResponse {
         hub_contract: deps.api.addr_humanize(&config.hub_contract)?.to_string(),
-        reward_denom: config.reward_denom,
+        reward_denom: config.reward_denom.clone(),
+        reward_asset: config.reward_asset(),
     })
 }
 
@@ -0,0 +1,189 @@
+use crate::payout::assert_native_rewards;
+use crate::querier::assert_hub_owner;
+use crate::state::{
+    read_config, read_state, store_config, store_state, PendingDenomMigration, DENOM_MIGRATIONS,
+    EARN, INDEX_HISTORY, KEEPER_RESERVE, PENDING_DENOM_MIGRATION, SWAP_ROUTES,
+};
+use crate::swap::check_swap_run;
+
+use basset::contract_error::ContractError;
+use basset::math::decimal_multiplication_in_256;
+use basset::reward::{DenomMigration, DenomMigrationsResponse, ExecuteMsg};
+use basset::validation::validate_native_denom;
+
+use cosmwasm_std::{
+    attr, to_binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Uint128, WasmMsg,
+};
+use cw_storage_plus::U64Key;
+use terra_cosmwasm::{create_swap_msg, TerraMsgWrapper};
+
+/// Swap the reward balance to new_denom, the contract completes the migration once the
+/// swap returned, within the same transaction. A failing swap reverts the migration
+/// Only the owner of the hub is allowed to execute
+pub fn execute_migrate_reward_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_denom: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+    assert_native_rewards(&config)?;
+
+    validate_native_denom(&new_denom)?;
+    if new_denom == config.reward_denom {
+        return Err(
+            StdError::generic_err("The new reward denom must differ from the current one").into(),
+        );
+    }
+    if cfg!(feature = "no-market") {
+        return Err(StdError::generic_err(
+            "The reward denom is migrated through the market module",
+        )
+        .into());
+    }
+    if EARN.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err(
+            "Stop depositing into Anchor Earn before migrating the reward denom",
+        )
+        .into());
+    }
+    if SWAP_ROUTES.has(deps.storage, &new_denom) {
+        return Err(StdError::generic_err(format!(
+            "Deregister the swap route of {} before migrating the reward denom to it",
+            new_denom
+        ))
+        .into());
+    }
+
+    // the last swap run is settled in the old denom
+    let offer = deps
+        .querier
+        .query_balance(env.contract.address.clone(), config.reward_denom.as_str())?;
+    check_swap_run(deps.storage, &deps.querier, &env, offer.amount)?;
+
+    let new_denom_balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), new_denom.as_str())?
+        .amount;
+    let pending = PendingDenomMigration {
+        old_denom: config.reward_denom,
+        new_denom,
+        offer_amount: offer.amount,
+        new_denom_balance,
+    };
+
+    let attributes = vec![
+        attr("action", "migrate_reward_denom"),
+        attr("old_denom", pending.old_denom.clone()),
+        attr("new_denom", pending.new_denom.clone()),
+        attr("offer_amount", offer.amount),
+    ];
+
+    // nothing is accrued without a balance, the denom is switched right away
+    if offer.amount.is_zero() {
+        let res = complete_migration(deps, &env, pending, Uint128::zero())?;
+        return Ok(res.add_attributes(attributes));
+    }
+
+    PENDING_DENOM_MIGRATION.save(deps.storage, &pending)?;
+    Ok(Response::new()
+        .add_message(create_swap_msg(offer, pending.new_denom))
+        .add_message(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::CompleteRewardDenomMigration {})?,
+            funds: vec![],
+        })
+        .add_attributes(attributes))
+}
+
+/// Rebase the accruals at the rate the swap returned
+/// Only the reward contract itself is allowed to execute
+pub fn execute_complete_reward_denom_migration(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let pending = PENDING_DENOM_MIGRATION
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No reward denom migration is pending"))?;
+    PENDING_DENOM_MIGRATION.remove(deps.storage);
+
+    let returned = deps
+        .querier
+        .query_balance(env.contract.address.clone(), pending.new_denom.as_str())?
+        .amount
+        .checked_sub(pending.new_denom_balance)?;
+    complete_migration(deps, &env, pending, returned)
+}
+
+fn complete_migration(
+    deps: DepsMut,
+    env: &Env,
+    pending: PendingDenomMigration,
+    returned: Uint128,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let rate = if pending.offer_amount.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(returned, pending.offer_amount)
+    };
+
+    // the reserve rounds down, the rewards owed to the holders round up within the balance
+    let mut state = read_state(deps.storage)?;
+    let global_index = state.global_index;
+    let reserve = KEEPER_RESERVE.may_load(deps.storage)?.unwrap_or_default() * rate;
+    let owed =
+        decimal_multiplication_in_256(Decimal::from_ratio(state.prev_reward_balance, 1u128), rate);
+    let mut prev_reward_balance = owed * Uint128::new(1);
+    if Decimal::from_ratio(prev_reward_balance, 1u128) < owed {
+        prev_reward_balance += Uint128::new(1);
+    }
+    state.prev_reward_balance = prev_reward_balance.min(returned.saturating_sub(reserve));
+    state.global_index = decimal_multiplication_in_256(global_index, rate);
+    store_state(deps.storage, &state)?;
+    KEEPER_RESERVE.save(deps.storage, &reserve)?;
+
+    // the holders are rebased as they are read, see read_holder
+    let mut migrations = DENOM_MIGRATIONS.may_load(deps.storage)?.unwrap_or_default();
+    migrations.push(DenomMigration {
+        old_denom: pending.old_denom,
+        new_denom: pending.new_denom.clone(),
+        rate,
+        global_index,
+        time: env.block.time.seconds(),
+    });
+    DENOM_MIGRATIONS.save(deps.storage, &migrations)?;
+    INDEX_HISTORY.save(
+        deps.storage,
+        U64Key::new(env.block.time.seconds()),
+        &state.global_index,
+    )?;
+
+    let mut config = read_config(deps.storage)?;
+    config.reward_denom = pending.new_denom;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "complete_reward_denom_migration"),
+        attr("returned", returned),
+        attr("rate", rate.to_string()),
+    ]))
+}
+
+/// The index history before the last migration is in another denom
+pub fn last_migration_time(deps: Deps) -> StdResult<Option<u64>> {
+    Ok(DENOM_MIGRATIONS
+        .may_load(deps.storage)?
+        .and_then(|migrations| migrations.last().map(|migration| migration.time)))
+}
+
+pub fn query_denom_migrations(deps: Deps) -> StdResult<DenomMigrationsResponse> {
+    Ok(DenomMigrationsResponse {
+        migrations: DENOM_MIGRATIONS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,180 @@
+use crate::payout::assert_native_rewards;
+use crate::querier::assert_hub_owner;
+use crate::state::{
+    read_config, Earn, EARN, EXECUTION_LOCK, PENDING_DEPOSIT, REWARD_DEPOSITS, TIMELOCK,
+};
+
+use basset::contract_error::ContractError;
+use basset::money_market::MarketExecuteMsg;
+use basset::querier::query_token_balance;
+use basset::reward::{EarnConfig, EarnConfigResponse, RewardDepositResponse};
+
+use cosmwasm_std::{
+    attr, to_binary, Addr, CanonicalAddr, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Reply,
+    Response, StdError, StdResult, Storage, SubMsg, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Reply id of the deposit of claimed rewards, the swaps use the ids after it
+pub const DEPOSIT_REPLY_ID: u64 = 0;
+
+/// Set the Anchor money market claimed rewards are deposited into
+/// Only the owner of the hub is allowed to execute. Setting a market goes through
+/// ProposePendingChange while the timelock is active, removing it does not
+pub fn execute_update_earn_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    earn: Option<EarnConfig>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    match earn {
+        Some(earn) => {
+            if TIMELOCK.is_active(deps.storage)? {
+                return Err(ContractError::Timelocked {});
+            }
+            set_earn_config(deps, earn)
+        }
+        None => {
+            EARN.remove(deps.storage);
+            Ok(Response::new().add_attribute("action", "remove_earn_config"))
+        }
+    }
+}
+
+pub(crate) fn set_earn_config(
+    deps: DepsMut,
+    earn: EarnConfig,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_native_rewards(&config)?;
+    EARN.save(
+        deps.storage,
+        &Earn {
+            market_contract: deps.api.addr_canonicalize(&earn.market_contract)?,
+            aterra_contract: deps.api.addr_canonicalize(&earn.aterra_contract)?,
+            deposit_by_default: earn.deposit_by_default,
+        },
+    )?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_earn_config"),
+        attr("market_contract", earn.market_contract),
+        attr("deposit_by_default", earn.deposit_by_default.to_string()),
+    ]))
+}
+
+/// Opt in or out of depositing the claimed rewards of the sender
+pub fn execute_update_reward_deposit(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    deposit: Option<bool>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let holder = deps.api.addr_canonicalize(info.sender.as_str())?;
+    match deposit {
+        Some(deposit) => REWARD_DEPOSITS.save(deps.storage, holder.as_slice(), &deposit)?,
+        None => REWARD_DEPOSITS.remove(deps.storage, holder.as_slice()),
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_reward_deposit"),
+        attr("holder_address", info.sender),
+        attr(
+            "deposit",
+            deposit.map_or("default".to_string(), |deposit| deposit.to_string()),
+        ),
+    ]))
+}
+
+/// The money market the claimed rewards of holder are deposited into, if any
+pub fn read_reward_deposit(
+    storage: &dyn Storage,
+    holder: &CanonicalAddr,
+) -> StdResult<Option<Earn>> {
+    let earn = match EARN.may_load(storage)? {
+        Some(earn) => earn,
+        None => return Ok(None),
+    };
+    let deposit = REWARD_DEPOSITS
+        .may_load(storage, holder.as_slice())?
+        .unwrap_or(earn.deposit_by_default);
+    Ok(if deposit { Some(earn) } else { None })
+}
+
+/// Deposit the claimed rewards, the reply hands the minted aTerra to the recipient
+pub fn create_deposit_msg(
+    deps: DepsMut,
+    earn: &Earn,
+    recipient: Addr,
+    rewards: Coin,
+) -> Result<SubMsg<TerraMsgWrapper>, ContractError> {
+    EXECUTION_LOCK.lock(deps.storage)?;
+    PENDING_DEPOSIT.save(deps.storage, &recipient)?;
+    Ok(SubMsg::reply_on_success(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&earn.market_contract)?.to_string(),
+            msg: to_binary(&MarketExecuteMsg::DepositStable {})?,
+            funds: vec![rewards],
+        }),
+        DEPOSIT_REPLY_ID,
+    ))
+}
+
+/// The contract holds no other aTerra, so its whole balance was minted by the deposit
+pub fn handle_deposit_reply(
+    deps: DepsMut,
+    env: Env,
+    _msg: Reply,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let recipient = PENDING_DEPOSIT.load(deps.storage)?;
+    PENDING_DEPOSIT.remove(deps.storage);
+    EXECUTION_LOCK.unlock(deps.storage);
+
+    let earn = EARN.load(deps.storage)?;
+    let aterra_contract = deps.api.addr_humanize(&earn.aterra_contract)?;
+    let amount = query_token_balance(
+        &deps.querier,
+        aterra_contract.as_str(),
+        env.contract.address.as_str(),
+    )?;
+    if amount.is_zero() {
+        return Err(StdError::generic_err("The deposit minted no aTerra").into());
+    }
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: aterra_contract.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })
+        .add_attributes(vec![
+            attr("action", "deposit_reward"),
+            attr("recipient", recipient),
+            attr("aterra_amount", amount),
+        ]))
+}
+
+pub fn query_earn_config(deps: Deps) -> StdResult<EarnConfigResponse> {
+    let earn = match EARN.may_load(deps.storage)? {
+        Some(earn) => Some(EarnConfig {
+            market_contract: deps.api.addr_humanize(&earn.market_contract)?.to_string(),
+            aterra_contract: deps.api.addr_humanize(&earn.aterra_contract)?.to_string(),
+            deposit_by_default: earn.deposit_by_default,
+        }),
+        None => None,
+    };
+    Ok(EarnConfigResponse { earn })
+}
+
+pub fn query_reward_deposit(deps: Deps, address: String) -> StdResult<RewardDepositResponse> {
+    let holder = deps.api.addr_canonicalize(&address)?;
+    Ok(RewardDepositResponse {
+        deposit: read_reward_deposit(deps.storage, &holder)?.is_some(),
+    })
+}
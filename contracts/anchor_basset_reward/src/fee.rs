@@ -0,0 +1,131 @@
+use crate::payout::{assert_native_rewards, reward_payout_msg};
+use crate::querier::assert_hub_owner;
+use crate::state::{read_config, Config, COMMUNITY_FEE, FEES_COLLECTED, TIMELOCK};
+
+use basset::contract_error::ContractError;
+use basset::distribution::{encode_msg_fund_community_pool, MSG_FUND_COMMUNITY_POOL_TYPE_URL};
+use basset::reward::{CommunityFee, CommunityFeeResponse, FeeDestination, FeesCollectedResponse};
+
+use cosmwasm_std::{
+    attr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Order, Response,
+    StdError, StdResult, Uint128,
+};
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// The largest share of the rewards the community fee may take, in percent
+const MAX_COMMUNITY_FEE_PERCENT: u64 = 20;
+
+/// Set the protocol fee taken out of the rewards of every index update
+/// Only the owner of the hub is allowed to execute. Setting a fee goes through
+/// ProposePendingChange while the timelock is active, removing it does not
+pub fn execute_update_community_fee(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    fee: Option<CommunityFee>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    match fee {
+        Some(fee) => {
+            if TIMELOCK.is_active(deps.storage)? {
+                return Err(ContractError::Timelocked {});
+            }
+            set_community_fee(deps, fee)
+        }
+        None => {
+            COMMUNITY_FEE.remove(deps.storage);
+            Ok(Response::new().add_attribute("action", "remove_community_fee"))
+        }
+    }
+}
+
+pub(crate) fn set_community_fee(
+    deps: DepsMut,
+    fee: CommunityFee,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    let max_rate = Decimal::percent(MAX_COMMUNITY_FEE_PERCENT);
+    if fee.rate > max_rate {
+        return Err(StdError::generic_err(format!(
+            "The fee rate must not be more than {}",
+            max_rate
+        ))
+        .into());
+    }
+    match &fee.destination {
+        FeeDestination::Address { address } => {
+            deps.api.addr_validate(address)?;
+        }
+        FeeDestination::CommunityPool {} => assert_native_rewards(&config)?,
+    }
+    COMMUNITY_FEE.save(deps.storage, &fee)?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_community_fee"),
+        attr("rate", fee.rate.to_string()),
+    ]))
+}
+
+/// The fee taken out of the claimed rewards and the message sending it, with its own event.
+/// None when no fee is taken
+pub fn community_fee_msg(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    claimed_rewards: Uint128,
+) -> Result<Option<(CosmosMsg<TerraMsgWrapper>, Uint128, Event)>, ContractError> {
+    let fee = match COMMUNITY_FEE.may_load(deps.storage)? {
+        Some(fee) => fee,
+        None => return Ok(None),
+    };
+    let amount = claimed_rewards * fee.rate;
+    if amount.is_zero() {
+        return Ok(None);
+    }
+
+    let (msg, destination) = match fee.destination {
+        FeeDestination::CommunityPool {} => {
+            assert_native_rewards(config)?;
+            (
+                CosmosMsg::Stargate {
+                    type_url: MSG_FUND_COMMUNITY_POOL_TYPE_URL.to_string(),
+                    value: encode_msg_fund_community_pool(
+                        &[Coin::new(amount.u128(), &config.reward_denom)],
+                        env.contract.address.as_str(),
+                    ),
+                },
+                "community_pool".to_string(),
+            )
+        }
+        FeeDestination::Address { address } => (
+            reward_payout_msg(deps, config, address.clone(), amount)?,
+            address,
+        ),
+    };
+    let event = Event::new("community_fee")
+        .add_attribute("amount", amount)
+        .add_attribute("destination", destination);
+
+    Ok(Some((msg, amount, event)))
+}
+
+pub fn query_community_fee(deps: Deps) -> StdResult<CommunityFeeResponse> {
+    Ok(CommunityFeeResponse {
+        fee: COMMUNITY_FEE.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_fees_collected(deps: Deps) -> StdResult<FeesCollectedResponse> {
+    let fees = FEES_COLLECTED
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, amount) = item?;
+            Ok(Coin {
+                denom: String::from_utf8(denom)?,
+                amount,
+            })
+        })
+        .collect::<StdResult<Vec<Coin>>>()?;
+    Ok(FeesCollectedResponse { fees })
+}
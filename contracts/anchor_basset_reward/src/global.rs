@@ -1,108 +1,311 @@
-use crate::state::{read_config, read_state, store_state, Config, State};
+use crate::denom::last_migration_time;
+use crate::fee::community_fee_msg;
+use crate::hubs::assert_hub;
+use crate::payout::query_reward_balance;
+use crate::querier::query_tax_free;
+use crate::state::{
+    read_config, read_state, store_state, Config, PendingSwap, State, FEES_COLLECTED,
+    INDEX_HISTORY, KEEPER_RESERVE, PENDING_SWAPS, SWAP_ROUTES,
+};
+use crate::swap::{check_swap_run, create_hop_swap_msg, record_swap_error, store_swap_run};
 
-use crate::math::decimal_summation_in_256;
+use basset::contract_error::ContractError;
+use basset::math::{
+    decimal_division_in_256, decimal_multiplication_in_256, decimal_subtraction_in_256,
+    decimal_summation_in_256,
+};
+use basset::oracle::query_oracle_price;
+use basset::querier::{query_exchange_rate, query_hub_parameters, query_hub_price_guard};
+use basset::reward::{AprResponse, SwapReport};
+use basset::{treasury, TaxCache};
 
 use cosmwasm_std::{
-    attr, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    attr, Coin, ContractResult, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, SubMsg, Uint128,
 };
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
 use terra_cosmwasm::{create_swap_msg, ExchangeRatesResponse, TerraMsgWrapper, TerraQuerier};
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Reply ids of the swaps start here, offset by the index of the swapped coin
+pub const SWAP_REPLY_ID_OFFSET: u64 = 1;
+
 /// Swap all native tokens to reward_denom
-/// Only hub_contract is allowed to execute
-#[allow(clippy::if_same_then_else)]
+/// Only the hubs are allowed to execute
 pub fn execute_swap(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-) -> StdResult<Response<TerraMsgWrapper>> {
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let config = read_config(deps.storage)?;
-    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    assert_hub(deps.as_ref(), &config, &info.sender)?;
 
-    if sender_raw != config.hub_contract {
-        return Err(StdError::generic_err("unauthorized"));
-    }
-
-    let contr_addr = env.contract.address;
-    let balance = deps.querier.query_all_balances(contr_addr)?;
-    let mut messages: Vec<CosmosMsg<TerraMsgWrapper>> = Vec::new();
+    let contr_addr = env.contract.address.clone();
+    let mut balance = deps.querier.query_all_balances(contr_addr)?;
+    // the order of the balances is up to the querier, the swaps and their reply ids are not
+    balance.sort_by(|a, b| a.denom.cmp(&b.denom));
 
-    let reward_denom = config.reward_denom;
+    let reward_denom = config.reward_denom.clone();
 
-    let mut is_listed = true;
+    // without the market module only the denoms routed through Astroport are swapped,
+    // the market does not return cw20 tokens either
+    let market = cfg!(not(feature = "no-market")) && !config.cw20_rewards;
+    let mut is_listed = market;
 
     let denoms: Vec<String> = balance.iter().map(|item| item.denom.clone()).collect();
 
-    if query_exchange_rates(&deps, reward_denom.clone(), denoms).is_err() {
+    if market && query_exchange_rates(&deps, reward_denom.clone(), denoms).is_err() {
         is_listed = false;
     }
 
+    // the routed swaps deduct the tax of each denom, the treasury is queried once per value
+    let tax_free = query_tax_free(deps.as_ref(), &config.hub_contract)?;
+    let mut tax = TaxCache::new(treasury(&deps.querier, tax_free));
+
+    // a failing swap must not revert the others, its reply reports the denom
+    let mut pending: Vec<PendingSwap> = Vec::new();
+    let mut messages: Vec<SubMsg<TerraMsgWrapper>> = Vec::new();
+    let mut attributes = vec![attr("action", "swap"), attr("sender", info.sender)];
+    let mut reports: Vec<SwapReport> = Vec::new();
+    let mut reward_balance = if config.cw20_rewards {
+        query_reward_balance(&deps.querier, &env, &config)?
+    } else {
+        Uint128::zero()
+    };
     for coin in balance {
         if coin.denom == reward_denom.clone() {
+            reward_balance = coin.amount;
             continue;
         }
-        if is_listed {
-            messages.push(create_swap_msg(coin, reward_denom.to_string()));
-        } else if query_exchange_rates(&deps, reward_denom.clone(), vec![coin.denom.clone()])
-            .is_ok()
+        let id = SWAP_REPLY_ID_OFFSET + pending.len() as u64;
+        let denom = coin.denom.clone();
+        let mut report = SwapReport {
+            offer_denom: coin.denom.clone(),
+            offer_amount: coin.amount,
+            remaining: None,
+            error: None,
+        };
+        if let Some(hops) = SWAP_ROUTES.may_load(deps.storage, &coin.denom)? {
+            // routed swaps reply on success too, to send the next hop
+            match create_hop_swap_msg(deps.as_ref(), &mut tax, &hops[0], coin) {
+                Ok(msg) => messages.push(SubMsg::reply_always(msg, id)),
+                Err(err) => {
+                    attributes.push(attr("swap_skipped", denom));
+                    attributes.push(attr("error", err.to_string()));
+                    report.error = Some(err.to_string());
+                    reports.push(report);
+                    continue;
+                }
+            }
+        } else if is_listed
+            || (market
+                && query_exchange_rates(&deps, reward_denom.clone(), vec![coin.denom.clone()])
+                    .is_ok())
         {
-            messages.push(create_swap_msg(coin, reward_denom.to_string()));
+            messages.push(SubMsg::reply_on_error(
+                create_swap_msg(coin, reward_denom.to_string()),
+                id,
+            ));
+        } else {
+            continue;
         }
+        reports.push(report);
+        pending.push(PendingSwap { denom, hop: 0 });
     }
+    tax.save(deps.storage, env.block.height)?;
+    PENDING_SWAPS.save(deps.storage, &pending)?;
+    store_swap_run(deps.storage, &env, reward_balance, reports)?;
 
     let res = Response::new()
-        .add_messages(messages)
-        .add_attributes(vec![attr("action", "swap")]);
+        .add_submessages(messages)
+        .add_attributes(attributes);
 
     Ok(res)
 }
 
+/// Record a failed swap instead of reverting the whole SwapToRewardDenom,
+/// the coins stay in the contract and are swapped on the next call.
+/// A successful hop of an Astroport route sends the next hop with what it returned.
+pub fn handle_swap_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let id = msg.id;
+    let index = (id - SWAP_REPLY_ID_OFFSET) as usize;
+    let mut pending = PENDING_SWAPS.load(deps.storage)?;
+    let swap = pending
+        .get(index)
+        .cloned()
+        .ok_or_else(|| StdError::generic_err(format!("Unknown reply id {}", id)))?;
+
+    let error = match msg.result {
+        ContractResult::Err(error) => error,
+        ContractResult::Ok(response) => {
+            // market swaps only reply on error, so this is a hop of a route
+            let hops = SWAP_ROUTES
+                .may_load(deps.storage, &swap.denom)?
+                .unwrap_or_default();
+            let next = match hops.get(swap.hop + 1) {
+                Some(next) => next,
+                None => return Ok(Response::new()),
+            };
+            // the balance of the intermediate denom may hold coins swapped by other runs
+            let hop = &hops[swap.hop];
+            let returned = match returned_amount(&response.events, &hop.pair_contract) {
+                Ok(amount) => Coin::new(amount.u128(), hop.ask_denom.as_str()),
+                Err(err) => return swap_failed(deps, &swap.denom, err.to_string()),
+            };
+
+            // the hops reply within the block of the swap, which cached the tax
+            let config = read_config(deps.storage)?;
+            let tax_free = query_tax_free(deps.as_ref(), &config.hub_contract)?;
+            let mut tax = TaxCache::load(
+                treasury(&deps.querier, tax_free),
+                deps.storage,
+                env.block.height,
+            );
+            match create_hop_swap_msg(deps.as_ref(), &mut tax, next, returned) {
+                Ok(msg) => {
+                    tax.save(deps.storage, env.block.height)?;
+                    pending[index].hop += 1;
+                    PENDING_SWAPS.save(deps.storage, &pending)?;
+                    return Ok(Response::new()
+                        .add_submessage(SubMsg::reply_always(msg, id))
+                        .add_attributes(vec![
+                            attr("action", "swap_hop"),
+                            attr("denom", swap.denom),
+                            attr("hop", (swap.hop + 1).to_string()),
+                        ]));
+                }
+                // the intermediate denom stays in the contract until it is routed itself
+                Err(err) => err.to_string(),
+            }
+        }
+    };
+
+    swap_failed(deps, &swap.denom, error)
+}
+
+fn swap_failed(
+    deps: DepsMut,
+    denom: &str,
+    error: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    record_swap_error(deps.storage, denom, &error)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "swap_failed"),
+        attr("denom", denom),
+        attr("error", error),
+    ]))
+}
+
+/// What an Astroport pair sent back for a swap, the return_amount of its swap event less
+/// the tax_amount it paid on the transfer
+fn returned_amount(events: &[Event], pair_contract: &str) -> StdResult<Uint128> {
+    let attribute = |event: &Event, key: &str| {
+        event
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.clone())
+    };
+    let event = events
+        .iter()
+        .find(|event| {
+            event.ty == "wasm"
+                && attribute(event, "_contract_address").as_deref() == Some(pair_contract)
+                && attribute(event, "return_amount").is_some()
+        })
+        .ok_or_else(|| {
+            StdError::generic_err(format!(
+                "The swap of {} reports no return_amount",
+                pair_contract
+            ))
+        })?;
+    let amount = |key: &str| -> StdResult<Uint128> {
+        attribute(event, key).map_or(Ok(Uint128::zero()), |value| {
+            value
+                .parse::<u128>()
+                .map(Uint128::from)
+                .map_err(|_| StdError::generic_err(format!("Invalid {} {}", key, value)))
+        })
+    };
+    Ok(amount("return_amount")?.checked_sub(amount("tax_amount")?)?)
+}
+
 /// Increase global_index according to claimed rewards amount
-/// Only hub_contract is allowed to execute
+/// Only the hubs are allowed to execute
 pub fn execute_update_global_index(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-) -> StdResult<Response<TerraMsgWrapper>> {
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let mut state: State = read_state(deps.storage)?;
 
     // Permission check
-    if config.hub_contract != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(StdError::generic_err("Unauthorized"));
-    }
+    assert_hub(deps.as_ref(), &config, &info.sender)?;
 
     // Zero staking balance check
     if state.total_balance.is_zero() {
-        return Err(StdError::generic_err("No asset is bonded by Hub"));
+        return Err(StdError::generic_err("No asset is bonded by Hub").into());
     }
 
-    let reward_denom = read_config(deps.storage)?.reward_denom;
+    let config = read_config(deps.storage)?;
+    let reward_denom = config.reward_denom.clone();
 
     // Load the reward contract balance
-    let balance = deps
-        .querier
-        .query_balance(env.contract.address, reward_denom.as_str())?;
+    let balance = query_reward_balance(&deps.querier, &env, &config)?;
+
+    check_swap_run(deps.storage, &deps.querier, &env, balance)?;
 
     let previous_balance = state.prev_reward_balance;
 
-    // claimed_rewards = current_balance - prev_balance;
-    let claimed_rewards = balance.amount.checked_sub(previous_balance)?;
+    // claimed_rewards = current_balance - prev_balance - keeper_reserve;
+    // an underfunded balance distributes nothing, the claims are paid out of what is left
+    let keeper_reserve = KEEPER_RESERVE.may_load(deps.storage)?.unwrap_or_default();
+    let claimed_rewards = balance
+        .saturating_sub(previous_balance)
+        .saturating_sub(keeper_reserve);
+
+    // the community fee leaves the balance before the rewards are distributed
+    let mut res = Response::new();
+    let mut distributed_rewards = claimed_rewards;
+    if let Some((fee_msg, fee, event)) =
+        community_fee_msg(deps.as_ref(), &env, &config, claimed_rewards)?
+    {
+        distributed_rewards = claimed_rewards.checked_sub(fee)?;
+        FEES_COLLECTED.update(deps.storage, &reward_denom, |collected| -> StdResult<_> {
+            Ok(collected.unwrap_or_default().checked_add(fee)?)
+        })?;
+        res = res.add_message(fee_msg).add_event(event);
+    }
 
-    state.prev_reward_balance = balance.amount;
+    state.prev_reward_balance = previous_balance + distributed_rewards;
 
-    // global_index += claimed_rewards / total_balance;
+    // global_index += distributed_rewards / total_balance;
     state.global_index = decimal_summation_in_256(
         state.global_index,
-        Decimal::from_ratio(claimed_rewards, state.total_balance),
+        Decimal::from_ratio(distributed_rewards, state.total_balance),
     );
     store_state(deps.storage, &state)?;
+    INDEX_HISTORY.save(
+        deps.storage,
+        U64Key::new(env.block.time.seconds()),
+        &state.global_index,
+    )?;
 
     let attributes = vec![
         attr("action", "update_global_index"),
+        attr("sender", info.sender),
         attr("claimed_rewards", claimed_rewards),
     ];
-    let res = Response::new().add_attributes(attributes);
 
-    Ok(res)
+    Ok(res.add_attributes(attributes))
 }
 
 pub fn query_exchange_rates(
@@ -114,3 +317,82 @@ pub fn query_exchange_rates(
     let res: ExchangeRatesResponse = querier.query_exchange_rates(base_denom, quote_denoms)?;
     Ok(res)
 }
+
+/// Derive the yield from the index updates, from the last one at least period seconds
+/// before the latest update up to the latest update
+pub fn query_apr(deps: Deps, period: u64) -> StdResult<AprResponse> {
+    let (end_time, end_index) = match INDEX_HISTORY
+        .range(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+    {
+        Some((key, index)) => (read_history_time(&key)?, index),
+        None => return Err(StdError::generic_err("The global index was never updated")),
+    };
+    let start = end_time
+        .checked_sub(period)
+        .filter(|_| period > 0)
+        .ok_or_else(|| StdError::generic_err("Invalid period"))?;
+    // the index is rebased by a denom migration, the window starts after it
+    let min = last_migration_time(deps)?.map(|time| Bound::inclusive(U64Key::new(time)));
+    let (start_time, start_index) = match INDEX_HISTORY
+        .range(
+            deps.storage,
+            min,
+            Some(Bound::inclusive(U64Key::new(start))),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+    {
+        Some((key, index)) => (read_history_time(&key)?, index),
+        None => {
+            return Err(StdError::generic_err(format!(
+                "The index history is shorter than {} seconds",
+                period
+            )))
+        }
+    };
+
+    let reward_per_basset = decimal_subtraction_in_256(end_index, start_index);
+    let annual_reward = decimal_multiplication_in_256(
+        reward_per_basset,
+        Decimal::from_ratio(SECONDS_PER_YEAR, end_time - start_time),
+    );
+
+    // the bAsset is valued in the reward denom through the oracle of the hub
+    let config = read_config(deps.storage)?;
+    let hub_addr = deps.api.addr_humanize(&config.hub_contract)?;
+    let apr = match query_hub_price_guard(&deps.querier, hub_addr.as_str())? {
+        Some(guard) => {
+            let underlying_denom =
+                query_hub_parameters(&deps.querier, hub_addr.as_str())?.underlying_coin_denom;
+            let price = query_oracle_price(
+                &deps.querier,
+                deps.api.addr_humanize(&guard.oracle_contract)?.as_str(),
+                &underlying_denom,
+                &config.reward_denom,
+            )?;
+            let exchange_rate = query_exchange_rate(&deps.querier, hub_addr.as_str())?;
+            Some(decimal_division_in_256(
+                annual_reward,
+                decimal_multiplication_in_256(exchange_rate, price),
+            )?)
+        }
+        None => None,
+    };
+
+    Ok(AprResponse {
+        start_time,
+        end_time,
+        reward_per_basset,
+        apr,
+    })
+}
+
+fn read_history_time(key: &[u8]) -> StdResult<u64> {
+    let time: [u8; 8] = key
+        .try_into()
+        .map_err(|_| StdError::generic_err("Corrupted index history key"))?;
+    Ok(u64::from_be_bytes(time))
+}
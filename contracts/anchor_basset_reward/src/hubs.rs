@@ -0,0 +1,81 @@
+use crate::querier::assert_hub_owner;
+use crate::state::{read_config, Config, FEDERATED_HUBS, TIMELOCK};
+
+use basset::contract_error::ContractError;
+use basset::reward::HubsResponse;
+
+use cosmwasm_std::{
+    attr, Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
+};
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Fails unless sender is hub_contract or one of the hubs added next to it
+pub fn assert_hub(deps: Deps, config: &Config, sender: &Addr) -> Result<(), ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    if sender_raw != config.hub_contract && !FEDERATED_HUBS.has(deps.storage, sender_raw.as_slice())
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// Only the owner of the hub is allowed to execute, through ProposePendingChange while
+/// the timelock is active
+pub fn execute_add_hub(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    hub: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+    if TIMELOCK.is_active(deps.storage)? {
+        return Err(ContractError::Timelocked {});
+    }
+
+    add_hub(deps, hub)
+}
+
+pub(crate) fn add_hub(
+    deps: DepsMut,
+    hub: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    let hub_raw = deps
+        .api
+        .addr_canonicalize(deps.api.addr_validate(&hub)?.as_str())?;
+    if hub_raw == config.hub_contract {
+        return Err(StdError::generic_err("The hub contract is always allowed").into());
+    }
+    FEDERATED_HUBS.save(deps.storage, hub_raw.as_slice(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "add_hub"), attr("hub", hub)]))
+}
+
+/// Only the owner of the hub is allowed to execute
+pub fn execute_remove_hub(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    hub: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    let hub_raw = deps.api.addr_canonicalize(&hub)?;
+    if !FEDERATED_HUBS.has(deps.storage, hub_raw.as_slice()) {
+        return Err(StdError::generic_err(format!("{} is not an added hub", hub)).into());
+    }
+    FEDERATED_HUBS.remove(deps.storage, hub_raw.as_slice());
+
+    Ok(Response::new().add_attributes(vec![attr("action", "remove_hub"), attr("hub", hub)]))
+}
+
+pub fn query_hubs(deps: Deps) -> StdResult<HubsResponse> {
+    let config = read_config(deps.storage)?;
+    let mut hubs = vec![deps.api.addr_humanize(&config.hub_contract)?.to_string()];
+    for key in FEDERATED_HUBS.keys(deps.storage, None, None, Order::Ascending) {
+        hubs.push(deps.api.addr_humanize(&key.into())?.to_string());
+    }
+    Ok(HubsResponse { hubs })
+}
@@ -0,0 +1,70 @@
+use crate::hubs::assert_hub;
+use crate::payout::{query_reward_balance, reward_payout_msg};
+use crate::state::{read_config, read_state, KEEPER_RESERVE};
+
+use basset::contract_error::ContractError;
+use basset::hub::{MAX_KEEPER_BOUNTY, MAX_KEEPER_RESERVE};
+use basset::reward::KeeperReserveResponse;
+
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128};
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Hold back the rewards not distributed yet until the reserve reaches target, at most
+/// MAX_KEEPER_RESERVE. The index update before the next one distributes what is left
+/// Only the hubs are allowed to execute
+pub fn execute_fund_keeper_reserve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    target: Uint128,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub(deps.as_ref(), &config, &info.sender)?;
+
+    let reserve = KEEPER_RESERVE.may_load(deps.storage)?.unwrap_or_default();
+    let balance = query_reward_balance(&deps.querier, &env, &config)?;
+    let undistributed = balance
+        .saturating_sub(read_state(deps.storage)?.prev_reward_balance)
+        .saturating_sub(reserve);
+    let target = target.min(Uint128::new(MAX_KEEPER_RESERVE));
+    let funded = target.saturating_sub(reserve).min(undistributed);
+    KEEPER_RESERVE.save(deps.storage, &(reserve + funded))?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "fund_keeper_reserve"),
+        attr("funded", funded),
+    ]))
+}
+
+/// Pay the bounty out of the reserve, at most MAX_KEEPER_BOUNTY. An empty reserve pays
+/// nothing. Only the hubs are allowed to execute
+pub fn execute_pay_keeper_bounty(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub(deps.as_ref(), &config, &info.sender)?;
+
+    let reserve = KEEPER_RESERVE.may_load(deps.storage)?.unwrap_or_default();
+    let paid = amount.min(Uint128::new(MAX_KEEPER_BOUNTY)).min(reserve);
+    KEEPER_RESERVE.save(deps.storage, &(reserve - paid))?;
+
+    let mut res = Response::new().add_attributes(vec![
+        attr("action", "pay_keeper_bounty"),
+        attr("recipient", recipient.clone()),
+        attr("amount", paid),
+    ]);
+    if !paid.is_zero() {
+        res = res.add_message(reward_payout_msg(deps.as_ref(), &config, recipient, paid)?);
+    }
+    Ok(res)
+}
+
+pub fn query_keeper_reserve(deps: Deps) -> StdResult<KeeperReserveResponse> {
+    Ok(KeeperReserveResponse {
+        reserve: KEEPER_RESERVE.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
@@ -1,9 +1,17 @@
 pub mod contract;
 pub mod state;
 
+mod denom;
+mod earn;
+mod fee;
 mod global;
-mod math;
+mod hubs;
+mod keeper;
+mod payout;
 mod querier;
+mod swap;
+mod tiers;
+mod timelock;
 mod user;
 
 #[cfg(test)]
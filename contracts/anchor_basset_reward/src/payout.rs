@@ -0,0 +1,132 @@
+use crate::querier::{assert_hub_owner, query_tax_free};
+use crate::state::{read_config, read_state, Config, KEEPER_RESERVE, PRO_RATA_CLAIMS};
+
+use basset::astroport::AssetInfo;
+use basset::contract_error::ContractError;
+use basset::querier::query_token_balance;
+use basset::reward::SolvencyResponse;
+use basset::{deduct_tax, treasury};
+
+use cosmwasm_std::{
+    attr, to_binary, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QuerierWrapper,
+    Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// The balance of the contract in the asset the rewards are paid in
+pub fn query_reward_balance(
+    querier: &QuerierWrapper,
+    env: &Env,
+    config: &Config,
+) -> StdResult<Uint128> {
+    match config.reward_asset() {
+        AssetInfo::NativeToken { denom } => Ok(querier
+            .query_balance(env.contract.address.clone(), denom)?
+            .amount),
+        AssetInfo::Token { contract_addr } => {
+            query_token_balance(querier, &contract_addr, env.contract.address.as_str())
+        }
+    }
+}
+
+/// Pay amount of the rewards to recipient, the tax is deducted from native rewards
+pub fn reward_payout_msg(
+    deps: Deps,
+    config: &Config,
+    recipient: String,
+    amount: Uint128,
+) -> StdResult<CosmosMsg<TerraMsgWrapper>> {
+    match config.reward_asset() {
+        AssetInfo::NativeToken { denom } => {
+            let tax_free = query_tax_free(deps, &config.hub_contract)?;
+            Ok(CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient,
+                amount: vec![deduct_tax(
+                    treasury(&deps.querier, tax_free),
+                    Coin::new(amount.u128(), denom),
+                )?],
+            }))
+        }
+        AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+            funds: vec![],
+        })),
+    }
+}
+
+/// Fails when the rewards are paid in a cw20 token, for the features swapping or
+/// depositing native rewards
+pub fn assert_native_rewards(config: &Config) -> Result<(), ContractError> {
+    if config.cw20_rewards {
+        return Err(StdError::generic_err(format!(
+            "The rewards are paid in the cw20 token {}",
+            config.reward_denom
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// The reward balance the claims are paid from, the keeper reserve is not theirs
+fn claimable_balance(
+    storage: &dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    config: &Config,
+) -> StdResult<Uint128> {
+    let keeper_reserve = KEEPER_RESERVE.may_load(storage)?.unwrap_or_default();
+    Ok(query_reward_balance(querier, env, config)?.saturating_sub(keeper_reserve))
+}
+
+/// The part of rewards out of owed the balance pays. A balance below owed, e.g. after a
+/// failed swap, pays each claim its share with pro rata claims, otherwise it pays the claims
+/// it covers and refuses the others
+pub fn claim_payout(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    rewards: Uint128,
+    owed: Uint128,
+) -> StdResult<Uint128> {
+    let balance = claimable_balance(deps.storage, &deps.querier, env, config)?;
+    let paid = if balance < owed && PRO_RATA_CLAIMS.may_load(deps.storage)?.unwrap_or_default() {
+        rewards.multiply_ratio(balance, owed)
+    } else {
+        rewards
+    };
+    if paid > balance || paid.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "The reward balance is {}{}, it does not pay the {}{} claimed",
+            balance, config.reward_denom, rewards, config.reward_denom
+        )));
+    }
+    Ok(paid)
+}
+
+/// Only the owner of the hub is allowed to execute
+pub fn execute_update_pro_rata_claims(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+    PRO_RATA_CLAIMS.save(deps.storage, &enabled)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_pro_rata_claims"),
+        attr("enabled", enabled.to_string()),
+    ]))
+}
+
+pub fn query_solvency(deps: Deps, env: Env) -> StdResult<SolvencyResponse> {
+    let config = read_config(deps.storage)?;
+    Ok(SolvencyResponse {
+        reward_balance: claimable_balance(deps.storage, &deps.querier, &env, &config)?,
+        owed: read_state(deps.storage)?.prev_reward_balance,
+        pro_rata_claims: PRO_RATA_CLAIMS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
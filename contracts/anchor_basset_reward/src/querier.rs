@@ -1,17 +1,70 @@
+use crate::state::OPERATOR;
+use basset::contract_error::ContractError;
 use basset::hub::Config;
+use basset::querier::{query_hub_circuit_breaker, query_hub_tax_free};
 use cosmwasm_std::{Addr, Binary, CanonicalAddr, Deps, QueryRequest, StdResult, WasmQuery};
 use cosmwasm_storage::to_length_prefixed;
 
 pub fn query_token_contract(deps: Deps, contract_addr: Addr) -> StdResult<CanonicalAddr> {
-    let conf: Config = deps
-        .querier
-        .query(&QueryRequest::Wasm(WasmQuery::Raw {
-            contract_addr: contract_addr.to_string(),
-            key: Binary::from(to_length_prefixed(b"config")),
-        }))
-        .unwrap();
-
-    Ok(conf
-        .token_contract
-        .expect("the token contract must have been registered"))
+    let conf: Config = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Raw {
+        contract_addr: contract_addr.to_string(),
+        key: Binary::from(to_length_prefixed(b"config")),
+    }))?;
+
+    conf.token_contract.ok_or_else(|| {
+        ContractError::ContractNotRegistered {
+            contract: "token".to_string(),
+        }
+        .into()
+    })
+}
+
+pub fn query_hub_owner(deps: Deps, contract_addr: Addr) -> StdResult<CanonicalAddr> {
+    let conf: Config = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Raw {
+        contract_addr: contract_addr.to_string(),
+        key: Binary::from(to_length_prefixed(b"config")),
+    }))?;
+
+    Ok(conf.creator)
+}
+
+/// Only the owner of the hub is allowed to manage the reward contract
+pub fn assert_hub_owner(
+    deps: Deps,
+    hub_contract: &CanonicalAddr,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    let owner = query_hub_owner(deps, deps.api.addr_humanize(hub_contract)?)?;
+    if deps.api.addr_canonicalize(sender.as_str())? != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// The operator of the reward contract is allowed next to the owner of the hub
+pub fn assert_hub_owner_or_operator(
+    deps: Deps,
+    hub_contract: &CanonicalAddr,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    let owner = query_hub_owner(deps, deps.api.addr_humanize(hub_contract)?)?;
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    OPERATOR.assert_allowed(deps.storage, &owner, &sender_raw)
+}
+
+/// The tax is left out of the rewards sent once the owner of the hub flagged the chain as
+/// tax free
+pub fn query_tax_free(deps: Deps, hub_contract: &CanonicalAddr) -> StdResult<bool> {
+    query_hub_tax_free(
+        &deps.querier,
+        deps.api.addr_humanize(hub_contract)?.as_str(),
+    )
+}
+
+/// Claims are frozen while the hub is paused
+pub fn assert_hub_not_paused(deps: Deps, contract_addr: Addr) -> Result<(), ContractError> {
+    if query_hub_circuit_breaker(&deps.querier, contract_addr.as_str())?.paused {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
 }
@@ -1,19 +1,131 @@
-use cosmwasm_std::{Addr, Api, CanonicalAddr, Decimal, Deps, Order, StdResult, Storage, Uint128};
+use cosmwasm_std::{Addr, CanonicalAddr, Decimal, Deps, Order, StdResult, Storage, Uint128};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use basset::reward::HolderResponse;
-use cw_storage_plus::{Bound, Item, Map};
+use basset::astroport::AssetInfo;
+use basset::lock::ExecutionLock;
+use basset::math::decimal_multiplication_in_256;
+use basset::operator::Operator;
+use basset::pagination::{calc_limit, calc_range_start};
+use basset::reward::{
+    CommunityFee, DenomMigration, HolderResponse, HolderTier, SwapHop, SwapRouteResponse, SwapRun,
+    TimelockedChange,
+};
+use basset::timelock::Timelock;
+use cw_storage_plus::{
+    Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex, U128Key, U64Key,
+};
 
 pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
-pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
-pub const HOLDERS: Map<&[u8], Holder> = Map::new("holders");
+pub const CONFIG_KEY: &str = "\u{0}\u{6}config";
+pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+/// The token of the hub, registered by the hub, the only sender of the balance updates
+pub const TOKEN_CONTRACT: Item<CanonicalAddr> = Item::new("token_contract");
+/// Manages the swap routes next to the owner of the hub
+pub const OPERATOR: Operator = Operator::new("operator");
+/// Held by a claim until its rewards are deposited to Anchor Earn
+pub const EXECUTION_LOCK: ExecutionLock = ExecutionLock::new("execution_lock");
+/// Holders not yet in the balance index are indexed from the key after this one,
+/// unset once every holder is indexed
+pub const HOLDERS_INDEX_CURSOR: Item<Vec<u8>> = Item::new("holders_index_cursor");
+/// Swaps sent by the last SwapToRewardDenom, indexed by reply id
+pub const PENDING_SWAPS: Item<Vec<PendingSwap>> = Item::new("pending_swaps");
+/// Astroport routes of the denoms not swapped by the market module, keyed by offered denom
+pub const SWAP_ROUTES: Map<&str, Vec<SwapHop>> = Map::new("swap_routes");
+/// Reports of the most recent SwapToRewardDenom runs, keyed by run id
+pub const SWAP_RUNS: Map<U64Key, SwapRun> = Map::new("swap_runs");
+/// The number of swap runs so far, the id of the next run
+pub const SWAP_RUN_COUNT: Item<u64> = Item::new("swap_run_count");
+/// The Anchor money market claimed rewards are deposited into, unset when nothing is deposited
+pub const EARN: Item<Earn> = Item::new("earn");
+/// The hubs allowed next to hub_contract, keyed by canonical address
+pub const FEDERATED_HUBS: Map<&[u8], bool> = Map::new("federated_hubs");
+/// Deposit preferences of the holders, keyed by canonical address
+pub const REWARD_DEPOSITS: Map<&[u8], bool> = Map::new("reward_deposits");
+/// The default recipients of the claimed rewards, keyed by the canonical address of the holder
+pub const REWARD_RECIPIENTS: Map<&[u8], CanonicalAddr> = Map::new("reward_recipients");
+/// Where the holders that opted out of accruing send their accruals
+pub const ACCRUAL_REDIRECTS: Map<&[u8], AccrualRedirect> = Map::new("accrual_redirects");
+/// The recipient of the aTerra minted by the deposit in flight
+pub const PENDING_DEPOSIT: Item<Addr> = Item::new("pending_deposit");
+/// The protocol fee taken at every index update, unset when no fee is taken
+pub const COMMUNITY_FEE: Item<CommunityFee> = Item::new("community_fee");
+/// The lifetime community fees taken, per denom
+pub const FEES_COLLECTED: Map<&str, Uint128> = Map::new("fees_collected");
+/// The rewards held back from the holders to pay the keeper bounties
+pub const KEEPER_RESERVE: Item<Uint128> = Item::new("keeper_reserve");
+/// Whether the claims are paid pro rata while the balance is below the rewards owed
+pub const PRO_RATA_CLAIMS: Item<bool> = Item::new("pro_rata_claims");
+/// The global index after each update, keyed by block time
+pub const INDEX_HISTORY: Map<U64Key, Decimal> = Map::new("index_history");
+/// The reward denom migrations so far, the oldest first
+pub const DENOM_MIGRATIONS: Item<Vec<DenomMigration>> = Item::new("denom_migrations");
+/// The number of denom migrations the stored holders are rebased for, missing for none
+pub const HOLDER_MIGRATIONS: Map<&[u8], u32> = Map::new("holder_migrations");
+/// The reward denom migration waiting for its swap to return
+pub const PENDING_DENOM_MIGRATION: Item<PendingDenomMigration> =
+    Item::new("pending_denom_migration");
+/// The holders counted in each tier, unset when they are not counted
+pub const HOLDER_TIERS: Item<Vec<HolderTier>> = Item::new("holder_tiers");
+/// Sensitive changes waiting for the timelock period to pass
+pub const TIMELOCK: Timelock<TimelockedChange> =
+    Timelock::new("timelock_period", "pending_change_id", "pending_changes");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingDenomMigration {
+    pub old_denom: String,
+    pub new_denom: String,
+    /// The old denom offered to the swap
+    pub offer_amount: Uint128,
+    /// The new denom held before the swap
+    pub new_denom_balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Earn {
+    pub market_contract: CanonicalAddr,
+    pub aterra_contract: CanonicalAddr,
+    pub deposit_by_default: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingSwap {
+    pub denom: String,
+    /// Index of the hop in flight when the denom is routed through Astroport
+    pub hop: usize,
+}
+
+/// The stored AccrualRecipient
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccrualRedirect {
+    Address(CanonicalAddr),
+    Pool,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub hub_contract: CanonicalAddr,
+    /// The native denom of the rewards, the address of the token with cw20_rewards
     pub reward_denom: String,
+    #[serde(default)]
+    pub cw20_rewards: bool,
+}
+
+impl Config {
+    /// The asset the rewards are paid in
+    pub fn reward_asset(&self) -> AssetInfo {
+        if self.cw20_rewards {
+            AssetInfo::Token {
+                contract_addr: self.reward_denom.clone(),
+            }
+        } else {
+            AssetInfo::NativeToken {
+                denom: self.reward_denom.clone(),
+            }
+        }
+    }
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -46,19 +158,98 @@ pub struct Holder {
     pub pending_rewards: Decimal,
 }
 
+pub struct HolderIndexes<'a> {
+    /// Holders ordered by balance, the canonical address breaks ties
+    pub balance: MultiIndex<'a, (U128Key, Vec<u8>), Holder>,
+}
+
+impl<'a> IndexList<Holder> for HolderIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Holder>> + '_> {
+        let v: Vec<&dyn Index<Holder>> = vec![&self.balance];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Holders keyed by canonical address, under the namespace of the legacy bucket
+pub fn holders<'a>() -> IndexedMap<'a, &'a [u8], Holder, HolderIndexes<'a>> {
+    let indexes = HolderIndexes {
+        balance: MultiIndex::new(
+            |holder, pk| (U128Key::new(holder.balance.u128()), pk),
+            "holders",
+            "holders__balance",
+        ),
+    };
+    IndexedMap::new("holders", indexes)
+}
+
 // This is similar to HashMap<holder's address, Hodler>
 pub fn store_holder(
     storage: &mut dyn Storage,
     holder_address: &CanonicalAddr,
     holder: &Holder,
 ) -> StdResult<()> {
-    HOLDERS.save(storage, holder_address.as_slice(), holder)
+    update_holder_tiers(storage, holder_address, holder.balance)?;
+    // the stale balance entry is dropped, a holder stored before the index is indexed now
+    holders().save(storage, holder_address.as_slice(), holder)?;
+
+    // the holder is stored rebased by read_holder
+    let migrations = DENOM_MIGRATIONS.may_load(storage)?.unwrap_or_default();
+    if !migrations.is_empty() {
+        HOLDER_MIGRATIONS.save(
+            storage,
+            holder_address.as_slice(),
+            &(migrations.len() as u32),
+        )?;
+    }
+    Ok(())
+}
+
+/// Move a holder from the tier of its stored balance to the tier of balance
+fn update_holder_tiers(
+    storage: &mut dyn Storage,
+    holder_address: &CanonicalAddr,
+    balance: Uint128,
+) -> StdResult<()> {
+    let mut tiers = match HOLDER_TIERS.may_load(storage)? {
+        Some(tiers) => tiers,
+        None => return Ok(()),
+    };
+    let stored = holders()
+        .may_load(storage, holder_address.as_slice())?
+        .map_or_else(Uint128::zero, |holder| holder.balance);
+    if stored == balance {
+        return Ok(());
+    }
+
+    if let Some(tier) = tier_of(&mut tiers, stored) {
+        tier.holders -= 1;
+        tier.balance = tier.balance.checked_sub(stored)?;
+    }
+    if let Some(tier) = tier_of(&mut tiers, balance) {
+        tier.holders += 1;
+        tier.balance += balance;
+    }
+    HOLDER_TIERS.save(storage, &tiers)
+}
+
+/// The tier a balance is counted in, none for a zero balance or one below every tier
+pub fn tier_of(tiers: &mut [HolderTier], balance: Uint128) -> Option<&mut HolderTier> {
+    if balance.is_zero() {
+        return None;
+    }
+    tiers
+        .iter_mut()
+        .rev()
+        .find(|tier| tier.min_balance <= balance)
 }
 
 pub fn read_holder(storage: &dyn Storage, holder_address: &CanonicalAddr) -> StdResult<Holder> {
-    let res = HOLDERS.may_load(storage, holder_address.as_slice())?;
+    let res = holders().may_load(storage, holder_address.as_slice())?;
     match res {
-        Some(holder) => Ok(holder),
+        Some(holder) => {
+            let migrations = DENOM_MIGRATIONS.may_load(storage)?.unwrap_or_default();
+            rebase_holder(storage, holder_address.as_slice(), holder, &migrations)
+        }
         None => Ok(Holder {
             balance: Uint128::zero(),
             index: Decimal::zero(),
@@ -67,45 +258,162 @@ pub fn read_holder(storage: &dyn Storage, holder_address: &CanonicalAddr) -> Std
     }
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+/// Read a holder to update it. A new holder starts at global_index, the rewards
+/// distributed before it held any balance are not its own
+pub fn read_holder_or_init(
+    storage: &dyn Storage,
+    holder_address: &CanonicalAddr,
+    global_index: Decimal,
+) -> StdResult<Holder> {
+    match holders().may_load(storage, holder_address.as_slice())? {
+        Some(_) => read_holder(storage, holder_address),
+        None => Ok(Holder {
+            balance: Uint128::zero(),
+            index: global_index,
+            pending_rewards: Decimal::zero(),
+        }),
+    }
+}
+
+/// The accruals of a holder are in the denom of its last update, they are brought to the
+/// current reward denom by the rates of the denom migrations since
+fn rebase_holder(
+    storage: &dyn Storage,
+    key: &[u8],
+    mut holder: Holder,
+    migrations: &[DenomMigration],
+) -> StdResult<Holder> {
+    if migrations.is_empty() {
+        return Ok(holder);
+    }
+    let applied = HOLDER_MIGRATIONS
+        .may_load(storage, key)?
+        .unwrap_or_default() as usize;
+    for migration in migrations.iter().skip(applied) {
+        holder.index = decimal_multiplication_in_256(holder.index, migration.rate);
+        holder.pending_rewards =
+            decimal_multiplication_in_256(holder.pending_rewards, migration.rate);
+    }
+    Ok(holder)
+}
+
+/// Run f over every holder, rebased to the current reward denom
+pub fn for_each_holder(
+    storage: &dyn Storage,
+    mut f: impl FnMut(Holder) -> StdResult<()>,
+) -> StdResult<()> {
+    let migrations = DENOM_MIGRATIONS.may_load(storage)?.unwrap_or_default();
+    for item in holders().range(storage, None, None, Order::Ascending) {
+        let (key, holder) = item?;
+        f(rebase_holder(storage, &key, holder, &migrations)?)?;
+    }
+    Ok(())
+}
+
 pub fn read_holders(
     deps: Deps,
     start_after: Option<Addr>,
     limit: Option<u32>,
 ) -> StdResult<Vec<HolderResponse>> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(deps.api, start_after.map(Addr::unchecked))?.map(Bound::exclusive);
+    let limit = calc_limit(limit);
+    let start = calc_range_start(
+        start_after
+            .map(|address| deps.api.addr_canonicalize(address.as_str()))
+            .transpose()?,
+    );
+    let migrations = DENOM_MIGRATIONS.may_load(deps.storage)?.unwrap_or_default();
 
-    HOLDERS
+    holders()
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
-        .map(|elem| {
-            let (k, v) = elem?;
-            let address: String = deps.api.addr_humanize(&CanonicalAddr::from(k))?.to_string();
-            Ok(HolderResponse {
-                address,
-                balance: v.balance,
-                index: v.index,
-                pending_rewards: v.pending_rewards,
-            })
-        })
+        .map(|elem| holder_response(deps, elem?, &migrations))
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(api: &dyn Api, start_after: Option<Addr>) -> StdResult<Option<Vec<u8>>> {
-    match start_after {
-        Some(human) => {
-            let mut v: Vec<u8> = api.addr_canonicalize(human.as_ref())?.0.into();
-            v.push(0);
-            Ok(Some(v))
+/// The holders with the largest balances, largest first
+pub fn read_top_holders(deps: Deps, limit: Option<u32>) -> StdResult<Vec<HolderResponse>> {
+    let limit = calc_limit(limit);
+    let migrations = DENOM_MIGRATIONS.may_load(deps.storage)?.unwrap_or_default();
+
+    holders()
+        .idx
+        .balance
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|elem| holder_response(deps, elem?, &migrations))
+        .collect()
+}
+
+/// Adds up to limit holders stored before the balance index to it, returns whether
+/// every holder is indexed
+pub fn index_holders(storage: &mut dyn Storage, limit: u32) -> StdResult<bool> {
+    let limit = limit.max(1) as usize;
+    let cursor = match HOLDERS_INDEX_CURSOR.may_load(storage)? {
+        Some(cursor) => cursor,
+        None => return Ok(true),
+    };
+    let start = if cursor.is_empty() {
+        None
+    } else {
+        Some(Bound::exclusive(cursor))
+    };
+
+    let map = holders();
+    let page: Vec<(Vec<u8>, Holder)> = map
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    for (key, holder) in page.iter() {
+        // the entry of a holder indexed since is overwritten with the same key
+        map.replace(storage, key, Some(holder), None)?;
+    }
+
+    match page.last() {
+        Some((key, _)) if page.len() == limit => {
+            HOLDERS_INDEX_CURSOR.save(storage, key)?;
+            Ok(false)
+        }
+        _ => {
+            HOLDERS_INDEX_CURSOR.remove(storage);
+            Ok(true)
         }
-        None => Ok(None),
     }
 }
 
+fn holder_response(
+    deps: Deps,
+    (k, v): (Vec<u8>, Holder),
+    migrations: &[DenomMigration],
+) -> StdResult<HolderResponse> {
+    let v = rebase_holder(deps.storage, &k, v, migrations)?;
+    let address: String = deps.api.addr_humanize(&CanonicalAddr::from(k))?.to_string();
+    Ok(HolderResponse {
+        address,
+        balance: v.balance,
+        index: v.index,
+        pending_rewards: v.pending_rewards,
+    })
+}
+
+pub fn read_swap_routes(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SwapRouteResponse>> {
+    let limit = calc_limit(limit);
+    let start = start_after.map(|denom| Bound::exclusive(denom.as_bytes()));
+
+    SWAP_ROUTES
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (k, hops) = elem?;
+            let offer_denom = String::from_utf8(k)?;
+            Ok(SwapRouteResponse { offer_denom, hops })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -135,12 +443,12 @@ mod test {
     }
 
     /// balances are state of the erc20 tokens
-    pub fn legacy_holders(storage: &mut dyn Storage) -> Bucket<Holder> {
+    pub fn legacy_holders(storage: &mut dyn Storage) -> Bucket<'_, Holder> {
         bucket(storage, PREFIX_HOLDERS)
     }
 
     /// balances are state of the erc20 tokens (read-only version for queries)
-    pub fn legacy_holders_read(storage: &dyn Storage) -> ReadonlyBucket<Holder> {
+    pub fn legacy_holders_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Holder> {
         bucket_read(storage, PREFIX_HOLDERS)
     }
 
@@ -171,6 +479,7 @@ mod test {
             &Config {
                 hub_contract: deps.api.addr_canonicalize("hub").unwrap(),
                 reward_denom: "".to_string(),
+                cw20_rewards: false,
             },
         )
         .unwrap();
@@ -201,8 +510,50 @@ mod test {
 
         let balances_read = legacy_holders_read(&deps.storage);
         assert_eq!(
-            HOLDERS.load(&deps.storage, key1).unwrap(),
+            holders().load(&deps.storage, key1).unwrap(),
             balances_read.load(key1).unwrap()
         );
     }
+
+    #[test]
+    fn legacy_holders_are_indexed_lazily() {
+        let mut deps = mock_dependencies(&[]);
+        let holder = |balance: u128| Holder {
+            balance: Uint128::from(balance),
+            index: Default::default(),
+            pending_rewards: Default::default(),
+        };
+        for (address, balance) in [("addr0000", 300u128), ("addr0001", 100), ("addr0002", 200)] {
+            let key = deps.api.addr_canonicalize(address).unwrap();
+            legacy_holders(&mut deps.storage)
+                .save(key.as_slice(), &holder(balance))
+                .unwrap();
+        }
+        HOLDERS_INDEX_CURSOR
+            .save(&mut deps.storage, &vec![])
+            .unwrap();
+        assert!(read_top_holders(deps.as_ref(), None).unwrap().is_empty());
+
+        // a stored holder is indexed, its stale entry replaced
+        let addr1 = deps.api.addr_canonicalize("addr0001").unwrap();
+        store_holder(&mut deps.storage, &addr1, &holder(400)).unwrap();
+        store_holder(&mut deps.storage, &addr1, &holder(500)).unwrap();
+        let top = read_top_holders(deps.as_ref(), None).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].balance, Uint128::from(500u128));
+
+        assert!(!index_holders(&mut deps.storage, 2).unwrap());
+        assert!(index_holders(&mut deps.storage, 2).unwrap());
+        assert_eq!(HOLDERS_INDEX_CURSOR.may_load(&deps.storage).unwrap(), None);
+
+        let balances: Vec<Uint128> = read_top_holders(deps.as_ref(), Some(2))
+            .unwrap()
+            .into_iter()
+            .map(|holder| holder.balance)
+            .collect();
+        assert_eq!(
+            balances,
+            vec![Uint128::from(500u128), Uint128::from(300u128)]
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use crate::querier::assert_hub_owner_or_operator;
+use crate::state::{read_config, read_swap_routes, SWAP_ROUTES, SWAP_RUNS, SWAP_RUN_COUNT};
+
+use basset::astroport::{Asset, PairExecuteMsg, PairQueryMsg, SimulationResponse};
+use basset::contract_error::ContractError;
+use basset::oracle::{assert_price_deviation, query_oracle_price};
+use basset::querier::query_hub_price_guard;
+use basset::reward::{SwapHistoryResponse, SwapHop, SwapReport, SwapRoutesResponse, SwapRun};
+use basset::validation::validate_native_denom;
+use basset::TaxCache;
+
+use cosmwasm_std::{
+    attr, to_binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
+    QuerierWrapper, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw_storage_plus::U64Key;
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Every hop is a submessage and a reply, so keep the routes short
+const MAX_SWAP_HOPS: usize = 3;
+
+/// The swap runs kept for the SwapHistory query, older runs are removed
+const MAX_SWAP_RUNS: u64 = 10;
+
+/// Route offer_denom through Astroport pairs, the route must end with the reward denom,
+/// or with the reward token of cw20 rewards
+/// Only the owner of the hub and the operator are allowed to execute
+pub fn execute_register_swap_route(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    offer_denom: String,
+    hops: Vec<SwapHop>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner_or_operator(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    validate_native_denom(&offer_denom)?;
+    if offer_denom == config.reward_denom {
+        return Err(StdError::generic_err("The reward denom is not swapped").into());
+    }
+    if hops.is_empty() || hops.len() > MAX_SWAP_HOPS {
+        return Err(StdError::generic_err(format!(
+            "A swap route must have between 1 and {} hops",
+            MAX_SWAP_HOPS
+        ))
+        .into());
+    }
+
+    let mut denoms = vec![offer_denom.clone()];
+    for (i, hop) in hops.iter().enumerate() {
+        deps.api.addr_validate(&hop.pair_contract)?;
+        // the hops offer native coins, only the last one may return the reward token
+        if !(config.cw20_rewards && i == hops.len() - 1) {
+            validate_native_denom(&hop.ask_denom)?;
+        }
+        if denoms.contains(&hop.ask_denom) {
+            return Err(StdError::generic_err(format!(
+                "The swap route returns {} more than once",
+                hop.ask_denom
+            ))
+            .into());
+        }
+        if let Some(max_spread) = hop.max_spread {
+            if max_spread >= Decimal::one() {
+                return Err(StdError::generic_err("max_spread must be less than 1").into());
+            }
+        }
+        denoms.push(hop.ask_denom.clone());
+    }
+    if denoms.last() != Some(&config.reward_denom) {
+        return Err(StdError::generic_err(format!(
+            "The swap route must end with {}",
+            config.reward_denom
+        ))
+        .into());
+    }
+
+    SWAP_ROUTES.save(deps.storage, &offer_denom, &hops)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_swap_route"),
+        attr("offer_denom", offer_denom),
+        attr("route", denoms.join(",")),
+    ]))
+}
+
+/// Swap offer_denom through the market module again
+/// Only the owner of the hub and the operator are allowed to execute
+pub fn execute_deregister_swap_route(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    offer_denom: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner_or_operator(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    if !SWAP_ROUTES.has(deps.storage, &offer_denom) {
+        return Err(StdError::generic_err(format!(
+            "No swap route is registered for {}",
+            offer_denom
+        ))
+        .into());
+    }
+    SWAP_ROUTES.remove(deps.storage, &offer_denom);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "deregister_swap_route"),
+        attr("offer_denom", offer_denom),
+    ]))
+}
+
+/// Swap the coin through the pair of the hop, the simulated return is the belief price
+/// so that max_spread bounds the slippage of the whole swap
+pub fn create_hop_swap_msg(
+    deps: Deps,
+    tax: &mut TaxCache,
+    hop: &SwapHop,
+    coin: Coin,
+) -> Result<CosmosMsg<TerraMsgWrapper>, ContractError> {
+    let offer = tax.deduct_tax(coin)?;
+    let offer_asset = Asset::native(&offer.denom, offer.amount);
+
+    let simulation: SimulationResponse = deps.querier.query_wasm_smart(
+        hop.pair_contract.clone(),
+        &PairQueryMsg::Simulation {
+            offer_asset: offer_asset.clone(),
+        },
+    )?;
+    if simulation.return_amount.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "Swapping {} returns no {}",
+            offer, hop.ask_denom
+        ))
+        .into());
+    }
+    assert_oracle_price(deps, &offer, &hop.ask_denom, simulation.return_amount)?;
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: hop.pair_contract.clone(),
+        msg: to_binary(&PairExecuteMsg::Swap {
+            offer_asset,
+            belief_price: Some(Decimal::from_ratio(offer.amount, simulation.return_amount)),
+            max_spread: hop.max_spread,
+            to: None,
+        })?,
+        funds: vec![offer],
+    }))
+}
+
+/// A pool moved away from the oracle price, e.g. by a sandwich around the index update,
+/// is not swapped against. The oracle is configured on the hub
+fn assert_oracle_price(
+    deps: Deps,
+    offer: &Coin,
+    ask_denom: &str,
+    returned: Uint128,
+) -> Result<(), ContractError> {
+    let config = read_config(deps.storage)?;
+    let hub_addr = deps.api.addr_humanize(&config.hub_contract)?;
+    let guard = match query_hub_price_guard(&deps.querier, hub_addr.as_str())? {
+        Some(guard) => guard,
+        None => return Ok(()),
+    };
+
+    let price = query_oracle_price(
+        &deps.querier,
+        deps.api.addr_humanize(&guard.oracle_contract)?.as_str(),
+        &offer.denom,
+        ask_denom,
+    )?;
+    assert_price_deviation(
+        Decimal::from_ratio(returned, offer.amount),
+        price,
+        guard.max_deviation,
+    )
+}
+
+/// Record the coins offered by a SwapToRewardDenom as the latest run
+pub fn store_swap_run(
+    storage: &mut dyn Storage,
+    env: &Env,
+    reward_balance: Uint128,
+    swaps: Vec<SwapReport>,
+) -> StdResult<()> {
+    let run_id = SWAP_RUN_COUNT.may_load(storage)?.unwrap_or_default();
+    let run = SwapRun {
+        run_id,
+        time: env.block.time.seconds(),
+        swaps,
+        reward_balance,
+        returned: None,
+    };
+    SWAP_RUNS.save(storage, U64Key::new(run_id), &run)?;
+    if run_id >= MAX_SWAP_RUNS {
+        SWAP_RUNS.remove(storage, U64Key::new(run_id - MAX_SWAP_RUNS));
+    }
+    SWAP_RUN_COUNT.save(storage, &(run_id + 1))
+}
+
+fn latest_swap_run(storage: &dyn Storage) -> StdResult<Option<SwapRun>> {
+    Ok(SWAP_RUNS
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, run)| run))
+}
+
+/// Record the error of a failed swap or hop in the latest run
+pub fn record_swap_error(storage: &mut dyn Storage, denom: &str, error: &str) -> StdResult<()> {
+    let mut run = match latest_swap_run(storage)? {
+        Some(run) => run,
+        None => return Ok(()),
+    };
+    for swap in run.swaps.iter_mut() {
+        if swap.offer_denom == denom {
+            swap.error = Some(error.to_string());
+        }
+    }
+    SWAP_RUNS.save(storage, U64Key::new(run.run_id), &run)
+}
+
+/// Complete the latest run with the balances after its swaps, the index update following
+/// the swaps checks the reward balance anyway
+pub fn check_swap_run(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    reward_balance: Uint128,
+) -> StdResult<()> {
+    let mut run = match latest_swap_run(storage)? {
+        Some(run) if run.returned.is_none() => run,
+        _ => return Ok(()),
+    };
+    for swap in run.swaps.iter_mut() {
+        let remaining = querier.query_balance(env.contract.address.clone(), &swap.offer_denom)?;
+        swap.remaining = Some(remaining.amount);
+    }
+    run.returned = Some(reward_balance.saturating_sub(run.reward_balance));
+    SWAP_RUNS.save(storage, U64Key::new(run.run_id), &run)
+}
+
+pub fn query_swap_history(deps: Deps) -> StdResult<SwapHistoryResponse> {
+    let runs = SWAP_RUNS
+        .range(deps.storage, None, None, Order::Descending)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<Vec<SwapRun>>>()?;
+    Ok(SwapHistoryResponse { runs })
+}
+
+pub fn query_swap_routes(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<SwapRoutesResponse> {
+    Ok(SwapRoutesResponse {
+        routes: read_swap_routes(deps.storage, start_after, limit)?,
+    })
+}
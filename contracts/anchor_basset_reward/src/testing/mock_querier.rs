@@ -1,14 +1,20 @@
-use basset::hub::Config;
+use basset::astroport::{PairQueryMsg, SimulationResponse};
+use basset::hub::{
+    CircuitBreaker, Config, Parameters, QueryMsg as HubQueryMsg, StateResponse as HubStateResponse,
+};
+use basset::oracle::{OracleQueryMsg, PriceGuard, PriceResponse};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_slice, to_binary, Api, Coin, ContractResult, Decimal, OwnedDeps, Querier, QuerierResult,
-    QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+    from_slice, to_binary, Api, Binary, Coin, ContractResult, Decimal, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
 use cosmwasm_storage::to_length_prefixed;
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use std::collections::HashMap;
 use std::str::FromStr;
 use terra_cosmwasm::{
-    ExchangeRateItem, ExchangeRatesResponse, TaxCapResponse, TaxRateResponse, TerraQuery,
-    TerraQueryWrapper, TerraRoute,
+    ExchangeRateItem, ExchangeRatesResponse, SwapResponse, TaxCapResponse, TaxRateResponse,
+    TerraQuery, TerraQueryWrapper, TerraRoute,
 };
 
 pub const MOCK_HUB_CONTRACT_ADDR: &str = "hub";
@@ -31,6 +37,13 @@ pub fn mock_dependencies(
 
 pub struct WasmMockQuerier {
     base: MockQuerier<TerraQueryWrapper>,
+    circuit_breaker: Option<CircuitBreaker>,
+    pair_rates: HashMap<String, Decimal>,
+    price_guard: Option<PriceGuard>,
+    tax_free: bool,
+    oracle_prices: HashMap<(String, String), Decimal>,
+    /// The balances cw20 tokens hold for the contract
+    token_balances: HashMap<String, Uint128>,
 }
 
 impl Querier for WasmMockQuerier {
@@ -89,6 +102,17 @@ impl WasmMockQuerier {
                         }
                         _ => panic!("DO NOT ENTER HERE"),
                     }
+                } else if &TerraRoute::Market == route {
+                    match query_data {
+                        // the market swaps 22 of the 22.1 of the oracle rate
+                        TerraQuery::Swap {
+                            offer_coin,
+                            ask_denom,
+                        } => SystemResult::Ok(ContractResult::from(to_binary(&SwapResponse {
+                            receive: Coin::new(offer_coin.amount.u128() * 22, ask_denom),
+                        }))),
+                        _ => panic!("DO NOT ENTER HERE"),
+                    }
                 } else {
                     panic!("DO NOT ENTER HERE")
                 }
@@ -113,6 +137,24 @@ impl WasmMockQuerier {
                             ),
                         };
                         SystemResult::Ok(ContractResult::from(to_binary(&config)))
+                    } else if key.as_slice() == b"circuit_breaker" {
+                        let value = match &self.circuit_breaker {
+                            Some(breaker) => to_binary(breaker).unwrap(),
+                            None => Binary::default(),
+                        };
+                        SystemResult::Ok(ContractResult::Ok(value))
+                    } else if key.as_slice() == b"price_guard" {
+                        let value = match &self.price_guard {
+                            Some(guard) => to_binary(guard).unwrap(),
+                            None => Binary::default(),
+                        };
+                        SystemResult::Ok(ContractResult::Ok(value))
+                    } else if key.as_slice() == b"tax_free" {
+                        let value = match self.tax_free {
+                            true => to_binary(&true).unwrap(),
+                            false => Binary::default(),
+                        };
+                        SystemResult::Ok(ContractResult::Ok(value))
                     } else {
                         unimplemented!()
                     }
@@ -120,6 +162,77 @@ impl WasmMockQuerier {
                     unimplemented!()
                 }
             }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == "oracle" =>
+            {
+                let OracleQueryMsg::Price { base, quote } = from_slice(msg).unwrap();
+                let res = PriceResponse {
+                    rate: self.oracle_prices[&(base, quote)],
+                    last_updated_base: 0,
+                    last_updated_quote: 0,
+                };
+                SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == MOCK_HUB_CONTRACT_ADDR =>
+            {
+                match from_slice(msg).unwrap() {
+                    HubQueryMsg::State {} => {
+                        let res = HubStateResponse {
+                            exchange_rate: Decimal::one(),
+                            total_bond_amount: Uint128::zero(),
+                            last_index_modification: 0,
+                            prev_hub_balance: Uint128::zero(),
+                            actual_unbonded_amount: Uint128::zero(),
+                            last_unbonded_time: 0,
+                            last_processed_batch: 0,
+                        };
+                        SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                    }
+                    HubQueryMsg::Parameters {} => {
+                        let res = Parameters {
+                            epoch_period: 30,
+                            underlying_coin_denom: "uluna".to_string(),
+                            unbonding_period: 210,
+                            peg_recovery_fee: Decimal::zero(),
+                            er_threshold: Decimal::one(),
+                            reward_denom: "uusd".to_string(),
+                            unbond_fee_bps: 0,
+                        };
+                        SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if self.token_balances.contains_key(contract_addr) =>
+            {
+                let Cw20QueryMsg::Balance { address } = from_slice(msg).unwrap() else {
+                    panic!("DO NOT ENTER HERE")
+                };
+                assert_eq!(address, MOCK_CONTRACT_ADDR);
+                let res = BalanceResponse {
+                    balance: self.token_balances[contract_addr],
+                };
+                SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                let rate = match self.pair_rates.get(contract_addr) {
+                    Some(rate) => *rate,
+                    None => {
+                        return SystemResult::Err(SystemError::NoSuchContract {
+                            addr: contract_addr.to_string(),
+                        })
+                    }
+                };
+                let PairQueryMsg::Simulation { offer_asset } = from_slice(msg).unwrap();
+                let res = SimulationResponse {
+                    return_amount: offer_asset.amount * rate,
+                    spread_amount: Uint128::zero(),
+                    commission_amount: Uint128::zero(),
+                };
+                SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            }
             _ => self.base.handle_query(request),
         }
     }
@@ -127,6 +240,56 @@ impl WasmMockQuerier {
 
 impl WasmMockQuerier {
     pub fn new(base: MockQuerier<TerraQueryWrapper>) -> Self {
-        WasmMockQuerier { base }
+        WasmMockQuerier {
+            base,
+            circuit_breaker: None,
+            pair_rates: HashMap::new(),
+            price_guard: None,
+            tax_free: false,
+            oracle_prices: HashMap::new(),
+            token_balances: HashMap::from([("aterra".to_string(), Uint128::zero())]),
+        }
+    }
+
+    // configure the circuit breaker stored by the hub
+    pub fn with_circuit_breaker(&mut self, breaker: CircuitBreaker) {
+        self.circuit_breaker = Some(breaker);
+    }
+
+    // configure the hub flagging the chain as tax free
+    pub fn with_tax_free(&mut self) {
+        self.tax_free = true;
+    }
+
+    // configure an Astroport pair returning rate ask per offered coin
+    pub fn with_pair_rate(&mut self, pair_contract: &str, rate: Decimal) {
+        self.pair_rates.insert(pair_contract.to_string(), rate);
+    }
+
+    // configure the native balances of the contract
+    pub fn with_balance(&mut self, balance: &[Coin]) {
+        self.base
+            .update_balance(MOCK_CONTRACT_ADDR, balance.to_vec());
+    }
+
+    // configure the aTerra the "aterra" token holds for the contract
+    pub fn with_aterra_balance(&mut self, balance: Uint128) {
+        self.with_token_balance("aterra", balance);
+    }
+
+    // configure the balance the cw20 token holds for the contract
+    pub fn with_token_balance(&mut self, token: &str, balance: Uint128) {
+        self.token_balances.insert(token.to_string(), balance);
+    }
+
+    // configure the price guard of the hub, the oracle is the "oracle" contract
+    pub fn with_oracle_price(&mut self, base: &str, quote: &str, rate: Decimal) {
+        let api = MockApi::default();
+        self.price_guard = Some(PriceGuard {
+            oracle_contract: api.addr_canonicalize("oracle").unwrap(),
+            max_deviation: Decimal::percent(5),
+        });
+        self.oracle_prices
+            .insert((base.to_string(), quote.to_string()), rate);
     }
 }
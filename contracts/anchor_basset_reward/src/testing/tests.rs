@@ -16,23 +16,40 @@
 //!          //...
 //!      });
 //! 4. Anywhere you see query(deps.as_ref(), mock_env(),...) you must replace it with query(&mut deps, ...)
+#![allow(clippy::doc_overindented_list_items)]
 
-use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, Api, BankMsg, Coin, CosmosMsg, Decimal, StdError, SubMsg, Uint128,
+    attr, from_binary, to_binary, Api, BankMsg, Binary, Coin, ContractResult, CosmosMsg, Decimal,
+    Deps, Event, OwnedDeps, Reply, StdError, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
 };
-use terra_cosmwasm::create_swap_msg;
+use terra_cosmwasm::{create_swap_msg, create_swap_send_msg, TerraMsg, TerraMsgWrapper};
 
-use crate::contract::{execute, instantiate, query};
-use crate::math::{decimal_multiplication_in_256, decimal_subtraction_in_256};
-use crate::state::{store_holder, store_state, Holder, State};
+use crate::contract::{execute, instantiate, query, reply};
+use crate::state::{
+    read_config, read_state, store_holder, store_state, Config, Holder, State, CONFIG_KEY,
+};
 use crate::testing::mock_querier::{
     mock_dependencies, MOCK_HUB_CONTRACT_ADDR, MOCK_TOKEN_CONTRACT_ADDR,
 };
+use basset::astroport::{Asset, AssetInfo, PairExecuteMsg};
+use basset::contract_error::ContractError;
+use basset::distribution::{encode_msg_fund_community_pool, MSG_FUND_COMMUNITY_POOL_TYPE_URL};
+use basset::hub::{CircuitBreaker, MAX_KEEPER_BOUNTY, MAX_KEEPER_RESERVE};
+use basset::math::{decimal_multiplication_in_256, decimal_subtraction_in_256};
+use basset::money_market::MarketExecuteMsg;
 use basset::reward::{
-    ConfigResponse, ExecuteMsg, HolderResponse, HoldersResponse, InstantiateMsg, QueryMsg,
-    StateResponse,
+    AccrualRecipient, AccrualRecipientResponse, AccruedRewardsResponse, AprResponse, CommunityFee,
+    CommunityFeeResponse, ConfigResponse, DenomMigrationsResponse, EarnConfig, EarnConfigResponse,
+    ExecuteMsg, FeeDestination, FeesCollectedResponse, HolderDistributionResponse, HolderResponse,
+    HolderTier, HoldersResponse, HubsResponse, IndexDeltaResponse, InstantiateMsg,
+    KeeperReserveResponse, PendingChangesResponse, PrecisionReportResponse, QueryMsg,
+    RegisteredContractsResponse, RewardDepositResponse, RewardRecipientResponse, SolvencyResponse,
+    StateResponse, SwapHistoryResponse, SwapHop, SwapReport, SwapRouteResponse, SwapRoutesResponse,
+    SwapRun, TimelockedChange,
 };
+use basset::snapshot::{ExportStateResponse, StateEntry};
+use cw20::Cw20ExecuteMsg;
 use std::str::FromStr;
 
 const DEFAULT_REWARD_DENOM: &str = "uusd";
@@ -41,13 +58,22 @@ fn default_init() -> InstantiateMsg {
     InstantiateMsg {
         hub_contract: String::from(MOCK_HUB_CONTRACT_ADDR),
         reward_denom: DEFAULT_REWARD_DENOM.to_string(),
+        reward_asset: None,
     }
 }
 
 #[test]
 fn proper_init() {
     let mut deps = mock_dependencies(&[]);
-    let init_msg = default_init();
+    let init_msg: InstantiateMsg = from_binary(&Binary::from(
+        format!(
+            r#"{{"hub_contract":"{}","reward_denom":"{}"}}"#,
+            MOCK_HUB_CONTRACT_ADDR, DEFAULT_REWARD_DENOM
+        )
+        .as_bytes(),
+    ))
+    .unwrap();
+    assert_eq!(init_msg, default_init());
 
     let info = mock_info("addr0000", &[]);
 
@@ -61,6 +87,9 @@ fn proper_init() {
         ConfigResponse {
             hub_contract: String::from(MOCK_HUB_CONTRACT_ADDR),
             reward_denom: DEFAULT_REWARD_DENOM.to_string(),
+            reward_asset: AssetInfo::NativeToken {
+                denom: DEFAULT_REWARD_DENOM.to_string(),
+            },
         }
     );
 
@@ -110,32 +139,410 @@ pub fn swap_to_reward_denom() {
     let msg = ExecuteMsg::SwapToRewardDenom {};
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    if cfg!(feature = "no-market") {
+        assert!(res.messages.is_empty());
+        return;
+    }
     assert_eq!(
         res.messages,
         vec![
-            SubMsg::new(create_swap_msg(
-                Coin {
-                    denom: "ukrw".to_string(),
-                    amount: Uint128::new(1000u128),
-                },
-                DEFAULT_REWARD_DENOM.to_string()
-            )),
-            SubMsg::new(create_swap_msg(
-                Coin {
-                    denom: "usdr".to_string(),
-                    amount: Uint128::new(50u128)
-                },
-                DEFAULT_REWARD_DENOM.to_string()
-            )),
-            SubMsg::new(create_swap_msg(
-                Coin {
-                    denom: "uinr".to_string(),
-                    amount: Uint128::new(50u128)
-                },
-                DEFAULT_REWARD_DENOM.to_string()
-            )),
+            SubMsg::reply_on_error(
+                create_swap_msg(
+                    Coin {
+                        denom: "uinr".to_string(),
+                        amount: Uint128::new(50u128)
+                    },
+                    DEFAULT_REWARD_DENOM.to_string()
+                ),
+                1
+            ),
+            SubMsg::reply_on_error(
+                create_swap_msg(
+                    Coin {
+                        denom: "ukrw".to_string(),
+                        amount: Uint128::new(1000u128),
+                    },
+                    DEFAULT_REWARD_DENOM.to_string()
+                ),
+                2
+            ),
+            SubMsg::reply_on_error(
+                create_swap_msg(
+                    Coin {
+                        denom: "usdr".to_string(),
+                        amount: Uint128::new(50u128)
+                    },
+                    DEFAULT_REWARD_DENOM.to_string()
+                ),
+                3
+            ),
+        ]
+    );
+
+    // a failing swap is reported without reverting the others
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 3,
+            result: ContractResult::Err("swap pool unavailable".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "swap_failed"),
+            attr("denom", "usdr"),
+            attr("error", "swap pool unavailable"),
+        ]
+    );
+}
+
+#[test]
+fn swaps_are_sent_in_denom_order() {
+    let coins = vec![
+        Coin::new(50u128, "usdr"),
+        Coin::new(100u128, "uusd"),
+        Coin::new(1000u128, "ukrw"),
+        Coin::new(50u128, "uinr"),
+    ];
+    let mut deps = mock_dependencies(&coins);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let first = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+
+    // the same balances listed in another order send the same swaps
+    let mut reversed = coins;
+    reversed.reverse();
+    deps.querier.with_balance(&reversed);
+    let second = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    assert_eq!(first.messages, second.messages);
+
+    let offered: Vec<String> = first
+        .messages
+        .iter()
+        .map(|msg| match &msg.msg {
+            CosmosMsg::Custom(TerraMsgWrapper {
+                msg_data: TerraMsg::Swap { offer_coin, .. },
+                ..
+            }) => offer_coin.denom.clone(),
+            _ => panic!("DO NOT ENTER HERE"),
+        })
+        .collect();
+    if cfg!(feature = "no-market") {
+        assert!(offered.is_empty());
+    } else {
+        assert_eq!(offered, vec!["uinr", "ukrw", "usdr"]);
+    }
+}
+
+#[test]
+fn swap_through_astroport_routes() {
+    let mut deps = mock_dependencies(&[
+        Coin {
+            denom: "ukrw".to_string(),
+            amount: Uint128::new(1000u128),
+        },
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20u128),
+        },
+    ]);
+    deps.querier
+        .with_pair_rate("krw_luna_pair", Decimal::from_str("0.02").unwrap());
+    deps.querier
+        .with_pair_rate("luna_usd_pair", Decimal::from_str("5").unwrap());
+
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, default_init()).unwrap();
+
+    let route = vec![
+        SwapHop {
+            pair_contract: "krw_luna_pair".to_string(),
+            ask_denom: "uluna".to_string(),
+            max_spread: Some(Decimal::percent(1)),
+        },
+        SwapHop {
+            pair_contract: "luna_usd_pair".to_string(),
+            ask_denom: DEFAULT_REWARD_DENOM.to_string(),
+            max_spread: None,
+        },
+    ];
+    let register = ExecuteMsg::RegisterSwapRoute {
+        offer_denom: "ukrw".to_string(),
+        hops: route.clone(),
+    };
+
+    // only the owner of the hub can register a route
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, register.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the route must end with the reward denom
+    let info = mock_info("owner1", &[]);
+    let msg = ExecuteMsg::RegisterSwapRoute {
+        offer_denom: "ukrw".to_string(),
+        hops: route[..1].to_vec(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("The swap route must end with uusd"))
+    );
+
+    // a route can not go through a denom twice
+    let mut looping = route.clone();
+    looping[1].ask_denom = "ukrw".to_string();
+    let msg = ExecuteMsg::RegisterSwapRoute {
+        offer_denom: "ukrw".to_string(),
+        hops: looping,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The swap route returns ukrw more than once"
+        ))
+    );
+
+    execute(deps.as_mut(), mock_env(), info, register).unwrap();
+    let res: SwapRoutesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SwapRoutes {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.routes,
+        vec![SwapRouteResponse {
+            offer_denom: "ukrw".to_string(),
+            hops: route,
+        }]
+    );
+
+    // the routed denom goes to its first pair, the others still go to the market
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    let offered = Coin::new(990, "ukrw"); // 1% tax
+    let mut expected = vec![SubMsg::reply_always(
+        WasmMsg::Execute {
+            contract_addr: "krw_luna_pair".to_string(),
+            msg: to_binary(&PairExecuteMsg::Swap {
+                offer_asset: Asset::native("ukrw", offered.amount),
+                belief_price: Some(Decimal::from_ratio(990u128, 19u128)),
+                max_spread: Some(Decimal::percent(1)),
+                to: None,
+            })
+            .unwrap(),
+            funds: vec![offered],
+        },
+        1,
+    )];
+    if !cfg!(feature = "no-market") {
+        expected.push(SubMsg::reply_on_error(
+            create_swap_msg(Coin::new(20, "uluna"), DEFAULT_REWARD_DENOM.to_string()),
+            2,
+        ));
+    }
+    assert_eq!(res.messages, expected);
+
+    // a hop that reports no return is not followed, its uluna stays in the contract
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "swap_failed"),
+            attr("denom", "ukrw"),
+            attr(
+                "error",
+                "Generic error: The swap of krw_luna_pair reports no return_amount"
+            ),
+        ]
+    );
+
+    // only the returned uluna is offered to the next pair, not the uluna swapped by the market
+    let swap_event = Event::new("wasm")
+        .add_attribute("_contract_address", "krw_luna_pair")
+        .add_attribute("action", "swap")
+        .add_attribute("return_amount", "16")
+        .add_attribute("tax_amount", "1");
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![swap_event],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    let offered = Coin::new(15, "uluna");
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "luna_usd_pair".to_string(),
+                msg: to_binary(&PairExecuteMsg::Swap {
+                    offer_asset: Asset::native("uluna", offered.amount),
+                    belief_price: Some(Decimal::from_ratio(15u128, 75u128)),
+                    max_spread: None,
+                    to: None,
+                })
+                .unwrap(),
+                funds: vec![offered],
+            },
+            1
+        )]
+    );
+
+    // the last hop ends the route
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    // the denom is swapped by the market again once deregistered
+    let info = mock_info("owner1", &[]);
+    let msg = ExecuteMsg::DeregisterSwapRoute {
+        offer_denom: "ukrw".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    if cfg!(feature = "no-market") {
+        assert!(res.messages.is_empty());
+        return;
+    }
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_error(
+            create_swap_msg(Coin::new(1000, "ukrw"), DEFAULT_REWARD_DENOM.to_string()),
+            1
+        )
+    );
+}
+
+#[test]
+fn routed_swaps_follow_the_oracle_price() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "ukrw".to_string(),
+        amount: Uint128::new(1000000u128),
+    }]);
+    deps.querier
+        .with_pair_rate("krw_usd_pair", Decimal::from_str("0.0008").unwrap());
+
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, default_init()).unwrap();
+    let msg = ExecuteMsg::RegisterSwapRoute {
+        offer_denom: "ukrw".to_string(),
+        hops: vec![SwapHop {
+            pair_contract: "krw_usd_pair".to_string(),
+            ask_denom: DEFAULT_REWARD_DENOM.to_string(),
+            max_spread: None,
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg).unwrap();
+
+    // the pool returns 20% less than the oracle price, it is not swapped against
+    deps.querier.with_oracle_price(
+        "ukrw",
+        DEFAULT_REWARD_DENOM,
+        Decimal::from_str("0.001").unwrap(),
+    );
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes[2..],
+        [
+            attr("swap_skipped", "ukrw"),
+            attr(
+                "error",
+                "Price 0.0007999200079992 deviates too much from the oracle price 0.001"
+            ),
         ]
     );
+
+    deps.querier.with_oracle_price(
+        "ukrw",
+        DEFAULT_REWARD_DENOM,
+        Decimal::from_str("0.00082").unwrap(),
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
 }
 
 #[test]
@@ -164,7 +571,9 @@ fn update_global_index() {
     let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
     match res {
-        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "No asset is bonded by Hub"),
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "No asset is bonded by Hub")
+        }
         _ => panic!("DO NOT ENTER HERE"),
     }
 
@@ -195,250 +604,479 @@ fn update_global_index() {
 }
 
 #[test]
-fn increase_balance() {
+fn added_hubs_update_the_index() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
         amount: Uint128::new(100u128),
     }]);
-
-    let init_msg = default_init();
     let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, default_init()).unwrap();
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::from(100u128),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+    .unwrap();
 
-    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
-
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
-    };
-
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
-    match res {
-        Err(_e) => {}
-        _ => panic!("DO NOT ENTER HERE"),
+    let add_hub = ExecuteMsg::AddHub {
+        hub: "hub2".to_string(),
     };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub2", &[]),
+        add_hub.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::AddHub {
+            hub: MOCK_HUB_CONTRACT_ADDR.to_string(),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("The hub contract is always allowed"))
+    );
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), add_hub).unwrap();
 
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Hubs {}).unwrap();
+    let hubs: HubsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        hubs.hubs,
+        vec![MOCK_HUB_CONTRACT_ADDR.to_string(), "hub2".to_string()]
+    );
 
-    let res = query(
-        deps.as_ref(),
+    // the added hub updates the index like hub_contract
+    execute(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0000"),
-        },
+        mock_info("hub2", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
     assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0000"),
-            balance: Uint128::from(100u128),
-            index: Decimal::zero(),
-            pending_rewards: Decimal::zero(),
-        }
+        read_state(&deps.storage).unwrap().global_index,
+        Decimal::one()
     );
 
-    // claimed_rewards = 100, total_balance = 100
-    // global_index == 1
-    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::UpdateGlobalIndex {};
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
+    let remove_hub = ExecuteMsg::RemoveHub {
+        hub: "hub2".to_string(),
     };
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let res = query(
-        deps.as_ref(),
+    execute(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0000"),
-        },
+        mock_info("owner1", &[]),
+        remove_hub.clone(),
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub2", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        remove_hub,
+    );
     assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0000"),
-            balance: Uint128::from(200u128),
-            index: Decimal::one(),
-            pending_rewards: Decimal::from_str("100").unwrap(),
-        }
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("hub2 is not an added hub"))
     );
 }
 
 #[test]
-fn increase_balance_with_decimals() {
-    let mut deps = mock_dependencies(&[Coin {
-        denom: "uusd".to_string(),
-        amount: Uint128::new(100000u128),
-    }]);
-
-    let init_msg = default_init();
+fn timelocked_changes() {
+    let mut deps = mock_dependencies(&[]);
     let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, default_init()).unwrap();
+    let owner_info = mock_info("owner1", &[]);
 
-    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
-
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(11u128),
+    // only the owner of the hub can propose
+    let set_period = ExecuteMsg::ProposePendingChange {
+        change: TimelockedChange::UpdateTimelockPeriod {
+            timelock_period: 100,
+        },
     };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub2", &[]),
+        set_period.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
 
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
-    match res {
-        Err(_e) => {}
-        _ => panic!("DO NOT ENTER HERE"),
-    };
+    // without a timelock period the change can be applied right away
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), set_period).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::ApplyPendingChange { id: 0 },
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("action", "update_timelock_period")));
 
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // the contracts trusted with the rewards can no longer be set directly
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::AddHub {
+            hub: "hub2".to_string(),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let earn = EarnConfig {
+        market_contract: "market".to_string(),
+        aterra_contract: "aterra".to_string(),
+        deposit_by_default: false,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateEarnConfig {
+            earn: Some(earn.clone()),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateCommunityFee {
+            fee: Some(CommunityFee {
+                rate: Decimal::percent(10),
+                destination: FeeDestination::CommunityPool {},
+            }),
+        },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Timelocked {});
+    // stopping the deposits is not delayed
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateEarnConfig { earn: None },
+    )
+    .unwrap();
 
-    let res = query(
-        deps.as_ref(),
+    execute(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0000"),
+        owner_info.clone(),
+        ExecuteMsg::ProposePendingChange {
+            change: TimelockedChange::AddHub {
+                hub: "hub2".to_string(),
+            },
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::ProposePendingChange {
+            change: TimelockedChange::UpdateEarnConfig { earn },
         },
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0000"),
-            balance: Uint128::from(11u128),
-            index: Decimal::zero(),
-            pending_rewards: Decimal::zero(),
-        }
-    );
-
-    // claimed_rewards = 100000 , total_balance = 11
-    // global_index == 9077.727272727272727272
-    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::UpdateGlobalIndex {};
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(10u128),
-    };
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0000"),
+        QueryMsg::PendingChanges {
+            start_after: None,
+            limit: None,
         },
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
-    let index = decimal_multiplication_in_256(
-        Decimal::from_ratio(Uint128::new(100000), Uint128::new(11)),
-        Decimal::one(),
-    );
-    let user_pend_reward = decimal_multiplication_in_256(
-        Decimal::from_str("11").unwrap(),
-        decimal_subtraction_in_256(holder_response.index, Decimal::zero()),
+    let pending: PendingChangesResponse = from_binary(&res).unwrap();
+    assert_eq!(pending.timelock_period, 100);
+    assert_eq!(pending.changes.len(), 2);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::ApplyPendingChange { id: 1 },
     );
     assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0000"),
-            balance: Uint128::from(21u128),
-            index,
-            pending_rewards: user_pend_reward,
+        res.unwrap_err(),
+        ContractError::TimelockNotExpired {
+            id: 1,
+            effective_at: mock_env().block.time.seconds() + 100,
         }
     );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::ApplyPendingChange { id: 1 },
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("action", "add_hub")));
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Hubs {}).unwrap();
+    let hubs: HubsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        hubs.hubs,
+        vec![MOCK_HUB_CONTRACT_ADDR.to_string(), "hub2".to_string()]
+    );
+
+    // a cancelled change cannot be applied
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::CancelPendingChange { id: 2 },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env,
+        owner_info,
+        ExecuteMsg::ApplyPendingChange { id: 2 },
+    );
+    assert!(res.is_err());
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::EarnConfig {}).unwrap();
+    let earn: EarnConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(earn.earn, None);
 }
 
 #[test]
-fn decrease_balance() {
+fn update_global_index_takes_the_community_fee() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
         amount: Uint128::new(100u128),
     }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
 
-    let init_msg = default_init();
-    let info = mock_info("addr0000", &[]);
-
-    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
-
-    let msg = ExecuteMsg::DecreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
+    // only the owner of the hub sets the fee
+    let update = |destination| ExecuteMsg::UpdateCommunityFee {
+        fee: Some(CommunityFee {
+            rate: Decimal::percent(10),
+            destination,
+        }),
     };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        update(FeeDestination::CommunityPool {}),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::UpdateCommunityFee {
+            fee: Some(CommunityFee {
+                rate: Decimal::percent(21),
+                destination: FeeDestination::CommunityPool {},
+            }),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The fee rate must not be more than 0.2"
+        ))
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(FeeDestination::CommunityPool {}),
+    )
+    .unwrap();
 
-    // Failed unautorized
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
-    match res {
-        Err(_e) => {}
-        _ => panic!("DO NOT ENTER HERE"),
-    };
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::from(100u128),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Stargate {
+            type_url: MSG_FUND_COMMUNITY_POOL_TYPE_URL.to_string(),
+            value: encode_msg_fund_community_pool(&[Coin::new(10, "uusd")], MOCK_CONTRACT_ADDR),
+        })]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("community_fee")
+            .add_attribute("amount", "10")
+            .add_attribute("destination", "community_pool")]
+    );
 
-    // Failed underflow
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg);
-    match res {
-        Err(StdError::GenericErr { msg, .. }) => {
-            assert_eq!(msg, "Decrease amount cannot exceed user balance: 0")
+    // the holders share what is left after the fee
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state_response: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        state_response,
+        StateResponse {
+            global_index: Decimal::percent(90),
+            total_balance: Uint128::from(100u128),
+            prev_reward_balance: Uint128::from(90u128)
         }
-        _ => panic!("DO NOT ENTER HERE"),
-    };
-
-    // Increase balance first
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
-    };
+    );
 
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // an address receives the fee net of the tax
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update(FeeDestination::Address {
+            address: "treasury".to_string(),
+        }),
+    )
+    .unwrap();
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::from(100u128),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(9, "uusd")],
+        }))]
+    );
 
-    // claimed_rewards = 100, total_balance = 100
-    // global_index == 1
-    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::UpdateGlobalIndex {};
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::CommunityFee {}).unwrap();
+    let fee: CommunityFeeResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        fee.fee.unwrap().destination,
+        FeeDestination::Address {
+            address: "treasury".to_string()
+        }
+    );
 
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::DecreaseBalance {
-        address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
-    };
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // the treasury reconciles the fees it received
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::FeesCollected {}).unwrap();
+    let collected: FeesCollectedResponse = from_binary(&res).unwrap();
+    assert_eq!(collected.fees, vec![Coin::new(20, "uusd")]);
+}
 
-    let res = query(
-        deps.as_ref(),
+#[test]
+fn apr_follows_the_index_history() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0000"),
-        },
+        mock_info("addr0000", &[]),
+        default_init(),
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    let apr = |deps: &OwnedDeps<_, _, _>, period| {
+        query(deps.as_ref(), mock_env(), QueryMsg::Apr { period })
+            .and_then(|res| from_binary::<AprResponse>(&res))
+    };
     assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0000"),
-            balance: Uint128::zero(),
-            index: Decimal::one(),
-            pending_rewards: Decimal::from_str("100").unwrap(),
+        apr(&deps, 1).unwrap_err(),
+        StdError::generic_err("The global index was never updated")
+    );
+
+    // every update claims 100 for 100 bAsset, a day apart
+    let mut env = mock_env();
+    let start_time = env.block.time.seconds();
+    for _ in 0..2 {
+        let global_index = read_state(&deps.storage).unwrap().global_index;
+        store_state(
+            &mut deps.storage,
+            &State {
+                global_index,
+                total_balance: Uint128::from(100u128),
+                prev_reward_balance: Uint128::zero(),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+            ExecuteMsg::UpdateGlobalIndex {},
+        )
+        .unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+    }
+
+    assert_eq!(
+        apr(&deps, 2 * 86400).unwrap_err(),
+        StdError::generic_err("The index history is shorter than 172800 seconds")
+    );
+    // without an oracle on the hub only the accrued reward is known
+    let res = apr(&deps, 86400).unwrap();
+    assert_eq!(
+        res,
+        AprResponse {
+            start_time,
+            end_time: start_time + 86400,
+            reward_per_basset: Decimal::one(),
+            apr: None,
         }
     );
+
+    // 365 uusd a year for a bAsset worth 73 uusd
+    deps.querier
+        .with_oracle_price("uluna", "uusd", Decimal::from_str("73").unwrap());
+    assert_eq!(
+        apr(&deps, 86400).unwrap().apr,
+        Some(Decimal::from_str("5").unwrap())
+    );
+    // a shorter period still starts at the previous update
+    assert_eq!(apr(&deps, 3600).unwrap().start_time, start_time);
 }
 
 #[test]
-fn claim_rewards() {
+fn increase_balance() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
         amount: Uint128::new(100u128),
@@ -454,6 +1092,13 @@ fn claim_rewards() {
         amount: Uint128::from(100u128),
     };
 
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(_e) => {}
+        _ => panic!("DO NOT ENTER HERE"),
+    };
+
     let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -482,62 +1127,11 @@ fn claim_rewards() {
     let msg = ExecuteMsg::UpdateGlobalIndex {};
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::ClaimRewards { recipient: None };
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: String::from("addr0000"),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(99u128), // 1% tax
-            },]
-        }))]
-    );
-
-    // Set recipient
-    // claimed_rewards = 100, total_balance = 100
-    // global_index == 1
-    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::UpdateGlobalIndex {};
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::ClaimRewards {
-        recipient: Some(String::from("addr0001")),
-    };
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: String::from("addr0001"),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(99u128), // 1% tax
-            },]
-        }))]
-    );
-}
-
-#[test]
-fn claim_rewards_with_decimals() {
-    let mut deps = mock_dependencies(&[Coin {
-        denom: "uusd".to_string(),
-        amount: Uint128::new(99999u128),
-    }]);
-
-    let init_msg = default_init();
-    let info = mock_info("addr0000", &[]);
-
-    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
-
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
     let msg = ExecuteMsg::IncreaseBalance {
         address: String::from("addr0000"),
-        amount: Uint128::from(11u128),
+        amount: Uint128::from(100u128),
     };
-
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(
@@ -553,73 +1147,196 @@ fn claim_rewards_with_decimals() {
         holder_response,
         HolderResponse {
             address: String::from("addr0000"),
-            balance: Uint128::from(11u128),
-            index: Decimal::zero(),
-            pending_rewards: Decimal::zero(),
+            balance: Uint128::from(200u128),
+            index: Decimal::one(),
+            pending_rewards: Decimal::from_str("100").unwrap(),
         }
     );
+}
 
-    // claimed_rewards = 1000000, total_balance = 11
-    // global_index ==
-    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-
-    let msg = ExecuteMsg::UpdateGlobalIndex {};
-    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let msg = ExecuteMsg::ClaimRewards { recipient: None };
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: String::from("addr0000"),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::from(99007u128), // 1% tax
-            },]
-        }))]
-    );
-
-    let res = query(
-        deps.as_ref(),
+#[test]
+fn holders_migrate_their_rewards() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1000000u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        ExecuteMsg::IncreaseBalance {
             address: String::from("addr0000"),
+            amount: Uint128::from(10u128),
         },
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
-    let index = decimal_multiplication_in_256(
-        Decimal::from_ratio(Uint128::new(99999), Uint128::new(11)),
-        Decimal::one(),
+    // global_index == 100000
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let migrate = |new_address: &str| ExecuteMsg::MigrateHolder {
+        new_address: new_address.to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), migrate("addr0000"));
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The rewards cannot be migrated to the same address"
+        ))
     );
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), migrate("addr0001")).unwrap();
     assert_eq!(
-        holder_response,
+        res.attributes,
+        vec![
+            attr("action", "migrate_holder"),
+            attr("old_address", "addr0000"),
+            attr("new_address", "addr0001"),
+            attr("rewards", "1000000"),
+        ]
+    );
+
+    // the balance stays with the bAsset, the rewards moved
+    let holder = |deps: Deps, address: &str| -> HolderResponse {
+        from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::Holder {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let global_index = Decimal::from_ratio(100000u128, 1u128);
+    assert_eq!(
+        holder(deps.as_ref(), "addr0000"),
         HolderResponse {
             address: String::from("addr0000"),
-            balance: Uint128::from(11u128),
-            index,
-            pending_rewards: Decimal::from_str("0.999999999999999991").unwrap(),
+            balance: Uint128::from(10u128),
+            index: global_index,
+            pending_rewards: Decimal::zero(),
+        }
+    );
+    assert_eq!(
+        holder(deps.as_ref(), "addr0001"),
+        HolderResponse {
+            address: String::from("addr0001"),
+            balance: Uint128::zero(),
+            index: global_index,
+            pending_rewards: Decimal::from_ratio(1000000u128, 1u128),
         }
     );
+    let res = execute(deps.as_mut(), mock_env(), info, migrate("addr0001"));
+    assert_eq!(res.unwrap_err(), ContractError::NoRewards {});
+}
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state_response: StateResponse = from_binary(&res).unwrap();
+#[test]
+fn new_holders_start_at_the_global_index() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1000000u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    let token_info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info.clone(),
+        ExecuteMsg::IncreaseBalance {
+            address: String::from("addr0000"),
+            amount: Uint128::from(10u128),
+        },
+    )
+    .unwrap();
+
+    // global_index == 100000
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    // a mint to a brand-new address right after the bump
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info,
+        ExecuteMsg::IncreaseBalance {
+            address: String::from("newcomer"),
+            amount: Uint128::from(1000u128),
+        },
+    )
+    .unwrap();
+    let holder: HolderResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Holder {
+                address: String::from("newcomer"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
     assert_eq!(
-        state_response,
-        StateResponse {
-            global_index: index,
-            total_balance: Uint128::new(11u128),
-            prev_reward_balance: Uint128::new(1)
+        holder,
+        HolderResponse {
+            address: String::from("newcomer"),
+            balance: Uint128::from(1000u128),
+            index: Decimal::from_ratio(100000u128, 1u128),
+            pending_rewards: Decimal::zero(),
         }
     );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("newcomer", &[]),
+        ExecuteMsg::ClaimRewards { recipient: None },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::NoRewards {});
+
+    // a checkpoint of an unknown address starts it at the global index as well
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::SyncHolder {
+            address: String::from("escrow"),
+        },
+    )
+    .unwrap();
+    let synced: HolderResponse = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(synced.index, Decimal::from_ratio(100000u128, 1u128));
 }
 
 #[test]
-fn query_holders() {
+fn increase_balance_with_decimals() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: Uint128::new(100u128),
+        amount: Uint128::new(100000u128),
     }]);
 
     let init_msg = default_init();
@@ -629,141 +1346,81 @@ fn query_holders() {
 
     let msg = ExecuteMsg::IncreaseBalance {
         address: String::from("addr0000"),
-        amount: Uint128::from(100u128),
-    };
-
-    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
-    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0001"),
-        amount: Uint128::from(200u128),
+        amount: Uint128::from(11u128),
     };
 
-    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-    let msg = ExecuteMsg::IncreaseBalance {
-        address: String::from("addr0002"),
-        amount: Uint128::from(300u128),
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(_e) => {}
+        _ => panic!("DO NOT ENTER HERE"),
     };
 
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::Holders {
-            start_after: None,
-            limit: None,
+        QueryMsg::Holder {
+            address: String::from("addr0000"),
         },
     )
     .unwrap();
-    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
     assert_eq!(
-        holders_response,
-        HoldersResponse {
-            holders: vec![
-                HolderResponse {
-                    address: String::from("addr0000"),
-                    balance: Uint128::from(100u128),
-                    index: Decimal::zero(),
-                    pending_rewards: Decimal::zero(),
-                },
-                HolderResponse {
-                    address: String::from("addr0001"),
-                    balance: Uint128::from(200u128),
-                    index: Decimal::zero(),
-                    pending_rewards: Decimal::zero(),
-                },
-                HolderResponse {
-                    address: String::from("addr0002"),
-                    balance: Uint128::from(300u128),
-                    index: Decimal::zero(),
-                    pending_rewards: Decimal::zero(),
-                },
-            ],
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0000"),
+            balance: Uint128::from(11u128),
+            index: Decimal::zero(),
+            pending_rewards: Decimal::zero(),
         }
     );
 
-    // Set limit
-    let res = query(
-        deps.as_ref(),
-        mock_env(),
-        QueryMsg::Holders {
-            start_after: None,
-            limit: Some(1),
-        },
-    )
-    .unwrap();
-    let holders_response: HoldersResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        holders_response,
-        HoldersResponse {
-            holders: vec![HolderResponse {
-                address: String::from("addr0000"),
-                balance: Uint128::from(100u128),
-                index: Decimal::zero(),
-                pending_rewards: Decimal::zero(),
-            }],
-        }
-    );
+    // claimed_rewards = 100000 , total_balance = 11
+    // global_index == 9077.727272727272727272
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    // Set start_after
-    let res = query(
-        deps.as_ref(),
-        mock_env(),
-        QueryMsg::Holders {
-            start_after: Some(String::from("addr0000")),
-            limit: None,
-        },
-    )
-    .unwrap();
-    let holders_response: HoldersResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        holders_response,
-        HoldersResponse {
-            holders: vec![
-                HolderResponse {
-                    address: String::from("addr0001"),
-                    balance: Uint128::from(200u128),
-                    index: Decimal::zero(),
-                    pending_rewards: Decimal::zero(),
-                },
-                HolderResponse {
-                    address: String::from("addr0002"),
-                    balance: Uint128::from(300u128),
-                    index: Decimal::zero(),
-                    pending_rewards: Decimal::zero(),
-                }
-            ],
-        }
-    );
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(10u128),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    // Set start_after and limit
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::Holders {
-            start_after: Some(String::from("addr0000")),
-            limit: Some(1),
+        QueryMsg::Holder {
+            address: String::from("addr0000"),
         },
     )
     .unwrap();
-    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    let index = decimal_multiplication_in_256(
+        Decimal::from_ratio(Uint128::new(100000), Uint128::new(11)),
+        Decimal::one(),
+    );
+    let user_pend_reward = decimal_multiplication_in_256(
+        Decimal::from_str("11").unwrap(),
+        decimal_subtraction_in_256(holder_response.index, Decimal::zero()),
+    );
     assert_eq!(
-        holders_response,
-        HoldersResponse {
-            holders: vec![HolderResponse {
-                address: String::from("addr0001"),
-                balance: Uint128::from(200u128),
-                index: Decimal::zero(),
-                pending_rewards: Decimal::zero(),
-            }],
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0000"),
+            balance: Uint128::from(21u128),
+            index,
+            pending_rewards: user_pend_reward,
         }
     );
 }
 
 #[test]
-fn proper_prev_balance() {
+fn decrease_balance() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
         amount: Uint128::new(100u128),
@@ -774,92 +1431,50 @@ fn proper_prev_balance() {
 
     instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
 
-    let amount1 = Uint128::from(8899999999988889u128);
-    let amount2 = Uint128::from(14487875351811111u128);
-    let amount3 = Uint128::from(1100000000000000u128);
-
-    let rewards = Uint128::new(677101666827000000u128);
-
-    let all_balance = amount1 + amount2 + amount3;
-
-    let global_index = Decimal::from_ratio(rewards, all_balance);
-    store_state(
-        &mut deps.storage,
-        &State {
-            global_index,
-            total_balance: all_balance,
-            prev_reward_balance: rewards,
-        },
-    )
-    .unwrap();
-
-    let holder = Holder {
-        balance: amount1,
-        index: Decimal::from_str("0").unwrap(),
-        pending_rewards: Decimal::from_str("0").unwrap(),
+    let msg = ExecuteMsg::DecreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
     };
-    store_holder(
-        &mut deps.storage,
-        &deps
-            .api
-            .addr_canonicalize(&String::from("addr0000"))
-            .unwrap(),
-        &holder,
-    )
-    .unwrap();
 
-    let holder = Holder {
-        balance: amount2,
-        index: Decimal::from_str("0").unwrap(),
-        pending_rewards: Decimal::from_str("0").unwrap(),
+    // Failed unautorized
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+    match res {
+        Err(_e) => {}
+        _ => panic!("DO NOT ENTER HERE"),
     };
-    store_holder(
-        &mut deps.storage,
-        &deps
-            .api
-            .addr_canonicalize(&String::from("addr0001"))
-            .unwrap(),
-        &holder,
-    )
-    .unwrap();
 
-    let holder = Holder {
-        balance: amount3,
-        index: Decimal::from_str("0").unwrap(),
-        pending_rewards: Decimal::from_str("0").unwrap(),
+    // Failed underflow
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "Decrease amount cannot exceed user balance: 0")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
     };
-    store_holder(
-        &mut deps.storage,
-        &deps
-            .api
-            .addr_canonicalize(&String::from("addr0002"))
-            .unwrap(),
-        &holder,
-    )
-    .unwrap();
 
-    let msg = ExecuteMsg::ClaimRewards { recipient: None };
-    let info = mock_info("addr0000", &[]);
-    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // Increase balance first
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
 
-    let msg = ExecuteMsg::ClaimRewards { recipient: None };
-    let info = mock_info("addr0001", &[]);
-    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::ClaimRewards { recipient: None };
-    let info = mock_info("addr0002", &[]);
-    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    // claimed_rewards = 100, total_balance = 100
+    // global_index == 1
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
-    let state_response: StateResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        state_response,
-        StateResponse {
-            global_index,
-            total_balance: all_balance,
-            prev_reward_balance: Uint128::new(1)
-        }
-    );
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::DecreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(
         deps.as_ref(),
@@ -874,17 +1489,38 @@ fn proper_prev_balance() {
         holder_response,
         HolderResponse {
             address: String::from("addr0000"),
-            balance: amount1,
-            index: global_index,
-            pending_rewards: Decimal::from_str("0.212799238975421283").unwrap(),
+            balance: Uint128::zero(),
+            index: Decimal::one(),
+            pending_rewards: Decimal::from_str("100").unwrap(),
         }
     );
+}
+
+#[test]
+fn claim_rewards() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(
         deps.as_ref(),
         mock_env(),
         QueryMsg::Holder {
-            address: String::from("addr0001"),
+            address: String::from("addr0000"),
         },
     )
     .unwrap();
@@ -892,29 +1528,2303 @@ fn proper_prev_balance() {
     assert_eq!(
         holder_response,
         HolderResponse {
-            address: String::from("addr0001"),
-            balance: amount2,
-            index: global_index,
-            pending_rewards: Decimal::from_str("0.078595712259178717").unwrap(),
+            address: String::from("addr0000"),
+            balance: Uint128::from(100u128),
+            index: Decimal::zero(),
+            pending_rewards: Decimal::zero(),
         }
     );
 
-    let res = query(
-        deps.as_ref(),
+    // claimed_rewards = 100, total_balance = 100
+    // global_index == 1
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0000"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(99u128), // 1% tax
+            },]
+        }))]
+    );
+
+    // Set recipient
+    // claimed_rewards = 100, total_balance = 100
+    // global_index == 1
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards {
+        recipient: Some(String::from("addr0001")),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0001"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(99u128), // 1% tax
+            },]
+        }))]
+    );
+}
+
+#[test]
+fn claim_rewards_skip_the_tax_once_the_hub_is_tax_free() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the treasury still charges 1%, the flag of the hub leaves it out
+    deps.querier.with_tax_free();
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0000"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(100u128),
+            },]
+        }))]
+    );
+}
+
+#[test]
+fn claim_rewards_swapped_into_another_denom() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
         mock_env(),
-        QueryMsg::Holder {
-            address: String::from("addr0002"),
-        },
+        info,
+        ExecuteMsg::UpdateGlobalIndex {},
     )
     .unwrap();
-    let holder_response: HolderResponse = from_binary(&res).unwrap();
-    assert_eq!(
-        holder_response,
-        HolderResponse {
-            address: String::from("addr0002"),
+
+    let claim_as = |denom: &str, max_spread: Option<Decimal>| ExecuteMsg::ClaimRewardsAs {
+        denom: denom.to_string(),
+        max_spread,
+    };
+    let info = mock_info("addr0000", &[]);
+    if cfg!(feature = "no-market") {
+        let res = execute(deps.as_mut(), mock_env(), info, claim_as("ukrw", None));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::Std(StdError::generic_err(
+                "The rewards are swapped through the market module"
+            ))
+        );
+        return;
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        claim_as("uusd", None),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The rewards are paid in uusd already"
+        ))
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        claim_as("ukrw", None),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(create_swap_send_msg(
+            String::from("addr0000"),
+            Coin::new(100, "uusd"),
+            "ukrw".to_string(),
+        ))]
+    );
+    assert!(res.attributes.contains(&attr("simulated_return", "2200")));
+
+    // the next rewards are claimed with a tighter max_spread
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    let hub = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    // the market returns 2200 of the 2210 at the oracle rate
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        claim_as("ukrw", Some(Decimal::permille(1))),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The swap returns 2200ukrw, less than the 2207ukrw max_spread allows"
+        ))
+    );
+}
+
+#[test]
+fn accrued_rewards_at_a_past_index() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // global_index == 1, then 3
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(300u128),
+    }]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let accrued_at = |deps: Deps, global_index: u64| {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::AccruedRewardsAt {
+                address: String::from("addr0000"),
+                global_index: Decimal::from_ratio(global_index, 1u64),
+            },
+        )
+        .and_then(|res| from_binary::<AccruedRewardsResponse>(&res))
+        .map(|res| res.rewards)
+    };
+    assert_eq!(accrued_at(deps.as_ref(), 1).unwrap(), Uint128::new(100));
+    assert_eq!(accrued_at(deps.as_ref(), 3).unwrap(), Uint128::new(300));
+    assert_eq!(
+        accrued_at(deps.as_ref(), 4).unwrap_err(),
+        StdError::generic_err("The global index 4 is past the current index 3")
+    );
+
+    // a balance change settles the accruals, the past indexes are not known anymore
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        accrued_at(deps.as_ref(), 1).unwrap_err(),
+        StdError::generic_err("The rewards of addr0000 are settled up to the index 3, past 1")
+    );
+    assert_eq!(accrued_at(deps.as_ref(), 3).unwrap(), Uint128::new(300));
+}
+
+#[test]
+fn malformed_recipients_are_refused() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+
+    // an invalid address is an error, it used to panic
+    let msg = ExecuteMsg::ClaimRewards {
+        recipient: Some(String::from("x")),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    assert!(matches!(
+        res,
+        Err(ContractError::Std(StdError::GenericErr { .. }))
+    ));
+}
+
+#[test]
+fn claim_rewards_to_the_routed_recipient() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let route = |recipient: Option<&str>| ExecuteMsg::UpdateRewardRecipient {
+        recipient: recipient.map(String::from),
+    };
+    let recipient = |deps: Deps| -> String {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::RewardRecipient {
+                address: String::from("addr0000"),
+            },
+        )
+        .unwrap();
+        from_binary::<RewardRecipientResponse>(&res)
+            .unwrap()
+            .recipient
+    };
+    assert_eq!(recipient(deps.as_ref()), "addr0000");
+    let info = mock_info("addr0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        route(Some("vault")),
+    )
+    .unwrap();
+    assert_eq!(recipient(deps.as_ref()), "vault");
+
+    // a claim without a recipient pays the routed one, a named recipient still wins
+    let payout = |to_address: &str| {
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from(to_address),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(99u128), // 1% tax
+            }],
+        }))]
+    };
+    let hub_info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let claim = |recipient: Option<&str>| ExecuteMsg::ClaimRewards {
+        recipient: recipient.map(String::from),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), claim(None)).unwrap();
+    assert_eq!(res.messages, payout("vault"));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        claim(Some("addr0001")),
+    )
+    .unwrap();
+    assert_eq!(res.messages, payout("addr0001"));
+
+    // removing the route pays the holder again
+    execute(deps.as_mut(), mock_env(), info.clone(), route(None)).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, claim(None)).unwrap();
+    assert_eq!(res.messages, payout("addr0000"));
+}
+
+#[test]
+fn holders_redirect_or_renounce_their_accruals() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(200u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    for address in ["addr0000", "market0000"] {
+        let msg = ExecuteMsg::IncreaseBalance {
+            address: String::from(address),
+            amount: Uint128::from(100u128),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let market = mock_info("market0000", &[]);
+    let redirect = |recipient| ExecuteMsg::SetRewardRecipient { recipient };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        market.clone(),
+        redirect(Some(AccrualRecipient::Address {
+            address: String::from("market0000"),
+        })),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The rewards cannot be redirected to the holder itself"
+        ))
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        market.clone(),
+        redirect(Some(AccrualRecipient::Address {
+            address: String::from("treasury"),
+        })),
+    )
+    .unwrap();
+
+    let hub_info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let update_index = |deps: &mut OwnedDeps<_, _, _>| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            hub_info.clone(),
+            ExecuteMsg::UpdateGlobalIndex {},
+        )
+        .unwrap();
+    };
+    let sync = |deps: &mut OwnedDeps<_, _, _>, address: &str| -> HolderResponse {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::SyncHolder {
+                address: String::from(address),
+            },
+        )
+        .unwrap();
+        from_binary(&res.data.unwrap()).unwrap()
+    };
+    let accrued = |deps: Deps, address: &str| -> Uint128 {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::AccruedRewards {
+                address: String::from(address),
+            },
+        )
+        .unwrap();
+        from_binary::<AccruedRewardsResponse>(&res).unwrap().rewards
+    };
+
+    // the share of the market maker accrues to the treasury
+    update_index(&mut deps);
+    assert_eq!(accrued(deps.as_ref(), "market0000"), Uint128::zero());
+    sync(&mut deps, "market0000");
+    assert_eq!(accrued(deps.as_ref(), "treasury"), Uint128::new(100));
+    assert_eq!(accrued(deps.as_ref(), "market0000"), Uint128::zero());
+
+    // renounced, the share is distributed again by the next index update
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        market,
+        redirect(Some(AccrualRecipient::Pool {})),
+    )
+    .unwrap();
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(400u128),
+    }]);
+    update_index(&mut deps);
+    sync(&mut deps, "market0000");
+    assert_eq!(
+        read_state(&deps.storage).unwrap().prev_reward_balance,
+        Uint128::new(300)
+    );
+    update_index(&mut deps);
+    assert_eq!(accrued(deps.as_ref(), "addr0000"), Uint128::new(250));
+    assert_eq!(accrued(deps.as_ref(), "treasury"), Uint128::new(100));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AccrualRecipient {
+            address: String::from("market0000"),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<AccrualRecipientResponse>(&res).unwrap(),
+        AccrualRecipientResponse {
+            recipient: Some(AccrualRecipient::Pool {})
+        }
+    );
+}
+
+#[test]
+fn claim_rewards_in_a_cw20_token() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    let init_msg = InstantiateMsg {
+        hub_contract: String::from(MOCK_HUB_CONTRACT_ADDR),
+        reward_denom: String::from("uusd"),
+        reward_asset: Some(AssetInfo::Token {
+            contract_addr: String::from("stable"),
+        }),
+    };
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        init_msg.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "reward_denom must be the denom or the token address of reward_asset"
+        ))
+    );
+    let init_msg = InstantiateMsg {
+        reward_denom: String::from("stable"),
+        ..init_msg
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        init_msg,
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        ExecuteMsg::IncreaseBalance {
+            address: String::from("addr0000"),
+            amount: Uint128::from(100u128),
+        },
+    )
+    .unwrap();
+
+    // the market does not return the token, the native rewards wait for a route
+    deps.querier
+        .with_token_balance("stable", Uint128::new(100u128));
+    let hub_info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    // the route must end with the token
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::RegisterSwapRoute {
+            offer_denom: "uusd".to_string(),
+            hops: vec![
+                SwapHop {
+                    pair_contract: "usd_stable_pair".to_string(),
+                    ask_denom: "stable".to_string(),
+                    max_spread: None,
+                },
+                SwapHop {
+                    pair_contract: "stable_luna_pair".to_string(),
+                    ask_denom: "uluna".to_string(),
+                    max_spread: None,
+                },
+            ],
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err("The swap route must end with stable"))
+    );
+    deps.querier
+        .with_pair_rate("usd_stable_pair", Decimal::one());
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::RegisterSwapRoute {
+            offer_denom: "uusd".to_string(),
+            hops: vec![SwapHop {
+                pair_contract: "usd_stable_pair".to_string(),
+                ask_denom: "stable".to_string(),
+                max_spread: None,
+            }],
+        },
+    )
+    .unwrap();
+
+    // the native rewards are swapped to the token
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    let offered = Coin::new(99, "uusd"); // 1% tax
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "usd_stable_pair".to_string(),
+                msg: to_binary(&PairExecuteMsg::Swap {
+                    offer_asset: Asset::native("uusd", offered.amount),
+                    belief_price: Some(Decimal::one()),
+                    max_spread: None,
+                    to: None,
+                })
+                .unwrap(),
+                funds: vec![offered],
+            },
+            1,
+        )]
+    );
+
+    // global_index == 2, the rewards are tracked by the token balance
+    deps.querier
+        .with_token_balance("stable", Uint128::new(200u128));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::ClaimRewards { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("stable"),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("addr0000"),
+                amount: Uint128::new(200u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+}
+
+#[test]
+fn escrows_sync_and_attribute_rewards() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        ExecuteMsg::IncreaseBalance {
+            address: String::from("escrow"),
+            amount: Uint128::from(40u128),
+        },
+    )
+    .unwrap();
+
+    // global_index == 2.5
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let delta: IndexDeltaResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::IndexDelta {
+                address: String::from("escrow"),
+                since_index: Decimal::one(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        delta,
+        IndexDeltaResponse {
+            address: String::from("escrow"),
+            balance: Uint128::new(40),
+            since_index: Decimal::one(),
+            global_index: Decimal::from_ratio(5u128, 2u128),
+            rewards: Decimal::from_ratio(60u128, 1u128),
+        }
+    );
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::IndexDelta {
+            address: String::from("escrow"),
+            since_index: Decimal::from_ratio(3u128, 1u128),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("The global index is 2.5, it never was 3")
+    );
+
+    // anyone checkpoints the escrow, the rewards stay in the contract
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::SyncHolder {
+            address: String::from("escrow"),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    let synced: HolderResponse = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(
+        synced,
+        HolderResponse {
+            address: String::from("escrow"),
+            balance: Uint128::new(40),
+            index: Decimal::from_ratio(5u128, 2u128),
+            pending_rewards: Decimal::from_ratio(100u128, 1u128),
+        }
+    );
+    let accrued: AccruedRewardsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccruedRewards {
+                address: String::from("escrow"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(accrued.rewards, Uint128::new(100));
+}
+
+#[test]
+fn claim_rewards_into_anchor_earn() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(200u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    for address in ["addr0000", "addr0001"] {
+        let msg = ExecuteMsg::IncreaseBalance {
+            address: address.to_string(),
+            amount: Uint128::from(100u128),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+
+    // only the owner of the hub configures the market
+    let earn = EarnConfig {
+        market_contract: "market".to_string(),
+        aterra_contract: "aterra".to_string(),
+        deposit_by_default: true,
+    };
+    let msg = ExecuteMsg::UpdateEarnConfig {
+        earn: Some(earn.clone()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg).unwrap();
+    let res: EarnConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::EarnConfig {}).unwrap()).unwrap();
+    assert_eq!(res.earn, Some(earn));
+
+    // addr0001 opts out of the default
+    let msg = ExecuteMsg::UpdateRewardDeposit {
+        deposit: Some(false),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg).unwrap();
+    for (address, deposit) in [("addr0000", true), ("addr0001", false)] {
+        let msg = QueryMsg::RewardDeposit {
+            address: address.to_string(),
+        };
+        let res: RewardDepositResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.deposit, deposit);
+    }
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: String::from("addr0001"),
+            amount: vec![Coin::new(99, "uusd")],
+        })]
+    );
+
+    // the rewards of addr0000 are deposited, then the minted aUST is handed over
+    let msg = ExecuteMsg::ClaimRewards {
+        recipient: Some(String::from("addr0002")),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Execute {
+                contract_addr: "market".to_string(),
+                msg: to_binary(&MarketExecuteMsg::DepositStable {}).unwrap(),
+                funds: vec![Coin::new(99, "uusd")],
+            },
+            0
+        )]
+    );
+
+    // the market cannot reenter the claims before the deposit replied
+    let locked = ContractError::Locked {};
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("market", &[]), msg);
+    assert_eq!(res.unwrap_err(), locked);
+    let msg = ExecuteMsg::MigrateHolder {
+        new_address: String::from("market"),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    assert_eq!(res.unwrap_err(), locked);
+
+    deps.querier.with_aterra_balance(Uint128::new(80));
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 0,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "aterra".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0002".to_string(),
+                amount: Uint128::new(80),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    assert_eq!(res.unwrap_err(), ContractError::NoRewards {});
+
+    // removing the market stops the deposits
+    let msg = ExecuteMsg::UpdateEarnConfig { earn: None };
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg).unwrap();
+    let msg = QueryMsg::RewardDeposit {
+        address: "addr0000".to_string(),
+    };
+    let res: RewardDepositResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(!res.deposit);
+}
+
+#[test]
+fn claim_rewards_follow_the_hub_pause() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, default_init()).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let mut breaker = CircuitBreaker {
+        max_exchange_rate_change: None,
+        guardian: None,
+        paused: true,
+        freeze_transfers: false,
+    };
+    deps.querier.with_circuit_breaker(breaker.clone());
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Paused {});
+
+    // balance updates follow the token, which decides whether transfers are frozen
+    let decrease = ExecuteMsg::DecreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(10u128),
+    };
+    let token_info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), token_info, decrease).unwrap();
+
+    breaker.paused = false;
+    deps.querier.with_circuit_breaker(breaker);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn claim_rewards_with_decimals() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(99999u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(11u128),
+    };
+
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holder {
+            address: String::from("addr0000"),
+        },
+    )
+    .unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0000"),
+            balance: Uint128::from(11u128),
+            index: Decimal::zero(),
+            pending_rewards: Decimal::zero(),
+        }
+    );
+
+    // claimed_rewards = 1000000, total_balance = 11
+    // global_index ==
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0000"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(99007u128), // 1% tax
+            },]
+        }))]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holder {
+            address: String::from("addr0000"),
+        },
+    )
+    .unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    let index = decimal_multiplication_in_256(
+        Decimal::from_ratio(Uint128::new(99999), Uint128::new(11)),
+        Decimal::one(),
+    );
+    assert_eq!(
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0000"),
+            balance: Uint128::from(11u128),
+            index,
+            pending_rewards: Decimal::from_str("0.999999999999999991").unwrap(),
+        }
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state_response: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        state_response,
+        StateResponse {
+            global_index: index,
+            total_balance: Uint128::new(11u128),
+            prev_reward_balance: Uint128::new(1)
+        }
+    );
+}
+
+#[test]
+fn query_holders() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0001"),
+        amount: Uint128::from(200u128),
+    };
+
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0002"),
+        amount: Uint128::from(300u128),
+    };
+
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holders {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holders_response,
+        HoldersResponse {
+            holders: vec![
+                HolderResponse {
+                    address: String::from("addr0000"),
+                    balance: Uint128::from(100u128),
+                    index: Decimal::zero(),
+                    pending_rewards: Decimal::zero(),
+                },
+                HolderResponse {
+                    address: String::from("addr0001"),
+                    balance: Uint128::from(200u128),
+                    index: Decimal::zero(),
+                    pending_rewards: Decimal::zero(),
+                },
+                HolderResponse {
+                    address: String::from("addr0002"),
+                    balance: Uint128::from(300u128),
+                    index: Decimal::zero(),
+                    pending_rewards: Decimal::zero(),
+                },
+            ],
+        }
+    );
+
+    // Set limit
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holders {
+            start_after: None,
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holders_response,
+        HoldersResponse {
+            holders: vec![HolderResponse {
+                address: String::from("addr0000"),
+                balance: Uint128::from(100u128),
+                index: Decimal::zero(),
+                pending_rewards: Decimal::zero(),
+            }],
+        }
+    );
+
+    // Set start_after
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holders {
+            start_after: Some(String::from("addr0000")),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holders_response,
+        HoldersResponse {
+            holders: vec![
+                HolderResponse {
+                    address: String::from("addr0001"),
+                    balance: Uint128::from(200u128),
+                    index: Decimal::zero(),
+                    pending_rewards: Decimal::zero(),
+                },
+                HolderResponse {
+                    address: String::from("addr0002"),
+                    balance: Uint128::from(300u128),
+                    index: Decimal::zero(),
+                    pending_rewards: Decimal::zero(),
+                }
+            ],
+        }
+    );
+
+    // Set start_after and limit
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holders {
+            start_after: Some(String::from("addr0000")),
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let holders_response: HoldersResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holders_response,
+        HoldersResponse {
+            holders: vec![HolderResponse {
+                address: String::from("addr0001"),
+                balance: Uint128::from(200u128),
+                index: Decimal::zero(),
+                pending_rewards: Decimal::zero(),
+            }],
+        }
+    );
+}
+
+#[test]
+fn proper_prev_balance() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let amount1 = Uint128::from(8899999999988889u128);
+    let amount2 = Uint128::from(14487875351811111u128);
+    let amount3 = Uint128::from(1100000000000000u128);
+
+    let rewards = Uint128::new(677101666827000000u128);
+
+    let all_balance = amount1 + amount2 + amount3;
+
+    // the balance holds the rewards the state distributes
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: rewards,
+    }]);
+
+    let global_index = Decimal::from_ratio(rewards, all_balance);
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index,
+            total_balance: all_balance,
+            prev_reward_balance: rewards,
+        },
+    )
+    .unwrap();
+
+    let holder = Holder {
+        balance: amount1,
+        index: Decimal::from_str("0").unwrap(),
+        pending_rewards: Decimal::from_str("0").unwrap(),
+    };
+    store_holder(
+        &mut deps.storage,
+        &deps
+            .api
+            .addr_canonicalize(&String::from("addr0000"))
+            .unwrap(),
+        &holder,
+    )
+    .unwrap();
+
+    let holder = Holder {
+        balance: amount2,
+        index: Decimal::from_str("0").unwrap(),
+        pending_rewards: Decimal::from_str("0").unwrap(),
+    };
+    store_holder(
+        &mut deps.storage,
+        &deps
+            .api
+            .addr_canonicalize(&String::from("addr0001"))
+            .unwrap(),
+        &holder,
+    )
+    .unwrap();
+
+    let holder = Holder {
+        balance: amount3,
+        index: Decimal::from_str("0").unwrap(),
+        pending_rewards: Decimal::from_str("0").unwrap(),
+    };
+    store_holder(
+        &mut deps.storage,
+        &deps
+            .api
+            .addr_canonicalize(&String::from("addr0002"))
+            .unwrap(),
+        &holder,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0001", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    let info = mock_info("addr0002", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state_response: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        state_response,
+        StateResponse {
+            global_index,
+            total_balance: all_balance,
+            prev_reward_balance: Uint128::new(1)
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holder {
+            address: String::from("addr0000"),
+        },
+    )
+    .unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0000"),
+            balance: amount1,
+            index: global_index,
+            pending_rewards: Decimal::from_str("0.212799238975421283").unwrap(),
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holder {
+            address: String::from("addr0001"),
+        },
+    )
+    .unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0001"),
+            balance: amount2,
+            index: global_index,
+            pending_rewards: Decimal::from_str("0.078595712259178717").unwrap(),
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Holder {
+            address: String::from("addr0002"),
+        },
+    )
+    .unwrap();
+    let holder_response: HolderResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        holder_response,
+        HolderResponse {
+            address: String::from("addr0002"),
             balance: amount3,
             index: global_index,
             pending_rewards: Decimal::from_str("0.701700000000000000").unwrap(),
         }
     );
 }
+
+#[test]
+fn export_import_state() {
+    let mut deps = mock_dependencies(&[]);
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), default_init()).unwrap();
+    for i in 0..5u128 {
+        let msg = ExecuteMsg::IncreaseBalance {
+            address: format!("addr000{}", i),
+            amount: Uint128::from(100u128 + i),
+        };
+        let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    let mut restored = mock_dependencies(&[]);
+    instantiate(restored.as_mut(), mock_env(), info, default_init()).unwrap();
+
+    let mut start_after = None;
+    loop {
+        let export = QueryMsg::ExportState {
+            start_after,
+            limit: Some(2),
+        };
+        let page: ExportStateResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), export).unwrap()).unwrap();
+        if page.entries.is_empty() {
+            break;
+        }
+        start_after = page.entries.last().map(|entry| entry.key.clone());
+
+        // only the owner of the hub can import
+        let import = ExecuteMsg::ImportState {
+            entries: page.entries,
+        };
+        let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+        let res = execute(restored.as_mut(), mock_env(), info, import.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let info = mock_info("owner1", &[]);
+        let res = execute(restored.as_mut(), mock_env(), info, import).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "import_state"));
+    }
+
+    // the hub trusted by the contract cannot be replaced by an import
+    let config = Config {
+        hub_contract: restored.api.addr_canonicalize("other_hub").unwrap(),
+        ..read_config(&restored.storage).unwrap()
+    };
+    let import = ExecuteMsg::ImportState {
+        entries: vec![StateEntry {
+            key: Binary::from(CONFIG_KEY.as_bytes()),
+            value: to_binary(&config).unwrap(),
+        }],
+    };
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        import,
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The imported state belongs to another hub"
+        ))
+    );
+
+    // the contract cannot be used until the import is finished
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    );
+    assert_eq!(res.unwrap_err(), ContractError::ImportInProgress {});
+    execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::FinishImport {},
+    )
+    .unwrap();
+    let res = execute(
+        restored.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        ExecuteMsg::ImportState { entries: vec![] },
+    );
+    assert_eq!(res.unwrap_err(), ContractError::ImportClosed {});
+
+    let holders = QueryMsg::Holders {
+        start_after: None,
+        limit: None,
+    };
+    let res: HoldersResponse =
+        from_binary(&query(restored.as_ref(), mock_env(), holders.clone()).unwrap()).unwrap();
+    assert_eq!(res.holders.len(), 5);
+    assert_eq!(
+        query(deps.as_ref(), mock_env(), holders).unwrap(),
+        query(
+            restored.as_ref(),
+            mock_env(),
+            QueryMsg::Holders {
+                start_after: None,
+                limit: None,
+            }
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap(),
+        query(restored.as_ref(), mock_env(), QueryMsg::State {}).unwrap()
+    );
+}
+
+#[test]
+fn swap_history_reports_each_run() {
+    let mut deps = mock_dependencies(&[
+        Coin::new(100, DEFAULT_REWARD_DENOM),
+        Coin::new(1000, "ukrw"),
+        Coin::new(500, "umnt"),
+    ]);
+    deps.querier
+        .with_pair_rate("krw_usd_pair", Decimal::from_str("0.1").unwrap());
+    deps.querier
+        .with_pair_rate("mnt_usd_pair", Decimal::from_str("0.1").unwrap());
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    for (offer_denom, pair_contract) in [("ukrw", "krw_usd_pair"), ("umnt", "mnt_usd_pair")] {
+        let msg = ExecuteMsg::RegisterSwapRoute {
+            offer_denom: offer_denom.to_string(),
+            hops: vec![SwapHop {
+                pair_contract: pair_contract.to_string(),
+                ask_denom: DEFAULT_REWARD_DENOM.to_string(),
+                max_spread: None,
+            }],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg).unwrap();
+    }
+
+    let hub = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub.clone(),
+        ExecuteMsg::SwapToRewardDenom {},
+    )
+    .unwrap();
+    // the umnt swap fails, the ukrw one returns 99
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 2,
+            result: ContractResult::Err("insufficient liquidity".to_string()),
+        },
+    )
+    .unwrap();
+    deps.querier.with_balance(&[
+        Coin::new(199, DEFAULT_REWARD_DENOM),
+        Coin::new(10, "ukrw"),
+        Coin::new(500, "umnt"),
+    ]);
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::new(100),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let res: SwapHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::SwapHistory {}).unwrap()).unwrap();
+    assert_eq!(
+        res.runs,
+        vec![SwapRun {
+            run_id: 0,
+            time: mock_env().block.time.seconds(),
+            swaps: vec![
+                SwapReport {
+                    offer_denom: "ukrw".to_string(),
+                    offer_amount: Uint128::new(1000),
+                    remaining: Some(Uint128::new(10)),
+                    error: None,
+                },
+                SwapReport {
+                    offer_denom: "umnt".to_string(),
+                    offer_amount: Uint128::new(500),
+                    remaining: Some(Uint128::new(500)),
+                    error: Some("insufficient liquidity".to_string()),
+                },
+            ],
+            reward_balance: Uint128::new(100),
+            returned: Some(Uint128::new(99)),
+        }]
+    );
+}
+
+#[test]
+fn keeper_bounties_are_paid_from_the_reserve() {
+    let mut deps = mock_dependencies(&[Coin::new(100, DEFAULT_REWARD_DENOM)]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::new(100),
+            prev_reward_balance: Uint128::new(60),
+        },
+    )
+    .unwrap();
+
+    // only the hub funds the reserve, out of the 40 not distributed yet
+    let fund = ExecuteMsg::FundKeeperReserve {
+        target: Uint128::new(50),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        fund.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let hub = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), hub.clone(), fund).unwrap();
+    let res: KeeperReserveResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperReserve {}).unwrap())
+            .unwrap();
+    assert_eq!(res.reserve, Uint128::new(40));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub.clone(),
+        ExecuteMsg::PayKeeperBounty {
+            recipient: "keeper".to_string(),
+            amount: Uint128::new(10),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "keeper".to_string(),
+            amount: vec![Coin::new(9, DEFAULT_REWARD_DENOM)],
+        })]
+    );
+
+    // the reserve is not distributed to the holders
+    deps.querier
+        .with_balance(&[Coin::new(110, DEFAULT_REWARD_DENOM)]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub,
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(res.attributes[2], attr("claimed_rewards", "20"));
+}
+
+#[test]
+fn keeper_reserve_and_bounties_are_capped() {
+    let mut deps = mock_dependencies(&[Coin::new(5_000_000_000, DEFAULT_REWARD_DENOM)]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::zero(),
+            total_balance: Uint128::new(100),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+    .unwrap();
+
+    let hub = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub.clone(),
+        ExecuteMsg::FundKeeperReserve {
+            target: Uint128::new(5_000_000_000),
+        },
+    )
+    .unwrap();
+    let res: KeeperReserveResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperReserve {}).unwrap())
+            .unwrap();
+    assert_eq!(res.reserve, Uint128::new(MAX_KEEPER_RESERVE));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub,
+        ExecuteMsg::PayKeeperBounty {
+            recipient: "keeper".to_string(),
+            amount: Uint128::new(50_000_000),
+        },
+    )
+    .unwrap();
+    let res: KeeperReserveResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperReserve {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res.reserve,
+        Uint128::new(MAX_KEEPER_RESERVE - MAX_KEEPER_BOUNTY)
+    );
+}
+
+#[test]
+fn reward_denom_migration_rebases_the_accruals() {
+    let mut deps = mock_dependencies(&[Coin::new(1000, DEFAULT_REWARD_DENOM)]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    store_state(
+        &mut deps.storage,
+        &State {
+            global_index: Decimal::from_ratio(9u128, 1u128),
+            total_balance: Uint128::new(100),
+            prev_reward_balance: Uint128::new(900),
+        },
+    )
+    .unwrap();
+    let holder = deps.api.addr_canonicalize("addr0000").unwrap();
+    store_holder(
+        &mut deps.storage,
+        &holder,
+        &Holder {
+            balance: Uint128::new(100),
+            index: Decimal::zero(),
+            pending_rewards: Decimal::zero(),
+        },
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::MigrateRewardDenom {
+        new_denom: "uluna".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), msg);
+    if cfg!(feature = "no-market") {
+        assert!(res.is_err());
+        return;
+    }
+    let res = res.unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(create_swap_msg(
+                Coin::new(1000, DEFAULT_REWARD_DENOM),
+                "uluna".to_string()
+            )),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                msg: to_binary(&ExecuteMsg::CompleteRewardDenomMigration {}).unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+
+    // the swap returned two uluna for each uusd
+    deps.querier.with_balance(&[Coin::new(2000, "uluna")]);
+    let complete = ExecuteMsg::CompleteRewardDenomMigration {};
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        complete.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        complete,
+    )
+    .unwrap();
+
+    let state = read_state(&deps.storage).unwrap();
+    assert_eq!(state.prev_reward_balance, Uint128::new(1800));
+    assert_eq!(state.global_index, Decimal::from_ratio(18u128, 1u128));
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        config.reward_asset,
+        AssetInfo::NativeToken {
+            denom: "uluna".to_string()
+        }
+    );
+
+    // the holder loses nothing, the accruals are worth the same in uluna, which is not taxed
+    let res: AccruedRewardsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccruedRewards {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.rewards, Uint128::new(1800));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::ClaimRewards { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin::new(1800, "uluna")],
+        })]
+    );
+
+    let res: DenomMigrationsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DenomMigrations {}).unwrap())
+            .unwrap();
+    assert_eq!(res.migrations.len(), 1);
+    assert_eq!(res.migrations[0].rate, Decimal::from_ratio(2u128, 1u128));
+    assert_eq!(
+        res.migrations[0].global_index,
+        Decimal::from_ratio(9u128, 1u128)
+    );
+}
+
+#[test]
+fn balances_follow_the_token_registered_by_the_hub() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    let increase = ExecuteMsg::IncreaseBalance {
+        address: "addr0000".to_string(),
+        amount: Uint128::new(100),
+    };
+
+    // only the hub registers its token
+    let register = ExecuteMsg::RegisterTokenContract {
+        token_contract: "new_token".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        register.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        register,
+    )
+    .unwrap();
+    let res: RegisteredContractsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::RegisteredContracts {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        RegisteredContractsResponse {
+            hub_contract: MOCK_HUB_CONTRACT_ADDR.to_string(),
+            token_contract: Some("new_token".to_string()),
+        }
+    );
+
+    // the token of the hub config is not trusted anymore
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        increase.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("new_token", &[]),
+        increase,
+    )
+    .unwrap();
+}
+
+#[test]
+fn spoofed_token_contracts_are_refused() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    let increase = ExecuteMsg::IncreaseBalance {
+        address: "addr0000".to_string(),
+        amount: Uint128::new(100),
+    };
+    let decrease = ExecuteMsg::DecreaseBalance {
+        address: "addr0000".to_string(),
+        amount: Uint128::new(100),
+    };
+    let spoofed = mock_info("spoofed_token", &[]);
+
+    let res = execute(deps.as_mut(), mock_env(), spoofed.clone(), increase.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    // the first balance update registers the token of the hub config
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+        increase.clone(),
+    )
+    .unwrap();
+    let res: RegisteredContractsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::RegisteredContracts {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res.token_contract,
+        Some(MOCK_TOKEN_CONTRACT_ADDR.to_string())
+    );
+
+    // another token cannot be registered over it, the same one can
+    let register = |token_contract: &str| ExecuteMsg::RegisterTokenContract {
+        token_contract: token_contract.to_string(),
+    };
+    let hub = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub.clone(),
+        register("spoofed_token"),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(format!(
+            "The token contract {} is already registered",
+            MOCK_TOKEN_CONTRACT_ADDR
+        )))
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        hub,
+        register(MOCK_TOKEN_CONTRACT_ADDR),
+    )
+    .unwrap();
+
+    // the spoofed token moves no balance
+    for msg in [increase, decrease] {
+        let res = execute(deps.as_mut(), mock_env(), spoofed.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    }
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_balance, Uint128::new(100));
+}
+
+#[test]
+fn underfunded_claims_are_paid_pro_rata() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(200u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    for address in ["addr0000", "addr0001"] {
+        let msg = ExecuteMsg::IncreaseBalance {
+            address: String::from(address),
+            amount: Uint128::from(100u128),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    // e.g. a failed swap leaves half of the rewards owed
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(50u128),
+    }]);
+    let info = mock_info("addr0000", &[]);
+    let claim = ExecuteMsg::ClaimRewards { recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), claim.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The reward balance is 50uusd, it does not pay the 100uusd claimed"
+        ))
+    );
+
+    // only the owner of the hub enables the pro rata claims
+    let enable = ExecuteMsg::UpdateProRataClaims { enabled: true };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), enable.clone());
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner1", &[]), enable).unwrap();
+
+    let solvency = |deps: Deps| -> SolvencyResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::Solvency {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        solvency(deps.as_ref()),
+        SolvencyResponse {
+            reward_balance: Uint128::new(50),
+            owed: Uint128::new(200),
+            pro_rata_claims: true,
+        }
+    );
+
+    // a quarter of the balance is owed to the holder, the rest stays pending
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), claim.clone()).unwrap();
+    assert!(res.attributes.contains(&attr("rewards", "25")));
+    assert!(res.attributes.contains(&attr("shortfall", "75")));
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(25u128),
+    }]);
+    assert_eq!(solvency(deps.as_ref()).owed, Uint128::new(175));
+
+    // the shortfall is paid once the balance is back
+    deps.querier.with_balance(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(175u128),
+    }]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, claim).unwrap();
+    assert!(res.attributes.contains(&attr("rewards", "75")));
+    assert!(res.attributes.contains(&attr("shortfall", "0")));
+    assert_eq!(solvency(deps.as_ref()).owed, Uint128::new(100));
+}
+
+#[test]
+fn precision_report_measures_the_rounding_residue() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        default_init(),
+    )
+    .unwrap();
+    for address in ["addr0000", "addr0001", "addr0002"] {
+        let msg = ExecuteMsg::IncreaseBalance {
+            address: String::from(address),
+            amount: Uint128::from(1u128),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_HUB_CONTRACT_ADDR, &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    // 100 split over three holders truncates the last decimal of each share
+    let report = |deps: Deps| -> PrecisionReportResponse {
+        from_binary(&query(deps, mock_env(), QueryMsg::PrecisionReport {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        report(deps.as_ref()),
+        PrecisionReportResponse {
+            holders: 3,
+            unclaimed: Uint128::new(100),
+            claimable: Decimal::from_str("99.999999999999999999").unwrap(),
+            residue: Decimal::from_str("0.000000000000000001").unwrap(),
+            overclaimed: Decimal::zero(),
+        }
+    );
+
+    // a claim keeps its decimals pending, the residue does not grow
+    let msg = ExecuteMsg::ClaimRewards { recipient: None };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    let res = report(deps.as_ref());
+    assert_eq!(res.unclaimed, Uint128::new(67));
+    assert_eq!(
+        res.residue,
+        Decimal::from_str("0.000000000000000001").unwrap()
+    );
+}
+
+#[test]
+fn hubs_claim_the_rewards_of_unbonding_holders() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+
+    let init_msg = default_init();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+    let msg = ExecuteMsg::IncreaseBalance {
+        address: String::from("addr0000"),
+        amount: Uint128::from(100u128),
+    };
+    let info = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // nothing is accrued yet, the claim goes through without paying
+    let claim = ExecuteMsg::ClaimRewardsFor {
+        address: String::from("addr0000"),
+    };
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), claim.clone()).unwrap();
+    assert!(res.messages.is_empty());
+
+    let msg = ExecuteMsg::UpdateGlobalIndex {};
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // only the hubs claim for a holder
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        claim.clone(),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+    let res = execute(deps.as_mut(), mock_env(), info, claim).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0000"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(99u128), // 1% tax
+            },]
+        }))]
+    );
+}
+
+#[test]
+fn holder_tiers_follow_the_balances() {
+    let mut deps = mock_dependencies(&[]);
+    let init_msg = default_init();
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        init_msg,
+    )
+    .unwrap();
+
+    let increase = |address: &str, amount: u128| ExecuteMsg::IncreaseBalance {
+        address: address.to_string(),
+        amount: Uint128::new(amount),
+    };
+    let token = mock_info(MOCK_TOKEN_CONTRACT_ADDR, &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        increase("addr0000", 50),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        increase("addr0001", 500),
+    )
+    .unwrap();
+
+    // only the owner of the hub sets increasing tiers
+    let update = |min_balances: Vec<u128>| ExecuteMsg::UpdateHolderTiers {
+        min_balances: Some(min_balances.into_iter().map(Uint128::new).collect()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        update(vec![100, 1000]),
+    );
+    assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+    let owner = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        update(vec![1000, 100]),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        ContractError::Std(StdError::generic_err(
+            "The min balances of the tiers must be increasing"
+        ))
+    );
+
+    // the holders stored so far are counted
+    execute(deps.as_mut(), mock_env(), owner, update(vec![1, 100, 1000])).unwrap();
+    let tier = |min_balance: u128, holders: u64, balance: u128| HolderTier {
+        min_balance: Uint128::new(min_balance),
+        holders,
+        balance: Uint128::new(balance),
+    };
+    let distribution = |deps: &OwnedDeps<_, _, _>| -> Vec<HolderTier> {
+        let res: HolderDistributionResponse = from_binary(
+            &query(deps.as_ref(), mock_env(), QueryMsg::HolderDistribution {}).unwrap(),
+        )
+        .unwrap();
+        res.tiers
+    };
+    assert_eq!(
+        distribution(&deps),
+        vec![tier(1, 1, 50), tier(100, 1, 500), tier(1000, 0, 0)]
+    );
+
+    // the balance updates move the holders between the tiers
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        increase("addr0001", 600),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token.clone(),
+        increase("addr0002", 100),
+    )
+    .unwrap();
+    let decrease = ExecuteMsg::DecreaseBalance {
+        address: "addr0000".to_string(),
+        amount: Uint128::new(50),
+    };
+    execute(deps.as_mut(), mock_env(), token, decrease).unwrap();
+    assert_eq!(
+        distribution(&deps),
+        vec![tier(1, 0, 0), tier(100, 1, 100), tier(1000, 1, 1100)]
+    );
+}
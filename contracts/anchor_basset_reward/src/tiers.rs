@@ -0,0 +1,61 @@
+use crate::querier::assert_hub_owner;
+use crate::state::{for_each_holder, read_config, tier_of, HOLDER_TIERS};
+
+use basset::contract_error::ContractError;
+use basset::reward::{HolderDistributionResponse, HolderTier};
+
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Set the tiers the holders are counted in, every holder is counted again.
+/// Only the owner of the hub is allowed to execute
+pub fn execute_update_holder_tiers(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    min_balances: Option<Vec<Uint128>>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    let min_balances = match min_balances {
+        Some(min_balances) => min_balances,
+        None => {
+            HOLDER_TIERS.remove(deps.storage);
+            return Ok(Response::new().add_attribute("action", "remove_holder_tiers"));
+        }
+    };
+    if min_balances.is_empty() || min_balances.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(
+            StdError::generic_err("The min balances of the tiers must be increasing").into(),
+        );
+    }
+
+    let mut tiers: Vec<HolderTier> = min_balances
+        .into_iter()
+        .map(|min_balance| HolderTier {
+            min_balance,
+            holders: 0,
+            balance: Uint128::zero(),
+        })
+        .collect();
+    for_each_holder(deps.storage, |holder| {
+        if let Some(tier) = tier_of(&mut tiers, holder.balance) {
+            tier.holders += 1;
+            tier.balance += holder.balance;
+        }
+        Ok(())
+    })?;
+    HOLDER_TIERS.save(deps.storage, &tiers)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_holder_tiers"),
+        attr("tiers", tiers.len().to_string()),
+    ]))
+}
+
+pub fn query_holder_distribution(deps: Deps) -> StdResult<HolderDistributionResponse> {
+    Ok(HolderDistributionResponse {
+        tiers: HOLDER_TIERS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
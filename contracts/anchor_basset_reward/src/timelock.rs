@@ -0,0 +1,88 @@
+use crate::earn::set_earn_config;
+use crate::fee::set_community_fee;
+use crate::hubs::add_hub;
+use crate::querier::assert_hub_owner;
+use crate::state::{read_config, TIMELOCK};
+
+use basset::contract_error::ContractError;
+use basset::reward::{PendingChangesResponse, TimelockedChange};
+
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Queue a sensitive change, it can be applied once the timelock period has passed.
+/// Only the owner of the hub is allowed to execute
+pub fn execute_propose_pending_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    change: TimelockedChange,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    let pending = TIMELOCK.propose(deps.storage, env.block.time.seconds(), change)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "propose_pending_change"),
+        attr("id", pending.id.to_string()),
+        attr("effective_at", pending.effective_at.to_string()),
+    ]))
+}
+
+/// Apply a queued change whose timelock period has passed.
+/// Only the owner of the hub is allowed to execute
+pub fn execute_apply_pending_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    let change = TIMELOCK.take_ready(deps.storage, env.block.time.seconds(), id)?;
+    let res = match change {
+        TimelockedChange::AddHub { hub } => add_hub(deps, hub)?,
+        TimelockedChange::UpdateEarnConfig { earn } => set_earn_config(deps, earn)?,
+        TimelockedChange::UpdateCommunityFee { fee } => set_community_fee(deps, fee)?,
+        TimelockedChange::UpdateTimelockPeriod { timelock_period } => {
+            TIMELOCK.set_delay(deps.storage, timelock_period)?;
+            Response::new().add_attributes(vec![
+                attr("action", "update_timelock_period"),
+                attr("timelock_period", timelock_period.to_string()),
+            ])
+        }
+    };
+
+    Ok(res.add_attribute("pending_change_id", id.to_string()))
+}
+
+/// Drop a queued change. Only the owner of the hub is allowed to execute
+pub fn execute_cancel_pending_change(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub_owner(deps.as_ref(), &config.hub_contract, &info.sender)?;
+
+    TIMELOCK.cancel(deps.storage, id)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "cancel_pending_change"),
+        attr("id", id.to_string()),
+    ]))
+}
+
+pub fn query_pending_changes(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingChangesResponse> {
+    Ok(PendingChangesResponse {
+        timelock_period: TIMELOCK.delay(deps.storage)?,
+        changes: TIMELOCK.read_pending(deps.storage, start_after, limit)?,
+    })
+}
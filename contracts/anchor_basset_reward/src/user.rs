@@ -1,111 +1,400 @@
-use crate::querier::query_token_contract;
+use crate::earn::{create_deposit_msg, read_reward_deposit};
+use crate::hubs::assert_hub;
+use crate::payout::{assert_native_rewards, claim_payout, reward_payout_msg};
+use crate::querier::{assert_hub_not_paused, query_tax_free, query_token_contract};
 use crate::state::{
-    read_config, read_holder, read_holders, read_state, store_holder, store_state, Config, Holder,
-    State,
+    for_each_holder, index_holders, read_config, read_holder, read_holder_or_init, read_holders,
+    read_state, read_top_holders, store_holder, store_state, AccrualRedirect, Config, Holder,
+    State, ACCRUAL_REDIRECTS, EXECUTION_LOCK, HOLDERS_INDEX_CURSOR, REWARD_DEPOSITS,
+    REWARD_RECIPIENTS, TOKEN_CONTRACT,
+};
+use basset::reward::{
+    AccrualRecipient, AccrualRecipientResponse, AccruedRewardsResponse, HolderResponse,
+    HoldersResponse, IndexDeltaResponse, PrecisionReportResponse, RegisteredContractsResponse,
+    RewardRecipientResponse, TopHoldersResponse,
 };
-use basset::reward::{AccruedRewardsResponse, HolderResponse, HoldersResponse};
 
 use cosmwasm_std::{
-    attr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    attr, to_binary, Addr, CanonicalAddr, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, Storage, Uint128,
 };
 
-use crate::math::{
+use basset::contract_error::ContractError;
+use basset::math::{
     decimal_multiplication_in_256, decimal_subtraction_in_256, decimal_summation_in_256,
 };
-use basset::deduct_tax;
+use basset::snapshot::close_import;
+use basset::validation::validate_native_denom;
+use basset::{deduct_tax, treasury};
 use std::str::FromStr;
-use terra_cosmwasm::TerraMsgWrapper;
+use terra_cosmwasm::{create_swap_send_msg, TerraMsgWrapper, TerraQuerier};
+
+/// The max_spread of a claim swapped into another denom without one, in percent
+const DEFAULT_CLAIM_MAX_SPREAD: u64 = 1;
 
 pub fn execute_claim_rewards(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     recipient: Option<String>,
-) -> StdResult<Response<TerraMsgWrapper>> {
-    let holder_addr = info.sender;
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    claim_rewards(deps, env, info.sender, recipient)
+}
+
+/// Claim the rewards of address for one of the hubs, a holder without rewards claims nothing
+/// so that the unbond claiming them goes through
+pub fn execute_claim_rewards_for(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    assert_hub(deps.as_ref(), &config, &info.sender)?;
+    let holder_addr = deps.api.addr_validate(&address)?;
+
+    match claim_rewards(deps.branch(), env, holder_addr, None) {
+        Err(ContractError::NoRewards {}) => Ok(Response::new().add_attributes(vec![
+            attr("action", "claim_reward"),
+            attr("holder_address", address),
+            attr("amount", Uint128::zero()),
+        ])),
+        res => res,
+    }
+}
+
+fn claim_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    holder_addr: Addr,
+    recipient: Option<String>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    EXECUTION_LOCK.assert_unlocked(deps.storage)?;
     let holder_addr_raw = deps.api.addr_canonicalize(holder_addr.as_str())?;
     let recipient = match recipient {
-        Some(value) => deps.api.addr_validate(value.as_str()).unwrap(),
-        None => holder_addr.clone(),
+        Some(value) => deps.api.addr_validate(value.as_str())?,
+        None => read_reward_recipient(deps.as_ref(), &holder_addr_raw)?,
     };
 
-    let mut holder: Holder = read_holder(deps.storage, &holder_addr_raw)?;
-    let mut state: State = read_state(deps.storage)?;
     let config: Config = read_config(deps.storage)?;
+    assert_hub_not_paused(deps.as_ref(), deps.api.addr_humanize(&config.hub_contract)?)?;
+
+    let (paid, shortfall) = settle_claim(&mut deps, &env, &config, &holder_addr_raw)?;
+
+    let res = Response::new().add_attributes(vec![
+        attr("action", "claim_reward"),
+        attr("holder_address", holder_addr.as_str()),
+        attr("sender", holder_addr),
+        attr("recipient", recipient.as_str()),
+        attr("amount", paid),
+        attr("rewards", paid),
+        attr("shortfall", shortfall),
+    ]);
+
+    // the rewards compound in Anchor Earn when the holder deposits them
+    // only native rewards are deposited, see assert_native_rewards
+    let res = match read_reward_deposit(deps.storage, &holder_addr_raw)? {
+        Some(earn) => {
+            let tax_free = query_tax_free(deps.as_ref(), &config.hub_contract)?;
+            let rewards_after_tax = deduct_tax(
+                treasury(&deps.querier, tax_free),
+                Coin {
+                    denom: config.reward_denom,
+                    amount: paid,
+                },
+            )?;
+            res.add_submessage(create_deposit_msg(
+                deps,
+                &earn,
+                recipient,
+                rewards_after_tax,
+            )?)
+        }
+        None => res.add_message(reward_payout_msg(
+            deps.as_ref(),
+            &config,
+            recipient.to_string(),
+            paid,
+        )?),
+    };
 
-    let reward_with_decimals =
-        calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
+    Ok(res)
+}
 
-    let all_reward_with_decimals =
-        decimal_summation_in_256(reward_with_decimals, holder.pending_rewards);
+/// Claim the rewards of the sender swapped into denom through the market module and paid to
+/// its reward recipient. The swap fails when it returns max_spread less than the oracle rate,
+/// DEFAULT_CLAIM_MAX_SPREAD without one
+pub fn execute_claim_rewards_as(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    max_spread: Option<Decimal>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    EXECUTION_LOCK.assert_unlocked(deps.storage)?;
+    let holder_addr = info.sender;
+    let holder_addr_raw = deps.api.addr_canonicalize(holder_addr.as_str())?;
+    let recipient = read_reward_recipient(deps.as_ref(), &holder_addr_raw)?;
+
+    let config: Config = read_config(deps.storage)?;
+    assert_hub_not_paused(deps.as_ref(), deps.api.addr_humanize(&config.hub_contract)?)?;
+    assert_native_rewards(&config)?;
+    if cfg!(feature = "no-market") {
+        return Err(
+            StdError::generic_err("The rewards are swapped through the market module").into(),
+        );
+    }
+    validate_native_denom(&denom)?;
+    if denom == config.reward_denom {
+        return Err(
+            StdError::generic_err(format!("The rewards are paid in {} already", denom)).into(),
+        );
+    }
+    let max_spread = max_spread.unwrap_or_else(|| Decimal::percent(DEFAULT_CLAIM_MAX_SPREAD));
+    if max_spread >= Decimal::one() {
+        return Err(StdError::generic_err("max_spread must be less than 1").into());
+    }
+
+    let (paid, shortfall) = settle_claim(&mut deps, &env, &config, &holder_addr_raw)?;
+
+    // only the denoms the oracle prices are offered
+    let offer = Coin::new(paid.u128(), config.reward_denom.as_str());
+    let querier = TerraQuerier::new(&deps.querier);
+    let rate = querier
+        .query_exchange_rates(config.reward_denom.as_str(), vec![denom.as_str()])
+        .map_err(|_| StdError::generic_err(format!("{} is not priced by the oracle", denom)))?
+        .exchange_rates
+        .into_iter()
+        .find(|item| item.quote_denom == denom)
+        .map(|item| item.exchange_rate)
+        .ok_or_else(|| StdError::generic_err(format!("{} is not priced by the oracle", denom)))?;
+    let expected = paid * rate;
+    let simulated = querier
+        .query_swap(offer.clone(), denom.as_str())?
+        .receive
+        .amount;
+    let minimum = expected * (Decimal::one() - max_spread);
+    if simulated < minimum {
+        return Err(StdError::generic_err(format!(
+            "The swap returns {}{}, less than the {}{} max_spread allows",
+            simulated, denom, minimum, denom
+        ))
+        .into());
+    }
+
+    Ok(Response::new()
+        .add_message(create_swap_send_msg(
+            recipient.to_string(),
+            offer,
+            denom.clone(),
+        ))
+        .add_attributes(vec![
+            attr("action", "claim_reward"),
+            attr("holder_address", holder_addr.as_str()),
+            attr("sender", holder_addr),
+            attr("recipient", recipient.as_str()),
+            attr("amount", paid),
+            attr("rewards", paid),
+            attr("shortfall", shortfall),
+            attr("ask_denom", denom),
+            attr("simulated_return", simulated),
+        ]))
+}
+
+/// Settle the accruals of the holder and take the rewards the balance pays out of the
+/// state. Returns the rewards paid and the shortfall left pending for the next claim
+fn settle_claim(
+    deps: &mut DepsMut,
+    env: &Env,
+    config: &Config,
+    holder_addr_raw: &CanonicalAddr,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let mut holder: Holder = read_holder(deps.storage, holder_addr_raw)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    accrue(deps.storage, &mut state, holder_addr_raw, &mut holder)?;
+    let all_reward_with_decimals = holder.pending_rewards;
     let decimals = get_decimals(all_reward_with_decimals)?;
 
     let rewards = all_reward_with_decimals * Uint128::new(1);
 
     if rewards.is_zero() {
-        return Err(StdError::generic_err("No rewards have accrued yet"));
+        return Err(ContractError::NoRewards {});
     }
 
-    let new_balance = (state.prev_reward_balance.checked_sub(rewards))?;
+    // the shortfall of a pro rata claim stays pending for the next claim
+    let paid = claim_payout(
+        deps.as_ref(),
+        env,
+        config,
+        rewards,
+        state.prev_reward_balance,
+    )?;
+    let shortfall = rewards.checked_sub(paid)?;
+
+    let new_balance = (state.prev_reward_balance.checked_sub(paid))?;
     state.prev_reward_balance = new_balance;
     store_state(deps.storage, &state)?;
 
-    holder.pending_rewards = decimals;
+    holder.pending_rewards =
+        decimal_summation_in_256(decimals, Decimal::from_ratio(shortfall, 1u128));
     holder.index = state.global_index;
-    store_holder(deps.storage, &holder_addr_raw, &holder)?;
-
-    let bank_msg: CosmosMsg<TerraMsgWrapper> = CosmosMsg::Bank(BankMsg::Send {
-        to_address: recipient.to_string(),
-        amount: vec![deduct_tax(
-            &deps.querier,
-            Coin {
-                denom: config.reward_denom,
-                amount: rewards,
-            },
-        )?],
-    });
-
-    let res = Response::new()
-        .add_attributes(vec![
-            attr("action", "claim_reward"),
-            attr("holder_address", holder_addr),
-            attr("rewards", rewards),
-        ])
-        .add_message(bank_msg);
+    store_holder(deps.storage, holder_addr_raw, &holder)?;
 
-    Ok(res)
+    Ok((paid, shortfall))
 }
 
-pub fn execute_increase_balance(
+/// Route the claimed rewards of the sender to recipient, None pays the sender
+pub fn execute_update_reward_recipient(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let holder = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let recipient = recipient
+        .map(|recipient| deps.api.addr_validate(&recipient))
+        .transpose()?;
+    match &recipient {
+        Some(recipient) => REWARD_RECIPIENTS.save(
+            deps.storage,
+            holder.as_slice(),
+            &deps.api.addr_canonicalize(recipient.as_str())?,
+        )?,
+        None => REWARD_RECIPIENTS.remove(deps.storage, holder.as_slice()),
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_reward_recipient"),
+        attr("holder_address", info.sender.as_str()),
+        attr("recipient", recipient.unwrap_or(info.sender).as_str()),
+    ]))
+}
+
+/// The recipient of the rewards claimed by holder without a recipient
+fn read_reward_recipient(deps: Deps, holder: &CanonicalAddr) -> StdResult<Addr> {
+    let recipient = REWARD_RECIPIENTS
+        .may_load(deps.storage, holder.as_slice())?
+        .unwrap_or_else(|| holder.clone());
+    deps.api.addr_humanize(&recipient)
+}
+
+pub fn query_reward_recipient(deps: Deps, address: String) -> StdResult<RewardRecipientResponse> {
+    let holder = deps.api.addr_canonicalize(&address)?;
+    Ok(RewardRecipientResponse {
+        recipient: read_reward_recipient(deps, &holder)?.to_string(),
+    })
+}
+
+/// Send the rewards the sender accrues from now on to recipient, none accrues them to the
+/// sender again. The rewards accrued so far go where they went before
+pub fn execute_set_reward_recipient(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    recipient: Option<AccrualRecipient>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let holder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let redirect = match &recipient {
+        Some(AccrualRecipient::Address { address }) => {
+            let address = deps.api.addr_validate(address)?;
+            if address == info.sender {
+                return Err(StdError::generic_err(
+                    "The rewards cannot be redirected to the holder itself",
+                )
+                .into());
+            }
+            Some(AccrualRedirect::Address(
+                deps.api.addr_canonicalize(address.as_str())?,
+            ))
+        }
+        Some(AccrualRecipient::Pool {}) => Some(AccrualRedirect::Pool),
+        None => None,
+    };
+
+    let mut state = read_state(deps.storage)?;
+    let mut holder = read_holder_or_init(deps.storage, &holder_raw, state.global_index)?;
+    accrue(deps.storage, &mut state, &holder_raw, &mut holder)?;
+    store_holder(deps.storage, &holder_raw, &holder)?;
+    store_state(deps.storage, &state)?;
+
+    match redirect {
+        Some(redirect) => ACCRUAL_REDIRECTS.save(deps.storage, holder_raw.as_slice(), &redirect)?,
+        None => ACCRUAL_REDIRECTS.remove(deps.storage, holder_raw.as_slice()),
+    }
+
+    let recipient = match recipient {
+        Some(AccrualRecipient::Address { address }) => address,
+        Some(AccrualRecipient::Pool {}) => "pool".to_string(),
+        None => info.sender.to_string(),
+    };
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_reward_recipient"),
+        attr("holder_address", info.sender.as_str()),
+        attr("recipient", recipient),
+    ]))
+}
+
+pub fn query_accrual_recipient(deps: Deps, address: String) -> StdResult<AccrualRecipientResponse> {
+    let holder = deps.api.addr_canonicalize(&address)?;
+    let recipient = match ACCRUAL_REDIRECTS.may_load(deps.storage, holder.as_slice())? {
+        Some(AccrualRedirect::Address(recipient)) => Some(AccrualRecipient::Address {
+            address: deps.api.addr_humanize(&recipient)?.to_string(),
+        }),
+        Some(AccrualRedirect::Pool) => Some(AccrualRecipient::Pool {}),
+        None => None,
+    };
+    Ok(AccrualRecipientResponse { recipient })
+}
+
+/// Checkpoint holder at the global index. The rewards accrued since its index are added to
+/// its pending rewards, to the ones of its accrual recipient, or renounced to the pool by
+/// leaving them out of the balance accounted for. The holder itself is not stored
+fn accrue(
+    storage: &mut dyn Storage,
+    state: &mut State,
+    address: &CanonicalAddr,
+    holder: &mut Holder,
+) -> StdResult<()> {
+    let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
+    holder.index = state.global_index;
+    match ACCRUAL_REDIRECTS.may_load(storage, address.as_slice())? {
+        None => holder.pending_rewards = decimal_summation_in_256(rewards, holder.pending_rewards),
+        Some(AccrualRedirect::Address(recipient)) => {
+            let mut recipient_holder =
+                read_holder_or_init(storage, &recipient, state.global_index)?;
+            recipient_holder.pending_rewards =
+                decimal_summation_in_256(rewards, recipient_holder.pending_rewards);
+            store_holder(storage, &recipient, &recipient_holder)?;
+        }
+        Some(AccrualRedirect::Pool) => {
+            state.prev_reward_balance = state
+                .prev_reward_balance
+                .saturating_sub(rewards * Uint128::new(1));
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_increase_balance(
+    mut deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
     address: String,
     amount: Uint128,
-) -> StdResult<Response<TerraMsgWrapper>> {
-    let config = read_config(deps.storage)?;
-    let owner_human = deps.api.addr_humanize(&config.hub_contract)?;
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let address_raw = deps.api.addr_canonicalize(&address)?;
-    let sender = info.sender;
-
-    let token_address = deps
-        .api
-        .addr_humanize(&query_token_contract(deps.as_ref(), owner_human)?)?;
 
     // Check sender is token contract
-    if sender != token_address {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_token_contract(deps.branch(), &info.sender)?;
+    // the holders would lose their rewards to an imported state
+    close_import(deps.storage);
 
     let mut state: State = read_state(deps.storage)?;
-    let mut holder: Holder = read_holder(deps.storage, &address_raw)?;
+    let mut holder: Holder = read_holder_or_init(deps.storage, &address_raw, state.global_index)?;
 
     // get decimals
-    let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
-
-    holder.index = state.global_index;
-    holder.pending_rewards = decimal_summation_in_256(rewards, holder.pending_rewards);
+    accrue(deps.storage, &mut state, &address_raw, &mut holder)?;
     holder.balance += amount;
     state.total_balance += amount;
 
@@ -123,22 +412,16 @@ pub fn execute_increase_balance(
 }
 
 pub fn execute_decrease_balance(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     address: String,
     amount: Uint128,
-) -> StdResult<Response<TerraMsgWrapper>> {
-    let config = read_config(deps.storage)?;
-    let hub_contract = deps.api.addr_humanize(&config.hub_contract)?;
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
     let address_raw = deps.api.addr_canonicalize(&address)?;
 
     // Check sender is token contract
-    if query_token_contract(deps.as_ref(), hub_contract)?
-        != deps.api.addr_canonicalize(info.sender.as_str())?
-    {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_token_contract(deps.branch(), &info.sender)?;
 
     let mut state: State = read_state(deps.storage)?;
     let mut holder: Holder = read_holder(deps.storage, &address_raw)?;
@@ -146,13 +429,11 @@ pub fn execute_decrease_balance(
         return Err(StdError::generic_err(format!(
             "Decrease amount cannot exceed user balance: {}",
             holder.balance
-        )));
+        ))
+        .into());
     }
 
-    let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
-
-    holder.index = state.global_index;
-    holder.pending_rewards = decimal_summation_in_256(rewards, holder.pending_rewards);
+    accrue(deps.storage, &mut state, &address_raw, &mut holder)?;
     holder.balance = (holder.balance.checked_sub(amount))?;
     state.total_balance = (state.total_balance.checked_sub(amount))?;
 
@@ -170,12 +451,119 @@ pub fn execute_decrease_balance(
     Ok(res)
 }
 
+/// Register the token of the hub, the balances are only updated by it from then on.
+/// A token is registered once. Only hub_contract is allowed to execute
+pub fn execute_register_token_contract(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    token_contract: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let config = read_config(deps.storage)?;
+    if config.hub_contract != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // the registration is locked, registering the same token again changes nothing
+    let token_contract = deps.api.addr_validate(&token_contract)?;
+    let token_raw = deps.api.addr_canonicalize(token_contract.as_str())?;
+    match TOKEN_CONTRACT.may_load(deps.storage)? {
+        Some(registered) if registered != token_raw => {
+            return Err(StdError::generic_err(format!(
+                "The token contract {} is already registered",
+                deps.api.addr_humanize(&registered)?
+            ))
+            .into());
+        }
+        Some(_) => {}
+        None => TOKEN_CONTRACT.save(deps.storage, &token_raw)?,
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_token_contract"),
+        attr("token_contract", token_contract),
+    ]))
+}
+
+/// The balances follow the registered token, deployments whose hub did not register
+/// one yet trust the token of the hub config. Its first balance update registers it, so a
+/// later change of the hub config does not move the balances to another token
+fn assert_token_contract(deps: DepsMut, sender: &Addr) -> Result<(), ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    if let Some(token_contract) = TOKEN_CONTRACT.may_load(deps.storage)? {
+        if token_contract != sender_raw {
+            return Err(ContractError::Unauthorized {});
+        }
+        return Ok(());
+    }
+
+    let config = read_config(deps.storage)?;
+    let token_contract =
+        query_token_contract(deps.as_ref(), deps.api.addr_humanize(&config.hub_contract)?)?;
+    if token_contract != sender_raw {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(TOKEN_CONTRACT.save(deps.storage, &token_contract)?)
+}
+
+pub fn query_registered_contracts(deps: Deps) -> StdResult<RegisteredContractsResponse> {
+    let config = read_config(deps.storage)?;
+    let token_contract = match TOKEN_CONTRACT.may_load(deps.storage)? {
+        Some(token_contract) => Some(deps.api.addr_humanize(&token_contract)?.to_string()),
+        None => None,
+    };
+    Ok(RegisteredContractsResponse {
+        hub_contract: deps.api.addr_humanize(&config.hub_contract)?.to_string(),
+        token_contract,
+    })
+}
+
 pub fn query_accrued_rewards(deps: Deps, address: String) -> StdResult<AccruedRewardsResponse> {
     let global_index = read_state(deps.storage)?.global_index;
 
-    let holder: Holder = read_holder(deps.storage, &deps.api.addr_canonicalize(&address)?)?;
-    let reward_with_decimals =
-        calculate_decimal_rewards(global_index, holder.index, holder.balance)?;
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let holder: Holder = read_holder(deps.storage, &address_raw)?;
+    accrued_rewards(deps, &address_raw, &holder, global_index)
+}
+
+/// The rewards address was owed when the index was global_index, known as long as its
+/// balance did not change since then
+pub fn query_accrued_rewards_at(
+    deps: Deps,
+    address: String,
+    global_index: Decimal,
+) -> StdResult<AccruedRewardsResponse> {
+    let current_index = read_state(deps.storage)?.global_index;
+    if global_index > current_index {
+        return Err(StdError::generic_err(format!(
+            "The global index {} is past the current index {}",
+            global_index, current_index
+        )));
+    }
+
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let holder: Holder = read_holder(deps.storage, &address_raw)?;
+    if holder.index > global_index {
+        return Err(StdError::generic_err(format!(
+            "The rewards of {} are settled up to the index {}, past {}",
+            address, holder.index, global_index
+        )));
+    }
+    accrued_rewards(deps, &address_raw, &holder, global_index)
+}
+
+fn accrued_rewards(
+    deps: Deps,
+    address_raw: &CanonicalAddr,
+    holder: &Holder,
+    global_index: Decimal,
+) -> StdResult<AccruedRewardsResponse> {
+    // the accruals of a redirected holder are not its own
+    let reward_with_decimals = if ACCRUAL_REDIRECTS.has(deps.storage, address_raw.as_slice()) {
+        Decimal::zero()
+    } else {
+        calculate_decimal_rewards(global_index, holder.index, holder.balance)?
+    };
     let all_reward_with_decimals =
         decimal_summation_in_256(reward_with_decimals, holder.pending_rewards);
 
@@ -184,6 +572,161 @@ pub fn query_accrued_rewards(deps: Deps, address: String) -> StdResult<AccruedRe
     Ok(AccruedRewardsResponse { rewards })
 }
 
+/// Iterates every holder, meant for monitoring rather than for other contracts
+pub fn query_precision_report(deps: Deps) -> StdResult<PrecisionReportResponse> {
+    let state = read_state(deps.storage)?;
+
+    let mut holders = 0u64;
+    let mut claimable = Decimal::zero();
+    for_each_holder(deps.storage, |holder| {
+        let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
+        claimable = decimal_summation_in_256(
+            claimable,
+            decimal_summation_in_256(rewards, holder.pending_rewards),
+        );
+        holders += 1;
+        Ok(())
+    })?;
+
+    let unclaimed = Decimal::from_ratio(state.prev_reward_balance, 1u128);
+    let (residue, overclaimed) = if unclaimed >= claimable {
+        (
+            decimal_subtraction_in_256(unclaimed, claimable),
+            Decimal::zero(),
+        )
+    } else {
+        (
+            Decimal::zero(),
+            decimal_subtraction_in_256(claimable, unclaimed),
+        )
+    };
+    Ok(PrecisionReportResponse {
+        holders,
+        unclaimed: state.prev_reward_balance,
+        claimable,
+        residue,
+        overclaimed,
+    })
+}
+
+/// Checkpoint the rewards of address at the global index, nothing is paid out
+pub fn execute_sync_holder(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    address: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let mut state = read_state(deps.storage)?;
+    let global_index = state.global_index;
+
+    let mut holder: Holder = read_holder_or_init(deps.storage, &address_raw, global_index)?;
+    accrue(deps.storage, &mut state, &address_raw, &mut holder)?;
+    store_holder(deps.storage, &address_raw, &holder)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", "sync_holder"),
+            attr("address", address.as_str()),
+            attr("global_index", global_index.to_string()),
+            attr("pending_rewards", holder.pending_rewards.to_string()),
+        ])
+        .set_data(to_binary(&HolderResponse {
+            address,
+            balance: holder.balance,
+            index: holder.index,
+            pending_rewards: holder.pending_rewards,
+        })?))
+}
+
+/// Move the pending rewards of the sender to new_address, both holders are synced at the
+/// global index first so that neither loses the rewards accrued on its balance
+pub fn execute_migrate_holder(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    new_address: String,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    EXECUTION_LOCK.assert_unlocked(deps.storage)?;
+    let config = read_config(deps.storage)?;
+    assert_hub_not_paused(deps.as_ref(), deps.api.addr_humanize(&config.hub_contract)?)?;
+
+    let new_address = deps.api.addr_validate(&new_address)?;
+    if new_address == info.sender {
+        return Err(
+            StdError::generic_err("The rewards cannot be migrated to the same address").into(),
+        );
+    }
+    let old_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let new_raw = deps.api.addr_canonicalize(new_address.as_str())?;
+    let mut state = read_state(deps.storage)?;
+
+    let mut old_holder = read_holder(deps.storage, &old_raw)?;
+    accrue(deps.storage, &mut state, &old_raw, &mut old_holder)?;
+    let rewards = old_holder.pending_rewards;
+    if rewards.is_zero() {
+        return Err(ContractError::NoRewards {});
+    }
+    old_holder.pending_rewards = Decimal::zero();
+    store_holder(deps.storage, &old_raw, &old_holder)?;
+
+    let mut new_holder = read_holder_or_init(deps.storage, &new_raw, state.global_index)?;
+    accrue(deps.storage, &mut state, &new_raw, &mut new_holder)?;
+    new_holder.pending_rewards = decimal_summation_in_256(new_holder.pending_rewards, rewards);
+    store_holder(deps.storage, &new_raw, &new_holder)?;
+    store_state(deps.storage, &state)?;
+
+    // the preferences of the sender replace the ones of new_address
+    if let Some(deposit) = REWARD_DEPOSITS.may_load(deps.storage, old_raw.as_slice())? {
+        REWARD_DEPOSITS.save(deps.storage, new_raw.as_slice(), &deposit)?;
+        REWARD_DEPOSITS.remove(deps.storage, old_raw.as_slice());
+    }
+    if let Some(recipient) = REWARD_RECIPIENTS.may_load(deps.storage, old_raw.as_slice())? {
+        REWARD_RECIPIENTS.save(deps.storage, new_raw.as_slice(), &recipient)?;
+        REWARD_RECIPIENTS.remove(deps.storage, old_raw.as_slice());
+    }
+    if let Some(redirect) = ACCRUAL_REDIRECTS.may_load(deps.storage, old_raw.as_slice())? {
+        // new_address accrues to itself rather than to itself through the redirect
+        if redirect != AccrualRedirect::Address(new_raw.clone()) {
+            ACCRUAL_REDIRECTS.save(deps.storage, new_raw.as_slice(), &redirect)?;
+        }
+        ACCRUAL_REDIRECTS.remove(deps.storage, old_raw.as_slice());
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate_holder"),
+        attr("old_address", info.sender),
+        attr("new_address", new_address),
+        attr("rewards", rewards.to_string()),
+    ]))
+}
+
+/// The index history before a reward denom migration is in the old denom, since_index
+/// must be taken after the last migration
+pub fn query_index_delta(
+    deps: Deps,
+    address: String,
+    since_index: Decimal,
+) -> StdResult<IndexDeltaResponse> {
+    let global_index = read_state(deps.storage)?.global_index;
+    if since_index > global_index {
+        return Err(StdError::generic_err(format!(
+            "The global index is {}, it never was {}",
+            global_index, since_index
+        )));
+    }
+
+    let holder: Holder = read_holder(deps.storage, &deps.api.addr_canonicalize(&address)?)?;
+    Ok(IndexDeltaResponse {
+        address,
+        balance: holder.balance,
+        since_index,
+        global_index,
+        rewards: calculate_decimal_rewards(global_index, since_index, holder.balance)?,
+    })
+}
+
 pub fn query_holder(deps: Deps, address: String) -> StdResult<HolderResponse> {
     let holder: Holder = read_holder(deps.storage, &deps.api.addr_canonicalize(&address)?)?;
     Ok(HolderResponse {
@@ -210,6 +753,34 @@ pub fn query_holders(
     Ok(HoldersResponse { holders })
 }
 
+pub fn query_top_holders(deps: Deps, limit: Option<u32>) -> StdResult<TopHoldersResponse> {
+    Ok(TopHoldersResponse {
+        holders: read_top_holders(deps, limit)?,
+        indexed: HOLDERS_INDEX_CURSOR.may_load(deps.storage)?.is_none(),
+    })
+}
+
+const INDEX_HOLDERS_LIMIT: u32 = 100;
+
+/// The lazy migration of the balance index, holders are indexed as they are stored
+/// and the ones left are indexed a page at a time by anyone
+pub fn execute_index_holders(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response<TerraMsgWrapper>, ContractError> {
+    if HOLDERS_INDEX_CURSOR.may_load(deps.storage)?.is_none() {
+        return Err(StdError::generic_err("Every holder is already indexed").into());
+    }
+    let indexed = index_holders(deps.storage, limit.unwrap_or(INDEX_HOLDERS_LIMIT))?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "index_holders"),
+        attr("indexed", indexed.to_string()),
+    ]))
+}
+
 // calculate the reward based on the sender's index and the global index.
 fn calculate_decimal_rewards(
     global_index: Decimal,
@@ -225,12 +796,12 @@ fn calculate_decimal_rewards(
 
 // calculate the reward with decimal
 fn get_decimals(value: Decimal) -> StdResult<Decimal> {
-    let stringed: &str = &*value.to_string();
-    let parts: &[&str] = &*stringed.split('.').collect::<Vec<&str>>();
+    let stringed: &str = &value.to_string();
+    let parts: &[&str] = &stringed.split('.').collect::<Vec<&str>>();
     match parts.len() {
         1 => Ok(Decimal::zero()),
         2 => {
-            let decimals = Decimal::from_str(&*("0.".to_owned() + parts[1]))?;
+            let decimals = Decimal::from_str(&("0.".to_owned() + parts[1]))?;
             Ok(decimals)
         }
         _ => Err(StdError::generic_err("Unexpected number of dots")),
@@ -240,6 +811,12 @@ fn get_decimals(value: Decimal) -> StdResult<Decimal> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use basset::math::decimal_summation_in_256;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    const CASES: u64 = 256;
+    /// Upper bound of the whole Luna supply in uluna
+    const TOTAL_LUNA_SUPPLY: u64 = 1_000_000_000_000_000;
 
     #[test]
     pub fn proper_calculate_rewards() {
@@ -261,4 +838,41 @@ mod tests {
         .unwrap();
         assert_eq!(reward.to_string(), "0.9999999");
     }
+
+    #[test]
+    pub fn accrued_rewards_never_exceed_distributed() {
+        for case in 0..CASES {
+            let mut rng = XorShiftRng::seed_from_u64(case);
+            let balances: Vec<Uint128> = (0..rng.gen_range(1, 10))
+                .map(|_| Uint128::from(rng.gen_range(1, TOTAL_LUNA_SUPPLY / 10)))
+                .collect();
+            let total_balance = balances.iter().fold(Uint128::zero(), |sum, b| sum + *b);
+
+            // the global index grows as in execute_update_global_index
+            let mut global_index = Decimal::zero();
+            let mut distributed = Uint128::zero();
+            for _ in 0..rng.gen_range(1, 32) {
+                let rewards = Uint128::from(rng.gen_range(0, TOTAL_LUNA_SUPPLY));
+                global_index = decimal_summation_in_256(
+                    global_index,
+                    Decimal::from_ratio(rewards, total_balance),
+                );
+                distributed += rewards;
+            }
+
+            let mut accrued = Decimal::zero();
+            for balance in balances {
+                let rewards =
+                    calculate_decimal_rewards(global_index, Decimal::zero(), balance).unwrap();
+                accrued = decimal_summation_in_256(accrued, rewards);
+            }
+            assert!(
+                accrued <= Decimal::from_ratio(distributed, 1u128),
+                "case {}: {} accrued of {}",
+                case,
+                accrued,
+                distributed
+            );
+        }
+    }
 }
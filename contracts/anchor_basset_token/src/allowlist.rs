@@ -0,0 +1,85 @@
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult};
+
+use crate::querier::assert_hub_owner;
+use crate::state::{
+    read_hub_contract, read_send_allowlist, read_send_mode, SEND_ALLOWLIST, SEND_MODE,
+};
+use basset::token::{SendAllowlistResponse, SendMode};
+
+/// Restrict the contracts `Send` may call.
+/// Only the owner of the hub is allowed to execute
+pub fn execute_update_send_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    mode: SendMode,
+) -> StdResult<Response> {
+    assert_hub_owner(deps.as_ref(), &info.sender)?;
+
+    SEND_MODE.save(deps.storage, &mode)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_send_mode"),
+        attr("mode", format!("{:?}", mode).to_lowercase()),
+    ]))
+}
+
+/// Update the contracts `Send` may call in restricted mode.
+/// Only the owner of the hub is allowed to execute
+pub fn execute_update_send_allowlist(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> StdResult<Response> {
+    assert_hub_owner(deps.as_ref(), &info.sender)?;
+
+    for contract in add.unwrap_or_default() {
+        let contract_raw = deps.api.addr_canonicalize(contract.as_str())?;
+        SEND_ALLOWLIST.save(deps.storage, contract_raw.as_slice(), &true)?;
+    }
+    for contract in remove.unwrap_or_default() {
+        let contract_raw = deps.api.addr_canonicalize(contract.as_str())?;
+        SEND_ALLOWLIST.remove(deps.storage, contract_raw.as_slice());
+    }
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_send_allowlist")]))
+}
+
+/// Fails in restricted mode unless contract is on the send allowlist, the hub is always
+/// allowed so that holders can unbond
+pub fn assert_send_allowed(deps: Deps, contract: &str) -> StdResult<()> {
+    if read_send_mode(deps.storage)? == SendMode::Open {
+        return Ok(());
+    }
+    let contract_raw = deps.api.addr_canonicalize(contract)?;
+    if contract_raw != read_hub_contract(deps.storage)?
+        && !SEND_ALLOWLIST.has(deps.storage, contract_raw.as_slice())
+    {
+        return Err(StdError::generic_err(format!(
+            "Sending to {} is not allowed",
+            contract
+        )));
+    }
+    Ok(())
+}
+
+pub fn query_send_allowlist(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<SendAllowlistResponse> {
+    let start_after = start_after
+        .map(|contract| deps.api.addr_canonicalize(contract.as_str()))
+        .transpose()?;
+    let contracts = read_send_allowlist(deps.storage, start_after, limit)?
+        .iter()
+        .map(|contract| Ok(deps.api.addr_humanize(contract)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(SendAllowlistResponse {
+        mode: read_send_mode(deps.storage)?,
+        contracts,
+    })
+}
@@ -1,22 +1,41 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+};
 
 use cw20_legacy::allowances::{execute_decrease_allowance, execute_increase_allowance};
 use cw20_legacy::contract::instantiate as cw20_init;
 use cw20_legacy::contract::query as cw20_query;
-use cw20_legacy::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw20_legacy::msg::{InstantiateMsg, QueryMsg};
 
+use crate::allowlist::{
+    execute_update_send_allowlist, execute_update_send_mode, query_send_allowlist,
+};
 use crate::handler::*;
 use crate::msg::TokenInitMsg;
-use crate::state::store_hub_contract;
+use crate::querier::assert_transfers_allowed;
+use crate::state::{read_balance_at, store_hub_contract};
+use basset::migration::{
+    assert_state_version, init_version, migrate_state, MigrateMsg, MigrationStep, VERSIONING_STEP,
+};
+use basset::token::{BalanceAtResponse, TokenExecuteMsg, TokenQueryMsg};
+use basset::validation::assert_no_funds;
 use cw20::MinterResponse;
 use cw20_legacy::ContractError;
 
+const CONTRACT_NAME: &str = "crates.io:anchor-basset-token";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Recorded by the cw20 instantiation of the deployments that predate state versioning
+const LEGACY_CONTRACT_NAME: &str = "crates.io:cw20-base";
+/// Bump together with a new `MigrationStep` whenever the storage layout changes.
+const STATE_VERSION: u64 = 1;
+const MIGRATION_STEPS: &[MigrationStep] = &[VERSIONING_STEP];
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: TokenInitMsg,
@@ -27,7 +46,7 @@ pub fn instantiate(
     )?;
 
     cw20_init(
-        deps,
+        deps.branch(),
         env,
         info,
         InstantiateMsg {
@@ -42,6 +61,9 @@ pub fn instantiate(
         },
     )?;
 
+    // overrides the cw20-base contract info stored by cw20_init
+    init_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION, STATE_VERSION)?;
+
     Ok(Response::default())
 }
 
@@ -50,45 +72,121 @@ pub fn execute(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    msg: ExecuteMsg,
+    msg: TokenExecuteMsg,
 ) -> Result<Response, ContractError> {
+    assert_state_version(deps.storage, STATE_VERSION).map_err(StdError::from)?;
+    assert_no_funds(&info).map_err(StdError::from)?;
+
+    let transfer = matches!(
+        msg,
+        TokenExecuteMsg::Transfer { .. }
+            | TokenExecuteMsg::TransferAll { .. }
+            | TokenExecuteMsg::Burn { .. }
+            | TokenExecuteMsg::Send { .. }
+            | TokenExecuteMsg::TransferFrom { .. }
+            | TokenExecuteMsg::BurnFrom { .. }
+            | TokenExecuteMsg::SendFrom { .. }
+    );
+    if transfer {
+        assert_transfers_allowed(deps.as_ref())?;
+    }
+
     match msg {
-        ExecuteMsg::Transfer { recipient, amount } => {
+        TokenExecuteMsg::Transfer { recipient, amount } => {
             execute_transfer(deps, env, info, recipient, amount)
         }
-        ExecuteMsg::Burn { amount } => execute_burn(deps, env, info, amount),
-        ExecuteMsg::Send {
+        TokenExecuteMsg::TransferAll { recipient } => {
+            execute_transfer_all(deps, env, info, recipient)
+        }
+        TokenExecuteMsg::Burn { amount } => execute_burn(deps, env, info, amount),
+        TokenExecuteMsg::Send {
             contract,
             amount,
             msg,
         } => execute_send(deps, env, info, contract, amount, msg),
-        ExecuteMsg::Mint { recipient, amount } => execute_mint(deps, env, info, recipient, amount),
-        ExecuteMsg::IncreaseAllowance {
+        TokenExecuteMsg::Mint { recipient, amount } => {
+            execute_mint(deps, env, info, recipient, amount)
+        }
+        TokenExecuteMsg::IncreaseAllowance {
             spender,
             amount,
             expires,
         } => execute_increase_allowance(deps, env, info, spender, amount, expires),
-        ExecuteMsg::DecreaseAllowance {
+        TokenExecuteMsg::DecreaseAllowance {
             spender,
             amount,
             expires,
         } => execute_decrease_allowance(deps, env, info, spender, amount, expires),
-        ExecuteMsg::TransferFrom {
+        TokenExecuteMsg::TransferFrom {
             owner,
             recipient,
             amount,
         } => execute_transfer_from(deps, env, info, owner, recipient, amount),
-        ExecuteMsg::BurnFrom { owner, amount } => execute_burn_from(deps, env, info, owner, amount),
-        ExecuteMsg::SendFrom {
+        TokenExecuteMsg::BurnFrom { owner, amount } => {
+            execute_burn_from(deps, env, info, owner, amount)
+        }
+        TokenExecuteMsg::SendFrom {
             owner,
             contract,
             amount,
             msg,
         } => execute_send_from(deps, env, info, owner, contract, amount, msg),
+        TokenExecuteMsg::UpdateSendMode { mode } => {
+            Ok(execute_update_send_mode(deps, env, info, mode)?)
+        }
+        TokenExecuteMsg::UpdateSendAllowlist { add, remove } => {
+            Ok(execute_update_send_allowlist(deps, env, info, add, remove)?)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous = migrate_state(
+        deps.storage,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+        &[LEGACY_CONTRACT_NAME],
+        STATE_VERSION,
+        MIGRATION_STEPS,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("from_state_version", previous.to_string()),
+        attr("to_state_version", STATE_VERSION.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: TokenQueryMsg) -> StdResult<Binary> {
+    let msg = match msg {
+        TokenQueryMsg::BalanceAt { address, height } => {
+            let address = deps.api.addr_canonicalize(&address)?;
+            return to_binary(&BalanceAtResponse {
+                balance: read_balance_at(deps.storage, &address, height)?,
+                height,
+            });
+        }
+        TokenQueryMsg::SendAllowlist { start_after, limit } => {
+            return to_binary(&query_send_allowlist(deps, start_after, limit)?);
+        }
+        TokenQueryMsg::Balance { address } => QueryMsg::Balance { address },
+        TokenQueryMsg::TokenInfo {} => QueryMsg::TokenInfo {},
+        TokenQueryMsg::Minter {} => QueryMsg::Minter {},
+        TokenQueryMsg::Allowance { owner, spender } => QueryMsg::Allowance { owner, spender },
+        TokenQueryMsg::AllAllowances {
+            owner,
+            start_after,
+            limit,
+        } => QueryMsg::AllAllowances {
+            owner,
+            start_after,
+            limit,
+        },
+        TokenQueryMsg::AllAccounts { start_after, limit } => {
+            QueryMsg::AllAccounts { start_after, limit }
+        }
+    };
     cw20_query(deps, _env, msg)
 }
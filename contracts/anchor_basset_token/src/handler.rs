@@ -1,8 +1,11 @@
 use cosmwasm_std::{
-    to_binary, Binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128, WasmMsg,
+    to_binary, Binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdResult, SubMsg, Uint128,
+    WasmMsg,
 };
 
+use crate::allowlist::assert_send_allowed;
 use crate::querier::query_reward_contract;
+use crate::state::record_balance;
 use basset::reward::ExecuteMsg::{DecreaseBalance, IncreaseBalance};
 use cw20_legacy::allowances::{
     execute_burn_from as cw20_burn_from, execute_send_from as cw20_send_from,
@@ -12,10 +15,51 @@ use cw20_legacy::contract::{
     execute_burn as cw20_burn, execute_mint as cw20_mint, execute_send as cw20_send,
     execute_transfer as cw20_transfer,
 };
+use cw20_legacy::state::BALANCES;
 use cw20_legacy::ContractError;
 
+/// Snapshot the balances an operation changes, before it changes them
+fn record_balances(deps: &mut DepsMut, env: &Env, addresses: &[&str]) -> StdResult<()> {
+    for address in addresses {
+        let address = deps.api.addr_canonicalize(address)?;
+        record_balance(deps.storage, &address, env.block.height)?;
+    }
+    Ok(())
+}
+
+/// The hooks moving amount from one holder to the other at the reward contract, none when
+/// no balance changes, as for a self-transfer or a zero amount
+fn balance_hooks(
+    reward_contract: &str,
+    from: &str,
+    to: &str,
+    amount: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    if from == to || amount.is_zero() {
+        return Ok(vec![]);
+    }
+    Ok(vec![
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: reward_contract.to_string(),
+            msg: to_binary(&DecreaseBalance {
+                address: from.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: reward_contract.to_string(),
+            msg: to_binary(&IncreaseBalance {
+                address: to.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+    ])
+}
+
 pub fn execute_transfer(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     recipient: String,
@@ -26,49 +70,51 @@ pub fn execute_transfer(
 
     let rcpt_addr = deps.api.addr_validate(&recipient)?;
 
+    record_balances(&mut deps, &env, &[sender.as_str(), rcpt_addr.as_str()])?;
     let res: Response = cw20_transfer(deps, env, info, recipient, amount)?;
-    let messages = vec![
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: reward_contract.to_string(),
-            msg: to_binary(&DecreaseBalance {
-                address: sender.to_string(),
-                amount,
-            })
-            .unwrap(),
-            funds: vec![],
-        }),
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: reward_contract.to_string(),
-            msg: to_binary(&IncreaseBalance {
-                address: rcpt_addr.to_string(),
-                amount,
-            })
-            .unwrap(),
-            funds: vec![],
-        }),
-    ];
+    let messages = balance_hooks(
+        reward_contract.as_str(),
+        sender.as_str(),
+        rcpt_addr.as_str(),
+        amount,
+    )?;
     Ok(Response::new()
-        .add_messages(messages)
+        .add_submessages(messages)
         .add_attributes(res.attributes))
 }
 
-pub fn execute_burn(
+/// Transfer the balance of the sender when the message runs, the transfers it received
+/// after a balance query included
+pub fn execute_transfer_all(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let amount = BALANCES
+        .may_load(deps.storage, sender_raw.as_slice())?
+        .unwrap_or_default();
+    execute_transfer(deps, env, info, recipient, amount)
+}
+
+pub fn execute_burn(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let sender = info.sender.clone();
     let reward_contract = query_reward_contract(&deps)?;
 
+    record_balances(&mut deps, &env, &[sender.as_str()])?;
     let res: Response = cw20_burn(deps, env, info, amount)?;
     let messages = vec![CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: reward_contract.to_string(),
         msg: to_binary(&DecreaseBalance {
             address: sender.to_string(),
             amount,
-        })
-        .unwrap(),
+        })?,
         funds: vec![],
     })];
     Ok(Response::new()
@@ -77,7 +123,7 @@ pub fn execute_burn(
 }
 
 pub fn execute_mint(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     recipient: String,
@@ -85,6 +131,7 @@ pub fn execute_mint(
 ) -> Result<Response, ContractError> {
     let reward_contract = query_reward_contract(&deps)?;
 
+    record_balances(&mut deps, &env, &[&recipient])?;
     let res: Response = cw20_mint(deps, env, info, recipient.clone(), amount)?;
     Ok(Response::new()
         .add_messages(vec![CosmosMsg::Wasm(WasmMsg::Execute {
@@ -92,46 +139,28 @@ pub fn execute_mint(
             msg: to_binary(&IncreaseBalance {
                 address: recipient,
                 amount,
-            })
-            .unwrap(),
+            })?,
             funds: vec![],
         })])
         .add_attributes(res.attributes))
 }
 
 pub fn execute_send(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     contract: String,
     amount: Uint128,
     msg: Binary,
 ) -> Result<Response, ContractError> {
+    assert_send_allowed(deps.as_ref(), &contract)?;
     let sender = info.sender.clone();
     let reward_contract = query_reward_contract(&deps)?;
 
+    record_balances(&mut deps, &env, &[sender.as_str(), &contract])?;
     let res: Response = cw20_send(deps, env, info, contract.clone(), amount, msg)?;
-    let messages = vec![
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: reward_contract.to_string(),
-                msg: to_binary(&DecreaseBalance {
-                    address: sender.to_string(),
-                    amount,
-                })
-                .unwrap(),
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: reward_contract.to_string(),
-                msg: to_binary(&IncreaseBalance {
-                    address: contract,
-                    amount,
-                })
-                .unwrap(),
-                funds: vec![],
-            })),
-        ],
+    let messages = [
+        balance_hooks(reward_contract.as_str(), sender.as_str(), &contract, amount)?,
         res.messages,
     ]
     .concat();
@@ -142,7 +171,7 @@ pub fn execute_send(
 }
 
 pub fn execute_transfer_from(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     owner: String,
@@ -153,34 +182,21 @@ pub fn execute_transfer_from(
 
     let valid_owner = deps.api.addr_validate(owner.as_str())?;
 
+    record_balances(&mut deps, &env, &[valid_owner.as_str(), &recipient])?;
     let res: Response = cw20_transfer_from(deps, env, info, owner, recipient.clone(), amount)?;
-    let messages = vec![
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: reward_contract.to_string(),
-            msg: to_binary(&DecreaseBalance {
-                address: valid_owner.to_string(),
-                amount,
-            })
-            .unwrap(),
-            funds: vec![],
-        }),
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: reward_contract.to_string(),
-            msg: to_binary(&IncreaseBalance {
-                address: recipient,
-                amount,
-            })
-            .unwrap(),
-            funds: vec![],
-        }),
-    ];
+    let messages = balance_hooks(
+        reward_contract.as_str(),
+        valid_owner.as_str(),
+        &recipient,
+        amount,
+    )?;
     Ok(Response::new()
-        .add_messages(messages)
+        .add_submessages(messages)
         .add_attributes(res.attributes))
 }
 
 pub fn execute_burn_from(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     owner: String,
@@ -190,14 +206,14 @@ pub fn execute_burn_from(
 
     let valid_owner = deps.api.addr_validate(owner.as_str())?;
 
+    record_balances(&mut deps, &env, &[valid_owner.as_str()])?;
     let res: Response = cw20_burn_from(deps, env, info, owner, amount)?;
     let messages = vec![CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: reward_contract.to_string(),
         msg: to_binary(&DecreaseBalance {
             address: valid_owner.to_string(),
             amount,
-        })
-        .unwrap(),
+        })?,
         funds: vec![],
     })];
     Ok(Response::new()
@@ -206,7 +222,7 @@ pub fn execute_burn_from(
 }
 
 pub fn execute_send_from(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     owner: String,
@@ -214,32 +230,20 @@ pub fn execute_send_from(
     amount: Uint128,
     msg: Binary,
 ) -> Result<Response, ContractError> {
+    assert_send_allowed(deps.as_ref(), &contract)?;
     let reward_contract = query_reward_contract(&deps)?;
 
     let valid_owner = deps.api.addr_validate(owner.as_str())?;
 
+    record_balances(&mut deps, &env, &[valid_owner.as_str(), &contract])?;
     let res: Response = cw20_send_from(deps, env, info, owner, contract.clone(), amount, msg)?;
-    let messages = vec![
-        vec![
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: reward_contract.to_string(),
-                msg: to_binary(&DecreaseBalance {
-                    address: valid_owner.to_string(),
-                    amount,
-                })
-                .unwrap(),
-                funds: vec![],
-            })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: reward_contract.to_string(),
-                msg: to_binary(&IncreaseBalance {
-                    address: contract,
-                    amount,
-                })
-                .unwrap(),
-                funds: vec![],
-            })),
-        ],
+    let messages = [
+        balance_hooks(
+            reward_contract.as_str(),
+            valid_owner.as_str(),
+            &contract,
+            amount,
+        )?,
         res.messages,
     ]
     .concat();
@@ -2,6 +2,7 @@ pub mod contract;
 pub mod msg;
 pub mod state;
 
+mod allowlist;
 mod handler;
 mod querier;
 
@@ -1,27 +1,48 @@
-use cosmwasm_std::{Addr, Binary, DepsMut, QueryRequest, StdResult, WasmQuery};
+use cosmwasm_std::{Addr, Binary, Deps, DepsMut, QueryRequest, StdResult, WasmQuery};
 use cosmwasm_storage::to_length_prefixed;
 
 use crate::state::read_hub_contract;
+use basset::contract_error::ContractError;
 use basset::hub::Config;
+use basset::querier::query_hub_circuit_breaker;
 
-pub fn query_reward_contract(deps: &DepsMut) -> StdResult<Addr> {
-    let hub_address = deps
-        .api
-        .addr_humanize(&read_hub_contract(deps.storage).unwrap())
-        .unwrap();
+fn query_hub_config(deps: Deps) -> StdResult<Config> {
+    let hub_address = deps.api.addr_humanize(&read_hub_contract(deps.storage)?)?;
 
-    let config: Config = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Raw {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Raw {
         contract_addr: hub_address.to_string(),
         key: Binary::from(to_length_prefixed(b"config")),
-    }))?;
+    }))
+}
+
+pub fn query_reward_contract(deps: &DepsMut) -> StdResult<Addr> {
+    let config = query_hub_config(deps.as_ref())?;
 
     let address = deps
         .api
-        .addr_humanize(
-            &config
-                .reward_contract
-                .expect("the reward contract must have been registered"),
-        )
-        .unwrap();
+        .addr_humanize(&config.reward_contract.ok_or_else(|| {
+            ContractError::ContractNotRegistered {
+                contract: "reward".to_string(),
+            }
+        })?)?;
     Ok(address)
 }
+
+/// Transfers are frozen while the hub is paused, if its circuit breaker is configured so
+pub fn assert_transfers_allowed(deps: Deps) -> StdResult<()> {
+    let hub_address = deps.api.addr_humanize(&read_hub_contract(deps.storage)?)?;
+    let breaker = query_hub_circuit_breaker(&deps.querier, hub_address.as_str())?;
+    if breaker.paused && breaker.freeze_transfers {
+        return Err(ContractError::Paused {}.into());
+    }
+    Ok(())
+}
+
+/// Fails unless sender is the creator/owner of the hub
+pub fn assert_hub_owner(deps: Deps, sender: &Addr) -> StdResult<()> {
+    let config = query_hub_config(deps)?;
+    if deps.api.addr_canonicalize(sender.as_str())? != config.creator {
+        return Err(ContractError::Unauthorized {}.into());
+    }
+    Ok(())
+}
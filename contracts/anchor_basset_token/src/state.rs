@@ -1,8 +1,16 @@
-use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
+use basset::pagination::{calc_limit, calc_range_start};
+use basset::token::SendMode;
+use cosmwasm_std::{CanonicalAddr, Order, StdResult, Storage, Uint128};
 //use cosmwasm_storage::{singleton, singleton_read};
-use cw_storage_plus::Item;
+use cw20_legacy::state::BALANCES;
+use cw_storage_plus::{Bound, Item, Map, U64Key};
 
 pub const HUB_CONTRACT_KEY: Item<CanonicalAddr> = Item::new("\u{0}\u{c}hub_contract");
+/// Balances before their first change in a block, keyed by canonical address and height
+pub const BALANCE_CHANGELOG: Map<(&[u8], U64Key), Uint128> = Map::new("balance_changelog");
+pub const SEND_MODE: Item<SendMode> = Item::new("send_mode");
+/// The contracts `Send` may call in restricted mode
+pub const SEND_ALLOWLIST: Map<&[u8], bool> = Map::new("send_allowlist");
 
 // meta is the token definition as well as the total_supply
 pub fn read_hub_contract(storage: &dyn Storage) -> StdResult<CanonicalAddr> {
@@ -16,6 +24,66 @@ pub fn store_hub_contract(
     HUB_CONTRACT_KEY.save(storage, hub_contract)
 }
 
+/// Keep the balance of address before it changes at height, for BalanceAt
+pub fn record_balance(
+    storage: &mut dyn Storage,
+    address: &CanonicalAddr,
+    height: u64,
+) -> StdResult<()> {
+    let key = (address.as_slice(), U64Key::new(height));
+    if BALANCE_CHANGELOG.has(storage, key.clone()) {
+        return Ok(());
+    }
+    let balance = BALANCES
+        .may_load(storage, address.as_slice())?
+        .unwrap_or_default();
+    BALANCE_CHANGELOG.save(storage, key, &balance)
+}
+
+/// The balance before the first change since height, or the current balance when it
+/// has not changed since
+pub fn read_balance_at(
+    storage: &dyn Storage,
+    address: &CanonicalAddr,
+    height: u64,
+) -> StdResult<Uint128> {
+    let changed = BALANCE_CHANGELOG
+        .prefix(address.as_slice())
+        .range(
+            storage,
+            Some(Bound::inclusive(U64Key::new(height))),
+            None,
+            Order::Ascending,
+        )
+        .next()
+        .transpose()?;
+    match changed {
+        Some((_, balance)) => Ok(balance),
+        None => Ok(BALANCES
+            .may_load(storage, address.as_slice())?
+            .unwrap_or_default()),
+    }
+}
+
+pub fn read_send_mode(storage: &dyn Storage) -> StdResult<SendMode> {
+    Ok(SEND_MODE.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn read_send_allowlist(
+    storage: &dyn Storage,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let limit = calc_limit(limit);
+    let start = calc_range_start(start_after);
+
+    SEND_ALLOWLIST
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| Ok(CanonicalAddr::from(key)))
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
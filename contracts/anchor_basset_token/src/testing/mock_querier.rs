@@ -1,8 +1,8 @@
-use basset::hub::Config;
+use basset::hub::{CircuitBreaker, Config};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_slice, to_binary, Api, Coin, ContractResult, Empty, OwnedDeps, Querier, QuerierResult,
-    QueryRequest, SystemError, SystemResult, WasmQuery,
+    from_slice, to_binary, Api, Binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, WasmQuery,
 };
 use cosmwasm_storage::to_length_prefixed;
 
@@ -26,6 +26,8 @@ pub fn mock_dependencies(
 
 pub struct WasmMockQuerier {
     base: MockQuerier<Empty>,
+    circuit_breaker: Option<CircuitBreaker>,
+    hub_unavailable: bool,
 }
 
 impl Querier for WasmMockQuerier {
@@ -47,6 +49,11 @@ impl Querier for WasmMockQuerier {
 impl WasmMockQuerier {
     pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
         match &request {
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, .. }) if self.hub_unavailable => {
+                SystemResult::Err(SystemError::NoSuchContract {
+                    addr: contract_addr.to_string(),
+                })
+            }
             QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
                 if *contract_addr == MOCK_HUB_CONTRACT_ADDR {
                     let prefix_config = to_length_prefixed(b"config").to_vec();
@@ -67,6 +74,12 @@ impl WasmMockQuerier {
                             ),
                         };
                         SystemResult::Ok(ContractResult::from(to_binary(&config)))
+                    } else if key.as_slice() == b"circuit_breaker" {
+                        let value = match &self.circuit_breaker {
+                            Some(breaker) => to_binary(breaker).unwrap(),
+                            None => Binary::default(),
+                        };
+                        SystemResult::Ok(ContractResult::Ok(value))
                     } else {
                         unimplemented!()
                     }
@@ -81,6 +94,20 @@ impl WasmMockQuerier {
 
 impl WasmMockQuerier {
     pub fn new(base: MockQuerier<Empty>) -> Self {
-        WasmMockQuerier { base }
+        WasmMockQuerier {
+            base,
+            circuit_breaker: None,
+            hub_unavailable: false,
+        }
+    }
+
+    // configure the circuit breaker stored by the hub
+    pub fn with_circuit_breaker(&mut self, breaker: CircuitBreaker) {
+        self.circuit_breaker = Some(breaker);
+    }
+
+    // fail the queries of the hub, as if it was not reachable
+    pub fn with_hub_unavailable(&mut self) {
+        self.hub_unavailable = true;
     }
 }
@@ -1,15 +1,20 @@
 use cosmwasm_std::testing::{mock_env, mock_info};
 use cosmwasm_std::{
-    coins, to_binary, Api, CosmosMsg, DepsMut, OwnedDeps, Querier, Storage, SubMsg, Uint128,
-    WasmMsg,
+    coins, from_binary, to_binary, Api, Binary, CosmosMsg, DepsMut, OwnedDeps, Querier, StdError,
+    Storage, SubMsg, Uint128, WasmMsg,
 };
 
+use basset::hub::{CircuitBreaker, Cw20HookMsg};
 use basset::reward::ExecuteMsg::{DecreaseBalance, IncreaseBalance};
-use cw20::{Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
+use basset::token::{
+    BalanceAtResponse, SendAllowlistResponse, SendMode, TokenExecuteMsg, TokenQueryMsg,
+};
+use cw20::{BalanceResponse, Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
 use cw20_legacy::contract::{query_minter, query_token_info};
-use cw20_legacy::msg::ExecuteMsg;
 
-use crate::contract::{execute, instantiate};
+use cw20_legacy::ContractError;
+
+use crate::contract::{execute, instantiate, query};
 use crate::msg::TokenInitMsg;
 use crate::state::read_hub_contract;
 use crate::testing::mock_querier::{
@@ -66,7 +71,7 @@ fn _do_init<S: Storage, A: Api, Q: Querier>(
 }
 
 pub fn do_mint(deps: DepsMut, addr: String, amount: Uint128) {
-    let msg = ExecuteMsg::Mint {
+    let msg = TokenExecuteMsg::Mint {
         recipient: addr,
         amount,
     };
@@ -130,7 +135,7 @@ fn transfer() {
     do_mint(deps.as_mut(), addr1.clone(), amount1);
 
     let info = mock_info(addr1.as_str(), &[]);
-    let msg = ExecuteMsg::Transfer {
+    let msg = TokenExecuteMsg::Transfer {
         recipient: addr2.clone(),
         amount: Uint128::new(1u128),
     };
@@ -161,6 +166,64 @@ fn transfer() {
     );
 }
 
+#[test]
+fn transfer_all_sweeps_the_balance() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+    let addr2 = String::from("addr0002");
+    let amount1 = Uint128::from(12340000u128);
+
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+    do_mint(deps.as_mut(), addr1.clone(), amount1);
+
+    let info = mock_info(addr1.as_str(), &[]);
+    let msg = TokenExecuteMsg::TransferAll {
+        recipient: addr2.clone(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_REWARD_CONTRACT_ADDR),
+                msg: to_binary(&DecreaseBalance {
+                    address: addr1.clone(),
+                    amount: amount1,
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_REWARD_CONTRACT_ADDR),
+                msg: to_binary(&IncreaseBalance {
+                    address: addr2,
+                    amount: amount1,
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+        ]
+    );
+    let balance: BalanceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            TokenQueryMsg::Balance { address: addr1 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(balance.balance, Uint128::zero());
+
+    // nothing is left to sweep
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    assert_eq!(res.unwrap_err(), ContractError::InvalidZeroAmount {});
+}
+
 #[test]
 fn transfer_from() {
     let mut deps = mock_dependencies(&coins(2, "token"));
@@ -177,7 +240,7 @@ fn transfer_from() {
     do_mint(deps.as_mut(), addr1.clone(), amount1);
 
     let info = mock_info(addr1.as_str(), &[]);
-    let msg = ExecuteMsg::IncreaseAllowance {
+    let msg = TokenExecuteMsg::IncreaseAllowance {
         spender: addr3.clone(),
         amount: Uint128::new(1u128),
         expires: None,
@@ -185,7 +248,7 @@ fn transfer_from() {
     let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let info = mock_info(addr3.as_str(), &[]);
-    let msg = ExecuteMsg::TransferFrom {
+    let msg = TokenExecuteMsg::TransferFrom {
         owner: addr1.clone(),
         recipient: addr2.clone(),
         amount: Uint128::new(1u128),
@@ -229,7 +292,7 @@ fn mint() {
     );
 
     let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
-    let msg = ExecuteMsg::Mint {
+    let msg = TokenExecuteMsg::Mint {
         recipient: addr.clone(),
         amount: Uint128::new(1u128),
     };
@@ -263,7 +326,7 @@ fn burn() {
     do_mint(deps.as_mut(), addr.clone(), amount1);
 
     let info = mock_info(addr.as_str(), &[]);
-    let msg = ExecuteMsg::Burn {
+    let msg = TokenExecuteMsg::Burn {
         amount: Uint128::new(1u128),
     };
 
@@ -297,7 +360,7 @@ fn burn_from() {
     do_mint(deps.as_mut(), addr.clone(), amount1);
 
     let info = mock_info(addr.as_str(), &[]);
-    let msg = ExecuteMsg::IncreaseAllowance {
+    let msg = TokenExecuteMsg::IncreaseAllowance {
         spender: addr1.clone(),
         amount: Uint128::new(1u128),
         expires: None,
@@ -305,7 +368,7 @@ fn burn_from() {
     let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let info = mock_info(addr1.as_str(), &[]);
-    let msg = ExecuteMsg::BurnFrom {
+    let msg = TokenExecuteMsg::BurnFrom {
         owner: addr.clone(),
         amount: Uint128::new(1u128),
     };
@@ -339,13 +402,13 @@ fn send() {
     );
     do_mint(deps.as_mut(), addr1.clone(), amount1);
 
-    let dummy_msg = ExecuteMsg::Transfer {
+    let dummy_msg = TokenExecuteMsg::Transfer {
         recipient: addr1.clone(),
         amount: Uint128::new(1u128),
     };
 
     let info = mock_info(addr1.as_str(), &[]);
-    let msg = ExecuteMsg::Send {
+    let msg = TokenExecuteMsg::Send {
         contract: dummny_contract_addr.clone(),
         amount: Uint128::new(1u128),
         msg: to_binary(&dummy_msg).unwrap(),
@@ -404,20 +467,20 @@ fn send_from() {
     do_mint(deps.as_mut(), addr1.clone(), amount1);
 
     let info = mock_info(addr1.as_str(), &[]);
-    let msg = ExecuteMsg::IncreaseAllowance {
+    let msg = TokenExecuteMsg::IncreaseAllowance {
         spender: addr2.clone(),
         amount: Uint128::new(1u128),
         expires: None,
     };
     let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let dummy_msg = ExecuteMsg::Transfer {
+    let dummy_msg = TokenExecuteMsg::Transfer {
         recipient: addr1.clone(),
         amount: Uint128::new(1u128),
     };
 
     let info = mock_info(addr2.as_str(), &[]);
-    let msg = ExecuteMsg::SendFrom {
+    let msg = TokenExecuteMsg::SendFrom {
         owner: addr1.clone(),
         contract: dummny_contract_addr.clone(),
         amount: Uint128::new(1u128),
@@ -461,3 +524,325 @@ fn send_from() {
         .unwrap()
     );
 }
+
+#[test]
+fn transfers_follow_the_hub_pause() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+    let addr2 = String::from("addr0002");
+
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+    do_mint(deps.as_mut(), addr1.clone(), Uint128::new(100));
+
+    let transfer = TokenExecuteMsg::Transfer {
+        recipient: addr2,
+        amount: Uint128::new(1u128),
+    };
+
+    // a paused hub keeps the transfers open unless it freezes them
+    let mut breaker = CircuitBreaker {
+        max_exchange_rate_change: None,
+        guardian: None,
+        paused: true,
+        freeze_transfers: false,
+    };
+    deps.querier.with_circuit_breaker(breaker.clone());
+    let info = mock_info(addr1.as_str(), &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), transfer.clone()).unwrap();
+
+    breaker.freeze_transfers = true;
+    deps.querier.with_circuit_breaker(breaker);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), transfer).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("Contract is paused"))
+    );
+    let burn = TokenExecuteMsg::Burn {
+        amount: Uint128::new(1u128),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, burn).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("Contract is paused"))
+    );
+
+    // the hub can still mint, it is paused itself
+    do_mint(deps.as_mut(), addr1, Uint128::new(100));
+}
+
+#[test]
+fn hub_query_failures_are_errors() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+    do_mint(deps.as_mut(), addr1.clone(), Uint128::new(100));
+
+    // the handlers return the failure of the querier instead of aborting
+    deps.querier.with_hub_unavailable();
+    let transfer = TokenExecuteMsg::Transfer {
+        recipient: String::from("addr0002"),
+        amount: Uint128::new(1u128),
+    };
+    let info = mock_info(addr1.as_str(), &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, transfer).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::Std(StdError::GenericErr { .. })
+    ));
+    let mint = TokenExecuteMsg::Mint {
+        recipient: addr1,
+        amount: Uint128::new(1u128),
+    };
+    let info = mock_info(MOCK_HUB_CONTRACT_ADDR, &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, mint).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::Std(StdError::GenericErr { .. })
+    ));
+}
+
+#[test]
+fn balance_at_follows_the_changes() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+    let addr2 = String::from("addr0002");
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+
+    // minted at the mock height, then a transfer two blocks later
+    let height = mock_env().block.height;
+    do_mint(deps.as_mut(), addr1.clone(), Uint128::new(100));
+    let mut env = mock_env();
+    env.block.height += 2;
+    let msg = TokenExecuteMsg::Transfer {
+        recipient: addr2.clone(),
+        amount: Uint128::new(40),
+    };
+    execute(deps.as_mut(), env, mock_info(&addr1, &[]), msg).unwrap();
+
+    let balance_at = |deps: &OwnedDeps<_, _, _>, address: &str, height| -> Uint128 {
+        let msg = TokenQueryMsg::BalanceAt {
+            address: address.to_string(),
+            height,
+        };
+        let res: BalanceAtResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.balance
+    };
+    for (at, balance1, balance2) in [
+        (height, 0, 0),
+        (height + 1, 100, 0),
+        (height + 2, 100, 0),
+        (height + 3, 60, 40),
+    ] {
+        assert_eq!(balance_at(&deps, &addr1, at), Uint128::new(balance1));
+        assert_eq!(balance_at(&deps, &addr2, at), Uint128::new(balance2));
+    }
+}
+
+#[test]
+fn self_and_zero_transfers_skip_the_reward_hooks() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+    let addr2 = String::from("addr0002");
+    let amount1 = Uint128::from(12340000u128);
+
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+    do_mint(deps.as_mut(), addr1.clone(), amount1);
+
+    let info = mock_info(addr1.as_str(), &[]);
+    let msg = TokenExecuteMsg::Transfer {
+        recipient: addr1.clone(),
+        amount: Uint128::new(1u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    // the receive hook of a self-send is still sent, the reward hooks are not
+    let msg = TokenExecuteMsg::Send {
+        contract: addr1.clone(),
+        amount: Uint128::new(1u128),
+        msg: to_binary(&"notify").unwrap(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == &addr1
+    ));
+
+    let msg = TokenExecuteMsg::IncreaseAllowance {
+        spender: addr2.clone(),
+        amount: Uint128::new(1u128),
+        expires: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = TokenExecuteMsg::TransferFrom {
+        owner: addr1.clone(),
+        recipient: addr2,
+        amount: Uint128::zero(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    let balance: BalanceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            TokenQueryMsg::Balance { address: addr1 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(balance.balance, amount1);
+}
+
+#[test]
+fn restricted_sends_need_the_allowlist() {
+    let mut deps = mock_dependencies(&coins(2, "token"));
+    let addr1 = String::from("addr0001");
+
+    do_init_with_minter(
+        deps.borrow_mut(),
+        &String::from(MOCK_HUB_CONTRACT_ADDR),
+        None,
+    );
+    do_mint(deps.as_mut(), addr1.clone(), Uint128::new(100));
+
+    let send = |contract: &str| TokenExecuteMsg::Send {
+        contract: contract.to_string(),
+        amount: Uint128::new(1u128),
+        msg: Binary::default(),
+    };
+    let info = mock_info(addr1.as_str(), &[]);
+
+    // sends stay open by default
+    execute(deps.as_mut(), mock_env(), info.clone(), send("market")).unwrap();
+
+    // only the owner of the hub manages the allowlist
+    let update_mode = TokenExecuteMsg::UpdateSendMode {
+        mode: SendMode::Restricted,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), update_mode.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("unauthorized"))
+    );
+    let owner = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), owner.clone(), update_mode).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner.clone(),
+        TokenExecuteMsg::UpdateSendAllowlist {
+            add: Some(vec!["market".to_string(), "vault".to_string()]),
+            remove: None,
+        },
+    )
+    .unwrap();
+
+    execute(deps.as_mut(), mock_env(), info.clone(), send("market")).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), send("other")).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("Sending to other is not allowed"))
+    );
+
+    // the hub needs no entry, holders can always unbond
+    let unbond = to_binary(&Cw20HookMsg::Unbond {}).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        TokenExecuteMsg::Send {
+            contract: MOCK_HUB_CONTRACT_ADDR.to_string(),
+            amount: Uint128::new(1u128),
+            msg: unbond.clone(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last().unwrap().msg,
+        Cw20ReceiveMsg {
+            sender: addr1.clone(),
+            amount: Uint128::new(1),
+            msg: unbond,
+        }
+        .into_cosmos_msg(MOCK_HUB_CONTRACT_ADDR.to_string())
+        .unwrap()
+    );
+
+    // send_from follows the allowlist too
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        TokenExecuteMsg::IncreaseAllowance {
+            spender: "spender".to_string(),
+            amount: Uint128::new(10u128),
+            expires: None,
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("spender", &[]),
+        TokenExecuteMsg::SendFrom {
+            owner: addr1,
+            contract: "other".to_string(),
+            amount: Uint128::new(1u128),
+            msg: Binary::default(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("Sending to other is not allowed"))
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner,
+        TokenExecuteMsg::UpdateSendAllowlist {
+            add: None,
+            remove: Some(vec!["vault".to_string()]),
+        },
+    )
+    .unwrap();
+    let res: SendAllowlistResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            TokenQueryMsg::SendAllowlist {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        SendAllowlistResponse {
+            mode: SendMode::Restricted,
+            contracts: vec!["market".to_string()],
+        }
+    );
+}
@@ -0,0 +1,42 @@
+#![no_main]
+use anchor_basset_hub::contract::{execute, instantiate, query};
+use basset::hub::{ExecuteMsg, HubMode, InstantiateMsg, QueryMsg};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{coins, from_slice, Decimal};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks the entry point, the rest is the json message. Errors are fine,
+// a panic is a bug
+fuzz_target!(|data: &[u8]| {
+    let (entry, msg) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut deps = mock_dependencies(&[]);
+    let funds = coins(1000000, "uluna");
+    if *entry % 3 == 0 {
+        if let Ok(msg) = from_slice::<InstantiateMsg>(msg) {
+            let _ = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &funds), msg);
+        }
+        return;
+    }
+
+    let init = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 210,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        reward_denom: "uusd".to_string(),
+        validator: "validator".to_string(),
+        mode: HubMode::default(),
+    };
+    let _ = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &funds), init);
+    if *entry % 3 == 1 {
+        if let Ok(msg) = from_slice::<ExecuteMsg>(msg) {
+            let _ = execute(deps.as_mut(), mock_env(), mock_info("sender", &funds), msg);
+        }
+    } else if let Ok(msg) = from_slice::<QueryMsg>(msg) {
+        let _ = query(deps.as_ref(), mock_env(), msg);
+    }
+});
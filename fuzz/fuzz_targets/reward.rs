@@ -0,0 +1,36 @@
+#![no_main]
+use anchor_basset_reward::contract::{execute, instantiate, query};
+use basset::reward::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::from_slice;
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks the entry point, the rest is the json message. Errors are fine,
+// a panic is a bug
+fuzz_target!(|data: &[u8]| {
+    let (entry, msg) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut deps = mock_dependencies(&[]);
+    if *entry % 3 == 0 {
+        if let Ok(msg) = from_slice::<InstantiateMsg>(msg) {
+            let _ = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        }
+        return;
+    }
+
+    let init = InstantiateMsg {
+        hub_contract: "hub".to_string(),
+        reward_denom: "uusd".to_string(),
+        reward_asset: None,
+    };
+    let _ = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init);
+    if *entry % 3 == 1 {
+        if let Ok(msg) = from_slice::<ExecuteMsg>(msg) {
+            let _ = execute(deps.as_mut(), mock_env(), mock_info("sender", &[]), msg);
+        }
+    } else if let Ok(msg) = from_slice::<QueryMsg>(msg) {
+        let _ = query(deps.as_ref(), mock_env(), msg);
+    }
+});
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Binary, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -21,6 +21,22 @@ pub enum ExecuteMsg {
         amount: Uint128,
         proof: Vec<String>,
     },
+    /// Claims a stage of a registered airdrop token.
+    /// Only the owner, the operator and the keepers are allowed to execute
+    FabricateClaim {
+        airdrop_token: String,
+        stage: u8,
+        amount: Uint128,
+        proof: Vec<String>,
+    },
+    /// Records the amount the hub can claim at a stage of a registered airdrop token, taken
+    /// from the published airdrop data, for the UnclaimedAirdrops query.
+    /// Only the owner, the operator and the keepers are allowed to execute
+    RecordAllocation {
+        airdrop_token: String,
+        stage: u8,
+        amount: Uint128,
+    },
     UpdateConfig {
         owner: Option<String>,
         hub_contract: Option<String>,
@@ -37,6 +53,23 @@ pub enum ExecuteMsg {
         airdrop_token: String,
         airdrop_info: AirdropInfo,
     },
+    /// Set or remove the operator, allowed to manage the airdrops and fabricate the claims.
+    /// Only the owner and the operator are allowed to execute
+    UpdateOperator {
+        operator: Option<String>,
+    },
+    AddKeeper {
+        keeper: String,
+    },
+    RemoveKeeper {
+        keeper: String,
+    },
+    /// Sells the claimed airdrop token through its swap contract and sends
+    /// the proceeds to the reward contract.
+    /// Only the registry itself, the owner or the operator is allowed to execute
+    SwapToRewardDenom {
+        airdrop_token: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,6 +81,22 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    Keepers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ClaimHistory {
+        airdrop_token: String,
+        start_after: Option<u8>,
+        limit: Option<u32>,
+    },
+    /// Returns the stages of the registered airdrops that are not claimed by the hub yet,
+    /// paged by airdrop token. The raw format airdrops of a page are skipped
+    UnclaimedAirdrops {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Operator {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -70,6 +119,23 @@ pub enum ANCAirdropHandleMsg {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AirdropQueryMsg {
+    LatestStage {},
+    IsClaimed { stage: u8, address: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LatestStageResponse {
+    pub latest_stage: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsClaimedResponse {
+    pub is_claimed: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PairHandleMsg {
@@ -87,6 +153,18 @@ pub struct AirdropInfo {
     pub airdrop_swap_contract: String,
     pub swap_belief_price: Option<Decimal>,
     pub swap_max_spread: Option<Decimal>,
+    /// Schema of the claim message of the airdrop contract, defaults to `Merkle`
+    pub claim_format: Option<ClaimFormat>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimFormat {
+    /// `{"claim":{"stage":..,"amount":..,"proof":[..]}}`, used by the MIR and ANC airdrop contracts
+    Merkle {},
+    /// Raw claim message in which the `"$stage"`, `"$amount"` and `"$proof"` strings
+    /// are replaced by the values of the claim
+    Raw { template: Binary },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -107,3 +185,40 @@ pub struct AirdropInfoElem {
 pub struct AirdropInfoResponse {
     pub airdrop_info: Vec<AirdropInfoElem>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeepersResponse {
+    pub keepers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimHistoryElem {
+    pub stage: u8,
+    pub amount: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimHistoryResponse {
+    pub airdrop_token: String,
+    pub claims: Vec<ClaimHistoryElem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnclaimedStage {
+    pub stage: u8,
+    /// The amount recorded with RecordAllocation, unknown until then
+    pub amount: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnclaimedAirdropElem {
+    pub airdrop_token: String,
+    pub latest_stage: u8,
+    pub unclaimed_stages: Vec<UnclaimedStage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnclaimedAirdropsResponse {
+    pub airdrops: Vec<UnclaimedAirdropElem>,
+}
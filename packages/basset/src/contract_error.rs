@@ -1,6 +1,9 @@
-use cosmwasm_std::{OverflowError, StdError};
+use cosmwasm_std::{Decimal, OverflowError, StdError};
 use thiserror::Error;
 
+/// Failure causes shared by the bAsset contracts.
+/// The hub, reward and registry handlers return it, the token converts it into a `StdError`
+/// carrying the message below since its handlers return the cw20 error.
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
@@ -9,18 +12,120 @@ pub enum ContractError {
     #[error("{0}")]
     OverflowError(#[from] OverflowError),
 
-    #[error("Unauthorized")]
+    #[error("unauthorized")]
     Unauthorized {},
 
     #[error("Invalid zero amount")]
     InvalidZeroAmount {},
 
+    #[error("Invalid denom {denom}: {reason}")]
+    InvalidDenom { denom: String, reason: String },
+
+    #[error("Invalid cw20 address {address}")]
+    InvalidCw20Address { address: String },
+
     #[error("Max spread assertion")]
     MaxSpreadAssertion {},
 
     #[error("Max slippage assertion")]
     MaxSlippageAssertion {},
 
+    #[error("Slippage exceeded")]
+    SlippageExceeded {},
+
     #[error("Asset mismatch")]
     AssetMismatch {},
+
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("{address} is not allowed to bond")]
+    BondNotAllowed { address: String },
+
+    #[error("Contract is not paused")]
+    NotPaused {},
+
+    #[error("Price {implied} deviates too much from the oracle price {oracle}")]
+    PriceDeviation { implied: Decimal, oracle: Decimal },
+
+    #[error("No rewards have accrued yet")]
+    NoRewards {},
+
+    #[error("Stage {stage} of {airdrop_token} has already been claimed")]
+    AlreadyClaimed { airdrop_token: String, stage: u8 },
+
+    #[error("There is no balance for {address} in airdrop token contract {token_contract}")]
+    NoAirdropBalance {
+        address: String,
+        token_contract: String,
+    },
+
+    #[error("State version {found} does not match the expected version {expected}, the contract must be migrated")]
+    StateVersionMismatch { expected: u64, found: u64 },
+
+    #[error("No migration step from state version {from}")]
+    MissingMigrationStep { from: u64 },
+
+    #[error("Cannot migrate state version {from} down to {to}")]
+    MigrationDowngrade { from: u64, to: u64 },
+
+    #[error("Cannot migrate the contract {found} to {expected}")]
+    ContractNameMismatch { expected: String, found: String },
+
+    #[error("This change is timelocked, use ProposePendingChange")]
+    Timelocked {},
+
+    #[error("No pending change with id {id}")]
+    PendingChangeNotFound { id: u64 },
+
+    #[error("Pending change {id} cannot be applied before {effective_at}")]
+    TimelockNotExpired { id: u64, effective_at: u64 },
+
+    #[error("This message does not accept funds")]
+    UnexpectedFunds {},
+
+    #[error("The {contract} contract is not registered")]
+    ContractNotRegistered { contract: String },
+
+    #[error("Another operation of the contract is pending, the call cannot reenter it")]
+    Locked {},
+
+    #[error("The state can only be imported into a fresh contract")]
+    ImportClosed {},
+
+    #[error("The state is being imported, the import must be finished first")]
+    ImportInProgress {},
+}
+
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_into_std_error() {
+        let err: StdError = ContractError::Unauthorized {}.into();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+
+        let err: StdError = ContractError::AlreadyClaimed {
+            airdrop_token: "MIR".to_string(),
+            stage: 2,
+        }
+        .into();
+        assert_eq!(
+            err,
+            StdError::generic_err("Stage 2 of MIR has already been claimed")
+        );
+
+        let err: StdError = ContractError::Std(StdError::not_found("config")).into();
+        assert_eq!(err, StdError::not_found("config"));
+    }
 }
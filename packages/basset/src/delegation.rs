@@ -0,0 +1,116 @@
+use cosmwasm_std::{coin, CosmosMsg, StakingMsg, StdError, StdResult, Uint128};
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+/// Pick the validators undelegating claim out of delegations, the pairs of a validator and
+/// its delegated amount. A random validator is picked for every undelegation, seeded by
+/// seed, e.g. the block height, and one without the amount left undelegates all it has.
+/// The picks only depend on the arguments, so the same call always returns the same messages
+pub fn pick_undelegations(
+    delegations: &[(String, Uint128)],
+    claim: Uint128,
+    denom: &str,
+    seed: u64,
+) -> StdResult<Vec<CosmosMsg>> {
+    let delegated = delegations
+        .iter()
+        .try_fold(Uint128::zero(), |total, (_, amount)| {
+            total.checked_add(*amount)
+        })?;
+    if delegated < claim {
+        return Err(StdError::generic_err(format!(
+            "Only {}{} is delegated, {}{} cannot be undelegated",
+            delegated, denom, claim, denom
+        )));
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut claimed = claim;
+    let mut iteration_index = 0;
+    let mut deletable_delegations = delegations.to_vec();
+
+    while !claimed.is_zero() {
+        let mut rng = XorShiftRng::seed_from_u64(seed + iteration_index);
+        let random_index = rng.gen_range(0, deletable_delegations.len());
+        let (validator, val) = deletable_delegations.remove(random_index);
+        let undelegated_amount = val.min(claimed);
+        claimed = claimed.checked_sub(undelegated_amount)?;
+        if !undelegated_amount.is_zero() {
+            messages.push(CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator,
+                amount: coin(undelegated_amount.u128(), denom),
+            }));
+        }
+        iteration_index += 1;
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: u64 = 256;
+
+    fn undelegated(messages: &[CosmosMsg]) -> Vec<(String, Uint128)> {
+        messages
+            .iter()
+            .map(|msg| match msg {
+                CosmosMsg::Staking(StakingMsg::Undelegate { validator, amount }) => {
+                    (validator.clone(), amount.amount)
+                }
+                _ => panic!("Unexpected message: {:?}", msg),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn undelegations_add_up_to_the_claim() {
+        for case in 0..CASES {
+            let mut rng = XorShiftRng::seed_from_u64(case);
+            let delegations: Vec<(String, Uint128)> = (0..rng.gen_range(1, 10))
+                .map(|i| {
+                    (
+                        format!("validator{}", i),
+                        Uint128::from(rng.gen_range(0u64, 1_000_000)),
+                    )
+                })
+                .collect();
+            let delegated: u64 = delegations.iter().map(|(_, a)| a.u128() as u64).sum();
+            let claim = Uint128::from(rng.gen_range(0, delegated + 2));
+
+            let res = pick_undelegations(&delegations, claim, "uluna", case);
+            if claim.u128() > delegated as u128 {
+                assert!(res.is_err(), "case {}", case);
+                continue;
+            }
+            let picks = undelegated(&res.unwrap());
+
+            // every validator undelegates once, at most what it has, and nothing is left
+            let total: u128 = picks.iter().map(|(_, a)| a.u128()).sum();
+            assert_eq!(total, claim.u128(), "case {}", case);
+            for (i, (validator, amount)) in picks.iter().enumerate() {
+                assert!(!amount.is_zero(), "case {}", case);
+                assert!(picks[i + 1..].iter().all(|(v, _)| v != validator));
+                let (_, delegated) = delegations.iter().find(|(v, _)| v == validator).unwrap();
+                assert!(amount <= delegated, "case {}", case);
+            }
+
+            // the same arguments pick the same validators
+            assert_eq!(
+                pick_undelegations(&delegations, claim, "uluna", case).unwrap(),
+                pick_undelegations(&delegations, claim, "uluna", case).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_delegated_is_an_error() {
+        assert!(pick_undelegations(&[], Uint128::zero(), "uluna", 0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            pick_undelegations(&[], Uint128::new(10), "uluna", 0).unwrap_err(),
+            StdError::generic_err("Only 0uluna is delegated, 10uluna cannot be undelegated")
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use cosmwasm_std::{Binary, Coin};
+
+use crate::proto::encode_bytes_field;
+
+pub const MSG_FUND_COMMUNITY_POOL_TYPE_URL: &str =
+    "/cosmos.distribution.v1beta1.MsgFundCommunityPool";
+
+/// Protobuf encoding of MsgFundCommunityPool, there is no CosmosMsg funding the community pool
+pub fn encode_msg_fund_community_pool(amount: &[Coin], depositor: &str) -> Binary {
+    let mut buf = vec![];
+    for coin in amount {
+        let mut coin_buf = vec![];
+        encode_bytes_field(&mut coin_buf, 1, coin.denom.as_bytes());
+        encode_bytes_field(&mut coin_buf, 2, coin.amount.to_string().as_bytes());
+        encode_bytes_field(&mut buf, 1, &coin_buf);
+    }
+    encode_bytes_field(&mut buf, 2, depositor.as_bytes());
+    Binary::from(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coin;
+
+    #[test]
+    fn encode_fund_community_pool() {
+        let msg = encode_msg_fund_community_pool(&[coin(250, "uusd")], "reward");
+        let mut expected = vec![0x0a, 11, 0x0a, 4];
+        expected.extend_from_slice(b"uusd");
+        expected.extend_from_slice(&[0x12, 3]);
+        expected.extend_from_slice(b"250");
+        expected.extend_from_slice(&[0x12, 6]);
+        expected.extend_from_slice(b"reward");
+        assert_eq!(msg, Binary::from(expected));
+    }
+}
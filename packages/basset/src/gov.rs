@@ -0,0 +1,168 @@
+use cosmwasm_std::{Binary, Decimal, StdError, StdResult, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::math::decimal_subtraction_in_256;
+use crate::proto::{encode_bytes_field, encode_varint_field};
+
+pub const MSG_VOTE_WEIGHTED_TYPE_URL: &str = "/cosmos.gov.v1beta1.MsgVoteWeighted";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+    NoWithVeto,
+}
+
+impl VoteOption {
+    /// The value of the option in the gov module protobuf enum
+    fn proto_value(self) -> u64 {
+        match self {
+            VoteOption::Yes => 1,
+            VoteOption::Abstain => 2,
+            VoteOption::No => 3,
+            VoteOption::NoWithVeto => 4,
+        }
+    }
+}
+
+/// The bAsset signalled for each option of a proposal
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Tally {
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub abstain: Uint128,
+    pub no_with_veto: Uint128,
+}
+
+impl Tally {
+    fn option_mut(&mut self, option: VoteOption) -> &mut Uint128 {
+        match option {
+            VoteOption::Yes => &mut self.yes,
+            VoteOption::No => &mut self.no,
+            VoteOption::Abstain => &mut self.abstain,
+            VoteOption::NoWithVeto => &mut self.no_with_veto,
+        }
+    }
+
+    pub fn add(&mut self, option: VoteOption, weight: Uint128) {
+        *self.option_mut(option) += weight;
+    }
+
+    pub fn remove(&mut self, option: VoteOption, weight: Uint128) -> StdResult<()> {
+        let amount = self.option_mut(option);
+        *amount = amount.checked_sub(weight)?;
+        Ok(())
+    }
+
+    pub fn total(&self) -> Uint128 {
+        self.yes + self.no + self.abstain + self.no_with_veto
+    }
+
+    /// The split of a weighted vote, the weights add up to exactly one
+    pub fn weighted_options(&self) -> StdResult<Vec<(VoteOption, Decimal)>> {
+        let total = self.total();
+        if total.is_zero() {
+            return Err(StdError::generic_err("No votes are signalled"));
+        }
+        let options: Vec<(VoteOption, Uint128)> = vec![
+            (VoteOption::Yes, self.yes),
+            (VoteOption::No, self.no),
+            (VoteOption::Abstain, self.abstain),
+            (VoteOption::NoWithVeto, self.no_with_veto),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .collect();
+
+        let mut remaining = Decimal::one();
+        let mut weighted = vec![];
+        for (index, (option, amount)) in options.iter().enumerate() {
+            // the last option takes the rounding remainder
+            let weight = if index + 1 == options.len() {
+                remaining
+            } else {
+                Decimal::from_ratio(*amount, total)
+            };
+            remaining = decimal_subtraction_in_256(remaining, weight);
+            weighted.push((*option, weight));
+        }
+        Ok(weighted)
+    }
+}
+
+/// Protobuf encoding of MsgVoteWeighted, there is no CosmosMsg for weighted votes
+pub fn encode_msg_vote_weighted(
+    proposal_id: u64,
+    voter: &str,
+    options: &[(VoteOption, Decimal)],
+) -> Binary {
+    let mut buf = vec![];
+    encode_varint_field(&mut buf, 1, proposal_id);
+    encode_bytes_field(&mut buf, 2, voter.as_bytes());
+    for (option, weight) in options {
+        let mut option_buf = vec![];
+        encode_varint_field(&mut option_buf, 1, option.proto_value());
+        // sdk.Dec is encoded as its integer with 18 decimals
+        let atomics = Uint128::new(1_000_000_000_000_000_000u128) * *weight;
+        encode_bytes_field(&mut option_buf, 2, atomics.to_string().as_bytes());
+        encode_bytes_field(&mut buf, 3, &option_buf);
+    }
+    Binary::from(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn weighted_options_add_up_to_one() {
+        let tally = Tally {
+            yes: Uint128::new(1),
+            no: Uint128::new(1),
+            abstain: Uint128::zero(),
+            no_with_veto: Uint128::new(1),
+        };
+        let options = tally.weighted_options().unwrap();
+        assert_eq!(
+            options,
+            vec![
+                (
+                    VoteOption::Yes,
+                    Decimal::from_str("0.333333333333333333").unwrap()
+                ),
+                (
+                    VoteOption::No,
+                    Decimal::from_str("0.333333333333333333").unwrap()
+                ),
+                (
+                    VoteOption::NoWithVeto,
+                    Decimal::from_str("0.333333333333333334").unwrap()
+                ),
+            ]
+        );
+        assert!(Tally::default().weighted_options().is_err());
+    }
+
+    #[test]
+    fn encode_vote_weighted() {
+        let msg = encode_msg_vote_weighted(
+            300,
+            "hub",
+            &[
+                (VoteOption::Yes, Decimal::percent(50)),
+                (VoteOption::No, Decimal::percent(50)),
+            ],
+        );
+        let mut expected = vec![0x08, 0xac, 0x02, 0x12, 3];
+        expected.extend_from_slice(b"hub");
+        for option in [1u8, 3] {
+            expected.extend_from_slice(&[0x1a, 22, 0x08, option, 0x12, 18]);
+            expected.extend_from_slice(b"500000000000000000");
+        }
+        assert_eq!(msg, Binary::from(expected));
+    }
+}
@@ -1,8 +1,12 @@
-use cosmwasm_std::{Binary, CanonicalAddr, Decimal, Uint128};
+use cosmwasm_std::{Binary, CanonicalAddr, Decimal, StakingMsg, Uint128};
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::gov::{Tally, VoteOption};
+use crate::snapshot::StateEntry;
+use crate::timelock::PendingChange;
+
 pub type UnbondRequest = Vec<(u64, Uint128)>;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -13,7 +17,22 @@ pub struct InstantiateMsg {
     pub peg_recovery_fee: Decimal,
     pub er_threshold: Decimal,
     pub reward_denom: String,
+    /// The first validator bonded to, ignored in the external mode
     pub validator: String,
+    #[serde(default)]
+    pub mode: HubMode,
+}
+
+/// How the hub holds the underlying asset
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HubMode {
+    /// The underlying is delegated to validators, whose rewards are withdrawn
+    #[default]
+    Staking,
+    /// The underlying is kept by the hub, e.g. a bridged asset,
+    /// and its rewards are deposited with RewardTopUp
+    External,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
@@ -27,6 +46,19 @@ pub struct State {
     pub last_processed_batch: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Parameters {
+    pub epoch_period: u64,
+    pub underlying_coin_denom: String,
+    pub unbonding_period: u64,
+    pub peg_recovery_fee: Decimal,
+    pub er_threshold: Decimal,
+    pub reward_denom: String,
+    /// Share of every unbond in basis points kept by the pool, raising the exchange rate
+    #[serde(default)]
+    pub unbond_fee_bps: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub creator: CanonicalAddr,
@@ -35,6 +67,91 @@ pub struct Config {
     pub airdrop_registry_contract: Option<CanonicalAddr>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct CircuitBreaker {
+    /// Largest relative exchange rate move allowed within one operation, none disables the breaker
+    pub max_exchange_rate_change: Option<Decimal>,
+    pub guardian: Option<CanonicalAddr>,
+    pub paused: bool,
+    /// Whether the token also freezes transfers while paused, claims of the reward contract always are
+    #[serde(default)]
+    pub freeze_transfers: bool,
+}
+
+/// What the surplus of the underlying held by the hub is accounted as
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SurplusPolicy {
+    /// Kept liquid in the unbond buffer
+    #[default]
+    Buffer,
+    /// Delegated, raising the exchange rate
+    Bond,
+}
+
+/// Who is allowed to bond, public deployments stay open
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessMode {
+    #[default]
+    Open,
+    /// Only the listed addresses can bond
+    Allowlist,
+    /// The listed addresses cannot bond
+    Denylist,
+}
+
+/// The keeper operations, each kind has its own list of operators
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    UpdateGlobalIndex,
+    CheckSlashing,
+    Rebalance,
+    ClaimAirdrops,
+    RemoveExitedValidators,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::UpdateGlobalIndex => "update_global_index",
+            OperationKind::CheckSlashing => "check_slashing",
+            OperationKind::Rebalance => "rebalance",
+            OperationKind::ClaimAirdrops => "claim_airdrops",
+            OperationKind::RemoveExitedValidators => "remove_exited_validators",
+        }
+    }
+}
+
+/// A cron-style operation run by a keeper
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    UpdateGlobalIndex {},
+    CheckSlashing {},
+    /// Redelegate so that every whitelisted validator holds the same amount
+    Rebalance {},
+    /// Send the hooks to the airdrop registry, the hub must be one of its keepers
+    ClaimAirdrops {
+        airdrop_hooks: Vec<Binary>,
+    },
+    /// Deregister the whitelisted validators that left the active set, moving their stake
+    RemoveExitedValidators {},
+}
+
+impl Op {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Op::UpdateGlobalIndex {} => OperationKind::UpdateGlobalIndex,
+            Op::CheckSlashing {} => OperationKind::CheckSlashing,
+            Op::Rebalance {} => OperationKind::Rebalance,
+            Op::ClaimAirdrops { .. } => OperationKind::ClaimAirdrops,
+            Op::RemoveExitedValidators {} => OperationKind::RemoveExitedValidators,
+        }
+    }
+}
+
 impl State {
     pub fn update_exchange_rate(&mut self, total_issued: Uint128, requested_with_fee: Uint128) {
         let actual_supply = total_issued + requested_with_fee;
@@ -61,11 +178,20 @@ pub enum ExecuteMsg {
         airdrop_registry_contract: Option<String>,
     },
 
-    /// Register receives the reward contract address
+    /// Register receives the reward contract address.
+    /// Proposed as a TimelockedChange while the timelock is active
     RegisterValidator {
         validator: String,
     },
 
+    /// Register up to limit validators of the chain with a commission of at most
+    /// max_commission that are not registered yet, e.g. to bootstrap a new deployment.
+    /// Refused while the timelock is active. Only creator/owner is allowed to execute
+    ImportValidators {
+        max_commission: Decimal,
+        limit: Option<u32>,
+    },
+
     // Remove the validator from validators whitelist
     DeregisterValidator {
         validator: String,
@@ -77,8 +203,202 @@ pub enum ExecuteMsg {
         unbonding_period: Option<u64>,
         peg_recovery_fee: Option<Decimal>,
         er_threshold: Option<Decimal>,
+        #[serde(default)]
+        unbond_fee_bps: Option<u64>,
+    },
+
+    /// Queue a sensitive change, it can be applied once the timelock period has passed
+    ProposePendingChange {
+        change: TimelockedChange,
+    },
+
+    /// Apply a queued change whose timelock period has passed
+    ApplyPendingChange {
+        id: u64,
+    },
+
+    /// Drop a queued change
+    CancelPendingChange {
+        id: u64,
+    },
+
+    /// Set the allowed exchange rate move and the guardian reviewing a tripped circuit breaker.
+    /// A none max_exchange_rate_change disables the breaker
+    UpdateCircuitBreaker {
+        max_exchange_rate_change: Option<Decimal>,
+        guardian: Option<String>,
+        #[serde(default)]
+        freeze_transfers: bool,
+    },
+
+    /// Check peg recovery and the reward swaps against the prices of an oracle.
+    /// A none oracle_contract disables the checks
+    UpdatePriceGuard {
+        oracle_contract: Option<String>,
+        max_deviation: Decimal,
+    },
+
+    /// Deposit the rewards of the external mode, they are distributed on the next index update
+    RewardTopUp {},
+
+    /// Set the cw20-ics20 bridge that IbcTransfer hooks are forwarded to.
+    /// A none bridge_contract disables the transfers
+    UpdateIbcBridge {
+        bridge_contract: Option<String>,
+    },
+
+    /// Set the wormhole core contract a receipt of every withdrawn unbond is published to,
+    /// the wormhole fee is paid out of the withdrawal. A none core_contract disables the receipts
+    UpdateWormhole {
+        core_contract: Option<String>,
+    },
+
+    /// Pay a bounty to the callers of UpdateGlobalIndex and CheckSlashing out of a reserve the
+    /// reward contract holds back from the rewards, when the index update has rewards to
+    /// distribute or the check finds a slash. None stops paying. Proposed as a
+    /// TimelockedChange while the timelock is active. Only creator/owner is allowed to execute
+    UpdateKeeperIncentive {
+        incentive: Option<KeeperIncentive>,
+    },
+
+    /// Let bonds send the current batch to undelegation once the epoch period passed, when it
+    /// takes at most max_undelegations undelegate messages. None leaves the batches to the unbonds.
+    /// Only creator/owner is allowed to execute
+    UpdateEpochAutoAdvance {
+        max_undelegations: Option<u32>,
+    },
+
+    /// Refuse the bonds of less than min_bond_amount of the underlying, None accepts any bond
+    /// minting bAsset. Only creator/owner is allowed to execute
+    UpdateMinBondAmount {
+        min_bond_amount: Option<Uint128>,
+    },
+
+    /// Claim the rewards of a user unbonding all of its balance from the reward contract as
+    /// part of the unbond, so that they are not left behind. A failing claim does not revert
+    /// the unbond. Only creator/owner is allowed to execute
+    UpdateAutoClaim {
+        enabled: bool,
+    },
+
+    /// Send every amount of the hub and the reward contract without deducting the tax, once
+    /// the chain removed it. Only creator/owner is allowed to execute
+    UpdateTaxMode {
+        tax_free: bool,
+    },
+
+    /// Cap the stake a rebalance redelegates to each validator at max_growth per epoch, so a
+    /// freshly whitelisted validator ramps up over several epochs. None lifts the cap.
+    /// Only creator/owner is allowed to execute
+    UpdateRampUp {
+        max_growth: Option<Uint128>,
+    },
+
+    /// Let the unbond requests of less than max_request of the underlying be withdrawn from
+    /// the unbond buffer before their batch is released. None leaves every request to its
+    /// release. Only creator/owner is allowed to execute
+    UpdateUnbondPriority {
+        max_request: Option<Uint128>,
+    },
+
+    /// Add the underlying sent to the unbond buffer the small requests are withdrawn from.
+    /// Only creator/owner is allowed to execute
+    FundUnbondBuffer {},
+
+    /// Keep the unbond buffer at target_rate of the total bonded, each bond below the target
+    /// leaves skim_rate of itself undelegated in the buffer. None stops the skimming.
+    /// Only creator/owner is allowed to execute
+    UpdateBufferTarget {
+        target: Option<BufferTarget>,
+    },
+
+    /// Ask the insurance fund to cover the slashing CheckSlashing detects, up to the
+    /// max_cover of the insurance, before the exchange rate drops by it. None leaves the
+    /// slashing to the holders. Only creator/owner is allowed to execute
+    UpdateInsurance {
+        insurance: Option<Insurance>,
+    },
+
+    /// Set what ReconcileSurplus does with the underlying sent to the hub outside of its
+    /// operations, e.g. tips. Only creator/owner is allowed to execute
+    UpdateSurplusPolicy {
+        policy: SurplusPolicy,
     },
 
+    /// Bond the surplus of the underlying or add it to the unbond buffer, following the
+    /// surplus policy. Refused while a matured batch waits for its release, which takes the
+    /// balance received. Only creator/owner and the operator are allowed to execute
+    ReconcileSurplus {},
+
+    /// Open a gov proposal to the signals of the bAsset holders, weighted by their balance
+    /// at snapshot_height. Only the owner is allowed to execute
+    RegisterProposal {
+        proposal_id: u64,
+        snapshot_height: u64,
+        /// Signals are accepted and cast until this time, in seconds
+        end_time: u64,
+    },
+
+    /// Signal a vote with the bAsset held at the snapshot height of the proposal,
+    /// a new signal replaces the previous one
+    SignalVote {
+        proposal_id: u64,
+        vote: VoteOption,
+    },
+
+    /// Vote with the delegations of the hub, split by the signalled bAsset
+    /// Permissionless
+    CastVote {
+        proposal_id: u64,
+    },
+
+    /// Pause the hub, the token and the reward contract follow it.
+    /// Only the guardian, or the owner when there is no guardian, is allowed to execute
+    Pause {},
+
+    /// Resume the contract after the circuit breaker paused it.
+    /// Only the guardian, or the owner when there is no guardian, is allowed to execute
+    Resume {},
+
+    /// Emit staking messages to untangle the delegations while the contract is paused.
+    /// Delegations can only go to whitelisted validators, the funds never leave the hub
+    ExecuteEmergency {
+        msgs: Vec<StakingMsg>,
+    },
+
+    /// Set who is allowed to bond, the access list is kept when switching modes
+    UpdateAccessMode {
+        mode: AccessMode,
+    },
+
+    /// Add and remove addresses of the access list
+    UpdateAccessList {
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+    },
+
+    /// Set or remove the operator, allowed to run every keeper operation, to manage their
+    /// operators and to register validators. Only creator/owner and the operator are allowed
+    UpdateOperator {
+        operator: Option<String>,
+    },
+
+    /// Add and remove the operators allowed to run one kind of keeper operation
+    UpdateOperators {
+        kind: OperationKind,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+    },
+
+    /// Write a page of entries exported by ExportState, to recover the state on a fresh code id.
+    /// Only allowed before the first bond, the first page clears the instantiated state
+    ImportState {
+        entries: Vec<StateEntry>,
+    },
+
+    /// Close the import once every page is written, no state can be imported after it
+    FinishImport {},
+
     ////////////////////
     /// User's operations
     ////////////////////
@@ -90,6 +410,21 @@ pub enum ExecuteMsg {
         validator: String,
     },
 
+    /// Bond like Bond, then send the minted bAsset to target_contract with msg as the cw20
+    /// hook. The hub is the sender seen by the target
+    BondAndExecute {
+        validator: String,
+        target_contract: String,
+        msg: Binary,
+    },
+
+    /// Bond like Bond for custodial depositors, the minted bAsset is split among the
+    /// recipients in proportion to their amounts, which must add up to the attached coins
+    BondBatch {
+        validator: String,
+        allocations: Vec<(String, Uint128)>,
+    },
+
     /// Update global index
     UpdateGlobalIndex {
         airdrop_hooks: Option<Vec<Binary>>,
@@ -101,6 +436,13 @@ pub enum ExecuteMsg {
     /// Check whether the slashing has happened or not
     CheckSlashing {},
 
+    /// Run keeper operations in order, the sender must be an operator of every kind it runs.
+    /// Operators are meant to be accounts granting the execution to bot keys through x/authz,
+    /// so that the bot keys rotate without touching the contract
+    RunOperations {
+        ops: Vec<Op>,
+    },
+
     ////////////////////
     /// bAsset's operations
     ///////////////////
@@ -116,16 +458,43 @@ pub enum ExecuteMsg {
     ClaimAirdrop {
         airdrop_token_contract: String, // Contract address of MIR Cw20 Token
         airdrop_contract: String,       // Contract address of MIR Airdrop
-        airdrop_swap_contract: String,  // E.g. Contract address of MIR <> UST Terraswap Pair
         claim_msg: Binary,              // Base64-encoded JSON of MIRAirdropHandleMsg::Claim
-        swap_msg: Binary,               // Base64-encoded string of JSON of PairHandleMsg::Swap
     },
 
-    /// Swaps claimed airdrop tokens to UST through Terraswap & sends resulting UST to bLuna Reward contract
-    SwapHook {
+    /// Forwards claimed airdrop tokens to the airdrop registry to be converted into rewards
+    ForwardAirdropHook {
         airdrop_token_contract: String, // E.g. contract address of MIR Token
-        airdrop_swap_contract: String,  // E.g. Contract address of MIR <> UST Terraswap Pair
-        swap_msg: Binary,               // E.g. Base64-encoded JSON of PairHandleMsg::Swap
+    },
+}
+
+/// Changes that must go through the timelock once the timelock period is not zero
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelockedChange {
+    UpdateParams {
+        unbonding_period: Option<u64>,
+        peg_recovery_fee: Option<Decimal>,
+        er_threshold: Option<Decimal>,
+        #[serde(default)]
+        unbond_fee_bps: Option<u64>,
+    },
+    RegisterValidator {
+        validator: String,
+    },
+    DeregisterValidator {
+        validator: String,
+    },
+    UpdateTimelockPeriod {
+        timelock_period: u64,
+    },
+    UpdateConfig {
+        owner: Option<String>,
+        reward_contract: Option<String>,
+        token_contract: Option<String>,
+        airdrop_registry_contract: Option<String>,
+    },
+    UpdateKeeperIncentive {
+        incentive: Option<KeeperIncentive>,
     },
 }
 
@@ -140,19 +509,121 @@ pub enum QueryMsg {
         address: String,
     },
     Parameters {},
+    /// Paged by batch id, in the order of the encoded batch ids
     UnbondRequests {
         address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The bAsset of address and its unbond requests valued in the underlying, in one query
+    /// for the money market overseer
+    CollateralInfo {
+        address: String,
     },
     AllHistory {
         start_from: Option<u64>,
         limit: Option<u32>,
     },
+    PendingChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    CircuitBreaker {},
+    PriceGuard {},
+    IbcBridge {},
+    Wormhole {},
+    KeeperIncentive {},
+    EpochAutoAdvance {},
+    MinBondAmount {},
+    AutoClaim {},
+    TaxMode {},
+    /// The stake a rebalance may still redelegate to each validator in the current epoch
+    RampAllowances {},
+    /// The delegation of each validator against the last rebalance
+    ValidatorDelegations {},
+    /// The requests withdrawn ahead of their release and the buffer they are paid from
+    UnbondPriority {},
+    /// The level of the unbond buffer against its target
+    BufferTarget {},
+    /// The insurance fund and the slashing it is asked to cover
+    Insurance {},
+    /// The surplus of the underlying ReconcileSurplus would account for
+    Surplus {},
+    /// The lifetime peg recovery and unbond fees kept by the pool
+    FeesCollected {},
+    /// The exchange rate averaged over the last window_seconds, weighted by time
+    ExchangeRateTwap {
+        window_seconds: u64,
+    },
+    /// The unbond batches with the underlying still owed to them, paged by batch id
+    UnbondBatches {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The underlying the hub owes to the unbond requests
+    Liquidity {},
+    /// The phase of an unbond batch and when it entered each phase
+    UnbondBatchStatus {
+        batch_id: u64,
+    },
+    /// The rates an unbond batch is paid at and the slashing adjustment they derive
+    UnbondBatchProof {
+        batch_id: u64,
+    },
+    /// The bAsset minted for bonding amount and the exchange rate after the bond
+    SimulateBond {
+        amount: Uint128,
+    },
+    /// The request unbonding amount of bAsset is left with and the exchange rate after it
+    SimulateUnbond {
+        amount: Uint128,
+    },
+    /// The latest bonds, unbonds and withdrawals of address, paged by event id
+    UserHistory {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The config and parameter sets replaced so far, paged by version
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Mode {},
+    /// The live tally of the signals for a proposal
+    Proposal {
+        proposal_id: u64,
+    },
+    /// The access mode and the listed addresses, paged by address
+    AccessControl {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// The operator allowed to run every keeper operation
+    Operator {},
+    /// The operators of one kind of keeper operation, paged by address
+    Operators {
+        kind: OperationKind,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Raw storage entries in key order, paged with the last returned key
+    ExportState {
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
     Unbond {},
+    /// Forward the sent bAsset to the IBC bridge, to be received on another chain
+    IbcTransfer {
+        channel: String,
+        remote_address: String,
+        timeout: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -165,6 +636,24 @@ pub struct UnbondHistory {
     pub released: bool,
 }
 
+/// The data of the Bond, BondAndExecute and BondBatch responses, to be parsed by the calling contracts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BondData {
+    /// Unique among the bonds of the hub
+    pub bond_id: u64,
+    /// After the peg recovery fee
+    pub minted_amount: Uint128,
+    /// The exchange rate the bond was minted at
+    pub exchange_rate: Decimal,
+}
+
+/// The data of the unbond responses, the burnt bAsset is withdrawable once batch_id is released
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondData {
+    pub batch_id: u64,
+    pub burned_amount: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
     pub exchange_rate: Decimal,
@@ -184,6 +673,24 @@ pub struct ConfigResponse {
     pub airdrop_registry_contract: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralInfoResponse {
+    pub address: String,
+    pub exchange_rate: Decimal,
+    /// The bAsset held by address
+    pub balance: Uint128,
+    /// balance at the exchange rate, in the underlying
+    pub balance_value: Uint128,
+    /// The bAsset burnt by the unbond requests of address not withdrawn yet
+    pub unbonding: Uint128,
+    /// The underlying the requests pay, at the withdraw rate once their batch is released
+    pub unbonding_value: Uint128,
+    /// Only the first MAX_WITHDRAW_REQUESTS requests are counted, the others are left for
+    /// the UnbondRequests query
+    #[serde(default)]
+    pub more_requests: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct WhitelistedValidatorsResponse {
     pub validators: Vec<String>,
@@ -209,3 +716,343 @@ pub struct UnbondRequestsResponse {
 pub struct AllHistoryResponse {
     pub history: Vec<UnbondHistory>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChangesResponse {
+    pub timelock_period: u64,
+    pub changes: Vec<PendingChange<TimelockedChange>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreakerResponse {
+    pub max_exchange_rate_change: Option<Decimal>,
+    pub guardian: Option<String>,
+    pub paused: bool,
+    pub freeze_transfers: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceGuardResponse {
+    pub oracle_contract: Option<String>,
+    pub max_deviation: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalResponse {
+    pub proposal_id: u64,
+    pub snapshot_height: u64,
+    pub end_time: u64,
+    pub tally: Tally,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ModeResponse {
+    pub mode: HubMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcBridgeResponse {
+    pub bridge_contract: Option<String>,
+}
+
+/// The largest bounty of a keeper, in the smallest unit of the reward denom
+pub const MAX_KEEPER_BOUNTY: u128 = 10_000_000;
+/// The largest keeper reserve the reward contract holds back from the rewards
+pub const MAX_KEEPER_RESERVE: u128 = 1_000_000_000;
+
+/// The bounty of the permissionless maintenance handlers, in the reward denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeeperIncentive {
+    pub bounty: Uint128,
+    /// The callers after max_per_block bounties were paid in a block are not paid
+    pub max_per_block: u32,
+    /// The blocks after a paid call during which the same operation is not paid again
+    #[serde(default)]
+    pub min_blocks: u64,
+    /// The reward contract keeps up to this amount of every index update in its reserve
+    pub reserve: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeeperIncentiveResponse {
+    pub incentive: Option<KeeperIncentive>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochAutoAdvanceResponse {
+    pub max_undelegations: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MinBondAmountResponse {
+    pub min_bond_amount: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AutoClaimResponse {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TaxModeResponse {
+    /// Whether the tax is left out of the amounts sent by the hub and the reward contract
+    pub tax_free: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RampAllowance {
+    pub validator: String,
+    /// Redelegated to the validator by rebalances in the current epoch
+    pub received: Uint128,
+    /// None without a ramp up cap
+    pub allowance: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RampAllowancesResponse {
+    pub max_growth: Option<Uint128>,
+    pub allowances: Vec<RampAllowance>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorDelegation {
+    pub validator: String,
+    /// The stake the last rebalance moved the validator to, none before one
+    pub target: Option<Uint128>,
+    pub delegated: Uint128,
+    /// Delegated to the validator right after the last rebalance, none before one
+    pub rebalanced: Option<Uint128>,
+    /// The stake the bonds, unbonds and slashing moved since the last rebalance
+    pub gained: Uint128,
+    pub lost: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorDelegationsResponse {
+    /// The time of the last rebalance in seconds, none before one
+    pub rebalanced_at: Option<u64>,
+    pub validators: Vec<ValidatorDelegation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondPriorityResponse {
+    /// The requests of less than max_request are paid ahead, none without priority
+    pub max_request: Option<Uint128>,
+    /// The underlying kept liquid for them, refilled as their batches are released
+    pub buffer: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BufferTarget {
+    pub target_rate: Decimal,
+    pub skim_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BufferTargetResponse {
+    pub target: Option<BufferTarget>,
+    /// The underlying in the unbond buffer
+    pub buffer: Uint128,
+    /// The level the bonds refill the buffer to, zero without a target
+    pub target_amount: Uint128,
+    /// The bonded underlying skimmed into the buffer and still held by it, counted as bonded
+    pub skimmed: Uint128,
+}
+
+/// The insurance fund covering the slashing of the hub
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Insurance {
+    pub contract: String,
+    /// The most the fund is asked to cover of a single slashing
+    pub max_cover: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SurplusResponse {
+    pub policy: SurplusPolicy,
+    /// The balance above the one tracked by the hub and the unbonds still to arrive
+    pub surplus: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InsuranceResponse {
+    pub insurance: Option<Insurance>,
+    /// The slashing left out of the exchange rate until the fund replies
+    pub pending_cover: Uint128,
+    /// The lifetime cover paid by the fund
+    pub covered: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExchangeRateTwapResponse {
+    pub window_seconds: u64,
+    pub twap: Decimal,
+}
+
+/// The fees are in bAsset, they stay in the pool and raise the exchange rate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeesCollectedResponse {
+    pub peg_recovery_fees: Uint128,
+    pub unbond_fees: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WormholeResponse {
+    pub core_contract: Option<String>,
+    /// The number of receipts published so far
+    pub receipts: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondBatch {
+    pub batch_id: u64,
+    /// The burnt bAsset, including the peg recovery fee
+    pub requested: Uint128,
+    pub applied_exchange_rate: Decimal,
+    /// The end of the unbonding period of the batch
+    pub release_time: u64,
+    pub released: bool,
+    /// Estimated with the applied exchange rate until the batch is released
+    pub unclaimed: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondBatchesResponse {
+    pub batches: Vec<UnbondBatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateBondResponse {
+    pub minted: Uint128,
+    pub peg_recovery_fee: Uint128,
+    pub exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateUnbondResponse {
+    pub burned: Uint128,
+    /// The bAsset left after the fees, paid at the exchange rate of the batch
+    pub requested: Uint128,
+    pub peg_recovery_fee: Uint128,
+    pub unbond_fee: Uint128,
+    pub exchange_rate: Decimal,
+    /// The underlying the request is worth at the exchange rate after the unbond
+    pub estimated_release: Uint128,
+}
+
+/// The derivation of the payout of a batch, each request is paid requested * withdraw_rate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondBatchProofResponse {
+    pub batch_id: u64,
+    /// The burnt bAsset, including the peg recovery fee
+    pub requested: Uint128,
+    /// The exchange rate of the undelegation, the batch expected requested * rate
+    pub applied_exchange_rate: Decimal,
+    pub expected: Uint128,
+    /// The applied exchange rate until the release sets it to what the batch received
+    pub withdraw_rate: Decimal,
+    pub released: bool,
+    pub paid: Uint128,
+    /// expected - paid, the slashing of the undelegation borne by the batch
+    pub slashing_adjustment: Uint128,
+}
+
+/// The phases of an unbond batch, in order
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// The current batch, unbond requests are added to it
+    Collecting,
+    /// Undelegated at the end of its epoch, waiting for the unbonding period
+    Undelegating,
+    /// Reconciled with the received underlying, its requests can be withdrawn
+    Released,
+}
+
+impl BatchStatus {
+    pub fn next(self) -> Option<BatchStatus> {
+        match self {
+            BatchStatus::Collecting => Some(BatchStatus::Undelegating),
+            BatchStatus::Undelegating => Some(BatchStatus::Released),
+            BatchStatus::Released => None,
+        }
+    }
+}
+
+/// The transition times are missing for the phases entered before they were recorded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondBatchStatus {
+    pub batch_id: u64,
+    pub status: BatchStatus,
+    pub collecting_time: Option<u64>,
+    pub undelegating_time: Option<u64>,
+    pub released_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidityResponse {
+    /// Released and not withdrawn yet, the hub must keep it liquid
+    pub released_unclaimed: Uint128,
+    /// Owed to the batches still unbonding, estimated with their applied exchange rate
+    pub unbonding: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserAction {
+    /// amount of the underlying bonded for minted bAsset
+    Bond { amount: Uint128, minted: Uint128 },
+    /// amount of bAsset burnt, requested after the peg recovery fee, in batch_id
+    Unbond {
+        amount: Uint128,
+        requested: Uint128,
+        batch_id: u64,
+    },
+    /// amount of the underlying withdrawn from the released batches
+    WithdrawUnbonded { amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserEvent {
+    pub id: u64,
+    pub time: u64,
+    pub height: u64,
+    /// The exchange rate the action was applied at
+    pub exchange_rate: Decimal,
+    pub action: UserAction,
+}
+
+/// A config and parameter set, live until a change replaced it at replaced_height
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigVersion {
+    pub version: u64,
+    pub replaced_height: u64,
+    pub replaced_time: u64,
+    pub config: ConfigResponse,
+    pub params: Parameters,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigHistoryResponse {
+    /// The oldest first, only the latest versions are kept
+    pub versions: Vec<ConfigVersion>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserHistoryResponse {
+    pub address: String,
+    /// The oldest first, only the latest events of an address are kept
+    pub events: Vec<UserEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub kind: OperationKind,
+    pub operators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccessControlResponse {
+    pub mode: AccessMode,
+    pub addresses: Vec<String>,
+}
@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The hook of a cw20-ics20 bridge. The bridge locks the sent cw20 and transfers a voucher
+/// through ICS20, a voucher sent back releases the locked tokens to its receiver.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ics20TransferMsg {
+    /// The local channel to send the voucher on
+    pub channel: String,
+    /// The receiver on the remote chain
+    pub remote_address: String,
+    /// Seconds until the packet times out, the bridge default when unset
+    pub timeout: Option<u64>,
+}
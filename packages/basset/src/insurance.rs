@@ -0,0 +1,12 @@
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The message the hub sends the insurance fund once it detects a slashing
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InsuranceExecuteMsg {
+    /// Send up to amount of the underlying coin to the sender, the hub delegates what it
+    /// receives and reprices the bAsset by the rest
+    RequestCover { amount: Uint128 },
+}
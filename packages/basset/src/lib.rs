@@ -1,10 +1,35 @@
 mod tax_querier;
 
-pub use tax_querier::deduct_tax;
+pub use tax_querier::{
+    deduct_tax, is_tax_exempt, treasury, NoTax, TaxCache, Treasury, TAX_EXEMPT_DENOMS,
+};
 pub mod airdrop;
+pub mod astroport;
 pub mod contract_error;
+pub mod delegation;
+pub mod distribution;
+pub mod gov;
 pub mod hub;
+pub mod ibc;
+pub mod insurance;
+pub mod lock;
+pub mod math;
+pub mod migration;
+#[cfg(any(test, feature = "mock-querier"))]
+pub mod mock;
+pub mod money_market;
+pub mod operator;
+pub mod oracle;
+pub mod pagination;
+mod proto;
+pub mod querier;
 pub mod reward;
+pub mod snapshot;
+pub mod staking;
+pub mod timelock;
+pub mod token;
+pub mod validation;
+pub mod wormhole;
 
 #[cfg(test)]
 mod mock_querier;
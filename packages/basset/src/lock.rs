@@ -0,0 +1,64 @@
+use cosmwasm_std::Storage;
+use cw_storage_plus::Item;
+
+use crate::contract_error::ContractError;
+
+/// Held from the start of a flow of several messages until its final reply, so that the
+/// messages it sends cannot reenter the handlers the lock guards while its update is half done.
+/// A failing flow reverts the lock along with the rest of its state.
+pub struct ExecutionLock<'a> {
+    locked: Item<'a, bool>,
+}
+
+impl<'a> ExecutionLock<'a> {
+    pub const fn new(key: &'a str) -> Self {
+        ExecutionLock {
+            locked: Item::new(key),
+        }
+    }
+
+    /// Fails while a flow holds the lock
+    pub fn assert_unlocked(&self, storage: &dyn Storage) -> Result<(), ContractError> {
+        if self.locked.may_load(storage)?.unwrap_or_default() {
+            return Err(ContractError::Locked {});
+        }
+        Ok(())
+    }
+
+    pub fn lock(&self, storage: &mut dyn Storage) -> Result<(), ContractError> {
+        self.assert_unlocked(storage)?;
+        Ok(self.locked.save(storage, &true)?)
+    }
+
+    /// Called by the final reply of the flow
+    pub fn unlock(&self, storage: &mut dyn Storage) {
+        self.locked.remove(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const LOCK: ExecutionLock = ExecutionLock::new("lock");
+
+    #[test]
+    fn locked_flows_refuse_to_reenter() {
+        let mut storage = MockStorage::new();
+        LOCK.assert_unlocked(&storage).unwrap();
+        LOCK.lock(&mut storage).unwrap();
+
+        assert_eq!(
+            LOCK.assert_unlocked(&storage).unwrap_err(),
+            ContractError::Locked {}
+        );
+        assert_eq!(
+            LOCK.lock(&mut storage).unwrap_err(),
+            ContractError::Locked {}
+        );
+
+        LOCK.unlock(&mut storage);
+        LOCK.lock(&mut storage).unwrap();
+    }
+}
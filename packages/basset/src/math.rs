@@ -0,0 +1,184 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Decimal, StdError, StdResult, Uint128};
+
+/// return a + b
+pub fn decimal_summation_in_256(a: Decimal, b: Decimal) -> Decimal {
+    let a_u256: Decimal256 = a.into();
+    let b_u256: Decimal256 = b.into();
+    let c_u256: Decimal = (b_u256 + a_u256).into();
+    c_u256
+}
+
+/// return a - b
+pub fn decimal_subtraction_in_256(a: Decimal, b: Decimal) -> Decimal {
+    let a_u256: Decimal256 = a.into();
+    let b_u256: Decimal256 = b.into();
+    let c_u256: Decimal = (a_u256 - b_u256).into();
+    c_u256
+}
+
+/// return a * b
+pub fn decimal_multiplication_in_256(a: Decimal, b: Decimal) -> Decimal {
+    let a_u256: Decimal256 = a.into();
+    let b_u256: Decimal256 = b.into();
+    let c_u256: Decimal = (b_u256 * a_u256).into();
+    c_u256
+}
+
+/// return a / b
+pub fn decimal_division_in_256(a: Decimal, b: Decimal) -> StdResult<Decimal> {
+    if b.is_zero() {
+        return Err(StdError::generic_err("Division by zero"));
+    }
+    let a_u256: Decimal256 = a.into();
+    let b_u256: Decimal256 = b.into();
+    let c_u256: Decimal = (a_u256 / b_u256).into();
+    Ok(c_u256)
+}
+
+/// return nominator / denominator
+pub fn decimal_ratio_in_256(nominator: Uint128, denominator: Uint128) -> Decimal {
+    Decimal256::from_ratio(Uint256::from(nominator), Uint256::from(denominator)).into()
+}
+
+/// return a * b, truncated to an integer
+pub fn uint_multiplication_in_256(a: Uint128, b: Decimal) -> StdResult<Uint128> {
+    let c_u256 = Uint256::from(a) * Decimal256::from(b);
+    uint256_to_uint128(c_u256)
+}
+
+/// return a / b, truncated to an integer
+pub fn uint_division_in_256(a: Uint128, b: Decimal) -> StdResult<Uint128> {
+    if b.is_zero() {
+        return Err(StdError::generic_err("Division by zero"));
+    }
+    let c_u256 = Uint256::from(a) / Decimal256::from(b);
+    uint256_to_uint128(c_u256)
+}
+
+/// return the integer part of a
+pub fn decimal_truncation(a: Decimal) -> Uint128 {
+    (Uint256::one() * Decimal256::from(a)).into()
+}
+
+/// Converts a Uint256 to a Uint128, failing instead of panicking on overflow
+pub fn uint256_to_uint128(a: Uint256) -> StdResult<Uint128> {
+    if a > Uint256::from(u128::MAX) {
+        return Err(StdError::generic_err(format!(
+            "{} does not fit in 128 bits",
+            a
+        )));
+    }
+    Ok(a.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{Decimal, Uint128};
+
+    #[test]
+    fn test_decimal_multiplication() {
+        let a = Uint128::new(100);
+        let b = Decimal::from_ratio(Uint128::new(1111111), Uint128::new(10000000));
+        let multiplication =
+            decimal_multiplication_in_256(Decimal::from_ratio(a, Uint128::new(1)), b);
+        assert_eq!(multiplication.to_string(), "11.11111");
+    }
+
+    #[test]
+    fn test_decimal_sumation() {
+        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        let res = decimal_summation_in_256(a, b);
+        assert_eq!(res.to_string(), "0.6");
+    }
+
+    #[test]
+    fn test_decimal_subtraction() {
+        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        let res = decimal_subtraction_in_256(a, b);
+        assert_eq!(res.to_string(), "0.2");
+    }
+
+    #[test]
+    fn test_decimal_multiplication_in_256() {
+        let a = Uint128::new(100);
+        let b = Decimal::from_ratio(Uint128::new(1111111), Uint128::new(10000000));
+        let multiplication =
+            decimal_multiplication_in_256(Decimal::from_ratio(a, Uint128::new(1)), b);
+        assert_eq!(multiplication.to_string(), "11.11111");
+    }
+
+    #[test]
+    fn test_decimal_sumation_in_256() {
+        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        let res = decimal_summation_in_256(a, b);
+        assert_eq!(res.to_string(), "0.6");
+    }
+
+    #[test]
+    fn test_decimal_subtraction_in_256() {
+        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        let res = decimal_subtraction_in_256(a, b);
+        assert_eq!(res.to_string(), "0.2");
+    }
+
+    #[test]
+    fn test_decimal_division_in_256() {
+        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        let res = decimal_division_in_256(a, b).unwrap();
+        assert_eq!(res.to_string(), "2");
+
+        assert_eq!(
+            decimal_division_in_256(a, Decimal::zero()).unwrap_err(),
+            StdError::generic_err("Division by zero")
+        );
+    }
+
+    #[test]
+    fn test_decimal_ratio_in_256() {
+        let res = decimal_ratio_in_256(Uint128::new(1), Uint128::new(3));
+        assert_eq!(res.to_string(), "0.333333333333333333");
+
+        // overflows the 128 bits intermediate product of Decimal::from_ratio
+        let res = decimal_ratio_in_256(Uint128::new(u128::MAX / 2), Uint128::new(u128::MAX));
+        assert_eq!(res.to_string(), "0.499999999999999999");
+    }
+
+    #[test]
+    fn test_uint_multiplication_in_256() {
+        let a = Uint128::new(1000);
+        let b = Decimal::from_ratio(Uint128::new(1), Uint128::new(3));
+        assert_eq!(uint_multiplication_in_256(a, b).unwrap(), Uint128::new(333));
+
+        let res = uint_multiplication_in_256(Uint128::new(u128::MAX), Decimal::percent(200));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_uint_division_in_256() {
+        let a = Uint128::new(100);
+        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
+        assert_eq!(uint_division_in_256(a, b).unwrap(), Uint128::new(500));
+
+        let b = Decimal::from_ratio(Uint128::new(3), Uint128::new(1));
+        assert_eq!(uint_division_in_256(a, b).unwrap(), Uint128::new(33));
+
+        assert_eq!(
+            uint_division_in_256(a, Decimal::zero()).unwrap_err(),
+            StdError::generic_err("Division by zero")
+        );
+    }
+
+    #[test]
+    fn test_decimal_truncation() {
+        let a = Decimal::from_ratio(Uint128::new(2999), Uint128::new(1000));
+        assert_eq!(decimal_truncation(a), Uint128::new(2));
+        assert_eq!(decimal_truncation(Decimal::zero()), Uint128::zero());
+    }
+}
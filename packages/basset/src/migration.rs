@@ -0,0 +1,247 @@
+use cosmwasm_std::{StdResult, Storage};
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::contract_error::ContractError;
+
+/// Version of the storage layout, bumped by every state-upgrade step.
+/// Deployments instantiated before versioning was introduced have no entry and are at version 0.
+const STATE_VERSION: Item<u64> = Item::new("state_version");
+
+/// The migrate message shared by the bAsset suite, so the same proposal can migrate every contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
+/// A state-upgrade step taking the storage from state version `from` to `from + 1`.
+pub struct MigrationStep {
+    pub from: u64,
+    pub upgrade: fn(&mut dyn Storage) -> StdResult<()>,
+}
+
+/// Brings deployments that predate state versioning to version 1, their layout is unchanged.
+pub const VERSIONING_STEP: MigrationStep = MigrationStep {
+    from: 0,
+    upgrade: adopt_versioning,
+};
+
+fn adopt_versioning(_storage: &mut dyn Storage) -> StdResult<()> {
+    Ok(())
+}
+
+/// Stores the cw2 contract info and the state version at instantiation.
+pub fn init_version(
+    storage: &mut dyn Storage,
+    contract_name: &str,
+    contract_version: &str,
+    state_version: u64,
+) -> StdResult<()> {
+    set_contract_version(storage, contract_name, contract_version)?;
+    STATE_VERSION.save(storage, &state_version)
+}
+
+pub fn read_state_version(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(STATE_VERSION.may_load(storage)?.unwrap_or_default())
+}
+
+/// Refuses to run against a state written by another version of the contract.
+pub fn assert_state_version(storage: &dyn Storage, expected: u64) -> Result<(), ContractError> {
+    let found = read_state_version(storage)?;
+    if found != expected {
+        return Err(ContractError::StateVersionMismatch { expected, found });
+    }
+
+    Ok(())
+}
+
+/// Runs the steps needed to bring the state up to `target`, then records
+/// the new cw2 contract info and state version. Returns the previous state version.
+/// The stored contract must be `contract_name`. A deployment that predates state versioning
+/// may have no cw2 info yet, or the one of a contract listed in `legacy_names`.
+pub fn migrate_state(
+    storage: &mut dyn Storage,
+    contract_name: &str,
+    contract_version: &str,
+    legacy_names: &[&str],
+    target: u64,
+    steps: &[MigrationStep],
+) -> Result<u64, ContractError> {
+    let previous = read_state_version(storage)?;
+    assert_contract_name(storage, contract_name, previous == 0, legacy_names)?;
+    if previous > target {
+        return Err(ContractError::MigrationDowngrade {
+            from: previous,
+            to: target,
+        });
+    }
+
+    for from in previous..target {
+        let step = steps
+            .iter()
+            .find(|step| step.from == from)
+            .ok_or(ContractError::MissingMigrationStep { from })?;
+        (step.upgrade)(storage)?;
+    }
+
+    init_version(storage, contract_name, contract_version, target)?;
+
+    Ok(previous)
+}
+
+fn assert_contract_name(
+    storage: &dyn Storage,
+    contract_name: &str,
+    legacy: bool,
+    legacy_names: &[&str],
+) -> Result<(), ContractError> {
+    let found = match get_contract_version(storage) {
+        Ok(info) => info.contract,
+        Err(_) if legacy => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    if found != contract_name && !(legacy && legacy_names.contains(&found.as_str())) {
+        return Err(ContractError::ContractNameMismatch {
+            expected: contract_name.to_string(),
+            found,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdError;
+
+    fn set_flag(storage: &mut dyn Storage) -> StdResult<()> {
+        storage.set(b"flag", b"1");
+        Ok(())
+    }
+
+    fn set_other_flag(storage: &mut dyn Storage) -> StdResult<()> {
+        storage.set(b"other_flag", b"1");
+        Ok(())
+    }
+
+    #[test]
+    fn init_and_assert_version() {
+        let mut storage = MockStorage::new();
+        assert_eq!(read_state_version(&storage).unwrap(), 0);
+
+        init_version(&mut storage, "crates.io:test", "1.0.0", 1).unwrap();
+        assert_eq!(read_state_version(&storage).unwrap(), 1);
+        assert_eq!(
+            get_contract_version(&storage).unwrap().contract,
+            "crates.io:test"
+        );
+
+        assert_state_version(&storage, 1).unwrap();
+        let err = assert_state_version(&storage, 2).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::StateVersionMismatch {
+                expected: 2,
+                found: 1
+            }
+        );
+    }
+
+    #[test]
+    fn migrate_runs_pending_steps() {
+        let steps = [
+            MigrationStep {
+                from: 0,
+                upgrade: set_flag,
+            },
+            MigrationStep {
+                from: 1,
+                upgrade: set_other_flag,
+            },
+        ];
+
+        let mut storage = MockStorage::new();
+        init_version(&mut storage, "crates.io:test", "1.0.0", 1).unwrap();
+        let previous =
+            migrate_state(&mut storage, "crates.io:test", "1.1.0", &[], 2, &steps).unwrap();
+        assert_eq!(previous, 1);
+        assert_eq!(storage.get(b"flag"), None);
+        assert_eq!(storage.get(b"other_flag"), Some(b"1".to_vec()));
+        assert_eq!(read_state_version(&storage).unwrap(), 2);
+        assert_eq!(get_contract_version(&storage).unwrap().version, "1.1.0");
+
+        // an unversioned deployment goes through every step
+        let mut storage = MockStorage::new();
+        migrate_state(&mut storage, "crates.io:test", "1.1.0", &[], 2, &steps).unwrap();
+        assert_eq!(storage.get(b"flag"), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"other_flag"), Some(b"1".to_vec()));
+
+        let err =
+            migrate_state(&mut storage, "crates.io:test", "1.2.0", &[], 3, &steps).unwrap_err();
+        assert_eq!(err, ContractError::MissingMigrationStep { from: 2 });
+
+        let err =
+            migrate_state(&mut storage, "crates.io:test", "1.0.0", &[], 1, &steps).unwrap_err();
+        assert_eq!(err, ContractError::MigrationDowngrade { from: 2, to: 1 });
+    }
+
+    #[test]
+    fn migrate_checks_the_contract_name() {
+        let steps = [VERSIONING_STEP];
+
+        let mut storage = MockStorage::new();
+        init_version(&mut storage, "crates.io:other", "1.0.0", 1).unwrap();
+        let err =
+            migrate_state(&mut storage, "crates.io:test", "1.1.0", &[], 1, &steps).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ContractNameMismatch {
+                expected: "crates.io:test".to_string(),
+                found: "crates.io:other".to_string(),
+            }
+        );
+
+        // a versioned state must carry the cw2 info
+        let mut storage = MockStorage::new();
+        STATE_VERSION.save(&mut storage, &1).unwrap();
+        let err =
+            migrate_state(&mut storage, "crates.io:test", "1.1.0", &[], 1, &steps).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+
+        // a legacy deployment may carry the name of the contract it was built on
+        let mut storage = MockStorage::new();
+        set_contract_version(&mut storage, "crates.io:base", "0.1.0").unwrap();
+        let err =
+            migrate_state(&mut storage, "crates.io:test", "1.1.0", &[], 1, &steps).unwrap_err();
+        assert!(matches!(err, ContractError::ContractNameMismatch { .. }));
+        migrate_state(
+            &mut storage,
+            "crates.io:test",
+            "1.1.0",
+            &["crates.io:base"],
+            1,
+            &steps,
+        )
+        .unwrap();
+        assert_eq!(
+            get_contract_version(&storage).unwrap().contract,
+            "crates.io:test"
+        );
+
+        // once versioned, the legacy name is no longer accepted
+        set_contract_version(&mut storage, "crates.io:base", "0.1.0").unwrap();
+        let err = migrate_state(
+            &mut storage,
+            "crates.io:test",
+            "1.1.0",
+            &["crates.io:base"],
+            1,
+            &steps,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::ContractNameMismatch { .. }));
+    }
+}
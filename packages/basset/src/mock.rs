@@ -0,0 +1,350 @@
+//! Mock queriers emulating the bAsset contracts, for the unit tests of the protocols
+//! integrating them. Enabled by the `mock-querier` feature.
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_slice, to_binary, Addr, Binary, Coin, ContractResult, Decimal, FullDelegation, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, Validator, WasmQuery,
+};
+use cw20::{BalanceResponse, TokenInfoResponse};
+use std::collections::HashMap;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
+
+use crate::hub::{
+    ConfigResponse as HubConfigResponse, Parameters, QueryMsg as HubQueryMsg,
+    StateResponse as HubStateResponse,
+};
+use crate::math::{
+    decimal_multiplication_in_256, decimal_subtraction_in_256, decimal_summation_in_256,
+};
+use crate::reward::{
+    AccruedRewardsResponse, HolderResponse, QueryMsg as RewardQueryMsg,
+    StateResponse as RewardStateResponse,
+};
+use crate::token::{BalanceAtResponse, TokenQueryMsg};
+
+pub const MOCK_HUB_CONTRACT_ADDR: &str = "hub";
+pub const MOCK_REWARD_CONTRACT_ADDR: &str = "reward";
+pub const MOCK_TOKEN_CONTRACT_ADDR: &str = "token";
+
+/// The dependencies of a contract whose querier answers for the mock bAsset contracts
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, BassetMockQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: BassetMockQuerier::new(MockQuerier::new(&[(
+            MOCK_CONTRACT_ADDR,
+            contract_balance,
+        )])),
+    }
+}
+
+/// A delegation of the hub holding the accumulated staking rewards
+pub fn mock_delegation(validator: &str, amount: Coin, rewards: Vec<Coin>) -> FullDelegation {
+    FullDelegation {
+        delegator: Addr::unchecked(MOCK_HUB_CONTRACT_ADDR),
+        validator: validator.to_string(),
+        amount: amount.clone(),
+        can_redelegate: amount,
+        accumulated_rewards: rewards,
+    }
+}
+
+/// Answers the hub, reward and token queries the way the bAsset contracts do,
+/// and the treasury tax queries of Terra. Anything else goes to the base querier
+pub struct BassetMockQuerier {
+    base: MockQuerier<TerraQueryWrapper>,
+    hub_state: HubStateResponse,
+    hub_parameters: Parameters,
+    global_index: Decimal,
+    prev_reward_balance: Uint128,
+    holders: HashMap<String, HolderResponse>,
+    token_balances: HashMap<String, Uint128>,
+    tax_rate: Decimal,
+    tax_caps: HashMap<String, Uint128>,
+}
+
+impl Querier for BassetMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl BassetMockQuerier {
+    pub fn new(base: MockQuerier<TerraQueryWrapper>) -> Self {
+        BassetMockQuerier {
+            base,
+            hub_state: HubStateResponse {
+                exchange_rate: Decimal::one(),
+                total_bond_amount: Uint128::zero(),
+                last_index_modification: 0,
+                prev_hub_balance: Uint128::zero(),
+                actual_unbonded_amount: Uint128::zero(),
+                last_unbonded_time: 0,
+                last_processed_batch: 0,
+            },
+            hub_parameters: Parameters {
+                epoch_period: 30,
+                underlying_coin_denom: "uluna".to_string(),
+                unbonding_period: 210,
+                peg_recovery_fee: Decimal::zero(),
+                er_threshold: Decimal::one(),
+                reward_denom: "uusd".to_string(),
+                unbond_fee_bps: 0,
+            },
+            global_index: Decimal::zero(),
+            prev_reward_balance: Uint128::zero(),
+            holders: HashMap::new(),
+            token_balances: HashMap::new(),
+            tax_rate: Decimal::zero(),
+            tax_caps: HashMap::new(),
+        }
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
+        match request {
+            QueryRequest::Custom(TerraQueryWrapper { route, query_data })
+                if route == &TerraRoute::Treasury =>
+            {
+                match query_data {
+                    TerraQuery::TaxRate {} => reply(&TaxRateResponse {
+                        rate: self.tax_rate,
+                    }),
+                    TerraQuery::TaxCap { denom } => reply(&TaxCapResponse {
+                        cap: self.tax_caps.get(denom).copied().unwrap_or_default(),
+                    }),
+                    _ => unsupported(request),
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == MOCK_HUB_CONTRACT_ADDR =>
+            {
+                self.handle_hub_query(msg)
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == MOCK_REWARD_CONTRACT_ADDR =>
+            {
+                self.handle_reward_query(msg)
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if contract_addr == MOCK_TOKEN_CONTRACT_ADDR =>
+            {
+                self.handle_token_query(msg)
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    fn handle_hub_query(&self, msg: &Binary) -> QuerierResult {
+        match from_slice(msg) {
+            Ok(HubQueryMsg::State {}) => reply(&self.hub_state),
+            Ok(HubQueryMsg::Parameters {}) => reply(&self.hub_parameters),
+            Ok(HubQueryMsg::Config {}) => reply(&HubConfigResponse {
+                owner: "owner".to_string(),
+                reward_contract: Some(MOCK_REWARD_CONTRACT_ADDR.to_string()),
+                token_contract: Some(MOCK_TOKEN_CONTRACT_ADDR.to_string()),
+                airdrop_registry_contract: None,
+            }),
+            _ => unsupported_smart(MOCK_HUB_CONTRACT_ADDR),
+        }
+    }
+
+    fn handle_reward_query(&self, msg: &Binary) -> QuerierResult {
+        match from_slice(msg) {
+            Ok(RewardQueryMsg::State {}) => reply(&RewardStateResponse {
+                global_index: self.global_index,
+                total_balance: self.token_balances.values().sum(),
+                prev_reward_balance: self.prev_reward_balance,
+            }),
+            Ok(RewardQueryMsg::Holder { address }) => reply(&self.holder(address)),
+            Ok(RewardQueryMsg::AccruedRewards { address }) => {
+                let holder = self.holder(address);
+                let accrued = decimal_multiplication_in_256(
+                    decimal_subtraction_in_256(self.global_index, holder.index),
+                    Decimal::from_ratio(holder.balance, Uint128::new(1)),
+                );
+                reply(&AccruedRewardsResponse {
+                    rewards: decimal_summation_in_256(accrued, holder.pending_rewards)
+                        * Uint128::new(1),
+                })
+            }
+            _ => unsupported_smart(MOCK_REWARD_CONTRACT_ADDR),
+        }
+    }
+
+    fn handle_token_query(&self, msg: &Binary) -> QuerierResult {
+        match from_slice(msg) {
+            Ok(TokenQueryMsg::Balance { address }) => reply(&BalanceResponse {
+                balance: self.token_balance(&address),
+            }),
+            Ok(TokenQueryMsg::BalanceAt { address, height }) => reply(&BalanceAtResponse {
+                balance: self.token_balance(&address),
+                height,
+            }),
+            Ok(TokenQueryMsg::TokenInfo {}) => reply(&TokenInfoResponse {
+                name: "bluna".to_string(),
+                symbol: "BLUNA".to_string(),
+                decimals: 6,
+                total_supply: self.token_balances.values().sum(),
+            }),
+            _ => unsupported_smart(MOCK_TOKEN_CONTRACT_ADDR),
+        }
+    }
+
+    fn token_balance(&self, address: &str) -> Uint128 {
+        self.token_balances
+            .get(address)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// A holder the reward contract never saw holds its token balance at the global index
+    fn holder(&self, address: String) -> HolderResponse {
+        self.holders
+            .get(&address)
+            .cloned()
+            .unwrap_or_else(|| HolderResponse {
+                balance: self.token_balance(&address),
+                index: self.global_index,
+                pending_rewards: Decimal::zero(),
+                address,
+            })
+    }
+
+    // configure the exchange rate and the bonded amount of the hub
+    pub fn with_hub_state(&mut self, exchange_rate: Decimal, total_bond_amount: Uint128) {
+        self.hub_state.exchange_rate = exchange_rate;
+        self.hub_state.total_bond_amount = total_bond_amount;
+    }
+
+    // configure the parameters of the hub
+    pub fn with_hub_parameters(&mut self, parameters: Parameters) {
+        self.hub_parameters = parameters;
+    }
+
+    // configure the bAsset balances, the supply is their sum
+    pub fn with_token_balances(&mut self, balances: &[(&str, Uint128)]) {
+        self.token_balances = balances
+            .iter()
+            .map(|(address, balance)| (address.to_string(), *balance))
+            .collect();
+    }
+
+    // configure the global index and the last reward balance of the reward contract
+    pub fn with_reward_index(&mut self, global_index: Decimal, prev_reward_balance: Uint128) {
+        self.global_index = global_index;
+        self.prev_reward_balance = prev_reward_balance;
+    }
+
+    // configure the index and the pending rewards of a holder
+    pub fn with_holder(&mut self, address: &str, index: Decimal, pending_rewards: Decimal) {
+        let holder = HolderResponse {
+            address: address.to_string(),
+            balance: self.token_balance(address),
+            index,
+            pending_rewards,
+        };
+        self.holders.insert(address.to_string(), holder);
+    }
+
+    // configure the treasury tax
+    pub fn with_tax(&mut self, rate: Decimal, caps: &[(&str, Uint128)]) {
+        self.tax_rate = rate;
+        self.tax_caps = caps
+            .iter()
+            .map(|(denom, cap)| (denom.to_string(), *cap))
+            .collect();
+    }
+
+    // configure the validators and the delegations, see mock_delegation
+    pub fn with_delegations(&mut self, validators: &[Validator], delegations: &[FullDelegation]) {
+        let denom = delegations
+            .first()
+            .map(|delegation| delegation.amount.denom.clone())
+            .unwrap_or_else(|| self.hub_parameters.underlying_coin_denom.clone());
+        self.base.update_staking(&denom, validators, delegations);
+    }
+}
+
+fn reply<T: serde::Serialize>(response: &T) -> QuerierResult {
+    SystemResult::Ok(ContractResult::from(to_binary(response)))
+}
+
+fn unsupported(request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
+    SystemResult::Err(SystemError::UnsupportedRequest {
+        kind: format!("{:?}", request),
+    })
+}
+
+fn unsupported_smart(contract_addr: &str) -> QuerierResult {
+    SystemResult::Err(SystemError::UnsupportedRequest {
+        kind: format!("smart query to {}", contract_addr),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deduct_tax;
+    use crate::querier::{query_exchange_rate, query_reward_holder, query_token_supply};
+    use cosmwasm_std::{coin, QuerierWrapper, StdResult};
+
+    #[test]
+    fn mock_queriers_answer_like_the_contracts() -> StdResult<()> {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .with_hub_state(Decimal::percent(98), Uint128::new(1000));
+        deps.querier
+            .with_token_balances(&[("alice", Uint128::new(300)), ("bob", Uint128::new(700))]);
+        deps.querier
+            .with_reward_index(Decimal::percent(50), Uint128::new(500));
+        deps.querier
+            .with_holder("alice", Decimal::percent(20), Decimal::zero());
+        deps.querier
+            .with_tax(Decimal::percent(1), &[("uusd", Uint128::new(1000))]);
+        deps.querier.with_delegations(
+            &[],
+            &[mock_delegation(
+                "validator",
+                coin(1000, "uluna"),
+                vec![coin(10, "uusd")],
+            )],
+        );
+
+        let querier = QuerierWrapper::new(&deps.querier);
+        assert_eq!(
+            query_exchange_rate(&querier, MOCK_HUB_CONTRACT_ADDR)?,
+            Decimal::percent(98)
+        );
+        assert_eq!(
+            query_token_supply(&querier, MOCK_TOKEN_CONTRACT_ADDR)?,
+            Uint128::new(1000)
+        );
+        let alice = query_reward_holder(&querier, MOCK_REWARD_CONTRACT_ADDR, "alice")?;
+        assert_eq!(alice.balance, Uint128::new(300));
+        assert_eq!(alice.index, Decimal::percent(20));
+        let rewards: AccruedRewardsResponse = querier.query_wasm_smart(
+            MOCK_REWARD_CONTRACT_ADDR,
+            &RewardQueryMsg::AccruedRewards {
+                address: "alice".to_string(),
+            },
+        )?;
+        assert_eq!(rewards.rewards, Uint128::new(90));
+        assert_eq!(deduct_tax(&querier, coin(100, "uusd"))?, coin(99, "uusd"));
+        let delegation = querier
+            .query_delegation(MOCK_HUB_CONTRACT_ADDR, "validator")?
+            .unwrap();
+        assert_eq!(delegation.accumulated_rewards, vec![coin(10, "uusd")]);
+        Ok(())
+    }
+}
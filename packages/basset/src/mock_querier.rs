@@ -1,15 +1,13 @@
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
 use cosmwasm_std::{
-    from_slice, to_binary, AllBalanceResponse, Api, BalanceResponse, BankQuery, CanonicalAddr,
-    Coin, ContractResult, Decimal, OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError,
+    from_slice, to_binary, AllBalanceResponse, Api, BalanceResponse, BankQuery, Coin,
+    ContractResult, Decimal, OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError,
     SystemResult, Uint128, WasmQuery,
 };
 use cosmwasm_storage::to_length_prefixed;
 use std::collections::HashMap;
 
 use crate::hub::Config;
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 
 pub const MOCK_CONTRACT_ADDR: &str = "cosmos2contract";
@@ -164,11 +162,6 @@ impl WasmMockQuerier {
     }
 }
 
-#[derive(Clone, Default)]
-pub struct TokenQuerier {
-    balances: HashMap<String, HashMap<String, Uint128>>,
-}
-
 impl WasmMockQuerier {
     pub fn new<A: Api>(base: MockQuerier<TerraQueryWrapper>, _api: A) -> Self {
         WasmMockQuerier {
@@ -182,21 +175,3 @@ impl WasmMockQuerier {
         self.tax_querier = TaxQuerier::new(rate, caps);
     }
 }
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct TokenInfo {
-    pub name: String,
-    pub symbol: String,
-    pub decimals: u8,
-    pub total_supply: Uint128,
-    pub mint: Option<MinterData>,
-    pub owner: CanonicalAddr,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
-pub struct MinterData {
-    pub minter: CanonicalAddr,
-    /// cap is how many more tokens can be issued by the minter
-    pub cap: Option<Uint128>,
-}
@@ -0,0 +1,10 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The message of the Anchor money market used to deposit rewards into Anchor Earn
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketExecuteMsg {
+    /// Deposit the sent stable coin, the market mints aTerra to the sender
+    DepositStable {},
+}
@@ -0,0 +1,125 @@
+use cosmwasm_std::{Api, CanonicalAddr, StdResult, Storage};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::contract_error::ContractError;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorResponse {
+    pub operator: Option<String>,
+}
+
+/// The hot key of a contract, allowed to run the routine operations so that the owner key
+/// stays cold. Unset until the owner sets it, the owner is always allowed on its own.
+pub struct Operator<'a> {
+    operator: Item<'a, CanonicalAddr>,
+}
+
+impl<'a> Operator<'a> {
+    pub const fn new(key: &'a str) -> Self {
+        Operator {
+            operator: Item::new(key),
+        }
+    }
+
+    pub fn is_operator(&self, storage: &dyn Storage, sender: &CanonicalAddr) -> StdResult<bool> {
+        Ok(self.operator.may_load(storage)? == Some(sender.clone()))
+    }
+
+    /// Fails unless sender is the owner or the operator
+    pub fn assert_allowed(
+        &self,
+        storage: &dyn Storage,
+        owner: &CanonicalAddr,
+        sender: &CanonicalAddr,
+    ) -> Result<(), ContractError> {
+        if sender != owner && !self.is_operator(storage, sender)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        Ok(())
+    }
+
+    /// Rotate the operator, none removes it. The operator may hand its role over itself,
+    /// the owner may replace or remove it at any time
+    pub fn update(
+        &self,
+        storage: &mut dyn Storage,
+        api: &dyn Api,
+        owner: &CanonicalAddr,
+        sender: &CanonicalAddr,
+        operator: Option<String>,
+    ) -> Result<(), ContractError> {
+        self.assert_allowed(storage, owner, sender)?;
+        match operator {
+            Some(operator) => self
+                .operator
+                .save(storage, &api.addr_canonicalize(&operator)?)?,
+            None => self.operator.remove(storage),
+        }
+        Ok(())
+    }
+
+    pub fn query(&self, storage: &dyn Storage, api: &dyn Api) -> StdResult<OperatorResponse> {
+        Ok(OperatorResponse {
+            operator: self
+                .operator
+                .may_load(storage)?
+                .map(|operator| api.addr_humanize(&operator))
+                .transpose()?
+                .map(|operator| operator.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+
+    const OPERATOR: Operator = Operator::new("operator");
+
+    #[test]
+    fn operators_rotate_without_the_owner() {
+        let mut storage = MockStorage::new();
+        let api = MockApi::default();
+        let owner = api.addr_canonicalize("owner").unwrap();
+        let hot = api.addr_canonicalize("hot").unwrap();
+        let other = api.addr_canonicalize("other").unwrap();
+
+        assert_eq!(
+            OPERATOR.assert_allowed(&storage, &owner, &hot).unwrap_err(),
+            ContractError::Unauthorized {}
+        );
+        OPERATOR
+            .update(&mut storage, &api, &owner, &owner, Some("hot".to_string()))
+            .unwrap();
+        OPERATOR.assert_allowed(&storage, &owner, &hot).unwrap();
+        OPERATOR.assert_allowed(&storage, &owner, &owner).unwrap();
+
+        // the operator hands its role over, it is not allowed afterwards
+        OPERATOR
+            .update(&mut storage, &api, &owner, &hot, Some("other".to_string()))
+            .unwrap();
+        assert!(OPERATOR.assert_allowed(&storage, &owner, &hot).is_err());
+        assert_eq!(
+            OPERATOR.query(&storage, &api).unwrap(),
+            OperatorResponse {
+                operator: Some("other".to_string())
+            }
+        );
+
+        // the owner removes it
+        assert!(OPERATOR
+            .update(&mut storage, &api, &owner, &hot, None)
+            .is_err());
+        OPERATOR
+            .update(&mut storage, &api, &owner, &owner, None)
+            .unwrap();
+        assert!(!OPERATOR.is_operator(&storage, &other).unwrap());
+        assert_eq!(
+            OPERATOR.query(&storage, &api).unwrap(),
+            OperatorResponse { operator: None }
+        );
+    }
+}
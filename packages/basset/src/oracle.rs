@@ -0,0 +1,92 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    to_binary, CanonicalAddr, Decimal, QuerierWrapper, QueryRequest, StdResult, WasmQuery,
+};
+
+use crate::contract_error::ContractError;
+use crate::math::{decimal_multiplication_in_256, decimal_subtraction_in_256};
+
+/// The price query of the Anchor oracle, any oracle answering it can be configured
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    Price { base: String, quote: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    /// Price of one base in quote
+    pub rate: Decimal,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64,
+}
+
+/// Oracle the prices implied by swaps and peg recovery are checked against
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceGuard {
+    pub oracle_contract: CanonicalAddr,
+    /// Largest relative deviation from the oracle price that is accepted
+    pub max_deviation: Decimal,
+}
+
+pub fn query_oracle_price(
+    querier: &QuerierWrapper,
+    oracle_contract: &str,
+    base: &str,
+    quote: &str,
+) -> StdResult<Decimal> {
+    let res: PriceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_contract.to_string(),
+        msg: to_binary(&OracleQueryMsg::Price {
+            base: base.to_string(),
+            quote: quote.to_string(),
+        })?,
+    }))?;
+    Ok(res.rate)
+}
+
+/// Fails when the implied price is further than max_deviation from the oracle price
+pub fn assert_price_deviation(
+    implied: Decimal,
+    oracle: Decimal,
+    max_deviation: Decimal,
+) -> Result<(), ContractError> {
+    let deviation = if implied > oracle {
+        decimal_subtraction_in_256(implied, oracle)
+    } else {
+        decimal_subtraction_in_256(oracle, implied)
+    };
+    if deviation > decimal_multiplication_in_256(oracle, max_deviation) {
+        return Err(ContractError::PriceDeviation { implied, oracle });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn deviation_is_relative_to_the_oracle_price() {
+        let oracle = Decimal::from_str("2").unwrap();
+        let max_deviation = Decimal::percent(5);
+
+        assert_price_deviation(Decimal::from_str("2.1").unwrap(), oracle, max_deviation).unwrap();
+        assert_price_deviation(Decimal::from_str("1.9").unwrap(), oracle, max_deviation).unwrap();
+        assert_eq!(
+            assert_price_deviation(Decimal::from_str("2.11").unwrap(), oracle, max_deviation)
+                .unwrap_err(),
+            ContractError::PriceDeviation {
+                implied: Decimal::from_str("2.11").unwrap(),
+                oracle
+            }
+        );
+        assert!(
+            assert_price_deviation(Decimal::from_str("1.89").unwrap(), oracle, max_deviation)
+                .is_err()
+        );
+    }
+}
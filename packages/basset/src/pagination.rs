@@ -0,0 +1,72 @@
+use cosmwasm_std::CanonicalAddr;
+use cw_storage_plus::{Bound, U64Key};
+
+// settings for pagination
+pub const MAX_LIMIT: u32 = 30;
+pub const DEFAULT_LIMIT: u32 = 10;
+
+/// The number of entries of a page, the default limit when none is given
+pub fn calc_limit(limit: Option<u32>) -> usize {
+    calc_limit_up_to(limit, MAX_LIMIT)
+}
+
+/// The number of entries of a page, for queries allowing more than the max limit
+pub fn calc_limit_up_to(limit: Option<u32>, max_limit: u32) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(max_limit) as usize
+}
+
+/// The bound after the key of an address, the key is the canonical address
+pub fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Bound> {
+    start_after.map(|address| Bound::exclusive(address.as_slice()))
+}
+
+/// The bound before the key of an address, the key is the canonical address
+pub fn calc_range_end(end_before: Option<CanonicalAddr>) -> Option<Bound> {
+    end_before.map(|address| Bound::exclusive(address.as_slice()))
+}
+
+/// The bound after an id or a height
+pub fn calc_range_start_u64(start_after: Option<u64>) -> Option<Bound> {
+    start_after.map(|id| Bound::exclusive(U64Key::new(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{Api, Order};
+    use cw_storage_plus::Map;
+
+    const ADDRESSES: Map<&[u8], bool> = Map::new("addresses");
+
+    #[test]
+    fn pages_skip_their_bounds() {
+        assert_eq!(calc_limit(None), 10);
+        assert_eq!(calc_limit(Some(100)), 30);
+        assert_eq!(calc_limit_up_to(Some(100), 50), 50);
+
+        let api = MockApi::default();
+        let mut storage = MockStorage::new();
+        let mut addresses: Vec<CanonicalAddr> = ["addr0000", "addr0001", "addr0002", "addr0003"]
+            .iter()
+            .map(|human| api.addr_canonicalize(human).unwrap())
+            .collect();
+        addresses.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        for address in addresses.iter() {
+            ADDRESSES
+                .save(&mut storage, address.as_slice(), &true)
+                .unwrap();
+        }
+
+        let page: Vec<CanonicalAddr> = ADDRESSES
+            .keys(
+                &storage,
+                calc_range_start(Some(addresses[0].clone())),
+                calc_range_end(Some(addresses[3].clone())),
+                Order::Ascending,
+            )
+            .map(CanonicalAddr::from)
+            .collect();
+        assert_eq!(page, addresses[1..3].to_vec());
+    }
+}
@@ -0,0 +1,81 @@
+//! The protobuf wire format of the stargate messages and query responses
+
+use cosmwasm_std::{StdError, StdResult};
+
+pub(crate) fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+pub(crate) fn encode_varint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    encode_varint(buf, field << 3);
+    encode_varint(buf, value);
+}
+
+pub(crate) fn encode_bytes_field(buf: &mut Vec<u8>, field: u64, value: &[u8]) {
+    encode_varint(buf, field << 3 | 2);
+    encode_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn decode_varint(buf: &[u8], pos: &mut usize) -> StdResult<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf
+            .get(*pos)
+            .ok_or_else(|| StdError::generic_err("Truncated protobuf varint"))?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(StdError::generic_err("Invalid protobuf varint"))
+}
+
+/// A decoded field, the numbers of fixed size are skipped
+pub(crate) enum FieldValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// The fields of a protobuf message in wire order
+pub(crate) fn decode_fields(buf: &[u8]) -> StdResult<Vec<(u64, FieldValue<'_>)>> {
+    let mut fields = vec![];
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = decode_varint(buf, &mut pos)?;
+        let value = match key & 7 {
+            0 => FieldValue::Varint(decode_varint(buf, &mut pos)?),
+            1 | 5 => {
+                pos += if key & 7 == 1 { 8 } else { 4 };
+                FieldValue::Fixed
+            }
+            2 => {
+                let len = decode_varint(buf, &mut pos)? as usize;
+                let end = pos
+                    .checked_add(len)
+                    .filter(|end| *end <= buf.len())
+                    .ok_or_else(|| StdError::generic_err("Truncated protobuf field"))?;
+                let bytes = &buf[pos..end];
+                pos = end;
+                FieldValue::Bytes(bytes)
+            }
+            wire_type => {
+                return Err(StdError::generic_err(format!(
+                    "Unsupported protobuf wire type {}",
+                    wire_type
+                )))
+            }
+        };
+        fields.push((key >> 3, value));
+    }
+    if pos > buf.len() {
+        return Err(StdError::generic_err("Truncated protobuf field"));
+    }
+    Ok(fields)
+}
@@ -0,0 +1,127 @@
+use cosmwasm_std::{
+    from_binary, to_binary, to_vec, Binary, ContractResult, Decimal, Empty, QuerierWrapper,
+    QueryRequest, StdError, StdResult, SystemResult, Uint128, WasmQuery,
+};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use serde::de::DeserializeOwned;
+
+use crate::hub::{CircuitBreaker, Parameters, QueryMsg as HubQueryMsg, StateResponse};
+use crate::oracle::PriceGuard;
+use crate::reward::{HolderResponse, QueryMsg as RewardQueryMsg};
+use crate::token::{BalanceAtResponse, TokenQueryMsg};
+
+pub fn query_hub_state(querier: &QuerierWrapper, hub_contract: &str) -> StdResult<StateResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: hub_contract.to_string(),
+        msg: to_binary(&HubQueryMsg::State {})?,
+    }))
+}
+
+pub fn query_hub_parameters(querier: &QuerierWrapper, hub_contract: &str) -> StdResult<Parameters> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: hub_contract.to_string(),
+        msg: to_binary(&HubQueryMsg::Parameters {})?,
+    }))
+}
+
+/// Read raw like the hub config. A breaker the owner never configured is not stored,
+/// which is the default breaker that is not paused.
+pub fn query_hub_circuit_breaker(
+    querier: &QuerierWrapper,
+    hub_contract: &str,
+) -> StdResult<CircuitBreaker> {
+    query_hub_item(querier, hub_contract, b"circuit_breaker")
+}
+
+/// Read raw like the circuit breaker, none when the owner configured no oracle
+pub fn query_hub_price_guard(
+    querier: &QuerierWrapper,
+    hub_contract: &str,
+) -> StdResult<Option<PriceGuard>> {
+    query_hub_item(querier, hub_contract, b"price_guard")
+}
+
+/// Read raw like the circuit breaker, false until the owner flags the chain as tax free
+pub fn query_hub_tax_free(querier: &QuerierWrapper, hub_contract: &str) -> StdResult<bool> {
+    query_hub_item(querier, hub_contract, b"tax_free")
+}
+
+/// An item that is not stored is its default
+fn query_hub_item<T: DeserializeOwned + Default>(
+    querier: &QuerierWrapper,
+    hub_contract: &str,
+    key: &[u8],
+) -> StdResult<T> {
+    let request: QueryRequest<Empty> = QueryRequest::Wasm(WasmQuery::Raw {
+        contract_addr: hub_contract.to_string(),
+        key: Binary::from(key.to_vec()),
+    });
+    match querier.raw_query(&to_vec(&request)?) {
+        SystemResult::Ok(ContractResult::Ok(value)) if value.is_empty() => Ok(Default::default()),
+        SystemResult::Ok(ContractResult::Ok(value)) => from_binary(&value),
+        SystemResult::Ok(ContractResult::Err(err)) => Err(StdError::generic_err(format!(
+            "Querier contract error: {}",
+            err
+        ))),
+        SystemResult::Err(err) => Err(StdError::generic_err(format!(
+            "Querier system error: {}",
+            err
+        ))),
+    }
+}
+
+pub fn query_exchange_rate(querier: &QuerierWrapper, hub_contract: &str) -> StdResult<Decimal> {
+    Ok(query_hub_state(querier, hub_contract)?.exchange_rate)
+}
+
+pub fn query_reward_holder(
+    querier: &QuerierWrapper,
+    reward_contract: &str,
+    address: &str,
+) -> StdResult<HolderResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: reward_contract.to_string(),
+        msg: to_binary(&RewardQueryMsg::Holder {
+            address: address.to_string(),
+        })?,
+    }))
+}
+
+pub fn query_token_balance(
+    querier: &QuerierWrapper,
+    token_contract: &str,
+    address: &str,
+) -> StdResult<Uint128> {
+    let res: BalanceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: token_contract.to_string(),
+        msg: to_binary(&Cw20QueryMsg::Balance {
+            address: address.to_string(),
+        })?,
+    }))?;
+    Ok(res.balance)
+}
+
+/// The balance of address at the beginning of the block at height
+pub fn query_token_balance_at(
+    querier: &QuerierWrapper,
+    token_contract: &str,
+    address: &str,
+    height: u64,
+) -> StdResult<Uint128> {
+    let res: BalanceAtResponse = querier.query_wasm_smart(
+        token_contract,
+        &TokenQueryMsg::BalanceAt {
+            address: address.to_string(),
+            height,
+        },
+    )?;
+    Ok(res.balance)
+}
+
+pub fn query_token_supply(querier: &QuerierWrapper, token_contract: &str) -> StdResult<Uint128> {
+    let res: TokenInfoResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: token_contract.to_string(),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+    }))?;
+    Ok(res.total_supply)
+}
@@ -1,12 +1,22 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Binary, Coin, Decimal, Uint128};
+
+use crate::astroport::AssetInfo;
+use crate::snapshot::StateEntry;
+use crate::timelock::PendingChange;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub hub_contract: String,
+    /// The native denom of the rewards, the address of the token with a cw20 reward_asset
     pub reward_denom: String,
+    /// The native denom or the cw20 token the rewards are paid in, the native denom
+    /// reward_denom when unset. The native rewards of a cw20 are swapped through the
+    /// routes ending in the token
+    #[serde(default)]
+    pub reward_asset: Option<AssetInfo>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -22,10 +32,85 @@ pub enum ExecuteMsg {
     /// Update the global index
     UpdateGlobalIndex {},
 
+    /// Write a page of entries exported by ExportState, to recover the state on a fresh code id.
+    /// Only allowed before the first holder, the first page clears the instantiated state but
+    /// the config, whose hub cannot change. Only the owner of the hub is allowed to execute
+    ImportState { entries: Vec<StateEntry> },
+
+    /// Close the import once every page is written, no state can be imported after it
+    FinishImport {},
+
+    /// Swap offer_denom through the Astroport pairs of hops instead of the market module,
+    /// the last hop must return the reward denom.
+    /// Only the owner of the hub and the operator are allowed to execute
+    RegisterSwapRoute {
+        offer_denom: String,
+        hops: Vec<SwapHop>,
+    },
+
+    /// Swap offer_denom through the market module again.
+    /// Only the owner of the hub and the operator are allowed to execute
+    DeregisterSwapRoute { offer_denom: String },
+
+    /// Set or remove the operator, allowed to manage the swap routes.
+    /// Only the owner of the hub and the operator are allowed to execute
+    UpdateOperator { operator: Option<String> },
+
+    /// Deposit claimed rewards into Anchor Earn for the holders that opted in, or for every
+    /// holder that did not opt out with deposit_by_default. None stops depositing.
+    /// Only the owner of the hub is allowed to execute
+    UpdateEarnConfig { earn: Option<EarnConfig> },
+
+    /// Route a share of the rewards of every index update to the community pool or an
+    /// address, at most 20%. None stops taking the fee. Only the owner of the hub is allowed
+    /// to execute
+    UpdateCommunityFee { fee: Option<CommunityFee> },
+
+    /// Pay every claim the share of the rewards the balance covers while the balance is
+    /// below the rewards owed, instead of refusing the claims it does not cover. The rest
+    /// of a claim stays pending for the next one. Only the owner of the hub is allowed to execute
+    UpdateProRataClaims { enabled: bool },
+
+    /// Swap the whole reward balance to new_denom through the market module and pay the
+    /// rewards in new_denom from then on, the accrued rewards are rebased at the rate the
+    /// swap returned. Only the owner of the hub is allowed to execute
+    MigrateRewardDenom { new_denom: String },
+
+    /// Rebase the rewards once the swap of MigrateRewardDenom returned.
+    /// Only the reward contract itself is allowed to execute
+    CompleteRewardDenomMigration {},
+
+    /// Let hub update the index and fund the keeper reserve next to hub_contract, e.g. a
+    /// second hub sharing the reward pool during a migration window.
+    /// Only the owner of the hub is allowed to execute
+    AddHub { hub: String },
+
+    /// Stop accepting the index updates of hub, hub_contract cannot be removed.
+    /// Only the owner of the hub is allowed to execute
+    RemoveHub { hub: String },
+
+    /// Queue a sensitive change, it can be applied once the timelock period has passed.
+    /// Only the owner of the hub is allowed to execute
+    ProposePendingChange { change: TimelockedChange },
+
+    /// Apply a queued change whose timelock period has passed.
+    /// Only the owner of the hub is allowed to execute
+    ApplyPendingChange { id: u64 },
+
+    /// Drop a queued change. Only the owner of the hub is allowed to execute
+    CancelPendingChange { id: u64 },
+
+    /// Count the holders and their balances in tiers starting at each of min_balances, in
+    /// increasing order. The holders are counted again over the whole holder set, none stops
+    /// counting. Only the owner of the hub is allowed to execute
+    UpdateHolderTiers { min_balances: Option<Vec<Uint128>> },
+
     ////////////////////
     /// bAsset's operations
     ///////////////////
 
+    /// Accept balance updates from token_contract only, the hub registers its token once
+    RegisterTokenContract { token_contract: String },
     /// Increase user staking balance
     /// Withdraw rewards to pending rewards
     /// Set current reward index to global index
@@ -34,6 +119,13 @@ pub enum ExecuteMsg {
     /// Withdraw rewards to pending rewards
     /// Set current reward index to global index
     DecreaseBalance { address: String, amount: Uint128 },
+    /// Keep the rewards not distributed yet in the keeper reserve, up to target
+    FundKeeperReserve { target: Uint128 },
+    /// Pay the bounty of a keeper out of the keeper reserve
+    PayKeeperBounty { recipient: String, amount: Uint128 },
+    /// Claim the rewards of address to its reward recipient, e.g. when it unbonds.
+    /// A holder without rewards claims nothing
+    ClaimRewardsFor { address: String },
 
     ////////////////////
     /// User's operations
@@ -41,6 +133,97 @@ pub enum ExecuteMsg {
 
     /// return the accrued reward in uusd to the user.
     ClaimRewards { recipient: Option<String> },
+
+    /// Claim the rewards swapped into the native denom, paid to the reward recipient of the
+    /// sender. The swap returns at most max_spread less than the oracle rate, 1% without one
+    ClaimRewardsAs {
+        denom: String,
+        max_spread: Option<Decimal>,
+    },
+
+    /// Opt in or out of depositing the claimed rewards into Anchor Earn,
+    /// None follows deposit_by_default
+    UpdateRewardDeposit { deposit: Option<bool> },
+
+    /// Pay the rewards claimed by the sender to recipient when the claim names no recipient,
+    /// e.g. a segregated account. None pays the sender again
+    UpdateRewardRecipient { recipient: Option<String> },
+
+    /// Stop accruing rewards on the balance of the sender from now on, its share accrues to
+    /// recipient instead, e.g. a contract that keeps no reward accounting. None accrues to
+    /// the sender again, the rewards accrued so far stay claimable by it
+    SetRewardRecipient { recipient: Option<AccrualRecipient> },
+
+    /// Add up to limit holders stored before the balance index to it.
+    /// Anyone can execute, until every holder is indexed
+    IndexHolders { limit: Option<u32> },
+
+    /// Move the rewards accrued by address into its pending rewards at the global index,
+    /// without claiming them, e.g. for an escrow passing the rewards on to its users.
+    /// Anyone can execute, the data of the response is the synced HolderResponse
+    SyncHolder { address: String },
+
+    /// Move the rewards accrued by the sender and its deposit preference to new_address,
+    /// e.g. when a custodian rotates its wallet. The balance follows the bAsset, which is
+    /// moved with a transfer of the token
+    MigrateHolder { new_address: String },
+}
+
+/// Changes that must go through the timelock once the timelock period is not zero
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelockedChange {
+    AddHub { hub: String },
+    UpdateEarnConfig { earn: EarnConfig },
+    UpdateCommunityFee { fee: CommunityFee },
+    UpdateTimelockPeriod { timelock_period: u64 },
+}
+
+/// Where the rewards accrued by a holder that opted out of accruing go
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccrualRecipient {
+    /// Added to the pending rewards of address
+    Address { address: String },
+    /// Renounced, distributed to every holder by the next index update
+    Pool {},
+}
+
+/// The Anchor money market claimed rewards are deposited into
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EarnConfig {
+    pub market_contract: String,
+    /// The aTerra token minted by the market for the reward denom
+    pub aterra_contract: String,
+    /// Whether the holders without a preference deposit
+    pub deposit_by_default: bool,
+}
+
+/// The protocol fee taken out of the rewards before they are distributed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CommunityFee {
+    /// The share of the claimed rewards, at most 20%
+    pub rate: Decimal,
+    pub destination: FeeDestination,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeDestination {
+    /// Funded through MsgFundCommunityPool
+    CommunityPool {},
+    Address {
+        address: String,
+    },
+}
+
+/// A swap of a route, offering the denom returned by the previous hop to an Astroport pair
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapHop {
+    pub pair_contract: String,
+    pub ask_denom: String,
+    /// Largest accepted spread from the simulated return, the pair default when unset
+    pub max_spread: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,22 +231,119 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     State {},
+    /// The hub and the token contract allowed to update the balances
+    RegisteredContracts {},
+    /// The hubs allowed to update the index, hub_contract first
+    Hubs {},
+    Operator {},
     AccruedRewards {
         address: String,
     },
+    /// The rewards address was owed when the index was global_index, e.g. at a checkpoint.
+    /// Fails once the balance of address changed after that index
+    AccruedRewardsAt {
+        address: String,
+        global_index: Decimal,
+    },
+    /// The rewards accrued by the current balance of address since the global index was
+    /// since_index, the caller checkpoints since_index whenever the balance changes
+    IndexDelta {
+        address: String,
+        since_index: Decimal,
+    },
     Holder {
         address: String,
     },
+    /// The rewards distributed and not claimed against the sum of the holder claimable
+    /// rewards, over every holder
+    PrecisionReport {},
     Holders {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// The holders with the largest balances, largest first
+    TopHolders {
+        limit: Option<u32>,
+    },
+    /// The holders and their balances in each tier of UpdateHolderTiers
+    HolderDistribution {},
+    SwapRoutes {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// The reports of the most recent SwapToRewardDenom runs
+    SwapHistory {},
+    /// The reward denom migrations so far, the oldest first
+    DenomMigrations {},
+    EarnConfig {},
+    CommunityFee {},
+    /// The lifetime community fees taken, per denom
+    FeesCollected {},
+    KeeperReserve {},
+    /// The reward balance against the rewards owed to the holders
+    Solvency {},
+    /// Whether the claimed rewards of address are deposited into Anchor Earn
+    RewardDeposit {
+        address: String,
+    },
+    /// Where the rewards claimed by address are paid when the claim names no recipient
+    RewardRecipient {
+        address: String,
+    },
+    /// Where the rewards accrued by address go, none when it accrues them itself
+    AccrualRecipient {
+        address: String,
+    },
+    /// The yield over the last period seconds of the index history
+    Apr {
+        period: u64,
+    },
+    /// Raw storage entries in key order, paged with the last returned key
+    ExportState {
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    PendingChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub hub_contract: String,
+    /// The native denom of the rewards, the address of the token with a cw20 reward_asset
     pub reward_denom: String,
+    pub reward_asset: AssetInfo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RegisteredContractsResponse {
+    pub hub_contract: String,
+    /// Unset until the hub registers its token, the token of the hub config is trusted then
+    pub token_contract: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HubsResponse {
+    pub hubs: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChangesResponse {
+    pub timelock_period: u64,
+    pub changes: Vec<PendingChange<TimelockedChange>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AprResponse {
+    /// The index updates the window starts and ends at
+    pub start_time: u64,
+    pub end_time: u64,
+    /// Reward denom accrued by one bAsset within the window
+    pub reward_per_basset: Decimal,
+    /// Annualized relative to the value of the bAsset, none when the hub has no oracle
+    pub apr: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -73,11 +353,34 @@ pub struct StateResponse {
     pub prev_reward_balance: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PrecisionReportResponse {
+    pub holders: u64,
+    /// Distributed into the global index and not claimed yet
+    pub unclaimed: Uint128,
+    /// The rewards of every holder, with the decimals they keep pending
+    pub claimable: Decimal,
+    /// unclaimed - claimable, the rounding residue the truncated index leaves in the pool
+    pub residue: Decimal,
+    /// claimable - unclaimed, left at zero unless the index math credits more than it got
+    pub overclaimed: Decimal,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AccruedRewardsResponse {
     pub rewards: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IndexDeltaResponse {
+    pub address: String,
+    pub balance: Uint128,
+    pub since_index: Decimal,
+    pub global_index: Decimal,
+    /// With decimals, the rewards of balance over the index change
+    pub rewards: Decimal,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct HolderResponse {
     pub address: String,
@@ -90,3 +393,124 @@ pub struct HolderResponse {
 pub struct HoldersResponse {
     pub holders: Vec<HolderResponse>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TopHoldersResponse {
+    pub holders: Vec<HolderResponse>,
+    /// False while holders stored before the balance index are left out
+    pub indexed: bool,
+}
+
+/// The holders with a balance of at least min_balance and below the next tier
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderTier {
+    pub min_balance: Uint128,
+    pub holders: u64,
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderDistributionResponse {
+    /// Empty while no tiers are set
+    pub tiers: Vec<HolderTier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapRouteResponse {
+    pub offer_denom: String,
+    pub hops: Vec<SwapHop>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapRoutesResponse {
+    pub routes: Vec<SwapRouteResponse>,
+}
+
+/// A coin offered by a SwapToRewardDenom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapReport {
+    pub offer_denom: String,
+    pub offer_amount: Uint128,
+    /// Still held by the contract at the follow-up balance check
+    pub remaining: Option<Uint128>,
+    /// Why the swap was skipped or failed
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapRun {
+    pub run_id: u64,
+    pub time: u64,
+    pub swaps: Vec<SwapReport>,
+    /// The reward denom held before the swaps
+    pub reward_balance: Uint128,
+    /// The reward denom returned by the swaps, known once the balance is checked
+    pub returned: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapHistoryResponse {
+    /// The most recent first
+    pub runs: Vec<SwapRun>,
+}
+
+/// A MigrateRewardDenom, the accruals in old_denom are worth rate new_denom each
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomMigration {
+    pub old_denom: String,
+    pub new_denom: String,
+    pub rate: Decimal,
+    /// The global index before the rebase
+    pub global_index: Decimal,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomMigrationsResponse {
+    pub migrations: Vec<DenomMigration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeeperReserveResponse {
+    /// Held back from the holders to pay the keeper bounties
+    pub reserve: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EarnConfigResponse {
+    pub earn: Option<EarnConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CommunityFeeResponse {
+    pub fee: Option<CommunityFee>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeesCollectedResponse {
+    pub fees: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyResponse {
+    /// The reward balance without the keeper reserve
+    pub reward_balance: Uint128,
+    /// Distributed to the holders and not claimed yet
+    pub owed: Uint128,
+    pub pro_rata_claims: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardDepositResponse {
+    pub deposit: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardRecipientResponse {
+    pub recipient: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccrualRecipientResponse {
+    pub recipient: Option<AccrualRecipient>,
+}
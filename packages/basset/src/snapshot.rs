@@ -0,0 +1,242 @@
+use crate::contract_error::ContractError;
+use crate::pagination::calc_limit;
+use cosmwasm_std::{Binary, CanonicalAddr, Order, StdError, StdResult, Storage};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const IMPORT_STATUS_KEY: &str = "import_status";
+/// Missing once the import is closed, for good
+const IMPORT_STATUS: Item<ImportStatus> = Item::new(IMPORT_STATUS_KEY);
+
+/// The import of a snapshot into a fresh contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// Nothing imported yet, the first page clears the instantiated state
+    Open {},
+    /// Only importer writes the next pages, until it finishes the import
+    Importing { importer: CanonicalAddr },
+}
+
+/// A raw storage entry of a contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateEntry {
+    pub key: Binary,
+    pub value: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExportStateResponse {
+    pub entries: Vec<StateEntry>,
+}
+
+/// Pages through the whole contract storage in key order, so that the same state
+/// always exports the same entries.
+pub fn export_state(
+    storage: &dyn Storage,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<ExportStateResponse> {
+    let limit = calc_limit(limit);
+    // the smallest key after start_after is start_after followed by a zero byte
+    let start = start_after.map(|key| {
+        let mut key = key.to_vec();
+        key.push(0);
+        key
+    });
+
+    let entries = storage
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|(key, value)| StateEntry {
+            key: key.into(),
+            value: value.into(),
+        })
+        .collect();
+
+    Ok(ExportStateResponse { entries })
+}
+
+/// Lets a fresh contract import a snapshot, called when it is instantiated
+pub fn open_import(storage: &mut dyn Storage) -> StdResult<()> {
+    IMPORT_STATUS.save(storage, &ImportStatus::Open {})
+}
+
+/// Closes the import for good, e.g. once the contract holds funds of its own
+pub fn close_import(storage: &mut dyn Storage) {
+    IMPORT_STATUS.remove(storage)
+}
+
+pub fn is_importing(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(matches!(
+        IMPORT_STATUS.may_load(storage)?,
+        Some(ImportStatus::Importing { .. })
+    ))
+}
+
+/// The state is inconsistent until every page is imported
+pub fn assert_not_importing(storage: &dyn Storage) -> Result<(), ContractError> {
+    if is_importing(storage)? {
+        return Err(ContractError::ImportInProgress {});
+    }
+    Ok(())
+}
+
+/// Writes the exported entries back, pages can be imported in any order.
+/// The first page removes the state written since the contract was instantiated, except the
+/// keys of keep, and only importer writes the pages after it. The import status of the
+/// snapshot is not imported
+pub fn import_state(
+    storage: &mut dyn Storage,
+    importer: &CanonicalAddr,
+    keep: &[&[u8]],
+    entries: Vec<StateEntry>,
+) -> Result<(), ContractError> {
+    match IMPORT_STATUS.may_load(storage)? {
+        None => return Err(ContractError::ImportClosed {}),
+        Some(ImportStatus::Open {}) => {
+            let stale: Vec<Vec<u8>> = storage
+                .range(None, None, Order::Ascending)
+                .map(|(key, _)| key)
+                .filter(|key| key != IMPORT_STATUS_KEY.as_bytes() && !keep.contains(&&key[..]))
+                .collect();
+            for key in stale {
+                storage.remove(&key);
+            }
+            IMPORT_STATUS.save(
+                storage,
+                &ImportStatus::Importing {
+                    importer: importer.clone(),
+                },
+            )?;
+        }
+        Some(ImportStatus::Importing { importer: current }) => {
+            if current != *importer {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+    }
+
+    for entry in entries {
+        if entry.key.as_slice() != IMPORT_STATUS_KEY.as_bytes() {
+            storage.set(entry.key.as_slice(), entry.value.as_slice());
+        }
+    }
+    Ok(())
+}
+
+/// Closes the import once importer wrote every page
+pub fn finish_import(
+    storage: &mut dyn Storage,
+    importer: &CanonicalAddr,
+) -> Result<(), ContractError> {
+    match IMPORT_STATUS.may_load(storage)? {
+        Some(ImportStatus::Importing { importer: current }) if current == *importer => {
+            close_import(storage);
+            Ok(())
+        }
+        Some(ImportStatus::Importing { .. }) => Err(ContractError::Unauthorized {}),
+        _ => Err(StdError::generic_err("No import is in progress").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let mut storage = MockStorage::new();
+        for i in 0..25u8 {
+            storage.set(&[b'k', i], &[i]);
+        }
+
+        let importer = CanonicalAddr::from(vec![1u8; 20]);
+        let mut restored = MockStorage::new();
+        open_import(&mut restored).unwrap();
+        let mut start_after = None;
+        let mut pages = 0;
+        loop {
+            let page = export_state(&storage, start_after, None).unwrap();
+            if page.entries.is_empty() {
+                break;
+            }
+            start_after = page.entries.last().map(|entry| entry.key.clone());
+            import_state(&mut restored, &importer, &[], page.entries).unwrap();
+            pages += 1;
+        }
+        assert_eq!(pages, 3);
+        finish_import(&mut restored, &importer).unwrap();
+
+        let all = export_state(&storage, None, Some(crate::pagination::MAX_LIMIT)).unwrap();
+        assert_eq!(
+            all,
+            export_state(&restored, None, Some(crate::pagination::MAX_LIMIT)).unwrap()
+        );
+        assert_eq!(all.entries.len(), 25);
+        assert_eq!(
+            all.entries[1],
+            StateEntry {
+                key: Binary::from(vec![b'k', 1]),
+                value: Binary::from(vec![1]),
+            }
+        );
+    }
+
+    #[test]
+    fn import_into_a_fresh_contract_only() {
+        let importer = CanonicalAddr::from(vec![1u8; 20]);
+        let entries = vec![StateEntry {
+            key: Binary::from(b"imported".to_vec()),
+            value: Binary::from(vec![1]),
+        }];
+
+        // a contract instantiated before the imports existed never imports
+        let mut storage = MockStorage::new();
+        assert_eq!(
+            import_state(&mut storage, &importer, &[], entries.clone()).unwrap_err(),
+            ContractError::ImportClosed {}
+        );
+
+        // the first page clears the instantiated state but the kept keys
+        storage.set(b"stale", &[1]);
+        storage.set(b"kept", &[1]);
+        open_import(&mut storage).unwrap();
+        import_state(&mut storage, &importer, &[b"kept"], entries.clone()).unwrap();
+        assert_eq!(storage.get(b"stale"), None);
+        assert_eq!(storage.get(b"kept"), Some(vec![1]));
+        assert_eq!(storage.get(b"imported"), Some(vec![1]));
+        assert_eq!(
+            assert_not_importing(&storage).unwrap_err(),
+            ContractError::ImportInProgress {}
+        );
+
+        // only the importer of the first page continues
+        let other = CanonicalAddr::from(vec![2u8; 20]);
+        assert_eq!(
+            import_state(&mut storage, &other, &[], entries.clone()).unwrap_err(),
+            ContractError::Unauthorized {}
+        );
+        assert_eq!(
+            finish_import(&mut storage, &other).unwrap_err(),
+            ContractError::Unauthorized {}
+        );
+
+        // the status of the snapshot is not imported
+        let status = StateEntry {
+            key: Binary::from(IMPORT_STATUS_KEY.as_bytes().to_vec()),
+            value: Binary::from(b"{\"open\":{}}".to_vec()),
+        };
+        import_state(&mut storage, &importer, &[], vec![status]).unwrap();
+        assert!(is_importing(&storage).unwrap());
+
+        finish_import(&mut storage, &importer).unwrap();
+        assert_not_importing(&storage).unwrap();
+        assert_eq!(
+            import_state(&mut storage, &importer, &[], entries).unwrap_err(),
+            ContractError::ImportClosed {}
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use cosmwasm_std::{
+    to_vec, Binary, ContractResult, Empty, QuerierWrapper, QueryRequest, StdError, StdResult,
+    SystemError, SystemResult,
+};
+
+use crate::proto::{decode_fields, encode_bytes_field, encode_varint_field, FieldValue};
+
+pub const STAKING_PARAMS_QUERY_PATH: &str = "/cosmos.staking.v1beta1.Query/Params";
+
+/// The time past the unbonding time of the chain for the matured undelegations to be paid
+pub const UNBONDING_SAFETY_MARGIN: u64 = 600;
+
+/// The unbonding time of the chain in seconds, none when the chain does not serve the
+/// stargate queries of contracts
+pub fn query_unbonding_time(querier: &QuerierWrapper) -> StdResult<Option<u64>> {
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: STAKING_PARAMS_QUERY_PATH.to_string(),
+        data: Binary::default(),
+    };
+    match querier.raw_query(&to_vec(&request)?) {
+        SystemResult::Ok(ContractResult::Ok(value)) => decode_unbonding_time(&value).map(Some),
+        SystemResult::Ok(ContractResult::Err(err)) => Err(StdError::generic_err(format!(
+            "Querier contract error: {}",
+            err
+        ))),
+        SystemResult::Err(SystemError::UnsupportedRequest { .. }) => Ok(None),
+        SystemResult::Err(err) => Err(StdError::generic_err(format!(
+            "Querier system error: {}",
+            err
+        ))),
+    }
+}
+
+/// The seconds of params.unbonding_time in a protobuf QueryParamsResponse, the fields left
+/// out are zero
+pub fn decode_unbonding_time(response: &[u8]) -> StdResult<u64> {
+    let params = message_field(response, 1)?;
+    let unbonding_time = message_field(params, 1)?;
+    Ok(decode_fields(unbonding_time)?
+        .into_iter()
+        .find_map(|(field, value)| match value {
+            FieldValue::Varint(seconds) if field == 1 => Some(seconds),
+            _ => None,
+        })
+        .unwrap_or_default())
+}
+
+/// The protobuf QueryParamsResponse of an unbonding time, as a chain answers the query
+pub fn encode_unbonding_time(seconds: u64) -> Binary {
+    let mut duration = vec![];
+    encode_varint_field(&mut duration, 1, seconds);
+    let mut params = vec![];
+    encode_bytes_field(&mut params, 1, &duration);
+    let mut response = vec![];
+    encode_bytes_field(&mut response, 1, &params);
+    Binary::from(response)
+}
+
+fn message_field(buf: &[u8], number: u64) -> StdResult<&[u8]> {
+    Ok(decode_fields(buf)?
+        .into_iter()
+        .find_map(|(field, value)| match value {
+            FieldValue::Bytes(bytes) if field == number => Some(bytes),
+            _ => None,
+        })
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbonding_time_of_the_staking_params() {
+        // unbonding_time of 21 days, max_validators 130 and the bond denom
+        let mut duration = vec![];
+        encode_varint_field(&mut duration, 1, 1_814_400);
+        let mut params = vec![];
+        encode_bytes_field(&mut params, 1, &duration);
+        encode_varint_field(&mut params, 2, 130);
+        encode_bytes_field(&mut params, 5, b"uluna");
+        let mut response = vec![];
+        encode_bytes_field(&mut response, 1, &params);
+        assert_eq!(decode_unbonding_time(&response).unwrap(), 1_814_400);
+        assert_eq!(
+            decode_unbonding_time(&encode_unbonding_time(1_814_400)).unwrap(),
+            1_814_400
+        );
+
+        // the default params leave the fields out
+        assert_eq!(decode_unbonding_time(&[]).unwrap(), 0);
+        assert!(decode_unbonding_time(&response[..response.len() - 2]).is_err());
+    }
+}
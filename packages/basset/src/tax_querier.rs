@@ -1,15 +1,89 @@
-use cosmwasm_std::{Coin, Decimal, QuerierWrapper, StdResult, Uint128};
+use cosmwasm_std::{
+    from_slice, to_vec, Coin, Decimal, QuerierWrapper, StdResult, Storage, Uint128,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use terra_cosmwasm::TerraQuerier;
 
 static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
 
-pub fn compute_tax(querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128> {
-    let terra_querier = TerraQuerier::new(querier);
-    let tax_rate: Decimal = (terra_querier.query_tax_rate()?).rate;
-    let tax_cap: Uint128 = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
+static KEY_TAX_CACHE: &[u8] = b"tax_cache";
+
+/// Denoms that are not charged the stability tax
+pub const TAX_EXEMPT_DENOMS: &[&str] = &["uluna"];
+
+/// Returns true if sending the denom is not charged any tax
+pub fn is_tax_exempt(denom: &str) -> bool {
+    TAX_EXEMPT_DENOMS.contains(&denom)
+}
+
+/// The module charging a tax on native transfers. Terra's treasury is queried through the
+/// querier, chains without a treasury use NoTax and never query it.
+pub trait Treasury {
+    fn tax_rate(&self) -> StdResult<Decimal>;
+    fn tax_cap(&self, denom: &str) -> StdResult<Uint128>;
+}
+
+/// The treasury of a chain that removed the tax, every amount is sent unchanged
+pub struct NoTax;
+
+impl Treasury for NoTax {
+    fn tax_rate(&self) -> StdResult<Decimal> {
+        Ok(Decimal::zero())
+    }
+
+    fn tax_cap(&self, _denom: &str) -> StdResult<Uint128> {
+        Ok(Uint128::zero())
+    }
+}
+
+/// The treasury the tax is deducted with, NoTax once the owner of the hub flagged the chain
+/// as tax free
+pub fn treasury<'a>(querier: &'a QuerierWrapper, tax_free: bool) -> &'a dyn Treasury {
+    if tax_free {
+        &NoTax
+    } else {
+        querier
+    }
+}
+
+impl Treasury for QuerierWrapper<'_> {
+    fn tax_rate(&self) -> StdResult<Decimal> {
+        Ok(TerraQuerier::new(self).query_tax_rate()?.rate)
+    }
+
+    fn tax_cap(&self, denom: &str) -> StdResult<Uint128> {
+        Ok(TerraQuerier::new(self)
+            .query_tax_cap(denom.to_string())?
+            .cap)
+    }
+}
+
+pub fn compute_tax<T: Treasury + ?Sized>(treasury: &T, coin: &Coin) -> StdResult<Uint128> {
+    if is_tax_exempt(&coin.denom) {
+        return Ok(Uint128::zero());
+    }
+    let tax_rate = treasury.tax_rate()?;
+    let tax_cap = treasury.tax_cap(&coin.denom)?;
+    calculate_tax(coin.amount, tax_rate, tax_cap)
+}
+
+pub fn deduct_tax<T: Treasury + ?Sized>(treasury: &T, coin: Coin) -> StdResult<Coin> {
+    let tax_amount = compute_tax(treasury, &coin)?;
+    Ok(Coin {
+        denom: coin.denom,
+        amount: (coin.amount.checked_sub(tax_amount))?,
+    })
+}
+
+fn calculate_tax(amount: Uint128, tax_rate: Decimal, tax_cap: Uint128) -> StdResult<Uint128> {
+    if tax_rate.is_zero() || tax_cap.is_zero() {
+        return Ok(Uint128::zero());
+    }
     Ok(std::cmp::min(
-        (coin.amount.checked_sub(coin.amount.multiply_ratio(
+        (amount.checked_sub(amount.multiply_ratio(
             DECIMAL_FRACTION,
             DECIMAL_FRACTION * tax_rate + DECIMAL_FRACTION,
         )))?,
@@ -17,10 +91,97 @@ pub fn compute_tax(querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128>
     ))
 }
 
-pub fn deduct_tax(querier: &QuerierWrapper, coin: Coin) -> StdResult<Coin> {
-    let tax_amount = compute_tax(querier, &coin)?;
-    Ok(Coin {
-        denom: coin.denom,
-        amount: (coin.amount.checked_sub(tax_amount))?,
-    })
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct StoredTaxCache {
+    height: u64,
+    rate: Option<Decimal>,
+    caps: Vec<(String, Uint128)>,
+}
+
+/// Memoizes the tax rate and the tax caps so that deducting tax from many coins
+/// queries the treasury once per value. The results are identical to `deduct_tax`.
+pub struct TaxCache<'a> {
+    treasury: &'a dyn Treasury,
+    rate: Option<Decimal>,
+    caps: HashMap<String, Uint128>,
+}
+
+impl<'a> TaxCache<'a> {
+    pub fn new(treasury: &'a dyn Treasury) -> Self {
+        TaxCache {
+            treasury,
+            rate: None,
+            caps: HashMap::new(),
+        }
+    }
+
+    /// Loads the values cached in the contract storage during the same block.
+    /// The tax rate and caps can not change within a block.
+    pub fn load(treasury: &'a dyn Treasury, storage: &dyn Storage, height: u64) -> Self {
+        let mut cache = TaxCache::new(treasury);
+        if let Some(stored) = storage
+            .get(KEY_TAX_CACHE)
+            .and_then(|data| from_slice::<StoredTaxCache>(&data).ok())
+        {
+            if stored.height == height {
+                cache.rate = stored.rate;
+                cache.caps = stored.caps.into_iter().collect();
+            }
+        }
+        cache
+    }
+
+    /// Stores the cached values so that later executions in the same block can reuse them.
+    pub fn save(&self, storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+        let mut caps: Vec<(String, Uint128)> = self
+            .caps
+            .iter()
+            .map(|(denom, cap)| (denom.clone(), *cap))
+            .collect();
+        caps.sort();
+        storage.set(
+            KEY_TAX_CACHE,
+            &to_vec(&StoredTaxCache {
+                height,
+                rate: self.rate,
+                caps,
+            })?,
+        );
+        Ok(())
+    }
+
+    pub fn tax_rate(&mut self) -> StdResult<Decimal> {
+        if let Some(rate) = self.rate {
+            return Ok(rate);
+        }
+        let rate = self.treasury.tax_rate()?;
+        self.rate = Some(rate);
+        Ok(rate)
+    }
+
+    pub fn tax_cap(&mut self, denom: &str) -> StdResult<Uint128> {
+        if let Some(cap) = self.caps.get(denom) {
+            return Ok(*cap);
+        }
+        let cap = self.treasury.tax_cap(denom)?;
+        self.caps.insert(denom.to_string(), cap);
+        Ok(cap)
+    }
+
+    pub fn compute_tax(&mut self, coin: &Coin) -> StdResult<Uint128> {
+        if is_tax_exempt(&coin.denom) {
+            return Ok(Uint128::zero());
+        }
+        let tax_rate = self.tax_rate()?;
+        let tax_cap = self.tax_cap(&coin.denom)?;
+        calculate_tax(coin.amount, tax_rate, tax_cap)
+    }
+
+    pub fn deduct_tax(&mut self, coin: Coin) -> StdResult<Coin> {
+        let tax_amount = self.compute_tax(&coin)?;
+        Ok(Coin {
+            denom: coin.denom,
+            amount: (coin.amount.checked_sub(tax_amount))?,
+        })
+    }
 }
@@ -1,6 +1,7 @@
-use crate::deduct_tax;
 use crate::mock_querier::mock_dependencies;
-use cosmwasm_std::{Coin, Decimal, Uint128};
+use crate::{deduct_tax, TaxCache, Treasury};
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{Coin, Decimal, StdResult, Uint128};
 
 #[test]
 fn test_deduct_tax() {
@@ -29,3 +30,115 @@ fn test_deduct_tax() {
         }
     );
 }
+
+#[test]
+fn test_tax_cache() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[
+            (&"uusd".to_string(), &Uint128::from(1000000u128)),
+            (&"ukrw".to_string(), &Uint128::from(500u128)),
+        ],
+    );
+
+    let coins = [
+        Coin::new(10000000000u128, "uusd"),
+        Coin::new(50000000u128, "uusd"),
+        Coin::new(50000000u128, "ukrw"),
+        Coin::new(1u128, "ukrw"),
+    ];
+
+    // the cached results match the uncached ones
+    let deps_ref = deps.as_ref();
+    let mut cache = TaxCache::new(&deps_ref.querier);
+    for coin in coins.iter() {
+        assert_eq!(
+            cache.deduct_tax(coin.clone()).unwrap(),
+            deduct_tax(&deps_ref.querier, coin.clone()).unwrap()
+        );
+    }
+
+    let mut storage = MockStorage::default();
+    cache.save(&mut storage, 100).unwrap();
+
+    // the stored values are used within the same block
+    deps.querier.with_tax(
+        Decimal::percent(2),
+        &[(&"uusd".to_string(), &Uint128::from(2000000u128))],
+    );
+    let deps_ref = deps.as_ref();
+    let mut cache = TaxCache::load(&deps_ref.querier, &storage, 100);
+    assert_eq!(cache.tax_rate().unwrap(), Decimal::percent(1));
+    assert_eq!(cache.tax_cap("uusd").unwrap(), Uint128::from(1000000u128));
+
+    // and are refreshed in a new block
+    let mut cache = TaxCache::load(&deps_ref.querier, &storage, 101);
+    assert_eq!(cache.tax_rate().unwrap(), Decimal::percent(2));
+    assert_eq!(cache.tax_cap("uusd").unwrap(), Uint128::from(2000000u128));
+    assert_eq!(
+        cache.deduct_tax(Coin::new(50000000u128, "uusd")).unwrap(),
+        deduct_tax(&deps_ref.querier, Coin::new(50000000u128, "uusd")).unwrap()
+    );
+}
+
+#[test]
+fn test_deduct_tax_exempt() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    // luna is not charged the stability tax
+    assert_eq!(
+        deduct_tax(&deps.as_mut().querier, Coin::new(50000000u128, "uluna")).unwrap(),
+        Coin::new(50000000u128, "uluna")
+    );
+    let deps_ref = deps.as_ref();
+    let mut cache = TaxCache::new(&deps_ref.querier);
+    assert_eq!(
+        cache.deduct_tax(Coin::new(50000000u128, "uluna")).unwrap(),
+        Coin::new(50000000u128, "uluna")
+    );
+
+    // zero tax passes the amount through unchanged
+    deps.querier.with_tax(
+        Decimal::zero(),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    assert_eq!(
+        deduct_tax(&deps.as_mut().querier, Coin::new(50000001u128, "uusd")).unwrap(),
+        Coin::new(50000001u128, "uusd")
+    );
+}
+
+/// The treasury of another chain, charging a flat rate without cap
+struct FlatTreasury;
+
+impl Treasury for FlatTreasury {
+    fn tax_rate(&self) -> StdResult<Decimal> {
+        Ok(Decimal::percent(1))
+    }
+
+    fn tax_cap(&self, _denom: &str) -> StdResult<Uint128> {
+        Ok(Uint128::new(u128::MAX))
+    }
+}
+
+#[test]
+fn test_deduct_tax_with_another_treasury() {
+    assert_eq!(
+        deduct_tax(&FlatTreasury, Coin::new(1010000000u128, "uatom")).unwrap(),
+        Coin::new(1000000000u128, "uatom")
+    );
+    let mut cache = TaxCache::new(&FlatTreasury);
+    assert_eq!(
+        cache
+            .deduct_tax(Coin::new(1010000000u128, "uatom"))
+            .unwrap(),
+        Coin::new(1000000000u128, "uatom")
+    );
+}
@@ -0,0 +1,187 @@
+use cosmwasm_std::{Order, StdResult, Storage};
+use cw_storage_plus::{Item, Map, U64Key};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::contract_error::ContractError;
+use crate::pagination::{calc_limit, calc_range_start_u64};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChange<T> {
+    pub id: u64,
+    pub change: T,
+    /// Block time in seconds from which the change can be applied
+    pub effective_at: u64,
+}
+
+/// Queue of owner-proposed changes that only become applicable once the delay has passed.
+/// A zero delay, the default, lets a proposed change be applied right away.
+pub struct Timelock<'a, T> {
+    delay: Item<'a, u64>,
+    next_id: Item<'a, u64>,
+    pending: Map<'a, U64Key, PendingChange<T>>,
+}
+
+impl<'a, T> Timelock<'a, T> {
+    pub const fn new(delay_key: &'a str, next_id_key: &'a str, pending_namespace: &'a str) -> Self {
+        Timelock {
+            delay: Item::new(delay_key),
+            next_id: Item::new(next_id_key),
+            pending: Map::new(pending_namespace),
+        }
+    }
+}
+
+impl<'a, T> Timelock<'a, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn delay(&self, storage: &dyn Storage) -> StdResult<u64> {
+        Ok(self.delay.may_load(storage)?.unwrap_or_default())
+    }
+
+    pub fn set_delay(&self, storage: &mut dyn Storage, delay: u64) -> StdResult<()> {
+        self.delay.save(storage, &delay)
+    }
+
+    /// Sensitive changes must be proposed instead of applied directly once a delay is set.
+    pub fn is_active(&self, storage: &dyn Storage) -> StdResult<bool> {
+        Ok(self.delay(storage)? > 0)
+    }
+
+    pub fn propose(
+        &self,
+        storage: &mut dyn Storage,
+        now: u64,
+        change: T,
+    ) -> StdResult<PendingChange<T>> {
+        let id = self.next_id.may_load(storage)?.unwrap_or_default();
+        self.next_id.save(storage, &(id + 1))?;
+
+        let pending = PendingChange {
+            id,
+            change,
+            effective_at: now + self.delay(storage)?,
+        };
+        self.pending.save(storage, U64Key::from(id), &pending)?;
+
+        Ok(pending)
+    }
+
+    /// Removes and returns the change once its delay has passed.
+    pub fn take_ready(
+        &self,
+        storage: &mut dyn Storage,
+        now: u64,
+        id: u64,
+    ) -> Result<T, ContractError> {
+        let pending = self.load(storage, id)?;
+        if now < pending.effective_at {
+            return Err(ContractError::TimelockNotExpired {
+                id,
+                effective_at: pending.effective_at,
+            });
+        }
+
+        self.pending.remove(storage, U64Key::from(id));
+        Ok(pending.change)
+    }
+
+    pub fn cancel(&self, storage: &mut dyn Storage, id: u64) -> Result<(), ContractError> {
+        self.load(storage, id)?;
+        self.pending.remove(storage, U64Key::from(id));
+        Ok(())
+    }
+
+    pub fn read_pending(
+        &self,
+        storage: &dyn Storage,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<PendingChange<T>>> {
+        let limit = calc_limit(limit);
+        let start = calc_range_start_u64(start_after);
+
+        self.pending
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| Ok(item?.1))
+            .collect()
+    }
+
+    fn load(&self, storage: &dyn Storage, id: u64) -> Result<PendingChange<T>, ContractError> {
+        self.pending
+            .may_load(storage, U64Key::from(id))?
+            .ok_or(ContractError::PendingChangeNotFound { id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const TIMELOCK: Timelock<String> = Timelock::new("delay", "next_id", "pending");
+
+    #[test]
+    fn propose_and_apply() {
+        let mut storage = MockStorage::new();
+        assert!(!TIMELOCK.is_active(&storage).unwrap());
+
+        // without a delay the change is applicable right away
+        let pending = TIMELOCK
+            .propose(&mut storage, 100, "first".to_string())
+            .unwrap();
+        assert_eq!(pending.effective_at, 100);
+        assert_eq!(
+            TIMELOCK.take_ready(&mut storage, 100, pending.id).unwrap(),
+            "first"
+        );
+
+        TIMELOCK.set_delay(&mut storage, 50).unwrap();
+        assert!(TIMELOCK.is_active(&storage).unwrap());
+
+        let second = TIMELOCK
+            .propose(&mut storage, 100, "second".to_string())
+            .unwrap();
+        let third = TIMELOCK
+            .propose(&mut storage, 120, "third".to_string())
+            .unwrap();
+        assert_eq!(second.id, 1);
+        assert_eq!(third.effective_at, 170);
+        assert_eq!(
+            TIMELOCK.read_pending(&storage, None, None).unwrap(),
+            vec![second.clone(), third.clone()]
+        );
+        assert_eq!(
+            TIMELOCK.read_pending(&storage, Some(1), None).unwrap(),
+            vec![third.clone()]
+        );
+
+        let err = TIMELOCK
+            .take_ready(&mut storage, 149, second.id)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TimelockNotExpired {
+                id: 1,
+                effective_at: 150
+            }
+        );
+        assert_eq!(
+            TIMELOCK.take_ready(&mut storage, 150, second.id).unwrap(),
+            "second"
+        );
+
+        TIMELOCK.cancel(&mut storage, third.id).unwrap();
+        let err = TIMELOCK
+            .take_ready(&mut storage, 200, third.id)
+            .unwrap_err();
+        assert_eq!(err, ContractError::PendingChangeNotFound { id: 2 });
+        assert!(TIMELOCK
+            .read_pending(&storage, None, None)
+            .unwrap()
+            .is_empty());
+    }
+}
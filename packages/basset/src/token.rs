@@ -0,0 +1,134 @@
+use cosmwasm_std::{Binary, Uint128};
+use cw20::{Cw20Coin, Expiration, MinterResponse};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenInitMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_balances: Vec<Cw20Coin>,
+    pub mint: Option<MinterResponse>,
+    pub hub_contract: String,
+}
+
+/// Whether `Send` may call the hook of any contract, or only of the contracts on the
+/// allowlist of the owner, as for a permissioned deployment
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SendMode {
+    #[default]
+    Open,
+    /// Only the hub and the listed contracts can be sent to
+    Restricted,
+}
+
+/// The cw20 messages of the bAsset token, with the send allowlist of its owner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenExecuteMsg {
+    /// Transfer is a base message to move tokens to another account without triggering actions
+    Transfer { recipient: String, amount: Uint128 },
+    /// Transfer the whole balance of the sender, e.g. to sweep a wallet without querying
+    /// its balance first
+    TransferAll { recipient: String },
+    /// Burn is a base message to destroy tokens forever
+    Burn { amount: Uint128 },
+    /// Send is a base message to transfer tokens to a contract and trigger an action
+    /// on the receiving contract.
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Only with the "mintable" extension. If authorized, creates amount new tokens
+    /// and adds to the recipient balance.
+    Mint { recipient: String, amount: Uint128 },
+    /// Only with "approval" extension. Allows spender to access an additional amount tokens
+    /// from the owner's (env.sender) account. If expires is Some(), overwrites current allowance
+    /// expiration with this one.
+    IncreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Only with "approval" extension. Lowers the spender's access of tokens
+    /// from the owner's (env.sender) account by amount. If expires is Some(), overwrites current
+    /// allowance expiration with this one.
+    DecreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Only with "approval" extension. Transfers amount tokens from owner -> recipient
+    /// if `env.sender` has sufficient pre-approval.
+    TransferFrom {
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Only with "approval" extension. Sends amount tokens from owner -> contract
+    /// if `env.sender` has sufficient pre-approval.
+    SendFrom {
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Only with "approval" extension. Destroys tokens forever
+    BurnFrom { owner: String, amount: Uint128 },
+    /// Set the contracts `Send` may call, the allowlist is kept when switching modes.
+    /// Only the owner of the hub
+    UpdateSendMode { mode: SendMode },
+    /// Add and remove contracts of the send allowlist. Only the owner of the hub
+    UpdateSendAllowlist {
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+    },
+}
+
+/// The cw20 queries of the bAsset token, with the balance snapshots of governance votes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenQueryMsg {
+    /// Return type: BalanceResponse
+    Balance { address: String },
+    /// Return type: TokenInfoResponse
+    TokenInfo {},
+    /// Return type: MinterResponse
+    Minter {},
+    /// Return type: AllowanceResponse
+    Allowance { owner: String, spender: String },
+    /// Return type: AllAllowancesResponse
+    AllAllowances {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Return type: AllAccountsResponse
+    AllAccounts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// The balance of address at the beginning of the block at height.
+    /// Return type: BalanceAtResponse
+    BalanceAt { address: String, height: u64 },
+    /// Return type: SendAllowlistResponse
+    SendAllowlist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceAtResponse {
+    pub balance: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SendAllowlistResponse {
+    pub mode: SendMode,
+    pub contracts: Vec<String>,
+}
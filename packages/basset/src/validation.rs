@@ -0,0 +1,131 @@
+use cosmwasm_std::{Addr, Api, MessageInfo};
+
+use crate::contract_error::ContractError;
+
+const MIN_DENOM_LENGTH: usize = 3;
+const MAX_DENOM_LENGTH: usize = 128;
+const IBC_DENOM_PREFIX: &str = "ibc/";
+const IBC_HASH_LENGTH: usize = 64;
+
+/// Validates a native denom against the cosmos-sdk format `[a-zA-Z][a-zA-Z0-9/:._-]{2,127}`.
+/// IBC denoms must be `ibc/` followed by the 64 characters hex hash of the trace.
+pub fn validate_native_denom(denom: &str) -> Result<(), ContractError> {
+    if denom.len() < MIN_DENOM_LENGTH || denom.len() > MAX_DENOM_LENGTH {
+        return Err(invalid_denom(
+            denom,
+            format!(
+                "length must be between {} and {}",
+                MIN_DENOM_LENGTH, MAX_DENOM_LENGTH
+            ),
+        ));
+    }
+
+    let mut chars = denom.chars();
+    let first = chars.next().unwrap();
+    if !first.is_ascii_alphabetic() {
+        return Err(invalid_denom(denom, "must start with a letter".to_string()));
+    }
+    if !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | ':' | '.' | '_' | '-')) {
+        return Err(invalid_denom(
+            denom,
+            "contains invalid characters".to_string(),
+        ));
+    }
+
+    if let Some(hash) = denom.strip_prefix(IBC_DENOM_PREFIX) {
+        if hash.len() != IBC_HASH_LENGTH || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid_denom(
+                denom,
+                format!(
+                    "ibc denoms must be ibc/ followed by a {} characters hex hash",
+                    IBC_HASH_LENGTH
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the address of a cw20 contract and returns it in its normalized form.
+pub fn validate_cw20_address(api: &dyn Api, address: &str) -> Result<Addr, ContractError> {
+    api.addr_validate(address)
+        .map_err(|_| ContractError::InvalidCw20Address {
+            address: address.to_string(),
+        })
+}
+
+/// Fails if coins are attached to a message that does not use them, they would be absorbed
+pub fn assert_no_funds(info: &MessageInfo) -> Result<(), ContractError> {
+    if !info.funds.is_empty() {
+        return Err(ContractError::UnexpectedFunds {});
+    }
+    Ok(())
+}
+
+fn invalid_denom(denom: &str, reason: String) -> ContractError {
+    ContractError::InvalidDenom {
+        denom: denom.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockApi;
+
+    #[test]
+    fn test_validate_native_denom() {
+        assert!(validate_native_denom("uluna").is_ok());
+        assert!(validate_native_denom("uusd").is_ok());
+        assert!(validate_native_denom("factory/terra1abc/bluna.v2").is_ok());
+        assert!(validate_native_denom(
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        )
+        .is_ok());
+
+        assert_eq!(
+            validate_native_denom("ul").unwrap_err(),
+            ContractError::InvalidDenom {
+                denom: "ul".to_string(),
+                reason: "length must be between 3 and 128".to_string()
+            }
+        );
+        assert!(validate_native_denom(&"u".repeat(129)).is_err());
+        assert_eq!(
+            validate_native_denom("1uluna").unwrap_err(),
+            ContractError::InvalidDenom {
+                denom: "1uluna".to_string(),
+                reason: "must start with a letter".to_string()
+            }
+        );
+        assert_eq!(
+            validate_native_denom("ulu na").unwrap_err(),
+            ContractError::InvalidDenom {
+                denom: "ulu na".to_string(),
+                reason: "contains invalid characters".to_string()
+            }
+        );
+        assert!(validate_native_denom("ibc/27394FB0").is_err());
+        assert!(validate_native_denom(
+            "ibc/Z7394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_cw20_address() {
+        let api = MockApi::default();
+        assert_eq!(
+            validate_cw20_address(&api, "token").unwrap(),
+            Addr::unchecked("token")
+        );
+        assert_eq!(
+            validate_cw20_address(&api, "t").unwrap_err(),
+            ContractError::InvalidCw20Address {
+                address: "t".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use cosmwasm_std::{Binary, Coin, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever the layout of the receipts changes
+pub const RECEIPT_VERSION: u8 = 1;
+
+/// The messages of the wormhole core contract used to publish the receipts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WormholeExecuteMsg {
+    /// Publish message, the guardians sign it with the sender as the emitter
+    PostMessage { message: Binary, nonce: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WormholeQueryMsg {
+    GetState {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WormholeStateResponse {
+    /// Sent along with every published message
+    pub fee: Coin,
+}
+
+/// Published by the hub once an unbond is withdrawn
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondReceipt {
+    /// Counts the receipts of the hub, so a replayed receipt is detected
+    pub sequence: u64,
+    pub time: u64,
+    /// The withdrawn amount of the underlying coin, before the wormhole fee
+    pub amount: Uint128,
+    pub recipient: String,
+}
+
+impl UnbondReceipt {
+    /// The version, sequence, time and amount big endian, followed by the recipient
+    pub fn encode(&self) -> Binary {
+        let mut buf = Vec::with_capacity(33 + self.recipient.len());
+        buf.push(RECEIPT_VERSION);
+        buf.extend_from_slice(&self.sequence.to_be_bytes());
+        buf.extend_from_slice(&self.time.to_be_bytes());
+        buf.extend_from_slice(&self.amount.u128().to_be_bytes());
+        buf.extend_from_slice(self.recipient.as_bytes());
+        Binary::from(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_unbond_receipt() {
+        let receipt = UnbondReceipt {
+            sequence: 2,
+            time: 258,
+            amount: Uint128::new(1000),
+            recipient: "terra1".to_string(),
+        };
+        let mut expected = vec![1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 1, 2];
+        expected.extend_from_slice(&[0; 14]);
+        expected.extend_from_slice(&[3, 232]);
+        expected.extend_from_slice(b"terra1");
+        assert_eq!(receipt.encode(), Binary::from(expected));
+    }
+}
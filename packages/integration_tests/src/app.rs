@@ -0,0 +1,514 @@
+use crate::contract::{ContractResponse, ContractWrapper};
+use crate::querier::ChainQuerier;
+use crate::storage::ChainStorage;
+
+use cosmwasm_std::testing::{mock_env, MockApi};
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, BankMsg, Binary, BlockInfo, Coin, ContractInfo, ContractResult,
+    CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Env, Event, MessageInfo, QuerierWrapper,
+    Reply, ReplyOn, StakingMsg, StdError, StdResult, SubMsg, SubMsgExecutionResponse, Uint128,
+    Validator, WasmMsg,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Events and data of a processed message, including all of its submessages
+pub type AppResponse = SubMsgExecutionResponse;
+
+/// Undelegated coins are released to the delegator after the unbonding time
+#[derive(Clone, Debug)]
+pub(crate) struct Unbonding {
+    pub delegator: String,
+    pub amount: Uint128,
+    pub completion_time: u64,
+}
+
+/// Everything a failing message must roll back
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChainState {
+    pub storages: BTreeMap<String, ChainStorage>,
+    pub balances: BTreeMap<String, BTreeMap<String, Uint128>>,
+    /// (delegator, validator) -> bonded amount
+    pub delegations: BTreeMap<(String, String), Uint128>,
+    /// (delegator, validator) -> denom -> accrued amount
+    pub rewards: BTreeMap<(String, String), BTreeMap<String, Uint128>>,
+    pub withdraw_addresses: BTreeMap<String, String>,
+    pub unbondings: Vec<Unbonding>,
+}
+
+/// A mocked chain routing messages and queries between the registered contracts
+/// and the bank, staking and distribution modules.
+pub struct App {
+    pub(crate) api: MockApi,
+    pub(crate) block: BlockInfo,
+    pub(crate) bonded_denom: String,
+    pub(crate) unbonding_time: u64,
+    pub(crate) validators: Vec<Validator>,
+    pub(crate) contracts: BTreeMap<String, Rc<ContractWrapper>>,
+    pub(crate) state: ChainState,
+}
+
+impl App {
+    pub fn new(bonded_denom: &str, unbonding_time: u64) -> Self {
+        App {
+            api: MockApi::default(),
+            block: mock_env().block,
+            bonded_denom: bonded_denom.to_string(),
+            unbonding_time,
+            validators: vec![],
+            contracts: BTreeMap::new(),
+            state: ChainState::default(),
+        }
+    }
+
+    pub fn block_info(&self) -> BlockInfo {
+        self.block.clone()
+    }
+
+    /// Moves the chain forward and releases the unbondings that completed meanwhile
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.block.time = self.block.time.plus_seconds(seconds);
+        self.block.height += std::cmp::max(1, seconds / 5);
+
+        let now = self.block.time.seconds();
+        let (completed, pending): (Vec<Unbonding>, Vec<Unbonding>) = self
+            .state
+            .unbondings
+            .drain(..)
+            .partition(|unbonding| unbonding.completion_time <= now);
+        self.state.unbondings = pending;
+
+        let denom = self.bonded_denom.clone();
+        for unbonding in completed {
+            self.mint(&unbonding.delegator, &denom, unbonding.amount);
+        }
+    }
+
+    pub fn add_validator(&mut self, address: &str) {
+        self.validators.push(Validator {
+            address: address.to_string(),
+            commission: Decimal::zero(),
+            max_commission: Decimal::zero(),
+            max_change_rate: Decimal::zero(),
+        })
+    }
+
+    pub fn init_balance(&mut self, address: &str, coins: &[Coin]) {
+        for coin in coins {
+            self.mint(address, &coin.denom, coin.amount);
+        }
+    }
+
+    pub fn balance(&self, address: &str, denom: &str) -> Uint128 {
+        self.state
+            .balances
+            .get(address)
+            .and_then(|balances| balances.get(denom))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn all_balances(&self, address: &str) -> Vec<Coin> {
+        self.state
+            .balances
+            .get(address)
+            .map(|balances| {
+                balances
+                    .iter()
+                    .filter(|(_, amount)| !amount.is_zero())
+                    .map(|(denom, amount)| Coin::new(amount.u128(), denom))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn delegation(&self, delegator: &str, validator: &str) -> Uint128 {
+        self.state
+            .delegations
+            .get(&(delegator.to_string(), validator.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Distributes staking rewards to a delegation, they are paid out on withdrawal
+    pub fn accrue_rewards(&mut self, delegator: &str, validator: &str, coins: &[Coin]) {
+        let rewards = self
+            .state
+            .rewards
+            .entry((delegator.to_string(), validator.to_string()))
+            .or_default();
+        for coin in coins {
+            *rewards.entry(coin.denom.clone()).or_default() += coin.amount;
+        }
+    }
+
+    /// Registers the contract at the given address and instantiates it
+    pub fn instantiate<T: Serialize>(
+        &mut self,
+        contract: ContractWrapper,
+        address: &str,
+        sender: &str,
+        msg: &T,
+        funds: &[Coin],
+    ) -> StdResult<AppResponse> {
+        if self.contracts.contains_key(address) {
+            return Err(StdError::generic_err(format!(
+                "A contract is already registered at {}",
+                address
+            )));
+        }
+        self.contracts
+            .insert(address.to_string(), Rc::new(contract));
+
+        let msg = to_binary(msg)?;
+        let res = self.transact(|app| {
+            app.transfer(sender, address, funds)?;
+            let info = app.message_info(sender, funds);
+            let res = app.with_contract(address, |contract, deps, env| {
+                contract.instantiate(deps, env, info, &msg)
+            })?;
+            app.process_response(address, res)
+        });
+        if res.is_err() {
+            self.contracts.remove(address);
+        }
+        res
+    }
+
+    pub fn execute<T: Serialize>(
+        &mut self,
+        sender: &str,
+        contract: &str,
+        msg: &T,
+        funds: &[Coin],
+    ) -> StdResult<AppResponse> {
+        let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: to_binary(msg)?,
+            funds: funds.to_vec(),
+        });
+        self.transact(|app| app.dispatch(sender, msg))
+    }
+
+    pub fn query<M: Serialize, T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &M,
+    ) -> StdResult<T> {
+        from_binary(&self.query_wasm_smart(contract, &to_binary(msg)?)?)
+    }
+
+    pub(crate) fn query_wasm_smart(&self, address: &str, msg: &[u8]) -> StdResult<Binary> {
+        let contract = self
+            .contracts
+            .get(address)
+            .ok_or_else(|| StdError::generic_err(format!("No contract at {}", address)))?;
+        let storage = self.state.storages.get(address).ok_or_else(|| {
+            StdError::generic_err(format!("{} cannot be queried while it executes", address))
+        })?;
+
+        let querier = ChainQuerier::new(self);
+        let deps = Deps {
+            storage,
+            api: &self.api,
+            querier: QuerierWrapper::new(&querier),
+        };
+        contract.query(deps, self.env(address), msg)
+    }
+
+    pub(crate) fn env(&self, contract: &str) -> Env {
+        Env {
+            block: self.block.clone(),
+            contract: ContractInfo {
+                address: Addr::unchecked(contract),
+            },
+        }
+    }
+
+    fn message_info(&self, sender: &str, funds: &[Coin]) -> MessageInfo {
+        MessageInfo {
+            sender: Addr::unchecked(sender),
+            funds: funds.to_vec(),
+        }
+    }
+
+    /// Runs `action` atomically, the chain state is restored when it fails
+    fn transact<T>(&mut self, action: impl FnOnce(&mut Self) -> StdResult<T>) -> StdResult<T> {
+        let snapshot = self.state.clone();
+        let res = action(self);
+        if res.is_err() {
+            self.state = snapshot;
+        }
+        res
+    }
+
+    /// Lends the storage of the contract to `action`, the contract cannot query itself meanwhile
+    fn with_contract(
+        &mut self,
+        address: &str,
+        action: impl FnOnce(&ContractWrapper, DepsMut, Env) -> StdResult<ContractResponse>,
+    ) -> StdResult<ContractResponse> {
+        let contract = self
+            .contracts
+            .get(address)
+            .cloned()
+            .ok_or_else(|| StdError::generic_err(format!("No contract at {}", address)))?;
+        let mut storage = self.state.storages.remove(address).unwrap_or_default();
+
+        let env = self.env(address);
+        let res = {
+            let querier = ChainQuerier::new(self);
+            let deps = DepsMut {
+                storage: &mut storage,
+                api: &self.api,
+                querier: QuerierWrapper::new(&querier),
+            };
+            action(&contract, deps, env)
+        };
+
+        self.state.storages.insert(address.to_string(), storage);
+        res
+    }
+
+    fn dispatch(
+        &mut self,
+        sender: &str,
+        msg: CosmosMsg<TerraMsgWrapper>,
+    ) -> StdResult<AppResponse> {
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) => {
+                self.transfer(sender, &contract_addr, &funds)?;
+                let info = self.message_info(sender, &funds);
+                let res = self.with_contract(&contract_addr, |contract, deps, env| {
+                    contract.execute(deps, env, info, &msg)
+                })?;
+                self.process_response(&contract_addr, res)
+            }
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                self.transfer(sender, &to_address, &amount)?;
+                Ok(empty_response())
+            }
+            CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+                self.assert_bonded_coin(&validator, &amount)?;
+                self.burn(sender, &amount.denom, amount.amount)?;
+                *self
+                    .state
+                    .delegations
+                    .entry((sender.to_string(), validator))
+                    .or_default() += amount.amount;
+                Ok(empty_response())
+            }
+            CosmosMsg::Staking(StakingMsg::Undelegate { validator, amount }) => {
+                self.assert_bonded_coin(&validator, &amount)?;
+                self.decrease_delegation(sender, &validator, amount.amount)?;
+                self.state.unbondings.push(Unbonding {
+                    delegator: sender.to_string(),
+                    amount: amount.amount,
+                    completion_time: self.block.time.seconds() + self.unbonding_time,
+                });
+                Ok(empty_response())
+            }
+            CosmosMsg::Staking(StakingMsg::Redelegate {
+                src_validator,
+                dst_validator,
+                amount,
+            }) => {
+                self.assert_bonded_coin(&src_validator, &amount)?;
+                self.assert_bonded_coin(&dst_validator, &amount)?;
+                self.decrease_delegation(sender, &src_validator, amount.amount)?;
+                *self
+                    .state
+                    .delegations
+                    .entry((sender.to_string(), dst_validator))
+                    .or_default() += amount.amount;
+                Ok(empty_response())
+            }
+            CosmosMsg::Distribution(DistributionMsg::SetWithdrawAddress { address }) => {
+                self.state
+                    .withdraw_addresses
+                    .insert(sender.to_string(), address);
+                Ok(empty_response())
+            }
+            CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward { validator }) => {
+                let recipient = self
+                    .state
+                    .withdraw_addresses
+                    .get(sender)
+                    .cloned()
+                    .unwrap_or_else(|| sender.to_string());
+                let rewards = self
+                    .state
+                    .rewards
+                    .remove(&(sender.to_string(), validator))
+                    .unwrap_or_default();
+                for (denom, amount) in rewards {
+                    self.mint(&recipient, &denom, amount);
+                }
+                Ok(empty_response())
+            }
+            CosmosMsg::Custom(msg) => Err(StdError::generic_err(format!(
+                "Terra messages are not supported: {:?}",
+                msg.msg_data
+            ))),
+            msg => Err(StdError::generic_err(format!(
+                "Unsupported message: {:?}",
+                msg
+            ))),
+        }
+    }
+
+    fn dispatch_submessage(
+        &mut self,
+        contract: &str,
+        msg: SubMsg<TerraMsgWrapper>,
+    ) -> StdResult<AppResponse> {
+        let snapshot = self.state.clone();
+        let result = match self.dispatch(contract, msg.msg) {
+            Ok(res) => {
+                if !matches!(msg.reply_on, ReplyOn::Always | ReplyOn::Success) {
+                    return Ok(res);
+                }
+                ContractResult::Ok(res)
+            }
+            Err(err) => {
+                self.state = snapshot;
+                if !matches!(msg.reply_on, ReplyOn::Always | ReplyOn::Error) {
+                    return Err(err);
+                }
+                ContractResult::Err(err.to_string())
+            }
+        };
+
+        let mut events = match &result {
+            ContractResult::Ok(res) => res.events.clone(),
+            ContractResult::Err(_) => vec![],
+        };
+        let reply = Reply { id: msg.id, result };
+        let res = self.with_contract(contract, |contract, deps, env| {
+            contract.reply(deps, env, reply)
+        })?;
+        let mut reply_res = self.process_response(contract, res)?;
+        events.append(&mut reply_res.events);
+
+        Ok(AppResponse {
+            events,
+            data: reply_res.data,
+        })
+    }
+
+    fn process_response(
+        &mut self,
+        contract: &str,
+        res: ContractResponse,
+    ) -> StdResult<AppResponse> {
+        let mut events = vec![Event::new("wasm")
+            .add_attribute("contract_address", contract)
+            .add_attributes(res.attributes)];
+        events.extend(res.events.into_iter().map(|event| {
+            Event::new(format!("wasm-{}", event.ty))
+                .add_attribute("contract_address", contract)
+                .add_attributes(event.attributes)
+        }));
+
+        for msg in res.messages {
+            let mut sub_res = self.dispatch_submessage(contract, msg)?;
+            events.append(&mut sub_res.events);
+        }
+
+        Ok(AppResponse {
+            events,
+            data: res.data,
+        })
+    }
+
+    fn transfer(&mut self, from: &str, to: &str, coins: &[Coin]) -> StdResult<()> {
+        for coin in coins {
+            self.burn(from, &coin.denom, coin.amount)?;
+            self.mint(to, &coin.denom, coin.amount);
+        }
+        Ok(())
+    }
+
+    fn mint(&mut self, address: &str, denom: &str, amount: Uint128) {
+        *self
+            .state
+            .balances
+            .entry(address.to_string())
+            .or_default()
+            .entry(denom.to_string())
+            .or_default() += amount;
+    }
+
+    fn burn(&mut self, address: &str, denom: &str, amount: Uint128) -> StdResult<()> {
+        let balance = self.balance(address, denom);
+        let remaining = balance.checked_sub(amount).map_err(|_| {
+            StdError::generic_err(format!(
+                "{} has insufficient funds: {}{} is less than {}{}",
+                address, balance, denom, amount, denom
+            ))
+        })?;
+        self.state
+            .balances
+            .entry(address.to_string())
+            .or_default()
+            .insert(denom.to_string(), remaining);
+        Ok(())
+    }
+
+    fn decrease_delegation(
+        &mut self,
+        delegator: &str,
+        validator: &str,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let key = (delegator.to_string(), validator.to_string());
+        let delegated = self
+            .state
+            .delegations
+            .get(&key)
+            .copied()
+            .unwrap_or_default();
+        let remaining = delegated.checked_sub(amount).map_err(|_| {
+            StdError::generic_err(format!(
+                "{} has only {} delegated to {}",
+                delegator, delegated, validator
+            ))
+        })?;
+        if remaining.is_zero() {
+            self.state.delegations.remove(&key);
+        } else {
+            self.state.delegations.insert(key, remaining);
+        }
+        Ok(())
+    }
+
+    fn assert_bonded_coin(&self, validator: &str, coin: &Coin) -> StdResult<()> {
+        if !self.validators.iter().any(|v| v.address == validator) {
+            return Err(StdError::generic_err(format!(
+                "{} is not a validator",
+                validator
+            )));
+        }
+        if coin.denom != self.bonded_denom {
+            return Err(StdError::generic_err(format!(
+                "Only {} can be staked",
+                self.bonded_denom
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn empty_response() -> AppResponse {
+    AppResponse {
+        events: vec![],
+        data: None,
+    }
+}
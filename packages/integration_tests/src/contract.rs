@@ -0,0 +1,112 @@
+use cosmwasm_std::{
+    from_slice, to_vec, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use terra_cosmwasm::TerraMsgWrapper;
+
+/// Every contract response is handled as a terra response by the chain
+pub type ContractResponse = Response<TerraMsgWrapper>;
+
+type InstantiateFn = Box<dyn Fn(DepsMut, Env, MessageInfo, &[u8]) -> StdResult<ContractResponse>>;
+type ExecuteFn = Box<dyn Fn(DepsMut, Env, MessageInfo, &[u8]) -> StdResult<ContractResponse>>;
+type QueryFn = Box<dyn Fn(Deps, Env, &[u8]) -> StdResult<Binary>>;
+type ReplyFn = Box<dyn Fn(DepsMut, Env, Reply) -> StdResult<ContractResponse>>;
+
+/// Normalizes the entry point results of the contracts, which differ in
+/// their custom message and error types.
+pub trait IntoContractResult {
+    fn into_contract_result(self) -> StdResult<ContractResponse>;
+}
+
+impl<T, E> IntoContractResult for Result<Response<T>, E>
+where
+    T: Serialize + Clone + fmt::Debug + PartialEq + JsonSchema,
+    E: ToString,
+{
+    fn into_contract_result(self) -> StdResult<ContractResponse> {
+        let res = self.map_err(|err| StdError::generic_err(err.to_string()))?;
+        // messages without a custom payload serialize the same way for any custom type
+        from_slice(&to_vec(&res)?)
+    }
+}
+
+/// Type-erased entry points of a contract
+pub struct ContractWrapper {
+    instantiate: InstantiateFn,
+    execute: ExecuteFn,
+    query: QueryFn,
+    reply: Option<ReplyFn>,
+}
+
+impl ContractWrapper {
+    pub fn new<I, E, Q, IR, ER>(
+        instantiate: fn(DepsMut, Env, MessageInfo, I) -> IR,
+        execute: fn(DepsMut, Env, MessageInfo, E) -> ER,
+        query: fn(Deps, Env, Q) -> StdResult<Binary>,
+    ) -> Self
+    where
+        I: DeserializeOwned + 'static,
+        E: DeserializeOwned + 'static,
+        Q: DeserializeOwned + 'static,
+        IR: IntoContractResult + 'static,
+        ER: IntoContractResult + 'static,
+    {
+        ContractWrapper {
+            instantiate: Box::new(move |deps, env, info, msg| {
+                instantiate(deps, env, info, from_slice(msg)?).into_contract_result()
+            }),
+            execute: Box::new(move |deps, env, info, msg| {
+                execute(deps, env, info, from_slice(msg)?).into_contract_result()
+            }),
+            query: Box::new(move |deps, env, msg| query(deps, env, from_slice(msg)?)),
+            reply: None,
+        }
+    }
+
+    pub fn with_reply<R>(mut self, reply: fn(DepsMut, Env, Reply) -> R) -> Self
+    where
+        R: IntoContractResult + 'static,
+    {
+        self.reply = Some(Box::new(move |deps, env, msg| {
+            reply(deps, env, msg).into_contract_result()
+        }));
+        self
+    }
+
+    pub fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &[u8],
+    ) -> StdResult<ContractResponse> {
+        (self.instantiate)(deps, env, info, msg)
+    }
+
+    pub fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &[u8],
+    ) -> StdResult<ContractResponse> {
+        (self.execute)(deps, env, info, msg)
+    }
+
+    pub fn query(&self, deps: Deps, env: Env, msg: &[u8]) -> StdResult<Binary> {
+        (self.query)(deps, env, msg)
+    }
+
+    pub fn reply(&self, deps: DepsMut, env: Env, msg: Reply) -> StdResult<ContractResponse> {
+        match &self.reply {
+            Some(reply) => reply(deps, env, msg),
+            None => Err(StdError::generic_err(
+                "The contract has no reply entry point",
+            )),
+        }
+    }
+}
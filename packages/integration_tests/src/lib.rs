@@ -0,0 +1,11 @@
+//! End-to-end harness running the hub, reward, token and airdrop registry
+//! contracts together on a mocked chain with bank, staking and treasury modules.
+
+pub mod app;
+pub mod contract;
+pub mod querier;
+pub mod storage;
+pub mod suite;
+
+#[cfg(test)]
+mod testing;
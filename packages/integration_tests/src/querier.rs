@@ -0,0 +1,156 @@
+use crate::app::App;
+
+use basset::staking::{encode_unbonding_time, STAKING_PARAMS_QUERY_PATH};
+use cosmwasm_std::{
+    from_slice, to_binary, Addr, AllBalanceResponse, AllDelegationsResponse, AllValidatorsResponse,
+    BalanceResponse, BankQuery, Binary, BondedDenomResponse, Coin, ContractResult, Decimal,
+    Delegation, FullDelegation, Querier, QuerierResult, QueryRequest, StakingQuery, StdError,
+    StdResult, Storage, SystemError, SystemResult, Uint128, ValidatorResponse, WasmQuery,
+};
+use serde::Serialize;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper};
+
+/// cosmwasm-std does not export the response of StakingQuery::Delegation
+#[derive(Serialize)]
+struct DelegationResponse {
+    delegation: Option<FullDelegation>,
+}
+
+/// Answers the queries of the contracts from the current chain state.
+/// The treasury charges no tax and the oracle has no exchange rates, so no swap happens.
+pub struct ChainQuerier<'a> {
+    app: &'a App,
+}
+
+impl<'a> ChainQuerier<'a> {
+    pub fn new(app: &'a App) -> Self {
+        ChainQuerier { app }
+    }
+
+    fn query(&self, request: QueryRequest<TerraQueryWrapper>) -> StdResult<Binary> {
+        match request {
+            QueryRequest::Bank(BankQuery::Balance { address, denom }) => {
+                let amount = self.app.balance(&address, &denom);
+                to_binary(&BalanceResponse {
+                    amount: Coin { denom, amount },
+                })
+            }
+            QueryRequest::Bank(BankQuery::AllBalances { address }) => {
+                to_binary(&AllBalanceResponse {
+                    amount: self.app.all_balances(&address),
+                })
+            }
+            QueryRequest::Staking(StakingQuery::BondedDenom {}) => {
+                to_binary(&BondedDenomResponse {
+                    denom: self.app.bonded_denom.clone(),
+                })
+            }
+            QueryRequest::Staking(StakingQuery::AllValidators {}) => {
+                to_binary(&AllValidatorsResponse {
+                    validators: self.app.validators.clone(),
+                })
+            }
+            QueryRequest::Staking(StakingQuery::Validator { address }) => {
+                to_binary(&ValidatorResponse {
+                    validator: self
+                        .app
+                        .validators
+                        .iter()
+                        .find(|validator| validator.address == address)
+                        .cloned(),
+                })
+            }
+            QueryRequest::Staking(StakingQuery::AllDelegations { delegator }) => {
+                let delegations = self
+                    .app
+                    .state
+                    .delegations
+                    .iter()
+                    .filter(|((owner, _), _)| *owner == delegator)
+                    .map(|((owner, validator), amount)| Delegation {
+                        delegator: Addr::unchecked(owner),
+                        validator: validator.clone(),
+                        amount: Coin::new(amount.u128(), &self.app.bonded_denom),
+                    })
+                    .collect();
+                to_binary(&AllDelegationsResponse { delegations })
+            }
+            QueryRequest::Staking(StakingQuery::Delegation {
+                delegator,
+                validator,
+            }) => {
+                let amount = self.app.delegation(&delegator, &validator);
+                let delegation = if amount.is_zero() {
+                    None
+                } else {
+                    let amount = Coin::new(amount.u128(), &self.app.bonded_denom);
+                    Some(FullDelegation {
+                        delegator: Addr::unchecked(&delegator),
+                        validator: validator.clone(),
+                        can_redelegate: amount.clone(),
+                        amount,
+                        accumulated_rewards: self.accumulated_rewards(&delegator, &validator),
+                    })
+                };
+                to_binary(&DelegationResponse { delegation })
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                self.app.query_wasm_smart(&contract_addr, &msg)
+            }
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                let storage = self.app.state.storages.get(&contract_addr).ok_or_else(|| {
+                    StdError::generic_err(format!("No contract storage at {}", contract_addr))
+                })?;
+                Ok(storage.get(&key).unwrap_or_default().into())
+            }
+            QueryRequest::Stargate { path, .. } if path == STAKING_PARAMS_QUERY_PATH => {
+                Ok(encode_unbonding_time(self.app.unbonding_time))
+            }
+            QueryRequest::Custom(TerraQueryWrapper { query_data, .. }) => match query_data {
+                TerraQuery::TaxRate {} => to_binary(&TaxRateResponse {
+                    rate: Decimal::zero(),
+                }),
+                TerraQuery::TaxCap { .. } => to_binary(&TaxCapResponse {
+                    cap: Uint128::zero(),
+                }),
+                query => Err(StdError::generic_err(format!(
+                    "Terra query is not supported: {:?}",
+                    query
+                ))),
+            },
+            request => Err(StdError::generic_err(format!(
+                "Unsupported query: {:?}",
+                request
+            ))),
+        }
+    }
+
+    fn accumulated_rewards(&self, delegator: &str, validator: &str) -> Vec<Coin> {
+        self.app
+            .state
+            .rewards
+            .get(&(delegator.to_string(), validator.to_string()))
+            .map(|rewards| {
+                rewards
+                    .iter()
+                    .map(|(denom, amount)| Coin::new(amount.u128(), denom))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Querier for ChainQuerier<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(err) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: err.to_string(),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        SystemResult::Ok(ContractResult::from(self.query(request)))
+    }
+}
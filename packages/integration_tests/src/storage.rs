@@ -0,0 +1,49 @@
+use cosmwasm_std::{Order, Pair, Storage};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// In-memory contract storage that can be cloned to snapshot the chain
+#[derive(Clone, Debug, Default)]
+pub struct ChainStorage {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Storage for ChainStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Pair> + 'a> {
+        // BTreeMap panics on inverted bounds, the Storage contract describes an empty range
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Box::new(std::iter::empty());
+            }
+        }
+
+        let start = start.map_or(Bound::Unbounded, |s| Bound::Included(s.to_vec()));
+        let end = end.map_or(Bound::Unbounded, |e| Bound::Excluded(e.to_vec()));
+        let iter = self
+            .data
+            .range((start, end))
+            .map(|(key, value)| (key.clone(), value.clone()));
+
+        match order {
+            Order::Ascending => Box::new(iter),
+            Order::Descending => Box::new(iter.rev()),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::app::{App, AppResponse};
+use crate::contract::ContractWrapper;
+
+use basset::hub::{
+    Cw20HookMsg, ExecuteMsg as HubExecuteMsg, HubMode, InstantiateMsg as HubInstantiateMsg,
+    QueryMsg as HubQueryMsg, StateResponse,
+};
+use basset::reward::{
+    AccruedRewardsResponse, ExecuteMsg as RewardExecuteMsg, InstantiateMsg as RewardInstantiateMsg,
+    QueryMsg as RewardQueryMsg,
+};
+use basset::staking::UNBONDING_SAFETY_MARGIN;
+use basset::token::TokenInitMsg;
+use cosmwasm_std::{coins, to_binary, Decimal, StdResult, Uint128};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse, TokenInfoResponse};
+
+pub const OWNER: &str = "owner";
+pub const VALIDATOR: &str = "validator";
+pub const HUB: &str = "basset_hub";
+pub const TOKEN: &str = "basset_token";
+pub const REWARD: &str = "basset_reward";
+pub const AIRDROP_REGISTRY: &str = "airdrop_registry";
+
+pub const BONDED_DENOM: &str = "uluna";
+pub const REWARD_DENOM: &str = "uusd";
+/// Unbonding time of the staking module
+pub const UNBONDING_TIME: u64 = 1_814_400;
+/// The hub waits for the unbonding time and the safety margin
+pub const UNBONDING_PERIOD: u64 = UNBONDING_TIME + UNBONDING_SAFETY_MARGIN;
+pub const EPOCH_PERIOD: u64 = 259_200;
+/// Funds the owner bonds when instantiating the hub
+pub const INITIAL_BOND: u128 = 1_000_000;
+
+/// The bAsset contracts deployed and wired together, with helpers for user operations
+pub struct Suite {
+    pub app: App,
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        let mut app = App::new(BONDED_DENOM, UNBONDING_TIME);
+        app.add_validator(VALIDATOR);
+        app.init_balance(OWNER, &coins(INITIAL_BOND, BONDED_DENOM));
+
+        app.instantiate(
+            ContractWrapper::new(
+                anchor_basset_hub::contract::instantiate,
+                anchor_basset_hub::contract::execute,
+                anchor_basset_hub::contract::query,
+            )
+            .with_reply(anchor_basset_hub::contract::reply),
+            HUB,
+            OWNER,
+            &HubInstantiateMsg {
+                epoch_period: EPOCH_PERIOD,
+                underlying_coin_denom: BONDED_DENOM.to_string(),
+                unbonding_period: UNBONDING_PERIOD,
+                peg_recovery_fee: Decimal::zero(),
+                er_threshold: Decimal::one(),
+                reward_denom: REWARD_DENOM.to_string(),
+                validator: VALIDATOR.to_string(),
+                mode: HubMode::Staking,
+            },
+            &coins(INITIAL_BOND, BONDED_DENOM),
+        )
+        .unwrap();
+
+        app.instantiate(
+            ContractWrapper::new(
+                anchor_basset_token::contract::instantiate,
+                anchor_basset_token::contract::execute,
+                anchor_basset_token::contract::query,
+            ),
+            TOKEN,
+            OWNER,
+            &TokenInitMsg {
+                name: "bluna".to_string(),
+                symbol: "BLUNA".to_string(),
+                decimals: 6,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: HUB.to_string(),
+                    cap: None,
+                }),
+                hub_contract: HUB.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        app.instantiate(
+            ContractWrapper::new(
+                anchor_basset_reward::contract::instantiate,
+                anchor_basset_reward::contract::execute,
+                anchor_basset_reward::contract::query,
+            )
+            .with_reply(anchor_basset_reward::contract::reply),
+            REWARD,
+            OWNER,
+            &RewardInstantiateMsg {
+                hub_contract: HUB.to_string(),
+                reward_denom: REWARD_DENOM.to_string(),
+                reward_asset: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+        app.instantiate(
+            ContractWrapper::new(
+                anchor_airdrop_registry::contract::instantiate,
+                anchor_airdrop_registry::contract::execute,
+                anchor_airdrop_registry::contract::query,
+            )
+            .with_reply(anchor_airdrop_registry::contract::reply),
+            AIRDROP_REGISTRY,
+            OWNER,
+            &basset::airdrop::InstantiateMsg {
+                hub_contract: HUB.to_string(),
+                reward_contract: REWARD.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        app.execute(
+            OWNER,
+            HUB,
+            &HubExecuteMsg::UpdateConfig {
+                owner: None,
+                reward_contract: Some(REWARD.to_string()),
+                token_contract: Some(TOKEN.to_string()),
+                airdrop_registry_contract: Some(AIRDROP_REGISTRY.to_string()),
+            },
+            &[],
+        )
+        .unwrap();
+
+        Suite { app }
+    }
+
+    pub fn bond(&mut self, sender: &str, amount: u128) -> StdResult<AppResponse> {
+        self.app.execute(
+            sender,
+            HUB,
+            &HubExecuteMsg::Bond {
+                validator: VALIDATOR.to_string(),
+            },
+            &coins(amount, BONDED_DENOM),
+        )
+    }
+
+    /// Sends the bAsset to the hub, which queues it for the next undelegation batch
+    pub fn unbond(&mut self, sender: &str, amount: u128) -> StdResult<AppResponse> {
+        self.app.execute(
+            sender,
+            TOKEN,
+            &Cw20ExecuteMsg::Send {
+                contract: HUB.to_string(),
+                amount: Uint128::from(amount),
+                msg: to_binary(&Cw20HookMsg::Unbond {})?,
+            },
+            &[],
+        )
+    }
+
+    pub fn update_global_index(&mut self, sender: &str) -> StdResult<AppResponse> {
+        self.app.execute(
+            sender,
+            HUB,
+            &HubExecuteMsg::UpdateGlobalIndex {
+                airdrop_hooks: None,
+            },
+            &[],
+        )
+    }
+
+    pub fn claim_rewards(&mut self, sender: &str) -> StdResult<AppResponse> {
+        self.app.execute(
+            sender,
+            REWARD,
+            &RewardExecuteMsg::ClaimRewards { recipient: None },
+            &[],
+        )
+    }
+
+    pub fn withdraw_unbonded(&mut self, sender: &str) -> StdResult<AppResponse> {
+        self.app
+            .execute(sender, HUB, &HubExecuteMsg::WithdrawUnbonded {}, &[])
+    }
+
+    pub fn token_balance(&self, address: &str) -> Uint128 {
+        let res: BalanceResponse = self
+            .app
+            .query(
+                TOKEN,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    }
+
+    pub fn token_supply(&self) -> Uint128 {
+        let res: TokenInfoResponse = self.app.query(TOKEN, &Cw20QueryMsg::TokenInfo {}).unwrap();
+        res.total_supply
+    }
+
+    pub fn accrued_rewards(&self, address: &str) -> Uint128 {
+        let res: AccruedRewardsResponse = self
+            .app
+            .query(
+                REWARD,
+                &RewardQueryMsg::AccruedRewards {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.rewards
+    }
+
+    pub fn hub_state(&self) -> StateResponse {
+        self.app.query(HUB, &HubQueryMsg::State {}).unwrap()
+    }
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,173 @@
+use crate::suite::{
+    Suite, BONDED_DENOM, EPOCH_PERIOD, HUB, REWARD, REWARD_DENOM, TOKEN, VALIDATOR,
+};
+use cosmwasm_std::{coins, Decimal, Uint128};
+use cw20::Cw20ExecuteMsg;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+const CASES: u64 = 32;
+const STEPS: usize = 40;
+const USERS: [&str; 3] = ["alice", "bob", "carol"];
+/// Upper bound of the whole Luna supply in uluna, split among the users
+const TOTAL_LUNA_SUPPLY: u64 = 1_000_000_000_000_000;
+
+#[derive(Debug)]
+enum Operation {
+    Bond {
+        user: usize,
+        amount: u64,
+    },
+    Unbond {
+        user: usize,
+        percent: u64,
+    },
+    Transfer {
+        from: usize,
+        to: usize,
+        percent: u64,
+    },
+    Accrue {
+        amount: u64,
+    },
+    Claim {
+        user: usize,
+    },
+    Withdraw {
+        user: usize,
+    },
+    AdvanceTime {
+        seconds: u64,
+    },
+}
+
+fn random_operation(rng: &mut XorShiftRng) -> Operation {
+    let user = rng.gen_range(0, USERS.len());
+    match rng.gen_range(0, 7) {
+        0 => Operation::Bond {
+            user,
+            amount: rng.gen_range(1, TOTAL_LUNA_SUPPLY / 100),
+        },
+        1 => Operation::Unbond {
+            user,
+            percent: rng.gen_range(1, 101),
+        },
+        2 => Operation::Transfer {
+            from: user,
+            to: rng.gen_range(0, USERS.len()),
+            percent: rng.gen_range(1, 101),
+        },
+        3 => Operation::Accrue {
+            amount: rng.gen_range(1, 1_000_000_000_000),
+        },
+        4 => Operation::Claim { user },
+        5 => Operation::Withdraw { user },
+        _ => Operation::AdvanceTime {
+            seconds: rng.gen_range(1, 2 * EPOCH_PERIOD),
+        },
+    }
+}
+
+fn apply(suite: &mut Suite, operation: &Operation) {
+    match *operation {
+        Operation::Bond { user, amount } => {
+            suite.bond(USERS[user], amount as u128).unwrap();
+        }
+        Operation::Unbond { user, percent } => {
+            let amount = suite.token_balance(USERS[user]).u128() * percent as u128 / 100;
+            if amount > 0 {
+                suite.unbond(USERS[user], amount).unwrap();
+            }
+        }
+        Operation::Transfer { from, to, percent } => {
+            let amount = suite.token_balance(USERS[from]).u128() * percent as u128 / 100;
+            if amount > 0 {
+                suite
+                    .app
+                    .execute(
+                        USERS[from],
+                        TOKEN,
+                        &Cw20ExecuteMsg::Transfer {
+                            recipient: USERS[to].to_string(),
+                            amount: Uint128::from(amount),
+                        },
+                        &[],
+                    )
+                    .unwrap();
+            }
+        }
+        Operation::Accrue { amount } => {
+            suite
+                .app
+                .accrue_rewards(HUB, VALIDATOR, &coins(amount as u128, REWARD_DENOM));
+            // the reward contract rejects an update while nothing is bonded
+            if !suite.token_supply().is_zero() {
+                suite.update_global_index(USERS[0]).unwrap();
+            }
+        }
+        Operation::Claim { user } => {
+            if !suite.accrued_rewards(USERS[user]).is_zero() {
+                suite.claim_rewards(USERS[user]).unwrap();
+            }
+        }
+        Operation::Withdraw { user } => {
+            // nothing may be withdrawable yet, which is the only expected failure
+            if let Err(err) = suite.withdraw_unbonded(USERS[user]) {
+                assert!(err.to_string().contains("No withdrawable"), "{}", err);
+            }
+        }
+        Operation::AdvanceTime { seconds } => suite.app.advance_time(seconds),
+    }
+}
+
+#[test]
+fn randomized_operations_keep_invariants() {
+    for case in 0..CASES {
+        let mut rng = XorShiftRng::seed_from_u64(case);
+        let mut suite = Suite::new();
+        for user in USERS.iter() {
+            let funds = TOTAL_LUNA_SUPPLY / USERS.len() as u64;
+            suite
+                .app
+                .init_balance(user, &coins(funds as u128, BONDED_DENOM));
+        }
+
+        let mut exchange_rate = suite.hub_state().exchange_rate;
+        let mut distributed = Uint128::zero();
+        for step in 0..STEPS {
+            let operation = random_operation(&mut rng);
+            apply(&mut suite, &operation);
+            if let Operation::Accrue { amount } = operation {
+                distributed += Uint128::from(amount);
+            }
+            let context = format!("case {} step {}: {:?}", case, step, operation);
+
+            // without slashing the exchange rate never goes down
+            let state = suite.hub_state();
+            assert!(
+                state.exchange_rate >= exchange_rate,
+                "{}: {} -> {}",
+                context,
+                exchange_rate,
+                state.exchange_rate
+            );
+            exchange_rate = state.exchange_rate;
+
+            // holders are never owed more than what was distributed,
+            // and the reward contract can always pay what is accrued
+            let mut claimed = Uint128::zero();
+            let mut accrued = Uint128::zero();
+            for user in USERS.iter() {
+                claimed += suite.app.balance(user, REWARD_DENOM);
+                accrued += suite.accrued_rewards(user);
+            }
+            assert!(claimed + accrued <= distributed, "{}", context);
+            assert!(
+                accrued <= suite.app.balance(REWARD, REWARD_DENOM),
+                "{}",
+                context
+            );
+        }
+
+        assert!(exchange_rate >= Decimal::one());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::suite::{
+    Suite, BONDED_DENOM, EPOCH_PERIOD, HUB, INITIAL_BOND, OWNER, REWARD, REWARD_DENOM, TOKEN,
+    UNBONDING_PERIOD, VALIDATOR,
+};
+use cosmwasm_std::{coins, to_binary, Decimal, Uint128};
+use cw20::Cw20ExecuteMsg;
+
+const USER: &str = "user";
+
+#[test]
+fn bond_accrue_claim_unbond_withdraw() {
+    let mut suite = Suite::new();
+    assert_eq!(
+        suite.app.delegation(HUB, VALIDATOR),
+        Uint128::from(INITIAL_BOND)
+    );
+
+    // bond
+    suite
+        .app
+        .init_balance(USER, &coins(10_000_000, BONDED_DENOM));
+    suite.bond(USER, 10_000_000).unwrap();
+    assert_eq!(suite.token_balance(USER), Uint128::from(10_000_000u128));
+    assert_eq!(suite.app.balance(USER, BONDED_DENOM), Uint128::zero());
+    assert_eq!(
+        suite.app.delegation(HUB, VALIDATOR),
+        Uint128::from(INITIAL_BOND + 10_000_000)
+    );
+
+    // accrue
+    suite
+        .app
+        .accrue_rewards(HUB, VALIDATOR, &coins(1_000_000, REWARD_DENOM));
+    assert_eq!(suite.accrued_rewards(USER), Uint128::zero());
+
+    // update index, the rewards are withdrawn to the reward contract
+    suite.update_global_index(OWNER).unwrap();
+    assert_eq!(
+        suite.app.balance(REWARD, REWARD_DENOM),
+        Uint128::from(1_000_000u128)
+    );
+    assert_eq!(suite.accrued_rewards(USER), Uint128::from(1_000_000u128));
+
+    // claim
+    suite.claim_rewards(USER).unwrap();
+    assert_eq!(
+        suite.app.balance(USER, REWARD_DENOM),
+        Uint128::from(1_000_000u128)
+    );
+    assert_eq!(suite.accrued_rewards(USER), Uint128::zero());
+
+    // unbond, the epoch passed so the batch is undelegated right away
+    suite.app.advance_time(EPOCH_PERIOD + 1);
+    suite.unbond(USER, 10_000_000).unwrap();
+    assert_eq!(suite.token_balance(USER), Uint128::zero());
+    // the bond sent on instantiation backs no bAsset, it goes to the holders
+    assert_eq!(
+        suite.hub_state().exchange_rate,
+        Decimal::from_ratio(INITIAL_BOND + 10_000_000, 10_000_000u128)
+    );
+    assert_eq!(suite.app.delegation(HUB, VALIDATOR), Uint128::zero());
+    assert!(suite.withdraw_unbonded(USER).is_err());
+
+    // withdraw
+    suite.app.advance_time(UNBONDING_PERIOD);
+    assert_eq!(
+        suite.app.balance(HUB, BONDED_DENOM),
+        Uint128::from(INITIAL_BOND + 10_000_000)
+    );
+    suite.withdraw_unbonded(USER).unwrap();
+    assert_eq!(
+        suite.app.balance(USER, BONDED_DENOM),
+        Uint128::from(INITIAL_BOND + 10_000_000)
+    );
+    assert_eq!(suite.app.balance(HUB, BONDED_DENOM), Uint128::zero());
+}
+
+#[test]
+fn failing_hook_rolls_back_the_transfer() {
+    let mut suite = Suite::new();
+    suite
+        .app
+        .init_balance(USER, &coins(10_000_000, BONDED_DENOM));
+    suite.bond(USER, 10_000_000).unwrap();
+
+    // the hub rejects the hook after the token already moved the balance
+    let err = suite
+        .app
+        .execute(
+            USER,
+            TOKEN,
+            &Cw20ExecuteMsg::Send {
+                contract: HUB.to_string(),
+                amount: Uint128::from(10_000_000u128),
+                msg: to_binary(&"unknown hook").unwrap(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("Cw20HookMsg"), "{}", err);
+    assert_eq!(suite.token_balance(USER), Uint128::from(10_000_000u128));
+    assert_eq!(suite.token_balance(HUB), Uint128::zero());
+}